@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rq::{
+    data_source::{memory_data_source::MemoryDataSource, Source},
+    data_types::{
+        arrow_field_array::ArrowFieldArray,
+        column_array::{ArrayRef, DataType},
+        record_batch::RecordBatch,
+        schema::{Field, Schema},
+    },
+    logical_plan::expr::AggregateFunction,
+    physical_plan::{
+        aggregate::AggregateExpr,
+        expr::{Column, Expr},
+        hash::HashExec,
+        plan::{PhysicalPlan, Plan},
+        scan::ScanExec,
+    },
+};
+
+use arrow::array::Int64Array;
+
+const ROWS: usize = 10_000_000;
+const BATCH_SIZE: usize = 8192;
+const GROUPS: i64 = 1000;
+
+/// Group 10M rows of (key, value) into 1000 groups.
+fn hash_exec(source: &Source) -> HashExec {
+    let scan = ScanExec::new(source.clone(), vec!["key".to_string(), "value".to_string()]);
+    let schema = Schema::new(vec![
+        Field::new("key".to_string(), DataType::Int64),
+        Field::new("sum".to_string(), DataType::Int64),
+        Field::new("min".to_string(), DataType::Int64),
+        Field::new("max".to_string(), DataType::Int64),
+    ]);
+    let aggregate_expr = [
+        AggregateFunction::Sum,
+        AggregateFunction::Min,
+        AggregateFunction::Max,
+    ]
+    .into_iter()
+    .map(|fun| AggregateExpr::new(vec![Expr::Column(Column::new(1))], fun))
+    .collect();
+    HashExec::new(
+        Plan::Scan(scan),
+        schema,
+        vec![Expr::Column(Column::new(0))],
+        aggregate_expr,
+    )
+}
+
+fn source() -> Source {
+    let schema = Schema::new(vec![
+        Field::new("key".to_string(), DataType::Int64),
+        Field::new("value".to_string(), DataType::Int64),
+    ]);
+    let batches = (0..ROWS)
+        .step_by(BATCH_SIZE)
+        .map(|start| {
+            let rows = start as i64..(start + BATCH_SIZE).min(ROWS) as i64;
+            let key = Int64Array::from(rows.clone().map(|i| i % GROUPS).collect::<Vec<_>>());
+            let value = Int64Array::from(rows.collect::<Vec<_>>());
+            RecordBatch::new(
+                schema.clone(),
+                vec![
+                    Arc::new(ArrowFieldArray::new(Box::new(key))) as ArrayRef,
+                    Arc::new(ArrowFieldArray::new(Box::new(value))) as ArrayRef,
+                ],
+            )
+        })
+        .collect();
+    Source::Mem(MemoryDataSource::new(schema, batches))
+}
+
+fn bench_hash_aggregate(c: &mut Criterion) {
+    let source = source();
+    let mut group = c.benchmark_group("hash_aggregate");
+    group.sample_size(10);
+    group.bench_function("group_by_int64_10m_rows", |b| {
+        b.iter(|| {
+            let hash = hash_exec(&source);
+            hash.execute_all().unwrap().count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_hash_aggregate);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+/*
+ * C API of the rq query engine.
+ *
+ * Results are exported through the Arrow C Data Interface: every batch is a struct
+ * array with a child per column, described by the schema of the result.
+ * Functions that return an int return 0 on success and -1 on error, and functions that
+ * return a pointer return null on error. rq_last_error() then describes the error.
+ */
+#ifndef RQ_H
+#define RQ_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#ifndef ARROW_C_DATA_INTERFACE
+#define ARROW_C_DATA_INTERFACE
+
+#define ARROW_FLAG_DICTIONARY_ORDERED 1
+#define ARROW_FLAG_NULLABLE 2
+#define ARROW_FLAG_MAP_KEYS_SORTED 4
+
+struct ArrowSchema {
+  const char* format;
+  const char* name;
+  const char* metadata;
+  int64_t flags;
+  int64_t n_children;
+  struct ArrowSchema** children;
+  struct ArrowSchema* dictionary;
+  void (*release)(struct ArrowSchema*);
+  void* private_data;
+};
+
+struct ArrowArray {
+  int64_t length;
+  int64_t null_count;
+  int64_t offset;
+  int64_t n_buffers;
+  int64_t n_children;
+  const void** buffers;
+  struct ArrowArray** children;
+  struct ArrowArray* dictionary;
+  void (*release)(struct ArrowArray*);
+  void* private_data;
+};
+
+#endif /* ARROW_C_DATA_INTERFACE */
+
+typedef struct RqContext RqContext;
+typedef struct RqResult RqResult;
+
+/* Return the message of the last error of the calling thread, or null if there was none.
+ * The message is valid until the next call that fails on the same thread. */
+const char* rq_last_error(void);
+
+/* Create a context, to be freed with rq_context_free(). */
+RqContext* rq_context_new(size_t batch_size);
+void rq_context_free(RqContext* ctx);
+
+/* Register a CSV file without a header as a table, with the schema inferred from its
+ * first rows. The columns are named c1, c2, ... */
+int rq_register_csv(const RqContext* ctx, const char* name, const char* path);
+
+/* Plan and execute a SQL statement. The result is freed with rq_result_free(). */
+RqResult* rq_sql(const RqContext* ctx, const char* sql);
+
+/* Return the number of non-empty batches of the result. */
+size_t rq_result_batch_count(const RqResult* result);
+
+/* Export the schema of the result. The caller releases it. */
+int rq_result_schema(const RqResult* result, struct ArrowSchema* out);
+
+/* Export a batch of the result, sharing the buffers of its columns.
+ * The caller releases it, and it stays valid after the result is freed. */
+int rq_result_batch(const RqResult* result, size_t index, struct ArrowArray* out);
+
+void rq_result_free(RqResult* result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RQ_H */
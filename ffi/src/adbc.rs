@@ -0,0 +1,1553 @@
+use std::{
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::Arc,
+};
+
+use super::{panic_message, to_str};
+use rq::{
+    data_types::schema::{Field as SchemaField, Schema},
+    execution::ExecutionContext,
+    logical_plan::data_frame::DataFrame,
+};
+
+use anyhow::Result;
+use arrow::{
+    array::{
+        make_array, new_empty_array, new_null_array, Array, ArrayData, ArrayRef, Int16Array,
+        Int32Array, StringArray, StructArray,
+    },
+    buffer::Buffer,
+    datatypes::{DataType, Field, Schema as ArrowSchema, SchemaRef},
+    error::Result as ArrowResult,
+    ffi::{FFI_ArrowArray, FFI_ArrowSchema},
+    ffi_stream::FFI_ArrowArrayStream,
+    record_batch::{RecordBatch as ArrowRecordBatch, RecordBatchReader},
+};
+
+pub type AdbcStatusCode = u8;
+
+pub const ADBC_STATUS_OK: AdbcStatusCode = 0;
+pub const ADBC_STATUS_UNKNOWN: AdbcStatusCode = 1;
+pub const ADBC_STATUS_NOT_IMPLEMENTED: AdbcStatusCode = 2;
+pub const ADBC_STATUS_NOT_FOUND: AdbcStatusCode = 3;
+pub const ADBC_STATUS_INVALID_ARGUMENT: AdbcStatusCode = 5;
+pub const ADBC_STATUS_INVALID_STATE: AdbcStatusCode = 6;
+pub const ADBC_STATUS_INTERNAL: AdbcStatusCode = 9;
+
+/// The version of the ADBC API the driver implements.
+pub const ADBC_VERSION_1_0_0: c_int = 1_000_000;
+
+pub const ADBC_OBJECT_DEPTH_ALL: c_int = 0;
+pub const ADBC_OBJECT_DEPTH_CATALOGS: c_int = 1;
+pub const ADBC_OBJECT_DEPTH_DB_SCHEMAS: c_int = 2;
+pub const ADBC_OBJECT_DEPTH_TABLES: c_int = 3;
+
+/// Set before the database is initialized, the batch size of its context.
+pub const RQ_OPTION_BATCH_SIZE: &str = "rq.batch_size";
+const AUTOCOMMIT_OPTION: &str = "adbc.connection.autocommit";
+
+/// The tables of a database are all in a single catalog and schema.
+const CATALOG: &str = "rq";
+const DB_SCHEMA: &str = "public";
+const DEFAULT_BATCH_SIZE: usize = 1024;
+const TABLE: &str = "table";
+const VIEW: &str = "view";
+
+#[repr(C)]
+pub struct AdbcError {
+    pub message: *mut c_char,
+    pub vendor_code: i32,
+    pub sqlstate: [c_char; 5],
+    pub release: Option<unsafe extern "C" fn(*mut AdbcError)>,
+}
+
+#[repr(C)]
+pub struct AdbcDatabase {
+    pub private_data: *mut c_void,
+    pub private_driver: *mut AdbcDriver,
+}
+
+#[repr(C)]
+pub struct AdbcConnection {
+    pub private_data: *mut c_void,
+    pub private_driver: *mut AdbcDriver,
+}
+
+#[repr(C)]
+pub struct AdbcStatement {
+    pub private_data: *mut c_void,
+    pub private_driver: *mut AdbcDriver,
+}
+
+#[repr(C)]
+pub struct AdbcPartitions {
+    pub num_partitions: usize,
+    pub partitions: *mut *const u8,
+    pub partition_lengths: *const usize,
+    pub private_data: *mut c_void,
+    pub release: Option<unsafe extern "C" fn(*mut AdbcPartitions)>,
+}
+
+type Error = *mut AdbcError;
+
+/// The functions of the driver, in the order of the ADBC 1.0.0 `AdbcDriver` struct.
+#[repr(C)]
+pub struct AdbcDriver {
+    pub private_data: *mut c_void,
+    pub private_manager: *mut c_void,
+    pub release: Option<unsafe extern "C" fn(*mut AdbcDriver, Error) -> AdbcStatusCode>,
+    pub DatabaseInit: Option<unsafe extern "C" fn(*mut AdbcDatabase, Error) -> AdbcStatusCode>,
+    pub DatabaseNew: Option<unsafe extern "C" fn(*mut AdbcDatabase, Error) -> AdbcStatusCode>,
+    pub DatabaseSetOption: Option<
+        unsafe extern "C" fn(
+            *mut AdbcDatabase,
+            *const c_char,
+            *const c_char,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub DatabaseRelease: Option<unsafe extern "C" fn(*mut AdbcDatabase, Error) -> AdbcStatusCode>,
+    pub ConnectionCommit:
+        Option<unsafe extern "C" fn(*mut AdbcConnection, Error) -> AdbcStatusCode>,
+    pub ConnectionGetInfo: Option<
+        unsafe extern "C" fn(
+            *mut AdbcConnection,
+            *const u32,
+            usize,
+            *mut FFI_ArrowArrayStream,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub ConnectionGetObjects: Option<
+        unsafe extern "C" fn(
+            *mut AdbcConnection,
+            c_int,
+            *const c_char,
+            *const c_char,
+            *const c_char,
+            *const *const c_char,
+            *const c_char,
+            *mut FFI_ArrowArrayStream,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub ConnectionGetTableSchema: Option<
+        unsafe extern "C" fn(
+            *mut AdbcConnection,
+            *const c_char,
+            *const c_char,
+            *const c_char,
+            *mut FFI_ArrowSchema,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub ConnectionGetTableTypes: Option<
+        unsafe extern "C" fn(
+            *mut AdbcConnection,
+            *mut FFI_ArrowArrayStream,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub ConnectionInit: Option<
+        unsafe extern "C" fn(*mut AdbcConnection, *mut AdbcDatabase, Error) -> AdbcStatusCode,
+    >,
+    pub ConnectionNew: Option<unsafe extern "C" fn(*mut AdbcConnection, Error) -> AdbcStatusCode>,
+    pub ConnectionSetOption: Option<
+        unsafe extern "C" fn(
+            *mut AdbcConnection,
+            *const c_char,
+            *const c_char,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub ConnectionReadPartition: Option<
+        unsafe extern "C" fn(
+            *mut AdbcConnection,
+            *const u8,
+            usize,
+            *mut FFI_ArrowArrayStream,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub ConnectionRelease:
+        Option<unsafe extern "C" fn(*mut AdbcConnection, Error) -> AdbcStatusCode>,
+    pub ConnectionRollback:
+        Option<unsafe extern "C" fn(*mut AdbcConnection, Error) -> AdbcStatusCode>,
+    pub StatementBind: Option<
+        unsafe extern "C" fn(
+            *mut AdbcStatement,
+            *mut FFI_ArrowArray,
+            *mut FFI_ArrowSchema,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub StatementBindStream: Option<
+        unsafe extern "C" fn(
+            *mut AdbcStatement,
+            *mut FFI_ArrowArrayStream,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub StatementExecuteQuery: Option<
+        unsafe extern "C" fn(
+            *mut AdbcStatement,
+            *mut FFI_ArrowArrayStream,
+            *mut i64,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub StatementExecutePartitions: Option<
+        unsafe extern "C" fn(
+            *mut AdbcStatement,
+            *mut FFI_ArrowSchema,
+            *mut AdbcPartitions,
+            *mut i64,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub StatementGetParameterSchema: Option<
+        unsafe extern "C" fn(*mut AdbcStatement, *mut FFI_ArrowSchema, Error) -> AdbcStatusCode,
+    >,
+    pub StatementNew: Option<
+        unsafe extern "C" fn(*mut AdbcConnection, *mut AdbcStatement, Error) -> AdbcStatusCode,
+    >,
+    pub StatementPrepare: Option<unsafe extern "C" fn(*mut AdbcStatement, Error) -> AdbcStatusCode>,
+    pub StatementRelease: Option<unsafe extern "C" fn(*mut AdbcStatement, Error) -> AdbcStatusCode>,
+    pub StatementSetOption: Option<
+        unsafe extern "C" fn(
+            *mut AdbcStatement,
+            *const c_char,
+            *const c_char,
+            Error,
+        ) -> AdbcStatusCode,
+    >,
+    pub StatementSetSqlQuery:
+        Option<unsafe extern "C" fn(*mut AdbcStatement, *const c_char, Error) -> AdbcStatusCode>,
+    pub StatementSetSubstraitPlan:
+        Option<unsafe extern "C" fn(*mut AdbcStatement, *const u8, usize, Error) -> AdbcStatusCode>,
+}
+
+/// An error reported with its status code, instead of `ADBC_STATUS_UNKNOWN`.
+#[derive(Debug)]
+struct StatusError {
+    status: AdbcStatusCode,
+    message: String,
+}
+
+impl Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+fn status_error(status: AdbcStatusCode, message: impl Display) -> anyhow::Error {
+    anyhow::Error::new(StatusError {
+        status,
+        message: message.to_string(),
+    })
+}
+
+/// Run `f`, reporting its error in `error`, and return the status of the call.
+/// A panic is reported as an internal error, as unwinding out of a C function aborts the
+/// process.
+unsafe fn check(error: Error, f: impl FnOnce() -> Result<()>) -> AdbcStatusCode {
+    let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = format!("rq panicked: {}", panic_message(&payload));
+        Err(status_error(ADBC_STATUS_INTERNAL, message))
+    });
+    match result {
+        Ok(()) => ADBC_STATUS_OK,
+        Err(e) => {
+            let status = e
+                .downcast_ref::<StatusError>()
+                .map_or(ADBC_STATUS_UNKNOWN, |e| e.status);
+            set_error(error, &format!("{:#}", e));
+            status
+        }
+    }
+}
+
+unsafe fn set_error(error: Error, message: &str) {
+    if let Some(error) = error.as_mut() {
+        if let Some(release) = error.release {
+            release(error);
+        }
+        // Interior nul bytes cannot be part of a C string.
+        let message = CString::new(message.replace('\0', " ")).unwrap();
+        *error = AdbcError {
+            message: message.into_raw(),
+            vendor_code: 0,
+            sqlstate: [0; 5],
+            release: Some(release_error),
+        };
+    }
+}
+
+unsafe extern "C" fn release_error(error: *mut AdbcError) {
+    if let Some(error) = error.as_mut() {
+        if !error.message.is_null() {
+            drop(CString::from_raw(error.message));
+        }
+        error.message = ptr::null_mut();
+        error.release = None;
+    }
+}
+
+struct DatabaseState {
+    batch_size: usize,
+    /// Created when the database is initialized, and shared by its connections.
+    ctx: Option<Arc<ExecutionContext>>,
+}
+
+struct ConnectionState {
+    ctx: Option<Arc<ExecutionContext>>,
+}
+
+struct StatementState {
+    ctx: Arc<ExecutionContext>,
+    sql: Option<String>,
+}
+
+/// Return the state of a handle, failing if it was not created or was released.
+unsafe fn state<'a, T>(private_data: Option<*mut c_void>, what: &str) -> Result<&'a mut T> {
+    private_data
+        .and_then(|private_data| (private_data as *mut T).as_mut())
+        .ok_or_else(|| {
+            status_error(
+                ADBC_STATUS_INVALID_STATE,
+                format!("The {} is not created", what),
+            )
+        })
+}
+
+/// Store the state in a handle that has none.
+unsafe fn create<T>(private_data: Option<&mut *mut c_void>, state: T, what: &str) -> Result<()> {
+    match private_data {
+        None => Err(status_error(
+            ADBC_STATUS_INVALID_ARGUMENT,
+            format!("The {} is null", what),
+        )),
+        Some(private_data) if !private_data.is_null() => Err(status_error(
+            ADBC_STATUS_INVALID_STATE,
+            format!("The {} is already created", what),
+        )),
+        Some(private_data) => {
+            *private_data = Box::into_raw(Box::new(state)) as *mut c_void;
+            Ok(())
+        }
+    }
+}
+
+/// Free the state of a handle.
+unsafe fn release<T>(private_data: Option<&mut *mut c_void>, what: &str) -> Result<()> {
+    match private_data {
+        Some(private_data) if !private_data.is_null() => {
+            drop(Box::from_raw(*private_data as *mut T));
+            *private_data = ptr::null_mut();
+            Ok(())
+        }
+        _ => Err(status_error(
+            ADBC_STATUS_INVALID_STATE,
+            format!("The {} is not created", what),
+        )),
+    }
+}
+
+unsafe fn connection_ctx<'a>(connection: *mut AdbcConnection) -> Result<&'a Arc<ExecutionContext>> {
+    let state =
+        state::<ConnectionState>(connection.as_ref().map(|c| c.private_data), "connection")?;
+    state.ctx.as_ref().ok_or_else(|| {
+        status_error(
+            ADBC_STATUS_INVALID_STATE,
+            "The connection is not initialized",
+        )
+    })
+}
+
+unsafe fn optional_str<'a>(s: *const c_char) -> Result<Option<&'a str>> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(CStr::from_ptr(s).to_str()?))
+}
+
+/// Read a null-terminated array of strings.
+unsafe fn str_list<'a>(list: *const *const c_char) -> Result<Option<Vec<&'a str>>> {
+    if list.is_null() {
+        return Ok(None);
+    }
+    let mut strings = vec![];
+    let mut item = list;
+    while !(*item).is_null() {
+        strings.push(CStr::from_ptr(*item).to_str()?);
+        item = item.add(1);
+    }
+    Ok(Some(strings))
+}
+
+/// Return whether the value matches the search pattern, where `%` matches any characters
+/// and `_` a single one. There is no pattern to match when it is null.
+fn matches(pattern: Option<&str>, value: &str) -> bool {
+    fn like(pattern: &[char], value: &[char]) -> bool {
+        match pattern.split_first() {
+            None => value.is_empty(),
+            Some(('%', rest)) => (0..=value.len()).any(|i| like(rest, &value[i..])),
+            Some(('_', rest)) => !value.is_empty() && like(rest, &value[1..]),
+            Some((c, rest)) => value.first() == Some(c) && like(rest, &value[1..]),
+        }
+    }
+    match pattern {
+        None => true,
+        Some(pattern) => like(
+            &pattern.chars().collect::<Vec<_>>(),
+            &value.chars().collect::<Vec<_>>(),
+        ),
+    }
+}
+
+/// Batches exported through the Arrow C Stream Interface.
+struct BatchReader {
+    schema: SchemaRef,
+    batches: std::vec::IntoIter<ArrowRecordBatch>,
+}
+
+impl Iterator for BatchReader {
+    type Item = ArrowResult<ArrowRecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.batches.next().map(Ok)
+    }
+}
+
+impl RecordBatchReader for BatchReader {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+unsafe fn export_stream(
+    out: *mut FFI_ArrowArrayStream,
+    schema: SchemaRef,
+    batches: Vec<ArrowRecordBatch>,
+) -> Result<()> {
+    if out.is_null() {
+        return Err(status_error(
+            ADBC_STATUS_INVALID_ARGUMENT,
+            "The output stream is null",
+        ));
+    }
+    let reader = BatchReader {
+        schema,
+        batches: batches.into_iter(),
+    };
+    ptr::write(out, FFI_ArrowArrayStream::new(Box::new(reader)));
+    Ok(())
+}
+
+/// Fill the driver with the functions of rq. Only ADBC 1.0.0 is supported.
+///
+/// # Safety
+/// The driver must point to writable memory for an `AdbcDriver`.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcDriverInit(
+    version: c_int,
+    driver: *mut c_void,
+    error: Error,
+) -> AdbcStatusCode {
+    if version != ADBC_VERSION_1_0_0 {
+        set_error(error, &format!("Unsupported ADBC version {}", version));
+        return ADBC_STATUS_NOT_IMPLEMENTED;
+    }
+    let driver = driver as *mut AdbcDriver;
+    if driver.is_null() {
+        set_error(error, "The driver is null");
+        return ADBC_STATUS_INVALID_ARGUMENT;
+    }
+    // The driver manager may already have set its own data.
+    let private_manager = (*driver).private_manager;
+    ptr::write(
+        driver,
+        AdbcDriver {
+            private_data: ptr::null_mut(),
+            private_manager,
+            release: Some(release_driver),
+            DatabaseInit: Some(AdbcDatabaseInit),
+            DatabaseNew: Some(AdbcDatabaseNew),
+            DatabaseSetOption: Some(AdbcDatabaseSetOption),
+            DatabaseRelease: Some(AdbcDatabaseRelease),
+            ConnectionCommit: Some(AdbcConnectionCommit),
+            ConnectionGetInfo: Some(AdbcConnectionGetInfo),
+            ConnectionGetObjects: Some(AdbcConnectionGetObjects),
+            ConnectionGetTableSchema: Some(AdbcConnectionGetTableSchema),
+            ConnectionGetTableTypes: Some(AdbcConnectionGetTableTypes),
+            ConnectionInit: Some(AdbcConnectionInit),
+            ConnectionNew: Some(AdbcConnectionNew),
+            ConnectionSetOption: Some(AdbcConnectionSetOption),
+            ConnectionReadPartition: Some(AdbcConnectionReadPartition),
+            ConnectionRelease: Some(AdbcConnectionRelease),
+            ConnectionRollback: Some(AdbcConnectionRollback),
+            StatementBind: Some(AdbcStatementBind),
+            StatementBindStream: Some(AdbcStatementBindStream),
+            StatementExecuteQuery: Some(AdbcStatementExecuteQuery),
+            StatementExecutePartitions: Some(AdbcStatementExecutePartitions),
+            StatementGetParameterSchema: Some(AdbcStatementGetParameterSchema),
+            StatementNew: Some(AdbcStatementNew),
+            StatementPrepare: Some(AdbcStatementPrepare),
+            StatementRelease: Some(AdbcStatementRelease),
+            StatementSetOption: Some(AdbcStatementSetOption),
+            StatementSetSqlQuery: Some(AdbcStatementSetSqlQuery),
+            StatementSetSubstraitPlan: Some(AdbcStatementSetSubstraitPlan),
+        },
+    );
+    ADBC_STATUS_OK
+}
+
+unsafe extern "C" fn release_driver(driver: *mut AdbcDriver, _error: Error) -> AdbcStatusCode {
+    if let Some(driver) = driver.as_mut() {
+        driver.release = None;
+    }
+    ADBC_STATUS_OK
+}
+
+/// # Safety
+/// The database must be valid, and have no state yet.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcDatabaseNew(
+    database: *mut AdbcDatabase,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let state = DatabaseState {
+            batch_size: DEFAULT_BATCH_SIZE,
+            ctx: None,
+        };
+        create(
+            database.as_mut().map(|d| &mut d.private_data),
+            state,
+            "database",
+        )
+    })
+}
+
+/// Set an option of the database before it is initialized. `rq.batch_size` is the only
+/// option.
+///
+/// # Safety
+/// The database must be valid, and the key and value nul-terminated UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcDatabaseSetOption(
+    database: *mut AdbcDatabase,
+    key: *const c_char,
+    value: *const c_char,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let state = state::<DatabaseState>(database.as_ref().map(|d| d.private_data), "database")?;
+        if state.ctx.is_some() {
+            return Err(status_error(
+                ADBC_STATUS_INVALID_STATE,
+                "The options of the database are set before it is initialized",
+            ));
+        }
+        let (key, value) = (to_str(key, "option key")?, to_str(value, "option value")?);
+        match key {
+            RQ_OPTION_BATCH_SIZE => match value.parse::<usize>() {
+                Ok(batch_size) if batch_size > 0 => {
+                    state.batch_size = batch_size;
+                    Ok(())
+                }
+                _ => Err(status_error(
+                    ADBC_STATUS_INVALID_ARGUMENT,
+                    format!("Invalid batch size {}", value),
+                )),
+            },
+            _ => Err(status_error(
+                ADBC_STATUS_NOT_IMPLEMENTED,
+                format!("Unknown database option {}", key),
+            )),
+        }
+    })
+}
+
+/// Create the context of the database, shared by its connections.
+///
+/// # Safety
+/// The database must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcDatabaseInit(
+    database: *mut AdbcDatabase,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let state = state::<DatabaseState>(database.as_ref().map(|d| d.private_data), "database")?;
+        if state.ctx.is_some() {
+            return Err(status_error(
+                ADBC_STATUS_INVALID_STATE,
+                "The database is already initialized",
+            ));
+        }
+        state.ctx = Some(Arc::new(ExecutionContext::new(state.batch_size)));
+        Ok(())
+    })
+}
+
+/// # Safety
+/// The database must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcDatabaseRelease(
+    database: *mut AdbcDatabase,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        release::<DatabaseState>(database.as_mut().map(|d| &mut d.private_data), "database")
+    })
+}
+
+/// # Safety
+/// The connection must be valid, and have no state yet.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionNew(
+    connection: *mut AdbcConnection,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let state = ConnectionState { ctx: None };
+        create(
+            connection.as_mut().map(|c| &mut c.private_data),
+            state,
+            "connection",
+        )
+    })
+}
+
+/// Set an option of the connection. Only autocommit is supported, as rq has no
+/// transactions.
+///
+/// # Safety
+/// The connection must be valid, and the key and value nul-terminated UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionSetOption(
+    connection: *mut AdbcConnection,
+    key: *const c_char,
+    value: *const c_char,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        state::<ConnectionState>(connection.as_ref().map(|c| c.private_data), "connection")?;
+        let (key, value) = (to_str(key, "option key")?, to_str(value, "option value")?);
+        match (key, value) {
+            (AUTOCOMMIT_OPTION, "true") => Ok(()),
+            (AUTOCOMMIT_OPTION, _) => Err(status_error(
+                ADBC_STATUS_NOT_IMPLEMENTED,
+                "Transactions are not supported",
+            )),
+            _ => Err(status_error(
+                ADBC_STATUS_NOT_IMPLEMENTED,
+                format!("Unknown connection option {}", key),
+            )),
+        }
+    })
+}
+
+/// Connect to the initialized database.
+///
+/// # Safety
+/// The connection and the database must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionInit(
+    connection: *mut AdbcConnection,
+    database: *mut AdbcDatabase,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let database =
+            state::<DatabaseState>(database.as_ref().map(|d| d.private_data), "database")?;
+        let ctx = database.ctx.clone().ok_or_else(|| {
+            status_error(ADBC_STATUS_INVALID_STATE, "The database is not initialized")
+        })?;
+        state::<ConnectionState>(connection.as_ref().map(|c| c.private_data), "connection")?.ctx =
+            Some(ctx);
+        Ok(())
+    })
+}
+
+/// # Safety
+/// The connection must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionRelease(
+    connection: *mut AdbcConnection,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        release::<ConnectionState>(
+            connection.as_mut().map(|c| &mut c.private_data),
+            "connection",
+        )
+    })
+}
+
+/// # Safety
+/// The connection must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionCommit(
+    connection: *mut AdbcConnection,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        connection_ctx(connection)?;
+        Err(status_error(
+            ADBC_STATUS_INVALID_STATE,
+            "The connection is in autocommit mode",
+        ))
+    })
+}
+
+/// # Safety
+/// The connection must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionRollback(
+    connection: *mut AdbcConnection,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        connection_ctx(connection)?;
+        Err(status_error(
+            ADBC_STATUS_INVALID_STATE,
+            "The connection is in autocommit mode",
+        ))
+    })
+}
+
+/// # Safety
+/// The connection must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionGetInfo(
+    _connection: *mut AdbcConnection,
+    _info_codes: *const u32,
+    _info_codes_length: usize,
+    _out: *mut FFI_ArrowArrayStream,
+    error: Error,
+) -> AdbcStatusCode {
+    set_error(error, "Driver and database info is not supported");
+    ADBC_STATUS_NOT_IMPLEMENTED
+}
+
+/// List the catalog, schema, tables and views, and the columns of the tables and views,
+/// down to the given depth. Tables and views have no constraints.
+///
+/// # Safety
+/// The connection must be valid, the filters nul-terminated UTF-8 strings or null, the
+/// table types a null-terminated array of them or null, and `out` point to writable
+/// memory for an `ArrowArrayStream`.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionGetObjects(
+    connection: *mut AdbcConnection,
+    depth: c_int,
+    catalog: *const c_char,
+    db_schema: *const c_char,
+    table_name: *const c_char,
+    table_type: *const *const c_char,
+    column_name: *const c_char,
+    out: *mut FFI_ArrowArrayStream,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let ctx = connection_ctx(connection)?;
+        let filter = ObjectFilter {
+            depth,
+            catalog: optional_str(catalog)?,
+            db_schema: optional_str(db_schema)?,
+            table_name: optional_str(table_name)?,
+            table_types: str_list(table_type)?,
+            column_name: optional_str(column_name)?,
+        };
+        let batch = objects(ctx, &filter)?;
+        export_stream(out, batch.schema(), vec![batch])
+    })
+}
+
+/// Export the schema of a table or view.
+///
+/// # Safety
+/// The connection must be valid, the names nul-terminated UTF-8 strings, the catalog and
+/// schema may be null, and `schema` point to writable memory for an `ArrowSchema`.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionGetTableSchema(
+    connection: *mut AdbcConnection,
+    catalog: *const c_char,
+    db_schema: *const c_char,
+    table_name: *const c_char,
+    schema: *mut FFI_ArrowSchema,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let ctx = connection_ctx(connection)?;
+        let table_name = to_str(table_name, "table name")?;
+        let in_catalog = optional_str(catalog)?.is_none_or(|c| c == CATALOG)
+            && optional_str(db_schema)?.is_none_or(|s| s == DB_SCHEMA);
+        let df = match ctx.table(table_name) {
+            Ok(df) if in_catalog => df,
+            _ => {
+                return Err(status_error(
+                    ADBC_STATUS_NOT_FOUND,
+                    format!("No table named {}", table_name),
+                ))
+            }
+        };
+        if schema.is_null() {
+            return Err(status_error(
+                ADBC_STATUS_INVALID_ARGUMENT,
+                "The output schema is null",
+            ));
+        }
+        let arrow_schema: ArrowSchema = df.schema().into();
+        ptr::write(schema, FFI_ArrowSchema::try_from(&arrow_schema)?);
+        Ok(())
+    })
+}
+
+/// Export the table types, `table` and `view`.
+///
+/// # Safety
+/// The connection must be valid, and `out` point to writable memory for an
+/// `ArrowArrayStream`.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionGetTableTypes(
+    connection: *mut AdbcConnection,
+    out: *mut FFI_ArrowArrayStream,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        connection_ctx(connection)?;
+        let schema = Arc::new(ArrowSchema::new(vec![Field::new(
+            "table_type",
+            DataType::Utf8,
+            false,
+        )]));
+        let table_types = Arc::new(StringArray::from(vec![TABLE, VIEW])) as ArrayRef;
+        let batch = ArrowRecordBatch::try_new(schema.clone(), vec![table_types])?;
+        export_stream(out, schema, vec![batch])
+    })
+}
+
+/// # Safety
+/// The connection must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcConnectionReadPartition(
+    _connection: *mut AdbcConnection,
+    _serialized_partition: *const u8,
+    _serialized_length: usize,
+    _out: *mut FFI_ArrowArrayStream,
+    error: Error,
+) -> AdbcStatusCode {
+    set_error(error, "Partitioned results are not supported");
+    ADBC_STATUS_NOT_IMPLEMENTED
+}
+
+/// # Safety
+/// The connection must be valid and initialized, and the statement have no state yet.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementNew(
+    connection: *mut AdbcConnection,
+    statement: *mut AdbcStatement,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let state = StatementState {
+            ctx: connection_ctx(connection)?.clone(),
+            sql: None,
+        };
+        create(
+            statement.as_mut().map(|s| &mut s.private_data),
+            state,
+            "statement",
+        )
+    })
+}
+
+/// # Safety
+/// The statement must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementRelease(
+    statement: *mut AdbcStatement,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        release::<StatementState>(statement.as_mut().map(|s| &mut s.private_data), "statement")
+    })
+}
+
+/// Set the SQL statement executed by the statement.
+///
+/// # Safety
+/// The statement must be valid, and the query a nul-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementSetSqlQuery(
+    statement: *mut AdbcStatement,
+    query: *const c_char,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let state =
+            state::<StatementState>(statement.as_ref().map(|s| s.private_data), "statement")?;
+        state.sql = Some(to_str(query, "query")?.to_string());
+        Ok(())
+    })
+}
+
+/// Plan the SQL statement, to report its errors before it is executed.
+///
+/// # Safety
+/// The statement must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementPrepare(
+    statement: *mut AdbcStatement,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let state =
+            state::<StatementState>(statement.as_ref().map(|s| s.private_data), "statement")?;
+        plan(state).map(|_| ())
+    })
+}
+
+/// Execute the SQL statement, exporting its result to `out` unless it is null.
+/// The number of rows affected is unknown.
+///
+/// # Safety
+/// The statement must be valid, `out` point to writable memory for an `ArrowArrayStream`
+/// or be null, and `rows_affected` point to writable memory or be null.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementExecuteQuery(
+    statement: *mut AdbcStatement,
+    out: *mut FFI_ArrowArrayStream,
+    rows_affected: *mut i64,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        let state =
+            state::<StatementState>(statement.as_ref().map(|s| s.private_data), "statement")?;
+        let df = plan(state)?;
+        let batches = df
+            .collect(&state.ctx)?
+            .iter()
+            .filter(|batch| batch.row_count() > 0)
+            .map(ArrowRecordBatch::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(rows_affected) = rows_affected.as_mut() {
+            *rows_affected = -1;
+        }
+        if out.is_null() {
+            return Ok(());
+        }
+        let schema: ArrowSchema = df.schema().into();
+        export_stream(out, Arc::new(schema), batches)
+    })
+}
+
+fn plan(state: &StatementState) -> Result<DataFrame> {
+    let sql = state
+        .sql
+        .as_deref()
+        .ok_or_else(|| status_error(ADBC_STATUS_INVALID_STATE, "The statement has no query"))?;
+    state
+        .ctx
+        .sql(sql)
+        .map_err(|e| status_error(ADBC_STATUS_INVALID_ARGUMENT, format!("{:#}", e)))
+}
+
+/// # Safety
+/// The statement must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementSetOption(
+    _statement: *mut AdbcStatement,
+    key: *const c_char,
+    _value: *const c_char,
+    error: Error,
+) -> AdbcStatusCode {
+    check(error, || {
+        Err(status_error(
+            ADBC_STATUS_NOT_IMPLEMENTED,
+            format!("Unknown statement option {}", to_str(key, "option key")?),
+        ))
+    })
+}
+
+/// # Safety
+/// The statement must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementBind(
+    _statement: *mut AdbcStatement,
+    _values: *mut FFI_ArrowArray,
+    _schema: *mut FFI_ArrowSchema,
+    error: Error,
+) -> AdbcStatusCode {
+    set_error(error, "Binding parameters is not supported");
+    ADBC_STATUS_NOT_IMPLEMENTED
+}
+
+/// # Safety
+/// The statement must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementBindStream(
+    _statement: *mut AdbcStatement,
+    _stream: *mut FFI_ArrowArrayStream,
+    error: Error,
+) -> AdbcStatusCode {
+    set_error(error, "Binding parameters is not supported");
+    ADBC_STATUS_NOT_IMPLEMENTED
+}
+
+/// # Safety
+/// The statement must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementGetParameterSchema(
+    _statement: *mut AdbcStatement,
+    _schema: *mut FFI_ArrowSchema,
+    error: Error,
+) -> AdbcStatusCode {
+    set_error(error, "Binding parameters is not supported");
+    ADBC_STATUS_NOT_IMPLEMENTED
+}
+
+/// # Safety
+/// The statement must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementExecutePartitions(
+    _statement: *mut AdbcStatement,
+    _schema: *mut FFI_ArrowSchema,
+    _partitions: *mut AdbcPartitions,
+    _rows_affected: *mut i64,
+    error: Error,
+) -> AdbcStatusCode {
+    set_error(error, "Partitioned results are not supported");
+    ADBC_STATUS_NOT_IMPLEMENTED
+}
+
+/// # Safety
+/// The statement must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn AdbcStatementSetSubstraitPlan(
+    _statement: *mut AdbcStatement,
+    _plan: *const u8,
+    _length: usize,
+    error: Error,
+) -> AdbcStatusCode {
+    set_error(error, "Substrait plans are not supported");
+    ADBC_STATUS_NOT_IMPLEMENTED
+}
+
+/// The filters of `AdbcConnectionGetObjects`.
+struct ObjectFilter<'a> {
+    depth: c_int,
+    catalog: Option<&'a str>,
+    db_schema: Option<&'a str>,
+    table_name: Option<&'a str>,
+    table_types: Option<Vec<&'a str>>,
+    column_name: Option<&'a str>,
+}
+
+fn list_type(fields: Vec<Field>) -> DataType {
+    DataType::List(Box::new(Field::new("item", DataType::Struct(fields), true)))
+}
+
+fn usage_fields() -> Vec<Field> {
+    vec![
+        Field::new("fk_catalog", DataType::Utf8, true),
+        Field::new("fk_db_schema", DataType::Utf8, true),
+        Field::new("fk_table", DataType::Utf8, false),
+        Field::new("fk_column_name", DataType::Utf8, false),
+    ]
+}
+
+fn constraint_fields() -> Vec<Field> {
+    let column_names = Field::new("item", DataType::Utf8, true);
+    vec![
+        Field::new("constraint_name", DataType::Utf8, true),
+        Field::new("constraint_type", DataType::Utf8, false),
+        Field::new(
+            "constraint_column_names",
+            DataType::List(Box::new(column_names)),
+            false,
+        ),
+        Field::new("constraint_column_usage", list_type(usage_fields()), true),
+    ]
+}
+
+fn column_fields() -> Vec<Field> {
+    vec![
+        Field::new("column_name", DataType::Utf8, false),
+        Field::new("ordinal_position", DataType::Int32, true),
+        Field::new("remarks", DataType::Utf8, true),
+        Field::new("xdbc_data_type", DataType::Int16, true),
+        Field::new("xdbc_type_name", DataType::Utf8, true),
+        Field::new("xdbc_column_size", DataType::Int32, true),
+        Field::new("xdbc_decimal_digits", DataType::Int16, true),
+        Field::new("xdbc_num_prec_radix", DataType::Int16, true),
+        Field::new("xdbc_nullable", DataType::Int16, true),
+        Field::new("xdbc_column_def", DataType::Utf8, true),
+        Field::new("xdbc_sql_data_type", DataType::Int16, true),
+        Field::new("xdbc_datetime_sub", DataType::Int16, true),
+        Field::new("xdbc_char_octet_length", DataType::Int32, true),
+        Field::new("xdbc_is_nullable", DataType::Utf8, true),
+        Field::new("xdbc_scope_catalog", DataType::Utf8, true),
+        Field::new("xdbc_scope_schema", DataType::Utf8, true),
+        Field::new("xdbc_scope_table", DataType::Utf8, true),
+        Field::new("xdbc_is_autoincrement", DataType::Boolean, true),
+        Field::new("xdbc_is_generatedcolumn", DataType::Boolean, true),
+    ]
+}
+
+fn table_fields() -> Vec<Field> {
+    vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("table_type", DataType::Utf8, false),
+        Field::new("table_columns", list_type(column_fields()), true),
+        Field::new("table_constraints", list_type(constraint_fields()), true),
+    ]
+}
+
+fn db_schema_fields() -> Vec<Field> {
+    vec![
+        Field::new("db_schema_name", DataType::Utf8, true),
+        Field::new("db_schema_tables", list_type(table_fields()), true),
+    ]
+}
+
+fn objects_schema() -> ArrowSchema {
+    ArrowSchema::new(vec![
+        Field::new("catalog_name", DataType::Utf8, true),
+        Field::new("catalog_db_schemas", list_type(db_schema_fields()), true),
+    ])
+}
+
+/// Build a struct array from its fields and their columns.
+fn struct_array(fields: Vec<Field>, columns: Vec<ArrayRef>) -> ArrayRef {
+    Arc::new(StructArray::from(
+        fields.into_iter().zip(columns).collect::<Vec<_>>(),
+    ))
+}
+
+/// Build a list array whose lists are the values between consecutive offsets, or whose
+/// lists are all null below the depth of the request.
+fn list_array(
+    item_fields: Vec<Field>,
+    values: ArrayRef,
+    offsets: &[i32],
+    listed: bool,
+) -> Result<ArrayRef> {
+    let data_type = list_type(item_fields);
+    if !listed {
+        return Ok(new_null_array(&data_type, offsets.len() - 1));
+    }
+    let data = ArrayData::builder(data_type)
+        .len(offsets.len() - 1)
+        .add_buffer(Buffer::from_slice_ref(&offsets))
+        .add_child_data(values.data().clone())
+        .build()?;
+    Ok(make_array(data))
+}
+
+/// Return the offsets of lists of the given lengths.
+fn offsets(lengths: impl IntoIterator<Item = usize>) -> Vec<i32> {
+    let mut offsets = vec![0];
+    for length in lengths {
+        offsets.push(offsets.last().unwrap() + length as i32);
+    }
+    offsets
+}
+
+/// Return the columns of the table that match the filter, with their position.
+fn table_columns(
+    ctx: &ExecutionContext,
+    table_name: &str,
+    filter: &ObjectFilter,
+) -> Result<Vec<(i32, SchemaField)>> {
+    let schema: Schema = ctx.table(table_name)?.schema();
+    Ok(schema
+        .fields
+        .into_iter()
+        .enumerate()
+        .filter(|(_, field)| matches(filter.column_name, &field.name))
+        .map(|(i, field)| (i as i32 + 1, field))
+        .collect())
+}
+
+fn objects(ctx: &ExecutionContext, filter: &ObjectFilter) -> Result<ArrowRecordBatch> {
+    let schema = Arc::new(objects_schema());
+    if !matches(filter.catalog, CATALOG) {
+        return Ok(ArrowRecordBatch::new_empty(schema));
+    }
+    let list_schemas =
+        filter.depth == ADBC_OBJECT_DEPTH_ALL || filter.depth >= ADBC_OBJECT_DEPTH_DB_SCHEMAS;
+    let list_tables =
+        filter.depth == ADBC_OBJECT_DEPTH_ALL || filter.depth >= ADBC_OBJECT_DEPTH_TABLES;
+    let list_columns =
+        filter.depth == ADBC_OBJECT_DEPTH_ALL || filter.depth > ADBC_OBJECT_DEPTH_TABLES;
+
+    let db_schemas = if list_schemas && matches(filter.db_schema, DB_SCHEMA) {
+        vec![DB_SCHEMA]
+    } else {
+        vec![]
+    };
+    let tables = if list_tables && !db_schemas.is_empty() {
+        let table_names = ctx.table_names().into_iter().map(|name| (name, TABLE));
+        let view_names = ctx.view_names().into_iter().map(|name| (name, VIEW));
+        table_names
+            .chain(view_names)
+            .filter(|(name, table_type)| {
+                matches(filter.table_name, name)
+                    && filter
+                        .table_types
+                        .as_ref()
+                        .is_none_or(|types| types.contains(table_type))
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+    let columns = if list_columns {
+        tables
+            .iter()
+            .map(|(name, _)| table_columns(ctx, name, filter))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        tables.iter().map(|_| vec![]).collect()
+    };
+
+    // The columns of all the tables, then their lists per table.
+    let all_columns = columns.iter().flatten().collect::<Vec<_>>();
+    let column_count = all_columns.len();
+    let column_values = column_fields()
+        .iter()
+        .map(|field| -> ArrayRef {
+            match field.name().as_str() {
+                "column_name" => Arc::new(
+                    all_columns
+                        .iter()
+                        .map(|(_, f)| Some(f.name.as_str()))
+                        .collect::<StringArray>(),
+                ),
+                "ordinal_position" => Arc::new(
+                    all_columns
+                        .iter()
+                        .map(|(position, _)| Some(*position))
+                        .collect::<Int32Array>(),
+                ),
+                "xdbc_type_name" => Arc::new(
+                    all_columns
+                        .iter()
+                        .map(|(_, f)| {
+                            Some(match &f.extension {
+                                Some(extension) => extension.name().to_string(),
+                                None => f.data_type.to_string(),
+                            })
+                        })
+                        .collect::<StringArray>(),
+                ),
+                "xdbc_nullable" => Arc::new(
+                    all_columns
+                        .iter()
+                        .map(|(_, f)| Some(f.nullable as i16))
+                        .collect::<Int16Array>(),
+                ),
+                "xdbc_is_nullable" => Arc::new(
+                    all_columns
+                        .iter()
+                        .map(|(_, f)| Some(if f.nullable { "YES" } else { "NO" }))
+                        .collect::<StringArray>(),
+                ),
+                _ => new_null_array(field.data_type(), column_count),
+            }
+        })
+        .collect();
+    let table_columns = list_array(
+        column_fields(),
+        struct_array(column_fields(), column_values),
+        &offsets(columns.iter().map(|c| c.len())),
+        list_columns,
+    )?;
+    let table_constraints = list_array(
+        constraint_fields(),
+        new_empty_array(&DataType::Struct(constraint_fields())),
+        &offsets(tables.iter().map(|_| 0)),
+        list_columns,
+    )?;
+
+    let table_values = struct_array(
+        table_fields(),
+        vec![
+            Arc::new(
+                tables
+                    .iter()
+                    .map(|(name, _)| Some(name.as_str()))
+                    .collect::<StringArray>(),
+            ),
+            Arc::new(
+                tables
+                    .iter()
+                    .map(|(_, t)| Some(*t))
+                    .collect::<StringArray>(),
+            ),
+            table_columns,
+            table_constraints,
+        ],
+    );
+    let db_schema_tables = list_array(
+        table_fields(),
+        table_values,
+        &offsets(db_schemas.iter().map(|_| tables.len())),
+        list_tables,
+    )?;
+
+    let db_schema_values = struct_array(
+        db_schema_fields(),
+        vec![
+            Arc::new(db_schemas.iter().map(|s| Some(*s)).collect::<StringArray>()),
+            db_schema_tables,
+        ],
+    );
+    let catalog_db_schemas = list_array(
+        db_schema_fields(),
+        db_schema_values,
+        &offsets([db_schemas.len()]),
+        list_schemas,
+    )?;
+
+    Ok(ArrowRecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(StringArray::from(vec![CATALOG])),
+            catalog_db_schemas,
+        ],
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    use super::*;
+
+    use arrow::{
+        array::{Int64Array, ListArray},
+        ffi_stream::ArrowArrayStreamReader,
+    };
+
+    fn empty_error() -> AdbcError {
+        AdbcError {
+            message: ptr::null_mut(),
+            vendor_code: 0,
+            sqlstate: [0; 5],
+            release: None,
+        }
+    }
+
+    unsafe fn message(error: &AdbcError) -> &str {
+        CStr::from_ptr(error.message).to_str().unwrap()
+    }
+
+    unsafe fn read_stream(stream: &mut FFI_ArrowArrayStream) -> Vec<ArrowRecordBatch> {
+        let reader = ArrowArrayStreamReader::from_raw(stream).unwrap();
+        reader.collect::<ArrowResult<Vec<_>>>().unwrap()
+    }
+
+    unsafe fn execute(statement: &mut AdbcStatement, sql: &str) -> Vec<ArrowRecordBatch> {
+        let mut error = empty_error();
+        let sql = CString::new(sql).unwrap();
+        assert_eq!(
+            AdbcStatementSetSqlQuery(statement, sql.as_ptr(), &mut error),
+            ADBC_STATUS_OK
+        );
+        let mut stream = FFI_ArrowArrayStream::empty();
+        let mut rows_affected = 0;
+        let status =
+            AdbcStatementExecuteQuery(statement, &mut stream, &mut rows_affected, &mut error);
+        assert_eq!(status, ADBC_STATUS_OK);
+        assert_eq!(rows_affected, -1);
+        read_stream(&mut stream)
+    }
+
+    #[test]
+    fn test_adbc() {
+        unsafe {
+            let mut error = empty_error();
+            let mut driver = MaybeUninit::<AdbcDriver>::zeroed();
+            let raw_driver = driver.as_mut_ptr() as *mut c_void;
+            assert_eq!(
+                AdbcDriverInit(1_001_000, raw_driver, &mut error),
+                ADBC_STATUS_NOT_IMPLEMENTED
+            );
+            assert_eq!(message(&error), "Unsupported ADBC version 1001000");
+            assert_eq!(
+                AdbcDriverInit(ADBC_VERSION_1_0_0, raw_driver, &mut error),
+                ADBC_STATUS_OK
+            );
+            let driver = driver.assume_init();
+
+            let mut database = MaybeUninit::<AdbcDatabase>::zeroed().assume_init();
+            assert_eq!((driver.DatabaseNew.unwrap())(&mut database, &mut error), 0);
+            let (key, value) = (
+                CString::new("rq.batch_size").unwrap(),
+                CString::new("2").unwrap(),
+            );
+            let status =
+                AdbcDatabaseSetOption(&mut database, key.as_ptr(), value.as_ptr(), &mut error);
+            assert_eq!(status, ADBC_STATUS_OK);
+            assert_eq!(AdbcDatabaseInit(&mut database, &mut error), ADBC_STATUS_OK);
+
+            let mut connection = MaybeUninit::<AdbcConnection>::zeroed().assume_init();
+            assert_eq!(
+                AdbcConnectionNew(&mut connection, &mut error),
+                ADBC_STATUS_OK
+            );
+            let mut statement = MaybeUninit::<AdbcStatement>::zeroed().assume_init();
+            assert_eq!(
+                AdbcStatementNew(&mut connection, &mut statement, &mut error),
+                ADBC_STATUS_INVALID_STATE
+            );
+            assert_eq!(message(&error), "The connection is not initialized");
+            assert_eq!(
+                AdbcConnectionInit(&mut connection, &mut database, &mut error),
+                ADBC_STATUS_OK
+            );
+            assert_eq!(
+                AdbcStatementNew(&mut connection, &mut statement, &mut error),
+                ADBC_STATUS_OK
+            );
+
+            let path = format!(
+                "{}/../tests/data/primitive_field.csv",
+                env!("CARGO_MANIFEST_DIR")
+            );
+            execute(
+                &mut statement,
+                &format!(
+                    "CREATE EXTERNAL TABLE t (c1 INT, c2 INT, c3 BIGINT, c4 BIGINT, c5 REAL, \
+                     c6 DOUBLE) STORED AS CSV LOCATION '{}'",
+                    path
+                ),
+            );
+            let batches = execute(&mut statement, "SELECT c1, c3 * 2 AS d FROM t WHERE c1 > 1");
+            assert_eq!(batches[0].schema().field(1).name(), "d");
+            let values = batches
+                .iter()
+                .flat_map(|b| {
+                    let column = b.column(1).as_any().downcast_ref::<Int64Array>().unwrap();
+                    column.values().to_vec()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(values, vec![42, 44]);
+
+            let sql = CString::new("SELECT nope FROM t").unwrap();
+            AdbcStatementSetSqlQuery(&mut statement, sql.as_ptr(), &mut error);
+            assert_eq!(
+                AdbcStatementPrepare(&mut statement, &mut error),
+                ADBC_STATUS_INVALID_ARGUMENT
+            );
+            assert!(message(&error).starts_with("No column named nope"));
+
+            // The tables and their columns, in the only catalog and schema.
+            let mut stream = FFI_ArrowArrayStream::empty();
+            let status = AdbcConnectionGetObjects(
+                &mut connection,
+                ADBC_OBJECT_DEPTH_ALL,
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                &mut stream,
+                &mut error,
+            );
+            assert_eq!(status, ADBC_STATUS_OK);
+            let batches = read_stream(&mut stream);
+            let list = |array: &ArrayRef, index: usize| {
+                let list = array.as_any().downcast_ref::<ListArray>().unwrap();
+                StructArray::from(list.value(index).data().clone())
+            };
+            let string = |array: &ArrayRef, index: usize| {
+                let array = array.as_any().downcast_ref::<StringArray>().unwrap();
+                array.value(index).to_string()
+            };
+            assert_eq!(string(batches[0].column(0), 0), "rq");
+            let db_schemas = list(batches[0].column(1), 0);
+            assert_eq!(string(db_schemas.column(0), 0), "public");
+            let tables = list(db_schemas.column(1), 0);
+            assert_eq!(string(tables.column(0), 0), "t");
+            assert_eq!(string(tables.column(1), 0), "table");
+            let columns = list(tables.column(2), 0);
+            assert_eq!(columns.len(), 6);
+            assert_eq!(string(columns.column(0), 2), "c3");
+            assert_eq!(string(columns.column(4), 2), "Int64");
+
+            let mut schema = FFI_ArrowSchema::empty();
+            let table = CString::new("t").unwrap();
+            let status = AdbcConnectionGetTableSchema(
+                &mut connection,
+                ptr::null(),
+                ptr::null(),
+                table.as_ptr(),
+                &mut schema,
+                &mut error,
+            );
+            assert_eq!(status, ADBC_STATUS_OK);
+            assert_eq!(ArrowSchema::try_from(&schema).unwrap().fields().len(), 6);
+            let table = CString::new("nope").unwrap();
+            let status = AdbcConnectionGetTableSchema(
+                &mut connection,
+                ptr::null(),
+                ptr::null(),
+                table.as_ptr(),
+                &mut schema,
+                &mut error,
+            );
+            assert_eq!(status, ADBC_STATUS_NOT_FOUND);
+            assert_eq!(message(&error), "No table named nope");
+
+            assert_eq!(
+                AdbcStatementRelease(&mut statement, &mut error),
+                ADBC_STATUS_OK
+            );
+            assert_eq!(
+                AdbcConnectionRelease(&mut connection, &mut error),
+                ADBC_STATUS_OK
+            );
+            assert_eq!(
+                AdbcDatabaseRelease(&mut database, &mut error),
+                ADBC_STATUS_OK
+            );
+            assert_eq!(
+                AdbcDatabaseRelease(&mut database, &mut error),
+                ADBC_STATUS_INVALID_STATE
+            );
+            (error.release.unwrap())(&mut error);
+            assert!(error.message.is_null());
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(None, "t"));
+        assert!(matches(Some("t%"), "table"));
+        assert!(matches(Some("_ab%"), "table"));
+        assert!(!matches(Some("_ab"), "table"));
+        assert!(!matches(Some("view"), "table"));
+    }
+
+    #[test]
+    fn test_check_panic() {
+        let mut error = empty_error();
+        unsafe {
+            let status = check(&mut error, || panic!("boom"));
+            assert_eq!(status, ADBC_STATUS_INTERNAL);
+            assert_eq!(message(&error), "rq panicked: boom");
+            release_error(&mut error);
+        }
+    }
+}
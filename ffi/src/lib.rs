@@ -0,0 +1,298 @@
+/// An ADBC 1.0.0 driver, loaded by ADBC driver managers through `AdbcDriverInit`, whose
+/// functions are also exported under their own names to link to rq directly.
+#[allow(non_snake_case)]
+pub mod adbc;
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use rq::{
+    data_source::csv_data_source::CsvDataSource, data_types::record_batch::RecordBatch,
+    execution::ExecutionContext,
+};
+
+use anyhow::{anyhow, Result};
+use arrow::{
+    array::{Array, StructArray},
+    datatypes::Schema as ArrowSchema,
+    ffi::{FFI_ArrowArray, FFI_ArrowSchema},
+    record_batch::RecordBatch as ArrowRecordBatch,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, recording its error as the last error of the thread. A panic is recorded as
+/// an error, as unwinding out of a C function aborts the process.
+fn record_error<T>(f: impl FnOnce() -> Result<T>) -> Option<T> {
+    let result = panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|payload| Err(anyhow!("rq panicked: {}", panic_message(&payload))));
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            // Interior nul bytes cannot be part of a C string.
+            let message = format!("{:#}", e).replace('\0', " ");
+            LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+            None
+        }
+    }
+}
+
+/// Return the message a panic was raised with.
+fn panic_message(payload: &Box<dyn Any + Send>) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("unknown panic", String::as_str),
+    }
+}
+
+fn status(result: Option<()>) -> c_int {
+    match result {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+unsafe fn to_str<'a>(s: *const c_char, what: &str) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(anyhow!("The {} is null", what));
+    }
+    Ok(CStr::from_ptr(s).to_str()?)
+}
+
+/// The result of a query, with its schema and batches, executed when it was created.
+pub struct RqResult {
+    schema: ArrowSchema,
+    batches: Vec<ArrowRecordBatch>,
+}
+
+impl RqResult {
+    fn try_new(schema: ArrowSchema, batches: &[RecordBatch]) -> Result<Self> {
+        let batches = batches
+            .iter()
+            .filter(|batch| batch.row_count() > 0)
+            .map(ArrowRecordBatch::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { schema, batches })
+    }
+}
+
+/// Return the message of the last error of the calling thread, or null if there was none.
+/// The message is valid until the next call that fails on the same thread.
+#[no_mangle]
+pub extern "C" fn rq_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Create a context, to be freed with `rq_context_free`, or return null on error.
+#[no_mangle]
+pub extern "C" fn rq_context_new(batch_size: usize) -> *mut ExecutionContext {
+    record_error(|| Ok(Box::into_raw(Box::new(ExecutionContext::new(batch_size)))))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Free a context created by `rq_context_new`.
+///
+/// # Safety
+/// The context must have been created by `rq_context_new` and not freed yet, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn rq_context_free(ctx: *mut ExecutionContext) {
+    if !ctx.is_null() {
+        record_error(|| {
+            drop(Box::from_raw(ctx));
+            Ok(())
+        });
+    }
+}
+
+/// Register a CSV file without a header as a table, with the schema inferred from its
+/// first rows. Return 0 on success and -1 on error.
+///
+/// # Safety
+/// The context must be valid, and the name and path nul-terminated UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn rq_register_csv(
+    ctx: *const ExecutionContext,
+    name: *const c_char,
+    path: *const c_char,
+) -> c_int {
+    status(record_error(|| {
+        let ctx = ctx.as_ref().ok_or_else(|| anyhow!("The context is null"))?;
+        let name = to_str(name, "table name")?;
+        let path = to_str(path, "path")?;
+        let schema = CsvDataSource::infer_schema(path)?;
+        ctx.register_csv(name, path.to_string(), schema);
+        Ok(())
+    }))
+}
+
+/// Plan and execute a SQL statement, returning its result to be freed with
+/// `rq_result_free`, or null on error.
+///
+/// # Safety
+/// The context must be valid, and the statement a nul-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn rq_sql(ctx: *const ExecutionContext, sql: *const c_char) -> *mut RqResult {
+    let result = record_error(|| {
+        let ctx = ctx.as_ref().ok_or_else(|| anyhow!("The context is null"))?;
+        let df = ctx.sql(to_str(sql, "statement")?)?;
+        let batches = df.collect(ctx)?;
+        RqResult::try_new(df.schema().into(), &batches)
+    });
+    result.map_or(ptr::null_mut(), |result| Box::into_raw(Box::new(result)))
+}
+
+/// Return the number of non-empty batches of the result.
+///
+/// # Safety
+/// The result must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn rq_result_batch_count(result: *const RqResult) -> usize {
+    record_error(|| Ok(result.as_ref().map_or(0, |result| result.batches.len()))).unwrap_or(0)
+}
+
+/// Export the schema of the result, as the struct type of its batches. The caller owns
+/// the exported schema and releases it. Return 0 on success and -1 on error.
+///
+/// # Safety
+/// The result must be valid, and `out` point to writable memory for an `ArrowSchema`.
+#[no_mangle]
+pub unsafe extern "C" fn rq_result_schema(
+    result: *const RqResult,
+    out: *mut FFI_ArrowSchema,
+) -> c_int {
+    status(record_error(|| {
+        let result = result
+            .as_ref()
+            .ok_or_else(|| anyhow!("The result is null"))?;
+        if out.is_null() {
+            return Err(anyhow!("The output schema is null"));
+        }
+        ptr::write(out, FFI_ArrowSchema::try_from(&result.schema)?);
+        Ok(())
+    }))
+}
+
+/// Export a batch of the result as a struct array with a column per field, sharing the
+/// buffers of its columns. The caller owns the exported array and releases it.
+/// Return 0 on success and -1 on error.
+///
+/// # Safety
+/// The result must be valid, and `out` point to writable memory for an `ArrowArray`.
+#[no_mangle]
+pub unsafe extern "C" fn rq_result_batch(
+    result: *const RqResult,
+    index: usize,
+    out: *mut FFI_ArrowArray,
+) -> c_int {
+    status(record_error(|| {
+        let result = result
+            .as_ref()
+            .ok_or_else(|| anyhow!("The result is null"))?;
+        if out.is_null() {
+            return Err(anyhow!("The output array is null"));
+        }
+        let batch = result.batches.get(index).ok_or_else(|| {
+            anyhow!(
+                "Batch {} is out of range, the result has {} batches",
+                index,
+                result.batches.len()
+            )
+        })?;
+        let array = StructArray::from(batch.clone());
+        ptr::write(out, FFI_ArrowArray::new(array.data()));
+        Ok(())
+    }))
+}
+
+/// Free a result returned by `rq_sql`. The batches and schemas exported from it stay valid.
+///
+/// # Safety
+/// The result must have been returned by `rq_sql` and not freed yet, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn rq_result_free(result: *mut RqResult) {
+    if !result.is_null() {
+        record_error(|| {
+            drop(Box::from_raw(result));
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow::{
+        array::{ArrayRef, Int64Array},
+        datatypes::{DataType, Schema},
+        ffi::ArrowArray,
+    };
+
+    #[test]
+    fn test_sql() {
+        let path = CString::new(format!(
+            "{}/../tests/data/primitive_field.csv",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+        let name = CString::new("t").unwrap();
+        let sql = CString::new("SELECT c1, c3 * 2 AS d FROM t WHERE c1 > 1").unwrap();
+        unsafe {
+            let ctx = rq_context_new(2);
+            assert_eq!(rq_register_csv(ctx, name.as_ptr(), path.as_ptr()), 0);
+            let result = rq_sql(ctx, sql.as_ptr());
+            assert!(!result.is_null());
+
+            let mut schema = FFI_ArrowSchema::empty();
+            assert_eq!(rq_result_schema(result, &mut schema), 0);
+            let schema = Schema::try_from(&schema).unwrap();
+            assert_eq!(schema.field(1).name(), "d");
+            assert_eq!(schema.field(1).data_type(), &DataType::Int64);
+
+            let mut columns = vec![];
+            for i in 0..rq_result_batch_count(result) {
+                let mut array = FFI_ArrowArray::empty();
+                assert_eq!(rq_result_batch(result, i, &mut array), 0);
+                let ffi_schema = FFI_ArrowSchema::try_from(&schema).unwrap();
+                let array = ArrowArray::try_from_raw(&array, &ffi_schema).unwrap();
+                let array = StructArray::from(arrow::array::ArrayData::try_from(array).unwrap());
+                columns.push(array.column(1).clone());
+            }
+            rq_result_free(result);
+
+            let values = columns
+                .iter()
+                .flat_map(|c: &ArrayRef| {
+                    let c = c.as_any().downcast_ref::<Int64Array>().unwrap();
+                    c.iter().collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(values, vec![Some(42), Some(44)]);
+
+            let sql = CString::new("SELECT nope FROM t").unwrap();
+            assert!(rq_sql(ctx, sql.as_ptr()).is_null());
+            let error = CStr::from_ptr(rq_last_error()).to_str().unwrap();
+            assert!(error.starts_with("No column named nope"));
+            rq_context_free(ctx);
+        }
+    }
+
+    #[test]
+    fn test_panic() {
+        // A panic is reported as the last error instead of unwinding into C.
+        let result = record_error(|| -> Result<()> { panic!("boom") });
+        assert!(result.is_none());
+        let error = unsafe { CStr::from_ptr(rq_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "rq panicked: boom");
+        assert_eq!(status(record_error(|| Ok(()))), 0);
+    }
+}
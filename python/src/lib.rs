@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use rq::{
+    data_source::csv_data_source::CsvDataSource,
+    data_types::record_batch::RecordBatch,
+    execution::ExecutionContext,
+    logical_plan::{data_frame::DataFrame, plan::LogicalPlan},
+};
+
+use arrow::{
+    array::Array,
+    datatypes::Schema as ArrowSchema,
+    ffi::{ArrowArray, FFI_ArrowSchema},
+    record_batch::RecordBatch as ArrowRecordBatch,
+};
+use pyo3::{
+    create_exception, exceptions::PyException, ffi::Py_uintptr_t, prelude::*, types::IntoPyDict,
+};
+
+create_exception!(
+    pyrq,
+    RqError,
+    PyException,
+    "An error planning or executing a query."
+);
+
+fn to_py_err(error: anyhow::Error) -> PyErr {
+    RqError::new_err(format!("{:#}", error))
+}
+
+/// Export the schema to pyarrow through the Arrow C Data Interface.
+fn schema_to_pyarrow<'py>(py: Python<'py>, schema: &ArrowSchema) -> PyResult<Bound<'py, PyAny>> {
+    let c_schema = FFI_ArrowSchema::try_from(schema).map_err(|e| to_py_err(e.into()))?;
+    let c_schema_ptr = &c_schema as *const FFI_ArrowSchema;
+    py.import("pyarrow")?
+        .getattr("Schema")?
+        .call_method1("_import_from_c", (c_schema_ptr as Py_uintptr_t,))
+}
+
+/// Export the columns of the batch to pyarrow through the Arrow C Data Interface,
+/// so pyarrow shares their buffers instead of copying them.
+fn batch_to_pyarrow<'py>(
+    py: Python<'py>,
+    batch: &RecordBatch,
+    schema: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let batch = ArrowRecordBatch::try_from(batch).map_err(to_py_err)?;
+    let pyarrow = py.import("pyarrow")?;
+    let arrays = batch
+        .columns()
+        .iter()
+        .map(|column| {
+            let array =
+                ArrowArray::try_from(column.data().clone()).map_err(|e| to_py_err(e.into()))?;
+            let (array_ptr, schema_ptr) = ArrowArray::into_raw(array);
+            pyarrow.getattr("Array")?.call_method1(
+                "_import_from_c",
+                (array_ptr as Py_uintptr_t, schema_ptr as Py_uintptr_t),
+            )
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    pyarrow.getattr("RecordBatch")?.call_method(
+        "from_arrays",
+        (arrays,),
+        Some(&[("schema", schema)].into_py_dict(py)?),
+    )
+}
+
+/// The context queries are planned and executed in, with the tables registered in it.
+#[pyclass(name = "ExecutionContext")]
+struct PyExecutionContext {
+    ctx: Arc<ExecutionContext>,
+}
+
+#[pymethods]
+impl PyExecutionContext {
+    #[new]
+    #[pyo3(signature = (batch_size = 1024))]
+    fn new(batch_size: usize) -> Self {
+        Self {
+            ctx: Arc::new(ExecutionContext::new(batch_size)),
+        }
+    }
+
+    /// Register a CSV file without a header as a table, with the schema inferred from
+    /// its first rows.
+    fn register_csv(&self, name: &str, path: String) -> PyResult<()> {
+        let schema = CsvDataSource::infer_schema(&path).map_err(to_py_err)?;
+        self.ctx.register_csv(name, path, schema);
+        Ok(())
+    }
+
+    fn deregister_table(&self, name: &str) -> bool {
+        self.ctx.deregister_table(name).is_some()
+    }
+
+    fn table_names(&self) -> Vec<String> {
+        self.ctx.table_names()
+    }
+
+    fn table(&self, name: &str) -> PyResult<PyDataFrame> {
+        let df = self.ctx.table(name).map_err(to_py_err)?;
+        Ok(PyDataFrame::new(&self.ctx, df))
+    }
+
+    /// Plan a SQL statement. Queries are executed, and DDL statements and inserts applied,
+    /// when their result is collected.
+    fn sql(&self, sql: &str) -> PyResult<PyDataFrame> {
+        let df = self.ctx.sql(sql).map_err(to_py_err)?;
+        Ok(PyDataFrame::new(&self.ctx, df))
+    }
+}
+
+/// A query planned in a context, executed every time its result is collected.
+#[pyclass(name = "DataFrame")]
+struct PyDataFrame {
+    ctx: Arc<ExecutionContext>,
+    df: DataFrame,
+}
+
+impl PyDataFrame {
+    fn new(ctx: &Arc<ExecutionContext>, df: DataFrame) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            df,
+        }
+    }
+}
+
+#[pymethods]
+impl PyDataFrame {
+    /// Return the names of the columns of the result.
+    fn columns(&self) -> Vec<String> {
+        self.df
+            .schema()
+            .fields
+            .into_iter()
+            .map(|f| f.name)
+            .collect()
+    }
+
+    /// Return the schema of the result as a `pyarrow.Schema`.
+    fn schema<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        schema_to_pyarrow(py, &self.df.schema().into())
+    }
+
+    /// Execute the query and return its result as a list of `pyarrow.RecordBatch`.
+    fn collect<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyAny>>> {
+        let batches = py
+            .allow_threads(|| self.df.collect(&self.ctx))
+            .map_err(to_py_err)?;
+        let schema = self.schema(py)?;
+        batches
+            .iter()
+            .filter(|batch| batch.row_count() > 0)
+            .map(|batch| batch_to_pyarrow(py, batch, &schema))
+            .collect()
+    }
+
+    /// Execute the query and return its result as a `pyarrow.Table`.
+    fn to_arrow<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let batches = self.collect(py)?;
+        let schema = self.schema(py)?;
+        py.import("pyarrow")?.getattr("Table")?.call_method(
+            "from_batches",
+            (batches,),
+            Some(&[("schema", schema)].into_py_dict(py)?),
+        )
+    }
+
+    /// Execute the query and return its result as a `pandas.DataFrame`.
+    fn to_pandas<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.to_arrow(py)?.call_method0("to_pandas")
+    }
+
+    /// Return the number of rows of the result.
+    fn count(&self, py: Python<'_>) -> PyResult<usize> {
+        py.allow_threads(|| self.df.count(&self.ctx))
+            .map_err(to_py_err)
+    }
+
+    /// Return the optimized logical plan and the physical plan of the query.
+    #[pyo3(signature = (analyze = false))]
+    fn explain(&self, py: Python<'_>, analyze: bool) -> PyResult<String> {
+        py.allow_threads(|| self.ctx.explain(&self.df, analyze))
+            .map_err(to_py_err)
+    }
+
+    fn __repr__(&self) -> String {
+        self.df.logical_plan().pretty(0)
+    }
+}
+
+/// Python bindings of the rq query engine.
+#[pymodule]
+fn pyrq(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyExecutionContext>()?;
+    m.add_class::<PyDataFrame>()?;
+    m.add("RqError", m.py().get_type::<RqError>())?;
+    Ok(())
+}
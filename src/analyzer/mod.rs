@@ -0,0 +1,435 @@
+use crate::{
+    data_types::column_array::DataType,
+    logical_plan::{
+        aggregate::Aggregate,
+        distinct_on::DistinctOn,
+        expr::{Expr, LogicalExpr, Placeholder, ReturnType, ScalarValue, WindowFunction},
+        expr_fn::{cast, col, lit, typed_null, typed_placeholder},
+        fill::Fill,
+        gap_fill::GapFill,
+        join::Join,
+        limit::Limit,
+        plan::{LogicalPlan, Plan},
+        projection::Projection,
+        selection::Selection,
+        sessionize::Sessionize,
+        sort::{Sort, SortExpr},
+        window::Window,
+    },
+};
+
+use anyhow::{anyhow, bail, Result};
+
+/// Analyzer for logical plans, run before the optimizer.
+/// It checks that the plan is valid, so errors are reported at plan time, and rewrites it so
+/// the optimizer can rely on it: columns are referenced by name, operands are cast to the type
+/// their expression computes over, and NOT is expressed as a comparison.
+pub struct Analyzer;
+
+impl Analyzer {
+    pub fn analyze(plan: &Plan) -> Result<Plan> {
+        match plan {
+            // The inputs of commands are analyzed when they are executed.
+            Plan::Scan(_) | Plan::Command(_) => Ok(plan.clone()),
+            Plan::Projection(p) => {
+                let input = Analyzer::analyze(&p.input)?;
+                let exprs = analyze_exprs(&p.exprs, &input, Placement::Scalar)?;
+                Ok(Plan::Projection(Projection::new(input, exprs)))
+            }
+            Plan::Selection(s) => {
+                let input = Analyzer::analyze(&s.input)?;
+                let expr = analyze_expr(&s.expr, &input, Placement::Scalar)?;
+                let data_type = expr.to_field(&input)?.data_type;
+                if data_type != DataType::Boolean {
+                    bail!("Filter predicates must be Boolean, got {}", data_type);
+                }
+                Ok(Plan::Selection(Selection::new(input, expr)))
+            }
+            Plan::Aggregate(a) => {
+                let input = Analyzer::analyze(&a.input)?;
+                let group_exprs = analyze_exprs(&a.group_exprs, &input, Placement::Scalar)?;
+                let aggregate_exprs =
+                    analyze_exprs(&a.aggregate_exprs, &input, Placement::Aggregate)?;
+                Ok(Plan::Aggregate(Aggregate::new(
+                    input,
+                    group_exprs,
+                    aggregate_exprs,
+                )))
+            }
+            Plan::Join(j) => analyze_join(j),
+            Plan::Sessionize(s) => {
+                let input = Analyzer::analyze(&s.input)?;
+                let key = analyze_expr(&s.key, &input, Placement::Scalar)?;
+                let timestamp = analyze_expr(&s.timestamp, &input, Placement::Scalar)?;
+                Ok(Plan::Sessionize(Sessionize::new(
+                    input, key, timestamp, s.gap,
+                )))
+            }
+            Plan::GapFill(g) => {
+                let input = Analyzer::analyze(&g.input)?;
+                if input.schema().fields.is_empty() {
+                    bail!("Gap filling needs a column of time buckets");
+                }
+                Ok(Plan::GapFill(GapFill::new(input, g.every)))
+            }
+            Plan::DistinctOn(d) => {
+                let input = Analyzer::analyze(&d.input)?;
+                let keys = analyze_exprs(&d.keys, &input, Placement::Scalar)?;
+                let order_by = d
+                    .order_by
+                    .iter()
+                    .map(|e| {
+                        let expr = analyze_expr(&e.expr, &input, Placement::Scalar)?;
+                        Ok(SortExpr::new(expr, e.asc, e.nulls_first))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Plan::DistinctOn(DistinctOn::new(input, keys, order_by)))
+            }
+            Plan::Fill(f) => {
+                let input = Analyzer::analyze(&f.input)?;
+                let schema = input.schema();
+                if let Some(name) = f
+                    .columns
+                    .iter()
+                    .find(|name| !schema.fields.iter().any(|field| &&field.name == name))
+                {
+                    bail!("No column named {}", name);
+                }
+                let order_by = analyze_exprs(&f.order_by, &input, Placement::Scalar)?;
+                Ok(Plan::Fill(Fill::new(
+                    input,
+                    f.columns.clone(),
+                    order_by,
+                    f.direction,
+                )))
+            }
+            Plan::Window(w) => {
+                let input = Analyzer::analyze(&w.input)?;
+                let window_exprs = analyze_exprs(&w.window_exprs, &input, Placement::Window)?;
+                Ok(Plan::Window(Window::new(input, window_exprs)))
+            }
+            Plan::Sort(s) => {
+                let input = Analyzer::analyze(&s.input)?;
+                let order_by = s
+                    .order_by
+                    .iter()
+                    .map(|e| {
+                        let expr = analyze_expr(&e.expr, &input, Placement::Scalar)?;
+                        Ok(SortExpr::new(expr, e.asc, e.nulls_first))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Plan::Sort(Sort::new(input, order_by)))
+            }
+            Plan::Limit(l) => {
+                let input = Analyzer::analyze(&l.input)?;
+                Ok(Plan::Limit(Limit::new(input, l.skip, l.fetch)))
+            }
+        }
+    }
+}
+
+/// The plan computing an expression, which decides the functions it may call.
+#[derive(Clone, Copy)]
+enum Placement {
+    Scalar,
+    Aggregate,
+    Window,
+}
+
+fn analyze_exprs(exprs: &[Expr], input: &Plan, placement: Placement) -> Result<Vec<Expr>> {
+    exprs
+        .iter()
+        .map(|e| analyze_expr(e, input, placement))
+        .collect()
+}
+
+/// Check an expression computed over `input` and rewrite it, keeping the name of its output.
+fn analyze_expr(expr: &Expr, input: &Plan, placement: Placement) -> Result<Expr> {
+    let resolved = resolve_columns(expr, input)?;
+    check_placement(&resolved, placement)?;
+    let name = resolved.to_field(input)?.name;
+    let coerced = coerce(&resolved, input)?;
+    if coerced.to_field(input)?.name == name {
+        Ok(coerced)
+    } else {
+        Ok(coerced.alias(name))
+    }
+}
+
+/// Analyze the inputs of a join, whose column names must be distinct, and its keys.
+fn analyze_join(join: &Join) -> Result<Plan> {
+    let left = Analyzer::analyze(&join.left)?;
+    let right = Analyzer::analyze(&join.right)?;
+    let right_schema = right.schema();
+    if let Some(field) = left.schema().fields.iter().find(|left_field| {
+        right_schema
+            .fields
+            .iter()
+            .any(|f| f.name == left_field.name)
+    }) {
+        bail!(
+            "Both inputs of the join have a column named {}, alias them to tell them apart",
+            field.name
+        );
+    }
+    let on = join
+        .on
+        .iter()
+        .map(|(l, r)| analyze_join_key(l, r, &left, &right))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Plan::Join(Join::new(left, right, on)))
+}
+
+/// Check the keys a join compares, computed over its left and right inputs, and cast
+/// them to the type they are compared as.
+fn analyze_join_key(
+    left: &Expr,
+    right: &Expr,
+    left_input: &Plan,
+    right_input: &Plan,
+) -> Result<(Expr, Expr)> {
+    let left = analyze_expr(left, left_input, Placement::Scalar)?;
+    let right = analyze_expr(right, right_input, Placement::Scalar)?;
+    let left_type = left.to_field(left_input)?.data_type;
+    let right_type = right.to_field(right_input)?.data_type;
+    let common_type = match left_type.is_nested() || right_type.is_nested() {
+        true => None,
+        false => left_type.common_type(&right_type),
+    }
+    .ok_or_else(|| {
+        anyhow!(
+            "Cannot join {} and {}, of types {} and {}",
+            left,
+            right,
+            left_type,
+            right_type
+        )
+    })?;
+    Ok((
+        cast_to(left, &common_type, left_input)?,
+        cast_to(right, &common_type, right_input)?,
+    ))
+}
+
+/// Check that the referenced columns exist, and replace column indexes with the
+/// names of their columns when those are unique.
+fn resolve_columns(expr: &Expr, input: &Plan) -> Result<Expr> {
+    match expr {
+        Expr::Column(c) => match input.schema().fields.iter().any(|f| f.name == c.name) {
+            true => Ok(expr.clone()),
+            false => bail!("No column named {}", c.name),
+        },
+        Expr::ColumnIndex(c) => {
+            let fields = input.schema().fields;
+            let field = fields.get(c.index).ok_or_else(|| {
+                anyhow!(
+                    "No column at index {}, the input has {} columns",
+                    c.index,
+                    fields.len()
+                )
+            })?;
+            match fields.iter().filter(|f| f.name == field.name).count() {
+                1 => Ok(col(&field.name)),
+                _ => Ok(expr.clone()),
+            }
+        }
+        _ => expr.map_children(|e| resolve_columns(e, input)),
+    }
+}
+
+/// Check that aggregate and window functions are only called by the plans computing them,
+/// at the top of their expressions.
+fn check_placement(expr: &Expr, placement: Placement) -> Result<()> {
+    let check_scalars = |exprs: Vec<&Expr>| {
+        exprs
+            .into_iter()
+            .try_for_each(|e| check_placement(e, Placement::Scalar))
+    };
+    match (expr, placement) {
+        (Expr::Alias(a), _) => check_placement(&a.expr, placement),
+        (Expr::AggregateFunction(_), Placement::Aggregate)
+        | (Expr::WindowFunction(_), Placement::Window) => check_scalars(expr.children()),
+        (_, Placement::Aggregate) => bail!("{} is not an aggregate expression", expr),
+        (_, Placement::Window) => bail!("{} is not a window expression", expr),
+        (Expr::AggregateFunction(_), Placement::Scalar) => bail!(
+            "Aggregate functions can only be computed by an aggregate plan, got {}",
+            expr
+        ),
+        (Expr::WindowFunction(_), Placement::Scalar) => {
+            bail!("Window functions can only be computed by a window plan")
+        }
+        (_, Placement::Scalar) => check_scalars(expr.children()),
+    }
+}
+
+/// Cast the operands of the expression and its sub-expressions to the types they are
+/// computed over, and rewrite NOT as a comparison with false, which has the same nulls.
+fn coerce(expr: &Expr, input: &Plan) -> Result<Expr> {
+    let expr = expr.map_children(|e| coerce(e, input))?;
+    Ok(match expr {
+        Expr::Not(n) => n.expr.as_ref().clone().eq(lit(false)),
+        Expr::BinaryExpr(mut b) => {
+            if b.op.is_comparison() {
+                narrow_literal(&mut b.left, &b.right, input)?;
+                narrow_literal(&mut b.right, &b.left, input)?;
+            }
+            let operand_type = b.operand_type(input)?;
+            *b.left = cast_to(*b.left, &operand_type, input)?;
+            *b.right = cast_to(*b.right, &operand_type, input)?;
+            Expr::BinaryExpr(b)
+        }
+        Expr::GetIndex(mut g) => {
+            *g.index = cast_to(*g.index, &DataType::Int64, input)?;
+            Expr::GetIndex(g)
+        }
+        Expr::ScalarFunction(mut f) if f.return_type == ReturnType::CommonType => {
+            let common_type = f.to_field(input)?.data_type;
+            f.args = f
+                .args
+                .into_iter()
+                .map(|arg| cast_to(arg, &common_type, input))
+                .collect::<Result<_>>()?;
+            Expr::ScalarFunction(f)
+        }
+        // The default value of LEAD and LAG takes the type of the value.
+        Expr::WindowFunction(mut w)
+            if matches!(w.fun, WindowFunction::Lead | WindowFunction::Lag) =>
+        {
+            let data_type = w.args[0].to_field(input)?.data_type;
+            let default = w.args.pop().unwrap();
+            w.args.push(cast_to(default, &data_type, input)?);
+            Expr::WindowFunction(w)
+        }
+        expr => expr,
+    })
+}
+
+/// Narrow an Int64 literal compared with an Int32 expression to Int32 when its value
+/// fits, so the expression is compared as it is rather than cast, which would prevent
+/// the pruning with statistics.
+fn narrow_literal(literal: &mut Expr, other: &Expr, input: &Plan) -> Result<()> {
+    if let Expr::Literal(ScalarValue::Int64(value)) = literal {
+        if let Ok(value) = i32::try_from(*value) {
+            if !other.is_untyped() && other.to_field(input)?.data_type == DataType::Int32 {
+                *literal = lit(value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Cast the expression to the given type, unless it already has that type.
+/// Untyped nulls and placeholders take the type.
+fn cast_to(expr: Expr, data_type: &DataType, input: &Plan) -> Result<Expr> {
+    if expr.is_untyped_null() {
+        Ok(typed_null(data_type.clone()))
+    } else if let Expr::Placeholder(Placeholder {
+        id,
+        data_type: None,
+    }) = &expr
+    {
+        Ok(typed_placeholder(id, data_type.clone()))
+    } else if &expr.to_field(input)?.data_type == data_type {
+        Ok(expr)
+    } else {
+        Ok(cast(expr, data_type.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logical_plan::{
+            data_frame::DataFrame,
+            expr::ColumnIndex,
+            expr_fn::{coalesce, max, null, row_number},
+            scan::Scan,
+        },
+        test_util::get_primitive_field_data_source,
+    };
+
+    fn csv() -> DataFrame {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        DataFrame::new(Plan::Scan(Scan::new(path, csv_data_source, vec![])))
+    }
+
+    fn analyze(df: &DataFrame) -> Result<String> {
+        let plan = Analyzer::analyze(&df.logical_plan())?;
+        assert_eq!(plan.schema(), df.schema());
+        Ok(plan.to_string())
+    }
+
+    #[test]
+    fn test_analyze_rewrites() {
+        let df = csv().project(vec![
+            Expr::ColumnIndex(ColumnIndex { index: 1 }),
+            col("c1").gt(lit(1_i64)),
+            coalesce(vec![col("c1"), null(), col("c3")]),
+            !col("c1").eq(lit(1)),
+        ]);
+        assert_eq!(
+            analyze(&df).unwrap(),
+            "Projection: #c2,#c1 > 1,\
+             coalesce(CAST(#c1 AS Int64), NULL, #c3),#c1 = 1 = false as not"
+        );
+    }
+
+    #[test]
+    fn test_analyze_narrows_literals() {
+        // Only literals compared with the column, and whose value fits, are narrowed.
+        let df = csv().project(vec![
+            lit(2_i64).lt_eq(col("c1")),
+            col("c1").lt(lit(3_000_000_000_i64)),
+            (col("c1") + lit(1_i64)).gt(lit(1_i64)),
+        ]);
+        assert_eq!(
+            analyze(&df).unwrap(),
+            "Projection: 2 <= #c1,CAST(#c1 AS Int64) < 3000000000,\
+             CAST(#c1 AS Int64) + 1 > 1"
+        );
+    }
+
+    #[test]
+    fn test_analyze_errors() {
+        let error = |df: DataFrame| analyze(&df).unwrap_err().to_string();
+        assert_eq!(error(csv().project(vec![col("c9")])), "No column named c9");
+        assert_eq!(
+            error(csv().project(vec![Expr::ColumnIndex(ColumnIndex { index: 6 })])),
+            "No column at index 6, the input has 6 columns"
+        );
+        assert_eq!(
+            error(csv().project(vec![max(col("c1"))])),
+            "Aggregate functions can only be computed by an aggregate plan, got MAX(#c1)"
+        );
+        assert_eq!(
+            error(csv().aggregate(vec![], vec![max(col("c1")) + lit(1)])),
+            "MAX(#c1) + 1 is not an aggregate expression"
+        );
+        assert_eq!(
+            error(csv().aggregate(vec![], vec![max(max(col("c1")))])),
+            "Aggregate functions can only be computed by an aggregate plan, got MAX(#c1)"
+        );
+        assert_eq!(
+            error(csv().filter(row_number(vec![], vec![]).eq(lit(1_i64)))),
+            "Window functions can only be computed by a window plan"
+        );
+        assert_eq!(
+            error(csv().filter(col("c1"))),
+            "Filter predicates must be Boolean, got Int32"
+        );
+        assert_eq!(
+            error(csv().project(vec![col("c1") + lit("a".to_string())])),
+            "Cannot apply add to Int32 and Utf8"
+        );
+        assert_eq!(
+            error(csv().join(&csv(), vec![])),
+            "Both inputs of the join have a column named c1, alias them to tell them apart"
+        );
+        let right = csv().project(vec![col("c1").alias("k".to_string())]);
+        assert_eq!(
+            error(csv().join(&right, vec![(col("c1"), lit("a".to_string()))])),
+            "Cannot join #c1 and a, of types Int32 and Utf8"
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::{env, net::TcpListener, sync::Arc};
+
+use rq::{
+    data_source::csv_data_source::CsvDataSource, execution::ExecutionContext,
+    server::postgres::PgServer,
+};
+
+use anyhow::{anyhow, Result};
+
+#[cfg(not(feature = "flight-sql"))]
+const USAGE: &str = "Usage: rq-server [--listen ADDRESS] [TABLE=FILE.csv ...]";
+#[cfg(feature = "flight-sql")]
+const USAGE: &str =
+    "Usage: rq-server [--listen ADDRESS] [--flight-sql ADDRESS] [TABLE=FILE.csv ...]";
+
+/// Serve CSV files without a header as tables to PostgreSQL clients, e.g.
+/// `rq-server events=events.csv` and then `psql -h 127.0.0.1 -p 5432`.
+/// With the flight-sql feature, `--flight-sql 127.0.0.1:50051` also serves them to
+/// Arrow Flight SQL clients.
+fn main() -> Result<()> {
+    let mut address = "127.0.0.1:5432".to_string();
+    #[cfg(feature = "flight-sql")]
+    let mut flight_sql_address = None;
+    let ctx = ExecutionContext::new(1024);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--listen" {
+            address = args.next().ok_or_else(|| anyhow!(USAGE))?;
+            continue;
+        }
+        #[cfg(feature = "flight-sql")]
+        if arg == "--flight-sql" {
+            flight_sql_address = Some(args.next().ok_or_else(|| anyhow!(USAGE))?);
+            continue;
+        }
+        let (name, path) = arg.split_once('=').ok_or_else(|| anyhow!(USAGE))?;
+        ctx.register_csv(name, path.to_string(), CsvDataSource::infer_schema(path)?);
+    }
+    let ctx = Arc::new(ctx);
+
+    #[cfg(feature = "flight-sql")]
+    if let Some(flight_sql_address) = flight_sql_address {
+        serve_flight_sql(ctx.clone(), &flight_sql_address)?;
+    }
+
+    let listener = TcpListener::bind(&address)?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+    PgServer::new(ctx).serve(listener)
+}
+
+/// Serve Flight SQL on a runtime of its own, alongside the PostgreSQL server.
+#[cfg(feature = "flight-sql")]
+fn serve_flight_sql(ctx: Arc<ExecutionContext>, address: &str) -> Result<()> {
+    use rq::server::flight_sql::FlightSqlServer;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let listener = runtime.block_on(tokio::net::TcpListener::bind(address))?;
+    eprintln!("Serving Flight SQL on {}", listener.local_addr()?);
+    std::thread::spawn(move || {
+        if let Err(e) = runtime.block_on(FlightSqlServer::new(ctx).serve(listener)) {
+            eprintln!("Flight SQL server failed: {:#}", e);
+        }
+    });
+    Ok(())
+}
@@ -1,28 +1,62 @@
-use std::{fs::File, rc::Rc};
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::{
+    fmt::Display,
+    io::{Cursor, Read},
+    sync::Arc,
+};
 
 use super::{reader_parser::Parser, DataSource};
-use crate::data_types::{
-    arrow_field_array::ArrowFieldArray, column_array::ArrayRef, record_batch::RecordBatch,
-    schema::Schema,
+use crate::{
+    data_types::{
+        arrow_field_array::ArrowFieldArray,
+        binary::BinaryEncoding,
+        column_array::{ArrayRef, DataType},
+        extension::ExtensionRef,
+        record_batch::RecordBatch,
+        schema::{Field as SchemaField, Schema},
+    },
+    physical_plan::expr::evaluate_from_nullable_values,
 };
 
-use anyhow::{Ok, Result};
+use anyhow::{anyhow, bail, Ok, Result};
 use arrow::{
-    array::{BooleanArray, PrimitiveArray, StringArray},
+    array::{BinaryArray, BooleanArray, PrimitiveArray, StringArray},
     datatypes::{
         ArrowPrimitiveType, DataType as ArrowDataType, Field, Float32Type, Float64Type, Int32Type,
         Int64Type, Schema as ArrowSchema,
     },
 };
 use csv::{Reader, ReaderBuilder, StringRecord};
+use serde::{Deserialize, Serialize};
 
-// A data source that reads from a CSV file.
-#[derive(Clone)]
+// A data source that reads from a CSV file or from CSV data in memory.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CsvDataSource {
-    file_path: String,
+    input: CsvInput,
     schema: Schema,
     // The total number of rows in the CSV file.
     batch_size: usize,
+    binary_encoding: BinaryEncoding,
+}
+
+// Where the CSV data is read from, every time the data source is scanned.
+#[derive(Clone, Serialize, Deserialize)]
+enum CsvInput {
+    #[cfg(feature = "fs")]
+    File(String),
+    // Bytes held in memory, e.g. fetched by a browser, which has no filesystem.
+    Bytes(Arc<[u8]>),
+}
+
+impl CsvInput {
+    fn open(&self) -> Result<Box<dyn Read + Send>> {
+        Ok(match self {
+            #[cfg(feature = "fs")]
+            CsvInput::File(file_path) => Box::new(File::open(file_path)?),
+            CsvInput::Bytes(bytes) => Box::new(Cursor::new(bytes.clone())),
+        })
+    }
 }
 
 impl DataSource for CsvDataSource {
@@ -30,12 +64,13 @@ impl DataSource for CsvDataSource {
         &self.schema
     }
 
-    fn scan(&self, projections: Vec<&str>) -> Result<Box<dyn Iterator<Item = RecordBatch>>> {
-        let file = File::open(&self.file_path)?;
-
+    fn scan(
+        &self,
+        projections: Vec<&str>,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send>> {
         let mut csv_reader_builder = ReaderBuilder::new();
         csv_reader_builder.has_headers(false);
-        let mut csv_reader = csv_reader_builder.from_reader(file);
+        let mut csv_reader = csv_reader_builder.from_reader(self.input.open()?);
         // Set headers for the CSV reader.
         // This will append the name into the first record of reader.
         // We have to set all the fields of the schema to be able to parse the CSV file.
@@ -47,55 +82,156 @@ impl DataSource for CsvDataSource {
         } else {
             self.schema.select(projections)
         };
-        let csv_data_source_reader = CsvDataSourceReader::new(csv_reader, schema, self.batch_size);
+        let csv_data_source_reader =
+            CsvDataSourceReader::new(csv_reader, schema, self.batch_size, self.binary_encoding);
 
         Ok(Box::new(csv_data_source_reader))
     }
 }
 
 impl CsvDataSource {
+    #[cfg(feature = "fs")]
     pub fn new(file_name: String, schema: Schema, batch_size: usize) -> Self {
+        Self::with_input(CsvInput::File(file_name), schema, batch_size)
+    }
+
+    /// Create a data source that reads CSV data without a header from the bytes.
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>, schema: Schema, batch_size: usize) -> Self {
+        Self::with_input(CsvInput::Bytes(bytes.into()), schema, batch_size)
+    }
+
+    fn with_input(input: CsvInput, schema: Schema, batch_size: usize) -> Self {
         Self {
-            file_path: file_name,
+            input,
             schema,
             batch_size,
+            binary_encoding: BinaryEncoding::default(),
+        }
+    }
+
+    /// Return the path of the CSV file, unless the data is read from bytes.
+    pub fn file_path(&self) -> Option<&str> {
+        match &self.input {
+            #[cfg(feature = "fs")]
+            CsvInput::File(file_path) => Some(file_path),
+            CsvInput::Bytes(_) => None,
+        }
+    }
+
+    /// Set the encoding of the values of Binary columns, base64 by default.
+    pub fn with_binary_encoding(mut self, binary_encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = binary_encoding;
+        self
+    }
+
+    /// Infer the schema of a CSV file without a header from its first rows.
+    /// The columns are named `c1`, `c2`, ... and get the narrowest of Boolean,
+    /// Int64, Float64 and Utf8 that all their non-empty values parse as.
+    #[cfg(feature = "fs")]
+    pub fn infer_schema(file_path: &str) -> Result<Schema> {
+        match infer_schema_from_reader(File::open(file_path)?)? {
+            Some(schema) => Ok(schema),
+            None => bail!("Cannot infer the schema of the empty file {}", file_path),
+        }
+    }
+
+    /// Infer the schema of CSV data without a header from its first rows, as
+    /// `infer_schema` does for a file.
+    pub fn infer_schema_from_bytes(bytes: &[u8]) -> Result<Schema> {
+        match infer_schema_from_reader(bytes)? {
+            Some(schema) => Ok(schema),
+            None => bail!("Cannot infer the schema of empty CSV data"),
         }
     }
 }
 
+// Infer the schema from the first rows read, or return None if there are none.
+fn infer_schema_from_reader(reader: impl Read) -> Result<Option<Schema>> {
+    let mut csv_reader = ReaderBuilder::new().has_headers(false).from_reader(reader);
+    let mut columns: Vec<Option<DataType>> = vec![];
+    for record in csv_reader.records().take(INFER_SCHEMA_ROWS) {
+        let record = record?;
+        if columns.len() < record.len() {
+            columns.resize(record.len(), None);
+        }
+        for (column, value) in columns.iter_mut().zip(record.iter()) {
+            if !value.is_empty() {
+                *column = Some(widen(column.take(), value));
+            }
+        }
+    }
+    if columns.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Schema::new(
+        columns
+            .into_iter()
+            .enumerate()
+            .map(|(i, data_type)| {
+                SchemaField::new(format!("c{}", i + 1), data_type.unwrap_or(DataType::Utf8))
+            })
+            .collect(),
+    )))
+}
+
+// The number of rows read to infer the schema of a CSV file.
+const INFER_SCHEMA_ROWS: usize = 1000;
+
+// Return the narrowest type that can hold the values of the given type and the value.
+fn widen(data_type: Option<DataType>, value: &str) -> DataType {
+    let is_bool = parse_bool(value).is_some();
+    let is_int = value.parse::<i64>().is_ok();
+    let is_float = value.parse::<f64>().is_ok();
+    match data_type {
+        None | Some(DataType::Boolean) if is_bool => DataType::Boolean,
+        None | Some(DataType::Int64) if is_int => DataType::Int64,
+        None | Some(DataType::Int64) | Some(DataType::Float64) if is_float => DataType::Float64,
+        _ => DataType::Utf8,
+    }
+}
+
 // A reader for the CSV data source with the specified schema.
 struct CsvDataSourceReader {
-    parser: Reader<File>,
+    parser: Reader<Box<dyn Read + Send>>,
     schema: Schema,
     batch_size: usize,
+    binary_encoding: BinaryEncoding,
 }
 
 impl Iterator for CsvDataSourceReader {
-    type Item = RecordBatch;
+    type Item = Result<RecordBatch>;
 
+    // A malformed row fails the batch it is in with the error that locates it.
     fn next(&mut self) -> Option<Self::Item> {
-        self.next_batch()
+        self.next_batch().transpose()
     }
 }
 
 impl CsvDataSourceReader {
-    fn new(parser: Reader<File>, schema: Schema, batch_size: usize) -> CsvDataSourceReader {
+    fn new(
+        parser: Reader<Box<dyn Read + Send>>,
+        schema: Schema,
+        batch_size: usize,
+        binary_encoding: BinaryEncoding,
+    ) -> CsvDataSourceReader {
         let mut reader = CsvDataSourceReader {
             parser,
             schema,
             batch_size,
+            binary_encoding,
         };
         // Skip the header of the CSV file.
         let _ = reader.parser.records().next();
         reader
     }
 
-    fn next_batch(&mut self) -> Option<RecordBatch> {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
         let mut records = Vec::with_capacity(self.batch_size);
         loop {
             let line = self.parser.records().next();
             if let Some(line) = line {
-                records.push(line.unwrap());
+                records.push(line?);
             } else {
                 break;
             }
@@ -106,15 +242,15 @@ impl CsvDataSourceReader {
         }
 
         if records.is_empty() {
-            return None;
+            return Ok(None);
         }
 
-        Some(self.create_batch(records))
+        self.create_batch(records).map(Some)
     }
 
     // Build a record batch from the given records.
     // String -> ArrowFieldArray -> ArrayRef -> RecordBatch.
-    fn create_batch(&mut self, rows: Vec<StringRecord>) -> RecordBatch {
+    fn create_batch(&mut self, rows: Vec<StringRecord>) -> Result<RecordBatch> {
         let schema: ArrowSchema = self.schema.clone().into();
         let filed_with_col_index: Vec<(usize, &Field)> = schema
             .fields()
@@ -133,21 +269,43 @@ impl CsvDataSourceReader {
 
         let arrays = filed_with_col_index
             .iter()
-            .map(|(col_index, field)| match field.data_type() {
-                ArrowDataType::Boolean => build_boolean_array(&rows, *col_index),
-                ArrowDataType::Int32 => build_primitive_array::<Int32Type>(&rows, *col_index),
-                ArrowDataType::Int64 => build_primitive_array::<Int64Type>(&rows, *col_index),
-                ArrowDataType::Float32 => build_primitive_array::<Float32Type>(&rows, *col_index),
-                ArrowDataType::Float64 => build_primitive_array::<Float64Type>(&rows, *col_index),
-                ArrowDataType::Utf8 => build_string_array(&rows, *col_index),
-                _ => unreachable!(),
-            })
-            .collect();
+            .zip(self.schema.fields.iter())
+            .map(
+                |((col_index, field), schema_field)| match &schema_field.extension {
+                    Some(extension) => {
+                        build_extension_array(&rows, *col_index, field.name(), extension)
+                    }
+                    None => {
+                        let (rows, name) = (&rows, field.name());
+                        match field.data_type() {
+                            ArrowDataType::Boolean => build_boolean_array(rows, *col_index, name),
+                            ArrowDataType::Int32 => {
+                                build_primitive_array::<Int32Type>(rows, *col_index, name)
+                            }
+                            ArrowDataType::Int64 => {
+                                build_primitive_array::<Int64Type>(rows, *col_index, name)
+                            }
+                            ArrowDataType::Float32 => {
+                                build_primitive_array::<Float32Type>(rows, *col_index, name)
+                            }
+                            ArrowDataType::Float64 => {
+                                build_primitive_array::<Float64Type>(rows, *col_index, name)
+                            }
+                            ArrowDataType::Utf8 => Ok(build_string_array(rows, *col_index)),
+                            ArrowDataType::Binary => {
+                                build_binary_array(rows, *col_index, name, self.binary_encoding)
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
 
-        RecordBatch {
+        Ok(RecordBatch {
             schema: self.schema.clone(),
             fields: arrays,
-        }
+        })
     }
 }
 
@@ -161,53 +319,55 @@ fn parse_bool(string: &str) -> Option<bool> {
     }
 }
 
-fn build_boolean_array(rows: &[StringRecord], col_index: usize) -> ArrayRef {
+/// Return the error of a value that does not parse, located by its line and column.
+fn value_error(row: &StringRecord, column: &str, error: impl Display) -> anyhow::Error {
+    let line = row.position().map_or(0, |p| p.line());
+    anyhow!(
+        "Invalid value at line {}, column {}: {}",
+        line,
+        column,
+        error
+    )
+}
+
+fn build_boolean_array(rows: &[StringRecord], col_index: usize, column: &str) -> Result<ArrayRef> {
     let array = Box::new(
         rows.iter()
             .map(|row| match row.get(col_index) {
-                Some(s) => {
-                    if s.is_empty() {
-                        return None;
-                    }
-
-                    let parsed = parse_bool(s);
-                    match parsed {
-                        Some(e) => Some(e),
-                        None => panic!("Failed to parse bool: {}", s),
-                    }
-                }
-                None => None,
+                Some(s) if !s.is_empty() => match parse_bool(s) {
+                    Some(e) => Ok(Some(e)),
+                    None => Err(value_error(row, column, format!("{} is not a bool", s))),
+                },
+                _ => Ok(None),
             })
-            .collect::<BooleanArray>(),
+            .collect::<Result<BooleanArray>>()?,
     );
 
-    Rc::new(ArrowFieldArray::new(array)) as ArrayRef
+    Ok(Arc::new(ArrowFieldArray::new(array)) as ArrayRef)
 }
 
 fn build_primitive_array<T: ArrowPrimitiveType + Parser>(
     rows: &[StringRecord],
     col_index: usize,
-) -> ArrayRef {
+    column: &str,
+) -> Result<ArrayRef> {
     let array = Box::new(
         rows.iter()
             .map(|row| match row.get(col_index) {
-                Some(s) => {
-                    if s.is_empty() {
-                        return None;
-                    }
-
-                    let parsed = T::parse(s);
-                    match parsed {
-                        Some(e) => Some(e),
-                        None => panic!("Failed to parse {}", s),
-                    }
-                }
-                None => None,
+                Some(s) if !s.is_empty() => match T::parse(s) {
+                    Some(e) => Ok(Some(e)),
+                    None => Err(value_error(
+                        row,
+                        column,
+                        format!("{} is not a {}", s, T::DATA_TYPE),
+                    )),
+                },
+                _ => Ok(None),
             })
-            .collect::<PrimitiveArray<T>>(),
+            .collect::<Result<PrimitiveArray<T>>>()?,
     );
 
-    Rc::new(ArrowFieldArray::new(array)) as ArrayRef
+    Ok(Arc::new(ArrowFieldArray::new(array)) as ArrayRef)
 }
 
 fn build_string_array(rows: &[StringRecord], col_index: usize) -> ArrayRef {
@@ -217,7 +377,53 @@ fn build_string_array(rows: &[StringRecord], col_index: usize) -> ArrayRef {
             .collect::<StringArray>(),
     );
 
-    Rc::new(ArrowFieldArray::new(array)) as ArrayRef
+    Arc::new(ArrowFieldArray::new(array)) as ArrayRef
+}
+
+fn build_binary_array(
+    rows: &[StringRecord],
+    col_index: usize,
+    column: &str,
+    binary_encoding: BinaryEncoding,
+) -> Result<ArrayRef> {
+    let values = rows
+        .iter()
+        .map(|row| match row.get(col_index) {
+            Some(s) if !s.is_empty() => binary_encoding
+                .decode(s)
+                .map(Some)
+                .map_err(|e| value_error(row, column, e)),
+            _ => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let array = Box::new(BinaryArray::from(
+        values.iter().map(|v| v.as_deref()).collect::<Vec<_>>(),
+    ));
+
+    Ok(Arc::new(ArrowFieldArray::new(array)) as ArrayRef)
+}
+
+fn build_extension_array(
+    rows: &[StringRecord],
+    col_index: usize,
+    column: &str,
+    extension: &ExtensionRef,
+) -> Result<ArrayRef> {
+    let values = rows
+        .iter()
+        .map(|row| match row.get(col_index) {
+            Some(s) if !s.is_empty() => extension.parse(s).map(Some).map_err(|e| {
+                value_error(
+                    row,
+                    column,
+                    format!("{} is not a {}: {}", s, extension.name(), e),
+                )
+            }),
+            _ => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    evaluate_from_nullable_values(&values, &extension.storage_type())
 }
 
 #[cfg(test)]
@@ -225,9 +431,11 @@ mod tests {
     use std::{any::Any, fmt::Debug};
 
     use super::*;
+    #[cfg(feature = "fs")]
+    use crate::test_util::rq_test_data;
     use crate::{
-        data_types::{column_array::DataType, schema::Field},
-        test_util::{get_primitive_field_data_source, rq_test_data},
+        data_types::{column_array::DataType, extension::tests::Currency, schema::Field},
+        test_util::get_primitive_field_data_source,
     };
 
     fn assert_type_and_values<T: Any + PartialEq + Debug>(
@@ -251,13 +459,14 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "fs")]
     #[test]
     fn test_boolean_field_csv_data_source() {
         let data_path = rq_test_data("boolean_field.csv");
         let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Boolean)]);
         let csv_data_source = CsvDataSource::new(data_path, schema, 3);
         let mut reader = csv_data_source.scan(vec!["c1"]).unwrap();
-        let batch = reader.next().unwrap();
+        let batch = reader.next().unwrap().unwrap();
 
         assert_eq!(batch.row_count(), 3);
         assert_eq!(batch.column_count(), 1);
@@ -288,7 +497,7 @@ mod tests {
         let mut batchs = csv_data_source
             .scan(vec!["c1", "c2", "c3", "c4", "c5", "c6"])
             .unwrap();
-        let batch = batchs.next().unwrap();
+        let batch = batchs.next().unwrap().unwrap();
 
         assert_eq!(batch.row_count(), 3);
         assert_eq!(batch.column_count(), 6);
@@ -310,13 +519,14 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "fs")]
     #[test]
     fn test_string_field_csv_data_source() {
         let data_path = rq_test_data("string_field.csv");
         let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Utf8)]);
         let csv_data_source = CsvDataSource::new(data_path, schema, 3);
         let mut reader = csv_data_source.scan(vec!["c1"]).unwrap();
-        let batch = reader.next().unwrap();
+        let batch = reader.next().unwrap().unwrap();
 
         assert_eq!(batch.row_count(), 3);
         assert_eq!(batch.column_count(), 1);
@@ -354,7 +564,7 @@ mod tests {
     fn test_field_with_projection() {
         let (_, csv_data_source) = get_primitive_field_data_source();
         let mut reader = csv_data_source.scan(vec!["c4", "c5", "c6"]).unwrap();
-        let batch = reader.next().unwrap();
+        let batch = reader.next().unwrap().unwrap();
 
         assert_eq!(batch.row_count(), 3);
         assert_eq!(batch.column_count(), 3);
@@ -372,4 +582,161 @@ mod tests {
             ],
         );
     }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_extension_field_csv_data_source() {
+        let data_path = rq_test_data("currency_field.csv");
+        let schema = Schema::new(vec![Field::new_extension(
+            "c1".to_string(),
+            ExtensionRef::new(Currency),
+        )]);
+        let csv_data_source = CsvDataSource::new(data_path, schema, 3);
+        let mut reader = csv_data_source.scan(vec!["c1"]).unwrap();
+        let batch = reader.next().unwrap().unwrap();
+
+        assert_type_and_values::<i64>(&batch, 0, DataType::Int64, vec![123, 50, 1000]);
+    }
+
+    #[test]
+    fn test_extension_field_nulls_and_errors() {
+        let currency = ExtensionRef::new(Currency);
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(b"1,$1.23\n2,\n3,1.23\n".as_ref());
+        let rows = reader.records().collect::<Result<Vec<_>, _>>().unwrap();
+
+        // Empty cells are null.
+        let array = build_extension_array(&rows[..2], 1, "c2", &currency).unwrap();
+        assert_eq!(array.get_type(), DataType::Int64);
+        assert!(!array.is_null(0));
+        assert!(array.is_null(1));
+
+        let error = build_extension_array(&rows, 1, "c2", &currency)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Invalid value at line 3, column c2: 1.23 is not a currency: Invalid currency 1.23"
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_binary_field_csv_data_source() {
+        let data_path = rq_test_data("binary_field.csv");
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Binary),
+            Field::new("c2".to_string(), DataType::Binary),
+        ]);
+        let base64 = CsvDataSource::new(data_path.clone(), schema.clone(), 3);
+        let hex =
+            CsvDataSource::new(data_path, schema, 3).with_binary_encoding(BinaryEncoding::Hex);
+        for (csv_data_source, column) in [(base64, "c1"), (hex, "c2")] {
+            let batch = csv_data_source
+                .scan(vec![column])
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap();
+            let field = batch.field(0);
+            assert_eq!(field.get_type(), DataType::Binary);
+            assert_eq!(
+                field
+                    .get_value(0)
+                    .unwrap()
+                    .downcast_ref::<Vec<u8>>()
+                    .unwrap(),
+                b"rq\x00\xff"
+            );
+            assert!(field.is_null(1));
+            assert_eq!(
+                field
+                    .get_value(2)
+                    .unwrap()
+                    .downcast_ref::<Vec<u8>>()
+                    .unwrap(),
+                &[1, 2, 3]
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_binary_values() {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(b"1,cnE=\n2,\n3,cnE!\n".as_ref());
+        let rows = reader.records().collect::<Result<Vec<_>, _>>().unwrap();
+        let error = build_binary_array(&rows, 1, "c2", BinaryEncoding::Base64)
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("Invalid value at line 3, column c2: Invalid base64 value cnE!"));
+        assert!(build_binary_array(&rows, 1, "c2", BinaryEncoding::Hex).is_err());
+        assert!(build_primitive_array::<Int64Type>(&rows, 1, "c2").is_err());
+        assert!(build_binary_array(&rows[..2], 1, "c2", BinaryEncoding::Base64).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_value_fails_with_its_position() {
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int64)]);
+        let csv_data_source = CsvDataSource::from_bytes(b"1\n2\nx\n".to_vec(), schema, 2);
+        let mut batches = csv_data_source.scan(vec!["c1"]).unwrap();
+        assert!(batches.next().unwrap().is_ok());
+        let error = batches.next().unwrap().err().unwrap();
+        assert!(format!("{:#}", error)
+            .starts_with("Invalid value at line 3, column c1: x is not a Int64"));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_infer_schema() {
+        let schema = CsvDataSource::infer_schema(&rq_test_data("nullable_field.csv")).unwrap();
+        assert_eq!(
+            schema,
+            Schema::new(vec![
+                Field::new("c1".to_string(), DataType::Int64),
+                Field::new("c2".to_string(), DataType::Int64),
+            ])
+        );
+        let schema = CsvDataSource::infer_schema(&rq_test_data("boolean_field.csv")).unwrap();
+        assert_eq!(schema.fields[0].data_type, DataType::Boolean);
+        let schema = CsvDataSource::infer_schema(&rq_test_data("events.csv")).unwrap();
+        assert_eq!(schema.fields[0].data_type, DataType::Utf8);
+        assert_eq!(schema.fields[1].data_type, DataType::Int64);
+    }
+
+    #[test]
+    fn test_csv_data_source_from_bytes() {
+        let bytes = b"1,a,true\n2,,false\n3,c,\n".to_vec();
+        let schema = CsvDataSource::infer_schema_from_bytes(&bytes).unwrap();
+        assert_eq!(
+            schema,
+            Schema::new(vec![
+                Field::new("c1".to_string(), DataType::Int64),
+                Field::new("c2".to_string(), DataType::Utf8),
+                Field::new("c3".to_string(), DataType::Boolean),
+            ])
+        );
+        let csv_data_source = CsvDataSource::from_bytes(bytes, schema, 2);
+        // The bytes are read again by every scan.
+        for _ in 0..2 {
+            let batches = csv_data_source
+                .scan(vec!["c1"])
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(batches.len(), 2);
+            assert_type_and_values::<i64>(&batches[0], 0, DataType::Int64, vec![1, 2]);
+            assert_type_and_values::<i64>(&batches[1], 0, DataType::Int64, vec![3]);
+        }
+
+        assert_eq!(
+            CsvDataSource::infer_schema_from_bytes(b"")
+                .unwrap_err()
+                .to_string(),
+            "Cannot infer the schema of empty CSV data"
+        );
+    }
 }
@@ -0,0 +1,118 @@
+//! Arrow IPC streams written with a checksum of every batch, so batches corrupted on disk
+//! or in transit are detected when they are read back.
+//!
+//! The checksums follow the end of the data stream, in a trailer that readers of plain
+//! Arrow IPC streams never reach:
+//!
+//! ```text
+//! data stream | checksum stream | length of the checksum stream (u64 LE) | magic
+//! ```
+//!
+//! The checksum stream holds one batch with a `UInt32` column, the checksum of every batch
+//! of the data stream computed by [`RecordBatch::checksum`].
+
+use std::{
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    sync::Arc,
+};
+
+use super::memory_data_source::MemoryDataSource;
+use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+
+use anyhow::{bail, Context, Result};
+use arrow::{
+    array::UInt32Array,
+    datatypes::{DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema},
+    ipc::{reader::StreamReader, writer::StreamWriter},
+    record_batch::RecordBatch as ArrowRecordBatch,
+};
+
+const MAGIC: &[u8; 8] = b"RQCRC32\0";
+
+/// Write the trailer with the checksums of the batches, after the end of the data stream.
+pub(crate) fn write_checksums<W: Write>(writer: &mut W, checksums: Vec<u32>) -> Result<()> {
+    let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+        "checksum",
+        ArrowDataType::UInt32,
+        false,
+    )]));
+    let batch =
+        ArrowRecordBatch::try_new(schema.clone(), vec![Arc::new(UInt32Array::from(checksums))])?;
+    let mut stream = StreamWriter::try_new(vec![], &schema)?;
+    stream.write(&batch)?;
+    let stream = stream.into_inner()?;
+    writer.write_all(&stream)?;
+    writer.write_all(&(stream.len() as u64).to_le_bytes())?;
+    writer.write_all(MAGIC)?;
+    Ok(())
+}
+
+/// Read the checksums from the trailer of the stream that starts at `start`, if it has one.
+fn read_checksums<R: Read + Seek>(reader: &mut R, start: u64) -> Result<Option<Vec<u32>>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    if end < start + 16 {
+        return Ok(None);
+    }
+    let mut footer = [0; 16];
+    reader.seek(SeekFrom::Start(end - 16))?;
+    reader.read_exact(&mut footer)?;
+    if &footer[8..] != MAGIC {
+        return Ok(None);
+    }
+    let length = u64::from_le_bytes(footer[..8].try_into()?);
+    if length > end - 16 - start {
+        bail!("The checksum trailer of the IPC stream is corrupted");
+    }
+    let mut stream = vec![0; length as usize];
+    reader.seek(SeekFrom::Start(end - 16 - length))?;
+    reader.read_exact(&mut stream)?;
+    let read = || -> Result<Vec<u32>> {
+        let mut checksums = vec![];
+        for batch in StreamReader::try_new(Cursor::new(stream), None)? {
+            let batch = batch?;
+            match batch.column(0).as_any().downcast_ref::<UInt32Array>() {
+                Some(column) => checksums.extend(column.values()),
+                None => bail!("Expected a UInt32 column of checksums"),
+            }
+        }
+        Ok(checksums)
+    };
+    read()
+        .context("The checksum trailer of the IPC stream is corrupted")
+        .map(Some)
+}
+
+/// Read an Arrow IPC stream into a memory table. The batches of a stream written with
+/// checksums, as by [`DataFrame::write_ipc`], are verified against them.
+///
+/// [`DataFrame::write_ipc`]: crate::logical_plan::data_frame::DataFrame::write_ipc
+pub fn read_ipc<R: Read + Seek>(mut reader: R) -> Result<MemoryDataSource> {
+    let start = reader.stream_position()?;
+    let checksums = read_checksums(&mut reader, start)?;
+    reader.seek(SeekFrom::Start(start))?;
+    let stream = StreamReader::try_new(reader, None)?;
+    let schema = Schema::try_from(stream.schema().as_ref())?;
+    let mut batches = vec![];
+    for (i, batch) in stream.enumerate() {
+        let batch = RecordBatch::try_from(&batch?)?;
+        if let Some(checksums) = &checksums {
+            match checksums.get(i) {
+                Some(checksum) => batch
+                    .verify_checksum(*checksum)
+                    .with_context(|| format!("Batch {} of the IPC stream is corrupted", i))?,
+                None => bail!("Batch {} of the IPC stream has no checksum", i),
+            }
+        }
+        batches.push(batch);
+    }
+    if let Some(checksums) = &checksums {
+        if checksums.len() != batches.len() {
+            bail!(
+                "The IPC stream has {} batches, but {} checksums",
+                batches.len(),
+                checksums.len()
+            );
+        }
+    }
+    Ok(MemoryDataSource::new(schema, batches))
+}
@@ -1,5 +1,5 @@
 use super::DataSource;
-use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema, statistics::Statistics};
 
 use anyhow::Result;
 
@@ -7,34 +7,98 @@ use anyhow::Result;
 pub struct MemoryDataSource {
     schema: Schema,
     data: Vec<RecordBatch>,
+    partitions: usize,
 }
 
-impl DataSource for MemoryDataSource {
-    fn get_schema(&self) -> &Schema {
-        &self.schema
+impl MemoryDataSource {
+    pub fn new(schema: Schema, data: Vec<RecordBatch>) -> Self {
+        Self {
+            schema,
+            data,
+            partitions: 1,
+        }
     }
 
-    fn scan(&self, projection: Vec<&str>) -> Result<Box<dyn Iterator<Item = RecordBatch> + '_>> {
-        let projection_indices = projection
-            .iter()
-            .filter_map(|name| self.schema.fields.iter().position(|f| f.name == *name))
-            .collect::<Vec<_>>();
+    /// Split the batches into the number of partitions, round-robin.
+    pub fn with_partitions(mut self, partitions: usize) -> Self {
+        self.partitions = partitions.max(1);
+        self
+    }
+
+    /// Append batches, which must have the schema of the data source.
+    pub fn append(&mut self, batches: Vec<RecordBatch>) {
+        self.data.extend(batches);
+    }
+
+    fn project<'a>(
+        &'a self,
+        batches: impl Iterator<Item = &'a RecordBatch> + Send + 'a,
+        projection: Vec<&str>,
+    ) -> Box<dyn Iterator<Item = Result<RecordBatch>> + Send + 'a> {
+        // An empty projection reads all the columns, as for the other data sources.
+        let projection_indices = match projection.is_empty() {
+            true => (0..self.schema.fields.len()).collect::<Vec<_>>(),
+            false => projection
+                .iter()
+                .filter_map(|name| self.schema.fields.iter().position(|f| f.name == *name))
+                .collect::<Vec<_>>(),
+        };
+        let schema = Schema::new(
+            projection_indices
+                .iter()
+                .map(|i| self.schema.fields[*i].clone())
+                .collect(),
+        );
 
-        Ok(Box::new(self.data.iter().map(move |batch| {
-            RecordBatch {
-                schema: self.schema.clone(),
+        Box::new(batches.map(move |batch| {
+            Ok(RecordBatch {
+                schema: schema.clone(),
                 fields: projection_indices
                     .iter()
                     .map(|i| batch.field(*i).clone())
                     .collect(),
-            }
-        })))
+            })
+        }))
+    }
+}
+
+impl DataSource for MemoryDataSource {
+    fn get_schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn scan(
+        &self,
+        projection: Vec<&str>,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + '_>> {
+        Ok(self.project(self.data.iter(), projection))
+    }
+
+    fn partition_count(&self) -> usize {
+        self.partitions
+    }
+
+    fn scan_partition(
+        &self,
+        projection: Vec<&str>,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + '_>> {
+        let batches = self.data.iter().skip(partition).step_by(self.partitions);
+        Ok(self.project(batches, projection))
+    }
+
+    fn statistics(&self) -> Statistics {
+        let num_rows = self.data.iter().map(|batch| batch.row_count()).sum();
+        Statistics {
+            num_rows: Some(num_rows),
+            ..Statistics::unknown(self.schema.fields.len())
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
+    use std::sync::Arc;
 
     use super::*;
     use crate::data_types::{
@@ -51,10 +115,7 @@ mod tests {
             Field::new("a".to_string(), DataType::Int32),
             Field::new("b".to_string(), DataType::Int32),
         ]);
-        let data_source = MemoryDataSource {
-            schema: schema.clone(),
-            data: vec![],
-        };
+        let data_source = MemoryDataSource::new(schema.clone(), vec![]);
         assert_eq!(data_source.get_schema(), &schema);
     }
 
@@ -62,24 +123,53 @@ mod tests {
     fn test_scan() {
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
         let id = Int32Array::from(vec![1, 2, 3, 4, 5]);
-        let id_array = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_array = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
 
         let records = vec![RecordBatch::new(schema.clone(), id_array)];
-        let data_source = MemoryDataSource {
-            schema,
-            data: records,
-        };
+        let data_source = MemoryDataSource::new(schema, records);
 
         // None exists in the schema, so we should get an empty iterator.
         let projection = vec!["a"];
-        let result: Vec<RecordBatch> = data_source.scan(projection).unwrap().collect();
+        let result: Vec<RecordBatch> = data_source
+            .scan(projection)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].fields.len(), 0);
 
         let projection = vec!["id"];
-        let result: Vec<RecordBatch> = data_source.scan(projection).unwrap().collect();
+        let result: Vec<RecordBatch> = data_source
+            .scan(projection)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].fields.len(), 1);
         assert_eq!(result[0].fields[0].size(), 5);
     }
+
+    #[test]
+    fn test_scan_partition() {
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
+        let batch = |ids: Vec<i32>| {
+            let id = Int32Array::from(ids);
+            let fields = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+            RecordBatch::new(schema.clone(), fields)
+        };
+        let records = vec![batch(vec![1, 2]), batch(vec![3]), batch(vec![4, 5, 6])];
+        let data_source = MemoryDataSource::new(schema.clone(), records).with_partitions(2);
+        assert_eq!(data_source.partition_count(), 2);
+
+        let rows = |partition| {
+            data_source
+                .scan_partition(vec!["id"], partition)
+                .unwrap()
+                .map(|b| b.unwrap().row_count())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rows(0), vec![2, 3]);
+        assert_eq!(rows(1), vec![1]);
+        assert_eq!(data_source.scan(vec!["id"]).unwrap().count(), 3);
+    }
 }
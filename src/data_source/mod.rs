@@ -1,23 +1,54 @@
 pub mod csv_data_source;
+pub mod ipc;
 pub mod memory_data_source;
 pub mod reader_parser;
+pub mod series_data_source;
 
-use self::{csv_data_source::CsvDataSource, memory_data_source::MemoryDataSource};
-use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+use self::{
+    csv_data_source::CsvDataSource, memory_data_source::MemoryDataSource,
+    series_data_source::SeriesDataSource,
+};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema, statistics::Statistics};
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 pub trait DataSource {
     /// Return the schema for the underlying data source.
     fn get_schema(&self) -> &Schema;
     /// Scan the data source, selecting the specified columns.
-    fn scan(&self, projection: Vec<&str>) -> Result<Box<dyn Iterator<Item = RecordBatch> + '_>>;
+    /// A batch that cannot be read, e.g. from malformed input, is an error.
+    fn scan(
+        &self,
+        projection: Vec<&str>,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + '_>>;
+    /// Return the number of partitions the data source is split into, which can be
+    /// scanned independently.
+    fn partition_count(&self) -> usize {
+        1
+    }
+    /// Scan a partition of the data source, selecting the specified columns.
+    /// A data source with a single partition scans all of it.
+    fn scan_partition(
+        &self,
+        projection: Vec<&str>,
+        _partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + '_>> {
+        self.scan(projection)
+    }
+    /// Return the statistics of all the columns of the data source, unknown by default.
+    fn statistics(&self) -> Statistics {
+        Statistics::unknown(self.get_schema().fields.len())
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Source {
     Csv(CsvDataSource),
+    /// Memory tables hold their data, so plans that scan them cannot be serialized.
+    #[serde(skip)]
     Mem(MemoryDataSource),
+    Series(SeriesDataSource),
 }
 
 impl DataSource for Source {
@@ -25,13 +56,50 @@ impl DataSource for Source {
         match self {
             Source::Csv(csv_data_source) => csv_data_source.get_schema(),
             Source::Mem(memory_data_source) => memory_data_source.get_schema(),
+            Source::Series(series_data_source) => series_data_source.get_schema(),
         }
     }
 
-    fn scan(&self, projection: Vec<&str>) -> Result<Box<dyn Iterator<Item = RecordBatch> + '_>> {
+    fn scan(
+        &self,
+        projection: Vec<&str>,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + '_>> {
         match self {
             Source::Csv(csv_data_source) => csv_data_source.scan(projection),
             Source::Mem(memory_data_source) => memory_data_source.scan(projection),
+            Source::Series(series_data_source) => series_data_source.scan(projection),
+        }
+    }
+
+    fn partition_count(&self) -> usize {
+        match self {
+            Source::Csv(csv_data_source) => csv_data_source.partition_count(),
+            Source::Mem(memory_data_source) => memory_data_source.partition_count(),
+            Source::Series(series_data_source) => series_data_source.partition_count(),
+        }
+    }
+
+    fn scan_partition(
+        &self,
+        projection: Vec<&str>,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + '_>> {
+        match self {
+            Source::Csv(csv_data_source) => csv_data_source.scan_partition(projection, partition),
+            Source::Mem(memory_data_source) => {
+                memory_data_source.scan_partition(projection, partition)
+            }
+            Source::Series(series_data_source) => {
+                series_data_source.scan_partition(projection, partition)
+            }
+        }
+    }
+
+    fn statistics(&self) -> Statistics {
+        match self {
+            Source::Csv(csv_data_source) => csv_data_source.statistics(),
+            Source::Mem(memory_data_source) => memory_data_source.statistics(),
+            Source::Series(series_data_source) => series_data_source.statistics(),
         }
     }
 }
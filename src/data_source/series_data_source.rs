@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use super::DataSource;
+use crate::data_types::{
+    arrow_field_array::ArrowFieldArray,
+    column_array::{ArrayRef, DataType},
+    record_batch::RecordBatch,
+    schema::{Field, Schema},
+    statistics::{ColumnStatistics, Statistics},
+};
+
+use anyhow::{bail, Result};
+use arrow::array::{Float64Array, Int64Array};
+use serde::{Deserialize, Serialize};
+
+/// The bounds and step of a series. Both bounds are inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Series {
+    Int64(i64, i64, i64),
+    Float64(f64, f64, f64),
+}
+
+// A data source that generates a series of numbers in a single `value` column.
+// Timestamps are integers, so a series of timestamps is an Int64 series.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SeriesDataSource {
+    schema: Schema,
+    series: Series,
+    batch_size: usize,
+}
+
+impl SeriesDataSource {
+    pub fn try_new(series: Series, batch_size: usize) -> Result<Self> {
+        let data_type = match series {
+            Series::Int64(_, _, 0) => bail!("The step of a series must not be zero"),
+            Series::Int64(..) => DataType::Int64,
+            Series::Float64(_, _, step) if step == 0.0 || !step.is_finite() => {
+                bail!("The step of a series must be finite and not zero")
+            }
+            Series::Float64(..) => DataType::Float64,
+        };
+        Ok(Self {
+            schema: Schema::new(vec![Field::new("value".to_string(), data_type)]),
+            series,
+            batch_size,
+        })
+    }
+
+    /// Return the number of values in the series.
+    fn len(&self) -> usize {
+        match self.series {
+            Series::Int64(start, stop, step) => {
+                if (step > 0 && start > stop) || (step < 0 && start < stop) {
+                    0
+                } else {
+                    ((stop as i128 - start as i128) / step as i128) as usize + 1
+                }
+            }
+            Series::Float64(start, stop, step) => {
+                let steps = ((stop - start) / step).floor();
+                if steps < 0.0 {
+                    0
+                } else {
+                    steps as usize + 1
+                }
+            }
+        }
+    }
+
+    /// Return the values from `offset` to `end` as an array.
+    fn values(&self, offset: usize, end: usize) -> ArrayRef {
+        match self.series {
+            Series::Int64(start, _, step) => {
+                let values = (offset..end)
+                    .map(|i| (start as i128 + i as i128 * step as i128) as i64)
+                    .collect::<Int64Array>();
+                Arc::new(ArrowFieldArray::new(Box::new(values)))
+            }
+            Series::Float64(start, _, step) => {
+                let values = (offset..end)
+                    .map(|i| start + i as f64 * step)
+                    .collect::<Float64Array>();
+                Arc::new(ArrowFieldArray::new(Box::new(values)))
+            }
+        }
+    }
+}
+
+impl DataSource for SeriesDataSource {
+    fn get_schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn scan(
+        &self,
+        projection: Vec<&str>,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + '_>> {
+        let schema = if projection.is_empty() {
+            self.schema.clone()
+        } else {
+            self.schema.select(projection)
+        };
+        let len = self.len();
+        let batch_size = self.batch_size.max(1);
+        Ok(Box::new((0..len).step_by(batch_size).map(move |offset| {
+            let end = (offset + batch_size).min(len);
+            let fields = schema
+                .fields
+                .iter()
+                .map(|_| self.values(offset, end))
+                .collect();
+            Ok(RecordBatch::new(schema.clone(), fields))
+        })))
+    }
+
+    /// The values of a series are all distinct.
+    fn statistics(&self) -> Statistics {
+        let len = self.len();
+        Statistics::new(
+            Some(len),
+            vec![ColumnStatistics {
+                distinct_count: Some(len),
+            }],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_types::column_array::ColumnArray;
+
+    fn collect_i64(data_source: &SeriesDataSource) -> Vec<i64> {
+        data_source
+            .scan(vec![])
+            .unwrap()
+            .flat_map(|b| {
+                let b = b.unwrap();
+                (0..b.row_count())
+                    .map(|i| {
+                        *b.field(0)
+                            .get_value(i)
+                            .unwrap()
+                            .downcast_ref::<i64>()
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_int64_series() {
+        let data_source = SeriesDataSource::try_new(Series::Int64(1, 10, 3), 2).unwrap();
+        assert_eq!(collect_i64(&data_source), vec![1, 4, 7, 10]);
+        assert_eq!(data_source.scan(vec![]).unwrap().count(), 2);
+
+        let data_source = SeriesDataSource::try_new(Series::Int64(5, 1, -2), 2).unwrap();
+        assert_eq!(collect_i64(&data_source), vec![5, 3, 1]);
+
+        let data_source = SeriesDataSource::try_new(Series::Int64(5, 1, 1), 2).unwrap();
+        assert!(collect_i64(&data_source).is_empty());
+
+        assert!(SeriesDataSource::try_new(Series::Int64(1, 10, 0), 2).is_err());
+    }
+
+    #[test]
+    fn test_scan_float64_series() {
+        let data_source = SeriesDataSource::try_new(Series::Float64(0.0, 1.0, 0.25), 10).unwrap();
+        assert_eq!(
+            data_source.get_schema().fields[0].data_type,
+            DataType::Float64
+        );
+        let batch = data_source
+            .scan(vec!["value"])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let values = (0..batch.row_count())
+            .map(|i| {
+                *batch
+                    .field(0)
+                    .get_value(i)
+                    .unwrap()
+                    .downcast_ref::<f64>()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+    }
+}
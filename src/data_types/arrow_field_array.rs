@@ -4,7 +4,10 @@ use super::column_array::{ColumnArray, DataType};
 
 use anyhow::Result;
 use arrow::{
-    array::{Array, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array, StringArray},
+    array::{
+        Array, BinaryArray, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array,
+        ListArray, StringArray, StructArray,
+    },
     datatypes::DataType as ArrayDataType,
 };
 
@@ -15,15 +18,7 @@ pub struct ArrowFieldArray {
 
 impl ColumnArray for ArrowFieldArray {
     fn get_type(&self) -> DataType {
-        match self.field.data_type() {
-            ArrayDataType::Boolean => DataType::Boolean,
-            ArrayDataType::Int32 => DataType::Int32,
-            ArrayDataType::Int64 => DataType::Int64,
-            ArrayDataType::Float32 => DataType::Float32,
-            ArrayDataType::Float64 => DataType::Float64,
-            ArrayDataType::Utf8 => DataType::Utf8,
-            _ => unreachable!(),
-        }
+        self.field.data_type().try_into().unwrap()
     }
 
     fn get_value(&self, i: usize) -> Result<Box<dyn Any>> {
@@ -71,19 +66,73 @@ impl ColumnArray for ArrowFieldArray {
                     .value(i)
                     .to_string(),
             )),
+            ArrayDataType::Binary => Ok(Box::new(
+                self.field
+                    .as_any()
+                    .downcast_ref::<BinaryArray>()
+                    .expect("Failed to downcast")
+                    .value(i)
+                    .to_vec(),
+            )),
+            ArrayDataType::List(_) => {
+                let list = self
+                    .field
+                    .as_any()
+                    .downcast_ref::<ListArray>()
+                    .expect("Failed to downcast");
+                let items = ArrowFieldArray::new(Box::new(list.value(i)));
+                Ok(Box::new(
+                    (0..items.size())
+                        .map(|j| items.get_nullable_value(j))
+                        .collect::<Result<Vec<_>>>()?,
+                ))
+            }
+            ArrayDataType::Struct(_) => {
+                let fields = self
+                    .field
+                    .as_any()
+                    .downcast_ref::<StructArray>()
+                    .expect("Failed to downcast");
+                Ok(Box::new(
+                    fields
+                        .columns()
+                        .into_iter()
+                        .map(|c| ArrowFieldArray::new(Box::new(c.clone())).get_nullable_value(i))
+                        .collect::<Result<Vec<_>>>()?,
+                ))
+            }
             _ => unreachable!(),
         }
     }
 
+    fn is_null(&self, i: usize) -> bool {
+        self.field.is_null(i)
+    }
+
     fn size(&self) -> usize {
         self.field.len()
     }
+
+    fn memory_size(&self) -> usize {
+        self.field.get_array_memory_size()
+    }
+
+    fn as_arrow(&self) -> Option<&dyn Array> {
+        Some(self.field.as_ref())
+    }
 }
 
 impl ArrowFieldArray {
     pub fn new(value: Box<dyn Array>) -> Self {
         ArrowFieldArray { field: value }
     }
+
+    fn get_nullable_value(&self, i: usize) -> Result<Option<Box<dyn Any>>> {
+        if self.is_null(i) {
+            return Ok(None);
+        }
+        self.get_value(i).map(Some)
+    }
 }
 
 #[cfg(test)]
@@ -119,6 +168,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_nested_value() {
+        let tags = ListArray::from_iter_primitive::<arrow::datatypes::Int32Type, _, _>(vec![
+            Some(vec![Some(1), None]),
+            None,
+        ]);
+        let a = ArrowFieldArray::new(Box::new(tags));
+        assert_eq!(a.get_type(), DataType::List(Box::new(DataType::Int32)));
+        let value = a.get_value(0).unwrap();
+        let items = value.downcast_ref::<Vec<Option<Box<dyn Any>>>>().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap().downcast_ref::<i32>(), Some(&1));
+        assert!(items[1].is_none());
+        assert!(a.is_null(1));
+
+        let point = StructArray::from(vec![(
+            arrow::datatypes::Field::new("x", ArrayDataType::Int32, true),
+            std::sync::Arc::new(Int32Array::from(vec![7])) as arrow::array::ArrayRef,
+        )]);
+        let a = ArrowFieldArray::new(Box::new(point));
+        assert_eq!(
+            a.get_type(),
+            DataType::Struct(vec![("x".to_string(), DataType::Int32)])
+        );
+        let value = a.get_value(0).unwrap();
+        let fields = value.downcast_ref::<Vec<Option<Box<dyn Any>>>>().unwrap();
+        assert_eq!(fields[0].as_ref().unwrap().downcast_ref::<i32>(), Some(&7));
+    }
+
+    #[test]
+    fn test_is_null() {
+        let id = Int32Array::from(vec![Some(1), None]);
+        let a = ArrowFieldArray::new(Box::new(id));
+        assert!(!a.is_null(0));
+        assert!(a.is_null(1));
+    }
+
     #[test]
     fn test_size() {
         let id = Int32Array::from(vec![1, 2, 3, 4, 5]);
@@ -0,0 +1,68 @@
+use std::fmt::Display;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Text encoding of binary values, used to read them from text files
+/// and by the `encode` and `decode` functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BinaryEncoding {
+    #[default]
+    Base64,
+    Hex,
+}
+
+impl BinaryEncoding {
+    /// Parse the name of an encoding, ignoring case.
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "base64" => Ok(BinaryEncoding::Base64),
+            "hex" => Ok(BinaryEncoding::Hex),
+            _ => bail!("Unknown binary encoding {}, expected base64 or hex", name),
+        }
+    }
+
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Base64 => base64::encode(bytes),
+            BinaryEncoding::Hex => hex::encode(bytes),
+        }
+    }
+
+    pub fn decode(&self, s: &str) -> Result<Vec<u8>> {
+        let decoded = match self {
+            BinaryEncoding::Base64 => base64::decode(s).map_err(|e| e.to_string()),
+            BinaryEncoding::Hex => hex::decode(s).map_err(|e| e.to_string()),
+        };
+        decoded.map_err(|e| anyhow!("Invalid {} value {}: {}", self, s, e))
+    }
+}
+
+impl Display for BinaryEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryEncoding::Base64 => write!(f, "base64"),
+            BinaryEncoding::Hex => write!(f, "hex"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_encoding() {
+        let bytes = b"rq\x00\xff".to_vec();
+        for (name, encoded) in [("base64", "cnEA/w=="), ("HEX", "727100ff")] {
+            let encoding = BinaryEncoding::parse(name).unwrap();
+            assert_eq!(encoding.encode(&bytes), encoded);
+            assert_eq!(encoding.decode(encoded).unwrap(), bytes);
+        }
+        assert_eq!(
+            BinaryEncoding::Hex.decode("7g").unwrap_err().to_string(),
+            "Invalid hex value 7g: Invalid character 'g' at position 1"
+        );
+        assert!(BinaryEncoding::parse("utf8").is_err());
+    }
+}
@@ -1,11 +1,15 @@
-use std::{any::Any, fmt::Display, rc::Rc};
+use std::{any::Any, fmt::Display, sync::Arc};
 
-use anyhow::Result;
-use arrow::datatypes::DataType as ArrowDataType;
+use anyhow::{bail, Result};
+use arrow::{
+    array::Array,
+    datatypes::{DataType as ArrowDataType, Field as ArrowField},
+};
+use serde::{Deserialize, Serialize};
 
 // Data type of the column.
 // We only support the following types.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum DataType {
     Boolean,
     Int32,
@@ -13,6 +17,12 @@ pub enum DataType {
     Float32,
     Float64,
     Utf8,
+    /// Raw bytes, such as hashes and payloads.
+    Binary,
+    /// A list of values of the item type.
+    List(Box<DataType>),
+    /// A struct with named fields, in order.
+    Struct(Vec<(String, DataType)>),
 }
 
 impl From<DataType> for ArrowDataType {
@@ -24,6 +34,73 @@ impl From<DataType> for ArrowDataType {
             DataType::Float32 => ArrowDataType::Float32,
             DataType::Float64 => ArrowDataType::Float64,
             DataType::Utf8 => ArrowDataType::Utf8,
+            DataType::Binary => ArrowDataType::Binary,
+            DataType::List(item_type) => {
+                ArrowDataType::List(Box::new(ArrowField::new("item", (*item_type).into(), true)))
+            }
+            DataType::Struct(fields) => ArrowDataType::Struct(
+                fields
+                    .into_iter()
+                    .map(|(name, data_type)| ArrowField::new(&name, data_type.into(), true))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl TryFrom<&ArrowDataType> for DataType {
+    type Error = anyhow::Error;
+
+    fn try_from(data_type: &ArrowDataType) -> Result<Self> {
+        Ok(match data_type {
+            ArrowDataType::Boolean => DataType::Boolean,
+            ArrowDataType::Int32 => DataType::Int32,
+            ArrowDataType::Int64 => DataType::Int64,
+            ArrowDataType::Float32 => DataType::Float32,
+            ArrowDataType::Float64 => DataType::Float64,
+            ArrowDataType::Utf8 => DataType::Utf8,
+            ArrowDataType::Binary => DataType::Binary,
+            ArrowDataType::List(item) => DataType::List(Box::new(item.data_type().try_into()?)),
+            ArrowDataType::Struct(fields) => DataType::Struct(
+                fields
+                    .iter()
+                    .map(|f| Ok((f.name().clone(), f.data_type().try_into()?)))
+                    .collect::<Result<_>>()?,
+            ),
+            _ => bail!("Unsupported Arrow type {}", data_type),
+        })
+    }
+}
+
+impl DataType {
+    /// Return true if the type is a list or a struct.
+    pub fn is_nested(&self) -> bool {
+        matches!(self, DataType::List(_) | DataType::Struct(_))
+    }
+
+    /// Return true if the type is a numeric type.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            DataType::Int32 | DataType::Int64 | DataType::Float32 | DataType::Float64
+        )
+    }
+
+    /// Return the type both types can be converted to without losing range,
+    /// or None if there is no such type.
+    pub fn common_type(&self, other: &DataType) -> Option<DataType> {
+        if self == other {
+            return Some(self.clone());
+        }
+        match (self, other) {
+            (DataType::Int32, DataType::Int64) | (DataType::Int64, DataType::Int32) => {
+                Some(DataType::Int64)
+            }
+            (DataType::Int32, DataType::Float32) | (DataType::Float32, DataType::Int32) => {
+                Some(DataType::Float32)
+            }
+            (l, r) if l.is_numeric() && r.is_numeric() => Some(DataType::Float64),
+            _ => None,
         }
     }
 }
@@ -37,21 +114,43 @@ impl Display for DataType {
             DataType::Float32 => write!(f, "Float32"),
             DataType::Float64 => write!(f, "Float64"),
             DataType::Utf8 => write!(f, "Utf8"),
+            DataType::Binary => write!(f, "Binary"),
+            DataType::List(item_type) => write!(f, "List({})", item_type),
+            DataType::Struct(fields) => write!(
+                f,
+                "Struct({})",
+                fields
+                    .iter()
+                    .map(|(name, data_type)| format!("{}: {}", name, data_type))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
     }
 }
 
 /// Abstraction over different implementations of a column vector.
-pub trait ColumnArray {
+/// Columns are shared between threads, so implementations must be Send and Sync.
+pub trait ColumnArray: Send + Sync {
     /// Return the type of the column.
     fn get_type(&self) -> DataType;
     /// Return the value at the given index.
+    /// The value of a list or a struct is a `Vec<Option<Box<dyn Any>>>` of its items or fields.
     fn get_value(&self, i: usize) -> Result<Box<dyn Any>>;
+    /// Return true if the value at the given index is null.
+    fn is_null(&self, i: usize) -> bool;
     /// Return the number of elements in the column.
     fn size(&self) -> usize;
+    /// Return the number of bytes used by the column.
+    fn memory_size(&self) -> usize;
+    /// Return the underlying Arrow array, if the column is backed by one.
+    /// Operators use it to read values without boxing each of them.
+    fn as_arrow(&self) -> Option<&dyn Array> {
+        None
+    }
 }
 
-pub type ArrayRef = Rc<dyn ColumnArray>;
+pub type ArrayRef = Arc<dyn ColumnArray>;
 
 impl ColumnArray for ArrayRef {
     fn get_type(&self) -> DataType {
@@ -62,7 +161,57 @@ impl ColumnArray for ArrayRef {
         self.as_ref().get_value(i)
     }
 
+    fn is_null(&self, i: usize) -> bool {
+        self.as_ref().is_null(i)
+    }
+
     fn size(&self) -> usize {
         self.as_ref().size()
     }
+
+    fn memory_size(&self) -> usize {
+        self.as_ref().memory_size()
+    }
+
+    fn as_arrow(&self) -> Option<&dyn Array> {
+        self.as_ref().as_arrow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArrowDataType, DataType};
+
+    #[test]
+    fn test_common_type() {
+        assert_eq!(
+            DataType::Int32.common_type(&DataType::Int32),
+            Some(DataType::Int32)
+        );
+        assert_eq!(
+            DataType::Int32.common_type(&DataType::Int64),
+            Some(DataType::Int64)
+        );
+        assert_eq!(
+            DataType::Float32.common_type(&DataType::Int32),
+            Some(DataType::Float32)
+        );
+        assert_eq!(
+            DataType::Int64.common_type(&DataType::Float32),
+            Some(DataType::Float64)
+        );
+        assert_eq!(DataType::Utf8.common_type(&DataType::Int32), None);
+    }
+
+    #[test]
+    fn test_nested_arrow_type() {
+        let data_type = DataType::Struct(vec![
+            ("id".to_string(), DataType::Int64),
+            ("tags".to_string(), DataType::List(Box::new(DataType::Utf8))),
+        ]);
+        assert_eq!(data_type.to_string(), "Struct(id: Int64, tags: List(Utf8))");
+        let arrow_type = ArrowDataType::from(data_type.clone());
+        assert_eq!(DataType::try_from(&arrow_type).unwrap(), data_type);
+        assert!(DataType::try_from(&ArrowDataType::UInt8).is_err());
+    }
 }
@@ -0,0 +1,142 @@
+use std::{any::Any, fmt::Debug, sync::Arc};
+
+use super::{column_array::DataType, inet::Inet, point::Point};
+
+use anyhow::{bail, Result};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A user-defined logical type stored as one of the built-in data types.
+/// Values are parsed from text into the storage type when they are read,
+/// and comparisons operate on the stored values.
+pub trait ExtensionType: Send + Sync {
+    /// Return the name of the logical type, e.g. `uuid`.
+    fn name(&self) -> &str;
+    /// Return the built-in data type used to store the values.
+    fn storage_type(&self) -> DataType;
+    /// Parse a value from text into a value of the storage type.
+    fn parse(&self, s: &str) -> Result<Box<dyn Any>>;
+    /// Format a value of the storage type as text.
+    fn format(&self, value: &dyn Any) -> String;
+    /// Cast a value of the storage type to a built-in data type. By default values only
+    /// cast to Utf8, as their text form.
+    fn cast(&self, value: &dyn Any, data_type: &DataType) -> Result<Box<dyn Any>> {
+        match data_type {
+            DataType::Utf8 => Ok(Box::new(self.format(value))),
+            _ => bail!("Cannot cast {} to {}", self.name(), data_type),
+        }
+    }
+}
+
+/// Shared reference to an extension type.
+/// Two references are equal if they refer to types with the same name.
+#[derive(Clone)]
+pub struct ExtensionRef(pub Arc<dyn ExtensionType>);
+
+impl ExtensionRef {
+    pub fn new(extension: impl ExtensionType + 'static) -> Self {
+        Self(Arc::new(extension))
+    }
+
+    /// Return the built-in extension type with the name, if any.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "inet" => Some(ExtensionRef::new(Inet)),
+            "point" => Some(ExtensionRef::new(Point)),
+            _ => None,
+        }
+    }
+}
+
+impl std::ops::Deref for ExtensionRef {
+    type Target = dyn ExtensionType;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl Debug for ExtensionRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Extension({})", self.name())
+    }
+}
+
+impl PartialEq for ExtensionRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Eq for ExtensionRef {}
+
+/// Extension types are serialized by name, and only the built-in ones can be deserialized.
+impl Serialize for ExtensionRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for ExtensionRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        ExtensionRef::builtin(&name)
+            .ok_or_else(|| D::Error::custom(format!("Unknown extension type {}", name)))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use anyhow::anyhow;
+
+    /// Currency amounts such as `$1.23`, stored as cents.
+    pub(crate) struct Currency;
+
+    impl ExtensionType for Currency {
+        fn name(&self) -> &str {
+            "currency"
+        }
+
+        fn storage_type(&self) -> DataType {
+            DataType::Int64
+        }
+
+        fn parse(&self, s: &str) -> Result<Box<dyn Any>> {
+            let amount = s
+                .strip_prefix('$')
+                .ok_or_else(|| anyhow!("Invalid currency {}", s))?;
+            let (dollars, cents) = amount.split_once('.').unwrap_or((amount, "0"));
+            Ok(Box::new(
+                dollars.parse::<i64>()? * 100 + cents.parse::<i64>()?,
+            ))
+        }
+
+        fn format(&self, value: &dyn Any) -> String {
+            let cents = value.downcast_ref::<i64>().unwrap();
+            format!("${}.{:02}", cents / 100, cents % 100)
+        }
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        let currency = ExtensionRef::new(Currency);
+        let value = currency.parse("$1.23").unwrap();
+        assert_eq!(value.downcast_ref::<i64>().unwrap(), &123);
+        assert_eq!(currency.format(value.as_ref()), "$1.23");
+        assert!(currency.parse("1.23").is_err());
+
+        let text = currency.cast(value.as_ref(), &DataType::Utf8).unwrap();
+        assert_eq!(text.downcast_ref::<String>().unwrap(), "$1.23");
+        assert!(currency.cast(value.as_ref(), &DataType::Int64).is_err());
+    }
+
+    #[test]
+    fn test_eq() {
+        assert_eq!(ExtensionRef::new(Currency), ExtensionRef::new(Currency));
+        assert_eq!(
+            format!("{:?}", ExtensionRef::new(Currency)),
+            "Extension(currency)"
+        );
+    }
+}
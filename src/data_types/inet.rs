@@ -0,0 +1,137 @@
+use std::{
+    any::Any,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use super::{column_array::DataType, extension::ExtensionType};
+
+use anyhow::{anyhow, bail, Result};
+
+/// IPv4 and IPv6 addresses.
+/// Addresses are stored as the 32 hex digits of their IPv6 form (IPv4 addresses are
+/// IPv4-mapped), so comparing the stored strings orders the addresses numerically.
+pub struct Inet;
+
+impl ExtensionType for Inet {
+    fn name(&self) -> &str {
+        "inet"
+    }
+
+    fn storage_type(&self) -> DataType {
+        DataType::Utf8
+    }
+
+    fn parse(&self, s: &str) -> Result<Box<dyn Any>> {
+        Ok(Box::new(format!("{:032x}", parse_inet(s)?)))
+    }
+
+    fn format(&self, value: &dyn Any) -> String {
+        let stored = value.downcast_ref::<String>().unwrap();
+        match u128::from_str_radix(stored, 16) {
+            Ok(addr) => format_inet(addr),
+            Err(_) => stored.clone(),
+        }
+    }
+}
+
+/// Parse an address, either in text form or in the stored hex form.
+pub fn parse_inet(s: &str) -> Result<u128> {
+    if s.len() == 32 {
+        if let Ok(addr) = u128::from_str_radix(s, 16) {
+            return Ok(addr);
+        }
+    }
+    let addr = s
+        .parse::<IpAddr>()
+        .map_err(|_| anyhow!("Invalid IP address {}", s))?;
+    Ok(match addr {
+        IpAddr::V4(v4) => u128::from(v4.to_ipv6_mapped()),
+        IpAddr::V6(v6) => u128::from(v6),
+    })
+}
+
+/// Format an address, using the IPv4 form for IPv4-mapped addresses.
+pub fn format_inet(addr: u128) -> String {
+    let v6 = Ipv6Addr::from(addr);
+    match v6.to_ipv4_mapped() {
+        Some(v4) => v4.to_string(),
+        None => v6.to_string(),
+    }
+}
+
+/// Parse a CIDR block such as `10.0.0.0/8` into its network address and prefix length,
+/// both in the IPv6 address space.
+pub fn parse_cidr(s: &str) -> Result<(u128, u32)> {
+    let (addr, prefix) = s.split_once('/').unwrap_or((s, ""));
+    let is_v4 = addr.parse::<Ipv4Addr>().is_ok();
+    let max_prefix = if is_v4 { 32 } else { 128 };
+    let prefix = if prefix.is_empty() {
+        max_prefix
+    } else {
+        prefix
+            .parse::<u32>()
+            .map_err(|_| anyhow!("Invalid CIDR {}", s))?
+    };
+    if prefix > max_prefix {
+        bail!("Invalid CIDR {}", s);
+    }
+    let prefix = if is_v4 { prefix + 96 } else { prefix };
+
+    Ok((parse_inet(addr)? & mask(prefix), prefix))
+}
+
+/// Return true if the address is in the CIDR block.
+pub fn inet_contains(cidr: (u128, u32), addr: u128) -> bool {
+    addr & mask(cidr.1) == cidr.0
+}
+
+fn mask(prefix: u32) -> u128 {
+    u128::MAX.checked_shl(128 - prefix).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let inet = Inet;
+        for addr in ["10.0.0.1", "::1", "2001:db8::ff00:42:8329"] {
+            let stored = inet.parse(addr).unwrap();
+            assert_eq!(inet.format(stored.as_ref()), addr);
+        }
+        assert!(inet.parse("10.0.0.256").is_err());
+    }
+
+    #[test]
+    fn test_ordering() {
+        let inet = Inet;
+        let parse = |s| {
+            inet.parse(s)
+                .unwrap()
+                .downcast_ref::<String>()
+                .unwrap()
+                .clone()
+        };
+        assert!(parse("9.255.255.255") < parse("10.0.0.0"));
+        assert!(parse("10.0.0.2") < parse("10.0.0.10"));
+        assert!(parse("255.255.255.255") < parse("2001:db8::1"));
+    }
+
+    #[test]
+    fn test_inet_contains() {
+        let cidr = parse_cidr("10.0.0.0/8").unwrap();
+        assert!(inet_contains(cidr, parse_inet("10.1.2.3").unwrap()));
+        assert!(!inet_contains(cidr, parse_inet("11.0.0.0").unwrap()));
+
+        let cidr = parse_cidr("2001:db8::/32").unwrap();
+        assert!(inet_contains(cidr, parse_inet("2001:db8::1").unwrap()));
+        assert!(!inet_contains(cidr, parse_inet("10.1.2.3").unwrap()));
+
+        let cidr = parse_cidr("0.0.0.0/0").unwrap();
+        assert!(inet_contains(cidr, parse_inet("10.1.2.3").unwrap()));
+        assert!(!inet_contains(cidr, parse_inet("::1").unwrap()));
+
+        assert!(parse_cidr("10.0.0.0/33").is_err());
+    }
+}
@@ -12,7 +12,7 @@ pub struct LiteralValueArray<T> {
     size: usize,
 }
 
-impl<T: Clone + Any> ColumnArray for LiteralValueArray<T> {
+impl<T: Clone + Any + Send + Sync> ColumnArray for LiteralValueArray<T> {
     fn get_type(&self) -> DataType {
         self.arrow_type.clone()
     }
@@ -24,9 +24,18 @@ impl<T: Clone + Any> ColumnArray for LiteralValueArray<T> {
         Ok(Box::new(self.value.clone()))
     }
 
+    fn is_null(&self, _i: usize) -> bool {
+        false
+    }
+
     fn size(&self) -> usize {
         self.size
     }
+
+    // The value is stored once, whatever the size of the column.
+    fn memory_size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
 }
 
 impl<T: Clone + Any> LiteralValueArray<T> {
@@ -1,5 +1,12 @@
 pub mod arrow_field_array;
+pub mod binary;
 pub mod column_array;
+pub mod extension;
+pub mod inet;
 pub mod literal_value_array;
+pub mod point;
+#[cfg(feature = "polars")]
+pub mod polars;
 pub mod record_batch;
 pub mod schema;
+pub mod statistics;
@@ -0,0 +1,120 @@
+use std::any::Any;
+
+use super::{column_array::DataType, extension::ExtensionType};
+
+use anyhow::{anyhow, bail, Result};
+
+/// Mean radius of the Earth in meters.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Geographic points given as `lat,lon` in degrees.
+/// Points are stored in a normalized `lat,lon` text form.
+pub struct Point;
+
+impl ExtensionType for Point {
+    fn name(&self) -> &str {
+        "point"
+    }
+
+    fn storage_type(&self) -> DataType {
+        DataType::Utf8
+    }
+
+    fn parse(&self, s: &str) -> Result<Box<dyn Any>> {
+        let (lat, lon) = parse_point(s)?;
+        Ok(Box::new(format!("{},{}", lat, lon)))
+    }
+
+    fn format(&self, value: &dyn Any) -> String {
+        value.downcast_ref::<String>().unwrap().clone()
+    }
+}
+
+/// Parse a point such as `52.52,13.405` or `(52.52 13.405)` into its latitude and longitude.
+pub fn parse_point(s: &str) -> Result<(f64, f64)> {
+    let invalid = || anyhow!("Invalid point {}", s);
+    let trimmed = s.trim().trim_start_matches('(').trim_end_matches(')');
+    let (lat, lon) = trimmed
+        .split_once(',')
+        .or_else(|| trimmed.split_once(' '))
+        .ok_or_else(invalid)?;
+    let lat = lat.trim().parse::<f64>().map_err(|_| invalid())?;
+    let lon = lon.trim().parse::<f64>().map_err(|_| invalid())?;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        bail!("Point {} is out of range", s);
+    }
+    Ok((lat, lon))
+}
+
+/// Return the great-circle distance between two points in meters.
+pub fn st_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// Return true if the point is inside the bounding box given by its south-west
+/// and north-east corners. Boxes crossing the antimeridian have a west longitude
+/// greater than their east longitude.
+pub fn st_within_bbox(point: (f64, f64), south_west: (f64, f64), north_east: (f64, f64)) -> bool {
+    let (lat, lon) = point;
+    let in_lat = south_west.0 <= lat && lat <= north_east.0;
+    let in_lon = if south_west.1 <= north_east.1 {
+        south_west.1 <= lon && lon <= north_east.1
+    } else {
+        lon >= south_west.1 || lon <= north_east.1
+    };
+    in_lat && in_lon
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let point = Point;
+        let stored = point.parse("(52.52 13.405)").unwrap();
+        assert_eq!(point.format(stored.as_ref()), "52.52,13.405");
+        assert_eq!(parse_point(" -33.87, 151.21 ").unwrap(), (-33.87, 151.21));
+        assert!(parse_point("52.52").is_err());
+        assert!(parse_point("91,0").is_err());
+    }
+
+    #[test]
+    fn test_st_distance() {
+        let berlin = (52.52, 13.405);
+        let paris = (48.8566, 2.3522);
+        let distance = st_distance(berlin, paris);
+        assert!((distance - 877_500.0).abs() < 2_000.0, "{}", distance);
+        assert_eq!(st_distance(berlin, berlin), 0.0);
+    }
+
+    #[test]
+    fn test_st_within_bbox() {
+        assert!(st_within_bbox((52.52, 13.405), (47.0, 5.0), (55.0, 15.0)));
+        assert!(!st_within_bbox(
+            (48.8566, 2.3522),
+            (47.0, 5.0),
+            (55.0, 15.0)
+        ));
+        // A box crossing the antimeridian.
+        assert!(st_within_bbox(
+            (-17.7, 178.0),
+            (-20.0, 170.0),
+            (-10.0, -170.0)
+        ));
+        assert!(st_within_bbox(
+            (-17.7, -179.0),
+            (-20.0, 170.0),
+            (-10.0, -170.0)
+        ));
+        assert!(!st_within_bbox(
+            (-17.7, 0.0),
+            (-20.0, 170.0),
+            (-10.0, -170.0)
+        ));
+    }
+}
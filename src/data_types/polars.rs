@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use arrow::{
+    array::{
+        Array, ArrayRef as ArrowArrayRef, BinaryArray, BooleanArray, Float32Array, Float64Array,
+        Int32Array, Int64Array, StringArray,
+    },
+    datatypes::{DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema},
+    record_batch::RecordBatch as ArrowRecordBatch,
+};
+use polars::prelude::{
+    Column, DataFrame as PolarsDataFrame, DataType as PolarsDataType, NamedFrom, Series,
+};
+
+/// Copy the columns of the Arrow batches, which all have the given schema, into a polars
+/// DataFrame with one chunk per column. Lists and structs are not supported.
+pub fn to_polars(schema: &ArrowSchema, batches: &[ArrowRecordBatch]) -> Result<PolarsDataFrame> {
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let arrays = batches.iter().map(|b| b.column(i).as_any());
+            let name = field.name().as_str();
+            let series = match field.data_type() {
+                ArrowDataType::Boolean => {
+                    Series::new(name.into(), values::<BooleanArray, _>(arrays))
+                }
+                ArrowDataType::Int32 => Series::new(name.into(), values::<Int32Array, _>(arrays)),
+                ArrowDataType::Int64 => Series::new(name.into(), values::<Int64Array, _>(arrays)),
+                ArrowDataType::Float32 => {
+                    Series::new(name.into(), values::<Float32Array, _>(arrays))
+                }
+                ArrowDataType::Float64 => {
+                    Series::new(name.into(), values::<Float64Array, _>(arrays))
+                }
+                ArrowDataType::Utf8 => Series::new(name.into(), values::<StringArray, _>(arrays)),
+                ArrowDataType::Binary => Series::new(name.into(), values::<BinaryArray, _>(arrays)),
+                data_type => bail!(
+                    "Cannot convert column {} of type {} to polars",
+                    name,
+                    data_type
+                ),
+            };
+            Ok(Column::from(series))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(PolarsDataFrame::new(columns)?)
+}
+
+/// Copy the columns of a polars DataFrame into an Arrow batch. Columns of polars types
+/// that rq has no type for are not supported.
+pub fn from_polars(df: &PolarsDataFrame) -> Result<ArrowRecordBatch> {
+    if df.width() == 0 {
+        bail!("Cannot read a polars DataFrame without columns");
+    }
+    let mut fields = Vec::with_capacity(df.width());
+    let mut columns = Vec::with_capacity(df.width());
+    for column in df.get_columns() {
+        let series = column.as_materialized_series();
+        let array: ArrowArrayRef = match series.dtype() {
+            PolarsDataType::Boolean => {
+                Arc::new(series.bool()?.into_iter().collect::<BooleanArray>())
+            }
+            PolarsDataType::Int32 => Arc::new(series.i32()?.into_iter().collect::<Int32Array>()),
+            PolarsDataType::Int64 => Arc::new(series.i64()?.into_iter().collect::<Int64Array>()),
+            PolarsDataType::Float32 => {
+                Arc::new(series.f32()?.into_iter().collect::<Float32Array>())
+            }
+            PolarsDataType::Float64 => {
+                Arc::new(series.f64()?.into_iter().collect::<Float64Array>())
+            }
+            PolarsDataType::String => Arc::new(series.str()?.into_iter().collect::<StringArray>()),
+            PolarsDataType::Binary => {
+                Arc::new(series.binary()?.into_iter().collect::<BinaryArray>())
+            }
+            data_type => bail!(
+                "Cannot read column {} of polars type {}",
+                series.name(),
+                data_type
+            ),
+        };
+        let nullable = series.null_count() > 0;
+        fields.push(ArrowField::new(
+            series.name(),
+            array.data_type().clone(),
+            nullable,
+        ));
+        columns.push(array);
+    }
+    Ok(ArrowRecordBatch::try_new(
+        Arc::new(ArrowSchema::new(fields)),
+        columns,
+    )?)
+}
+
+/// Collect the values of the arrays, which are all of type `A`, in order.
+fn values<'a, A, T>(arrays: impl Iterator<Item = &'a dyn std::any::Any>) -> Vec<Option<T>>
+where
+    A: 'static,
+    &'a A: IntoIterator<Item = Option<T>>,
+{
+    arrays
+        .flat_map(|array| array.downcast_ref::<A>().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_polars, to_polars};
+
+    use polars::prelude::{df, NamedFrom, Series};
+
+    #[test]
+    fn test_polars_round_trip() {
+        let df = df!(
+            "id" => [Some(1_i64), None, Some(3)],
+            "name" => ["a", "b", "c"],
+            "score" => [0.5_f64, 1.5, 2.5],
+            "flag" => [Some(true), Some(false), None]
+        )
+        .unwrap();
+        let batch = from_polars(&df).unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert!(batch.schema().field(0).is_nullable());
+        assert!(!batch.schema().field(1).is_nullable());
+
+        // The batches are concatenated into one chunk per column.
+        let batches = [batch.slice(0, 2), batch.slice(2, 1)];
+        let round_trip = to_polars(batch.schema().as_ref(), &batches).unwrap();
+        assert!(round_trip.equals_missing(&df));
+
+        let empty = to_polars(batch.schema().as_ref(), &[]).unwrap();
+        assert_eq!(empty.shape(), (0, 4));
+
+        let df = df!("bytes" => Series::new("bytes".into(), [b"ab".as_ref(), b""])).unwrap();
+        let batch = from_polars(&df).unwrap();
+        assert!(to_polars(batch.schema().as_ref(), &[batch])
+            .unwrap()
+            .equals(&df));
+    }
+}
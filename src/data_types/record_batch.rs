@@ -1,7 +1,19 @@
+use std::{ops::Range, sync::Arc};
+
 use super::{
+    arrow_field_array::ArrowFieldArray,
     column_array::{ArrayRef, ColumnArray},
     schema::Schema,
 };
+use crate::physical_plan::expr::to_arrow_array;
+
+use anyhow::{bail, Result};
+use arrow::{
+    array::ArrayData,
+    datatypes::{DataType as ArrowDataType, Schema as ArrowSchema},
+    record_batch::RecordBatch as ArrowRecordBatch,
+    util::bit_util,
+};
 
 #[derive(Clone)]
 pub struct RecordBatch {
@@ -26,11 +38,169 @@ impl RecordBatch {
     pub fn column_count(&self) -> usize {
         self.fields.len()
     }
+
+    /// Return the rows from `offset`, `length` of them, sharing the buffers of the columns.
+    pub fn slice(&self, offset: usize, length: usize) -> Result<RecordBatch> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| {
+                let sliced = to_arrow_array(f)?.slice(offset, length);
+                Ok(Arc::new(ArrowFieldArray::new(Box::new(sliced))) as ArrayRef)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(self.schema.clone(), fields))
+    }
+
+    /// Return the number of bytes used by the columns.
+    pub fn memory_size(&self) -> usize {
+        self.fields.iter().map(|f| f.memory_size()).sum()
+    }
+
+    /// Compute a CRC32 checksum over the types, validity and values of all columns.
+    /// The Arrow buffers of the columns are hashed directly, a run of valid rows at a time,
+    /// as the values under nulls are unspecified.
+    pub fn checksum(&self) -> Result<u32> {
+        let mut hasher = crc32fast::Hasher::new();
+        for field in &self.fields {
+            hasher.update(field.get_type().to_string().as_bytes());
+            update_checksum(&mut hasher, to_arrow_array(field)?.data());
+        }
+
+        Ok(hasher.finalize())
+    }
+
+    /// Verify the batch against a checksum computed by [`RecordBatch::checksum`].
+    pub fn verify_checksum(&self, expected: u32) -> Result<()> {
+        let actual = self.checksum()?;
+        if actual != expected {
+            bail!(
+                "Checksum mismatch: expected {:#010x}, got {:#010x}",
+                expected,
+                actual
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Wrap the columns of an Arrow batch without copying them.
+impl TryFrom<&ArrowRecordBatch> for RecordBatch {
+    type Error = anyhow::Error;
+
+    fn try_from(batch: &ArrowRecordBatch) -> Result<Self> {
+        let schema = batch.schema().as_ref().try_into()?;
+        let fields = batch
+            .columns()
+            .iter()
+            .map(|column| Arc::new(ArrowFieldArray::new(Box::new(column.clone()))) as ArrayRef)
+            .collect();
+        Ok(Self::new(schema, fields))
+    }
+}
+
+/// Share the buffers of the columns with an Arrow batch.
+/// Only literal columns are copied, as they have no Arrow array.
+impl TryFrom<&RecordBatch> for ArrowRecordBatch {
+    type Error = anyhow::Error;
+
+    fn try_from(batch: &RecordBatch) -> Result<Self> {
+        let schema: ArrowSchema = batch.schema.clone().into();
+        let columns = batch
+            .fields
+            .iter()
+            .map(to_arrow_array)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ArrowRecordBatch::try_new(Arc::new(schema), columns)?)
+    }
+}
+
+/// Hash the bounds and the values of the runs of valid rows of the array.
+fn update_checksum(hasher: &mut crc32fast::Hasher, data: &ArrayData) {
+    hasher.update(&(data.len() as u64).to_le_bytes());
+    for run in valid_runs(data) {
+        hasher.update(&(run.start as u64).to_le_bytes());
+        hasher.update(&(run.end as u64).to_le_bytes());
+        match data.data_type() {
+            ArrowDataType::Boolean => {
+                let values = data.buffers()[0].as_slice();
+                let bits = run
+                    .map(|i| bit_util::get_bit(values, data.offset() + i) as u8)
+                    .collect::<Vec<_>>();
+                hasher.update(&bits);
+            }
+            ArrowDataType::Int32 | ArrowDataType::Float32 => {
+                hasher.update(value_bytes(data, run, 4));
+            }
+            ArrowDataType::Int64 | ArrowDataType::Float64 => {
+                hasher.update(value_bytes(data, run, 8));
+            }
+            ArrowDataType::Utf8 | ArrowDataType::Binary => {
+                let values = value_offset(data, run.start)..value_offset(data, run.end);
+                update_lengths(hasher, data, run);
+                hasher.update(&data.buffers()[1].as_slice()[values]);
+            }
+            ArrowDataType::List(_) => {
+                let values = value_offset(data, run.start)..value_offset(data, run.end);
+                update_lengths(hasher, data, run);
+                let items = data.child_data()[0].slice(values.start, values.len());
+                update_checksum(hasher, &items);
+            }
+            ArrowDataType::Struct(_) => {
+                for child in data.child_data() {
+                    update_checksum(hasher, &child.slice(run.start, run.len()));
+                }
+            }
+            data_type => unreachable!("{} is not the type of a column", data_type),
+        }
+    }
+}
+
+/// Return the runs of consecutive valid rows of the array.
+fn valid_runs(data: &ArrayData) -> Vec<Range<usize>> {
+    if data.null_count() == 0 {
+        return std::iter::once(0..data.len()).collect();
+    }
+    let mut runs = vec![];
+    let mut start = None;
+    for i in 0..data.len() {
+        match (data.is_valid(i), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    runs.extend(start.map(|s| s..data.len()));
+    runs
+}
+
+/// Return the bytes of the values of the rows of a fixed-width array.
+fn value_bytes(data: &ArrayData, rows: Range<usize>, width: usize) -> &[u8] {
+    let start = (data.offset() + rows.start) * width;
+    &data.buffers()[0].as_slice()[start..start + rows.len() * width]
+}
+
+/// Return the offset of the value of a row of a variable-width array in its values.
+fn value_offset(data: &ArrayData, i: usize) -> usize {
+    let offsets = value_bytes(data, i..i + 1, 4);
+    i32::from_le_bytes(offsets.try_into().unwrap()) as usize
+}
+
+/// Hash the lengths of the values of the rows of a variable-width array.
+fn update_lengths(hasher: &mut crc32fast::Hasher, data: &ArrayData, rows: Range<usize>) {
+    let lengths = rows
+        .flat_map(|i| ((value_offset(data, i + 1) - value_offset(data, i)) as u32).to_le_bytes())
+        .collect::<Vec<_>>();
+    hasher.update(&lengths);
 }
 
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
+    use std::sync::Arc;
 
     use super::*;
     use crate::data_types::{
@@ -38,12 +208,16 @@ mod tests {
         literal_value_array::LiteralValueArray, schema::Field,
     };
 
-    use arrow::array::Int32Array;
+    use arrow::{
+        array::{Array, ArrayData, BooleanArray, Int32Array, StringArray},
+        buffer::Buffer,
+        datatypes::DataType as ArrowDataType,
+    };
 
     #[test]
     fn test_new_with_arrow_field_array() {
         let id = Int32Array::from(vec![1, 2, 3, 4, 5]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
         let _ = RecordBatch::new(schema, id_arrary);
     }
@@ -51,7 +225,7 @@ mod tests {
     #[test]
     fn test_new_with_literal_value_array() {
         let id_arrary = LiteralValueArray::new(DataType::Int32, 1, 1);
-        let id_arrary = vec![Rc::new(id_arrary) as ArrayRef];
+        let id_arrary = vec![Arc::new(id_arrary) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
         let _ = RecordBatch::new(schema, id_arrary);
     }
@@ -59,7 +233,7 @@ mod tests {
     #[test]
     fn test_field() {
         let id = Int32Array::from(vec![1, 2, 3, 4, 5]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
         let batch = RecordBatch::new(schema, id_arrary);
         let field = batch.field(0);
@@ -90,7 +264,7 @@ mod tests {
     #[test]
     fn test_row_count() {
         let id = Int32Array::from(vec![1, 2, 3, 4, 5]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
         let batch = RecordBatch::new(schema, id_arrary);
         assert_eq!(batch.row_count(), 5);
@@ -99,9 +273,117 @@ mod tests {
     #[test]
     fn test_column_count() {
         let id = Int32Array::from(vec![1, 2, 3, 4, 5]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
         let batch = RecordBatch::new(schema, id_arrary);
         assert_eq!(batch.column_count(), 1);
     }
+
+    #[test]
+    fn test_checksum() {
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
+        let id = Int32Array::from(vec![1, 2, 3]);
+        let batch = RecordBatch::new(
+            schema.clone(),
+            vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef],
+        );
+        let checksum = batch.checksum().unwrap();
+        assert!(batch.verify_checksum(checksum).is_ok());
+
+        let id = Int32Array::from(vec![1, 2, 4]);
+        let corrupted = RecordBatch::new(
+            schema.clone(),
+            vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef],
+        );
+        assert_ne!(corrupted.checksum().unwrap(), checksum);
+        assert!(corrupted.verify_checksum(checksum).is_err());
+
+        // Nulls are hashed by their mask, whatever value is stored under them.
+        let nulls = |id: Int32Array| {
+            let batch = RecordBatch::new(
+                schema.clone(),
+                vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef],
+            );
+            batch.checksum().unwrap()
+        };
+        let null = nulls(Int32Array::from(vec![Some(1), None, Some(3)]));
+        assert_ne!(null, checksum);
+        assert_ne!(
+            null,
+            nulls(Int32Array::from(vec![Some(1), Some(0), Some(3)]))
+        );
+        let masked = ArrayData::builder(ArrowDataType::Int32)
+            .len(3)
+            .add_buffer(Buffer::from_slice_ref(&[1_i32, 2, 3]))
+            .null_bit_buffer(Buffer::from([0b101_u8]))
+            .build()
+            .unwrap();
+        assert_eq!(nulls(Int32Array::from(masked)), null);
+    }
+
+    #[test]
+    fn test_checksum_of_slices() {
+        let schema = Schema::new(vec![
+            Field::new("name".to_string(), DataType::Utf8),
+            Field::new("flag".to_string(), DataType::Boolean),
+        ]);
+        let batch = |names: Vec<Option<&str>>, flags: Vec<Option<bool>>| {
+            RecordBatch::new(
+                schema.clone(),
+                vec![
+                    Arc::new(ArrowFieldArray::new(Box::new(StringArray::from(names)))) as ArrayRef,
+                    Arc::new(ArrowFieldArray::new(Box::new(BooleanArray::from(flags)))) as ArrayRef,
+                ],
+            )
+        };
+        let full = batch(
+            vec![Some("a"), Some("bc"), None, Some("d"), Some("ef")],
+            vec![Some(true), None, Some(false), Some(true), Some(false)],
+        );
+        // A slice shares the buffers of the batch and hashes as a copy of its rows.
+        let sliced = full.slice(1, 3).unwrap();
+        let copied = batch(
+            vec![Some("bc"), None, Some("d")],
+            vec![None, Some(false), Some(true)],
+        );
+        assert_eq!(sliced.checksum().unwrap(), copied.checksum().unwrap());
+        // Moving bytes between two values changes the checksum.
+        let moved = batch(
+            vec![Some("b"), None, Some("cd")],
+            vec![None, Some(false), Some(true)],
+        );
+        assert_ne!(moved.checksum().unwrap(), copied.checksum().unwrap());
+    }
+
+    #[test]
+    fn test_arrow_record_batch_conversion() {
+        let schema = Schema::new(vec![
+            Field::new("id".to_string(), DataType::Int32).with_nullable(false),
+            Field::new("one".to_string(), DataType::Int32),
+        ]);
+        let id = Int32Array::from(vec![1, 2, 3]);
+        let batch = RecordBatch::new(
+            schema.clone(),
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef,
+                Arc::new(LiteralValueArray::new(DataType::Int32, 1, 3)) as ArrayRef,
+            ],
+        );
+        let buffer = batch.field(0).as_arrow().unwrap().data().buffers()[0].as_ptr();
+
+        let arrow_batch = ArrowRecordBatch::try_from(&batch).unwrap();
+        assert_eq!(arrow_batch.num_rows(), 3);
+        assert!(!arrow_batch.schema().field(0).is_nullable());
+        assert_eq!(arrow_batch.column(0).data().buffers()[0].as_ptr(), buffer);
+
+        let batch = RecordBatch::try_from(&arrow_batch).unwrap();
+        assert_eq!(batch.schema, schema);
+        let one = batch.field(1);
+        assert!(one.as_arrow().is_some());
+        assert_eq!(one.get_value(2).unwrap().downcast_ref::<i32>().unwrap(), &1);
+        assert_eq!(
+            batch.field(0).as_arrow().unwrap().data().buffers()[0].as_ptr(),
+            buffer
+        );
+    }
 }
@@ -1,27 +1,89 @@
-use arrow::datatypes::{Field as ArrowField, Schema as ArrowSchema};
+use std::collections::BTreeMap;
+
+use super::{column_array::DataType, extension::ExtensionRef};
 
-use super::column_array::DataType;
+use anyhow::Result;
+use arrow::datatypes::{Field as ArrowField, Schema as ArrowSchema};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Field {
     pub name: String,
     pub data_type: DataType,
+    /// Whether the field may contain nulls.
+    pub nullable: bool,
+    /// The extension type of the field, if any.
+    /// The data type is then the storage type of the extension.
+    pub extension: Option<ExtensionRef>,
 }
 
 impl Field {
     pub fn new(name: String, data_type: DataType) -> Self {
-        Self { name, data_type }
+        Self {
+            name,
+            data_type,
+            nullable: true,
+            extension: None,
+        }
+    }
+
+    pub fn with_nullable(mut self, nullable: bool) -> Self {
+        self.nullable = nullable;
+        self
+    }
+
+    pub fn new_extension(name: String, extension: ExtensionRef) -> Self {
+        Self {
+            name,
+            data_type: extension.storage_type(),
+            nullable: true,
+            extension: Some(extension),
+        }
     }
 }
 
+/// The metadata key of the Arrow field holding the name of its extension type.
+const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+
+/// The name of the extension type is kept in the metadata of the Arrow field.
 impl From<Field> for ArrowField {
     fn from(field: Field) -> Self {
-        ArrowField::new(field.name.as_str(), field.data_type.into(), false)
+        let arrow_field =
+            ArrowField::new(field.name.as_str(), field.data_type.into(), field.nullable);
+        match field.extension {
+            Some(extension) => arrow_field.with_metadata(Some(BTreeMap::from([(
+                EXTENSION_NAME_KEY.to_string(),
+                extension.name().to_string(),
+            )]))),
+            None => arrow_field,
+        }
+    }
+}
+
+/// The built-in extension types are restored from the metadata of the Arrow field.
+/// Fields of other extension types keep the storage type.
+impl TryFrom<&ArrowField> for Field {
+    type Error = anyhow::Error;
+
+    fn try_from(field: &ArrowField) -> Result<Self> {
+        let data_type = field.data_type().try_into()?;
+        let extension = field
+            .metadata()
+            .as_ref()
+            .and_then(|metadata| metadata.get(EXTENSION_NAME_KEY))
+            .and_then(|name| ExtensionRef::builtin(name))
+            .filter(|extension| extension.storage_type() == data_type);
+        Ok(Field {
+            name: field.name().clone(),
+            data_type,
+            nullable: field.is_nullable(),
+            extension,
+        })
     }
 }
 
 /// A schema is a list of fields.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Schema {
     pub fields: Vec<Field>,
 }
@@ -53,9 +115,24 @@ impl From<Schema> for ArrowSchema {
     }
 }
 
+impl TryFrom<&ArrowSchema> for Schema {
+    type Error = anyhow::Error;
+
+    fn try_from(schema: &ArrowSchema) -> Result<Self> {
+        Ok(Schema::new(
+            schema
+                .fields()
+                .iter()
+                .map(Field::try_from)
+                .collect::<Result<_>>()?,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data_types::{extension::tests::Currency, inet::Inet, point::Point};
 
     #[test]
     fn test_select() {
@@ -67,4 +144,32 @@ mod tests {
         assert_eq!(selected_schema.fields.len(), 1);
         assert_eq!(selected_schema.fields[0].name, "id");
     }
+
+    #[test]
+    fn test_arrow_round_trip() {
+        let schema = Schema::new(vec![
+            Field::new("id".to_string(), DataType::Int32).with_nullable(false),
+            Field::new_extension("addr".to_string(), ExtensionRef::new(Inet)),
+            Field::new_extension("location".to_string(), ExtensionRef::new(Point)),
+        ]);
+        let arrow_schema: ArrowSchema = schema.clone().into();
+        assert_eq!(
+            arrow_schema.field(1).metadata().as_ref().unwrap()[EXTENSION_NAME_KEY],
+            "inet"
+        );
+        let round_trip = Schema::try_from(&arrow_schema).unwrap();
+        assert_eq!(round_trip, schema);
+        assert!(round_trip.fields[0].extension.is_none());
+        assert_eq!(
+            round_trip.fields[2].extension.as_ref().unwrap().name(),
+            "point"
+        );
+
+        // Other extension types keep their storage type.
+        let field = Field::new_extension("price".to_string(), ExtensionRef::new(Currency));
+        let arrow_field: ArrowField = field.into();
+        let field = Field::try_from(&arrow_field).unwrap();
+        assert_eq!(field.data_type, DataType::Int64);
+        assert!(field.extension.is_none());
+    }
 }
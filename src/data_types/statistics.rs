@@ -0,0 +1,35 @@
+/// Estimated statistics of a relation, used by the optimizer to compare plans.
+/// `None` means the value is unknown.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Statistics {
+    pub num_rows: Option<usize>,
+    /// The statistics of every column, in schema order.
+    pub column_statistics: Vec<ColumnStatistics>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ColumnStatistics {
+    /// The number of distinct non-null values.
+    pub distinct_count: Option<usize>,
+}
+
+impl Statistics {
+    pub fn new(num_rows: Option<usize>, column_statistics: Vec<ColumnStatistics>) -> Self {
+        Self {
+            num_rows,
+            column_statistics,
+        }
+    }
+
+    /// Statistics of a relation with `column_count` columns about which nothing is known.
+    pub fn unknown(column_count: usize) -> Self {
+        Self::new(None, vec![ColumnStatistics::default(); column_count])
+    }
+
+    /// Return the distinct count of the column at `index`, if it is known.
+    pub fn distinct_count(&self, index: usize) -> Option<usize> {
+        self.column_statistics
+            .get(index)
+            .and_then(|c| c.distinct_count)
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt::Display;
+
+use crate::data_types::schema::Schema;
+
+/// A Graphviz graph of a plan tree, with a node per operator and an edge from every
+/// operator to each of its inputs.
+pub(crate) struct DotGraph {
+    nodes: Vec<Vec<Vec<String>>>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl DotGraph {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![],
+            edges: vec![],
+        }
+    }
+
+    /// Add a node, returning its id. The label is split into sections of lines,
+    /// which are shown one under the other.
+    pub fn add_node(&mut self, sections: Vec<Vec<String>>) -> usize {
+        self.nodes.push(sections);
+        self.nodes.len() - 1
+    }
+
+    pub fn add_edge(&mut self, from: usize, to: usize) {
+        self.edges.push((from, to));
+    }
+}
+
+impl Display for DotGraph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "digraph plan {{")?;
+        writeln!(f, "  node [shape=record, fontname=\"monospace\"];")?;
+        for (id, sections) in self.nodes.iter().enumerate() {
+            let label = sections
+                .iter()
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .map(|line| format!("{}\\l", escape(line)))
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("|");
+            writeln!(f, "  {} [label=\"{{{}}}\"];", id, label)?;
+        }
+        for (from, to) in &self.edges {
+            writeln!(f, "  {} -> {};", from, to)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Return a line per field of the schema, with its name and type.
+pub(crate) fn schema_lines(schema: &Schema) -> Vec<String> {
+    schema
+        .fields
+        .iter()
+        .map(|field| match &field.extension {
+            Some(extension) => format!("{}: {}", field.name, extension.name()),
+            None => format!("{}: {}", field.name, field.data_type),
+        })
+        .collect()
+}
+
+/// Escape the characters that are special in the labels of record nodes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '"' | '{' | '}' | '|' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\l"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DotGraph;
+
+    #[test]
+    fn test_dot_graph() {
+        let mut graph = DotGraph::new();
+        let root = graph.add_node(vec![vec!["Limit: 10".to_string()]]);
+        let input = graph.add_node(vec![
+            vec!["Selection: #a <> \"x|y\"".to_string()],
+            vec!["a: Utf8".to_string(), "b: Int64".to_string()],
+        ]);
+        graph.add_edge(root, input);
+        assert_eq!(
+            graph.to_string(),
+            "digraph plan {\n  \
+             node [shape=record, fontname=\"monospace\"];\n  \
+             0 [label=\"{Limit: 10\\l}\"];\n  \
+             1 [label=\"{Selection: #a \\<\\> \\\"x\\|y\\\"\\l|a: Utf8\\lb: Int64\\l}\"];\n  \
+             0 -> 1;\n\
+             }"
+        );
+    }
+}
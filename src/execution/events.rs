@@ -0,0 +1,82 @@
+use std::{fmt::Display, time::Duration};
+
+/// Lifecycle events emitted while a query is planned and executed.
+/// The events of a query carry its id, unique in the `ExecutionContext` running it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryEvent {
+    /// The logical plan is about to be optimized and planned.
+    PlanningStarted { query_id: u64, logical_plan: String },
+    /// The physical plan has been created.
+    PlanningFinished {
+        query_id: u64,
+        physical_plan: String,
+    },
+    /// A partition of an operator of the physical plan started executing.
+    OperatorStarted {
+        query_id: u64,
+        operator: String,
+        depth: usize,
+        partition: usize,
+    },
+    /// The query produced an output batch.
+    BatchProduced {
+        query_id: u64,
+        rows: usize,
+        columns: usize,
+    },
+    /// The query finished. The peak memory is the most bytes its operators
+    /// buffered at the same time.
+    QueryFinished {
+        query_id: u64,
+        batches: usize,
+        rows: usize,
+        elapsed: Duration,
+        peak_memory: usize,
+    },
+    /// The query failed while it was planned or executed.
+    QueryFailed { query_id: u64, error: String },
+}
+
+impl QueryEvent {
+    pub fn query_id(&self) -> u64 {
+        match self {
+            QueryEvent::PlanningStarted { query_id, .. }
+            | QueryEvent::PlanningFinished { query_id, .. }
+            | QueryEvent::OperatorStarted { query_id, .. }
+            | QueryEvent::BatchProduced { query_id, .. }
+            | QueryEvent::QueryFinished { query_id, .. }
+            | QueryEvent::QueryFailed { query_id, .. } => *query_id,
+        }
+    }
+}
+
+impl Display for QueryEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryEvent::PlanningStarted { .. } => write!(f, "PlanningStarted"),
+            QueryEvent::PlanningFinished { .. } => write!(f, "PlanningFinished"),
+            QueryEvent::OperatorStarted {
+                operator,
+                depth,
+                partition,
+                ..
+            } => write!(
+                f,
+                "OperatorStarted: {} (depth={}, partition={})",
+                operator, depth, partition
+            ),
+            QueryEvent::BatchProduced { rows, columns, .. } => {
+                write!(f, "BatchProduced: rows={}, columns={}", rows, columns)
+            }
+            QueryEvent::QueryFinished { batches, rows, .. } => {
+                write!(f, "QueryFinished: batches={}, rows={}", batches, rows)
+            }
+            QueryEvent::QueryFailed { error, .. } => write!(f, "QueryFailed: {}", error),
+        }
+    }
+}
+
+/// Receives the lifecycle events of the queries run by an `ExecutionContext`.
+pub trait QueryEventSubscriber: Send + Sync {
+    fn on_event(&self, event: &QueryEvent);
+}
@@ -0,0 +1,291 @@
+use crate::logical_plan::{expr::Expr, plan::Plan};
+
+use anyhow::{bail, Result};
+
+/// The default limit on the depth of the logical plan tree.
+/// Planning and executing recurse over the plan, so deeper plans may overflow the stack.
+pub const DEFAULT_MAX_PLAN_DEPTH: usize = 64;
+
+/// The default limit on the depth of an expression tree.
+pub const DEFAULT_MAX_EXPR_DEPTH: usize = 128;
+
+/// Limits on the complexity of a query.
+/// They are checked against the logical plan before it is optimized and executed,
+/// so pathological queries can be rejected cheaply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// The maximum depth of the logical plan tree.
+    pub max_plan_depth: Option<usize>,
+    /// The maximum number of nodes in a single expression tree.
+    pub max_expr_size: Option<usize>,
+    /// The maximum depth of a single expression tree.
+    pub max_expr_depth: Option<usize>,
+    /// The maximum number of joins in a query.
+    pub max_joins: Option<usize>,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryLimits {
+    /// Create the default limits, on the depths of the plan and of the expressions.
+    pub fn new() -> Self {
+        Self {
+            max_plan_depth: Some(DEFAULT_MAX_PLAN_DEPTH),
+            max_expr_size: None,
+            max_expr_depth: Some(DEFAULT_MAX_EXPR_DEPTH),
+            max_joins: None,
+        }
+    }
+
+    /// Set the maximum depth of the logical plan tree, or remove the limit.
+    pub fn with_max_plan_depth(mut self, max_plan_depth: Option<usize>) -> Self {
+        self.max_plan_depth = max_plan_depth;
+        self
+    }
+
+    /// Set the maximum number of nodes in a single expression tree, or remove the limit.
+    pub fn with_max_expr_size(mut self, max_expr_size: Option<usize>) -> Self {
+        self.max_expr_size = max_expr_size;
+        self
+    }
+
+    /// Set the maximum depth of a single expression tree, or remove the limit.
+    pub fn with_max_expr_depth(mut self, max_expr_depth: Option<usize>) -> Self {
+        self.max_expr_depth = max_expr_depth;
+        self
+    }
+
+    /// Set the maximum number of joins in a query, or remove the limit.
+    pub fn with_max_joins(mut self, max_joins: Option<usize>) -> Self {
+        self.max_joins = max_joins;
+        self
+    }
+
+    /// Check the plan against the limits.
+    /// The checks don't recurse, so they reject plans too deep to be planned.
+    pub fn check(&self, plan: &Plan) -> Result<()> {
+        if let Some(max_plan_depth) = self.max_plan_depth {
+            let depth = plan_depth(plan);
+            if depth > max_plan_depth {
+                bail!(
+                    "Plan depth {} exceeds the limit of {}",
+                    depth,
+                    max_plan_depth
+                );
+            }
+        }
+        if let Some(max_joins) = self.max_joins {
+            let joins = plan_joins(plan);
+            if joins > max_joins {
+                bail!(
+                    "Query has {} joins, which exceeds the limit of {}",
+                    joins,
+                    max_joins
+                );
+            }
+        }
+        let exprs = plan_exprs(plan);
+        if let Some(max_expr_depth) = self.max_expr_depth {
+            // The expression is not shown, as writing it recurses.
+            if let Some(depth) = exprs.iter().map(|e| expr_depth(e)).max() {
+                if depth > max_expr_depth {
+                    bail!(
+                        "Expression depth {} exceeds the limit of {}",
+                        depth,
+                        max_expr_depth
+                    );
+                }
+            }
+        }
+        if let Some(max_expr_size) = self.max_expr_size {
+            for expr in exprs {
+                let size = expr_size(expr);
+                if size > max_expr_size {
+                    bail!(
+                        "Expression {} has {} nodes, which exceeds the limit of {}",
+                        expr,
+                        size,
+                        max_expr_size
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Return the depth of the plan tree.
+fn plan_depth(plan: &Plan) -> usize {
+    let mut max_depth = 0;
+    let mut stack = vec![(plan, 1)];
+    while let Some((plan, depth)) = stack.pop() {
+        max_depth = max_depth.max(depth);
+        stack.extend(plan.inputs().into_iter().map(|input| (input, depth + 1)));
+    }
+    max_depth
+}
+
+/// Return the number of joins in the plan tree.
+fn plan_joins(plan: &Plan) -> usize {
+    let mut joins = 0;
+    let mut stack = vec![plan];
+    while let Some(plan) = stack.pop() {
+        if matches!(plan, Plan::Join(_)) {
+            joins += 1;
+        }
+        stack.extend(plan.inputs());
+    }
+    joins
+}
+
+/// Return the expressions of all the nodes of the plan.
+fn plan_exprs(plan: &Plan) -> Vec<&Expr> {
+    let mut exprs = vec![];
+    let mut stack = vec![plan];
+    while let Some(plan) = stack.pop() {
+        exprs.extend(plan.exprs());
+        stack.extend(plan.inputs());
+    }
+    exprs
+}
+
+/// Return the depth of the expression tree.
+fn expr_depth(expr: &Expr) -> usize {
+    let mut max_depth = 0;
+    let mut stack = vec![(expr, 1)];
+    while let Some((expr, depth)) = stack.pop() {
+        max_depth = max_depth.max(depth);
+        stack.extend(expr.children().into_iter().map(|child| (child, depth + 1)));
+    }
+    max_depth
+}
+
+/// Return the number of nodes in the expression tree.
+fn expr_size(expr: &Expr) -> usize {
+    let mut size = 0;
+    let mut stack = vec![expr];
+    while let Some(expr) = stack.pop() {
+        size += 1;
+        stack.extend(expr.children());
+    }
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logical_plan::{
+            data_frame::DataFrame,
+            expr_fn::{col, lit},
+            scan::Scan,
+        },
+        test_util::get_primitive_field_data_source,
+    };
+
+    fn csv() -> DataFrame {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        DataFrame::new(Plan::Scan(Scan::new(path, csv_data_source, vec![])))
+    }
+
+    #[test]
+    fn test_plan_depth() {
+        let df = csv().filter(col("c1").eq(lit(1))).project(vec![col("c1")]);
+        assert_eq!(plan_depth(&df.logical_plan()), 3);
+    }
+
+    #[test]
+    fn test_expr_size() {
+        assert_eq!(expr_size(&col("c1")), 1);
+        assert_eq!(expr_size(&(col("c1") + lit(1)).eq(lit(2))), 5);
+    }
+
+    #[test]
+    fn test_check_default() {
+        let filtered = |filters| (0..filters).fold(csv(), |df, _| df.filter(col("c1").eq(lit(1))));
+        let limits = QueryLimits::default();
+        assert!(limits.check(&filtered(63).logical_plan()).is_ok());
+        assert_eq!(
+            limits
+                .check(&filtered(100).logical_plan())
+                .unwrap_err()
+                .to_string(),
+            "Plan depth 101 exceeds the limit of 64"
+        );
+    }
+
+    #[test]
+    fn test_check_unlimited() {
+        let limits = QueryLimits::new()
+            .with_max_plan_depth(None)
+            .with_max_expr_size(None)
+            .with_max_expr_depth(None);
+        let predicate = (0..200).fold(col("c1").eq(lit(1)), |e, i| e.or(col("c1").eq(lit(i))));
+        let df = (0..100).fold(csv(), |df, _| df.filter(predicate.clone()));
+        assert!(limits.check(&df.logical_plan()).is_ok());
+    }
+
+    #[test]
+    fn test_check_plan_depth() {
+        let df = csv().filter(col("c1").eq(lit(1))).project(vec![col("c1")]);
+        let limits = QueryLimits::new().with_max_plan_depth(Some(2));
+        assert_eq!(
+            limits.check(&df.logical_plan()).unwrap_err().to_string(),
+            "Plan depth 3 exceeds the limit of 2"
+        );
+        let limits = QueryLimits::new().with_max_plan_depth(Some(3));
+        assert!(limits.check(&df.logical_plan()).is_ok());
+    }
+
+    #[test]
+    fn test_check_joins() {
+        let right = |name: &str| csv().project(vec![col("c1").alias(name.to_string())]);
+        let df = csv()
+            .join(&right("a"), vec![(col("c1"), col("a"))])
+            .join(&right("b"), vec![(col("c1"), col("b"))]);
+        assert_eq!(plan_joins(&df.logical_plan()), 2);
+        assert!(QueryLimits::default().check(&df.logical_plan()).is_ok());
+        let limits = QueryLimits::new().with_max_joins(Some(1));
+        assert_eq!(
+            limits.check(&df.logical_plan()).unwrap_err().to_string(),
+            "Query has 2 joins, which exceeds the limit of 1"
+        );
+        let limits = limits.with_max_joins(Some(2));
+        assert!(limits.check(&df.logical_plan()).is_ok());
+    }
+
+    #[test]
+    fn test_expr_depth() {
+        assert_eq!(expr_depth(&col("c1")), 1);
+        assert_eq!(expr_depth(&(col("c1") + lit(1)).eq(lit(2))), 3);
+    }
+
+    #[test]
+    fn test_check_expr_depth() {
+        let df = csv().filter((col("c1") + lit(1)).eq(lit(2)));
+        let limits = QueryLimits::default().with_max_expr_depth(Some(2));
+        assert_eq!(
+            limits.check(&df.logical_plan()).unwrap_err().to_string(),
+            "Expression depth 3 exceeds the limit of 2"
+        );
+        let limits = limits.with_max_expr_depth(None);
+        assert!(limits.check(&df.logical_plan()).is_ok());
+    }
+
+    #[test]
+    fn test_check_expr_size() {
+        let df = csv().filter((col("c1") + lit(1)).eq(lit(2)));
+        let limits = QueryLimits::new().with_max_expr_size(Some(4));
+        assert_eq!(
+            limits.check(&df.logical_plan()).unwrap_err().to_string(),
+            "Expression #c1 + 1 = 2 has 5 nodes, which exceeds the limit of 4"
+        );
+        let limits = QueryLimits::new().with_max_expr_size(Some(5));
+        assert!(limits.check(&df.logical_plan()).is_ok());
+    }
+}
@@ -1,46 +1,909 @@
+pub mod events;
+pub mod limits;
+pub mod prepared_plan;
+pub mod query_template;
+pub mod session_config;
+pub mod table_function;
+
+use std::{
+    collections::HashMap,
+    io::{Read, Seek},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock, RwLock,
+    },
+    thread,
+};
+
+#[cfg(feature = "fs")]
+use self::table_function::ReadCsv;
+use self::{
+    events::{QueryEvent, QueryEventSubscriber},
+    limits::QueryLimits,
+    prepared_plan::PreparedPlan,
+    query_template::QueryTemplate,
+    session_config::SessionConfig,
+    table_function::{GenerateSeries, TableFunction},
+};
 use crate::{
-    data_source::{csv_data_source::CsvDataSource, Source},
-    data_types::schema::Schema,
-    logical_plan::{data_frame::DataFrame, plan::Plan as LogicalPlan, scan::Scan},
-    optimizer::Optimizer,
-    physical_plan::plan::Plan as PhysicalPlan,
+    analyzer::Analyzer,
+    data_source::{csv_data_source::CsvDataSource, DataSource as _, Source},
+    data_types::{
+        arrow_field_array::ArrowFieldArray, column_array::ArrayRef, record_batch::RecordBatch,
+        schema::Schema,
+    },
+    logical_plan::{
+        command::Command,
+        data_frame::DataFrame,
+        expr::{ColumnIndex, Expr, ScalarValue},
+        expr_fn::cast,
+        plan::{LogicalPlan as _, Plan as LogicalPlan},
+        scan::Scan,
+        udaf::AggregateUdf,
+    },
+    optimizer::{Optimizer, OptimizerConfig},
+    physical_plan::{
+        expr::{DivisionByZeroMode, OverflowMode},
+        memory::{MemoryBudget, MemoryPool},
+        metrics::Instant,
+        observed::OperatorHook,
+        pipeline::MorselScheduler,
+        plan::{PhysicalPlan as _, Plan as PhysicalPlan},
+    },
     query_planner::planner::QueryPlanner,
+    sql::planner::SqlPlanner,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use arrow::array::Int64Array;
+use futures::{
+    channel::mpsc,
+    executor::block_on,
+    stream::{self, BoxStream},
+    SinkExt, StreamExt,
+};
+use rayon::{prelude::*, ThreadPool};
 
+/// The context queries are planned and executed in, with the tables and functions
+/// registered in it.
+///
+/// A context is `Send + Sync`, so one context, e.g. in an `Arc`, can run queries from
+/// several threads at the same time while tables and functions are registered.
+/// A query sees the registrations made before its DataFrame was created.
+/// Every query has its own memory budget, and they all share the memory pool.
+/// Subscribers are called from the threads running the query.
 pub struct ExecutionContext {
     batch_size: usize,
+    limits: QueryLimits,
+    optimizer_config: OptimizerConfig,
+    memory_limit: Option<usize>,
+    memory_pool: MemoryPool,
+    overflow_mode: OverflowMode,
+    division_by_zero: DivisionByZeroMode,
+    tables: RwLock<HashMap<String, Source>>,
+    views: RwLock<HashMap<String, LogicalPlan>>,
+    queries: RwLock<HashMap<String, Arc<QueryTemplate>>>,
+    udafs: RwLock<HashMap<String, AggregateUdf>>,
+    table_functions: RwLock<HashMap<String, Arc<dyn TableFunction>>>,
+    subscribers: RwLock<Vec<Arc<dyn QueryEventSubscriber>>>,
+    next_query_id: AtomicU64,
+    session_config: SessionConfig,
+    // Created from the session config the first time a plan has several partitions.
+    thread_pool: OnceLock<Arc<ThreadPool>>,
 }
 
 impl ExecutionContext {
     pub fn new(batch_size: usize) -> Self {
-        ExecutionContext { batch_size }
+        ExecutionContext {
+            batch_size,
+            limits: QueryLimits::default(),
+            optimizer_config: OptimizerConfig::default(),
+            memory_limit: None,
+            memory_pool: MemoryPool::default(),
+            overflow_mode: OverflowMode::default(),
+            division_by_zero: DivisionByZeroMode::default(),
+            tables: RwLock::default(),
+            views: RwLock::default(),
+            queries: RwLock::default(),
+            udafs: RwLock::default(),
+            table_functions: RwLock::new(HashMap::from([
+                (
+                    "generate_series".to_string(),
+                    Arc::new(GenerateSeries) as Arc<dyn TableFunction>,
+                ),
+                #[cfg(feature = "fs")]
+                (
+                    "read_csv".to_string(),
+                    Arc::new(ReadCsv) as Arc<dyn TableFunction>,
+                ),
+            ])),
+            subscribers: RwLock::default(),
+            next_query_id: AtomicU64::new(1),
+            session_config: SessionConfig::default(),
+            thread_pool: OnceLock::new(),
+        }
+    }
+
+    /// Set the complexity limits checked before a query is planned.
+    pub fn with_limits(mut self, limits: QueryLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the rules and the number of passes of the optimizer.
+    pub fn with_optimizer_config(mut self, optimizer_config: OptimizerConfig) -> Self {
+        self.optimizer_config = optimizer_config;
+        self
+    }
+
+    /// Limit the memory each query may use for the data buffered by its operators, such as
+    /// the groups of an aggregate. A query over the limit fails. By default there is no limit.
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Limit the memory all the queries of the context may use together.
+    /// By default there is no limit.
+    pub fn with_global_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_pool = MemoryPool::new(Some(bytes));
+        self
+    }
+
+    /// Return the pool the queries reserve their memory from, which reports
+    /// the memory in use and its peak.
+    pub fn memory_pool(&self) -> &MemoryPool {
+        &self.memory_pool
+    }
+
+    /// Set how integer arithmetic handles overflows. By default overflows fail the query.
+    pub fn with_overflow_mode(mut self, overflow_mode: OverflowMode) -> Self {
+        self.overflow_mode = overflow_mode;
+        self
+    }
+
+    /// Set what division and modulus by zero do. By default they fail the query.
+    pub fn with_division_by_zero(mut self, division_by_zero: DivisionByZeroMode) -> Self {
+        self.division_by_zero = division_by_zero;
+        self
+    }
+
+    /// Set the thread pool the partitions of the queries run on.
+    pub fn with_session_config(mut self, session_config: SessionConfig) -> Self {
+        self.session_config = session_config;
+        self.thread_pool = OnceLock::new();
+        self
     }
 
+    #[cfg(feature = "fs")]
     pub fn csv(&self, file_path: String, schema: Schema) -> DataFrame {
         let csv_data_source = CsvDataSource::new(file_path.clone(), schema, self.batch_size);
         let scan_plan = Scan::new(file_path, Source::Csv(csv_data_source), vec![]);
         DataFrame::new(LogicalPlan::Scan(scan_plan))
     }
 
+    /// Register a data source as a table under the given name.
+    /// A table registered under an existing name replaces the old one.
+    pub fn register_table(&self, name: &str, source: Source) {
+        self.tables
+            .write()
+            .unwrap()
+            .insert(name.to_string(), source);
+    }
+
+    /// Register a CSV file as a table under the given name.
+    #[cfg(feature = "fs")]
+    pub fn register_csv(&self, name: &str, file_path: String, schema: Schema) {
+        let csv_data_source = CsvDataSource::new(file_path, schema, self.batch_size);
+        self.register_table(name, Source::Csv(csv_data_source));
+    }
+
+    /// Register CSV data without a header held in memory as a table under the given name.
+    pub fn register_csv_bytes(&self, name: &str, bytes: impl Into<Arc<[u8]>>, schema: Schema) {
+        let csv_data_source = CsvDataSource::from_bytes(bytes, schema, self.batch_size);
+        self.register_table(name, Source::Csv(csv_data_source));
+    }
+
+    /// Create a DataFrame over a copy of the columns of a polars DataFrame.
+    #[cfg(feature = "polars")]
+    pub fn read_polars(&self, df: &polars::frame::DataFrame) -> Result<DataFrame> {
+        let batch = crate::data_types::polars::from_polars(df)?;
+        let batch = RecordBatch::try_from(&batch)?;
+        let data_source = crate::data_source::memory_data_source::MemoryDataSource::new(
+            batch.schema.clone(),
+            vec![batch],
+        );
+        let scan = Scan::new("polars".to_string(), Source::Mem(data_source), vec![]);
+        Ok(DataFrame::new(LogicalPlan::Scan(scan)))
+    }
+
+    /// Create a DataFrame over the batches of an Arrow IPC stream, read into memory.
+    /// The batches of a stream written by [`DataFrame::write_ipc`] are verified against
+    /// their checksums, so a corrupted batch is an error rather than wrong results.
+    pub fn read_ipc<R: Read + Seek>(&self, reader: R) -> Result<DataFrame> {
+        let data_source = crate::data_source::ipc::read_ipc(reader)?;
+        let scan = Scan::new("ipc".to_string(), Source::Mem(data_source), vec![]);
+        Ok(DataFrame::new(LogicalPlan::Scan(scan)))
+    }
+
+    /// Remove the table registered under the given name, returning its data source.
+    pub fn deregister_table(&self, name: &str) -> Option<Source> {
+        self.tables.write().unwrap().remove(name)
+    }
+
+    /// Return the names of the registered tables, in alphabetical order.
+    pub fn table_names(&self) -> Vec<String> {
+        let mut names = self
+            .tables
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Create a DataFrame that scans the table, or expands the view, registered under
+    /// the given name. A table hides a view of the same name.
+    pub fn table(&self, name: &str) -> Result<DataFrame> {
+        let source = self.tables.read().unwrap().get(name).cloned();
+        if let Some(source) = source {
+            let scan = Scan::new(name.to_string(), source, vec![]);
+            return Ok(DataFrame::new(LogicalPlan::Scan(scan)));
+        }
+        let view = self.views.read().unwrap().get(name).cloned();
+        match view {
+            Some(plan) => Ok(DataFrame::new(self.expand_view(&plan))),
+            None => Err(anyhow!("No table named {}", name)),
+        }
+    }
+
+    /// Append the rows of the DataFrame to the memory table registered under the given name,
+    /// returning the number of rows inserted. The columns of the DataFrame are inserted into
+    /// the columns of the table in order, and their types must be the types of the columns
+    /// or be widened to them without losing range.
+    pub fn insert_into(&self, name: &str, df: &DataFrame) -> Result<usize> {
+        let schema = match self.tables.read().unwrap().get(name) {
+            Some(Source::Mem(source)) => source.get_schema().clone(),
+            Some(_) => bail!(
+                "Only memory tables can be inserted into, {} is not one",
+                name
+            ),
+            None if self.views.read().unwrap().contains_key(name) => {
+                bail!(
+                    "Only memory tables can be inserted into, {} is a view",
+                    name
+                )
+            }
+            None => bail!("No table named {}", name),
+        };
+        let input = df.schema();
+        if input.fields.len() != schema.fields.len() {
+            bail!(
+                "Table {} has {} columns, but {} were inserted",
+                name,
+                schema.fields.len(),
+                input.fields.len()
+            );
+        }
+        let exprs = input
+            .fields
+            .iter()
+            .zip(&schema.fields)
+            .enumerate()
+            .map(|(index, (field, column))| {
+                let expr = Expr::ColumnIndex(ColumnIndex { index });
+                let expr = match field.data_type.common_type(&column.data_type) {
+                    _ if field.data_type == column.data_type => expr,
+                    Some(data_type) if data_type == column.data_type => cast(expr, data_type),
+                    _ => bail!(
+                        "Cannot insert {} values into column {} of type {}",
+                        field.data_type,
+                        column.name,
+                        column.data_type
+                    ),
+                };
+                Ok(expr.alias(column.name.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let batches = self
+            .execute(&df.project(exprs))?
+            .into_iter()
+            .map(|batch| {
+                for (field, array) in schema.fields.iter().zip(&batch.fields) {
+                    if !field.nullable && (0..array.size()).any(|i| array.is_null(i)) {
+                        bail!("Cannot insert nulls into column {}", field.name);
+                    }
+                }
+                Ok(RecordBatch::new(schema.clone(), batch.fields))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let rows = batches.iter().map(|batch| batch.row_count()).sum();
+        match self.tables.write().unwrap().get_mut(name) {
+            Some(Source::Mem(source)) if source.get_schema() == &schema => source.append(batches),
+            _ => bail!(
+                "Table {} was registered again while rows were inserted",
+                name
+            ),
+        }
+        Ok(rows)
+    }
+
+    /// Register the logical plan of the DataFrame as a view under the given name, which is
+    /// referred to like a table. A view registered under an existing name replaces the old one.
+    pub fn register_view(&self, name: &str, df: &DataFrame) {
+        self.views
+            .write()
+            .unwrap()
+            .insert(name.to_string(), df.logical_plan());
+    }
+
+    /// Remove the view registered under the given name, returning it.
+    pub fn deregister_view(&self, name: &str) -> Option<DataFrame> {
+        self.views.write().unwrap().remove(name).map(DataFrame::new)
+    }
+
+    /// Return the names of the registered views, in alphabetical order.
+    pub fn view_names(&self) -> Vec<String> {
+        let mut names = self
+            .views
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Scan the tables of a view with their current data sources, so the view reads the
+    /// data of tables registered again since it was created.
+    fn expand_view(&self, plan: &LogicalPlan) -> LogicalPlan {
+        match plan {
+            LogicalPlan::Scan(scan) => match self.tables.read().unwrap().get(&scan.path) {
+                Some(source) => LogicalPlan::Scan(Scan::new(
+                    scan.path.clone(),
+                    source.clone(),
+                    scan.projection.clone(),
+                )),
+                None => plan.clone(),
+            },
+            _ => plan.map_inputs(|input| self.expand_view(input)),
+        }
+    }
+
+    /// Plan a SQL query over the registered tables and functions. DDL statements and
+    /// inserts are applied when the DataFrame is executed. The errors are
+    /// [`SqlError`](crate::sql::error::SqlError)s, located in the SQL text when known.
+    pub fn sql(&self, sql: &str) -> Result<DataFrame> {
+        SqlPlanner::new(self).plan(sql)
+    }
+
+    /// Register a query template under the given name.
+    /// A template registered under an existing name replaces the old one.
+    pub fn register_query(&self, name: &str, template: QueryTemplate) {
+        self.queries
+            .write()
+            .unwrap()
+            .insert(name.to_string(), Arc::new(template));
+    }
+
+    /// Return true if a query template is registered under the given name.
+    pub fn has_query(&self, name: &str) -> bool {
+        self.queries.read().unwrap().contains_key(name)
+    }
+
+    /// Expand the query template registered under the given name.
+    pub fn query(&self, name: &str, args: Vec<Expr>) -> Result<DataFrame> {
+        // The template is expanded without the lock, as it may use the context.
+        let template = self
+            .queries
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No query named {}", name))?;
+        template.expand(&args)
+    }
+
+    /// Register a user-defined aggregate function under its name.
+    /// A function registered under an existing name replaces the old one.
+    pub fn register_udaf(&self, udaf: AggregateUdf) {
+        self.udafs
+            .write()
+            .unwrap()
+            .insert(udaf.name().to_string(), udaf);
+    }
+
+    /// Return the user-defined aggregate function registered under the given name.
+    pub fn udaf(&self, name: &str) -> Result<AggregateUdf> {
+        self.udafs
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No aggregate function named {}", name))
+    }
+
+    /// Register a table function under the given name.
+    /// A function registered under an existing name replaces the old one.
+    pub fn register_table_function(&self, name: &str, function: Arc<dyn TableFunction>) {
+        self.table_functions
+            .write()
+            .unwrap()
+            .insert(name.to_string(), function);
+    }
+
+    /// Create a DataFrame from the relation produced by the table function
+    /// registered under the given name. The arguments must be literals.
+    pub fn table_function(&self, name: &str, args: Vec<Expr>) -> Result<DataFrame> {
+        let function = self
+            .table_functions
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No table function named {}", name))?;
+        let args = args
+            .iter()
+            .map(|arg| match arg {
+                Expr::Literal(value) => Ok(value.clone()),
+                _ => Err(anyhow!(
+                    "Table function {} expects literal arguments, got {}",
+                    name,
+                    arg
+                )),
+            })
+            .collect::<Result<Vec<ScalarValue>>>()?;
+        let source = function.create_source(&args, self.batch_size)?;
+        let path = format!(
+            "{}({})",
+            name,
+            args.iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(DataFrame::new(LogicalPlan::Scan(Scan::new(
+            path,
+            source,
+            vec![],
+        ))))
+    }
+
+    /// Return the numbers from `start` to `stop` inclusive, `step` apart,
+    /// in a `value` column.
+    pub fn generate_series(&self, start: Expr, stop: Expr, step: Expr) -> Result<DataFrame> {
+        self.table_function("generate_series", vec![start, stop, step])
+    }
+
+    /// Subscribe to the lifecycle events of the queries run by this context.
+    pub fn subscribe(&self, subscriber: Arc<dyn QueryEventSubscriber>) {
+        self.subscribers.write().unwrap().push(subscriber);
+    }
+
+    fn emit(&self, event: QueryEvent) {
+        // The subscribers are called without the lock, so they may subscribe others.
+        let subscribers = self.subscribers.read().unwrap().clone();
+        subscribers.iter().for_each(|s| s.on_event(&event));
+    }
+
+    /// Emit a `QueryFailed` event if the query failed.
+    fn report_failure<T>(&self, query_id: u64, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result {
+            self.emit(QueryEvent::QueryFailed {
+                query_id,
+                error: e.to_string(),
+            });
+        }
+        result
+    }
+
+    /// Return a hook emitting an `OperatorStarted` event to the current subscribers,
+    /// or None if there are none.
+    fn operator_hook(&self, query_id: u64) -> Option<OperatorHook> {
+        let subscribers = self.subscribers.read().unwrap().clone();
+        if subscribers.is_empty() {
+            return None;
+        }
+        Some(OperatorHook::new(move |operator, depth, partition| {
+            let event = QueryEvent::OperatorStarted {
+                query_id,
+                operator: operator.to_string(),
+                depth,
+                partition,
+            };
+            subscribers.iter().for_each(|s| s.on_event(&event));
+        }))
+    }
+
     pub fn create_physical_plan(&self, df: &DataFrame) -> Result<PhysicalPlan> {
-        let optimized_plan = Optimizer::optimize(&df.logical_plan());
-        QueryPlanner::create_physical_plan(&optimized_plan)
+        let query_id = self.next_query_id.fetch_add(1, Ordering::Relaxed);
+        let physical_plan =
+            self.create_physical_plan_with_budget(query_id, df, self.memory_budget());
+        self.report_failure(query_id, physical_plan)
+    }
+
+    fn create_physical_plan_with_budget(
+        &self,
+        query_id: u64,
+        df: &DataFrame,
+        memory_budget: MemoryBudget,
+    ) -> Result<PhysicalPlan> {
+        let logical_plan = df.logical_plan();
+        // The plan is checked first, as everything that follows recurses over it.
+        self.limits.check(&logical_plan)?;
+        self.emit(QueryEvent::PlanningStarted {
+            query_id,
+            logical_plan: logical_plan.pretty(0),
+        });
+        let optimized_plan = self.optimize(&logical_plan)?;
+        let mut query_planner = self.query_planner(memory_budget)?;
+        if let Some(operator_hook) = self.operator_hook(query_id) {
+            query_planner = query_planner.with_operator_hook(operator_hook);
+        }
+        let physical_plan = query_planner.create_physical_plan(&optimized_plan)?;
+        self.emit(QueryEvent::PlanningFinished {
+            query_id,
+            physical_plan: physical_plan.pretty(0),
+        });
+        Ok(physical_plan)
+    }
+
+    /// Analyze and optimize the logical plan, once it was checked against the limits.
+    fn optimize(&self, logical_plan: &LogicalPlan) -> Result<LogicalPlan> {
+        let analyzed_plan = Analyzer::analyze(logical_plan)?;
+        Ok(Optimizer::new(self.optimizer_config.clone()).optimize(&analyzed_plan))
+    }
+
+    /// Create the memory budget of a query, reserving from the pool of the context.
+    fn memory_budget(&self) -> MemoryBudget {
+        self.memory_pool.budget(self.memory_limit)
+    }
+
+    fn query_planner(&self, memory_budget: MemoryBudget) -> Result<QueryPlanner> {
+        let mut query_planner = QueryPlanner::new(self.overflow_mode, self.division_by_zero)
+            .with_check_batches(cfg!(debug_assertions))
+            .with_memory_budget(memory_budget);
+        if let Some(morsel_size) = self.session_config.morsel_size {
+            let scheduler = MorselScheduler::try_new(self.thread_pool()?.clone(), morsel_size)?;
+            query_planner = query_planner.with_morsel_scheduler(scheduler);
+        }
+        Ok(query_planner)
+    }
+
+    /// Return the thread pool of the context, created the first time it is needed.
+    fn thread_pool(&self) -> Result<&Arc<ThreadPool>> {
+        match self.thread_pool.get() {
+            Some(thread_pool) => Ok(thread_pool),
+            None => {
+                let thread_pool = self.session_config.create_thread_pool()?;
+                Ok(self.thread_pool.get_or_init(|| thread_pool))
+            }
+        }
+    }
+
+    /// Apply the change of a command to the tables or views of the context. An insert
+    /// returns the number of rows inserted, the other commands return no batches.
+    fn execute_command(&self, command: &Command) -> Result<Vec<RecordBatch>> {
+        match command {
+            Command::CreateView {
+                name,
+                input,
+                or_replace,
+                if_not_exists,
+            } => {
+                if self.tables.read().unwrap().contains_key(name) {
+                    bail!("Table {} already exists", name);
+                }
+                let exists = self.views.read().unwrap().contains_key(name);
+                match exists {
+                    true if *if_not_exists => {}
+                    true if !or_replace => bail!("View {} already exists", name),
+                    _ => self.register_view(name, &DataFrame::new(input.as_ref().clone())),
+                }
+                Ok(vec![])
+            }
+            Command::DropViews { names, if_exists } => {
+                // No view is dropped unless they all exist.
+                let views = self.view_names();
+                if let Some(name) = names
+                    .iter()
+                    .find(|name| !if_exists && !views.contains(name))
+                {
+                    bail!("No view named {}", name);
+                }
+                for name in names {
+                    self.deregister_view(name);
+                }
+                Ok(vec![])
+            }
+            #[cfg_attr(not(feature = "fs"), allow(unused_variables))]
+            Command::CreateExternalTable {
+                name,
+                location,
+                schema,
+                if_not_exists,
+            } => {
+                if self.table(name).is_ok() {
+                    if *if_not_exists {
+                        return Ok(vec![]);
+                    }
+                    bail!("Table {} already exists", name);
+                }
+                #[cfg(not(feature = "fs"))]
+                bail!("Cannot read the file {} without the fs feature", location);
+                #[cfg(feature = "fs")]
+                {
+                    self.register_csv(name, location.clone(), schema.clone());
+                    Ok(vec![])
+                }
+            }
+            Command::Insert { table, input } => {
+                let rows = self.insert_into(table, &DataFrame::new(input.as_ref().clone()))?;
+                let count = Int64Array::from(vec![rows as i64]);
+                Ok(vec![RecordBatch::new(
+                    command.schema(),
+                    vec![Arc::new(ArrowFieldArray::new(Box::new(count))) as ArrayRef],
+                )])
+            }
+        }
+    }
+
+    /// Plan and execute the DataFrame, collecting all the result batches.
+    /// Commands change the tables or views of the context when they are executed.
+    pub fn execute(&self, df: &DataFrame) -> Result<Vec<RecordBatch>> {
+        let query_id = self.next_query_id.fetch_add(1, Ordering::Relaxed);
+        let batches = self.execute_query(query_id, df);
+        self.report_failure(query_id, batches)
+    }
+
+    fn execute_query(&self, query_id: u64, df: &DataFrame) -> Result<Vec<RecordBatch>> {
+        if let LogicalPlan::Command(command) = df.logical_plan() {
+            return self.execute_command(&command);
+        }
+        let start = Instant::now();
+        let memory_budget = self.memory_budget();
+        let physical_plan =
+            self.create_physical_plan_with_budget(query_id, df, memory_budget.clone())?;
+        let mut batches = vec![];
+        let mut rows = 0;
+        for batch in self
+            .execute_partitions(&physical_plan)?
+            .into_iter()
+            .flatten()
+        {
+            self.emit(QueryEvent::BatchProduced {
+                query_id,
+                rows: batch.row_count(),
+                columns: batch.column_count(),
+            });
+            rows += batch.row_count();
+            batches.push(batch);
+        }
+        self.emit(QueryEvent::QueryFinished {
+            query_id,
+            batches: batches.len(),
+            rows,
+            elapsed: start.elapsed(),
+            peak_memory: memory_budget.peak(),
+        });
+        Ok(batches)
+    }
+
+    /// Execute every partition of the plan, in parallel on the thread pool if there are
+    /// several, and return the batches of each partition in order.
+    fn execute_partitions(&self, plan: &PhysicalPlan) -> Result<Vec<Vec<RecordBatch>>> {
+        let partition_count = plan.output_partitioning().partition_count();
+        if partition_count <= 1 {
+            return Ok(vec![plan.execute_all()?.collect::<Result<_>>()?]);
+        }
+        // WebAssembly has no threads to run the partitions on, so they run one after the other.
+        if cfg!(target_family = "wasm") {
+            return (0..partition_count)
+                .map(|partition| plan.execute(partition)?.collect())
+                .collect();
+        }
+        self.thread_pool()?.install(|| {
+            (0..partition_count)
+                .into_par_iter()
+                .map(|partition| plan.execute(partition)?.collect())
+                .collect()
+        })
+    }
+
+    /// Plan the DataFrame and return a stream of the result batches. The plan runs on a
+    /// thread of its own, one batch ahead of the consumer, so polling the stream never
+    /// blocks an async runtime. The stream does not borrow the context, and may be moved
+    /// to another task. On WebAssembly, which has no threads, the batches are computed
+    /// before the stream is returned, as are the batches of commands.
+    pub fn execute_stream(
+        &self,
+        df: &DataFrame,
+    ) -> Result<BoxStream<'static, Result<RecordBatch>>> {
+        let query_id = self.next_query_id.fetch_add(1, Ordering::Relaxed);
+        if let LogicalPlan::Command(command) = df.logical_plan() {
+            let batches = self.report_failure(query_id, self.execute_command(&command))?;
+            return Ok(stream::iter(batches.into_iter().map(Ok)).boxed());
+        }
+        let start = Instant::now();
+        let memory_budget = self.memory_budget();
+        let physical_plan =
+            self.create_physical_plan_with_budget(query_id, df, memory_budget.clone());
+        let physical_plan = self.report_failure(query_id, physical_plan)?;
+        let subscribers = self.subscribers.read().unwrap().clone();
+        let emit = move |event: QueryEvent| subscribers.iter().for_each(|s| s.on_event(&event));
+        // `send` returns false once the consumer dropped the stream.
+        let produce = move |mut send: Box<dyn FnMut(Result<RecordBatch>) -> bool>| {
+            let batches = match physical_plan.execute_all() {
+                Ok(batches) => batches,
+                Err(e) => {
+                    emit(QueryEvent::QueryFailed {
+                        query_id,
+                        error: e.to_string(),
+                    });
+                    send(Err(e));
+                    return;
+                }
+            };
+            let (mut batch_count, mut rows) = (0, 0);
+            for batch in batches {
+                let batch = match batch {
+                    Ok(batch) => batch,
+                    Err(e) => {
+                        emit(QueryEvent::QueryFailed {
+                            query_id,
+                            error: e.to_string(),
+                        });
+                        send(Err(e));
+                        return;
+                    }
+                };
+                emit(QueryEvent::BatchProduced {
+                    query_id,
+                    rows: batch.row_count(),
+                    columns: batch.column_count(),
+                });
+                batch_count += 1;
+                rows += batch.row_count();
+                if !send(Ok(batch)) {
+                    return;
+                }
+            }
+            emit(QueryEvent::QueryFinished {
+                query_id,
+                batches: batch_count,
+                rows,
+                elapsed: start.elapsed(),
+                peak_memory: memory_budget.peak(),
+            });
+        };
+        if cfg!(target_family = "wasm") {
+            let mut batches = vec![];
+            produce(Box::new(|batch| {
+                batches.push(batch);
+                true
+            }));
+            return Ok(stream::iter(batches).boxed());
+        }
+        // A channel without buffer lets the plan run one batch ahead of the consumer.
+        let (mut sender, receiver) = mpsc::channel(0);
+        thread::Builder::new()
+            .name(format!("rq-query-{}", query_id))
+            .spawn(move || produce(Box::new(move |batch| block_on(sender.send(batch)).is_ok())))?;
+        Ok(receiver.boxed())
+    }
+
+    /// Plan the DataFrame once, to execute it with different values for its placeholders.
+    /// Untyped placeholders take the type of the expressions they are combined with.
+    pub fn prepare(&self, df: &DataFrame) -> Result<PreparedPlan> {
+        let logical_plan = df.logical_plan();
+        self.limits.check(&logical_plan)?;
+        let optimized_plan = self.optimize(&logical_plan)?;
+        let physical_plan = self
+            .query_planner(self.memory_budget())?
+            .create_physical_plan(&optimized_plan)?;
+        PreparedPlan::try_new(physical_plan, &optimized_plan)
+    }
+
+    /// Analyze and optimize the DataFrame, returning the name of every optimizer rule
+    /// applied with the text of the plan it produced.
+    pub fn optimizer_trace(&self, df: &DataFrame) -> Result<Vec<(&'static str, String)>> {
+        let logical_plan = df.logical_plan();
+        self.limits.check(&logical_plan)?;
+        let analyzed_plan = Analyzer::analyze(&logical_plan)?;
+        let optimizer = Optimizer::new(self.optimizer_config.clone());
+        Ok(optimizer.optimize_with_trace(&analyzed_plan).1)
+    }
+
+    /// Return the optimized logical plan and the physical plan of the DataFrame.
+    /// With `analyze`, the query is executed and every operator is shown with its
+    /// runtime metrics: the rows and batches it produced and the time it took,
+    /// including the time of its inputs.
+    pub fn explain(&self, df: &DataFrame, analyze: bool) -> Result<String> {
+        let logical_plan = df.logical_plan();
+        self.limits.check(&logical_plan)?;
+        let optimized_plan = self.optimize(&logical_plan)?;
+        let physical_plan = self
+            .query_planner(self.memory_budget())?
+            .with_collect_metrics(analyze)
+            .create_physical_plan(&optimized_plan)?;
+        if analyze {
+            physical_plan
+                .execute_all()?
+                .try_for_each(|batch| batch.map(drop))?;
+        }
+        Ok(format!(
+            "Logical plan:\n{}Physical plan:\n{}",
+            optimized_plan.pretty(1),
+            physical_plan.pretty(1)
+        ))
+    }
+
+    /// Plan and optimize the DataFrame without executing it, or emitting query events.
+    /// Returns the output schema, or the first error found while planning. Commands are
+    /// not applied, only the queries they read from are validated.
+    pub fn validate(&self, df: &DataFrame) -> Result<Schema> {
+        let logical_plan = df.logical_plan();
+        if let LogicalPlan::Command(command) = &logical_plan {
+            if let Command::CreateView { input, .. } | Command::Insert { input, .. } = command {
+                self.validate(&DataFrame::new(input.as_ref().clone()))?;
+            }
+            return Ok(command.schema());
+        }
+        self.limits.check(&logical_plan)?;
+        let optimized_plan = self.optimize(&logical_plan)?;
+        let physical_plan = self
+            .query_planner(self.memory_budget())?
+            .create_physical_plan(&optimized_plan)?;
+        Ok(physical_plan.schema())
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::{
+        any::Any,
+        sync::{Arc, Mutex},
+    };
+
     use super::*;
     use crate::{
+        data_source::memory_data_source::MemoryDataSource,
         data_types::{column_array::DataType, schema::Field},
-        logical_plan::expr_fn::{col, lit},
-        physical_plan::plan::PhysicalPlan,
+        execution::limits::{DEFAULT_MAX_EXPR_DEPTH, DEFAULT_MAX_PLAN_DEPTH},
+        logical_plan::expr_fn::{
+            approx_percentile, avg, col, count, lit, max, median, min, null, percentile_cont,
+            placeholder, row_number, sum, typed_placeholder,
+        },
+        physical_plan::{
+            fragment::PlanFragment,
+            pipeline::DEFAULT_MORSEL_SIZE,
+            plan::{Partitioning, PhysicalPlan},
+        },
+        testing::pretty_format_batches,
+    };
+    #[cfg(feature = "fs")]
+    use crate::{
+        data_types::{
+            extension::{tests::Currency, ExtensionRef},
+            inet::Inet,
+            point::Point,
+        },
+        logical_plan::{
+            expr::ReturnType,
+            expr_fn::{
+                coalesce, count_distinct, dense_rank, ext_lit, inet_contains, lag, ntile, nullif,
+                rank, regexp_like, st_distance, st_within_bbox, try_cast, typed_null,
+            },
+        },
+        physical_plan::aggregate::Accumulator,
         test_util::rq_test_data,
     };
 
+    #[cfg(feature = "fs")]
     #[test]
     fn test_execute_data_frame() {
         let ctx = ExecutionContext::new(3);
@@ -56,10 +919,10 @@ mod tests {
             .filter(col("c1").eq(lit(1_i32)))
             .project(vec![col("c1"), col("c2"), col("c3")]);
         let physical_plan = ctx.create_physical_plan(&df).unwrap();
-        let batches = physical_plan.execute();
+        let batches = physical_plan.execute_all();
         assert!(batches.is_ok());
         let mut batches = batches.unwrap();
-        let first = batches.next().unwrap();
+        let first = batches.next().unwrap().unwrap();
         assert_eq!(first.row_count(), 1);
         assert_eq!(first.column_count(), 3);
         assert_eq!(
@@ -72,4 +935,2096 @@ mod tests {
             &1
         )
     }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_stream() {
+        let ctx = ExecutionContext::new(1);
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let df = ctx.csv(data_path, schema).filter(col("c1").gt(lit(1_i32)));
+        let batches = block_on(ctx.execute_stream(&df).unwrap().collect::<Vec<_>>())
+            .into_iter()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            batches.iter().map(|b| b.row_count()).collect::<Vec<_>>(),
+            ctx.execute(&df)
+                .unwrap()
+                .iter()
+                .map(|b| b.row_count())
+                .collect::<Vec<_>>()
+        );
+
+        // The stream can be dropped before the plan is done.
+        let first = block_on(ctx.execute_stream(&df).unwrap().next());
+        assert!(first.unwrap().is_ok());
+
+        // The stream outlives the context, and is consumed on another thread.
+        let stream = ctx.execute_stream(&df).unwrap();
+        drop(ctx);
+        let batches = std::thread::spawn(move || block_on(stream.collect::<Vec<_>>()))
+            .join()
+            .unwrap();
+        assert!(!batches.is_empty() && batches.iter().all(Result::is_ok));
+
+        let ctx = ExecutionContext::new(1);
+        let df = df.project(vec![col("c9")]);
+        assert!(ctx.execute_stream(&df).is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_create_physical_plan_with_limits() {
+        let ctx =
+            ExecutionContext::new(3).with_limits(QueryLimits::new().with_max_plan_depth(Some(1)));
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let df = ctx.csv(data_path, schema).filter(col("c1").eq(lit(1_i32)));
+        assert!(ctx.create_physical_plan(&df).is_err());
+    }
+
+    #[test]
+    fn test_deep_plans() {
+        let ctx = ExecutionContext::new(3);
+        let series = ctx
+            .generate_series(lit(1_i64), lit(10_i64), lit(1_i64))
+            .unwrap();
+        let filtered = |filters| {
+            (1..filters).fold(series.clone(), |df, i| {
+                df.filter(col("value").gt(lit(-(i as i64))))
+            })
+        };
+        let rows = |df: &DataFrame| -> Result<usize> {
+            Ok(ctx.execute(df)?.iter().map(|b| b.row_count()).sum())
+        };
+        assert_eq!(rows(&filtered(DEFAULT_MAX_PLAN_DEPTH)).unwrap(), 10);
+        assert_eq!(
+            rows(&filtered(2_000)).unwrap_err().to_string(),
+            "Plan depth 2000 exceeds the limit of 64"
+        );
+
+        let ored = |depth| {
+            // The comparisons under the ORs are two levels deep.
+            let predicate = (2..depth).fold(col("value").gt(lit(0_i64)), |e, i| {
+                e.or(col("value").eq(lit(i as i64)))
+            });
+            series.filter(predicate)
+        };
+        assert_eq!(rows(&ored(DEFAULT_MAX_EXPR_DEPTH)).unwrap(), 10);
+        assert_eq!(
+            ctx.explain(&ored(2_000), false).unwrap_err().to_string(),
+            "Expression depth 2000 exceeds the limit of 128"
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_validate() {
+        let ctx = ExecutionContext::new(3);
+        let events = Arc::new(Mutex::new(vec![]));
+        ctx.subscribe(Arc::new(EventRecorder {
+            events: events.clone(),
+        }));
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int32),
+        ]);
+        let df = ctx
+            .csv(data_path.clone(), schema.clone())
+            .filter(col("c1").eq(lit(1_i32)))
+            .aggregate(vec![col("c1")], vec![max(col("c2"))]);
+        assert_eq!(
+            ctx.validate(&df).unwrap(),
+            Schema::new(vec![
+                Field::new("c1".to_string(), DataType::Int32),
+                Field::new("max(c2)".to_string(), DataType::Int32),
+            ])
+        );
+
+        let df = ctx
+            .csv(data_path, schema)
+            .aggregate(vec![col("c1")], vec![max(col("c3"))]);
+        assert_eq!(
+            ctx.validate(&df).unwrap_err().to_string(),
+            "No column named c3"
+        );
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_aliased_aggregate() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int32),
+        ]);
+        let df = ctx.csv(data_path.clone(), schema.clone()).aggregate(
+            vec![col("c1").alias("key".to_string())],
+            vec![max(col("c2")).alias("biggest".to_string())],
+        );
+        assert_eq!(
+            ctx.validate(&df).unwrap(),
+            Schema::new(vec![
+                Field::new("key".to_string(), DataType::Int32),
+                Field::new("biggest".to_string(), DataType::Int32),
+            ])
+        );
+
+        // Unaliased aggregates are named after their arguments, so only the same
+        // aggregate twice or colliding aliases share a name.
+        let unaliased = ctx
+            .csv(data_path.clone(), schema.clone())
+            .aggregate(vec![], vec![max(col("c1")), max(col("c2"))]);
+        let names = vec!["max(c1)".to_string(), "max(c2)".to_string()];
+        let field_names = |schema: Schema| schema.fields.into_iter().map(|f| f.name);
+        assert_eq!(field_names(unaliased.schema()).collect::<Vec<_>>(), names);
+        assert_eq!(
+            field_names(ctx.validate(&unaliased).unwrap()).collect::<Vec<_>>(),
+            names
+        );
+        let colliding = ctx.csv(data_path, schema).aggregate(
+            vec![],
+            vec![max(col("c1")), min(col("c2")).alias("max(c1)".to_string())],
+        );
+        assert_eq!(
+            ctx.validate(&colliding).unwrap_err().to_string(),
+            "Aggregate has several columns named max(c1), alias them to tell them apart"
+        );
+
+        let df = df.project(vec![col("biggest")]);
+        let batches = ctx.execute(&df).unwrap();
+        let mut biggest = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        *b.field(0)
+                            .get_value(i)
+                            .unwrap()
+                            .downcast_ref::<i32>()
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        biggest.sort();
+        assert_eq!(biggest, vec![9, 10, 11]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_filter_on_aggregate() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int32),
+        ]);
+        // The filter on the key runs before the aggregate, the one on its result after.
+        let df = ctx
+            .csv(data_path, schema)
+            .aggregate(
+                vec![col("c1").alias("key".to_string())],
+                vec![max(col("c2")).alias("biggest".to_string())],
+            )
+            .filter(
+                col("key")
+                    .gt(lit(1_i32))
+                    .and(col("biggest").lt(lit(11_i32))),
+            );
+        let batches = ctx.execute(&df).unwrap();
+        let rows = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        (0..2)
+                            .map(|c| {
+                                *b.field(c)
+                                    .get_value(i)
+                                    .unwrap()
+                                    .downcast_ref::<i32>()
+                                    .unwrap()
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![vec![2, 10]]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_constant_filter() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let csv = || ctx.csv(data_path.clone(), schema.clone());
+        let row_count = |df: &DataFrame| {
+            ctx.execute(df)
+                .unwrap()
+                .iter()
+                .map(|b| b.row_count())
+                .sum::<usize>()
+        };
+        assert_eq!(row_count(&csv().filter(lit(1).eq(lit(1)))), 3);
+        assert_eq!(row_count(&csv().filter(lit(1).eq(lit(2)))), 0);
+
+        // An aggregate without groups has a row whether its input is filtered at runtime or not.
+        let counted = |predicate| {
+            let df = csv()
+                .filter(predicate)
+                .aggregate(vec![], vec![count(col("c1"))]);
+            row_count(&df)
+        };
+        assert_eq!(counted(lit(false)), counted(col("c1").gt(lit(100_i32))));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_not() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let df = ctx
+            .csv(data_path, schema)
+            .filter(!col("c1").eq(lit(1_i32)))
+            .project(vec![!col("c1").gt(lit(2_i32))]);
+        let batches = ctx.execute(&df).unwrap();
+        let values = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        *b.field(0)
+                            .get_value(i)
+                            .unwrap()
+                            .downcast_ref::<bool>()
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(batches[0].schema.fields[0].name, "not");
+        assert_eq!(values, vec![true, false]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_with_optimizer_config() {
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let run = |ctx: &ExecutionContext| {
+            let df = ctx
+                .csv(data_path.clone(), schema.clone())
+                .filter(col("c1").gt(lit(1_i32)))
+                .project(vec![col("c1")]);
+            let trace = ctx.optimizer_trace(&df).unwrap();
+            let row_count = ctx
+                .execute(&df)
+                .unwrap()
+                .iter()
+                .map(|b| b.row_count())
+                .sum::<usize>();
+            (trace.len(), row_count)
+        };
+        assert_eq!(run(&ExecutionContext::new(3)), (6, 2));
+
+        let config = OptimizerConfig::default().with_max_passes(0);
+        let ctx = ExecutionContext::new(3).with_optimizer_config(config);
+        assert_eq!(run(&ctx), (0, 2));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_register_table() {
+        let ctx = ExecutionContext::new(3);
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        ctx.register_csv("primitive", rq_test_data("primitive_field.csv"), schema);
+        let series = ctx.generate_series(lit(1), lit(5), lit(1)).unwrap();
+        match series.logical_plan() {
+            LogicalPlan::Scan(scan) => ctx.register_table("series", scan.data_source),
+            _ => unreachable!(),
+        }
+        assert_eq!(ctx.table_names(), vec!["primitive", "series"]);
+
+        let df = ctx
+            .table("primitive")
+            .unwrap()
+            .filter(col("c1").gt(lit(1_i32)));
+        assert_eq!(df.schema().fields[0].name, "c1");
+        let rows = ctx.execute(&df).unwrap()[0].row_count();
+        assert_eq!(rows, 2);
+        assert!(ctx
+            .explain(&df, false)
+            .unwrap()
+            .contains("Scan: primitive; projection=[c1]"));
+
+        assert!(ctx.deregister_table("series").is_some());
+        assert_eq!(
+            ctx.table("series").err().unwrap().to_string(),
+            "No table named series"
+        );
+    }
+
+    #[test]
+    fn test_execute_partitions() {
+        let ctx =
+            ExecutionContext::new(2).with_session_config(SessionConfig::new().without_morsels());
+        let series = ctx.generate_series(lit(1), lit(6), lit(1)).unwrap();
+        let batches = series.collect(&ctx).unwrap();
+        let source = MemoryDataSource::new(series.schema(), batches).with_partitions(2);
+        ctx.register_table("series", Source::Mem(source));
+
+        let df = ctx.table("series").unwrap().filter(col("value").gt(lit(1)));
+        let physical_plan = ctx.create_physical_plan(&df).unwrap();
+        assert_eq!(
+            physical_plan.output_partitioning(),
+            Partitioning::Unknown(2)
+        );
+        let rows = |partition| -> Result<usize> {
+            physical_plan
+                .execute(partition)?
+                .map(|b| Ok(b?.row_count()))
+                .sum()
+        };
+        assert_eq!(rows(0).unwrap(), 3);
+        assert_eq!(rows(1).unwrap(), 2);
+        assert_eq!(
+            rows(2).unwrap_err().to_string(),
+            "Partition 2 is out of range, the plan has 2 partitions"
+        );
+
+        let df = df.aggregate(vec![], vec![sum(col("value"))]);
+        let physical_plan = ctx.create_physical_plan(&df).unwrap();
+        assert_eq!(
+            physical_plan.output_partitioning(),
+            Partitioning::Unknown(1)
+        );
+        assert_eq!(df.collect_scalar(&ctx).unwrap(), ScalarValue::Int64(20));
+    }
+
+    #[test]
+    fn test_concurrent_queries() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ExecutionContext>();
+
+        let ctx = Arc::new(ExecutionContext::new(4));
+        let series = |n: i64| ctx.generate_series(lit(1_i64), lit(n), lit(1_i64)).unwrap();
+        std::thread::scope(|scope| {
+            for t in 0..4_i64 {
+                let ctx = ctx.clone();
+                let series = series(10 * (t + 1));
+                scope.spawn(move || {
+                    let name = format!("series_{}", t);
+                    let batches = series.collect(&ctx).unwrap();
+                    let source = MemoryDataSource::new(series.schema(), batches);
+                    ctx.register_table(&name, Source::Mem(source));
+                    for _ in 0..10 {
+                        let df = ctx
+                            .table(&name)
+                            .unwrap()
+                            .aggregate(vec![], vec![sum(col("value"))]);
+                        let n = 10 * (t + 1);
+                        assert_eq!(
+                            df.collect_scalar(&ctx).unwrap(),
+                            ScalarValue::Int64(n * (n + 1) / 2)
+                        );
+                    }
+                });
+            }
+        });
+        assert_eq!(
+            ctx.table_names(),
+            vec!["series_0", "series_1", "series_2", "series_3"]
+        );
+    }
+
+    #[test]
+    fn test_execute_partitions_in_parallel() {
+        let thread_pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(2)
+                .build()
+                .unwrap(),
+        );
+        let ctx = ExecutionContext::new(2)
+            .with_session_config(SessionConfig::new().with_thread_pool(thread_pool));
+        let series = ctx.generate_series(lit(1), lit(9), lit(1)).unwrap();
+        let batches = series.collect(&ctx).unwrap();
+        let source = MemoryDataSource::new(series.schema(), batches).with_partitions(3);
+        ctx.register_table("series", Source::Mem(source));
+
+        let df = ctx.table("series").unwrap().filter(col("value").gt(lit(1)));
+        let values = df
+            .collect(&ctx)
+            .unwrap()
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        *b.field(0)
+                            .get_value(i)
+                            .unwrap()
+                            .downcast_ref::<i64>()
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        // The batches of the first partition come first, then those of the second, ...
+        assert_eq!(values, vec![2, 7, 8, 3, 4, 9, 5, 6]);
+
+        let ctx = ctx.with_session_config(SessionConfig::new().with_target_parallelism(0));
+        assert_eq!(
+            df.collect(&ctx).err().unwrap().to_string(),
+            "The target parallelism must be at least 1"
+        );
+    }
+
+    #[test]
+    fn test_morsel_pipelines() {
+        let context = |config: SessionConfig| {
+            let ctx = ExecutionContext::new(4).with_session_config(config);
+            let series = ctx.generate_series(lit(1), lit(20), lit(1)).unwrap();
+            let batches = series.collect(&ctx).unwrap();
+            let source = MemoryDataSource::new(series.schema(), batches).with_partitions(3);
+            ctx.register_table("series", Source::Mem(source));
+            ctx
+        };
+        let ctx = context(SessionConfig::new().without_morsels());
+        let morsel_ctx = context(
+            SessionConfig::new()
+                .with_target_parallelism(2)
+                .with_morsel_size(3),
+        );
+
+        let values = |ctx: &ExecutionContext, df: &DataFrame| {
+            df.collect(ctx)
+                .unwrap()
+                .iter()
+                .flat_map(|b| {
+                    (0..b.row_count())
+                        .map(|i| {
+                            *b.field(0)
+                                .get_value(i)
+                                .unwrap()
+                                .downcast_ref::<i64>()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let table = |ctx: &ExecutionContext, df: &DataFrame| {
+            pretty_format_batches(&df.collect(ctx).unwrap()).unwrap()
+        };
+        let df = morsel_ctx
+            .table("series")
+            .unwrap()
+            .filter(col("value").gt(lit(3)))
+            .project(vec![(col("value") + lit(1)).alias("value".to_string())]);
+        // The morsels are processed in parallel, but their rows keep the order of the scan.
+        let expected = values(&ctx, &df);
+        assert_eq!(expected.len(), 17);
+        assert_eq!(values(&morsel_ctx, &df), expected);
+        let plan = morsel_ctx.create_physical_plan(&df).unwrap();
+        assert!(plan.to_string().starts_with("PipelineExec: morsel_size=3"));
+        assert_eq!(plan.output_partitioning().partition_count(), 1);
+
+        // The input of an aggregate is a pipeline of its own.
+        let df = df.aggregate(vec![], vec![sum(col("value"))]);
+        assert_eq!(
+            df.collect_scalar(&morsel_ctx).unwrap(),
+            ScalarValue::Int64(expected.iter().sum())
+        );
+        let plan = morsel_ctx.create_physical_plan(&df).unwrap();
+        assert!(plan.pretty(0).contains("PipelineExec"));
+
+        // Every morsel is aggregated in the pipeline, and the groups of the morsels
+        // are merged in the order they are first seen, as the rows of the scan.
+        let series = morsel_ctx.table("series").unwrap();
+        let df = series.aggregate(
+            vec![(col("value") % lit(4)).alias("k".to_string())],
+            vec![count(col("value")), sum(col("value")), min(col("value"))],
+        );
+        let plan = morsel_ctx.create_physical_plan(&df).unwrap();
+        assert!(plan.pretty(0).starts_with(
+            "HashAggregateExec: groupExpr=#0, aggrExpr=SUM(#1), SUM(#2), MIN(#3)\n\
+             \tPipelineExec: morsel_size=3\n\
+             \t\tHashAggregateExec: groupExpr=#0 % CAST(4 AS Int64), \
+             aggrExpr=COUNT(#0), SUM(#0), MIN(#0)\n"
+        ));
+        assert_eq!(table(&morsel_ctx, &df), table(&ctx, &df));
+        // An average can't be merged from morsels, so the scan is aggregated on its own.
+        let df = series.aggregate(vec![], vec![avg(col("value"))]);
+        let plan = morsel_ctx.create_physical_plan(&df).unwrap();
+        assert!(plan
+            .pretty(0)
+            .starts_with("HashAggregateExec: groupExpr=, aggrExpr=AVG(#0)\n\tScanExec"));
+        assert_eq!(table(&morsel_ctx, &df), table(&ctx, &df));
+        // Without morsels, an aggregate without groups still has its row.
+        let df = series
+            .filter(col("value").gt(lit(100)))
+            .aggregate(vec![], vec![count(col("value"))]);
+        assert_eq!(
+            df.collect_scalar(&morsel_ctx).unwrap(),
+            ScalarValue::Int64(0)
+        );
+
+        let ctx =
+            ExecutionContext::new(4).with_session_config(SessionConfig::new().with_morsel_size(0));
+        let df = ctx.generate_series(lit(1), lit(20), lit(1)).unwrap();
+        assert_eq!(
+            df.collect(&ctx).err().unwrap().to_string(),
+            "The morsel size must be at least 1"
+        );
+    }
+
+    #[test]
+    fn test_collect() {
+        let ctx = ExecutionContext::new(2);
+        let df = ctx.generate_series(lit(1), lit(5), lit(1)).unwrap();
+        let batches = df.collect(&ctx).unwrap();
+        assert_eq!(batches.iter().map(|b| b.row_count()).sum::<usize>(), 5);
+
+        let scalar = |df: DataFrame| df.collect_scalar(&ctx);
+        assert_eq!(
+            scalar(df.aggregate(vec![], vec![sum(col("value"))])).unwrap(),
+            ScalarValue::Int64(15)
+        );
+        assert_eq!(
+            scalar(
+                df.filter(col("value").gt(lit(4)))
+                    .project(vec![col("value")])
+            )
+            .unwrap(),
+            ScalarValue::Int64(5)
+        );
+        assert_eq!(
+            scalar(df.aggregate(vec![], vec![max(col("value") + null())])).unwrap(),
+            ScalarValue::Null(Some(DataType::Int64))
+        );
+        assert_eq!(
+            scalar(df.clone()).unwrap_err().to_string(),
+            "Expected a single value, got 5 rows and 1 columns"
+        );
+    }
+
+    #[test]
+    fn test_prepare() {
+        let ctx = ExecutionContext::new(2);
+        let series = ctx.generate_series(lit(1), lit(5), lit(1)).unwrap();
+        let df = series.filter(col("value").gt(placeholder("$1")));
+        let prepared = ctx.prepare(&df).unwrap();
+        assert_eq!(prepared.param_count(), 1);
+        assert_eq!(prepared.param_type(1), Some(&DataType::Int64));
+        let rows = |params: &[ScalarValue]| -> Result<usize> {
+            let batches = prepared.execute(params)?;
+            Ok(batches.iter().map(|b| b.row_count()).sum())
+        };
+        assert_eq!(rows(&[ScalarValue::Int64(3)]).unwrap(), 2);
+        assert_eq!(rows(&[ScalarValue::Int64(0)]).unwrap(), 5);
+        assert_eq!(rows(&[ScalarValue::Null(None)]).unwrap(), 0);
+        assert_eq!(
+            rows(&[]).unwrap_err().to_string(),
+            "Expected 1 parameters, got 0"
+        );
+        assert_eq!(
+            rows(&[ScalarValue::Int32(3)]).unwrap_err().to_string(),
+            "Placeholder $1 has type Int64, got 3 of type Int32"
+        );
+
+        let df = series
+            .filter(col("value").eq(placeholder("$2")))
+            .project(vec![typed_placeholder("$1", DataType::Utf8)]);
+        let prepared = ctx.prepare(&df).unwrap();
+        let params = [ScalarValue::String("a".to_string()), ScalarValue::Int64(4)];
+        let batches = prepared.execute(&params).unwrap();
+        let batch = batches.iter().find(|b| b.row_count() > 0).unwrap();
+        assert_eq!(batches.iter().map(|b| b.row_count()).sum::<usize>(), 1);
+        assert_eq!(
+            ScalarValue::try_from_array(batch.field(0).as_ref(), 0).unwrap(),
+            params[0]
+        );
+
+        let error = |df: DataFrame| ctx.prepare(&df).err().unwrap().to_string();
+        assert_eq!(
+            error(series.project(vec![placeholder("$1")])),
+            "Cannot infer the type of placeholder $1"
+        );
+        assert_eq!(
+            error(series.filter(col("value").eq(placeholder("?")))),
+            "Invalid placeholder ?, expected $1, $2, ..."
+        );
+        assert_eq!(
+            error(
+                series.filter(
+                    col("value")
+                        .eq(placeholder("$1"))
+                        .and(lit("a".to_string()).eq(placeholder("$1")))
+                )
+            ),
+            "Placeholder $1 is used with types Utf8 and Int64"
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_count() {
+        let ctx = ExecutionContext::new(2);
+        let series = ctx.generate_series(lit(1), lit(5), lit(1)).unwrap();
+        assert_eq!(series.count(&ctx).unwrap(), 5);
+        let df = series.filter(col("value").gt(lit(1)));
+        assert_eq!(df.count(&ctx).unwrap(), 4);
+        let df = series.filter(col("value").gt(lit(5)));
+        assert_eq!(df.count(&ctx).unwrap(), 0);
+
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let csv = ctx.csv(data_path, schema);
+        assert_eq!(csv.count(&ctx).unwrap(), 3);
+        let df = csv.aggregate(vec![col("c1")], vec![count(col("c1"))]);
+        assert_eq!(df.count(&ctx).unwrap(), 3);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_cache() {
+        let ctx = ExecutionContext::new(2);
+        let data_path = std::env::temp_dir().join(format!("rq_cache_{}.csv", std::process::id()));
+        std::fs::write(&data_path, "1,a\n2,b\n3,c\n").unwrap();
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Utf8),
+        ]);
+        let cached = ctx
+            .csv(data_path.to_str().unwrap().to_string(), schema)
+            .filter(col("c1").gt(lit(1_i32)))
+            .cache(&ctx)
+            .unwrap();
+        // The file is read once, when the DataFrame is cached.
+        std::fs::remove_file(&data_path).unwrap();
+
+        assert_eq!(cached.count(&ctx).unwrap(), 2);
+        let df = cached
+            .filter(col("c1").eq(lit(3_i32)))
+            .project(vec![col("c2")]);
+        assert_eq!(
+            df.collect_scalar(&ctx).unwrap(),
+            ScalarValue::String("c".to_string())
+        );
+        assert!(ctx
+            .explain(&df, false)
+            .unwrap()
+            .contains("Scan: cache; projection=[c1,c2]"));
+    }
+
+    #[cfg(feature = "polars")]
+    #[test]
+    fn test_polars_round_trip() {
+        use polars::prelude::{df, Series};
+
+        let ctx = ExecutionContext::new(2);
+        let input = df!("k" => ["a", "b", "a", "c"], "v" => [1_i64, 2, 3, 4]).unwrap();
+        ctx.register_view("t", &ctx.read_polars(&input).unwrap());
+        let df = ctx
+            .sql("SELECT k, SUM(v) AS total FROM t WHERE v > 1 GROUP BY k ORDER BY k")
+            .unwrap();
+        let output = df.collect_polars(&ctx).unwrap();
+        let expected = df!("k" => ["a", "b", "c"], "total" => [3_i64, 2, 4]).unwrap();
+        assert!(output.equals(&expected));
+
+        let input =
+            df!("x" => Series::new_empty("x".into(), &polars::prelude::DataType::Int32)).unwrap();
+        let output = ctx
+            .read_polars(&input)
+            .unwrap()
+            .collect_polars(&ctx)
+            .unwrap();
+        assert_eq!(output.shape(), (0, 1));
+    }
+
+    #[test]
+    fn test_arrow_round_trip() {
+        use arrow::{
+            array::{Array, Float64Array, Int64Array, StringArray},
+            datatypes::DataType as ArrowDataType,
+        };
+
+        let ctx = ExecutionContext::new(2);
+        let df = ctx
+            .sql(
+                "SELECT value, value * 1.5 AS f, CAST(value AS VARCHAR) AS s \
+                 FROM generate_series(1, 5)",
+            )
+            .unwrap();
+        let batches = df.to_arrow(&ctx).unwrap();
+        let schema = batches[0].schema();
+        let types = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                ("value", &ArrowDataType::Int64),
+                ("f", &ArrowDataType::Float64),
+                ("s", &ArrowDataType::Utf8),
+            ]
+        );
+        let rows = batches.iter().map(|b| b.num_rows()).sum::<usize>();
+        assert_eq!(rows, 5);
+        let last = batches.iter().rev().find(|b| b.num_rows() > 0).unwrap();
+        let column = |i: usize| last.column(i).clone();
+        assert_eq!(
+            column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+                .value(0),
+            5
+        );
+        assert_eq!(
+            column(1)
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap()
+                .value(0),
+            7.5
+        );
+        assert_eq!(
+            column(2)
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .value(0),
+            "5"
+        );
+
+        let df = DataFrame::from_arrow(&batches).unwrap();
+        let types = df
+            .schema()
+            .fields
+            .into_iter()
+            .map(|f| f.data_type)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![DataType::Int64, DataType::Float64, DataType::Utf8]
+        );
+        let sum = df
+            .filter(col("value").gt(lit(3_i64)))
+            .aggregate(vec![], vec![sum(col("f"))])
+            .collect_scalar(&ctx)
+            .unwrap();
+        assert_eq!(sum, ScalarValue::Float64(13.5));
+
+        assert_eq!(
+            DataFrame::from_arrow(&[]).err().unwrap().to_string(),
+            "Cannot create a DataFrame without Arrow batches to get the schema of"
+        );
+        let other = ctx.sql("SELECT value FROM generate_series(1, 2)").unwrap();
+        let mixed = [batches[0].clone(), other.to_arrow(&ctx).unwrap()[0].clone()];
+        assert!(DataFrame::from_arrow(&mixed)
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("The Arrow batches have different schemas"));
+    }
+
+    #[test]
+    fn test_write_ipc() {
+        use arrow::{array::Int64Array, ipc::reader::StreamReader};
+
+        let ctx = ExecutionContext::new(2);
+        let df = ctx
+            .sql("SELECT value, value % 2 = 0 AS even FROM generate_series(1, 5)")
+            .unwrap();
+        let mut bytes = vec![];
+        df.write_ipc(&ctx, &mut bytes).unwrap();
+
+        let reader = StreamReader::try_new(std::io::Cursor::new(bytes), None).unwrap();
+        let schema = reader.schema();
+        assert_eq!(schema.field(0).name(), "value");
+        assert_eq!(schema.field(1).name(), "even");
+        let batches = reader.collect::<arrow::error::Result<Vec<_>>>().unwrap();
+        let values = batches
+            .iter()
+            .flat_map(|b| {
+                let column = b.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+                column.values().to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![1, 2, 3, 4, 5]);
+        // The batches read back convert to the batches executed.
+        let df = DataFrame::from_arrow(&batches).unwrap();
+        assert_eq!(df.count(&ctx).unwrap(), 5);
+
+        // The query is executed like the others, reporting its events.
+        struct Finished(Arc<Mutex<Vec<String>>>);
+
+        impl QueryEventSubscriber for Finished {
+            fn on_event(&self, event: &QueryEvent) {
+                if let QueryEvent::QueryFinished { .. } | QueryEvent::QueryFailed { .. } = event {
+                    self.0.lock().unwrap().push(event.to_string());
+                }
+            }
+        }
+
+        let finished = Arc::new(Mutex::new(vec![]));
+        ctx.subscribe(Arc::new(Finished(finished.clone())));
+        let df = ctx
+            .sql("SELECT value FROM generate_series(1, 5) WHERE value > 2")
+            .unwrap();
+        df.write_ipc(&ctx, &mut vec![]).unwrap();
+        let df = ctx
+            .sql("SELECT value / 0 FROM generate_series(1, 5)")
+            .unwrap();
+        assert!(df.write_ipc(&ctx, &mut vec![]).is_err());
+        let finished = finished.lock().unwrap();
+        assert_eq!(finished.len(), 2);
+        assert!(finished[0].starts_with("QueryFinished") && finished[0].ends_with("rows=3"));
+        assert_eq!(finished[1], "QueryFailed: Division by zero");
+    }
+
+    #[test]
+    fn test_read_ipc_checksums() {
+        use std::io::Cursor;
+
+        let ctx = ExecutionContext::new(2);
+        let df = ctx
+            .sql("SELECT value * 1000003 AS value FROM generate_series(1, 5)")
+            .unwrap();
+        let mut bytes = vec![];
+        df.write_ipc(&ctx, &mut bytes).unwrap();
+        let read = ctx.read_ipc(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(
+            pretty_format_batches(&ctx.execute(&read).unwrap()).unwrap(),
+            pretty_format_batches(&ctx.execute(&df).unwrap()).unwrap()
+        );
+
+        // A stream without the trailer is read without verifying it.
+        let end = bytes.len() - 16;
+        let length = u64::from_le_bytes(bytes[end..end + 8].try_into().unwrap()) as usize;
+        let plain = ctx
+            .read_ipc(Cursor::new(bytes[..end - length].to_vec()))
+            .unwrap();
+        assert_eq!(plain.count(&ctx).unwrap(), 5);
+
+        // A value corrupted in the stream fails the read.
+        let value = 3_000_009_i64.to_le_bytes();
+        let offset = bytes.windows(8).position(|w| w == value).unwrap();
+        bytes[offset] ^= 1;
+        let error = ctx.read_ipc(Cursor::new(bytes)).err().unwrap();
+        assert!(error
+            .to_string()
+            .ends_with("of the IPC stream is corrupted"));
+        assert!(error
+            .root_cause()
+            .to_string()
+            .starts_with("Checksum mismatch"));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_register_query() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let df = ctx.csv(data_path, schema);
+        ctx.register_query(
+            "c1_at_least",
+            QueryTemplate::new(vec!["n".to_string()], move |args| {
+                df.filter(col("c1").gt_eq(args[0].clone()))
+            }),
+        );
+
+        let df = ctx.query("c1_at_least", vec![lit(2_i32)]).unwrap();
+        let physical_plan = ctx.create_physical_plan(&df).unwrap();
+        let batch = physical_plan
+            .execute_all()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(batch.row_count(), 2);
+
+        assert!(ctx.query("c1_at_least", vec![]).is_err());
+        assert_eq!(
+            ctx.query("unknown", vec![]).err().unwrap().to_string(),
+            "No query named unknown"
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_regexp_filter() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("string_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Utf8)]);
+        let df = ctx
+            .csv(data_path, schema)
+            .filter(regexp_like(col("c1"), "^[ab]$"));
+        let physical_plan = ctx.create_physical_plan(&df).unwrap();
+        let batch = physical_plan
+            .execute_all()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(batch.row_count(), 2);
+    }
+
+    #[cfg(feature = "fs")]
+    struct EventRecorder {
+        events: Arc<Mutex<Vec<(u64, String)>>>,
+    }
+
+    #[cfg(feature = "fs")]
+    impl QueryEventSubscriber for EventRecorder {
+        fn on_event(&self, event: &QueryEvent) {
+            let event = (event.query_id(), event.to_string());
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_query_events() {
+        let ctx = ExecutionContext::new(3);
+        let events = Arc::new(Mutex::new(vec![]));
+        ctx.subscribe(Arc::new(EventRecorder {
+            events: events.clone(),
+        }));
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let df = ctx.csv(data_path, schema).filter(col("c1").gt(lit(1_i32)));
+        let batches = ctx.execute(&df).unwrap();
+        assert_eq!(batches.len(), 1);
+        let events = events.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert!(events.iter().all(|(query_id, _)| *query_id == 1));
+        assert_eq!(
+            events.into_iter().map(|(_, e)| e).collect::<Vec<_>>(),
+            vec![
+                "PlanningStarted",
+                "PlanningFinished",
+                "OperatorStarted: SelectionExec: #0 > 1 (depth=0, partition=0)",
+                "OperatorStarted: ScanExec: projection=c1 (depth=1, partition=0)",
+                "BatchProduced: rows=2, columns=1",
+                "QueryFinished: batches=1, rows=2",
+            ]
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_query_failed_event() {
+        let ctx = ExecutionContext::new(3);
+        let events = Arc::new(Mutex::new(vec![]));
+        ctx.subscribe(Arc::new(EventRecorder {
+            events: events.clone(),
+        }));
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]);
+        let df = ctx.csv(data_path, schema);
+        ctx.execute(&df.filter(col("c1").gt(lit(1_i32)))).unwrap();
+        // Operators are only started when the plan is executed.
+        ctx.create_physical_plan(&df).unwrap();
+        assert!(ctx.execute(&df.filter(col("c2").gt(lit(1_i32)))).is_err());
+        let events = events.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert_eq!(
+            events[events.len() - 4..],
+            vec![
+                (2, "PlanningStarted".to_string()),
+                (2, "PlanningFinished".to_string()),
+                (3, "PlanningStarted".to_string()),
+                (3, "QueryFailed: No column named c2".to_string()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_extension_type_filter() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("currency_field.csv");
+        let schema = Schema::new(vec![Field::new_extension(
+            "price".to_string(),
+            ExtensionRef::new(Currency),
+        )]);
+        let df = ctx
+            .csv(data_path, schema)
+            .filter(col("price").gt(lit(100_i64)));
+        let batches = ctx.execute(&df).unwrap();
+        assert_eq!(batches[0].row_count(), 2);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_inet_filter() {
+        let ctx = ExecutionContext::new(4);
+        let data_path = rq_test_data("inet_field.csv");
+        let inet = ExtensionRef::new(Inet);
+        let schema = Schema::new(vec![Field::new_extension("ip".to_string(), inet.clone())]);
+        let df = ctx
+            .csv(data_path.clone(), schema.clone())
+            .filter(inet_contains(lit("10.0.0.0/8".to_string()), col("ip")));
+        let batches = ctx.execute(&df).unwrap();
+        assert_eq!(batches[0].row_count(), 2);
+
+        // Addresses are ordered numerically, not as text.
+        let df = ctx
+            .csv(data_path, schema)
+            .filter(col("ip").gt(ext_lit(&inet, "10.0.0.2").unwrap()));
+        let batches = ctx.execute(&df).unwrap();
+        let ips = batches[0].field(0);
+        assert_eq!(ips.size(), 2);
+        assert_eq!(
+            inet.format(ips.get_value(0).unwrap().as_ref()),
+            "192.168.1.20"
+        );
+        assert_eq!(inet.format(ips.get_value(1).unwrap().as_ref()), "10.0.0.10");
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_inet_format_and_cast() {
+        let ctx = ExecutionContext::new(4);
+        let schema = Schema::new(vec![Field::new_extension(
+            "ip".to_string(),
+            ExtensionRef::new(Inet),
+        )]);
+        let df = ctx
+            .csv(rq_test_data("inet_field.csv"), schema)
+            .project(vec![
+                col("ip"),
+                cast(col("ip"), DataType::Utf8).alias("text".to_string()),
+                try_cast(col("ip"), DataType::Int64).alias("int".to_string()),
+            ]);
+        // Extension values are printed and cast to Utf8 in their text form, and the
+        // extension type decides which other casts are possible.
+        crate::assert_batches_eq!(
+            [
+                "+--------------+--------------+-----+",
+                "| ip           | text         | int |",
+                "+--------------+--------------+-----+",
+                "| 10.0.0.1     | 10.0.0.1     |     |",
+                "| 192.168.1.20 | 192.168.1.20 |     |",
+                "| 10.0.0.10    | 10.0.0.10    |     |",
+                "| ::1          | ::1          |     |",
+                "+--------------+--------------+-----+",
+            ],
+            &df.collect(&ctx).unwrap()
+        );
+
+        let df = df.project(vec![cast(col("ip"), DataType::Int64)]);
+        let error = df.collect(&ctx).err().unwrap();
+        assert_eq!(error.to_string(), "Cannot cast inet to Int64");
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_coalesce_and_nullif() {
+        let ctx = ExecutionContext::new(4);
+        let data_path = rq_test_data("nullable_field.csv");
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int64),
+        ]);
+        let df = ctx.csv(data_path, schema).project(vec![
+            coalesce(vec![col("c1"), col("c2"), lit(0_i32)]),
+            nullif(col("c1"), lit(4_i32)),
+        ]);
+        let batches = ctx.execute(&df).unwrap();
+        let coalesced = batches[0].field(0);
+        assert_eq!(coalesced.get_type(), DataType::Int64);
+        assert_eq!(
+            (0..coalesced.size())
+                .map(|i| *coalesced
+                    .get_value(i)
+                    .unwrap()
+                    .downcast_ref::<i64>()
+                    .unwrap())
+                .collect::<Vec<_>>(),
+            vec![1, 2, 0, 4]
+        );
+        let nulled = batches[0].field(1);
+        assert_eq!(
+            (0..nulled.size())
+                .map(|i| nulled.is_null(i))
+                .collect::<Vec<_>>(),
+            vec![true, false, true, true]
+        );
+
+        let df = ctx
+            .csv(
+                rq_test_data("nullable_field.csv"),
+                Schema::new(vec![Field::new("c1".to_string(), DataType::Int32)]),
+            )
+            .project(vec![coalesce(vec![col("c1"), lit("a".to_string())])]);
+        assert!(ctx.validate(&df).is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_point_filter() {
+        let ctx = ExecutionContext::new(4);
+        let data_path = rq_test_data("point_field.csv");
+        let point = ExtensionRef::new(Point);
+        let schema = Schema::new(vec![Field::new_extension(
+            "location".to_string(),
+            point.clone(),
+        )]);
+        let df = ctx
+            .csv(data_path.clone(), schema.clone())
+            .filter(st_within_bbox(
+                col("location"),
+                ext_lit(&point, "47,5").unwrap(),
+                ext_lit(&point, "55,15").unwrap(),
+            ));
+        let batches = ctx.execute(&df).unwrap();
+        assert_eq!(batches[0].row_count(), 2);
+
+        let berlin = ext_lit(&point, "52.52,13.405").unwrap();
+        let df = ctx
+            .csv(data_path, schema)
+            .filter(st_distance(col("location"), berlin).lt(lit(300_000.0_f64)));
+        let batches = ctx.execute(&df).unwrap();
+        assert_eq!(batches[0].row_count(), 2);
+    }
+
+    /// Average of the first argument weighted by the second.
+    #[cfg(feature = "fs")]
+    #[derive(Default)]
+    struct WeightedAvg {
+        sum: f64,
+        weight: f64,
+    }
+
+    #[cfg(feature = "fs")]
+    impl Accumulator for WeightedAvg {
+        fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+            if let [Some(value), Some(weight)] = values.as_slice() {
+                let weight = *weight.downcast_ref::<i64>().unwrap() as f64;
+                self.sum += *value.downcast_ref::<i32>().unwrap() as f64 * weight;
+                self.weight += weight;
+            }
+            Ok(())
+        }
+
+        fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+            Ok(vec![Some(Box::new(self.sum)), Some(Box::new(self.weight))])
+        }
+
+        fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+            self.sum += state[0].as_ref().unwrap().downcast_ref::<f64>().unwrap();
+            self.weight += state[1].as_ref().unwrap().downcast_ref::<f64>().unwrap();
+            Ok(())
+        }
+
+        fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+            Ok((self.weight != 0.0).then(|| Box::new(self.sum / self.weight) as Box<dyn Any>))
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_udaf() {
+        let ctx = ExecutionContext::new(4);
+        ctx.register_udaf(AggregateUdf::new(
+            "weighted_avg",
+            ReturnType::Fixed(DataType::Float64),
+            || Box::new(WeightedAvg::default()),
+        ));
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int32),
+            Field::new("c3".to_string(), DataType::Int64),
+        ]);
+        let weighted_avg = ctx.udaf("weighted_avg").unwrap();
+        let df = ctx
+            .csv(data_path, schema)
+            .aggregate(vec![], vec![weighted_avg.call(vec![col("c1"), col("c3")])]);
+        assert_eq!(
+            ctx.validate(&df).unwrap().fields[0],
+            Field::new("weighted_avg(c1, c3)".to_string(), DataType::Float64)
+        );
+        let batches = ctx.execute(&df).unwrap();
+        let result = batches[0].field(0);
+        assert_eq!(result.size(), 1);
+        assert_eq!(
+            result.get_value(0).unwrap().downcast_ref::<f64>().unwrap(),
+            &(128.0 / 63.0)
+        );
+
+        assert_eq!(
+            ctx.udaf("unknown").err().unwrap().to_string(),
+            "No aggregate function named unknown"
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_sessionize() {
+        let ctx = ExecutionContext::new(4);
+        let schema = Schema::new(vec![
+            Field::new("user".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        let df = ctx
+            .csv(rq_test_data("events.csv"), schema.clone())
+            .sessionize(col("user"), col("ts"), 1800)
+            .filter(col("session_id").eq(lit(3)))
+            .project(vec![col("ts")]);
+        let batches = ctx.execute(&df).unwrap();
+        let ts = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        *b.field(0)
+                            .get_value(i)
+                            .unwrap()
+                            .downcast_ref::<i64>()
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ts, vec![5000, 5100]);
+
+        let df =
+            ctx.csv(rq_test_data("events.csv"), schema)
+                .sessionize(col("ts"), col("user"), 1800);
+        assert!(ctx.validate(&df).is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_boolean_filter() {
+        let ctx = ExecutionContext::new(3);
+        let schema = Schema::new(vec![Field::new("flag".to_string(), DataType::Boolean)]);
+        let df = ctx
+            .csv(rq_test_data("boolean_field.csv"), schema)
+            .filter(col("flag").eq(lit(true)));
+        let batches = ctx.execute(&df).unwrap();
+        assert_eq!(batches[0].row_count(), 2);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_group_by_time() {
+        let ctx = ExecutionContext::new(4);
+        let schema = Schema::new(vec![
+            Field::new("user".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        let df = ctx
+            .csv(rq_test_data("events.csv"), schema)
+            .group_by_time(col("ts"), 1000, vec![sum(col("ts"))])
+            .unwrap();
+        assert_eq!(df.schema().fields[0].name, "ts");
+        let batches = ctx.execute(&df).unwrap();
+        let batch = &batches[0];
+        let buckets = (0..batch.row_count())
+            .map(|i| {
+                *batch
+                    .field(0)
+                    .get_value(i)
+                    .unwrap()
+                    .downcast_ref::<i64>()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(buckets, vec![0, 1000, 2000, 3000, 4000, 5000]);
+        let sums = (0..batch.row_count())
+            .map(|i| {
+                (!batch.field(1).is_null(i)).then(|| {
+                    *batch
+                        .field(1)
+                        .get_value(i)
+                        .unwrap()
+                        .downcast_ref::<i64>()
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sums, vec![Some(130), None, None, None, None, Some(10100)]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_count_distinct() {
+        let ctx = ExecutionContext::new(4);
+        let schema = Schema::new(vec![
+            Field::new("user".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        let df = ctx.csv(rq_test_data("events.csv"), schema).aggregate(
+            vec![(col("ts") % lit(2_i64)).alias("even".to_string())],
+            vec![
+                count_distinct(col("user")).alias("users".to_string()),
+                count_distinct(col("ts")).alias("timestamps".to_string()),
+            ],
+        );
+        assert_eq!(
+            ctx.validate(&df).unwrap().fields[1].data_type,
+            DataType::Int64
+        );
+        let batches = ctx.execute(&df).unwrap();
+        assert_eq!(batches[0].row_count(), 1);
+        let value = |column| {
+            *batches[0]
+                .field(column)
+                .get_value(0)
+                .unwrap()
+                .downcast_ref::<i64>()
+                .unwrap()
+        };
+        assert_eq!(value(1), 2);
+        assert_eq!(value(2), 6);
+    }
+
+    #[test]
+    fn test_execute_percentiles() {
+        let ctx = ExecutionContext::new(64);
+        let df = ctx
+            .generate_series(lit(1), lit(1000), lit(1))
+            .unwrap()
+            .aggregate(
+                vec![(col("value") % lit(2_i64)).alias("odd".to_string())],
+                vec![
+                    median(col("value")),
+                    percentile_cont(col("value"), 0.95),
+                    approx_percentile(col("value"), 0.95),
+                ],
+            );
+        assert_eq!(
+            ctx.validate(&df).unwrap().fields[1..]
+                .iter()
+                .map(|f| f.data_type.clone())
+                .collect::<Vec<_>>(),
+            vec![DataType::Float64; 3]
+        );
+        let batches = ctx.execute(&df).unwrap();
+        let value = |row, column| {
+            *batches[0]
+                .field(column)
+                .get_value(row)
+                .unwrap()
+                .downcast_ref::<f64>()
+                .unwrap()
+        };
+        // The first group is the odd values 1, 3, ..., 999.
+        assert_eq!(value(0, 1), 500.0);
+        assert!((value(0, 2) - 949.1).abs() < 1e-9);
+        assert!((value(0, 3) - 949.1).abs() < 5.0);
+        assert_eq!(value(1, 1), 501.0);
+    }
+
+    #[test]
+    fn test_execute_nested_types() {
+        use crate::data_types::{arrow_field_array::ArrowFieldArray, column_array::ArrayRef};
+        use arrow::{
+            array::{Array, Int64Array, ListBuilder, StringArray, StringBuilder, StructArray},
+            datatypes::{DataType as ArrowDataType, Field as ArrowField},
+        };
+
+        let user = StructArray::from(vec![
+            (
+                ArrowField::new("name", ArrowDataType::Utf8, true),
+                Arc::new(StringArray::from(vec!["ann", "bob", "cat"])) as Arc<dyn Array>,
+            ),
+            (
+                ArrowField::new("age", ArrowDataType::Int64, true),
+                Arc::new(Int64Array::from(vec![31, 25, 40])) as Arc<dyn Array>,
+            ),
+        ]);
+        let mut tags = ListBuilder::new(StringBuilder::new(8));
+        for row in [vec!["a", "b"], vec!["c"], vec![]] {
+            row.iter()
+                .for_each(|t| tags.values().append_value(t).unwrap());
+            tags.append(true).unwrap();
+        }
+        let fields = vec![
+            Arc::new(ArrowFieldArray::new(Box::new(user))) as ArrayRef,
+            Arc::new(ArrowFieldArray::new(Box::new(tags.finish()))) as ArrayRef,
+        ];
+        let schema = Schema::new(
+            fields
+                .iter()
+                .zip(["user", "tags"])
+                .map(|(f, name)| Field::new(name.to_string(), f.get_type()))
+                .collect(),
+        );
+        let batch = RecordBatch::new(schema.clone(), fields);
+        let source = Source::Mem(MemoryDataSource::new(schema, vec![batch]));
+        let df = DataFrame::new(LogicalPlan::Scan(Scan::new(
+            "users".to_string(),
+            source,
+            vec![],
+        )))
+        .filter(col("user").field("age").gt(lit(30_i64)))
+        .project(vec![
+            col("user").field("name"),
+            col("tags").index(lit(1)),
+            col("tags"),
+        ]);
+
+        let ctx = ExecutionContext::new(3);
+        let fields = ctx.validate(&df).unwrap().fields;
+        assert_eq!(fields[0].name, "user.name");
+        assert_eq!(fields[1].name, "tags[1]");
+        assert_eq!(fields[0].data_type, DataType::Utf8);
+        assert_eq!(fields[1].data_type, DataType::Utf8);
+        assert_eq!(
+            fields[2].data_type,
+            DataType::List(Box::new(DataType::Utf8))
+        );
+        let batches = ctx.execute(&df).unwrap();
+        let batch = &batches[0];
+        assert_eq!(batch.row_count(), 2);
+        let string = |column: usize, row| {
+            let value = batch.field(column).get_value(row).unwrap();
+            value.downcast_ref::<String>().unwrap().clone()
+        };
+        assert_eq!(string(0, 0), "ann");
+        assert_eq!(string(0, 1), "cat");
+        assert_eq!(string(1, 0), "b");
+        assert!(batch.field(1).is_null(1));
+        let tags = batch.field(2).get_value(0).unwrap();
+        assert_eq!(
+            tags.downcast_ref::<Vec<Option<Box<dyn Any>>>>()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let grouped = df.aggregate(vec![col("tags")], vec![max(col("tags[1]"))]);
+        let batches = ctx.execute(&grouped).unwrap();
+        assert_eq!(batches[0].row_count(), 2);
+        assert_eq!(batches[0].field(0).get_type(), fields[2].data_type);
+
+        let err = ctx
+            .validate(&df.project(vec![col("tags").field("name")]))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Cannot get field name of List(Utf8)");
+    }
+
+    #[test]
+    fn test_execute_generate_series() {
+        let ctx = ExecutionContext::new(3);
+        let df = ctx
+            .generate_series(lit(1), lit(10), lit(2))
+            .unwrap()
+            .filter(col("value").gt(lit(3)));
+        let batches = ctx.execute(&df).unwrap();
+        let values = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        *b.field(0)
+                            .get_value(i)
+                            .unwrap()
+                            .downcast_ref::<i64>()
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![5, 7, 9]);
+
+        let df = ctx
+            .table_function("generate_series", vec![lit(0.0), lit(1.0)])
+            .unwrap();
+        assert_eq!(df.schema().fields[0].data_type, DataType::Float64);
+        assert!(ctx
+            .table_function("generate_series", vec![col("a"), lit(1)])
+            .is_err());
+        assert!(ctx.table_function("unknown", vec![]).is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_explain() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int32),
+        ]);
+        let df = ctx
+            .csv(data_path.clone(), schema.clone())
+            .filter(col("c1").gt(lit(1_i32)))
+            .aggregate(vec![], vec![max(col("c2"))]);
+        let explain = |analyze| {
+            ctx.explain(&df, analyze)
+                .unwrap()
+                .replace(&data_path, "primitive_field.csv")
+        };
+        assert_eq!(
+            explain(false),
+            "Logical plan:\n\
+             \tAggregate: groupExpr=, aggregateExpr=MAX(#c2)\n\
+             \t\tSelection: #c1 > 1\n\
+             \t\t\tScan: primitive_field.csv; projection=[c1,c2]\n\
+             Physical plan:\n\
+             \tHashAggregateExec: groupExpr=, aggrExpr=MAX(#0)\n\
+             \t\tPipelineExec: morsel_size=16384\n\
+             \t\t\tHashAggregateExec: groupExpr=, aggrExpr=MAX(#1)\n\
+             \t\t\t\tSelectionExec: #0 > 1\n\
+             \t\t\t\t\tScanExec: projection=c1,c2\n"
+        );
+
+        // The elapsed times vary between runs.
+        let metrics = explain(true)
+            .lines()
+            .skip(5)
+            .map(|line| line.split(", elapsed=").next().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            metrics,
+            vec![
+                "\tHashAggregateExec: groupExpr=, aggrExpr=MAX(#0), \
+                 metrics=[output_rows=1, output_batches=1",
+                "\t\tPipelineExec: morsel_size=16384",
+                "\t\t\tHashAggregateExec: groupExpr=, aggrExpr=MAX(#1), \
+                 metrics=[output_rows=1, output_batches=1",
+                "\t\t\t\tSelectionExec: #0 > 1, metrics=[output_rows=2, output_batches=1",
+                "\t\t\t\t\tScanExec: projection=c1,c2, metrics=[output_rows=3, output_batches=1",
+            ]
+        );
+        assert_eq!(df.explain(false).unwrap(), ctx.explain(&df, false).unwrap());
+
+        // Analyzing a query fails with the errors of its execution.
+        let df = ctx
+            .csv(data_path, schema)
+            .project(vec![col("c1") / lit(0_i32)]);
+        assert!(ctx.explain(&df, false).is_ok());
+        assert_eq!(
+            ctx.explain(&df, true).unwrap_err().to_string(),
+            "Division by zero"
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_to_dot() {
+        let ctx = ExecutionContext::new(3);
+        let data_path = rq_test_data("primitive_field.csv");
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int32),
+        ]);
+        let df = ctx
+            .csv(data_path.clone(), schema)
+            .filter(col("c1").gt(lit(1_i32)));
+        let logical_plan = ctx.optimize(&df.logical_plan()).unwrap();
+        assert_eq!(
+            logical_plan
+                .to_dot()
+                .replace(&data_path, "primitive_field.csv"),
+            "digraph plan {\n  \
+             node [shape=record, fontname=\"monospace\"];\n  \
+             0 [label=\"{Selection: #c1 \\> 1\\l|c1: Int32\\lc2: Int32\\l}\"];\n  \
+             1 [label=\"{Scan: primitive_field.csv; projection=[c1,c2]\\l\
+             |c1: Int32\\lc2: Int32\\l}\"];\n  \
+             0 -> 1;\n\
+             }"
+        );
+
+        let physical_plan = ctx
+            .query_planner(ctx.memory_budget())
+            .unwrap()
+            .with_collect_metrics(true)
+            .create_physical_plan(&logical_plan)
+            .unwrap();
+        physical_plan.execute_all().unwrap().for_each(drop);
+        // The elapsed times vary between runs.
+        let dot = physical_plan.to_dot();
+        let nodes = dot
+            .lines()
+            .filter(|line| line.contains("label="))
+            .map(|line| line.split(", elapsed=").next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            nodes,
+            vec![
+                "  0 [label=\"{PipelineExec: morsel_size=16384\\l|c1: Int32\\lc2: Int32\\l}\"];",
+                "  1 [label=\"{SelectionExec: #0 \\> 1\\l|c1: Int32\\lc2: Int32\\l\
+                 |output_rows=2, output_batches=1",
+                "  2 [label=\"{ScanExec: projection=c1,c2\\l|c1: Int32\\lc2: Int32\\l\
+                 |output_rows=3, output_batches=1",
+            ]
+        );
+        assert!(dot.ends_with("  1 -> 2;\n  0 -> 1;\n}"));
+    }
+
+    #[test]
+    fn test_execute_with_memory_limit() {
+        let series = |ctx: &ExecutionContext| {
+            ctx.generate_series(lit(1_i64), lit(10_000_i64), lit(1_i64))
+                .unwrap()
+        };
+        let grouped = |ctx: &ExecutionContext| {
+            let df = series(ctx).aggregate(vec![col("value")], vec![count(col("value"))]);
+            ctx.execute(&df)
+        };
+        let ranked = |ctx: &ExecutionContext| {
+            let df = series(ctx).window(vec![row_number(vec![], vec![col("value")])]);
+            ctx.execute(&df)
+        };
+
+        let ctx = ExecutionContext::new(1024).with_memory_limit(64 * 1024);
+        assert_eq!(
+            grouped(&ctx).err().unwrap().to_string().split(':').next(),
+            Some("HashExec exceeded the memory limit of 65536 bytes")
+        );
+        assert!(ranked(&ctx)
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("WindowExec exceeded the memory limit"));
+
+        // Every query has the whole limit.
+        let ctx = ExecutionContext::new(1024).with_memory_limit(16 * 1024 * 1024);
+        assert_eq!(grouped(&ctx).unwrap()[0].row_count(), 10_000);
+        assert_eq!(grouped(&ctx).unwrap()[0].row_count(), 10_000);
+        assert!(ranked(&ctx).is_ok());
+        assert_eq!(ctx.memory_pool().used(), 0);
+        assert!(ctx.memory_pool().peak() > 64 * 1024);
+
+        // All the queries share the global limit.
+        let ctx = ExecutionContext::new(1024).with_global_memory_limit(64 * 1024);
+        assert!(grouped(&ctx)
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("HashExec exceeded the global memory limit of 65536 bytes"));
+    }
+
+    #[test]
+    fn test_query_peak_memory() {
+        struct PeakRecorder(Arc<Mutex<Option<usize>>>);
+
+        impl QueryEventSubscriber for PeakRecorder {
+            fn on_event(&self, event: &QueryEvent) {
+                if let QueryEvent::QueryFinished { peak_memory, .. } = event {
+                    *self.0.lock().unwrap() = Some(*peak_memory);
+                }
+            }
+        }
+
+        let ctx = ExecutionContext::new(1024);
+        let peak = Arc::new(Mutex::new(None));
+        ctx.subscribe(Arc::new(PeakRecorder(peak.clone())));
+        let series = ctx
+            .generate_series(lit(1_i64), lit(10_000_i64), lit(1_i64))
+            .unwrap();
+        ctx.execute(&series).unwrap();
+        assert_eq!(*peak.lock().unwrap(), Some(0));
+
+        let df = series.aggregate(vec![col("value")], vec![count(col("value"))]);
+        ctx.execute(&df).unwrap();
+        assert!(peak.lock().unwrap().unwrap() > 0);
+        assert_eq!(peak.lock().unwrap().unwrap(), ctx.memory_pool().peak());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_null_literal() {
+        let ctx = ExecutionContext::new(4);
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int64),
+        ]);
+        let df = ctx
+            .csv(rq_test_data("nullable_field.csv"), schema)
+            .project(vec![
+                typed_null(DataType::Float64).alias("nothing".to_string()),
+                coalesce(vec![col("c1"), null()]),
+            ]);
+        let batches = ctx.execute(&df).unwrap();
+        let nothing = batches[0].field(0);
+        assert_eq!(nothing.get_type(), DataType::Float64);
+        assert!((0..nothing.size()).all(|i| nothing.is_null(i)));
+        let coalesced = batches[0].field(1);
+        assert_eq!(coalesced.get_type(), DataType::Int32);
+        assert_eq!(
+            (0..coalesced.size())
+                .map(|i| coalesced.is_null(i))
+                .collect::<Vec<_>>(),
+            vec![true, false, true, false]
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_read_csv() {
+        let ctx = ExecutionContext::new(2);
+        let df = ctx
+            .table_function("read_csv", vec![lit(rq_test_data("primitive_field.csv"))])
+            .unwrap();
+        assert_eq!(
+            df.schema()
+                .fields
+                .iter()
+                .map(|f| f.data_type.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                DataType::Int64,
+                DataType::Int64,
+                DataType::Int64,
+                DataType::Int64,
+                DataType::Float64,
+                DataType::Float64
+            ]
+        );
+        let df = df.filter(col("c1").gt(lit(1_i64))).project(vec![col("c2")]);
+        let batches = ctx.execute(&df).unwrap();
+        assert_eq!(batches.iter().map(|b| b.row_count()).sum::<usize>(), 2);
+
+        assert!(ctx
+            .table_function("read_csv", vec![lit(rq_test_data("missing.csv"))])
+            .is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_distinct_on() {
+        let ctx = ExecutionContext::new(4);
+        let schema = Schema::new(vec![
+            Field::new("user".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        let events = ctx.csv(rq_test_data("events.csv"), schema);
+        let rows = |df: &DataFrame| {
+            ctx.execute(df)
+                .unwrap()
+                .iter()
+                .flat_map(|b| {
+                    (0..b.row_count())
+                        .map(|i| {
+                            (
+                                b.field(0)
+                                    .get_value(i)
+                                    .unwrap()
+                                    .downcast_ref::<String>()
+                                    .unwrap()
+                                    .clone(),
+                                *b.field(1)
+                                    .get_value(i)
+                                    .unwrap()
+                                    .downcast_ref::<i64>()
+                                    .unwrap(),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = events
+            .distinct_on(vec![col("user")], vec![col("ts").sort(true, false)])
+            .project(vec![col("user"), col("ts")]);
+        assert_eq!(
+            rows(&first),
+            vec![("alice".to_string(), 0), ("bob".to_string(), 10)]
+        );
+        let last = events
+            .distinct_on(vec![col("user")], vec![col("ts").sort(false, false)])
+            .project(vec![col("user"), col("ts")]);
+        assert_eq!(
+            rows(&last),
+            vec![("alice".to_string(), 5100), ("bob".to_string(), 20)]
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_window() {
+        let ctx = ExecutionContext::new(4);
+        let schema = Schema::new(vec![
+            Field::new("user".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        let events = ctx.csv(rq_test_data("events.csv"), schema);
+
+        // Keep the latest event of each user.
+        let latest = events
+            .window(vec![row_number(
+                vec![col("user")],
+                vec![lit(0_i64) - col("ts")],
+            )
+            .alias("rn".to_string())])
+            .filter(col("rn").eq(lit(1_i64)))
+            .project(vec![col("user"), col("ts")]);
+        let batches = ctx.execute(&latest).unwrap();
+        let rows = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        (
+                            b.field(0)
+                                .get_value(i)
+                                .unwrap()
+                                .downcast_ref::<String>()
+                                .unwrap()
+                                .clone(),
+                            *b.field(1)
+                                .get_value(i)
+                                .unwrap()
+                                .downcast_ref::<i64>()
+                                .unwrap(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![("bob".to_string(), 20), ("alice".to_string(), 5100)]
+        );
+
+        let ranked = events
+            .window(vec![
+                rank(vec![], vec![col("user")]),
+                dense_rank(vec![], vec![col("user")]),
+            ])
+            .project(vec![col("rank"), col("dense_rank")]);
+        let batches = ctx.execute(&ranked).unwrap();
+        let ranks = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        (
+                            *b.field(0)
+                                .get_value(i)
+                                .unwrap()
+                                .downcast_ref::<i64>()
+                                .unwrap(),
+                            *b.field(1)
+                                .get_value(i)
+                                .unwrap()
+                                .downcast_ref::<i64>()
+                                .unwrap(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ranks, vec![(1, 1), (5, 2), (1, 1), (1, 1), (5, 2), (1, 1)]);
+
+        // The time since the previous event of the user, or null for the first event.
+        let deltas = events
+            .window(vec![lag(
+                col("ts"),
+                1,
+                null(),
+                vec![col("user")],
+                vec![col("ts")],
+            )])
+            .project(vec![col("ts") - col("lag")]);
+        let batches = ctx.execute(&deltas).unwrap();
+        let deltas = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        (!b.field(0).is_null(i)).then(|| {
+                            *b.field(0)
+                                .get_value(i)
+                                .unwrap()
+                                .downcast_ref::<i64>()
+                                .unwrap()
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deltas,
+            vec![None, None, Some(100), Some(4900), Some(10), Some(100)]
+        );
+
+        let quartiles = events
+            .window(vec![ntile(4, vec![], vec![col("ts")])])
+            .project(vec![col("ntile")]);
+        let batches = ctx.execute(&quartiles).unwrap();
+        let quartiles = batches
+            .iter()
+            .flat_map(|b| {
+                (0..b.row_count())
+                    .map(|i| {
+                        *b.field(0)
+                            .get_value(i)
+                            .unwrap()
+                            .downcast_ref::<i64>()
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(quartiles, vec![1, 1, 2, 3, 2, 4]);
+
+        let invalid = events.project(vec![row_number(vec![], vec![col("ts")])]);
+        assert_eq!(
+            ctx.execute(&invalid).err().unwrap().to_string(),
+            "Window functions can only be computed by a window plan"
+        );
+    }
+
+    #[test]
+    fn test_execute_with_overflow_mode() {
+        let series = |ctx: &ExecutionContext| {
+            ctx.generate_series(lit(i64::MAX - 1), lit(i64::MAX), lit(1_i64))
+                .unwrap()
+                .project(vec![col("value") * lit(2_i64)])
+        };
+        let ctx = ExecutionContext::new(4);
+        assert!(ctx.execute(&series(&ctx)).is_err());
+
+        let ctx = ExecutionContext::new(4).with_overflow_mode(OverflowMode::Saturating);
+        let batches = ctx.execute(&series(&ctx)).unwrap();
+        let doubled = batches[0].field(0);
+        assert_eq!(
+            *doubled.get_value(1).unwrap().downcast_ref::<i64>().unwrap(),
+            i64::MAX
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_execute_fill() {
+        let ctx = ExecutionContext::new(4);
+        let schema = Schema::new(vec![
+            Field::new("ts".to_string(), DataType::Int64),
+            Field::new("value".to_string(), DataType::Int64),
+        ]);
+        let readings = ctx.csv(rq_test_data("readings.csv"), schema);
+        let values = |df: &DataFrame| {
+            ctx.execute(df)
+                .unwrap()
+                .iter()
+                .flat_map(|b| {
+                    let value = b.field(1);
+                    (0..b.row_count())
+                        .map(|i| {
+                            (!value.is_null(i)).then(|| {
+                                *value.get_value(i).unwrap().downcast_ref::<i64>().unwrap()
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let df = readings.fill_null("value", lit(0)).unwrap();
+        assert_eq!(df.schema().fields[1].data_type, DataType::Int64);
+        assert_eq!(
+            values(&df),
+            vec![Some(0), Some(10), Some(0), Some(50), Some(0), Some(0)]
+        );
+        let df = readings.ffill(vec!["value"], vec![col("ts")]);
+        assert_eq!(
+            values(&df),
+            vec![Some(10), Some(10), Some(10), Some(50), Some(10), Some(50)]
+        );
+        let df = readings.bfill(vec!["value"], vec![col("ts")]);
+        assert_eq!(
+            values(&df),
+            vec![Some(50), Some(10), Some(50), Some(50), Some(50), None]
+        );
+        assert!(readings.fill_null("missing", lit(0)).is_err());
+    }
+
+    #[test]
+    fn test_plan_fragment() {
+        let ctx = ExecutionContext::new(4);
+        let rows = |batches: Vec<RecordBatch>| {
+            batches
+                .iter()
+                .flat_map(|b| {
+                    (0..b.row_count())
+                        .map(|i| {
+                            (0..b.column_count())
+                                .map(|c| ScalarValue::try_from_array(b.field(c).as_ref(), i))
+                                .map(|v| v.unwrap().to_string())
+                                .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let df = ctx
+            .sql(
+                "SELECT value % 3 AS k, SUM(value * 2) AS s, COUNT(*) AS n \
+                 FROM generate_series(1, 20) WHERE value > 2 \
+                 GROUP BY 1 ORDER BY k DESC LIMIT 2",
+            )
+            .unwrap();
+        let plan = ctx.create_physical_plan(&df).unwrap();
+        let fragment = PlanFragment::try_new(&plan).unwrap();
+        let json = fragment.to_json().unwrap();
+
+        // The worker plans the fragment with its own memory budget and scheduler.
+        let scheduler =
+            MorselScheduler::try_new(ctx.thread_pool().unwrap().clone(), DEFAULT_MORSEL_SIZE)
+                .unwrap();
+        let worker_plan = PlanFragment::from_json(&json)
+            .unwrap()
+            .create_physical_plan(&MemoryBudget::default(), Some(&scheduler))
+            .unwrap();
+        assert_eq!(
+            PlanFragment::try_new(&worker_plan)
+                .unwrap()
+                .to_json()
+                .unwrap(),
+            json
+        );
+        let expected = rows(ctx.execute(&df).unwrap());
+        assert_eq!(expected.len(), 2);
+        assert_eq!(
+            rows(
+                worker_plan
+                    .execute_all()
+                    .unwrap()
+                    .collect::<Result<_>>()
+                    .unwrap()
+            ),
+            expected
+        );
+
+        // Memory tables hold their data, and are not shipped with the plan.
+        let source = MemoryDataSource::new(df.schema(), vec![]);
+        ctx.register_table("t", Source::Mem(source));
+        let plan = ctx
+            .create_physical_plan(&ctx.sql("SELECT k FROM t").unwrap())
+            .unwrap();
+        let error = PlanFragment::try_new(&plan).unwrap().to_json().unwrap_err();
+        assert!(error.to_string().contains("cannot be serialized"));
+    }
+
+    #[test]
+    fn test_execute_malformed_csv() {
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int64)]);
+        ctx.register_csv_bytes("t", b"1\n2\nx\n4\n".to_vec(), schema);
+        let df = ctx.table("t").unwrap().filter(col("c1").gt(lit(0_i64)));
+        let error = df.collect(&ctx).err().unwrap();
+        assert!(
+            format!("{:#}", error).contains("Invalid value at line 3, column c1: x is not a Int64")
+        );
+    }
 }
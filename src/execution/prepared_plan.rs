@@ -0,0 +1,225 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    data_types::{column_array::DataType, record_batch::RecordBatch, schema::Schema},
+    logical_plan::{
+        expr::{Expr, LogicalExpr as _, ScalarValue},
+        plan::{LogicalPlan as _, Plan as LogicalPlan},
+    },
+    physical_plan::{
+        expr::{Parameters, ScalarValue as PhysicalScalarValue},
+        plan::{PhysicalPlan as _, Plan as PhysicalPlan},
+    },
+    query_planner::planner::create_physical_scalar,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+/// A planned query with placeholders `$1`, `$2`, ... for its parameters.
+/// It is bound to different values for the parameters without being planned again,
+/// and the bound plans are executed independently of each other.
+pub struct PreparedPlan {
+    // Shared by the bound plans, which execute it with their own parameters.
+    physical_plan: Arc<PhysicalPlan>,
+    // The type of every placeholder, by its position.
+    placeholders: BTreeMap<usize, DataType>,
+}
+
+impl PreparedPlan {
+    /// Create a prepared plan from the physical plan created for the logical plan.
+    pub fn try_new(physical_plan: PhysicalPlan, logical_plan: &LogicalPlan) -> Result<Self> {
+        let mut placeholders = BTreeMap::new();
+        collect_placeholders(logical_plan, &mut placeholders)?;
+        Ok(Self {
+            physical_plan: Arc::new(physical_plan),
+            placeholders,
+        })
+    }
+
+    /// Return the number of parameters the plan is executed with.
+    pub fn param_count(&self) -> usize {
+        self.placeholders.keys().max().copied().unwrap_or_default()
+    }
+
+    /// Return the type of the placeholder at `position`, starting from 1.
+    pub fn param_type(&self, position: usize) -> Option<&DataType> {
+        self.placeholders.get(&position)
+    }
+
+    pub fn schema(&self) -> Schema {
+        self.physical_plan.schema()
+    }
+
+    /// Bind the values to the placeholders, `$1` to the first one, returning a plan that
+    /// executes with them. A NULL takes the type of its placeholder.
+    pub fn bind(&self, params: &[ScalarValue]) -> Result<BoundPlan> {
+        if params.len() != self.param_count() {
+            bail!(
+                "Expected {} parameters, got {}",
+                self.param_count(),
+                params.len()
+            );
+        }
+        let values = params
+            .iter()
+            .enumerate()
+            .filter_map(|(i, value)| {
+                let data_type = self.placeholders.get(&(i + 1))?;
+                Some(bind(i + 1, value, data_type))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(BoundPlan {
+            physical_plan: self.physical_plan.clone(),
+            parameters: Parameters::new(values),
+        })
+    }
+
+    /// Bind the values to the placeholders and execute the plan,
+    /// collecting all the result batches.
+    pub fn execute(&self, params: &[ScalarValue]) -> Result<Vec<RecordBatch>> {
+        self.bind(params)?.collect()
+    }
+}
+
+/// A prepared plan bound to values for its parameters.
+pub struct BoundPlan {
+    physical_plan: Arc<PhysicalPlan>,
+    parameters: Parameters,
+}
+
+impl BoundPlan {
+    pub fn schema(&self) -> Schema {
+        self.physical_plan.schema()
+    }
+
+    /// Execute the plan with the parameters in scope, collecting all the result batches.
+    pub fn collect(&self) -> Result<Vec<RecordBatch>> {
+        self.parameters
+            .scope(|| self.physical_plan.execute_all()?.collect())
+    }
+
+    /// Execute the plan with the parameters in scope, passing the result batches to `f`
+    /// as they are produced, until it returns false.
+    pub fn try_for_each(&self, mut f: impl FnMut(RecordBatch) -> bool) -> Result<()> {
+        self.parameters.scope(|| {
+            for batch in self.physical_plan.execute_all()? {
+                if !f(batch?) {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Return the id and physical value bound to the placeholder at `position`,
+/// checking that the value has the type of the placeholder.
+fn bind(
+    position: usize,
+    value: &ScalarValue,
+    data_type: &DataType,
+) -> Result<(String, PhysicalScalarValue)> {
+    let value = match value {
+        ScalarValue::Null(_) => ScalarValue::Null(Some(data_type.clone())),
+        value => value.clone(),
+    };
+    let value_type = value.data_type();
+    if &value_type != data_type {
+        bail!(
+            "Placeholder ${} has type {}, got {} of type {}",
+            position,
+            data_type,
+            value,
+            value_type
+        );
+    }
+    Ok((format!("${}", position), create_physical_scalar(&value)))
+}
+
+fn collect_placeholders(
+    plan: &LogicalPlan,
+    placeholders: &mut BTreeMap<usize, DataType>,
+) -> Result<()> {
+    plan.exprs()
+        .into_iter()
+        .try_for_each(|e| collect_expr_placeholders(e, plan, placeholders))?;
+    plan.children()
+        .iter()
+        .try_for_each(|child| collect_placeholders(child, placeholders))
+}
+
+fn collect_expr_placeholders(
+    expr: &Expr,
+    plan: &LogicalPlan,
+    placeholders: &mut BTreeMap<usize, DataType>,
+) -> Result<()> {
+    if let Expr::Placeholder(p) = expr {
+        let position = placeholder_position(&p.id)?;
+        let data_type = p.to_field(plan)?.data_type;
+        if let Some(other) = placeholders.insert(position, data_type.clone()) {
+            if other != data_type {
+                bail!(
+                    "Placeholder {} is used with types {} and {}",
+                    p.id,
+                    other,
+                    data_type
+                );
+            }
+        }
+    }
+    expr.children()
+        .into_iter()
+        .try_for_each(|e| collect_expr_placeholders(e, plan, placeholders))
+}
+
+/// Return the position of the placeholder `$n`.
+fn placeholder_position(id: &str) -> Result<usize> {
+    id.strip_prefix('$')
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .ok_or_else(|| anyhow!("Invalid placeholder {}, expected $1, $2, ...", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        execution::{session_config::SessionConfig, ExecutionContext},
+        logical_plan::expr_fn::{col, lit, placeholder},
+    };
+
+    #[test]
+    fn test_bind_without_planning() {
+        // The scan is split into morsels, so the placeholders are evaluated on the threads
+        // of the pool.
+        let ctx =
+            ExecutionContext::new(2).with_session_config(SessionConfig::new().with_morsel_size(2));
+        let series = ctx.generate_series(lit(1), lit(100), lit(1)).unwrap();
+        let df = series.filter(col("value").gt(placeholder("$1")));
+        let prepared = ctx.prepare(&df).unwrap();
+
+        let bound = (0..4)
+            .map(|i| prepared.bind(&[ScalarValue::Int64(i * 25)]).unwrap())
+            .collect::<Vec<_>>();
+        // All the bindings execute the plan created when it was prepared.
+        assert!(bound
+            .iter()
+            .all(|b| Arc::ptr_eq(&b.physical_plan, &prepared.physical_plan)));
+        let rows = std::thread::scope(|scope| {
+            let handles = bound
+                .iter()
+                .map(|b| scope.spawn(|| b.collect().unwrap()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap().iter().map(|b| b.row_count()).sum())
+                .collect::<Vec<usize>>()
+        });
+        assert_eq!(rows, vec![100, 75, 50, 25]);
+        // The parameters are only in scope while a bound plan executes.
+        assert_eq!(
+            df.collect(&ctx).err().unwrap().to_string(),
+            "No value bound to placeholder $1"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use crate::logical_plan::{data_frame::DataFrame, expr::Expr};
+
+use anyhow::{bail, Result};
+
+// Builds the DataFrame of a template from its arguments.
+type TemplateFn = Box<dyn Fn(&[Expr]) -> DataFrame + Send + Sync>;
+
+/// A named, parameterized query.
+/// The template is expanded into a DataFrame each time it is invoked with arguments.
+pub struct QueryTemplate {
+    params: Vec<String>,
+    build: TemplateFn,
+}
+
+impl QueryTemplate {
+    pub fn new(
+        params: Vec<String>,
+        build: impl Fn(&[Expr]) -> DataFrame + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            params,
+            build: Box::new(build),
+        }
+    }
+
+    /// Return the names of the parameters.
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// Expand the template with the given arguments.
+    pub fn expand(&self, args: &[Expr]) -> Result<DataFrame> {
+        if args.len() != self.params.len() {
+            bail!(
+                "Expected {} arguments ({}), got {}",
+                self.params.len(),
+                self.params.join(", "),
+                args.len()
+            );
+        }
+
+        Ok((self.build)(args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logical_plan::{
+            expr_fn::{col, lit},
+            plan::Plan,
+            scan::Scan,
+        },
+        test_util::get_primitive_field_data_source,
+    };
+
+    fn template() -> QueryTemplate {
+        QueryTemplate::new(vec!["min_c1".to_string()], |args| {
+            let (path, csv_data_source) = get_primitive_field_data_source();
+            DataFrame::new(Plan::Scan(Scan::new(path, csv_data_source, vec![])))
+                .filter(col("c1").gt_eq(args[0].clone()))
+        })
+    }
+
+    #[test]
+    fn test_expand() {
+        let df = template().expand(&[lit(2)]).unwrap();
+        assert_eq!(df.logical_plan().to_string(), "Selection: #c1 >= 2");
+    }
+
+    #[test]
+    fn test_expand_with_wrong_arguments() {
+        assert_eq!(
+            template().expand(&[]).err().unwrap().to_string(),
+            "Expected 1 arguments (min_c1), got 0"
+        );
+    }
+}
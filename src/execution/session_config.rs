@@ -0,0 +1,214 @@
+use std::{
+    io,
+    num::NonZeroUsize,
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use crate::physical_plan::pipeline::DEFAULT_MORSEL_SIZE;
+
+use anyhow::{anyhow, bail, Result};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// How a session runs the partitions of its queries.
+/// The partitions of a plan run in parallel on a thread pool, either the one supplied
+/// by the application or one the session creates the first time it is needed.
+#[derive(Clone)]
+pub struct SessionConfig {
+    /// The number of threads of the pool created by the session.
+    pub target_parallelism: usize,
+    /// The cores the threads of the pool created by the session are pinned to, in turn.
+    pub pinned_cores: Option<Vec<usize>>,
+    /// The pool supplied by the application, used instead of creating one.
+    pub thread_pool: Option<Arc<ThreadPool>>,
+    /// The number of rows of the morsels the pipelines of a query are split into,
+    /// [`DEFAULT_MORSEL_SIZE`] by default. Pipelines run partition by partition when it
+    /// is None, as they do on WebAssembly, which has no threads to run morsels on.
+    pub morsel_size: Option<usize>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            target_parallelism: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            pinned_cores: None,
+            thread_pool: None,
+            morsel_size: (!cfg!(target_family = "wasm")).then_some(DEFAULT_MORSEL_SIZE),
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of partitions run at the same time.
+    pub fn with_target_parallelism(mut self, target_parallelism: usize) -> Self {
+        self.target_parallelism = target_parallelism;
+        self
+    }
+
+    /// Pin the threads of the pool to the given cores. Only supported on Linux.
+    /// Creating the pool fails if a core is not available to the process, or a thread
+    /// cannot be pinned to it.
+    pub fn with_pinned_cores(mut self, cores: Vec<usize>) -> Self {
+        self.pinned_cores = Some(cores);
+        self
+    }
+
+    /// Run the partitions on a pool owned by the application, so no thread is created.
+    pub fn with_thread_pool(mut self, thread_pool: Arc<ThreadPool>) -> Self {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
+
+    /// Split the scans of the pipelines into morsels of the number of rows, processed
+    /// through all the operators of the pipeline by whichever thread of the pool is idle.
+    pub fn with_morsel_size(mut self, morsel_size: usize) -> Self {
+        self.morsel_size = Some(morsel_size);
+        self
+    }
+
+    /// Run the pipelines partition by partition, each on the thread running its partition.
+    pub fn without_morsels(mut self) -> Self {
+        self.morsel_size = None;
+        self
+    }
+
+    /// Return the pool supplied by the application, or create one as configured.
+    pub fn create_thread_pool(&self) -> Result<Arc<ThreadPool>> {
+        if let Some(thread_pool) = &self.thread_pool {
+            return Ok(thread_pool.clone());
+        }
+        if self.target_parallelism == 0 {
+            bail!("The target parallelism must be at least 1");
+        }
+        let mut builder = ThreadPoolBuilder::new()
+            .num_threads(self.target_parallelism)
+            .thread_name(|i| format!("rq-worker-{}", i));
+        let Some(cores) = self.pinned_cores.clone() else {
+            return Ok(Arc::new(builder.build()?));
+        };
+        if cores.is_empty() {
+            bail!("No cores to pin the threads to");
+        }
+        if !cfg!(target_os = "linux") {
+            bail!("Pinning threads to cores is only supported on Linux");
+        }
+        let available = available_cores()?;
+        if let Some(core) = cores.iter().find(|core| !available.contains(core)) {
+            bail!(
+                "Cannot pin threads to core {}, the available cores are {:?}",
+                core,
+                available
+            );
+        }
+        // The threads report whether they were pinned, so a failure fails the pool
+        // rather than leaving the thread unpinned.
+        let (sender, receiver) = mpsc::channel();
+        builder = builder.start_handler(move |i| {
+            let core = cores[i % cores.len()];
+            let pinned = pin_current_thread(core)
+                .map_err(|e| anyhow!("Cannot pin thread {} to core {}: {}", i, core, e));
+            let _ = sender.send(pinned);
+        });
+        let pool = builder.build()?;
+        for pinned in receiver.iter().take(self.target_parallelism) {
+            pinned?;
+        }
+        Ok(Arc::new(pool))
+    }
+}
+
+/// Return the cores the process is allowed to run on.
+#[cfg(target_os = "linux")]
+fn available_cores() -> Result<Vec<usize>> {
+    // SAFETY: the set is a plain bitmask, filled in by the kernel.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            bail!(
+                "Cannot read the available cores: {}",
+                io::Error::last_os_error()
+            );
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|&core| libc::CPU_ISSET(core, &set))
+            .collect())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn available_cores() -> Result<Vec<usize>> {
+    Ok(vec![])
+}
+
+/// Pin the current thread to the core, which must be below `CPU_SETSIZE`.
+#[cfg(target_os = "linux")]
+fn pin_current_thread(core: usize) -> io::Result<()> {
+    // SAFETY: the set is a plain bitmask, zeroed before the core is added to it.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_current_thread(_core: usize) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_thread_pool() {
+        let pool = SessionConfig::new()
+            .with_target_parallelism(2)
+            .create_thread_pool()
+            .unwrap();
+        assert_eq!(pool.current_num_threads(), 2);
+        let name = pool.install(|| thread::current().name().unwrap().to_string());
+        assert!(name.starts_with("rq-worker-"));
+
+        let config = SessionConfig::new().with_thread_pool(pool.clone());
+        assert!(Arc::ptr_eq(&config.create_thread_pool().unwrap(), &pool));
+
+        let config = SessionConfig::new().with_target_parallelism(0);
+        assert_eq!(
+            config.create_thread_pool().unwrap_err().to_string(),
+            "The target parallelism must be at least 1"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pinned_cores() {
+        let pool = SessionConfig::new()
+            .with_target_parallelism(1)
+            .with_pinned_cores(vec![0])
+            .create_thread_pool()
+            .unwrap();
+        let affinity = pool.install(|| unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set);
+            (0..libc::CPU_SETSIZE as usize)
+                .filter(|&c| libc::CPU_ISSET(c, &set))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(affinity, vec![0]);
+
+        let config = SessionConfig::new().with_pinned_cores(vec![libc::CPU_SETSIZE as usize]);
+        assert!(config
+            .create_thread_pool()
+            .unwrap_err()
+            .to_string()
+            .starts_with(&format!("Cannot pin threads to core {}", libc::CPU_SETSIZE)));
+    }
+}
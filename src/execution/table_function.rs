@@ -0,0 +1,85 @@
+#[cfg(feature = "fs")]
+use crate::data_source::csv_data_source::CsvDataSource;
+use crate::{
+    data_source::{
+        series_data_source::{Series, SeriesDataSource},
+        Source,
+    },
+    logical_plan::expr::ScalarValue,
+};
+
+use anyhow::{bail, Result};
+
+/// A function that produces a relation from literal arguments.
+pub trait TableFunction: Send + Sync {
+    /// Create the data source of the relation for the given arguments.
+    fn create_source(&self, args: &[ScalarValue], batch_size: usize) -> Result<Source>;
+}
+
+/// `generate_series(start, stop[, step])` returns the numbers from `start` to `stop`
+/// inclusive in a `value` column. The step defaults to 1. Integer arguments, which are
+/// also used for timestamps, give an Int64 series; any float argument gives a Float64 one.
+pub struct GenerateSeries;
+
+impl TableFunction for GenerateSeries {
+    fn create_source(&self, args: &[ScalarValue], batch_size: usize) -> Result<Source> {
+        if !(2..=3).contains(&args.len()) {
+            bail!(
+                "generate_series expects 2 or 3 arguments, got {}",
+                args.len()
+            );
+        }
+        let step = args.get(2).cloned().unwrap_or(ScalarValue::Int64(1));
+        let series = match (&args[0], &args[1], &step) {
+            (start, stop, step) if [start, stop, step].iter().all(|v| as_i64(v).is_some()) => {
+                Series::Int64(
+                    as_i64(start).unwrap(),
+                    as_i64(stop).unwrap(),
+                    as_i64(step).unwrap(),
+                )
+            }
+            (start, stop, step) => Series::Float64(as_f64(start)?, as_f64(stop)?, as_f64(step)?),
+        };
+        Ok(Source::Series(SeriesDataSource::try_new(
+            series, batch_size,
+        )?))
+    }
+}
+
+/// `read_csv(path)` reads a CSV file without a header, inferring its schema.
+#[cfg(feature = "fs")]
+pub struct ReadCsv;
+
+#[cfg(feature = "fs")]
+impl TableFunction for ReadCsv {
+    fn create_source(&self, args: &[ScalarValue], batch_size: usize) -> Result<Source> {
+        let path = match args {
+            [ScalarValue::String(path)] => path,
+            _ => bail!("read_csv expects a single string argument"),
+        };
+        let schema = CsvDataSource::infer_schema(path)?;
+        Ok(Source::Csv(CsvDataSource::new(
+            path.clone(),
+            schema,
+            batch_size,
+        )))
+    }
+}
+
+fn as_i64(value: &ScalarValue) -> Option<i64> {
+    match value {
+        ScalarValue::Int32(v) => Some(*v as i64),
+        ScalarValue::Int64(v) => Some(*v),
+        _ => None,
+    }
+}
+
+fn as_f64(value: &ScalarValue) -> Result<f64> {
+    Ok(match value {
+        ScalarValue::Int32(v) => *v as f64,
+        ScalarValue::Int64(v) => *v as f64,
+        ScalarValue::Float32(v) => *v as f64,
+        ScalarValue::Float64(v) => *v,
+        _ => bail!("generate_series expects numeric arguments, got {}", value),
+    })
+}
@@ -1,9 +1,17 @@
+pub mod analyzer;
 pub mod data_source;
 pub mod data_types;
+/// Graphviz rendering of plan trees.
+mod dot;
 pub mod execution;
 pub mod logical_plan;
 pub mod optimizer;
 pub mod physical_plan;
 pub mod query_planner;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod sql;
 #[cfg(test)]
 mod test_util;
+/// Helpers for writing tests over query results, such as [`assert_batches_eq!`].
+pub mod testing;
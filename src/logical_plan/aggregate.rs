@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
 
 use super::{
     expr::{Expr, LogicalExpr},
@@ -6,10 +6,12 @@ use super::{
 };
 use crate::data_types::schema::Schema;
 
+use serde::{Deserialize, Serialize};
+
 /// Logical plan representing an aggregate query against an input.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Aggregate {
-    pub input: Box<Plan>,
+    pub input: Arc<Plan>,
     pub group_exprs: Vec<Expr>,
     pub aggregate_exprs: Vec<Expr>,
 }
@@ -55,7 +57,7 @@ impl fmt::Display for Aggregate {
 impl Aggregate {
     pub fn new(input: Plan, group_exprs: Vec<Expr>, aggregate_exprs: Vec<Expr>) -> Self {
         Aggregate {
-            input: Box::new(input),
+            input: Arc::new(input),
             group_exprs,
             aggregate_exprs,
         }
@@ -89,7 +91,7 @@ mod tests {
             agg.schema(),
             Schema::new(vec![
                 Field::new("c1".to_string(), DataType::Int32),
-                Field::new("max".to_string(), DataType::Int32),
+                Field::new("max(c2)".to_string(), DataType::Int32),
             ])
         );
     }
@@ -0,0 +1,70 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::plan::{LogicalPlan, Plan};
+use crate::data_types::{
+    column_array::DataType,
+    schema::{Field, Schema},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Logical plan of a statement that changes the tables or views of the context.
+/// Planning it changes nothing: the context applies the change when it executes the plan.
+/// The plans of the inputs are analyzed and optimized on their own when they are executed,
+/// so they are not children of the command.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Command {
+    /// Register the input as a view, unless a table or another view has the name.
+    CreateView {
+        name: String,
+        input: Arc<Plan>,
+        or_replace: bool,
+        if_not_exists: bool,
+    },
+    /// Remove the views, none of them unless they all exist.
+    DropViews { names: Vec<String>, if_exists: bool },
+    /// Register a CSV file as a table, unless a table has the name.
+    CreateExternalTable {
+        name: String,
+        location: String,
+        schema: Schema,
+        if_not_exists: bool,
+    },
+    /// Append the rows of the input to a memory table, returning their number in a
+    /// `count` column.
+    Insert { table: String, input: Arc<Plan> },
+}
+
+impl LogicalPlan for Command {
+    fn schema(&self) -> Schema {
+        match self {
+            Command::Insert { .. } => {
+                Schema::new(vec![
+                    Field::new("count".to_string(), DataType::Int64).with_nullable(false)
+                ])
+            }
+            _ => Schema::new(vec![]),
+        }
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![]
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::CreateView { name, .. } => write!(f, "CreateView: name={}", name),
+            Command::DropViews { names, .. } => write!(f, "DropViews: names={}", names.join(", ")),
+            Command::CreateExternalTable { name, location, .. } => {
+                write!(
+                    f,
+                    "CreateExternalTable: name={}, location={}",
+                    name, location
+                )
+            }
+            Command::Insert { table, .. } => write!(f, "Insert: table={}", table),
+        }
+    }
+}
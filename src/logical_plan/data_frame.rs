@@ -1,11 +1,34 @@
+use std::io::Write;
+
 use super::{
     aggregate::Aggregate,
-    expr::Expr,
+    distinct_on::DistinctOn,
+    expr::{Expr, LogicalExpr, ScalarValue},
+    expr_fn::{cast, coalesce, col, count, date_bin, lit},
+    fill::{Fill, FillDirection},
+    gap_fill::GapFill,
+    join::Join,
+    limit::Limit,
     plan::{LogicalPlan, Plan},
     projection::Projection,
+    scan::Scan,
     selection::Selection,
+    sessionize::Sessionize,
+    sort::{Sort, SortExpr},
+    window::Window,
+};
+use crate::{
+    data_source::{ipc, memory_data_source::MemoryDataSource, DataSource, Source},
+    data_types::{record_batch::RecordBatch, schema::Schema},
+    execution::ExecutionContext,
+};
+
+use anyhow::{anyhow, bail, Result};
+use arrow::{
+    datatypes::Schema as ArrowSchema, ipc::writer::StreamWriter,
+    record_batch::RecordBatch as ArrowRecordBatch,
 };
-use crate::data_types::schema::Schema;
+use futures::executor::block_on_stream;
 
 #[derive(Clone)]
 pub struct DataFrame {
@@ -34,6 +57,102 @@ impl DataFrame {
         DataFrame::new(plan)
     }
 
+    /// Join with `right` on pairs of keys, the left ones over this data frame and the right
+    /// ones over `right`, keeping the pairs of rows whose keys are equal. Without keys,
+    /// every pair of rows is kept. The column names of both sides must be distinct.
+    pub fn join(&self, right: &DataFrame, on: Vec<(Expr, Expr)>) -> Self {
+        let plan = Plan::Join(Join::new(self.plan.clone(), right.plan.clone(), on));
+        DataFrame::new(plan)
+    }
+
+    /// Split the events of each key into sessions separated by more than `gap`
+    /// between consecutive timestamps, adding a `session_id` column.
+    pub fn sessionize(&self, key: Expr, timestamp: Expr, gap: i64) -> Self {
+        let plan = Plan::Sessionize(Sessionize::new(self.plan.clone(), key, timestamp, gap));
+        DataFrame::new(plan)
+    }
+
+    /// Keep one row per distinct value of the keys, the first in the order of `order_by`.
+    pub fn distinct_on(&self, keys: Vec<Expr>, order_by: Vec<SortExpr>) -> Self {
+        let plan = Plan::DistinctOn(DistinctOn::new(self.plan.clone(), keys, order_by));
+        DataFrame::new(plan)
+    }
+
+    /// Compute window functions, adding a column per window expression.
+    pub fn window(&self, window_exprs: Vec<Expr>) -> Self {
+        let plan = Plan::Window(Window::new(self.plan.clone(), window_exprs));
+        DataFrame::new(plan)
+    }
+
+    /// Order the rows by the expressions, keeping the input order of the rows that tie.
+    pub fn sort(&self, order_by: Vec<SortExpr>) -> Self {
+        let plan = Plan::Sort(Sort::new(self.plan.clone(), order_by));
+        DataFrame::new(plan)
+    }
+
+    /// Skip the first `skip` rows, and keep at most `fetch` of the rows after them.
+    pub fn limit(&self, skip: usize, fetch: Option<usize>) -> Self {
+        let plan = Plan::Limit(Limit::new(self.plan.clone(), skip, fetch));
+        DataFrame::new(plan)
+    }
+
+    /// Replace the nulls of the column by the value, cast to the type of the column.
+    pub fn fill_null(&self, column: &str, value: Expr) -> Result<Self> {
+        let schema = self.schema();
+        let field = schema
+            .fields
+            .iter()
+            .find(|f| f.name == column)
+            .ok_or_else(|| anyhow!("No column named {}", column))?;
+        let exprs = schema
+            .fields
+            .iter()
+            .map(|f| {
+                if f.name == column {
+                    let value = cast(value.clone(), field.data_type.clone());
+                    coalesce(vec![col(&f.name), value]).alias(f.name.clone())
+                } else {
+                    col(&f.name)
+                }
+            })
+            .collect();
+        Ok(self.project(exprs))
+    }
+
+    /// Replace the nulls of the columns by the previous non-null value in
+    /// ascending order of `order_by`.
+    pub fn ffill(&self, columns: Vec<&str>, order_by: Vec<Expr>) -> Self {
+        self.fill(columns, order_by, FillDirection::Forward)
+    }
+
+    /// Replace the nulls of the columns by the next non-null value in
+    /// ascending order of `order_by`.
+    pub fn bfill(&self, columns: Vec<&str>, order_by: Vec<Expr>) -> Self {
+        self.fill(columns, order_by, FillDirection::Backward)
+    }
+
+    fn fill(&self, columns: Vec<&str>, order_by: Vec<Expr>, direction: FillDirection) -> Self {
+        let columns = columns.into_iter().map(|c| c.to_string()).collect();
+        let plan = Plan::Fill(Fill::new(self.plan.clone(), columns, order_by, direction));
+        DataFrame::new(plan)
+    }
+
+    /// Aggregate the rows into time buckets `every` wide, aligned to 0.
+    /// The bucket start is the first column, named after the timestamp column, and
+    /// buckets without rows are filled with nulls so the result has no gaps.
+    pub fn group_by_time(
+        &self,
+        timestamp: Expr,
+        every: i64,
+        aggregates: Vec<Expr>,
+    ) -> Result<Self> {
+        let name = timestamp.to_field(&self.plan)?.name;
+        let bucket = date_bin(every, timestamp, 0).alias(name);
+        let aggregate = self.aggregate(vec![bucket], aggregates);
+        let plan = Plan::GapFill(GapFill::new(aggregate.plan, every));
+        Ok(DataFrame::new(plan))
+    }
+
     /// Returns the schema of the data that will be produced by this DataFrame.
     pub fn schema(&self) -> Schema {
         self.plan.schema()
@@ -43,6 +162,135 @@ impl DataFrame {
     pub fn logical_plan(&self) -> Plan {
         self.plan.clone()
     }
+
+    /// Plan, optimize and execute the DataFrame in the context, collecting all the result batches.
+    pub fn collect(&self, ctx: &ExecutionContext) -> Result<Vec<RecordBatch>> {
+        ctx.execute(self)
+    }
+
+    /// Execute the DataFrame in the context and return its only value,
+    /// failing unless the result has a single row and a single column.
+    pub fn collect_scalar(&self, ctx: &ExecutionContext) -> Result<ScalarValue> {
+        let batches = self.collect(ctx)?;
+        let rows = batches.iter().map(|b| b.row_count()).sum::<usize>();
+        let columns = self.schema().fields.len();
+        if rows != 1 || columns != 1 {
+            bail!(
+                "Expected a single value, got {} rows and {} columns",
+                rows,
+                columns
+            );
+        }
+        let batch = batches.iter().find(|b| b.row_count() == 1).unwrap();
+        ScalarValue::try_from_array(batch.field(0), 0)
+    }
+
+    /// Execute the DataFrame and return a DataFrame over the result batches, kept in memory,
+    /// so the queries derived from it do not execute it again.
+    pub fn cache(&self, ctx: &ExecutionContext) -> Result<DataFrame> {
+        let data_source = MemoryDataSource::new(self.schema(), self.collect(ctx)?);
+        let scan = Scan::new("cache".to_string(), Source::Mem(data_source), vec![]);
+        Ok(DataFrame::new(Plan::Scan(scan)))
+    }
+
+    /// Execute the DataFrame and return the result batches as Arrow batches, which share
+    /// the buffers of the columns.
+    pub fn to_arrow(&self, ctx: &ExecutionContext) -> Result<Vec<ArrowRecordBatch>> {
+        self.collect(ctx)?
+            .iter()
+            .map(ArrowRecordBatch::try_from)
+            .collect()
+    }
+
+    /// Create a DataFrame over Arrow batches kept in memory, without copying their columns.
+    /// The batches must all have the same schema, which becomes the schema of the DataFrame.
+    pub fn from_arrow(batches: &[ArrowRecordBatch]) -> Result<DataFrame> {
+        let arrow_schema = match batches.first() {
+            Some(batch) => batch.schema(),
+            None => bail!("Cannot create a DataFrame without Arrow batches to get the schema of"),
+        };
+        if let Some(batch) = batches.iter().find(|b| b.schema() != arrow_schema) {
+            bail!(
+                "The Arrow batches have different schemas: {} and {}",
+                arrow_schema,
+                batch.schema()
+            );
+        }
+        let schema = Schema::try_from(arrow_schema.as_ref())?;
+        let batches = batches
+            .iter()
+            .map(RecordBatch::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        let data_source = MemoryDataSource::new(schema, batches);
+        let scan = Scan::new("arrow".to_string(), Source::Mem(data_source), vec![]);
+        Ok(DataFrame::new(Plan::Scan(scan)))
+    }
+
+    /// Execute the DataFrame and copy the result into a polars DataFrame.
+    #[cfg(feature = "polars")]
+    pub fn collect_polars(&self, ctx: &ExecutionContext) -> Result<polars::frame::DataFrame> {
+        let schema: ArrowSchema = self.schema().into();
+        crate::data_types::polars::to_polars(&schema, &self.to_arrow(ctx)?)
+    }
+
+    /// Convert the plan of the DataFrame to a DataFusion logical plan, resolving the
+    /// functions it calls in the registry, such as a DataFusion `SessionContext`.
+    #[cfg(feature = "datafusion")]
+    pub fn to_datafusion(
+        &self,
+        registry: &dyn datafusion::execution::FunctionRegistry,
+    ) -> Result<datafusion::logical_expr::LogicalPlan> {
+        super::datafusion::to_datafusion(&self.plan, registry)
+    }
+
+    /// Execute the DataFrame and write the result to the writer in the Arrow IPC stream
+    /// format. The batches are written as [`ExecutionContext::execute_stream`] produces them,
+    /// followed by a trailer with their checksums that [`ExecutionContext::read_ipc`]
+    /// verifies them against. Other readers of IPC streams stop before the trailer.
+    pub fn write_ipc<W: Write>(&self, ctx: &ExecutionContext, writer: W) -> Result<()> {
+        let batches = block_on_stream(ctx.execute_stream(self)?);
+        let schema: ArrowSchema = self.schema().into();
+        let mut writer = StreamWriter::try_new(writer, &schema)?;
+        let mut checksums = vec![];
+        for batch in batches {
+            let batch = batch?;
+            if batch.row_count() > 0 {
+                checksums.push(batch.checksum()?);
+                writer.write(&ArrowRecordBatch::try_from(&batch)?)?;
+            }
+        }
+        // The writer is finished before it is returned.
+        let mut writer = writer.into_inner()?;
+        ipc::write_checksums(&mut writer, checksums)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Return the number of rows of the DataFrame. It is read from the statistics of a data
+    /// source that knows it, otherwise only the rows are counted, without reading the columns.
+    pub fn count(&self, ctx: &ExecutionContext) -> Result<usize> {
+        if let Plan::Scan(scan) = &self.plan {
+            if let Some(num_rows) = scan.data_source.statistics().num_rows {
+                return Ok(num_rows);
+            }
+        }
+        match self
+            .aggregate(vec![], vec![count(lit(1_i64))])
+            .collect_scalar(ctx)?
+        {
+            ScalarValue::Int64(count) => Ok(count as usize),
+            value => bail!("Expected a count, got {}", value),
+        }
+    }
+
+    /// Return the optimized logical plan and the physical plan. With `analyze`, the query
+    /// is executed and every operator is shown with its runtime metrics.
+    /// The query is planned with the default settings, see [`ExecutionContext::explain`]
+    /// to plan it with the settings of a context.
+    pub fn explain(&self, analyze: bool) -> Result<String> {
+        // The batch size of a context only applies to the data sources it creates.
+        ExecutionContext::new(1024).explain(self, analyze)
+    }
 }
 
 #[cfg(test)]
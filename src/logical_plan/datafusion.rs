@@ -0,0 +1,692 @@
+use std::{fmt, sync::Arc};
+
+use super::{
+    expr::{
+        AggregateExpr, AggregateFunction, Expr, LogicalExpr as _, Operator, ScalarFunction,
+        ScalarValue,
+    },
+    plan::{LogicalPlan as _, Plan},
+    scan::Scan,
+};
+use crate::{
+    data_source::{memory_data_source::MemoryDataSource, DataSource, Source},
+    data_types::{column_array::DataType, record_batch::RecordBatch, schema::Schema},
+};
+
+use anyhow::{anyhow, bail, Result};
+use arrow::{
+    array::ArrayRef as ArrowArrayRef,
+    ffi::{FFI_ArrowArray, FFI_ArrowSchema},
+    record_batch::RecordBatch as ArrowRecordBatch,
+};
+use arrow_array::{
+    ffi::{from_ffi, FFI_ArrowArray as DfFfiArray, FFI_ArrowSchema as DfFfiSchema},
+    make_array, ArrayRef as DfArrayRef,
+};
+use datafusion::{
+    arrow::{
+        datatypes::{
+            DataType as DfDataType, Field as DfField, Schema as DfSchema, SchemaRef as DfSchemaRef,
+        },
+        record_batch::RecordBatch as DfRecordBatch,
+    },
+    catalog::{streaming::StreamingTable, TableProvider},
+    common::{
+        Column as DfColumn, DFSchema, DataFusionError, JoinType, ScalarValue as DfScalarValue,
+        TableReference,
+    },
+    datasource::{
+        file_format::csv::CsvFormat,
+        listing::{ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl},
+        provider_as_source, MemTable,
+    },
+    execution::{FunctionRegistry, SendableRecordBatchStream, TaskContext},
+    logical_expr::{
+        expr::{self as df_expr, Sort as DfSortExpr},
+        Expr as DfExpr, LogicalPlan as DfLogicalPlan, LogicalPlanBuilder, Operator as DfOperator,
+    },
+    physical_plan::{stream::RecordBatchStreamAdapter, streaming::PartitionStream},
+};
+use futures::stream;
+
+/// Convert the logical plan to a DataFusion logical plan computing the same rows, so
+/// DataFusion can execute a query prototyped with rq. The functions are resolved in the
+/// registry, such as a DataFusion `SessionContext`, by the names DataFusion gives them.
+///
+/// No data is read to convert the plan: DataFusion reads CSV files itself, with the
+/// schema of the data source, shares the batches of memory tables, and has rq scan the
+/// other data sources as it executes the plan. The columns keep
+/// the names DataFusion gives them, unless they are aliased, and aggregates return the
+/// types DataFusion gives them, e.g. Int64 for the sum of Int32 values. Plans,
+/// expressions and functions without an equivalent in DataFusion fail to convert, with
+/// an error naming them.
+pub fn to_datafusion(plan: &Plan, registry: &dyn FunctionRegistry) -> Result<DfLogicalPlan> {
+    Ok(Converter { registry }.plan(plan)?.build()?)
+}
+
+struct Converter<'a> {
+    registry: &'a dyn FunctionRegistry,
+}
+
+impl Converter<'_> {
+    fn plan(&self, plan: &Plan) -> Result<LogicalPlanBuilder> {
+        Ok(match plan {
+            Plan::Scan(scan) => scan_plan(scan)?,
+            Plan::Projection(projection) => {
+                let input = self.plan(&projection.input)?;
+                let exprs = self.exprs(&projection.exprs, &projection.input, input.schema())?;
+                input.project(exprs)?
+            }
+            Plan::Selection(selection) => {
+                let input = self.plan(&selection.input)?;
+                let expr = self.expr(&selection.expr, &selection.input, input.schema())?;
+                input.filter(expr)?
+            }
+            Plan::Aggregate(aggregate) => {
+                let input = self.plan(&aggregate.input)?;
+                let schema = input.schema().clone();
+                let group_exprs = self.exprs(&aggregate.group_exprs, &aggregate.input, &schema)?;
+                let aggregate_exprs =
+                    self.exprs(&aggregate.aggregate_exprs, &aggregate.input, &schema)?;
+                input.aggregate(group_exprs, aggregate_exprs)?
+            }
+            Plan::Join(join) => {
+                let left = self.plan(&join.left)?;
+                let right = self.plan(&join.right)?;
+                if join.on.is_empty() {
+                    return Ok(left.cross_join(right.build()?)?);
+                }
+                let on = join
+                    .on
+                    .iter()
+                    .map(|(l, r)| {
+                        Ok(DfExpr::BinaryExpr(df_expr::BinaryExpr::new(
+                            Box::new(self.expr(l, &join.left, left.schema())?),
+                            DfOperator::Eq,
+                            Box::new(self.expr(r, &join.right, right.schema())?),
+                        )))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                left.join_on(right.build()?, JoinType::Inner, on)?
+            }
+            Plan::Sort(sort) => {
+                let input = self.plan(&sort.input)?;
+                let order_by = sort
+                    .order_by
+                    .iter()
+                    .map(|e| {
+                        let expr = self.expr(&e.expr, &sort.input, input.schema())?;
+                        Ok(DfSortExpr::new(expr, e.asc, e.nulls_first))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                input.sort(order_by)?
+            }
+            Plan::Limit(limit) => self.plan(&limit.input)?.limit(limit.skip, limit.fetch)?,
+            Plan::Command(_) => bail!("Statements that change tables or views run in rq only"),
+            Plan::Sessionize(_)
+            | Plan::GapFill(_)
+            | Plan::DistinctOn(_)
+            | Plan::Fill(_)
+            | Plan::Window(_) => bail!("DataFusion has no equivalent of the plan {}", plan),
+        })
+    }
+
+    fn exprs(&self, exprs: &[Expr], input: &Plan, schema: &DFSchema) -> Result<Vec<DfExpr>> {
+        exprs.iter().map(|e| self.expr(e, input, schema)).collect()
+    }
+
+    /// Convert an expression evaluated on the rows of the input, which DataFusion reads
+    /// with the schema. The columns of both are in the same order, but their names differ.
+    fn expr(&self, expr: &Expr, input: &Plan, schema: &DFSchema) -> Result<DfExpr> {
+        Ok(match expr {
+            Expr::Column(column) => {
+                let index = input
+                    .schema()
+                    .fields
+                    .iter()
+                    .position(|f| f.name == column.name)
+                    .ok_or_else(|| anyhow!("No column named '{}'", column.name))?;
+                DfExpr::Column(DfColumn::from(schema.qualified_field(index)))
+            }
+            Expr::ColumnIndex(column_index) => {
+                if column_index.index >= schema.fields().len() {
+                    bail!("No column at index {}", column_index.index);
+                }
+                DfExpr::Column(DfColumn::from(schema.qualified_field(column_index.index)))
+            }
+            Expr::Literal(value) => DfExpr::Literal(scalar_value(value)?),
+            Expr::Placeholder(placeholder) => {
+                let data_type = placeholder.data_type.as_ref().map(data_type);
+                DfExpr::Placeholder(df_expr::Placeholder::new(placeholder.id.clone(), data_type))
+            }
+            Expr::Not(not) => DfExpr::Not(Box::new(self.expr(&not.expr, input, schema)?)),
+            Expr::Cast(cast) => {
+                let expr = Box::new(self.expr(&cast.expr, input, schema)?);
+                let data_type = data_type(&cast.data_type);
+                match cast.try_cast {
+                    true => DfExpr::TryCast(df_expr::TryCast::new(expr, data_type)),
+                    false => DfExpr::Cast(df_expr::Cast::new(expr, data_type)),
+                }
+            }
+            Expr::BinaryExpr(binary) => DfExpr::BinaryExpr(df_expr::BinaryExpr::new(
+                Box::new(self.expr(&binary.left, input, schema)?),
+                operator(binary.op),
+                Box::new(self.expr(&binary.right, input, schema)?),
+            )),
+            Expr::Alias(alias) => self.expr(&alias.expr, input, schema)?.alias(&alias.alias),
+            Expr::ScalarFunction(function) => self.scalar_function(function, input, schema)?,
+            Expr::AggregateFunction(aggregate) => self.aggregate(aggregate, input, schema)?,
+            Expr::GetField(_) | Expr::GetIndex(_) => {
+                bail!(
+                    "Struct fields and list items are not converted to DataFusion: {}",
+                    expr
+                )
+            }
+            Expr::WindowFunction(_) => {
+                bail!(
+                    "DataFusion has no equivalent of the window function {}",
+                    expr
+                )
+            }
+        })
+    }
+
+    /// Convert a call of one of the functions DataFusion has an equivalent of.
+    fn scalar_function(
+        &self,
+        function: &ScalarFunction,
+        input: &Plan,
+        schema: &DFSchema,
+    ) -> Result<DfExpr> {
+        let name = match function.name.as_str() {
+            "pow" => "power",
+            name @ ("abs" | "sqrt" | "exp" | "ln" | "log10" | "round" | "floor" | "ceil"
+            | "coalesce" | "nullif" | "regexp_like" | "levenshtein") => name,
+            name => bail!("DataFusion has no equivalent of the function {}", name),
+        };
+        let args = self.exprs(&function.args, input, schema)?;
+        let udf = self.registry.udf(name)?;
+        let call = DfExpr::ScalarFunction(df_expr::ScalarFunction::new_udf(udf, args));
+        // The result is cast to the type rq returns, e.g. pow of integers is a Float64.
+        let return_type = data_type(&function.to_field(input)?.data_type);
+        Ok(DfExpr::Cast(df_expr::Cast::new(
+            Box::new(call),
+            return_type,
+        )))
+    }
+
+    fn aggregate(
+        &self,
+        aggregate: &AggregateExpr,
+        input: &Plan,
+        schema: &DFSchema,
+    ) -> Result<DfExpr> {
+        let mut args = self.exprs(&aggregate.args, input, schema)?;
+        let mut order_by = None;
+        let mut distinct = aggregate.is_distinct;
+        let name = match &aggregate.fun {
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+            AggregateFunction::Avg => "avg",
+            AggregateFunction::Count => "count",
+            AggregateFunction::CountDistinct => {
+                distinct = true;
+                "count"
+            }
+            AggregateFunction::Median => "median",
+            AggregateFunction::ApproxPercentile => "approx_percentile_cont",
+            AggregateFunction::BoolAnd => "bool_and",
+            AggregateFunction::BoolOr => "bool_or",
+            // The rows are ordered by the arguments after the value, ascending with the
+            // nulls last.
+            AggregateFunction::FirstValue | AggregateFunction::LastValue => {
+                if args.len() > 1 {
+                    let keys = args.split_off(1).into_iter();
+                    order_by = Some(keys.map(|e| DfSortExpr::new(e, true, false)).collect());
+                }
+                match aggregate.fun {
+                    AggregateFunction::FirstValue => "first_value",
+                    _ => "last_value",
+                }
+            }
+            fun => bail!(
+                "DataFusion has no equivalent of the aggregate function {}",
+                fun
+            ),
+        };
+        let udaf = self.registry.udaf(name)?;
+        Ok(DfExpr::AggregateFunction(
+            df_expr::AggregateFunction::new_udf(udaf, args, distinct, None, order_by, None),
+        ))
+    }
+}
+
+/// Scan the data source in DataFusion without reading it: CSV files are read by
+/// DataFusion, memory tables share their batches with a DataFusion memory table, and the
+/// other data sources are scanned by rq when DataFusion executes the plan.
+fn scan_plan(scan: &Scan) -> Result<LogicalPlanBuilder> {
+    let schema = scan.data_source.get_schema();
+    let projection = match scan.projection.is_empty() {
+        true => None,
+        false => Some(
+            scan.projection
+                .iter()
+                .map(|name| {
+                    schema
+                        .fields
+                        .iter()
+                        .position(|f| &f.name == name)
+                        .ok_or_else(|| anyhow!("No column named '{}'", name))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ),
+    };
+    let table = match &scan.data_source {
+        Source::Csv(csv) => match csv.file_path() {
+            Some(file_path) => csv_table(file_path, schema)?,
+            None => stream_table(&scan.data_source)?,
+        },
+        Source::Mem(memory) => memory_table(memory)?,
+        source => stream_table(source)?,
+    };
+    let table_name = TableReference::bare(scan.path.clone());
+    Ok(LogicalPlanBuilder::scan(
+        table_name,
+        provider_as_source(table),
+        projection,
+    )?)
+}
+
+/// Read the CSV file, without a header, as a DataFusion listing table.
+fn csv_table(file_path: &str, schema: &Schema) -> Result<Arc<dyn TableProvider>> {
+    if let Some(field) = schema
+        .fields
+        .iter()
+        .find(|f| f.data_type == DataType::Binary)
+    {
+        bail!(
+            "DataFusion does not decode the Binary column {} of a CSV file",
+            field.name
+        );
+    }
+    let format = CsvFormat::default().with_has_header(false);
+    // The file is read whatever its extension.
+    let options = ListingOptions::new(Arc::new(format)).with_file_extension("");
+    let config = ListingTableConfig::new(ListingTableUrl::parse(file_path)?)
+        .with_listing_options(options)
+        .with_schema(Arc::new(df_schema(schema)));
+    Ok(Arc::new(ListingTable::try_new(config)?))
+}
+
+/// Share the batches of the memory table, by partition, with a DataFusion memory table.
+fn memory_table(memory: &MemoryDataSource) -> Result<Arc<dyn TableProvider>> {
+    let schema = Arc::new(df_schema(memory.get_schema()));
+    let partitions = (0..memory.partition_count())
+        .map(|partition| {
+            let batches = memory.scan_partition(vec![], partition)?;
+            batches.map(|batch| share_batch(&schema, &batch?)).collect()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Arc::new(MemTable::try_new(schema, partitions)?))
+}
+
+/// Scan the partitions of the data source with rq, as DataFusion executes the plan.
+fn stream_table(source: &Source) -> Result<Arc<dyn TableProvider>> {
+    let schema = Arc::new(df_schema(source.get_schema()));
+    let partitions = (0..source.partition_count())
+        .map(|partition| {
+            Arc::new(SourceStream {
+                schema: schema.clone(),
+                source: source.clone(),
+                partition,
+            }) as Arc<dyn PartitionStream>
+        })
+        .collect();
+    Ok(Arc::new(StreamingTable::try_new(schema, partitions)?))
+}
+
+/// A partition of a data source, scanned by rq when DataFusion executes the plan.
+struct SourceStream {
+    schema: DfSchemaRef,
+    source: Source,
+    partition: usize,
+}
+
+impl fmt::Debug for SourceStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SourceStream: partition={}", self.partition)
+    }
+}
+
+impl PartitionStream for SourceStream {
+    fn schema(&self) -> &DfSchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let batches = self
+            .source
+            .scan_partition(vec![], self.partition)
+            .and_then(|batches| {
+                batches
+                    .map(|batch| share_batch(&self.schema, &batch?))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .map_err(|e| DataFusionError::External(e.into()));
+        let batches = match batches {
+            Ok(batches) => batches.into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        };
+        Box::pin(RecordBatchStreamAdapter::new(
+            self.schema.clone(),
+            stream::iter(batches),
+        ))
+    }
+}
+
+/// Share the columns of the batch with the Arrow version of DataFusion.
+fn share_batch(schema: &DfSchemaRef, batch: &RecordBatch) -> Result<DfRecordBatch> {
+    let batch = ArrowRecordBatch::try_from(batch)?;
+    let columns = batch.columns().iter().map(share_array);
+    Ok(DfRecordBatch::try_new(
+        schema.clone(),
+        columns.collect::<Result<_>>()?,
+    )?)
+}
+
+/// Share the buffers of the array with the Arrow version of DataFusion, through the
+/// Arrow C data interface.
+fn share_array(array: &ArrowArrayRef) -> Result<DfArrayRef> {
+    let mut ffi_array = FFI_ArrowArray::new(array.data());
+    let mut ffi_schema = FFI_ArrowSchema::try_from(array.data_type())?;
+    // Both versions of Arrow lay the structs of the C data interface out as C does. They
+    // are moved out of, and the released structs left behind are dropped.
+    let data = unsafe {
+        let ffi_array = DfFfiArray::from_raw(&mut ffi_array as *mut _ as *mut DfFfiArray);
+        let ffi_schema = DfFfiSchema::from_raw(&mut ffi_schema as *mut _ as *mut DfFfiSchema);
+        from_ffi(ffi_array, &ffi_schema)?
+    };
+    Ok(make_array(data))
+}
+
+fn df_schema(schema: &Schema) -> DfSchema {
+    DfSchema::new(
+        schema
+            .fields
+            .iter()
+            .map(|f| DfField::new(&f.name, data_type(&f.data_type), f.nullable))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn data_type(data_type: &DataType) -> DfDataType {
+    match data_type {
+        DataType::Boolean => DfDataType::Boolean,
+        DataType::Int32 => DfDataType::Int32,
+        DataType::Int64 => DfDataType::Int64,
+        DataType::Float32 => DfDataType::Float32,
+        DataType::Float64 => DfDataType::Float64,
+        DataType::Utf8 => DfDataType::Utf8,
+        DataType::Binary => DfDataType::Binary,
+        DataType::List(item_type) => DfDataType::new_list(self::data_type(item_type), true),
+        DataType::Struct(fields) => DfDataType::Struct(
+            fields
+                .iter()
+                .map(|(name, data_type)| DfField::new(name, self::data_type(data_type), true))
+                .collect(),
+        ),
+    }
+}
+
+fn scalar_value(value: &ScalarValue) -> Result<DfScalarValue> {
+    Ok(match value {
+        ScalarValue::Boolean(b) => DfScalarValue::Boolean(Some(*b)),
+        ScalarValue::String(s) => DfScalarValue::Utf8(Some(s.clone())),
+        ScalarValue::Int32(i) => DfScalarValue::Int32(Some(*i)),
+        ScalarValue::Int64(i) => DfScalarValue::Int64(Some(*i)),
+        ScalarValue::Float32(f) => DfScalarValue::Float32(Some(*f)),
+        ScalarValue::Float64(f) => DfScalarValue::Float64(Some(*f)),
+        ScalarValue::Null(Some(null_type)) => DfScalarValue::try_from(&data_type(null_type))?,
+        ScalarValue::Null(None) => DfScalarValue::Null,
+    })
+}
+
+fn operator(op: Operator) -> DfOperator {
+    match op {
+        Operator::And => DfOperator::And,
+        Operator::Or => DfOperator::Or,
+        Operator::Eq => DfOperator::Eq,
+        Operator::Neq => DfOperator::NotEq,
+        Operator::Gt => DfOperator::Gt,
+        Operator::GtEq => DfOperator::GtEq,
+        Operator::Lt => DfOperator::Lt,
+        Operator::LtEq => DfOperator::LtEq,
+        Operator::Add => DfOperator::Plus,
+        Operator::Subtract => DfOperator::Minus,
+        Operator::Multiply => DfOperator::Multiply,
+        Operator::Divide => DfOperator::Divide,
+        Operator::Modulus => DfOperator::Modulo,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{
+            column_array::DataType,
+            schema::{Field, Schema},
+        },
+        execution::ExecutionContext,
+        logical_plan::{
+            expr_fn::{col, lit},
+            sort::SortExpr,
+        },
+        test_util::rq_test_data,
+    };
+
+    use datafusion::{
+        arrow::{record_batch::RecordBatch, util::pretty::pretty_format_batches},
+        common::{ParamValues, ScalarValue},
+        logical_expr::LogicalPlan,
+        prelude::SessionContext,
+    };
+
+    fn execute(df_ctx: &SessionContext, plan: LogicalPlan) -> String {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let batches: Vec<RecordBatch> = runtime.block_on(async {
+            let df = df_ctx.execute_logical_plan(plan).await.unwrap();
+            df.collect().await.unwrap()
+        });
+        pretty_format_batches(&batches).unwrap().to_string()
+    }
+
+    fn context() -> ExecutionContext {
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![
+            Field::new("k".to_string(), DataType::Utf8),
+            Field::new("v".to_string(), DataType::Int64),
+        ]);
+        ctx.register_table("t", Source::Mem(MemoryDataSource::new(schema, vec![])));
+        ctx.sql(
+            "INSERT INTO t SELECT CAST(value % 2 AS VARCHAR), value FROM generate_series(1, 5)",
+        )
+        .unwrap()
+        .collect(&ctx)
+        .unwrap();
+        ctx
+    }
+
+    #[test]
+    fn test_to_datafusion() {
+        let ctx = context();
+        let df_ctx = SessionContext::new();
+        let df = ctx
+            .sql(
+                "SELECT k, SUM(v) AS total, COUNT(*) AS n, MAX(pow(v, 2)) AS m FROM t \
+                 WHERE v > 1 GROUP BY k ORDER BY k DESC LIMIT 2",
+            )
+            .unwrap();
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        assert_eq!(
+            execute(&df_ctx, plan),
+            "+---+-------+---+------+\n\
+             | k | total | n | m    |\n\
+             +---+-------+---+------+\n\
+             | 1 | 8     | 2 | 25.0 |\n\
+             | 0 | 6     | 2 | 16.0 |\n\
+             +---+-------+---+------+"
+        );
+
+        let df = ctx
+            .sql("SELECT value * 10 AS v FROM generate_series(1, 6) WHERE value % 3 = $1 LIMIT 1 OFFSET 1")
+            .unwrap();
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        let plan = plan
+            .with_param_values(ParamValues::List(vec![ScalarValue::Int64(Some(0))]))
+            .unwrap();
+        assert_eq!(
+            execute(&df_ctx, plan),
+            "+----+\n\
+             | v  |\n\
+             +----+\n\
+             | 60 |\n\
+             +----+"
+        );
+
+        // The relations of a join share column names, which are qualified in the plan.
+        let df = ctx
+            .sql(
+                "SELECT a.v, b.v AS w FROM t AS a JOIN t AS b ON a.v = b.v - 1 \
+                 WHERE a.k = '1' ORDER BY a.v",
+            )
+            .unwrap();
+        let expected = "+---+---+\n\
+                        | v | w |\n\
+                        +---+---+\n\
+                        | 1 | 2 |\n\
+                        | 3 | 4 |\n\
+                        +---+---+";
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        assert_eq!(execute(&df_ctx, plan), expected);
+        let right = ctx.sql("SELECT v AS w FROM t").unwrap();
+        let df = ctx
+            .table("t")
+            .unwrap()
+            .filter(col("k").eq(lit("1".to_string())))
+            .join(&right, vec![(col("v") + lit(1_i64), col("w"))])
+            .project(vec![col("v"), col("w")])
+            .sort(vec![SortExpr::new(col("v"), true, false)]);
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        assert_eq!(execute(&df_ctx, plan), expected);
+    }
+
+    #[test]
+    fn test_to_datafusion_sources() {
+        let ctx = ExecutionContext::new(2);
+        let df_ctx = SessionContext::new();
+        let schema = Schema::new(vec![
+            Field::new("name".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        ctx.register_csv("events", rq_test_data("events.csv"), schema.clone());
+        let df = ctx
+            .sql("SELECT name, COUNT(*) AS n, MAX(ts) AS last FROM events GROUP BY name ORDER BY name")
+            .unwrap();
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        assert_eq!(
+            execute(&df_ctx, plan),
+            "+-------+---+------+\n\
+             | name  | n | last |\n\
+             +-------+---+------+\n\
+             | alice | 4 | 5100 |\n\
+             | bob   | 2 | 20   |\n\
+             +-------+---+------+"
+        );
+        let df = ctx.sql("SELECT ts FROM events WHERE ts < 50").unwrap();
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        assert_eq!(
+            execute(&df_ctx, plan),
+            "+----+\n\
+             | ts |\n\
+             +----+\n\
+             | 0  |\n\
+             | 10 |\n\
+             | 20 |\n\
+             +----+"
+        );
+
+        // Series are generated by rq, as DataFusion executes the plan.
+        let df = ctx
+            .sql("SELECT value FROM generate_series(0.5, 1.75, 0.5)")
+            .unwrap();
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        assert_eq!(
+            execute(&df_ctx, plan),
+            "+-------+\n\
+             | value |\n\
+             +-------+\n\
+             | 0.5   |\n\
+             | 1.0   |\n\
+             | 1.5   |\n\
+             +-------+"
+        );
+        let df = ctx
+            .sql("SELECT COUNT(*) AS n FROM generate_series(3, 1)")
+            .unwrap();
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        assert_eq!(
+            execute(&df_ctx, plan),
+            "+---+\n\
+             | n |\n\
+             +---+\n\
+             | 0 |\n\
+             +---+"
+        );
+
+        // CSV data held in memory is read by rq, as DataFusion executes the plan.
+        ctx.register_csv_bytes("bytes", b"alice,0\nbob,10\n".to_vec(), schema);
+        let df = ctx.sql("SELECT name FROM bytes WHERE ts > 0").unwrap();
+        let plan = df.to_datafusion(&df_ctx).unwrap();
+        assert_eq!(
+            execute(&df_ctx, plan),
+            "+------+\n\
+             | name |\n\
+             +------+\n\
+             | bob  |\n\
+             +------+"
+        );
+    }
+
+    #[test]
+    fn test_to_datafusion_unsupported() {
+        let ctx = context();
+        let df_ctx = SessionContext::new();
+        let error = |df: crate::logical_plan::data_frame::DataFrame| {
+            df.to_datafusion(&df_ctx).unwrap_err().to_string()
+        };
+
+        let df = ctx.sql("SELECT soundex(k) FROM t").unwrap();
+        assert_eq!(
+            error(df),
+            "DataFusion has no equivalent of the function soundex"
+        );
+        let df = ctx.table("t").unwrap().sessionize(col("k"), col("v"), 2);
+        assert!(error(df).starts_with("DataFusion has no equivalent of the plan Sessionize"));
+        let df = ctx.sql("INSERT INTO t SELECT k, v FROM t").unwrap();
+        assert_eq!(
+            error(df),
+            "Statements that change tables or views run in rq only"
+        );
+        let schema = Schema::new(vec![
+            Field::new("b".to_string(), DataType::Binary),
+            Field::new("s".to_string(), DataType::Utf8),
+        ]);
+        let df = ctx.csv(rq_test_data("binary_field.csv"), schema);
+        assert_eq!(
+            error(df),
+            "DataFusion does not decode the Binary column b of a CSV file"
+        );
+    }
+}
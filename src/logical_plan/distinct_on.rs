@@ -0,0 +1,59 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::{
+    expr::Expr,
+    plan::{LogicalPlan, Plan},
+    sort::SortExpr,
+};
+use crate::data_types::schema::Schema;
+
+use serde::{Deserialize, Serialize};
+
+/// Logical plan that keeps one row per distinct value of the keys: the first row
+/// in the order of the `order_by` expressions.
+/// Rows that tie on the ordering are kept in input order.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DistinctOn {
+    pub input: Arc<Plan>,
+    pub keys: Vec<Expr>,
+    pub order_by: Vec<SortExpr>,
+}
+
+impl LogicalPlan for DistinctOn {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![self.input.as_ref().clone()]
+    }
+}
+
+impl Display for DistinctOn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "DistinctOn: keys={}, orderBy={}",
+            self.keys
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            self.order_by
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+impl DistinctOn {
+    pub fn new(input: Plan, keys: Vec<Expr>, order_by: Vec<SortExpr>) -> Self {
+        DistinctOn {
+            input: Arc::new(input),
+            keys,
+            order_by,
+        }
+    }
+}
@@ -1,13 +1,19 @@
-use std::{cmp::Ordering, fmt::Display, hash::Hash, ops};
+use std::{any::Any, cmp::Ordering, fmt::Display, hash::Hash, ops};
 
 use super::{
     expr_fn::binary_expr,
     plan::{LogicalPlan, Plan},
+    sort::SortExpr,
+    udaf::AggregateUdf,
+};
+use crate::data_types::{
+    column_array::{ColumnArray, DataType},
+    schema::Field,
 };
-use crate::data_types::{column_array::DataType, schema::Field};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
 
 /// Logical Expression for use in logical query plans.
 /// The logical expression provides information needed
@@ -20,7 +26,7 @@ pub trait LogicalExpr: Display {
 
 /// `Expr` represent logical expressions such as `A + 1`, or `CAST(c1 AS
 /// int)`.
-#[derive(Debug, PartialEq, PartialOrd, Clone, Hash)]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Hash, Serialize, Deserialize)]
 pub enum Expr {
     /// A named reference to a qualified filed in a schema.
     Column(Column),
@@ -28,19 +34,27 @@ pub enum Expr {
     ColumnIndex(ColumnIndex),
     /// A constant value.
     Literal(ScalarValue),
+    /// A parameter of a prepared plan, such as `$1`, bound to a value at execution.
+    Placeholder(Placeholder),
     /// Negation of an expression. The expression's type must be a boolean to make sense.
     Not(Not),
-    /// Casts the expression to a given type and will return a runtime error if the expression cannot be cast.
-    /// This expression is guaranteed to have a fixed type.
+    /// Casts the expression to a given type and will return a runtime error if the expression cannot be cast,
+    /// or a null for a TRY_CAST. This expression is guaranteed to have a fixed type.
     Cast(Cast),
     /// A binary expression such as "age > 21"
     BinaryExpr(BinaryExpr),
     /// An expression with a specific name.
     Alias(Alias),
+    /// Access to a named field of a struct.
+    GetField(GetField),
+    /// Access to an item of a list by its index, which is null when out of bounds.
+    GetIndex(GetIndex),
     /// Represents the call of a built-in scalar function with a set of arguments.
     ScalarFunction(ScalarFunction),
-    /// Represents the call of an aggregate built-in function with arguments.
+    /// Represents the call of a built-in or user-defined aggregate function with arguments.
     AggregateFunction(AggregateExpr),
+    /// Represents the call of a window function over the partitions of the input.
+    WindowFunction(WindowExpr),
 }
 
 impl LogicalExpr for Expr {
@@ -49,13 +63,83 @@ impl LogicalExpr for Expr {
             Expr::Column(column) => column.to_field(input),
             Expr::ColumnIndex(column_index) => column_index.to_field(input),
             Expr::Literal(literal) => literal.to_field(input),
+            Expr::Placeholder(placeholder) => placeholder.to_field(input),
             Expr::Not(not) => not.to_field(input),
             Expr::Cast(cast) => cast.to_field(input),
             Expr::BinaryExpr(binary) => binary.to_field(input),
             Expr::Alias(alias) => alias.to_field(input),
+            Expr::GetField(get_field) => get_field.to_field(input),
+            Expr::GetIndex(get_index) => get_index.to_field(input),
             Expr::ScalarFunction(function) => function.to_field(input),
             Expr::AggregateFunction(function) => function.to_field(input),
+            Expr::WindowFunction(function) => function.to_field(input),
+        }
+    }
+}
+
+impl Expr {
+    /// Return true if the expression is a NULL literal without a type.
+    /// Such a literal takes the type of the expressions it is combined with.
+    pub fn is_untyped_null(&self) -> bool {
+        matches!(self, Expr::Literal(ScalarValue::Null(None)))
+    }
+
+    /// Return true if the expression is an untyped NULL or a placeholder without a type,
+    /// which both take the type of the expressions they are combined with.
+    pub fn is_untyped(&self) -> bool {
+        self.is_untyped_null() || matches!(self, Expr::Placeholder(p) if p.data_type.is_none())
+    }
+
+    /// Return the direct sub-expressions of the expression.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Not(n) => vec![&n.expr],
+            Expr::Cast(c) => vec![&c.expr],
+            Expr::BinaryExpr(b) => vec![&b.left, &b.right],
+            Expr::Alias(a) => vec![&a.expr],
+            Expr::GetField(g) => vec![&g.expr],
+            Expr::GetIndex(g) => vec![&g.expr, &g.index],
+            Expr::ScalarFunction(f) => f.args.iter().collect(),
+            Expr::AggregateFunction(a) => a.args.iter().collect(),
+            Expr::WindowFunction(w) => w
+                .args
+                .iter()
+                .chain(&w.partition_by)
+                .chain(&w.order_by)
+                .collect(),
+            Expr::Column(_) | Expr::ColumnIndex(_) | Expr::Literal(_) | Expr::Placeholder(_) => {
+                vec![]
+            }
+        }
+    }
+
+    /// Return a copy of the expression with `f` applied to its direct sub-expressions.
+    pub fn map_children(&self, mut f: impl FnMut(&Expr) -> Result<Expr>) -> Result<Expr> {
+        let mut map_all = |exprs: &[Expr]| exprs.iter().map(&mut f).collect::<Result<Vec<_>>>();
+        let mut expr = self.clone();
+        match &mut expr {
+            Expr::Not(n) => *n.expr = f(&n.expr)?,
+            Expr::Cast(c) => *c.expr = f(&c.expr)?,
+            Expr::BinaryExpr(b) => {
+                *b.left = f(&b.left)?;
+                *b.right = f(&b.right)?;
+            }
+            Expr::Alias(a) => *a.expr = f(&a.expr)?,
+            Expr::GetField(g) => *g.expr = f(&g.expr)?,
+            Expr::GetIndex(g) => {
+                *g.expr = f(&g.expr)?;
+                *g.index = f(&g.index)?;
+            }
+            Expr::ScalarFunction(s) => s.args = map_all(&s.args)?,
+            Expr::AggregateFunction(a) => a.args = map_all(&a.args)?,
+            Expr::WindowFunction(w) => {
+                w.args = map_all(&w.args)?;
+                w.partition_by = map_all(&w.partition_by)?;
+                w.order_by = map_all(&w.order_by)?;
+            }
+            Expr::Column(_) | Expr::ColumnIndex(_) | Expr::Literal(_) | Expr::Placeholder(_) => {}
         }
+        Ok(expr)
     }
 }
 
@@ -65,12 +149,16 @@ impl Display for Expr {
             Expr::Column(column) => column.fmt(f),
             Expr::ColumnIndex(column_index) => column_index.fmt(f),
             Expr::Literal(literal) => literal.fmt(f),
+            Expr::Placeholder(placeholder) => placeholder.fmt(f),
             Expr::Not(not) => not.fmt(f),
             Expr::Cast(cast) => cast.fmt(f),
             Expr::BinaryExpr(binary) => binary.fmt(f),
             Expr::Alias(alias) => alias.fmt(f),
+            Expr::GetField(get_field) => get_field.fmt(f),
+            Expr::GetIndex(get_index) => get_index.fmt(f),
             Expr::ScalarFunction(function) => function.fmt(f),
             Expr::AggregateFunction(function) => function.fmt(f),
+            Expr::WindowFunction(function) => function.fmt(f),
         }
     }
 }
@@ -168,10 +256,25 @@ impl Expr {
     pub fn alias(self, name: String) -> Expr {
         Expr::Alias(Alias::new(self, name))
     }
+
+    /// Return the field `name` of the struct `self`
+    pub fn field(self, name: &str) -> Expr {
+        Expr::GetField(GetField::new(self, name.to_string()))
+    }
+
+    /// Return the item at the zero-based `index` of the list `self`
+    pub fn index(self, index: Expr) -> Expr {
+        Expr::GetIndex(GetIndex::new(self, index))
+    }
+
+    /// Return an ordering by `self`, ascending or descending, with nulls first or last
+    pub fn sort(self, asc: bool, nulls_first: bool) -> SortExpr {
+        SortExpr::new(self, asc, nulls_first)
+    }
 }
 
 /// Logical expression representing a reference to a column by name.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Column {
     pub name: String,
 }
@@ -201,7 +304,7 @@ impl From<&str> for Column {
 }
 
 /// Logical expression representing a reference to a column by index.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct ColumnIndex {
     pub index: usize,
 }
@@ -219,35 +322,47 @@ impl Display for ColumnIndex {
 }
 
 /// Represents a dynamically typed single value.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ScalarValue {
+    Boolean(bool),
     String(String),
     Int32(i32),
     Int64(i64),
     Float32(f32),
     Float64(f64),
+    /// A NULL of the given type. Untyped nulls take the type of the expressions
+    /// they are combined with, and are Utf8 on their own.
+    Null(Option<DataType>),
 }
 
 impl LogicalExpr for ScalarValue {
     fn to_field(&self, _input: &Plan) -> Result<Field> {
-        match &self {
-            ScalarValue::String(s) => Ok(Field::new(s.clone(), DataType::Utf8)),
-            ScalarValue::Int32(i) => Ok(Field::new(i.to_string(), DataType::Int32)),
-            ScalarValue::Int64(i) => Ok(Field::new(i.to_string(), DataType::Int64)),
-            ScalarValue::Float32(f) => Ok(Field::new(f.to_string(), DataType::Float32)),
-            ScalarValue::Float64(f) => Ok(Field::new(f.to_string(), DataType::Float64)),
-        }
+        let field = match &self {
+            ScalarValue::Boolean(b) => Field::new(b.to_string(), DataType::Boolean),
+            ScalarValue::String(s) => Field::new(s.clone(), DataType::Utf8),
+            ScalarValue::Int32(i) => Field::new(i.to_string(), DataType::Int32),
+            ScalarValue::Int64(i) => Field::new(i.to_string(), DataType::Int64),
+            ScalarValue::Float32(f) => Field::new(f.to_string(), DataType::Float32),
+            ScalarValue::Float64(f) => Field::new(f.to_string(), DataType::Float64),
+            ScalarValue::Null(data_type) => {
+                let data_type = data_type.clone().unwrap_or(DataType::Utf8);
+                return Ok(Field::new("NULL".to_string(), data_type));
+            }
+        };
+        Ok(field.with_nullable(false))
     }
 }
 
 impl Display for ScalarValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            ScalarValue::Boolean(b) => write!(f, "{}", b),
             ScalarValue::String(s) => write!(f, "{}", s),
             ScalarValue::Int32(i) => write!(f, "{}", i),
             ScalarValue::Int64(i) => write!(f, "{}", i),
             ScalarValue::Float32(ft) => write!(f, "{}", ft),
             ScalarValue::Float64(ft) => write!(f, "{}", ft),
+            ScalarValue::Null(_) => write!(f, "NULL"),
         }
     }
 }
@@ -255,6 +370,7 @@ impl Display for ScalarValue {
 impl std::hash::Hash for ScalarValue {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
+            ScalarValue::Boolean(b) => b.hash(state),
             ScalarValue::String(s) => s.hash(state),
             ScalarValue::Int32(i) => i.hash(state),
             ScalarValue::Int64(i) => i.hash(state),
@@ -266,6 +382,7 @@ impl std::hash::Hash for ScalarValue {
                 let ft = OrderedFloat(*ft);
                 ft.hash(state)
             }
+            ScalarValue::Null(data_type) => data_type.hash(state),
         }
     }
 }
@@ -273,6 +390,7 @@ impl std::hash::Hash for ScalarValue {
 impl PartialEq for ScalarValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (ScalarValue::Boolean(b), ScalarValue::Boolean(o)) => b == o,
             (ScalarValue::String(s), ScalarValue::String(o)) => s == o,
             (ScalarValue::Int32(i), ScalarValue::Int32(o)) => i == o,
             (ScalarValue::Int64(i), ScalarValue::Int64(o)) => i == o,
@@ -286,6 +404,7 @@ impl PartialEq for ScalarValue {
                 let v2 = OrderedFloat(*o);
                 v1.eq(&v2)
             }
+            (ScalarValue::Null(t), ScalarValue::Null(o)) => t == o,
             _ => false,
         }
     }
@@ -294,6 +413,7 @@ impl PartialEq for ScalarValue {
 impl PartialOrd for ScalarValue {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
+            (ScalarValue::Boolean(b), ScalarValue::Boolean(o)) => b.partial_cmp(o),
             (ScalarValue::String(s), ScalarValue::String(o)) => s.partial_cmp(o),
             (ScalarValue::Int32(i), ScalarValue::Int32(o)) => i.partial_cmp(o),
             (ScalarValue::Int64(i), ScalarValue::Int64(o)) => i.partial_cmp(o),
@@ -307,6 +427,7 @@ impl PartialOrd for ScalarValue {
                 let v2 = OrderedFloat(*o);
                 v1.partial_cmp(&v2)
             }
+            (ScalarValue::Null(t), ScalarValue::Null(o)) => t.partial_cmp(o),
             _ => None,
         }
     }
@@ -314,28 +435,74 @@ impl PartialOrd for ScalarValue {
 
 impl Eq for ScalarValue {}
 
+impl ScalarValue {
+    /// Return the type of the value. Untyped nulls are Utf8.
+    pub fn data_type(&self) -> DataType {
+        match self {
+            ScalarValue::Boolean(_) => DataType::Boolean,
+            ScalarValue::String(_) => DataType::Utf8,
+            ScalarValue::Int32(_) => DataType::Int32,
+            ScalarValue::Int64(_) => DataType::Int64,
+            ScalarValue::Float32(_) => DataType::Float32,
+            ScalarValue::Float64(_) => DataType::Float64,
+            ScalarValue::Null(data_type) => data_type.clone().unwrap_or(DataType::Utf8),
+        }
+    }
+
+    /// Create a value from the element of the array at the given index.
+    pub fn try_from_array(array: &dyn ColumnArray, index: usize) -> Result<Self> {
+        let data_type = array.get_type();
+        if array.is_null(index) {
+            return Ok(ScalarValue::Null(Some(data_type)));
+        }
+        let value = array.get_value(index)?;
+        let value = value.as_ref();
+        Ok(match data_type {
+            DataType::Boolean => ScalarValue::Boolean(*downcast::<bool>(value)?),
+            DataType::Utf8 => ScalarValue::String(downcast::<String>(value)?.clone()),
+            DataType::Int32 => ScalarValue::Int32(*downcast::<i32>(value)?),
+            DataType::Int64 => ScalarValue::Int64(*downcast::<i64>(value)?),
+            DataType::Float32 => ScalarValue::Float32(*downcast::<f32>(value)?),
+            DataType::Float64 => ScalarValue::Float64(*downcast::<f64>(value)?),
+            DataType::Binary | DataType::List(_) | DataType::Struct(_) => {
+                bail!("There is no scalar value of type {}", data_type)
+            }
+        })
+    }
+}
+
+fn downcast<T: Any>(value: &dyn Any) -> Result<&T> {
+    value
+        .downcast_ref::<T>()
+        .ok_or_else(|| anyhow!("Expected a value of type {}", std::any::type_name::<T>()))
+}
+
 /// Cast a given expression to a given data type field.
-#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+/// A TRY_CAST returns null for the values that cannot be cast instead of failing.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct Cast {
     pub expr: Box<Expr>,
     pub data_type: DataType,
+    pub try_cast: bool,
 }
 
 impl LogicalExpr for Cast {
     fn to_field(&self, input: &Plan) -> Result<Field> {
         let field = self.expr.to_field(input)?;
-        Ok(Field::new(field.name, self.data_type.clone()))
+        let nullable = field.nullable || self.try_cast;
+        Ok(Field::new(field.name, self.data_type.clone()).with_nullable(nullable))
     }
 }
 
 impl Display for Cast {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "CAST({} AS {})", self.expr, self.data_type)
+        let name = if self.try_cast { "TRY_CAST" } else { "CAST" };
+        write!(f, "{}({} AS {})", name, self.expr, self.data_type)
     }
 }
 
 /// Logical expression representing a logical NOT.
-#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct Not {
     name: String,
     op: String,
@@ -353,19 +520,21 @@ impl Not {
 }
 
 impl LogicalExpr for Not {
-    fn to_field(&self, _input: &Plan) -> Result<Field> {
-        Ok(Field::new(self.name.clone(), DataType::Boolean))
+    fn to_field(&self, input: &Plan) -> Result<Field> {
+        let nullable = self.expr.to_field(input)?.nullable;
+        Ok(Field::new(self.name.clone(), DataType::Boolean).with_nullable(nullable))
     }
 }
 
 impl Display for Not {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {}", self.op, self.expr)
+        write!(f, "{} ", self.op)?;
+        fmt_operand(f, &self.expr, |_| true)
     }
 }
 
 /// Operators applied to expressions
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum Operator {
     And,
     Or,
@@ -383,6 +552,47 @@ pub enum Operator {
 }
 
 impl Operator {
+    /// Return whether the operator compares its operands.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Operator::Eq
+                | Operator::Neq
+                | Operator::Gt
+                | Operator::GtEq
+                | Operator::Lt
+                | Operator::LtEq
+        )
+    }
+
+    /// Return whether the operator computes a number from numeric operands.
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            Operator::Add
+                | Operator::Subtract
+                | Operator::Multiply
+                | Operator::Divide
+                | Operator::Modulus
+        )
+    }
+
+    /// Return how tightly the operator binds its operands, higher binding tighter.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::Or => 5,
+            Operator::And => 10,
+            Operator::Eq
+            | Operator::Neq
+            | Operator::Gt
+            | Operator::GtEq
+            | Operator::Lt
+            | Operator::LtEq => 15,
+            Operator::Add | Operator::Subtract => 20,
+            Operator::Multiply | Operator::Divide | Operator::Modulus => 30,
+        }
+    }
+
     fn get_name(&self) -> String {
         match self {
             Operator::And => "and".to_string(),
@@ -424,26 +634,127 @@ impl Display for Operator {
 }
 
 /// Binary expressions that return a boolean type.
-#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+/// Logical expression representing a parameter of a prepared plan.
+/// A placeholder without a type takes the type of the expressions it is combined with.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct Placeholder {
+    pub id: String,
+    pub data_type: Option<DataType>,
+}
+
+impl LogicalExpr for Placeholder {
+    fn to_field(&self, _input: &Plan) -> Result<Field> {
+        match &self.data_type {
+            Some(data_type) => Ok(Field::new(self.id.clone(), data_type.clone())),
+            None => bail!("Cannot infer the type of placeholder {}", self.id),
+        }
+    }
+}
+
+impl Display for Placeholder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct BinaryExpr {
     pub op: Operator,
     pub left: Box<Expr>,
     pub right: Box<Expr>,
 }
 
+impl BinaryExpr {
+    /// Return the type both operands are converted to before the operator is applied.
+    /// Numeric operands of different types are converted to their common type,
+    /// and an untyped NULL is converted to the type of the other operand.
+    pub fn operand_type(&self, input: &Plan) -> Result<DataType> {
+        let (left, right) = self.operand_fields(input)?;
+        self.common_operand_type(left.as_ref(), right.as_ref(), input)
+    }
+
+    /// Return the fields of the operands, or None for untyped operands.
+    fn operand_fields(&self, input: &Plan) -> Result<(Option<Field>, Option<Field>)> {
+        let field = |e: &Expr| match e.is_untyped() {
+            true => Ok(None),
+            false => e.to_field(input).map(Some),
+        };
+        Ok((field(&self.left)?, field(&self.right)?))
+    }
+
+    fn common_operand_type(
+        &self,
+        left: Option<&Field>,
+        right: Option<&Field>,
+        input: &Plan,
+    ) -> Result<DataType> {
+        let (left, right) = match (left, right) {
+            // Fails, as the type of an untyped operand can't be inferred.
+            (None, None) => return Ok(self.right.to_field(input)?.data_type),
+            (None, Some(field)) | (Some(field), None) => return Ok(field.data_type.clone()),
+            (Some(left), Some(right)) => (&left.data_type, &right.data_type),
+        };
+        let common_type = match left.is_nested() || right.is_nested() {
+            true => None,
+            false => left.common_type(right),
+        };
+        common_type.ok_or_else(|| {
+            anyhow!(
+                "Cannot apply {} to {} and {}",
+                self.op.get_name(),
+                left,
+                right
+            )
+        })
+    }
+}
+
 impl LogicalExpr for BinaryExpr {
-    fn to_field(&self, _input: &Plan) -> Result<Field> {
-        Ok(Field::new(self.op.get_name(), DataType::Boolean))
+    fn to_field(&self, input: &Plan) -> Result<Field> {
+        // The operands are only resolved once, as they may be deeply nested.
+        let (left, right) = self.operand_fields(input)?;
+        let operand_type = self.common_operand_type(left.as_ref(), right.as_ref(), input)?;
+        let data_type = match self.op {
+            Operator::Add
+            | Operator::Subtract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::Modulus => operand_type,
+            _ => DataType::Boolean,
+        };
+        // Untyped operands are nulls or placeholders, which may be bound to a null.
+        let nullable = [left, right]
+            .iter()
+            .any(|f| f.as_ref().is_none_or(|f| f.nullable));
+        Ok(Field::new(self.op.get_name(), data_type).with_nullable(nullable))
     }
 }
 
 impl Display for BinaryExpr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {} {}", self.left, self.op, self.right)
+        // Operators are left-associative, so a right operand of the same precedence
+        // needs parentheses too.
+        let precedence = self.op.precedence();
+        fmt_operand(f, &self.left, |p| p < precedence)?;
+        write!(f, " {} ", self.op)?;
+        fmt_operand(f, &self.right, |p| p <= precedence)
+    }
+}
+
+/// Write an operand, in parentheses if it is a binary expression whose operator
+/// precedence needs them.
+fn fmt_operand(
+    f: &mut std::fmt::Formatter<'_>,
+    operand: &Expr,
+    needs_parentheses: impl Fn(u8) -> bool,
+) -> std::fmt::Result {
+    match operand {
+        Expr::BinaryExpr(b) if needs_parentheses(b.op.precedence()) => write!(f, "({})", b),
+        _ => write!(f, "{}", operand),
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct Alias {
     pub expr: Box<Expr>,
     pub alias: String,
@@ -451,10 +762,10 @@ pub struct Alias {
 
 impl LogicalExpr for Alias {
     fn to_field(&self, input: &Plan) -> Result<Field> {
-        Ok(Field::new(
-            self.alias.clone(),
-            self.expr.to_field(input)?.data_type,
-        ))
+        Ok(Field {
+            name: self.alias.clone(),
+            ..self.expr.to_field(input)?
+        })
     }
 }
 
@@ -473,16 +784,156 @@ impl Alias {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct GetField {
+    pub expr: Box<Expr>,
+    pub name: String,
+}
+
+impl GetField {
+    pub fn new(expr: Expr, name: String) -> Self {
+        GetField {
+            expr: Box::new(expr),
+            name,
+        }
+    }
+}
+
+impl LogicalExpr for GetField {
+    fn to_field(&self, input: &Plan) -> Result<Field> {
+        let field = self.expr.to_field(input)?;
+        let data_type = match field.data_type {
+            DataType::Struct(fields) => fields
+                .into_iter()
+                .find(|(name, _)| name == &self.name)
+                .map(|(_, data_type)| data_type)
+                .ok_or_else(|| anyhow!("No field named {} in {}", self.name, self.expr))?,
+            data_type => return Err(anyhow!("Cannot get field {} of {}", self.name, data_type)),
+        };
+        Ok(Field::new(
+            format!("{}.{}", field.name, self.name),
+            data_type,
+        ))
+    }
+}
+
+impl Display for GetField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.expr, self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct GetIndex {
+    pub expr: Box<Expr>,
+    pub index: Box<Expr>,
+}
+
+impl GetIndex {
+    pub fn new(expr: Expr, index: Expr) -> Self {
+        GetIndex {
+            expr: Box::new(expr),
+            index: Box::new(index),
+        }
+    }
+}
+
+impl LogicalExpr for GetIndex {
+    fn to_field(&self, input: &Plan) -> Result<Field> {
+        let field = self.expr.to_field(input)?;
+        let index_type = self.index.to_field(input)?.data_type;
+        if !matches!(index_type, DataType::Int32 | DataType::Int64) {
+            return Err(anyhow!("List index must be an integer, got {}", index_type));
+        }
+        match field.data_type {
+            DataType::List(item_type) => Ok(Field::new(
+                format!("{}[{}]", field.name, self.index),
+                *item_type,
+            )),
+            data_type => Err(anyhow!("Cannot index into {}", data_type)),
+        }
+    }
+}
+
+impl Display for GetIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]", self.expr, self.index)
+    }
+}
+
+/// The return type of a scalar or user-defined aggregate function.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum ReturnType {
+    /// The function always returns the given type.
+    Fixed(DataType),
+    /// The function returns the type of its first argument.
+    SameAsFirstArg,
+    /// The function returns the common type of all its arguments,
+    /// which are converted to that type before the function is evaluated.
+    /// Untyped NULL arguments do not take part in choosing the type.
+    CommonType,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct ScalarFunction {
     pub name: String,
     pub args: Vec<Expr>,
-    pub return_type: DataType,
+    pub return_type: ReturnType,
+}
+
+impl ReturnType {
+    /// Return the type returned by the function `name` called with the arguments.
+    pub fn resolve(&self, name: &str, args: &[Expr], input: &Plan) -> Result<DataType> {
+        Ok(match self {
+            ReturnType::Fixed(data_type) => data_type.clone(),
+            ReturnType::SameAsFirstArg => match args.first() {
+                Some(arg) => arg.to_field(input)?.data_type,
+                None => return Err(anyhow!("{} expects at least one argument", name)),
+            },
+            ReturnType::CommonType => {
+                if args.iter().all(Expr::is_untyped) {
+                    return args
+                        .first()
+                        .ok_or_else(|| anyhow!("{} expects at least one argument", name))?
+                        .to_field(input)
+                        .map(|f| f.data_type);
+                }
+                let mut common: Option<DataType> = None;
+                for arg in args.iter().filter(|arg| !arg.is_untyped()) {
+                    let data_type = arg.to_field(input)?.data_type;
+                    common = Some(match common {
+                        None => data_type,
+                        Some(common) => common.common_type(&data_type).ok_or_else(|| {
+                            anyhow!(
+                                "{} arguments have incompatible types {} and {}",
+                                name,
+                                common,
+                                data_type
+                            )
+                        })?,
+                    });
+                }
+                common.ok_or_else(|| anyhow!("{} expects at least one argument", name))?
+            }
+        })
+    }
 }
 
 impl LogicalExpr for ScalarFunction {
-    fn to_field(&self, _input: &Plan) -> Result<Field> {
-        Ok(Field::new(self.name.clone(), self.return_type.clone()))
+    fn to_field(&self, input: &Plan) -> Result<Field> {
+        let data_type = self.return_type.resolve(&self.name, &self.args, input)?;
+        let nullable = self
+            .args
+            .iter()
+            .map(|arg| Ok(arg.to_field(input)?.nullable))
+            .collect::<Result<Vec<_>>>()?;
+        // COALESCE only returns null if all its arguments do, NULLIF can always return null.
+        let nullable = match self.name.as_str() {
+            "coalesce" => nullable.iter().all(|n| *n),
+            "nullif" => true,
+            _ => nullable.iter().any(|n| *n),
+        };
+        Ok(Field::new(self.name.clone(), data_type).with_nullable(nullable))
     }
 }
 
@@ -501,7 +952,7 @@ impl Display for ScalarFunction {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum AggregateFunction {
     Sum,
     Min,
@@ -509,6 +960,24 @@ pub enum AggregateFunction {
     Avg,
     Count,
     CountDistinct,
+    Median,
+    /// The exact percentile, interpolated between the closest values.
+    PercentileCont,
+    /// An approximate percentile, computed from a t-digest.
+    ApproxPercentile,
+    /// The value of the first row, by the ordering arguments that follow the value if any.
+    FirstValue,
+    /// The value of the last row, by the ordering arguments that follow the value if any.
+    LastValue,
+    /// Any non-null value of the group.
+    AnyValue,
+    /// True if all the non-null values of the group are true.
+    BoolAnd,
+    /// True if any non-null value of the group is true.
+    BoolOr,
+    /// A user-defined aggregate function.
+    #[serde(skip)]
+    Udaf(AggregateUdf),
 }
 
 impl AggregateFunction {
@@ -520,6 +989,15 @@ impl AggregateFunction {
             AggregateFunction::Avg => "avg".to_string(),
             AggregateFunction::Count => "count".to_string(),
             AggregateFunction::CountDistinct => "count_distinct".to_string(),
+            AggregateFunction::Median => "median".to_string(),
+            AggregateFunction::PercentileCont => "percentile_cont".to_string(),
+            AggregateFunction::ApproxPercentile => "approx_percentile".to_string(),
+            AggregateFunction::FirstValue => "first_value".to_string(),
+            AggregateFunction::LastValue => "last_value".to_string(),
+            AggregateFunction::AnyValue => "any_value".to_string(),
+            AggregateFunction::BoolAnd => "bool_and".to_string(),
+            AggregateFunction::BoolOr => "bool_or".to_string(),
+            AggregateFunction::Udaf(udaf) => udaf.name().to_string(),
         }
     }
 }
@@ -533,43 +1011,245 @@ impl Display for AggregateFunction {
             AggregateFunction::Avg => "AVG",
             AggregateFunction::Count => "COUNT",
             AggregateFunction::CountDistinct => "COUNT DISTINCT",
+            AggregateFunction::Median => "MEDIAN",
+            AggregateFunction::PercentileCont => "PERCENTILE_CONT",
+            AggregateFunction::ApproxPercentile => "APPROX_PERCENTILE",
+            AggregateFunction::FirstValue => "FIRST_VALUE",
+            AggregateFunction::LastValue => "LAST_VALUE",
+            AggregateFunction::AnyValue => "ANY_VALUE",
+            AggregateFunction::BoolAnd => "BOOL_AND",
+            AggregateFunction::BoolOr => "BOOL_OR",
+            AggregateFunction::Udaf(udaf) => udaf.name(),
         };
         write!(f, "{}", display)
     }
 }
 
 /// AggregateFunction is a logical expression that represents an aggregate function.
-#[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct AggregateExpr {
     pub fun: AggregateFunction,
-    pub expr: Box<Expr>,
+    pub args: Vec<Expr>,
     pub is_distinct: bool,
 }
 
 impl LogicalExpr for AggregateExpr {
     fn to_field(&self, input: &Plan) -> Result<Field> {
-        Ok(Field::new(
-            self.fun.get_name(),
-            self.expr.to_field(input)?.data_type,
-        ))
+        let name = self.fun.get_name();
+        let return_type = match &self.fun {
+            AggregateFunction::Udaf(udaf) => udaf.return_type().clone(),
+            AggregateFunction::Count | AggregateFunction::CountDistinct => {
+                ReturnType::Fixed(DataType::Int64)
+            }
+            AggregateFunction::Avg
+            | AggregateFunction::Median
+            | AggregateFunction::PercentileCont
+            | AggregateFunction::ApproxPercentile => {
+                let arg_type = ReturnType::SameAsFirstArg.resolve(&name, &self.args, input)?;
+                if !arg_type.is_numeric() {
+                    return Err(anyhow!(
+                        "{} expects a numeric argument, got {}",
+                        name,
+                        arg_type
+                    ));
+                }
+                ReturnType::Fixed(DataType::Float64)
+            }
+            AggregateFunction::BoolAnd | AggregateFunction::BoolOr => {
+                let arg_type = ReturnType::SameAsFirstArg.resolve(&name, &self.args, input)?;
+                if arg_type != DataType::Boolean {
+                    return Err(anyhow!(
+                        "{} expects a Boolean argument, got {}",
+                        name,
+                        arg_type
+                    ));
+                }
+                ReturnType::Fixed(DataType::Boolean)
+            }
+            _ => ReturnType::SameAsFirstArg,
+        };
+        let data_type = return_type.resolve(&name, &self.args, input)?;
+        Ok(Field::new(self.default_name(), data_type))
+    }
+}
+
+impl AggregateExpr {
+    /// The name of the output column of the aggregate unless it is aliased, the
+    /// function with its arguments, such as `max(c1)`.
+    fn default_name(&self) -> String {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| match arg {
+                Expr::Column(column) => column.name.clone(),
+                arg => arg.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self.is_distinct && self.fun != AggregateFunction::CountDistinct {
+            true => format!("{}(DISTINCT {})", self.fun.get_name(), args),
+            false => format!("{}({})", self.fun.get_name(), args),
+        }
     }
 }
 
 impl Display for AggregateExpr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
         if self.is_distinct {
-            write!(f, "{}(DISTINCT {})", self.fun, self.expr)
+            write!(f, "{}(DISTINCT {})", self.fun, args)
         } else {
-            write!(f, "{}({})", self.fun, self.expr)
+            write!(f, "{}({})", self.fun, args)
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum WindowFunction {
+    /// The number of the row in its partition, from 1.
+    RowNumber,
+    /// The rank of the row in its partition, with gaps after ties.
+    Rank,
+    /// The rank of the row in its partition, without gaps after ties.
+    DenseRank,
+    /// The value of the row a number of rows after the row in its partition,
+    /// or a default value when there is no such row.
+    Lead,
+    /// The value of the row a number of rows before the row in its partition,
+    /// or a default value when there is no such row.
+    Lag,
+    /// The number of the bucket of the row when its partition is split into
+    /// a number of buckets of as equal sizes as possible, from 1.
+    Ntile,
+}
+
+impl WindowFunction {
+    fn get_name(&self) -> String {
+        match self {
+            WindowFunction::RowNumber => "row_number".to_string(),
+            WindowFunction::Rank => "rank".to_string(),
+            WindowFunction::DenseRank => "dense_rank".to_string(),
+            WindowFunction::Lead => "lead".to_string(),
+            WindowFunction::Lag => "lag".to_string(),
+            WindowFunction::Ntile => "ntile".to_string(),
+        }
+    }
+}
+
+impl Display for WindowFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let display = match &self {
+            WindowFunction::RowNumber => "ROW_NUMBER",
+            WindowFunction::Rank => "RANK",
+            WindowFunction::DenseRank => "DENSE_RANK",
+            WindowFunction::Lead => "LEAD",
+            WindowFunction::Lag => "LAG",
+            WindowFunction::Ntile => "NTILE",
+        };
+        write!(f, "{}", display)
+    }
+}
+
+/// WindowExpr is a logical expression that represents a window function, computed for each
+/// row over the rows of its partition in ascending order of `order_by`.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct WindowExpr {
+    pub fun: WindowFunction,
+    pub args: Vec<Expr>,
+    pub partition_by: Vec<Expr>,
+    pub order_by: Vec<Expr>,
+}
+
+impl LogicalExpr for WindowExpr {
+    fn to_field(&self, input: &Plan) -> Result<Field> {
+        for expr in self
+            .args
+            .iter()
+            .chain(&self.partition_by)
+            .chain(&self.order_by)
+        {
+            expr.to_field(input)?;
+        }
+        let name = self.fun.get_name();
+        let data_type = match self.fun {
+            WindowFunction::RowNumber | WindowFunction::Rank | WindowFunction::DenseRank => {
+                DataType::Int64
+            }
+            WindowFunction::Lead | WindowFunction::Lag => {
+                if self.args.len() != 3 {
+                    return Err(anyhow!(
+                        "{} expects a value, an offset and a default value",
+                        name
+                    ));
+                }
+                let offset_type = self.args[1].to_field(input)?.data_type;
+                if !matches!(offset_type, DataType::Int32 | DataType::Int64) {
+                    return Err(anyhow!(
+                        "{} offset must be an integer, got {}",
+                        name,
+                        offset_type
+                    ));
+                }
+                ReturnType::SameAsFirstArg.resolve(&name, &self.args, input)?
+            }
+            WindowFunction::Ntile => {
+                let buckets_type = ReturnType::SameAsFirstArg.resolve(&name, &self.args, input)?;
+                if !matches!(buckets_type, DataType::Int32 | DataType::Int64) {
+                    return Err(anyhow!(
+                        "{} number of buckets must be an integer, got {}",
+                        name,
+                        buckets_type
+                    ));
+                }
+                DataType::Int64
+            }
+        };
+        Ok(Field::new(name, data_type))
+    }
+}
+
+impl Display for WindowExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |exprs: &[Expr]| {
+            exprs
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(f, "{}({}) OVER (", self.fun, join(&self.args))?;
+        if !self.partition_by.is_empty() {
+            write!(f, "PARTITION BY {}", join(&self.partition_by))?;
+            if !self.order_by.is_empty() {
+                write!(f, " ")?;
+            }
+        }
+        if !self.order_by.is_empty() {
+            write!(f, "ORDER BY {}", join(&self.order_by))?;
+        }
+        write!(f, ")")
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::ops::{Add, Not};
 
-    use crate::logical_plan::expr_fn::{col, lit};
+    use super::LogicalExpr;
+    use crate::{
+        data_types::{column_array::DataType, schema::Field},
+        logical_plan::{
+            expr_fn::{abs, bool_and, bool_or, coalesce, col, lit, null, sqrt, typed_null},
+            plan::Plan,
+            scan::Scan,
+        },
+        test_util::get_primitive_field_data_source,
+    };
 
     #[test]
     fn test_add() {
@@ -624,4 +1304,51 @@ mod test {
         let e = col("a") % lit(1);
         assert_eq!(e.to_string(), "#a % 1");
     }
+
+    #[test]
+    fn test_bool_aggregate_to_field() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let plan = Plan::Scan(Scan::new(path, csv_data_source, vec![]));
+        let field = bool_and(col("c1").gt(lit(1))).to_field(&plan).unwrap();
+        assert_eq!(field.data_type, DataType::Boolean);
+        assert_eq!(
+            bool_or(col("c1")).to_field(&plan).unwrap_err().to_string(),
+            "bool_or expects a Boolean argument, got Int32"
+        );
+    }
+
+    #[test]
+    fn test_scalar_function_to_field() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let plan = Plan::Scan(Scan::new(path, csv_data_source, vec![]));
+        assert_eq!(
+            abs(col("c3")).to_field(&plan).unwrap(),
+            Field::new("abs".to_string(), DataType::Int64)
+        );
+        assert_eq!(
+            sqrt(col("c3")).to_field(&plan).unwrap(),
+            Field::new("sqrt".to_string(), DataType::Float64)
+        );
+    }
+
+    #[test]
+    fn test_null_to_field() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let plan = Plan::Scan(Scan::new(path, csv_data_source, vec![]));
+        assert_eq!(
+            typed_null(DataType::Int64).to_field(&plan).unwrap(),
+            Field::new("NULL".to_string(), DataType::Int64)
+        );
+        assert_eq!(null().to_field(&plan).unwrap().data_type, DataType::Utf8);
+        assert!(!lit(1).to_field(&plan).unwrap().nullable);
+        assert!((lit(1) + null()).to_field(&plan).unwrap().nullable);
+        assert_eq!(
+            (lit(1) + null()).to_field(&plan).unwrap().data_type,
+            DataType::Int32
+        );
+
+        let field = coalesce(vec![null(), lit(1_i64)]).to_field(&plan).unwrap();
+        assert_eq!(field.data_type, DataType::Int64);
+        assert!(!field.nullable);
+    }
 }
@@ -1,4 +1,10 @@
-use super::expr::{AggregateExpr, AggregateFunction, BinaryExpr, Expr, Operator, ScalarValue};
+use super::expr::{
+    AggregateExpr, AggregateFunction, BinaryExpr, Cast, Expr, Operator, Placeholder, ReturnType,
+    ScalarFunction, ScalarValue, WindowExpr, WindowFunction,
+};
+use crate::data_types::{column_array::DataType, extension::ExtensionRef};
+
+use anyhow::{bail, Result};
 
 /// Create a column expression based on a qualified or unqualified column name
 pub fn col(ident: &str) -> Expr {
@@ -37,7 +43,7 @@ pub fn min(expr: Expr) -> Expr {
     Expr::AggregateFunction(AggregateExpr {
         fun: AggregateFunction::Min,
         is_distinct: false,
-        expr: Box::new(expr),
+        args: vec![expr],
     })
 }
 
@@ -46,7 +52,7 @@ pub fn max(expr: Expr) -> Expr {
     Expr::AggregateFunction(AggregateExpr {
         fun: AggregateFunction::Max,
         is_distinct: false,
-        expr: Box::new(expr),
+        args: vec![expr],
     })
 }
 
@@ -55,7 +61,7 @@ pub fn sum(expr: Expr) -> Expr {
     Expr::AggregateFunction(AggregateExpr {
         fun: AggregateFunction::Sum,
         is_distinct: false,
-        expr: Box::new(expr),
+        args: vec![expr],
     })
 }
 
@@ -64,7 +70,7 @@ pub fn avg(expr: Expr) -> Expr {
     Expr::AggregateFunction(AggregateExpr {
         fun: AggregateFunction::Avg,
         is_distinct: false,
-        expr: Box::new(expr),
+        args: vec![expr],
     })
 }
 
@@ -73,7 +79,85 @@ pub fn count(expr: Expr) -> Expr {
     Expr::AggregateFunction(AggregateExpr {
         fun: AggregateFunction::Count,
         is_distinct: false,
-        expr: Box::new(expr),
+        args: vec![expr],
+    })
+}
+
+/// Create an expression to represent the median() aggregate function
+pub fn median(expr: Expr) -> Expr {
+    Expr::AggregateFunction(AggregateExpr {
+        fun: AggregateFunction::Median,
+        is_distinct: false,
+        args: vec![expr],
+    })
+}
+
+/// Create an expression to represent the percentile_cont() aggregate function,
+/// where the percentile is between 0 and 1.
+pub fn percentile_cont(expr: Expr, percentile: f64) -> Expr {
+    Expr::AggregateFunction(AggregateExpr {
+        fun: AggregateFunction::PercentileCont,
+        is_distinct: false,
+        args: vec![expr, lit(percentile)],
+    })
+}
+
+/// Create an expression to represent the approx_percentile() aggregate function,
+/// where the percentile is between 0 and 1.
+pub fn approx_percentile(expr: Expr, percentile: f64) -> Expr {
+    Expr::AggregateFunction(AggregateExpr {
+        fun: AggregateFunction::ApproxPercentile,
+        is_distinct: false,
+        args: vec![expr, lit(percentile)],
+    })
+}
+
+/// Create an expression to represent the first_value() aggregate function,
+/// which returns the value of the first row by the ordering expressions,
+/// or of the first row read when there are none.
+pub fn first_value(expr: Expr, order_by: Vec<Expr>) -> Expr {
+    Expr::AggregateFunction(AggregateExpr {
+        fun: AggregateFunction::FirstValue,
+        is_distinct: false,
+        args: std::iter::once(expr).chain(order_by).collect(),
+    })
+}
+
+/// Create an expression to represent the last_value() aggregate function,
+/// which returns the value of the last row by the ordering expressions,
+/// or of the last row read when there are none.
+pub fn last_value(expr: Expr, order_by: Vec<Expr>) -> Expr {
+    Expr::AggregateFunction(AggregateExpr {
+        fun: AggregateFunction::LastValue,
+        is_distinct: false,
+        args: std::iter::once(expr).chain(order_by).collect(),
+    })
+}
+
+/// Create an expression to represent the any_value() aggregate function
+pub fn any_value(expr: Expr) -> Expr {
+    Expr::AggregateFunction(AggregateExpr {
+        fun: AggregateFunction::AnyValue,
+        is_distinct: false,
+        args: vec![expr],
+    })
+}
+
+/// Create an expression to represent the bool_and() aggregate function
+pub fn bool_and(expr: Expr) -> Expr {
+    Expr::AggregateFunction(AggregateExpr {
+        fun: AggregateFunction::BoolAnd,
+        is_distinct: false,
+        args: vec![expr],
+    })
+}
+
+/// Create an expression to represent the bool_or() aggregate function
+pub fn bool_or(expr: Expr) -> Expr {
+    Expr::AggregateFunction(AggregateExpr {
+        fun: AggregateFunction::BoolOr,
+        is_distinct: false,
+        args: vec![expr],
     })
 }
 
@@ -82,21 +166,382 @@ pub fn count_distinct(expr: Expr) -> Expr {
     Expr::AggregateFunction(AggregateExpr {
         fun: AggregateFunction::CountDistinct,
         is_distinct: true,
-        expr: Box::new(expr),
+        args: vec![expr],
     })
 }
 
+fn window_function(
+    fun: WindowFunction,
+    args: Vec<Expr>,
+    partition_by: Vec<Expr>,
+    order_by: Vec<Expr>,
+) -> Expr {
+    Expr::WindowFunction(WindowExpr {
+        fun,
+        args,
+        partition_by,
+        order_by,
+    })
+}
+
+/// Create an expression to represent the row_number() window function
+pub fn row_number(partition_by: Vec<Expr>, order_by: Vec<Expr>) -> Expr {
+    window_function(WindowFunction::RowNumber, vec![], partition_by, order_by)
+}
+
+/// Create an expression to represent the rank() window function
+pub fn rank(partition_by: Vec<Expr>, order_by: Vec<Expr>) -> Expr {
+    window_function(WindowFunction::Rank, vec![], partition_by, order_by)
+}
+
+/// Create an expression to represent the dense_rank() window function
+pub fn dense_rank(partition_by: Vec<Expr>, order_by: Vec<Expr>) -> Expr {
+    window_function(WindowFunction::DenseRank, vec![], partition_by, order_by)
+}
+
+/// Create an expression to represent the lead() window function, which returns the value
+/// `offset` rows after the row in its partition, or `default` when there is no such row
+pub fn lead(
+    expr: Expr,
+    offset: i64,
+    default: Expr,
+    partition_by: Vec<Expr>,
+    order_by: Vec<Expr>,
+) -> Expr {
+    window_function(
+        WindowFunction::Lead,
+        vec![expr, lit(offset), default],
+        partition_by,
+        order_by,
+    )
+}
+
+/// Create an expression to represent the lag() window function, which returns the value
+/// `offset` rows before the row in its partition, or `default` when there is no such row
+pub fn lag(
+    expr: Expr,
+    offset: i64,
+    default: Expr,
+    partition_by: Vec<Expr>,
+    order_by: Vec<Expr>,
+) -> Expr {
+    window_function(
+        WindowFunction::Lag,
+        vec![expr, lit(offset), default],
+        partition_by,
+        order_by,
+    )
+}
+
+/// Create an expression to represent the ntile() window function, which splits
+/// each partition into `buckets` buckets and returns the number of the bucket of the row
+pub fn ntile(buckets: i64, partition_by: Vec<Expr>, order_by: Vec<Expr>) -> Expr {
+    window_function(
+        WindowFunction::Ntile,
+        vec![lit(buckets)],
+        partition_by,
+        order_by,
+    )
+}
+
+fn scalar_function(name: &str, args: Vec<Expr>, return_type: ReturnType) -> Expr {
+    Expr::ScalarFunction(ScalarFunction {
+        name: name.to_string(),
+        args,
+        return_type,
+    })
+}
+
+/// Create an expression that returns true if the string matches the regex pattern
+pub fn regexp_like(expr: Expr, pattern: &str) -> Expr {
+    scalar_function(
+        "regexp_like",
+        vec![expr, lit(pattern.to_string())],
+        ReturnType::Fixed(DataType::Boolean),
+    )
+}
+
+/// Create an expression that returns the first capture group matched by the regex pattern
+/// (or the whole match if the pattern has no groups)
+pub fn regexp_match(expr: Expr, pattern: &str) -> Expr {
+    scalar_function(
+        "regexp_match",
+        vec![expr, lit(pattern.to_string())],
+        ReturnType::Fixed(DataType::Utf8),
+    )
+}
+
+/// Create an expression that returns the given capture group matched by the regex pattern
+/// (group 0 is the whole match)
+pub fn regexp_extract(expr: Expr, pattern: &str, group: i32) -> Expr {
+    scalar_function(
+        "regexp_extract",
+        vec![expr, lit(pattern.to_string()), lit(group)],
+        ReturnType::Fixed(DataType::Utf8),
+    )
+}
+
+/// Create an expression that returns a list of the given capture group of every match of
+/// the regex pattern (group 0 is the whole match)
+pub fn regexp_extract_all(expr: Expr, pattern: &str, group: i32) -> Expr {
+    scalar_function(
+        "regexp_extract_all",
+        vec![expr, lit(pattern.to_string()), lit(group)],
+        ReturnType::Fixed(DataType::List(Box::new(DataType::Utf8))),
+    )
+}
+
+/// Create an expression to represent the abs() scalar function
+pub fn abs(expr: Expr) -> Expr {
+    scalar_function("abs", vec![expr], ReturnType::SameAsFirstArg)
+}
+
+/// Create an expression to represent the sqrt() scalar function
+pub fn sqrt(expr: Expr) -> Expr {
+    scalar_function("sqrt", vec![expr], ReturnType::Fixed(DataType::Float64))
+}
+
+/// Create an expression to represent the pow() scalar function
+pub fn pow(base: Expr, exponent: Expr) -> Expr {
+    scalar_function(
+        "pow",
+        vec![base, exponent],
+        ReturnType::Fixed(DataType::Float64),
+    )
+}
+
+/// Create an expression to represent the exp() scalar function
+pub fn exp(expr: Expr) -> Expr {
+    scalar_function("exp", vec![expr], ReturnType::Fixed(DataType::Float64))
+}
+
+/// Create an expression to represent the ln() scalar function
+pub fn ln(expr: Expr) -> Expr {
+    scalar_function("ln", vec![expr], ReturnType::Fixed(DataType::Float64))
+}
+
+/// Create an expression to represent the log10() scalar function
+pub fn log10(expr: Expr) -> Expr {
+    scalar_function("log10", vec![expr], ReturnType::Fixed(DataType::Float64))
+}
+
+/// Create an expression to represent the round() scalar function
+pub fn round(expr: Expr) -> Expr {
+    scalar_function("round", vec![expr], ReturnType::SameAsFirstArg)
+}
+
+/// Create an expression to represent the floor() scalar function
+pub fn floor(expr: Expr) -> Expr {
+    scalar_function("floor", vec![expr], ReturnType::SameAsFirstArg)
+}
+
+/// Create an expression to represent the ceil() scalar function
+pub fn ceil(expr: Expr) -> Expr {
+    scalar_function("ceil", vec![expr], ReturnType::SameAsFirstArg)
+}
+
+/// Create an expression that returns true if the CIDR block contains the IP address
+pub fn inet_contains(cidr: Expr, ip: Expr) -> Expr {
+    scalar_function(
+        "inet_contains",
+        vec![cidr, ip],
+        ReturnType::Fixed(DataType::Boolean),
+    )
+}
+
+/// Create an expression that returns the edit distance between two strings
+pub fn levenshtein(a: Expr, b: Expr) -> Expr {
+    scalar_function(
+        "levenshtein",
+        vec![a, b],
+        ReturnType::Fixed(DataType::Int32),
+    )
+}
+
+/// Create an expression that returns the Jaro-Winkler similarity of two strings, between 0 and 1
+pub fn jaro_winkler(a: Expr, b: Expr) -> Expr {
+    scalar_function(
+        "jaro_winkler",
+        vec![a, b],
+        ReturnType::Fixed(DataType::Float64),
+    )
+}
+
+/// Create an expression that returns the Soundex code of a string
+pub fn soundex(expr: Expr) -> Expr {
+    scalar_function("soundex", vec![expr], ReturnType::Fixed(DataType::Utf8))
+}
+
+/// Create an expression that returns the start of the `interval` wide bucket containing
+/// the timestamp, with buckets aligned to `origin`
+pub fn date_bin(interval: i64, timestamp: Expr, origin: i64) -> Expr {
+    scalar_function(
+        "date_bin",
+        vec![lit(interval), timestamp, lit(origin)],
+        ReturnType::Fixed(DataType::Int64),
+    )
+}
+
+/// Create an expression that returns the host of a URL
+pub fn url_host(url: Expr) -> Expr {
+    scalar_function("url_host", vec![url], ReturnType::Fixed(DataType::Utf8))
+}
+
+/// Create an expression that returns the path of a URL
+pub fn url_path(url: Expr) -> Expr {
+    scalar_function("url_path", vec![url], ReturnType::Fixed(DataType::Utf8))
+}
+
+/// Create an expression that returns the value of the named query parameter of a URL
+pub fn url_query_param(url: Expr, name: &str) -> Expr {
+    scalar_function(
+        "url_query_param",
+        vec![url, lit(name.to_string())],
+        ReturnType::Fixed(DataType::Utf8),
+    )
+}
+
+/// Create an expression that returns the distance between two points in meters
+pub fn st_distance(a: Expr, b: Expr) -> Expr {
+    scalar_function(
+        "st_distance",
+        vec![a, b],
+        ReturnType::Fixed(DataType::Float64),
+    )
+}
+
+/// Create an expression that returns true if the point is inside the bounding box
+/// given by its south-west and north-east corners
+pub fn st_within_bbox(point: Expr, south_west: Expr, north_east: Expr) -> Expr {
+    scalar_function(
+        "st_within_bbox",
+        vec![point, south_west, north_east],
+        ReturnType::Fixed(DataType::Boolean),
+    )
+}
+
+/// Create an expression that returns the text form of a binary value, in base64 or hex
+pub fn encode(expr: Expr, encoding: &str) -> Expr {
+    scalar_function(
+        "encode",
+        vec![expr, lit(encoding.to_string())],
+        ReturnType::Fixed(DataType::Utf8),
+    )
+}
+
+/// Create an expression that returns the binary value of a text in base64 or hex
+pub fn decode(expr: Expr, encoding: &str) -> Expr {
+    scalar_function(
+        "decode",
+        vec![expr, lit(encoding.to_string())],
+        ReturnType::Fixed(DataType::Binary),
+    )
+}
+
+/// Create an expression that returns the MD5 digest of a string or binary value,
+/// as lowercase hex digits
+pub fn md5(expr: Expr) -> Expr {
+    scalar_function("md5", vec![expr], ReturnType::Fixed(DataType::Utf8))
+}
+
+/// Create an expression that returns the SHA-256 digest of a string or binary value,
+/// as lowercase hex digits
+pub fn sha256(expr: Expr) -> Expr {
+    scalar_function("sha256", vec![expr], ReturnType::Fixed(DataType::Utf8))
+}
+
+/// Create an expression that returns the first of its arguments that is not null
+pub fn coalesce(exprs: Vec<Expr>) -> Expr {
+    scalar_function("coalesce", exprs, ReturnType::CommonType)
+}
+
+/// Create an expression that returns null if both arguments are equal, and the first argument otherwise
+pub fn nullif(expr: Expr, other: Expr) -> Expr {
+    scalar_function("nullif", vec![expr, other], ReturnType::CommonType)
+}
+
+/// Create a literal expression from the text form of a value of an extension type.
+/// The literal holds the stored value, so it can be compared with columns of that type.
+pub fn ext_lit(extension: &ExtensionRef, s: &str) -> Result<Expr> {
+    let value = extension.parse(s)?;
+    let value = match extension.storage_type() {
+        DataType::Int32 => ScalarValue::Int32(*value.downcast_ref::<i32>().unwrap()),
+        DataType::Int64 => ScalarValue::Int64(*value.downcast_ref::<i64>().unwrap()),
+        DataType::Float32 => ScalarValue::Float32(*value.downcast_ref::<f32>().unwrap()),
+        DataType::Float64 => ScalarValue::Float64(*value.downcast_ref::<f64>().unwrap()),
+        DataType::Boolean => ScalarValue::Boolean(*value.downcast_ref::<bool>().unwrap()),
+        DataType::Utf8 => ScalarValue::String(value.downcast_ref::<String>().unwrap().clone()),
+        data_type @ (DataType::Binary | DataType::List(_) | DataType::Struct(_)) => {
+            bail!("Literals of type {} are not supported", data_type)
+        }
+    };
+    Ok(Expr::Literal(value))
+}
+
 /// Create a literal expression
 pub fn lit<T: Literal>(n: T) -> Expr {
     n.lit()
 }
 
+/// Create an expression that casts the expression to the given type,
+/// failing on values that cannot be cast
+pub fn cast(expr: Expr, data_type: DataType) -> Expr {
+    Expr::Cast(Cast {
+        expr: Box::new(expr),
+        data_type,
+        try_cast: false,
+    })
+}
+
+/// Create an expression that casts the expression to the given type,
+/// returning null for values that cannot be cast
+pub fn try_cast(expr: Expr, data_type: DataType) -> Expr {
+    Expr::Cast(Cast {
+        expr: Box::new(expr),
+        data_type,
+        try_cast: true,
+    })
+}
+
+/// Create an untyped NULL literal, which takes the type of the expressions it is
+/// combined with
+pub fn null() -> Expr {
+    Expr::Literal(ScalarValue::Null(None))
+}
+
+/// Create a NULL literal of the given type
+pub fn typed_null(data_type: DataType) -> Expr {
+    Expr::Literal(ScalarValue::Null(Some(data_type)))
+}
+
+/// Create a placeholder such as `$1`, which takes the type of the expressions it is combined with
+pub fn placeholder(id: &str) -> Expr {
+    Expr::Placeholder(Placeholder {
+        id: id.to_string(),
+        data_type: None,
+    })
+}
+
+/// Create a placeholder of the given type
+pub fn typed_placeholder(id: &str, data_type: DataType) -> Expr {
+    Expr::Placeholder(Placeholder {
+        id: id.to_string(),
+        data_type: Some(data_type),
+    })
+}
+
 /// Trait for converting a type to a [`Literal`] literal expression.
 pub trait Literal {
     /// convert the value to a Literal expression
     fn lit(&self) -> Expr;
 }
 
+impl Literal for bool {
+    fn lit(&self) -> Expr {
+        Expr::Literal(ScalarValue::Boolean(*self))
+    }
+}
+
 impl Literal for String {
     fn lit(&self) -> Expr {
         Expr::Literal(ScalarValue::String(self.clone()))
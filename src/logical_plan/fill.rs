@@ -0,0 +1,71 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::{
+    expr::Expr,
+    plan::{LogicalPlan, Plan},
+};
+use crate::data_types::schema::Schema;
+
+use serde::{Deserialize, Serialize};
+
+/// The direction in which the last non-null value is carried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FillDirection {
+    /// Replace nulls by the previous non-null value.
+    Forward,
+    /// Replace nulls by the next non-null value.
+    Backward,
+}
+
+/// Logical plan that replaces the nulls of the columns by the previous or next
+/// non-null value of the column, in ascending order of the `order_by` expressions.
+/// Nulls with no such value are kept.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub input: Arc<Plan>,
+    pub columns: Vec<String>,
+    pub order_by: Vec<Expr>,
+    pub direction: FillDirection,
+}
+
+impl LogicalPlan for Fill {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![self.input.as_ref().clone()]
+    }
+}
+
+impl Display for Fill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Fill: direction={:?}, columns=[{}], orderBy={}",
+            self.direction,
+            self.columns.join(","),
+            self.order_by
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+impl Fill {
+    pub fn new(
+        input: Plan,
+        columns: Vec<String>,
+        order_by: Vec<Expr>,
+        direction: FillDirection,
+    ) -> Self {
+        Fill {
+            input: Arc::new(input),
+            columns,
+            order_by,
+            direction,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::plan::{LogicalPlan, Plan};
+use crate::data_types::schema::Schema;
+
+use serde::{Deserialize, Serialize};
+
+/// Logical plan that sorts its input by the time bucket in the first column and
+/// adds a row with null values for every missing bucket between the first and
+/// the last one, so the buckets are `every` apart.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GapFill {
+    pub input: Arc<Plan>,
+    pub every: i64,
+}
+
+impl LogicalPlan for GapFill {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![self.input.as_ref().clone()]
+    }
+}
+
+impl Display for GapFill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GapFill: every={}", self.every)
+    }
+}
+
+impl GapFill {
+    pub fn new(input: Plan, every: i64) -> Self {
+        GapFill {
+            input: Arc::new(input),
+            every,
+        }
+    }
+}
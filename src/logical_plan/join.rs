@@ -0,0 +1,100 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::{
+    expr::Expr,
+    plan::{LogicalPlan, Plan},
+};
+use crate::data_types::schema::Schema;
+
+use serde::{Deserialize, Serialize};
+
+/// Logical plan of an inner join, which returns the columns of the left input followed by
+/// those of the right one, for every pair of rows whose keys are equal.
+/// The left keys are computed over the left input and the right keys over the right one.
+/// Null keys never match, and without keys every pair of rows is returned.
+/// The hash table of the join is built on the left input, so it should be the smaller one.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Join {
+    pub left: Arc<Plan>,
+    pub right: Arc<Plan>,
+    pub on: Vec<(Expr, Expr)>,
+}
+
+impl LogicalPlan for Join {
+    fn schema(&self) -> Schema {
+        let mut fields = self.left.schema().fields;
+        fields.extend(self.right.schema().fields);
+        Schema::new(fields)
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![self.left.as_ref().clone(), self.right.as_ref().clone()]
+    }
+}
+
+impl Display for Join {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Join: on={}",
+            self.on
+                .iter()
+                .map(|(left, right)| format!("{} = {}", left, right))
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    }
+}
+
+impl Join {
+    pub fn new(left: Plan, right: Plan, on: Vec<(Expr, Expr)>) -> Self {
+        Join {
+            left: Arc::new(left),
+            right: Arc::new(right),
+            on,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{
+            column_array::DataType,
+            schema::{Field, Schema},
+        },
+        logical_plan::{
+            expr_fn::col,
+            join::Join,
+            plan::{LogicalPlan, Plan},
+            scan::Scan,
+        },
+    };
+
+    #[test]
+    fn test_join() {
+        let scan = |name: &str, columns: &[&str]| {
+            let fields = columns
+                .iter()
+                .map(|c| Field::new(c.to_string(), DataType::Int64))
+                .collect();
+            let data_source = Source::Mem(MemoryDataSource::new(Schema::new(fields), vec![]));
+            Plan::Scan(Scan::new(name.to_string(), data_source, vec![]))
+        };
+        let join = Join::new(
+            scan("orders", &["id", "customer_id"]),
+            scan("customers", &["customer", "region"]),
+            vec![(col("customer_id"), col("customer"))],
+        );
+        assert_eq!(join.to_string(), "Join: on=#customer_id = #customer");
+        let names = join
+            .schema()
+            .fields
+            .into_iter()
+            .map(|f| f.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["id", "customer_id", "customer", "region"]);
+        assert_eq!(join.children().len(), 2);
+    }
+}
@@ -0,0 +1,44 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::plan::{LogicalPlan, Plan};
+use crate::data_types::schema::Schema;
+
+use serde::{Deserialize, Serialize};
+
+/// Logical plan that skips the first `skip` rows of the input, and returns at most
+/// `fetch` of the rows after them, or all of them without `fetch`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Limit {
+    pub input: Arc<Plan>,
+    pub skip: usize,
+    pub fetch: Option<usize>,
+}
+
+impl LogicalPlan for Limit {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![self.input.as_ref().clone()]
+    }
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.fetch {
+            Some(fetch) => write!(f, "Limit: skip={}, fetch={}", self.skip, fetch),
+            None => write!(f, "Limit: skip={}, fetch=all", self.skip),
+        }
+    }
+}
+
+impl Limit {
+    pub fn new(input: Plan, skip: usize, fetch: Option<usize>) -> Self {
+        Limit {
+            input: Arc::new(input),
+            skip,
+            fetch,
+        }
+    }
+}
@@ -1,8 +1,20 @@
 pub mod aggregate;
+pub mod command;
 pub mod data_frame;
+#[cfg(feature = "datafusion")]
+pub mod datafusion;
+pub mod distinct_on;
 pub mod expr;
 pub mod expr_fn;
+pub mod fill;
+pub mod gap_fill;
+pub mod join;
+pub mod limit;
 pub mod plan;
 pub mod projection;
 pub mod scan;
 pub mod selection;
+pub mod sessionize;
+pub mod sort;
+pub mod udaf;
+pub mod window;
@@ -1,7 +1,17 @@
 use std::fmt::Display;
 
-use super::{aggregate::Aggregate, projection::Projection, scan::Scan, selection::Selection};
-use crate::data_types::schema::Schema;
+use super::{
+    aggregate::Aggregate, command::Command, distinct_on::DistinctOn, expr::Expr, fill::Fill,
+    gap_fill::GapFill, join::Join, limit::Limit, projection::Projection, scan::Scan,
+    selection::Selection, sessionize::Sessionize, sort::Sort, window::Window,
+};
+use crate::{
+    data_types::schema::Schema,
+    dot::{schema_lines, DotGraph},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 /// A logical plan represents a data transformation
 /// or action that returns a relation(a set of tuples).
@@ -27,12 +37,21 @@ pub trait LogicalPlan: Display {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Plan {
     Scan(Scan),
     Projection(Projection),
     Selection(Selection),
     Aggregate(Aggregate),
+    Join(Join),
+    Sessionize(Sessionize),
+    GapFill(GapFill),
+    DistinctOn(DistinctOn),
+    Fill(Fill),
+    Window(Window),
+    Sort(Sort),
+    Limit(Limit),
+    Command(Command),
 }
 
 impl LogicalPlan for Plan {
@@ -42,6 +61,15 @@ impl LogicalPlan for Plan {
             Plan::Projection(projection) => projection.schema(),
             Plan::Selection(selection) => selection.schema(),
             Plan::Aggregate(aggregate) => aggregate.schema(),
+            Plan::Join(join) => join.schema(),
+            Plan::Sessionize(sessionize) => sessionize.schema(),
+            Plan::GapFill(gap_fill) => gap_fill.schema(),
+            Plan::DistinctOn(distinct_on) => distinct_on.schema(),
+            Plan::Fill(fill) => fill.schema(),
+            Plan::Window(window) => window.schema(),
+            Plan::Sort(sort) => sort.schema(),
+            Plan::Limit(limit) => limit.schema(),
+            Plan::Command(command) => command.schema(),
         }
     }
 
@@ -51,7 +79,125 @@ impl LogicalPlan for Plan {
             Plan::Projection(projection) => projection.children(),
             Plan::Selection(selection) => selection.children(),
             Plan::Aggregate(aggregate) => aggregate.children(),
+            Plan::Join(join) => join.children(),
+            Plan::Sessionize(sessionize) => sessionize.children(),
+            Plan::GapFill(gap_fill) => gap_fill.children(),
+            Plan::DistinctOn(distinct_on) => distinct_on.children(),
+            Plan::Fill(fill) => fill.children(),
+            Plan::Window(window) => window.children(),
+            Plan::Sort(sort) => sort.children(),
+            Plan::Limit(limit) => limit.children(),
+            Plan::Command(command) => command.children(),
+        }
+    }
+}
+
+impl Plan {
+    /// Return the expressions computed by the plan itself, not by its inputs.
+    pub fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            Plan::Scan(_) | Plan::GapFill(_) | Plan::Limit(_) | Plan::Command(_) => vec![],
+            Plan::Projection(p) => p.exprs.iter().collect(),
+            Plan::Selection(s) => vec![&s.expr],
+            Plan::Aggregate(a) => a
+                .group_exprs
+                .iter()
+                .chain(a.aggregate_exprs.iter())
+                .collect(),
+            Plan::Join(j) => j.on.iter().flat_map(|(l, r)| [l, r]).collect(),
+            Plan::Sessionize(s) => vec![&s.key, &s.timestamp],
+            Plan::DistinctOn(d) => d
+                .keys
+                .iter()
+                .chain(d.order_by.iter().map(|e| &e.expr))
+                .collect(),
+            Plan::Fill(f) => f.order_by.iter().collect(),
+            Plan::Window(w) => w.window_exprs.iter().collect(),
+            Plan::Sort(s) => s.order_by.iter().map(|e| &e.expr).collect(),
+        }
+    }
+
+    /// Return the inputs of the plan, without cloning them like `children`.
+    pub fn inputs(&self) -> Vec<&Plan> {
+        match self {
+            Plan::Scan(_) | Plan::Command(_) => vec![],
+            Plan::Projection(p) => vec![&p.input],
+            Plan::Selection(s) => vec![&s.input],
+            Plan::Aggregate(a) => vec![&a.input],
+            Plan::Join(j) => vec![&j.left, &j.right],
+            Plan::Sessionize(s) => vec![&s.input],
+            Plan::GapFill(g) => vec![&g.input],
+            Plan::DistinctOn(d) => vec![&d.input],
+            Plan::Fill(f) => vec![&f.input],
+            Plan::Window(w) => vec![&w.input],
+            Plan::Sort(s) => vec![&s.input],
+            Plan::Limit(l) => vec![&l.input],
+        }
+    }
+
+    /// Rebuild the plan with `f` applied to each of its inputs. Scans and commands have no
+    /// input and are returned as is.
+    pub fn map_inputs(&self, mut f: impl FnMut(&Plan) -> Plan) -> Plan {
+        match self {
+            Plan::Scan(_) | Plan::Command(_) => self.clone(),
+            Plan::Projection(p) => Plan::Projection(Projection::new(f(&p.input), p.exprs.clone())),
+            Plan::Selection(s) => Plan::Selection(Selection::new(f(&s.input), s.expr.clone())),
+            Plan::Aggregate(a) => Plan::Aggregate(Aggregate::new(
+                f(&a.input),
+                a.group_exprs.clone(),
+                a.aggregate_exprs.clone(),
+            )),
+            Plan::Join(j) => Plan::Join(Join::new(f(&j.left), f(&j.right), j.on.clone())),
+            Plan::Sessionize(s) => Plan::Sessionize(Sessionize::new(
+                f(&s.input),
+                s.key.clone(),
+                s.timestamp.clone(),
+                s.gap,
+            )),
+            Plan::GapFill(g) => Plan::GapFill(GapFill::new(f(&g.input), g.every)),
+            Plan::DistinctOn(d) => Plan::DistinctOn(DistinctOn::new(
+                f(&d.input),
+                d.keys.clone(),
+                d.order_by.clone(),
+            )),
+            Plan::Fill(fill) => Plan::Fill(Fill::new(
+                f(&fill.input),
+                fill.columns.clone(),
+                fill.order_by.clone(),
+                fill.direction,
+            )),
+            Plan::Window(w) => Plan::Window(Window::new(f(&w.input), w.window_exprs.clone())),
+            Plan::Sort(sort) => Plan::Sort(Sort::new(f(&sort.input), sort.order_by.clone())),
+            Plan::Limit(l) => Plan::Limit(Limit::new(f(&l.input), l.skip, l.fetch)),
+        }
+    }
+
+    /// Serialize the plan to JSON. Plans that scan memory tables or call user-defined
+    /// aggregate functions cannot be serialized.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deserialize a plan serialized by `to_json`.
+    pub fn from_json(json: &str) -> Result<Plan> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Return a Graphviz graph of the plan, with a node per operator showing its
+    /// output schema.
+    pub fn to_dot(&self) -> String {
+        let mut graph = DotGraph::new();
+        self.add_to_dot(&mut graph);
+        graph.to_string()
+    }
+
+    fn add_to_dot(&self, graph: &mut DotGraph) -> usize {
+        let node = graph.add_node(vec![vec![self.to_string()], schema_lines(&self.schema())]);
+        for child in self.children() {
+            let child = child.add_to_dot(graph);
+            graph.add_edge(node, child);
         }
+        node
     }
 }
 
@@ -62,6 +208,67 @@ impl Display for Plan {
             Plan::Projection(projection) => projection.fmt(f),
             Plan::Selection(selection) => selection.fmt(f),
             Plan::Aggregate(aggregate) => aggregate.fmt(f),
+            Plan::Join(join) => join.fmt(f),
+            Plan::Sessionize(sessionize) => sessionize.fmt(f),
+            Plan::GapFill(gap_fill) => gap_fill.fmt(f),
+            Plan::DistinctOn(distinct_on) => distinct_on.fmt(f),
+            Plan::Fill(fill) => fill.fmt(f),
+            Plan::Window(window) => window.fmt(f),
+            Plan::Sort(sort) => sort.fmt(f),
+            Plan::Limit(limit) => limit.fmt(f),
+            Plan::Command(command) => command.fmt(f),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{LogicalPlan, Plan};
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{
+            column_array::DataType,
+            schema::{Field, Schema},
+        },
+        logical_plan::{
+            data_frame::DataFrame,
+            expr_fn::{cast, col, lit, row_number, sum},
+            scan::Scan,
+            sort::SortExpr,
+        },
+        test_util::get_primitive_field_data_source,
+    };
+
+    #[test]
+    fn test_json_round_trip() {
+        let (path, data_source) = get_primitive_field_data_source();
+        let scan = Scan::new(path, data_source, vec![]);
+        let df = DataFrame::new(Plan::Scan(scan))
+            .filter(col("c1").gt(lit(1)).and(col("c5").lt(lit(2.5_f32))))
+            .aggregate(
+                vec![col("c2")],
+                vec![sum(cast(col("c3"), DataType::Float64))],
+            )
+            .window(vec![row_number(vec![], vec![col("c2")])])
+            .sort(vec![SortExpr::new(col("c2"), false, true)])
+            .limit(1, Some(10));
+        let plan = df.logical_plan();
+
+        let json = plan.to_json().unwrap();
+        let decoded = Plan::from_json(&json).unwrap();
+        assert_eq!(decoded.pretty(0), plan.pretty(0));
+        assert_eq!(decoded.schema(), plan.schema());
+        assert_eq!(decoded.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn test_json_memory_table() {
+        let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Int64)]);
+        let data_source = Source::Mem(MemoryDataSource::new(schema, vec![]));
+        let plan = Plan::Scan(Scan::new("t".to_string(), data_source, vec![]));
+        let error = plan.to_json().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Source::Mem cannot be serialized"));
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use super::{
     expr::{Expr, LogicalExpr},
@@ -6,9 +6,11 @@ use super::{
 };
 use crate::data_types::schema::Schema;
 
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Projection {
-    pub input: Box<Plan>,
+    pub input: Arc<Plan>,
     pub exprs: Vec<Expr>,
 }
 
@@ -44,7 +46,7 @@ impl Display for Projection {
 impl Projection {
     pub fn new(input: Plan, exprs: Vec<Expr>) -> Self {
         Projection {
-            input: Box::new(input),
+            input: Arc::new(input),
             exprs,
         }
     }
@@ -107,4 +109,21 @@ mod tests {
         let plan = Projection::new(Plan::Scan(scan_plan), vec![c1, c2, c3, c4, c5, c6]);
         assert_eq!(plan.to_string(), "Projection: #c1,#c2,#c3,#c4,#c5,#c6");
     }
+
+    #[test]
+    fn test_display_nested_expr() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let scan_plan = Scan::new(path, csv_data_source, vec![]);
+        let exprs = vec![
+            (col("c1") + col("c2")) * col("c3"),
+            col("c1") + col("c2") * col("c3"),
+            col("c1") - (col("c2") - col("c3")),
+            !(col("c1").eq(col("c2")).or(col("c3").eq(col("c4")))),
+        ];
+        let plan = Projection::new(Plan::Scan(scan_plan), exprs);
+        assert_eq!(
+            plan.to_string(),
+            "Projection: (#c1 + #c2) * #c3,#c1 + #c2 * #c3,#c1 - (#c2 - #c3),NOT (#c1 = #c2 OR #c3 = #c4)"
+        );
+    }
 }
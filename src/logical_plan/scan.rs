@@ -2,11 +2,13 @@ use std::fmt::Display;
 
 use super::plan::{LogicalPlan, Plan};
 use crate::{
-    data_source::{DataSource, Source},
+    data_source::{memory_data_source::MemoryDataSource, DataSource, Source},
     data_types::schema::Schema,
 };
 
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Scan {
     pub path: String,
     pub data_source: Source,
@@ -56,6 +58,12 @@ impl Scan {
             projection,
         }
     }
+
+    /// A scan of a relation without rows, with the given schema.
+    pub fn empty(schema: Schema) -> Self {
+        let data_source = Source::Mem(MemoryDataSource::new(schema, vec![]));
+        Scan::new("empty".to_string(), data_source, vec![])
+    }
 }
 
 #[cfg(test)]
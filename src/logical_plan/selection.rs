@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use super::{
     expr::Expr,
@@ -6,10 +6,12 @@ use super::{
 };
 use crate::data_types::schema::Schema;
 
+use serde::{Deserialize, Serialize};
+
 /// Logical plan representing a selection (a.k.a. filter) against an input.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Selection {
-    pub input: Box<Plan>,
+    pub input: Arc<Plan>,
     pub expr: Expr,
 }
 
@@ -32,7 +34,7 @@ impl Display for Selection {
 impl Selection {
     pub fn new(input: Plan, expr: Expr) -> Self {
         Selection {
-            input: Box::new(input),
+            input: Arc::new(input),
             expr,
         }
     }
@@ -0,0 +1,81 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::{
+    expr::Expr,
+    plan::{LogicalPlan, Plan},
+};
+use crate::data_types::{
+    column_array::DataType,
+    schema::{Field, Schema},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Logical plan that splits the events of each key into sessions.
+/// A new session starts when an event comes more than `gap` after the previous
+/// event of the same key. The input columns are kept and a `session_id` column is added.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sessionize {
+    pub input: Arc<Plan>,
+    pub key: Expr,
+    pub timestamp: Expr,
+    pub gap: i64,
+}
+
+impl LogicalPlan for Sessionize {
+    fn schema(&self) -> Schema {
+        let mut fields = self.input.schema().fields;
+        fields.push(Field::new("session_id".to_string(), DataType::Int64));
+        Schema::new(fields)
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![self.input.as_ref().clone()]
+    }
+}
+
+impl Display for Sessionize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Sessionize: key={}, timestamp={}, gap={}",
+            self.key, self.timestamp, self.gap
+        )
+    }
+}
+
+impl Sessionize {
+    pub fn new(input: Plan, key: Expr, timestamp: Expr, gap: i64) -> Self {
+        Sessionize {
+            input: Arc::new(input),
+            key,
+            timestamp,
+            gap,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logical_plan::{expr_fn::col, scan::Scan},
+        test_util::get_primitive_field_data_source,
+    };
+
+    #[test]
+    fn test_sessionize() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let scan_plan = Scan::new(path, csv_data_source, vec![]);
+        let plan = Sessionize::new(Plan::Scan(scan_plan), col("c1"), col("c3"), 10);
+        assert_eq!(
+            plan.to_string(),
+            "Sessionize: key=#c1, timestamp=#c3, gap=10"
+        );
+        let schema = plan.schema();
+        assert_eq!(
+            schema.fields.last().unwrap(),
+            &Field::new("session_id".to_string(), DataType::Int64)
+        );
+    }
+}
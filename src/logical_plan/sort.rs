@@ -0,0 +1,107 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::{
+    expr::Expr,
+    plan::{LogicalPlan, Plan},
+};
+use crate::data_types::schema::Schema;
+
+use serde::{Deserialize, Serialize};
+
+/// An expression to order rows by, with its direction and where its nulls go.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct SortExpr {
+    pub expr: Expr,
+    pub asc: bool,
+    pub nulls_first: bool,
+}
+
+impl SortExpr {
+    pub fn new(expr: Expr, asc: bool, nulls_first: bool) -> Self {
+        Self {
+            expr,
+            asc,
+            nulls_first,
+        }
+    }
+}
+
+impl Display for SortExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} NULLS {}",
+            self.expr,
+            if self.asc { "ASC" } else { "DESC" },
+            if self.nulls_first { "FIRST" } else { "LAST" }
+        )
+    }
+}
+
+/// Logical plan that orders the rows of the input by the expressions, the first one
+/// deciding first. Rows that tie are kept in input order.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sort {
+    pub input: Arc<Plan>,
+    pub order_by: Vec<SortExpr>,
+}
+
+impl LogicalPlan for Sort {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![self.input.as_ref().clone()]
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Sort: orderBy={}",
+            self.order_by
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+impl Sort {
+    pub fn new(input: Plan, order_by: Vec<SortExpr>) -> Self {
+        Sort {
+            input: Arc::new(input),
+            order_by,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logical_plan::{expr_fn::col, scan::Scan},
+        test_util::get_primitive_field_data_source,
+    };
+
+    #[test]
+    fn test_sort() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let scan_plan = Scan::new(path, csv_data_source, vec![]);
+        let plan = Sort::new(
+            Plan::Scan(scan_plan.clone()),
+            vec![
+                col("c1").sort(false, true),
+                (col("c2") + col("c3")).sort(true, false),
+            ],
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Sort: orderBy=#c1 DESC NULLS FIRST,#c2 + #c3 ASC NULLS LAST"
+        );
+        assert_eq!(plan.schema(), scan_plan.schema());
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    cmp::Ordering,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use super::expr::{AggregateExpr, AggregateFunction, Expr, ReturnType};
+use crate::physical_plan::aggregate::Accumulator;
+
+type AccumulatorFactory = Arc<dyn Fn() -> Box<dyn Accumulator> + Send + Sync>;
+
+/// A user-defined aggregate function.
+/// Each group of rows is aggregated by a new accumulator created by the function.
+/// Two functions are equal if they have the same name.
+#[derive(Clone)]
+pub struct AggregateUdf {
+    name: String,
+    return_type: ReturnType,
+    accumulator: AccumulatorFactory,
+}
+
+impl AggregateUdf {
+    pub fn new(
+        name: &str,
+        return_type: ReturnType,
+        accumulator: impl Fn() -> Box<dyn Accumulator> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            return_type,
+            accumulator: Arc::new(accumulator),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn return_type(&self) -> &ReturnType {
+        &self.return_type
+    }
+
+    pub fn create_accumulator(&self) -> Box<dyn Accumulator> {
+        (self.accumulator)()
+    }
+
+    /// Create an expression that calls the function with the given arguments.
+    pub fn call(&self, args: Vec<Expr>) -> Expr {
+        Expr::AggregateFunction(AggregateExpr {
+            fun: AggregateFunction::Udaf(self.clone()),
+            args,
+            is_distinct: false,
+        })
+    }
+}
+
+impl Debug for AggregateUdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AggregateUdf({})", self.name)
+    }
+}
+
+impl PartialEq for AggregateUdf {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for AggregateUdf {}
+
+impl PartialOrd for AggregateUdf {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.name.partial_cmp(&other.name)
+    }
+}
+
+impl Hash for AggregateUdf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
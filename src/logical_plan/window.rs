@@ -0,0 +1,94 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::{
+    expr::{Expr, LogicalExpr},
+    plan::{LogicalPlan, Plan},
+};
+use crate::data_types::schema::Schema;
+
+use serde::{Deserialize, Serialize};
+
+/// Logical plan that computes window functions over the input.
+/// The input rows and columns are kept and a column is added per window expression.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Window {
+    pub input: Arc<Plan>,
+    pub window_exprs: Vec<Expr>,
+}
+
+impl LogicalPlan for Window {
+    fn schema(&self) -> Schema {
+        let mut fields = self.input.schema().fields;
+        fields.extend(
+            self.window_exprs
+                .iter()
+                .map(|e| e.to_field(&self.input).unwrap()),
+        );
+        Schema::new(fields)
+    }
+
+    fn children(&self) -> Vec<Plan> {
+        vec![self.input.as_ref().clone()]
+    }
+}
+
+impl Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Window: windowExpr={}",
+            self.window_exprs
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+impl Window {
+    pub fn new(input: Plan, window_exprs: Vec<Expr>) -> Self {
+        Window {
+            input: Arc::new(input),
+            window_exprs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_types::{column_array::DataType, schema::Field},
+        logical_plan::{
+            expr_fn::{col, rank, row_number},
+            scan::Scan,
+        },
+        test_util::get_primitive_field_data_source,
+    };
+
+    #[test]
+    fn test_window() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let scan_plan = Scan::new(path, csv_data_source, vec![]);
+        let plan = Window::new(
+            Plan::Scan(scan_plan),
+            vec![
+                row_number(vec![col("c1")], vec![col("c3")]).alias("rn".to_string()),
+                rank(vec![], vec![col("c2"), col("c3")]),
+            ],
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Window: windowExpr=ROW_NUMBER() OVER (PARTITION BY #c1 ORDER BY #c3) as rn,RANK() OVER (ORDER BY #c2, #c3)"
+        );
+        let fields = plan.schema().fields;
+        assert_eq!(
+            &fields[fields.len() - 2..],
+            &[
+                Field::new("rn".to_string(), DataType::Int64),
+                Field::new("rank".to_string(), DataType::Int64)
+            ]
+        );
+    }
+}
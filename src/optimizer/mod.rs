@@ -1,13 +1,198 @@
 pub mod rule;
+pub mod statistics;
 
-use self::rule::{OptimizerRule, ProjectionPushDownRule};
-use crate::logical_plan::plan::Plan;
+use self::rule::{
+    ConstantSelectionRule, JoinReorderRule, OptimizerRule, PredicatePushDownRule,
+    PredicateReorderRule, ProjectionEliminationRule, ProjectionPushDownRule,
+};
+use crate::logical_plan::plan::{LogicalPlan, Plan};
+
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+
+/// Configuration of the optimizer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizerConfig {
+    disabled_rules: HashSet<&'static str>,
+    max_passes: usize,
+}
+
+impl Default for OptimizerConfig {
+    fn default() -> Self {
+        Self {
+            disabled_rules: HashSet::new(),
+            max_passes: 1,
+        }
+    }
+}
+
+impl OptimizerConfig {
+    /// Do not apply the rule with the given name, one of `Optimizer::rule_names`.
+    pub fn with_disabled_rule(mut self, name: &str) -> Result<Self> {
+        match rules().into_iter().find(|(rule, _)| *rule == name) {
+            Some((rule, _)) => self.disabled_rules.insert(rule),
+            None => bail!(
+                "No optimizer rule named {}, expected one of {}",
+                name,
+                Optimizer::rule_names().join(", ")
+            ),
+        };
+        Ok(self)
+    }
+
+    /// Set the maximum number of passes over the rules, 1 by default. The passes stop once one
+    /// leaves the plan unchanged, and no rule is applied with 0 passes.
+    pub fn with_max_passes(mut self, max_passes: usize) -> Self {
+        self.max_passes = max_passes;
+        self
+    }
+}
 
 /// Optimizer for logical plans.
-pub struct Optimizer;
+#[derive(Default)]
+pub struct Optimizer {
+    config: OptimizerConfig,
+}
 
 impl Optimizer {
-    pub fn optimize(plan: &Plan) -> Plan {
-        ProjectionPushDownRule::optimize(plan)
+    pub fn new(config: OptimizerConfig) -> Self {
+        Self { config }
+    }
+
+    /// Return the names of the rules, in the order they are applied.
+    pub fn rule_names() -> Vec<&'static str> {
+        rules().into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// Optimize a logical plan checked by the analyzer.
+    pub fn optimize(&self, plan: &Plan) -> Plan {
+        self.apply_rules(plan, |_, _| {})
+    }
+
+    /// Optimize a logical plan checked by the analyzer, and return the name of every
+    /// rule applied with the text of the plan it produced.
+    pub fn optimize_with_trace(&self, plan: &Plan) -> (Plan, Vec<(&'static str, String)>) {
+        let mut trace = vec![];
+        let plan = self.apply_rules(plan, |name, plan| trace.push((name, plan.pretty(0))));
+        (plan, trace)
+    }
+
+    /// Apply the enabled rules in passes, calling `on_rule` with the plan produced by each.
+    fn apply_rules(&self, plan: &Plan, mut on_rule: impl FnMut(&'static str, &Plan)) -> Plan {
+        let mut plan = plan.clone();
+        for _ in 0..self.config.max_passes {
+            let before = plan.pretty(0);
+            for (name, rule) in rules() {
+                if !self.config.disabled_rules.contains(name) {
+                    plan = rule(&plan);
+                    on_rule(name, &plan);
+                }
+            }
+            if plan.pretty(0) == before {
+                break;
+            }
+        }
+        plan
+    }
+}
+
+/// A named optimizer rule.
+type Rule = (&'static str, fn(&Plan) -> Plan);
+
+/// The rules of the optimizer, in the order they are applied.
+fn rules() -> Vec<Rule> {
+    vec![
+        ("constant_selection", ConstantSelectionRule::optimize),
+        ("predicate_push_down", PredicatePushDownRule::optimize),
+        ("predicate_reorder", PredicateReorderRule::optimize),
+        ("join_reorder", JoinReorderRule::optimize),
+        (
+            "projection_elimination",
+            ProjectionEliminationRule::optimize,
+        ),
+        ("projection_push_down", ProjectionPushDownRule::optimize),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logical_plan::{
+            data_frame::DataFrame,
+            expr_fn::{col, lit},
+            scan::Scan,
+        },
+        test_util::get_primitive_field_data_source,
+    };
+
+    #[test]
+    fn test_optimize_with_trace() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let df = DataFrame::new(Plan::Scan(Scan::new(path, csv_data_source, vec![])))
+            .filter(lit(1).eq(lit(1)))
+            .project(vec![col("c1")]);
+        let optimizer = Optimizer::default();
+        let (plan, trace) = optimizer.optimize_with_trace(&df.logical_plan());
+        assert_eq!(
+            plan.pretty(0),
+            optimizer.optimize(&df.logical_plan()).pretty(0)
+        );
+        assert_eq!(
+            trace.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+            Optimizer::rule_names()
+        );
+        assert!(trace[0].1.starts_with("Projection: #c1\n\tScan: "));
+        assert_eq!(trace[5].1, plan.pretty(0));
+    }
+
+    #[test]
+    fn test_optimizer_config() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let plan = DataFrame::new(Plan::Scan(Scan::new(path, csv_data_source, vec![])))
+            .filter(lit(1).eq(lit(1)))
+            .project(vec![col("c1")])
+            .logical_plan();
+
+        assert_eq!(
+            Optimizer::rule_names(),
+            vec![
+                "constant_selection",
+                "predicate_push_down",
+                "predicate_reorder",
+                "join_reorder",
+                "projection_elimination",
+                "projection_push_down"
+            ]
+        );
+        assert_eq!(
+            OptimizerConfig::default()
+                .with_disabled_rule("magic")
+                .unwrap_err()
+                .to_string(),
+            "No optimizer rule named magic, expected one of constant_selection, \
+             predicate_push_down, predicate_reorder, join_reorder, projection_elimination, \
+             projection_push_down"
+        );
+
+        let config = OptimizerConfig::default()
+            .with_disabled_rule("constant_selection")
+            .unwrap()
+            .with_disabled_rule("projection_push_down")
+            .unwrap();
+        let (optimized_plan, trace) = Optimizer::new(config).optimize_with_trace(&plan);
+        assert_eq!(trace.len(), 4);
+        assert_eq!(optimized_plan.pretty(0), plan.pretty(0));
+
+        let optimizer = Optimizer::new(OptimizerConfig::default().with_max_passes(0));
+        assert_eq!(optimizer.optimize_with_trace(&plan).1.len(), 0);
+
+        // The second pass removes the projection made redundant by pushing it into the
+        // scan, and the third one leaves the plan unchanged, so the others are skipped.
+        let optimizer = Optimizer::new(OptimizerConfig::default().with_max_passes(5));
+        let (optimized_plan, trace) = optimizer.optimize_with_trace(&plan);
+        assert_eq!(trace.len(), 18);
+        assert!(optimized_plan.pretty(0).starts_with("Scan: "));
+        assert!(optimized_plan.pretty(0).ends_with("projection=[c1]\n"));
     }
 }
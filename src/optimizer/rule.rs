@@ -1,15 +1,22 @@
-use crate::{
-    data_source::DataSource,
-    logical_plan::{
-        aggregate::Aggregate,
-        expr::Expr,
-        plan::{LogicalPlan, Plan},
-        projection::Projection,
-        scan::Scan,
-        selection::Selection,
-    },
+use super::statistics::{estimate_statistics, evaluation_cost, selectivity};
+use crate::logical_plan::{
+    aggregate::Aggregate,
+    distinct_on::DistinctOn,
+    expr::{Expr, LogicalExpr, Operator, ScalarValue},
+    expr_fn::{and, col},
+    fill::Fill,
+    gap_fill::GapFill,
+    join::Join,
+    limit::Limit,
+    plan::{LogicalPlan, Plan},
+    projection::Projection,
+    scan::Scan,
+    selection::Selection,
+    sessionize::Sessionize,
+    sort::Sort,
+    window::Window,
 };
-use std::collections::HashSet;
+use std::{cmp::Ordering, collections::HashSet, mem::discriminant};
 
 /// Rule for optimizing a logical plan.
 pub trait OptimizerRule {
@@ -17,55 +24,777 @@ pub trait OptimizerRule {
 }
 
 /// Rule for pushing down projections.
+/// Only the columns referenced anywhere above a scan are read from its data source.
 pub struct ProjectionPushDownRule;
 
 impl ProjectionPushDownRule {
-    fn push_down(plan: &Plan, column_names: &mut HashSet<String>) -> Plan {
+    /// Push down the columns of `plan` needed by its parent, all of them when `column_names`
+    /// is `None`. Projections and aggregates only need the columns their expressions reference,
+    /// the other operators pass their input columns through and add the ones they reference.
+    fn push_down(plan: &Plan, mut column_names: Option<&mut HashSet<String>>) -> Plan {
         match plan {
             Plan::Projection(p) => {
-                extract_columns(&p.exprs, &p.input, column_names);
-                let input = ProjectionPushDownRule::push_down(&p.input, column_names);
+                let mut column_names = HashSet::new();
+                extract_columns(&p.exprs, &p.input, &mut column_names);
+                let input = ProjectionPushDownRule::push_down(&p.input, Some(&mut column_names));
                 Plan::Projection(Projection::new(input, p.exprs.clone()))
             }
             Plan::Selection(s) => {
-                extract_column(&s.expr, &s.input, column_names);
+                if let Some(column_names) = column_names.as_deref_mut() {
+                    extract_column(&s.expr, &s.input, column_names);
+                }
                 let input = ProjectionPushDownRule::push_down(&s.input, column_names);
                 Plan::Selection(Selection::new(input, s.expr.clone()))
             }
             Plan::Aggregate(a) => {
-                extract_columns(&a.group_exprs, &a.input, column_names);
-                extract_columns(&a.aggregate_exprs, &a.input, column_names);
-                let input = ProjectionPushDownRule::push_down(&a.input, column_names);
+                let mut column_names = HashSet::new();
+                extract_columns(&a.group_exprs, &a.input, &mut column_names);
+                extract_columns(&a.aggregate_exprs, &a.input, &mut column_names);
+                let input = ProjectionPushDownRule::push_down(&a.input, Some(&mut column_names));
                 Plan::Aggregate(Aggregate::new(
                     input,
                     a.group_exprs.clone(),
                     a.aggregate_exprs.clone(),
                 ))
             }
+            Plan::Join(j) => push_down_join(j, column_names.as_deref()),
+            Plan::Sessionize(s) => {
+                if let Some(column_names) = column_names.as_deref_mut() {
+                    extract_column(&s.key, &s.input, column_names);
+                    extract_column(&s.timestamp, &s.input, column_names);
+                }
+                let input = ProjectionPushDownRule::push_down(&s.input, column_names);
+                Plan::Sessionize(Sessionize::new(
+                    input,
+                    s.key.clone(),
+                    s.timestamp.clone(),
+                    s.gap,
+                ))
+            }
+            Plan::DistinctOn(d) => {
+                if let Some(column_names) = column_names.as_deref_mut() {
+                    extract_columns(&d.keys, &d.input, column_names);
+                    d.order_by
+                        .iter()
+                        .for_each(|e| extract_column(&e.expr, &d.input, column_names));
+                }
+                let input = ProjectionPushDownRule::push_down(&d.input, column_names);
+                Plan::DistinctOn(DistinctOn::new(input, d.keys.clone(), d.order_by.clone()))
+            }
+            Plan::Fill(f) => {
+                if let Some(column_names) = column_names.as_deref_mut() {
+                    column_names.extend(f.columns.iter().cloned());
+                    extract_columns(&f.order_by, &f.input, column_names);
+                }
+                let input = ProjectionPushDownRule::push_down(&f.input, column_names);
+                Plan::Fill(Fill::new(
+                    input,
+                    f.columns.clone(),
+                    f.order_by.clone(),
+                    f.direction,
+                ))
+            }
+            Plan::Window(w) => {
+                if let Some(column_names) = column_names.as_deref_mut() {
+                    extract_columns(&w.window_exprs, &w.input, column_names);
+                }
+                let input = ProjectionPushDownRule::push_down(&w.input, column_names);
+                Plan::Window(Window::new(input, w.window_exprs.clone()))
+            }
+            Plan::Sort(sort) => {
+                if let Some(column_names) = column_names.as_deref_mut() {
+                    sort.order_by
+                        .iter()
+                        .for_each(|e| extract_column(&e.expr, &sort.input, column_names));
+                }
+                let input = ProjectionPushDownRule::push_down(&sort.input, column_names);
+                Plan::Sort(Sort::new(input, sort.order_by.clone()))
+            }
+            Plan::Limit(l) => {
+                let input = ProjectionPushDownRule::push_down(&l.input, column_names);
+                Plan::Limit(Limit::new(input, l.skip, l.fetch))
+            }
+            Plan::GapFill(g) => {
+                // The time buckets are in the first column.
+                if let Some(column_names) = column_names.as_deref_mut() {
+                    if let Some(bucket) = g.input.schema().fields.first() {
+                        column_names.insert(bucket.name.clone());
+                    }
+                }
+                let input = ProjectionPushDownRule::push_down(&g.input, column_names);
+                Plan::GapFill(GapFill::new(input, g.every))
+            }
             Plan::Scan(s) => {
-                let valid_filed_names = s
-                    .data_source
-                    .get_schema()
+                // Keep the order of the scanned schema, so column positions stay stable.
+                let push_down = s
+                    .schema()
                     .fields
                     .iter()
+                    .filter(|f| column_names.as_ref().is_none_or(|c| c.contains(&f.name)))
                     .map(|f| f.name.clone())
-                    .collect::<HashSet<String>>();
-
-                let mut push_down = valid_filed_names
-                    .iter()
-                    .filter(|&n| column_names.contains(n))
-                    .cloned()
                     .collect::<Vec<String>>();
-                push_down.sort();
                 Plan::Scan(Scan::new(s.path.clone(), s.data_source.clone(), push_down))
             }
+            // The inputs of commands are optimized when they are executed.
+            Plan::Command(_) => plan.clone(),
         }
     }
 }
 
+/// Push down the columns of a join needed by its parent into its inputs, which also read
+/// their keys.
+fn push_down_join(join: &Join, column_names: Option<&HashSet<String>>) -> Plan {
+    let push_down_input = |input: &Plan, keys: Vec<&Expr>| {
+        let mut input_names = column_names.map(|column_names| {
+            let mut input_names = input
+                .schema()
+                .fields
+                .into_iter()
+                .map(|f| f.name)
+                .filter(|name| column_names.contains(name))
+                .collect::<HashSet<_>>();
+            keys.iter()
+                .for_each(|e| extract_column(e, input, &mut input_names));
+            input_names
+        });
+        push_down_join_input(input, input_names.as_mut())
+    };
+    let left = push_down_input(&join.left, join.on.iter().map(|(l, _)| l).collect());
+    let right = push_down_input(&join.right, join.on.iter().map(|(_, r)| r).collect());
+    Plan::Join(Join::new(left, right, join.on.clone()))
+}
+
+/// Push down the columns of an input of a join needed above it. A projection below the
+/// join, such as one renaming columns the other input also has, only computes those columns,
+/// as the plans above the join refer to its columns by name.
+fn push_down_join_input(input: &Plan, column_names: Option<&mut HashSet<String>>) -> Plan {
+    match (input, column_names) {
+        (Plan::Projection(p), Some(column_names)) => {
+            let exprs = p
+                .exprs
+                .iter()
+                .filter(|e| {
+                    e.to_field(&p.input)
+                        .is_ok_and(|f| column_names.contains(&f.name))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            // Without columns, the rows could not be counted.
+            let exprs = match exprs.is_empty() {
+                true => p.exprs.clone(),
+                false => exprs,
+            };
+            let projection = Plan::Projection(Projection::new(p.input.as_ref().clone(), exprs));
+            ProjectionPushDownRule::push_down(&projection, None)
+        }
+        (input, column_names) => ProjectionPushDownRule::push_down(input, column_names),
+    }
+}
+
 impl OptimizerRule for ProjectionPushDownRule {
     fn optimize(plan: &Plan) -> Plan {
-        ProjectionPushDownRule::push_down(plan, &mut HashSet::new())
+        ProjectionPushDownRule::push_down(plan, None)
+    }
+}
+
+/// Rule for removing filters that do not depend on their input.
+/// The terms of a filter that are always true are dropped, with the filter when none is left,
+/// and a filter with a term that is always false is replaced by an empty relation.
+pub struct ConstantSelectionRule;
+
+impl ConstantSelectionRule {
+    fn eliminate(plan: &Plan) -> Plan {
+        let plan = plan.map_inputs(ConstantSelectionRule::eliminate);
+        let Plan::Selection(s) = &plan else {
+            return plan;
+        };
+        let mut kept = vec![];
+        for conjunct in split_conjunction(&s.expr) {
+            match constant_truth(conjunct) {
+                Some(true) => {}
+                Some(false) => return Plan::Scan(Scan::empty(plan.schema())),
+                None => kept.push(conjunct.clone()),
+            }
+        }
+        match conjunction(kept) {
+            Some(predicate) => Plan::Selection(Selection::new(s.input.as_ref().clone(), predicate)),
+            None => s.input.as_ref().clone(),
+        }
+    }
+}
+
+impl OptimizerRule for ConstantSelectionRule {
+    fn optimize(plan: &Plan) -> Plan {
+        ConstantSelectionRule::eliminate(plan)
+    }
+}
+
+/// Fold a predicate made of literals to its value.
+/// Returns `None` when it depends on the input or may be null.
+fn constant_truth(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Literal(ScalarValue::Boolean(b)) => Some(*b),
+        Expr::Not(n) => constant_truth(&n.expr).map(|b| !b),
+        Expr::Alias(a) => constant_truth(&a.expr),
+        Expr::BinaryExpr(b) => {
+            let (left, right) = (constant_truth(&b.left), constant_truth(&b.right));
+            match b.op {
+                Operator::And => match (left, right) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                },
+                Operator::Or => match (left, right) {
+                    (Some(true), _) | (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                },
+                op => {
+                    let (Expr::Literal(left), Expr::Literal(right)) =
+                        (b.left.as_ref(), b.right.as_ref())
+                    else {
+                        return None;
+                    };
+                    if discriminant(left) != discriminant(right)
+                        || matches!(left, ScalarValue::Null(_))
+                    {
+                        return None;
+                    }
+                    let ordering = left.partial_cmp(right)?;
+                    match op {
+                        Operator::Eq => Some(ordering == Ordering::Equal),
+                        Operator::Neq => Some(ordering != Ordering::Equal),
+                        Operator::Gt => Some(ordering == Ordering::Greater),
+                        Operator::GtEq => Some(ordering != Ordering::Less),
+                        Operator::Lt => Some(ordering == Ordering::Less),
+                        Operator::LtEq => Some(ordering != Ordering::Greater),
+                        _ => None,
+                    }
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Rule for reordering the terms of filters.
+/// Filters evaluate their AND terms in order and skip the rows a term rejects, so the terms
+/// that are cheap and reject many rows go first, and expensive ones like regular expressions last.
+/// A term that can fail stays after the terms written before it, which may guard it.
+pub struct PredicateReorderRule;
+
+impl PredicateReorderRule {
+    fn reorder(plan: &Plan) -> Plan {
+        let plan = plan.map_inputs(PredicateReorderRule::reorder);
+        let Plan::Selection(s) = &plan else {
+            return plan;
+        };
+        let statistics = estimate_statistics(&s.input);
+        // The classic rank of a term: its cost per rejected row.
+        let rank = |e: &Expr| {
+            let rejected = 1.0 - selectivity(e, &s.input, &statistics);
+            match rejected > 0.0 {
+                true => evaluation_cost(e) / rejected,
+                false => f64::INFINITY,
+            }
+        };
+        // The terms between two that can fail are sorted, and those that can fail stay
+        // where they are.
+        let mut conjuncts = vec![];
+        let mut sorted = 0;
+        for conjunct in split_conjunction(&s.expr) {
+            if can_fail(conjunct) {
+                sorted = conjuncts.len() + 1;
+            }
+            conjuncts.push((rank(conjunct), conjunct.clone()));
+            conjuncts[sorted..]
+                .sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        }
+        let predicate = conjunction(conjuncts.into_iter().map(|(_, e)| e).collect()).unwrap();
+        Plan::Selection(Selection::new(s.input.as_ref().clone(), predicate))
+    }
+}
+
+impl OptimizerRule for PredicateReorderRule {
+    fn optimize(plan: &Plan) -> Plan {
+        PredicateReorderRule::reorder(plan)
+    }
+}
+
+/// The functions that can fail on some values of their arguments.
+const FALLIBLE_FUNCTIONS: &[&str] = &[
+    "abs",
+    "date_bin",
+    "decode",
+    "inet_contains",
+    "st_distance",
+    "st_within_bbox",
+];
+
+/// Return true if evaluating the expression can fail on some rows: integer arithmetic
+/// can overflow or divide by zero, casts can fail to convert, and so can some functions.
+fn can_fail(expr: &Expr) -> bool {
+    let fails = match expr {
+        Expr::BinaryExpr(b) => matches!(
+            b.op,
+            Operator::Add
+                | Operator::Subtract
+                | Operator::Multiply
+                | Operator::Divide
+                | Operator::Modulus
+        ),
+        Expr::Cast(c) => !c.try_cast,
+        Expr::ScalarFunction(f) => FALLIBLE_FUNCTIONS.contains(&f.name.as_str()),
+        _ => false,
+    };
+    fails || expr.children().into_iter().any(can_fail)
+}
+
+/// Rule for pushing down predicates.
+/// The parts of a filter on top of an aggregate that only test its group keys are
+/// applied to the input of the aggregate instead, so fewer groups are built.
+/// The parts of a filter on top of a join that only test the columns of one of its inputs
+/// are applied to that input, and the equalities between both inputs become keys of the join.
+pub struct PredicatePushDownRule;
+
+impl PredicatePushDownRule {
+    fn push_down(plan: &Plan) -> Plan {
+        let plan = match plan {
+            Plan::Selection(s) => match s.input.as_ref() {
+                Plan::Aggregate(a) => push_below_aggregate(s, a),
+                Plan::Join(j) => push_into_join(s, j),
+                _ => plan.clone(),
+            },
+            _ => plan.clone(),
+        };
+        plan.map_inputs(PredicatePushDownRule::push_down)
+    }
+}
+
+impl OptimizerRule for PredicatePushDownRule {
+    fn optimize(plan: &Plan) -> Plan {
+        PredicatePushDownRule::push_down(plan)
+    }
+}
+
+/// Move the conjuncts of `selection` that only reference the group keys of `aggregate` below it,
+/// rewritten in terms of the group expressions.
+/// Aggregates without groups produce a row even for an empty input, so nothing moves below them.
+fn push_below_aggregate(selection: &Selection, aggregate: &Aggregate) -> Plan {
+    let aggregate_plan = selection.input.as_ref();
+    let mut pushed = vec![];
+    let mut kept = vec![];
+    for conjunct in split_conjunction(&selection.expr) {
+        let mut on_keys = !aggregate.group_exprs.is_empty();
+        let rewritten = transform_expr(conjunct, &mut |e| {
+            column_position(e, aggregate_plan).map(|i| match aggregate.group_exprs.get(i) {
+                Some(key) => unalias(key).clone(),
+                None => {
+                    on_keys = false;
+                    e.clone()
+                }
+            })
+        });
+        if on_keys {
+            pushed.push(rewritten);
+        } else {
+            kept.push(conjunct.clone());
+        }
+    }
+
+    let Some(pushed) = conjunction(pushed) else {
+        return Plan::Selection(selection.clone());
+    };
+    let aggregate = Plan::Aggregate(Aggregate::new(
+        Plan::Selection(Selection::new(aggregate.input.as_ref().clone(), pushed)),
+        aggregate.group_exprs.clone(),
+        aggregate.aggregate_exprs.clone(),
+    ));
+    match conjunction(kept) {
+        Some(kept) => Plan::Selection(Selection::new(aggregate, kept)),
+        None => aggregate,
+    }
+}
+
+/// Move the conjuncts of `selection` that only reference the columns of one input of `join`
+/// below it, and make the equalities between expressions over each input keys of the join.
+/// Conjuncts that can fail stay above the join, which may remove the rows they fail on.
+fn push_into_join(selection: &Selection, join: &Join) -> Plan {
+    let join_plan = selection.input.as_ref();
+    let column_names = |plan: &Plan| {
+        plan.schema()
+            .fields
+            .into_iter()
+            .map(|f| f.name)
+            .collect::<HashSet<_>>()
+    };
+    let (left_names, right_names) = (column_names(&join.left), column_names(&join.right));
+    let mut on = join.on.clone();
+    let (mut left, mut right, mut kept) = (vec![], vec![], vec![]);
+    for conjunct in split_conjunction(&selection.expr) {
+        if can_fail(conjunct) {
+            kept.push(conjunct.clone());
+        } else if references_only(conjunct, join_plan, &left_names) {
+            left.push(conjunct.clone());
+        } else if references_only(conjunct, join_plan, &right_names) {
+            right.push(conjunct.clone());
+        } else if let Some(key) = join_key(conjunct, join_plan, &left_names, &right_names) {
+            on.push(key);
+        } else {
+            kept.push(conjunct.clone());
+        }
+    }
+
+    if left.is_empty() && right.is_empty() && on.len() == join.on.len() {
+        return Plan::Selection(selection.clone());
+    }
+    let filter = |input: &Plan, predicates| match conjunction(predicates) {
+        Some(predicate) => Plan::Selection(Selection::new(input.clone(), predicate)),
+        None => input.clone(),
+    };
+    let join = Plan::Join(Join::new(
+        filter(&join.left, left),
+        filter(&join.right, right),
+        on,
+    ));
+    match conjunction(kept) {
+        Some(kept) => Plan::Selection(Selection::new(join, kept)),
+        None => join,
+    }
+}
+
+/// Return true if the expression references columns of `input`, all in `column_names`.
+fn references_only(expr: &Expr, input: &Plan, column_names: &HashSet<String>) -> bool {
+    let mut columns = HashSet::new();
+    extract_column(expr, input, &mut columns);
+    !columns.is_empty() && columns.is_subset(column_names)
+}
+
+/// Return the keys compared by an equality between an expression over the columns of the
+/// left input of a join and one over the columns of its right input.
+fn join_key(
+    expr: &Expr,
+    input: &Plan,
+    left_names: &HashSet<String>,
+    right_names: &HashSet<String>,
+) -> Option<(Expr, Expr)> {
+    let Expr::BinaryExpr(b) = expr else {
+        return None;
+    };
+    if b.op != Operator::Eq {
+        return None;
+    }
+    let (l, r) = (b.left.as_ref(), b.right.as_ref());
+    if references_only(l, input, left_names) && references_only(r, input, right_names) {
+        Some((l.clone(), r.clone()))
+    } else if references_only(l, input, right_names) && references_only(r, input, left_names) {
+        Some((r.clone(), l.clone()))
+    } else {
+        None
+    }
+}
+
+/// Split a predicate into the terms that are combined with AND.
+fn split_conjunction(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::BinaryExpr(b) if b.op == Operator::And => {
+            let mut conjuncts = split_conjunction(&b.left);
+            conjuncts.extend(split_conjunction(&b.right));
+            conjuncts
+        }
+        _ => vec![expr],
+    }
+}
+
+/// Combine predicates with AND, or return `None` when there are none.
+fn conjunction(exprs: Vec<Expr>) -> Option<Expr> {
+    exprs.into_iter().reduce(and)
+}
+
+/// Rule for ordering joins by their estimated cardinality.
+/// The inputs of a tree of joins are joined one at a time, starting with the two whose join is
+/// estimated to be the smallest, then adding the input that keeps the intermediate result the
+/// smallest among those sharing a key with the inputs joined so far. The hash table of every
+/// join is built on its smaller input, on the left, and inputs of unknown size are assumed to
+/// be the larger ones. A projection on top keeps the columns in their order.
+pub struct JoinReorderRule;
+
+/// A key of a join in a tree of joins, with the inputs of the tree each side refers to.
+struct JoinKey {
+    left: Expr,
+    right: Expr,
+    left_inputs: HashSet<usize>,
+    right_inputs: HashSet<usize>,
+}
+
+impl JoinReorderRule {
+    fn reorder(plan: &Plan) -> Plan {
+        if !matches!(plan, Plan::Join(_)) {
+            return plan.map_inputs(JoinReorderRule::reorder);
+        }
+        let mut inputs = vec![];
+        let mut on = vec![];
+        flatten_joins(plan, &mut inputs, &mut on);
+        let inputs = inputs
+            .iter()
+            .map(JoinReorderRule::reorder)
+            .collect::<Vec<_>>();
+        // The column names of the inputs of a tree of joins are unique.
+        let input_of = |e: &Expr| {
+            let mut columns = HashSet::new();
+            column_names(e, &mut columns)?;
+            columns
+                .iter()
+                .map(|c| {
+                    inputs
+                        .iter()
+                        .position(|input| input.schema().fields.iter().any(|f| &f.name == c))
+                })
+                .collect::<Option<HashSet<_>>>()
+        };
+        let Some(keys) = on
+            .into_iter()
+            .map(|(left, right)| {
+                Some(JoinKey {
+                    left_inputs: input_of(&left)?,
+                    right_inputs: input_of(&right)?,
+                    left,
+                    right,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            return plan.map_inputs(JoinReorderRule::reorder);
+        };
+
+        // Joins sharing a key come first, then the smallest ones, then the first inputs.
+        let cost = |(join, used): &(Plan, Vec<usize>)| (used.is_empty(), estimated_rows(join));
+        let mut placed = vec![false; keys.len()];
+        let ((i, j), (mut tree, used)) = (0..inputs.len())
+            .flat_map(|i| (i + 1..inputs.len()).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let first = HashSet::from([i]);
+                let join = join_inputs(&inputs[i], &first, &inputs[j], j, &keys, &placed);
+                ((i, j), join)
+            })
+            .min_by_key(|(_, join)| cost(join))
+            .expect("a join has two inputs");
+        used.into_iter().for_each(|k| placed[k] = true);
+        let mut joined = HashSet::from([i, j]);
+        let mut remaining = (0..inputs.len())
+            .filter(|input| !joined.contains(input))
+            .collect::<Vec<_>>();
+        while !remaining.is_empty() {
+            let (position, (join, used)) = remaining
+                .iter()
+                .map(|&input| join_inputs(&tree, &joined, &inputs[input], input, &keys, &placed))
+                .enumerate()
+                .min_by_key(|(_, join)| cost(join))
+                .unwrap();
+            used.into_iter().for_each(|k| placed[k] = true);
+            joined.insert(remaining.remove(position));
+            tree = join;
+        }
+
+        // The keys that compare inputs of both sides of a join are checked above the tree.
+        let unplaced = keys
+            .iter()
+            .zip(&placed)
+            .filter(|(_, placed)| !**placed)
+            .map(|(k, _)| k.left.clone().eq(k.right.clone()))
+            .collect();
+        if let Some(predicate) = conjunction(unplaced) {
+            tree = Plan::Selection(Selection::new(tree, predicate));
+        }
+        let schema = plan.schema();
+        if tree.schema() == schema {
+            return tree;
+        }
+        let columns = schema.fields.iter().map(|f| col(&f.name)).collect();
+        Plan::Projection(Projection::new(tree, columns))
+    }
+}
+
+impl OptimizerRule for JoinReorderRule {
+    fn optimize(plan: &Plan) -> Plan {
+        JoinReorderRule::reorder(plan)
+    }
+}
+
+/// Collect the inputs and the keys of a tree of joins.
+fn flatten_joins(plan: &Plan, inputs: &mut Vec<Plan>, on: &mut Vec<(Expr, Expr)>) {
+    match plan {
+        Plan::Join(j) => {
+            flatten_joins(&j.left, inputs, on);
+            flatten_joins(&j.right, inputs, on);
+            on.extend(j.on.iter().cloned());
+        }
+        _ => inputs.push(plan.clone()),
+    }
+}
+
+/// Join `tree`, which joins the inputs `joined`, with the input at `index`, on the keys not
+/// placed yet that compare them. The smaller of both is on the left.
+/// Returns the join with the indexes of the keys it uses.
+fn join_inputs(
+    tree: &Plan,
+    joined: &HashSet<usize>,
+    input: &Plan,
+    index: usize,
+    keys: &[JoinKey],
+    placed: &[bool],
+) -> (Plan, Vec<usize>) {
+    let other = HashSet::from([index]);
+    let mut on = vec![];
+    let mut used = vec![];
+    for (k, key) in keys.iter().enumerate() {
+        if placed[k] {
+            continue;
+        }
+        if key.left_inputs.is_subset(joined) && key.right_inputs.is_subset(&other) {
+            on.push((key.left.clone(), key.right.clone()));
+        } else if key.right_inputs.is_subset(joined) && key.left_inputs.is_subset(&other) {
+            on.push((key.right.clone(), key.left.clone()));
+        } else {
+            continue;
+        }
+        used.push(k);
+    }
+    let join = match estimated_rows(input) < estimated_rows(tree) {
+        true => Join::new(
+            input.clone(),
+            tree.clone(),
+            on.into_iter().map(|(l, r)| (r, l)).collect(),
+        ),
+        false => Join::new(tree.clone(), input.clone(), on),
+    };
+    (Plan::Join(join), used)
+}
+
+/// Return the estimated number of rows of the plan, the most there can be when unknown.
+fn estimated_rows(plan: &Plan) -> usize {
+    estimate_statistics(plan).num_rows.unwrap_or(usize::MAX)
+}
+
+/// Collect the names of the columns the expression references, or return `None` if it
+/// references a column by index.
+fn column_names(expr: &Expr, accum: &mut HashSet<String>) -> Option<()> {
+    match expr {
+        Expr::Column(c) => {
+            accum.insert(c.name.clone());
+        }
+        Expr::ColumnIndex(_) => return None,
+        _ => {}
+    }
+    expr.children()
+        .into_iter()
+        .try_for_each(|e| column_names(e, accum))
+}
+
+/// Rule for removing redundant projections.
+/// Projections that pass their input through unchanged are dropped, and a projection on top of
+/// another one is merged into it when that does not compute an expression more than once.
+pub struct ProjectionEliminationRule;
+
+impl ProjectionEliminationRule {
+    fn eliminate(plan: &Plan) -> Plan {
+        let plan = plan.map_inputs(ProjectionEliminationRule::eliminate);
+        let Plan::Projection(p) = &plan else {
+            return plan;
+        };
+        let p = match p.input.as_ref() {
+            Plan::Projection(inner) => match merge_projections(&p.exprs, inner) {
+                Some(merged) => merged,
+                None => p.clone(),
+            },
+            _ => p.clone(),
+        };
+        if is_pass_through(&p.exprs, &p.input) {
+            p.input.as_ref().clone()
+        } else {
+            Plan::Projection(p)
+        }
+    }
+}
+
+impl OptimizerRule for ProjectionEliminationRule {
+    fn optimize(plan: &Plan) -> Plan {
+        ProjectionEliminationRule::eliminate(plan)
+    }
+}
+
+/// Return true if `exprs` select every column of `input` in order, without renaming them.
+fn is_pass_through(exprs: &[Expr], input: &Plan) -> bool {
+    let fields = input.schema().fields;
+    exprs.len() == fields.len()
+        && exprs
+            .iter()
+            .enumerate()
+            .all(|(i, e)| column_position(e, input) == Some(i))
+}
+
+/// Return the position in `input` of the column referenced by `expr`, if it is a column.
+fn column_position(expr: &Expr, input: &Plan) -> Option<usize> {
+    match expr {
+        Expr::Column(c) => input.schema().fields.iter().position(|f| f.name == c.name),
+        Expr::ColumnIndex(c) => Some(c.index),
+        _ => None,
+    }
+}
+
+/// Merge the projection `exprs` on top of `inner` by replacing their column references
+/// with the expressions of `inner` they refer to, keeping the output names.
+/// Returns `None` when that would compute an expression of `inner` more than once.
+fn merge_projections(exprs: &[Expr], inner: &Projection) -> Option<Projection> {
+    let input = Plan::Projection(inner.clone());
+    let mut uses = vec![0; inner.exprs.len()];
+    exprs.iter().for_each(|e| {
+        transform_expr(e, &mut |e| {
+            column_position(e, &input).map(|i| {
+                uses[i] += 1;
+                e.clone()
+            })
+        });
+    });
+    let computed_twice = uses
+        .iter()
+        .zip(&inner.exprs)
+        .any(|(&uses, e)| uses > 1 && !matches!(unalias(e), Expr::Column(_) | Expr::Literal(_)));
+    if computed_twice {
+        return None;
+    }
+
+    let merged = exprs
+        .iter()
+        .map(|e| {
+            let name = e.to_field(&input).ok()?.name;
+            let merged = transform_expr(e, &mut |e| {
+                column_position(e, &input).map(|i| unalias(&inner.exprs[i]).clone())
+            });
+            if merged.to_field(&inner.input).ok()?.name == name {
+                Some(merged)
+            } else {
+                Some(unalias(&merged).clone().alias(name))
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(Projection::new(inner.input.as_ref().clone(), merged))
+}
+
+fn unalias(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Alias(a) => unalias(&a.expr),
+        _ => expr,
+    }
+}
+
+/// Rewrite `expr` top-down, replacing every sub-expression for which `f` returns a new one.
+fn transform_expr(expr: &Expr, f: &mut impl FnMut(&Expr) -> Option<Expr>) -> Expr {
+    match f(expr) {
+        Some(e) => e,
+        None => expr
+            .map_children(|e| Ok(transform_expr(e, f)))
+            .expect("the rewrite cannot fail"),
     }
 }
 
@@ -90,12 +819,20 @@ fn extract_column(expr: &Expr, input: &Plan, accum: &mut HashSet<String>) {
         }
         Expr::Alias(e) => extract_column(&e.expr, input, accum),
         Expr::Cast(c) => extract_column(&c.expr, input, accum),
-        Expr::AggregateFunction(a) => {
-            extract_column(&a.expr, input, accum);
+        Expr::GetField(g) => extract_column(&g.expr, input, accum),
+        Expr::GetIndex(g) => {
+            extract_column(&g.expr, input, accum);
+            extract_column(&g.index, input, accum);
+        }
+        Expr::AggregateFunction(a) => extract_columns(&a.args, input, accum),
+        Expr::WindowFunction(w) => {
+            extract_columns(&w.args, input, accum);
+            extract_columns(&w.partition_by, input, accum);
+            extract_columns(&w.order_by, input, accum);
         }
-        Expr::Literal(_) => {}
-        Expr::Not(_) => {}
-        Expr::ScalarFunction(_) => {}
+        Expr::Not(n) => extract_column(&n.expr, input, accum),
+        Expr::ScalarFunction(f) => extract_columns(&f.args, input, accum),
+        Expr::Literal(_) | Expr::Placeholder(_) => {}
     };
 }
 
@@ -103,10 +840,13 @@ fn extract_column(expr: &Expr, input: &Plan, accum: &mut HashSet<String>) {
 mod tests {
     use super::*;
     use crate::{
-        data_source::DataSource,
+        data_source::{
+            series_data_source::{Series, SeriesDataSource},
+            DataSource, Source,
+        },
         logical_plan::{
             data_frame::DataFrame,
-            expr_fn::{and, col, count, lit, max, min},
+            expr_fn::{and, col, count, lit, max, min, null, or, regexp_like, row_number},
             plan::Plan,
             scan::Scan,
         },
@@ -131,6 +871,13 @@ mod tests {
         DataFrame::new(Plan::Scan(scan_plan))
     }
 
+    /// A series of `len` distinct values in a column named `name`.
+    fn series(name: &str, len: i64) -> DataFrame {
+        let data_source = SeriesDataSource::try_new(Series::Int64(1, len, 1), 10).unwrap();
+        let scan = Scan::new(name.to_string(), Source::Series(data_source), vec![]);
+        DataFrame::new(Plan::Scan(scan)).project(vec![col("value").alias(name.to_string())])
+    }
+
     #[test]
     fn test_extract_columns() {
         let mut accum: HashSet<String> = HashSet::new();
@@ -170,4 +917,307 @@ mod tests {
             optimized_plan.pretty(0)
         );
     }
+
+    #[test]
+    fn test_projection_push_down_through_operators() {
+        // Without a projection on top, every scanned column is part of the result.
+        let df = csv().filter(col("c1").eq(lit(1)));
+        let optimized_plan = ProjectionPushDownRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Selection: #c1 = 1\n\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+
+        let df = csv()
+            .filter(col("c4").gt(lit(30)))
+            .window(vec![
+                row_number(vec![col("c1")], vec![col("c3")]).alias("rn".to_string())
+            ])
+            .project(vec![col("c2"), col("rn")])
+            .aggregate(vec![], vec![max(col("rn"))]);
+        let optimized_plan = ProjectionPushDownRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Aggregate: groupExpr=, aggregateExpr=MAX(#rn)\n\
+             \tProjection: #c2,#rn\n\
+             \t\tWindow: windowExpr=ROW_NUMBER() OVER (PARTITION BY #c1 ORDER BY #c3) as rn\n\
+             \t\t\tSelection: #c4 > 30\n\
+             \t\t\t\tScan: push_down_test; projection=[c1,c2,c3,c4]\n",
+            optimized_plan.pretty(0)
+        );
+    }
+
+    #[test]
+    fn test_projection_elimination() {
+        let all_columns = || ["c1", "c2", "c3", "c4", "c5", "c6"].map(col).to_vec();
+        let df = csv().project(all_columns());
+        let optimized_plan = ProjectionEliminationRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Scan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+
+        // Reordering the columns and back is a pass-through once merged.
+        let mut reversed = all_columns();
+        reversed.reverse();
+        let df = csv().project(reversed).project(all_columns());
+        let optimized_plan = ProjectionEliminationRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Scan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+
+        let df = csv()
+            .project(vec![col("c1"), (col("c2") + lit(1)).alias("x".to_string())])
+            .filter(col("x").gt(lit(2)))
+            .project(vec![col("c1"), col("x")])
+            .project(vec![
+                col("x").alias("y".to_string()),
+                col("c1").alias("c1".to_string()),
+            ]);
+        let optimized_plan = ProjectionEliminationRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Projection: #x as y,#c1 as c1\n\
+             \tSelection: #x > 2\n\
+             \t\tProjection: #c1,#c2 + 1 as x\n\
+             \t\t\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+
+        let df = csv()
+            .project(vec![col("c1"), (col("c2") + lit(1)).alias("x".to_string())])
+            .project(vec![col("x").alias("y".to_string()), col("c1")]);
+        let optimized_plan = ProjectionEliminationRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Projection: #c2 + 1 as y,#c1\n\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+    }
+
+    #[test]
+    fn test_projection_elimination_keeps_shared_expressions() {
+        let df = csv()
+            .project(vec![(col("c2") + lit(1)).alias("x".to_string())])
+            .project(vec![col("x"), col("x") * lit(2)]);
+        let optimized_plan = ProjectionEliminationRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Projection: #x,#x * 2\n\
+             \tProjection: #c2 + 1 as x\n\
+             \t\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+
+        // Columns and literals are free to compute again.
+        let df = csv()
+            .project(vec![
+                col("c2").alias("x".to_string()),
+                lit(1).alias("one".to_string()),
+            ])
+            .project(vec![col("x") + col("one"), col("x") * col("one")]);
+        let optimized_plan = ProjectionEliminationRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Projection: #c2 + 1,#c2 * 1\n\
+             \tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+    }
+
+    #[test]
+    fn test_predicate_push_down_through_aggregate() {
+        let df = csv()
+            .aggregate(
+                vec![col("c1"), (col("c2") + lit(1)).alias("k".to_string())],
+                vec![max(col("c3")).alias("m".to_string())],
+            )
+            .filter(
+                col("c1")
+                    .gt(lit(1))
+                    .and(col("m").lt(lit(30)))
+                    .and(col("k").eq(col("c1"))),
+            );
+        let optimized_plan = PredicatePushDownRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Selection: #m < 30\n\
+             \tAggregate: groupExpr=#c1,#c2 + 1 as k, aggregateExpr=MAX(#c3) as m\n\
+             \t\tSelection: #c1 > 1 AND #c2 + 1 = #c1\n\
+             \t\t\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+
+        // The selection is removed when all of it moves below.
+        let df = csv()
+            .aggregate(vec![col("c1")], vec![count(col("c2"))])
+            .filter(col("c1").eq(lit(1)));
+        let optimized_plan = PredicatePushDownRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Aggregate: groupExpr=#c1, aggregateExpr=COUNT(#c2)\n\
+             \tSelection: #c1 = 1\n\
+             \t\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+    }
+
+    #[test]
+    fn test_predicate_push_down_keeps_unsafe_predicates() {
+        // Filters on aggregate results, even combined with keys by OR, must stay above.
+        let df = csv()
+            .aggregate(vec![col("c1")], vec![max(col("c2"))])
+            .filter(or(col("c1").eq(lit(1)), col("max(c2)").gt(lit(10))));
+        let optimized_plan = PredicatePushDownRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Selection: #c1 = 1 OR #max(c2) > 10\n\
+             \tAggregate: groupExpr=#c1, aggregateExpr=MAX(#c2)\n\
+             \t\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+
+        // An aggregate without groups returns a row for an empty input.
+        let df = csv()
+            .aggregate(vec![], vec![count(col("c1"))])
+            .filter(lit(false));
+        let optimized_plan = PredicatePushDownRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Selection: false\n\
+             \tAggregate: groupExpr=, aggregateExpr=COUNT(#c1)\n\
+             \t\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+    }
+
+    #[test]
+    fn test_constant_selection() {
+        let df = csv()
+            .filter(lit(1).eq(lit(1)).and(col("c1").gt(lit(1))))
+            .filter(lit(true));
+        let optimized_plan = ConstantSelectionRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Selection: #c1 > 1\n\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+
+        let df = csv()
+            .filter(col("c1").gt(lit(1)).and(lit(1).gt(lit(2))))
+            .project(vec![col("c1")]);
+        let optimized_plan = ConstantSelectionRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Projection: #c1\n\tScan: empty; projection=None\n",
+            optimized_plan.pretty(0)
+        );
+        assert_eq!(optimized_plan.schema(), df.schema());
+
+        // Nulls and literals of different types are left to the execution.
+        for predicate in [
+            lit(1).eq(lit(1_i64)),
+            null().eq(null()),
+            or(col("c1").gt(lit(1)), lit(false)),
+        ] {
+            let df = csv().filter(predicate);
+            let optimized_plan = ConstantSelectionRule::optimize(&df.logical_plan());
+            assert!(matches!(optimized_plan, Plan::Selection(_)));
+        }
+    }
+
+    #[test]
+    fn test_predicate_reorder() {
+        let df = csv().filter(
+            regexp_like(col("c1"), "^1")
+                .and(col("c2").gt(lit(1)))
+                .and(col("c3").eq(lit(1)))
+                .and(lit(true).or(col("c4").eq(lit(1))))
+                .and((col("c5") + col("c6")).gt(lit(1))),
+        );
+        let optimized_plan = PredicateReorderRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Selection: #c3 = 1 AND #c2 > 1 AND regexp_like(#c1, ^1) AND (true OR #c4 = 1) AND #c5 + #c6 > 1\n\
+             \tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+    }
+
+    #[test]
+    fn test_projection_push_down_through_join() {
+        let right = csv().project(vec![
+            col("c1").alias("k".to_string()),
+            col("c2").alias("v".to_string()),
+            col("c3").alias("w".to_string()),
+        ]);
+        let df = csv()
+            .join(&right, vec![(col("c1"), col("k"))])
+            .project(vec![col("c3"), col("v")]);
+        let optimized_plan = ProjectionPushDownRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Projection: #c3,#v\n\
+             \tJoin: on=#c1 = #k\n\
+             \t\tScan: push_down_test; projection=[c1,c3]\n\
+             \t\tProjection: #c1 as k,#c2 as v\n\
+             \t\t\tScan: push_down_test; projection=[c1,c2]\n",
+            optimized_plan.pretty(0)
+        );
+    }
+
+    #[test]
+    fn test_predicate_push_down_into_join() {
+        let df = series("a", 10).join(&series("b", 10), vec![]).filter(
+            col("a")
+                .eq(col("b"))
+                .and(col("a").gt(lit(5_i64)))
+                .and((col("a") + col("b")).gt(lit(1_i64))),
+        );
+        let optimized_plan = PredicatePushDownRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Selection: #a + #b > 1\n\
+             \tJoin: on=#a = #b\n\
+             \t\tSelection: #a > 5\n\
+             \t\t\tProjection: #value as a\n\
+             \t\t\t\tScan: a; projection=None\n\
+             \t\tProjection: #value as b\n\
+             \t\t\tScan: b; projection=None\n",
+            optimized_plan.pretty(0)
+        );
+    }
+
+    #[test]
+    fn test_join_reorder() {
+        // A star schema joined in the order of the query builds the hash tables on the
+        // fact table. The most selective dimension is joined first, and every hash table
+        // is built on the smaller side.
+        let df = series("fact", 10000)
+            .join(&series("d1", 100), vec![(col("fact"), col("d1"))])
+            .join(&series("d2", 10), vec![(col("fact"), col("d2"))]);
+        let optimized_plan = JoinReorderRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Projection: #fact,#d1,#d2\n\
+             \tJoin: on=#fact = #d1\n\
+             \t\tJoin: on=#d2 = #fact\n\
+             \t\t\tProjection: #value as d2\n\
+             \t\t\t\tScan: d2; projection=None\n\
+             \t\t\tProjection: #value as fact\n\
+             \t\t\t\tScan: fact; projection=None\n\
+             \t\tProjection: #value as d1\n\
+             \t\t\tScan: d1; projection=None\n",
+            optimized_plan.pretty(0)
+        );
+        assert_eq!(optimized_plan.schema(), df.schema());
+        assert_eq!(
+            JoinReorderRule::optimize(&optimized_plan).pretty(0),
+            optimized_plan.pretty(0)
+        );
+
+        // Inputs without a key between them are joined last, and those of unknown size are
+        // never built on.
+        let df = csv()
+            .join(&series("d1", 10), vec![])
+            .join(&series("d2", 100), vec![(col("d1"), col("d2"))]);
+        let optimized_plan = JoinReorderRule::optimize(&df.logical_plan());
+        assert_eq!(
+            "Projection: #c1,#c2,#c3,#c4,#c5,#c6,#d1,#d2\n\
+             \tJoin: on=\n\
+             \t\tJoin: on=#d1 = #d2\n\
+             \t\t\tProjection: #value as d1\n\
+             \t\t\t\tScan: d1; projection=None\n\
+             \t\t\tProjection: #value as d2\n\
+             \t\t\t\tScan: d2; projection=None\n\
+             \t\tScan: push_down_test; projection=[c1,c2,c3,c4,c5,c6]\n",
+            optimized_plan.pretty(0)
+        );
+    }
 }
@@ -0,0 +1,347 @@
+use crate::{
+    data_source::DataSource,
+    data_types::statistics::{ColumnStatistics, Statistics},
+    logical_plan::{
+        expr::{Expr, Operator, ScalarValue},
+        plan::{LogicalPlan, Plan},
+    },
+};
+
+/// Selectivity of a predicate the estimates know nothing about.
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+/// Selectivity of an equality when the distinct count of the column is unknown.
+const EQ_SELECTIVITY: f64 = 0.1;
+/// Selectivity of a range comparison.
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+/// Relative cost of evaluating an operator, a scalar function, and one of the functions
+/// below that do much more work per value, such as matching a regular expression.
+const OPERATOR_COST: f64 = 1.0;
+const FUNCTION_COST: f64 = 10.0;
+const EXPENSIVE_FUNCTION_COST: f64 = 100.0;
+const EXPENSIVE_FUNCTIONS: &[&str] = &[
+    "regexp_like",
+    "regexp_match",
+    "regexp_extract",
+    "regexp_extract_all",
+    "levenshtein",
+    "jaro_winkler",
+    "url_host",
+    "url_path",
+    "url_query_param",
+    "md5",
+    "sha256",
+];
+
+/// Estimate the statistics of the output of a plan, bottom-up from the statistics
+/// of its data sources.
+pub fn estimate_statistics(plan: &Plan) -> Statistics {
+    match plan {
+        Plan::Scan(s) => {
+            let source_statistics = s.data_source.statistics();
+            let source_schema = s.data_source.get_schema();
+            let column_statistics = s
+                .schema()
+                .fields
+                .iter()
+                .map(|f| {
+                    source_schema
+                        .fields
+                        .iter()
+                        .position(|source_field| source_field.name == f.name)
+                        .and_then(|i| source_statistics.column_statistics.get(i).copied())
+                        .unwrap_or_default()
+                })
+                .collect();
+            Statistics::new(source_statistics.num_rows, column_statistics)
+        }
+        Plan::Projection(p) => {
+            let input = estimate_statistics(&p.input);
+            let column_statistics = p
+                .exprs
+                .iter()
+                .map(|e| column_statistics(e, &p.input, &input))
+                .collect();
+            Statistics::new(input.num_rows, column_statistics)
+        }
+        Plan::Selection(s) => {
+            let input = estimate_statistics(&s.input);
+            let selectivity = selectivity(&s.expr, &s.input, &input);
+            let num_rows = input
+                .num_rows
+                .map(|n| (n as f64 * selectivity).round() as usize);
+            Statistics::new(
+                num_rows,
+                cap_distinct_counts(input.column_statistics, num_rows),
+            )
+        }
+        Plan::Aggregate(a) => {
+            let input = estimate_statistics(&a.input);
+            let num_rows = group_count(&a.group_exprs, &a.input, &input);
+            let mut column_statistics = a
+                .group_exprs
+                .iter()
+                .map(|e| column_statistics(e, &a.input, &input))
+                .collect::<Vec<_>>();
+            column_statistics.resize(
+                a.group_exprs.len() + a.aggregate_exprs.len(),
+                ColumnStatistics::default(),
+            );
+            Statistics::new(num_rows, cap_distinct_counts(column_statistics, num_rows))
+        }
+        Plan::Join(j) => {
+            let left = estimate_statistics(&j.left);
+            let right = estimate_statistics(&j.right);
+            // Every key keeps the pairs of rows that have the same value, one of the
+            // distinct values of the side with the most of them.
+            let selectivity =
+                j.on.iter()
+                    .map(|(l, r)| {
+                        let distinct_count = [
+                            column_statistics(l, &j.left, &left).distinct_count,
+                            column_statistics(r, &j.right, &right).distinct_count,
+                        ]
+                        .into_iter()
+                        .flatten()
+                        .max();
+                        match distinct_count {
+                            Some(0) => 0.0,
+                            Some(distinct_count) => 1.0 / distinct_count as f64,
+                            None => EQ_SELECTIVITY,
+                        }
+                    })
+                    .product::<f64>();
+            let num_rows = match (left.num_rows, right.num_rows) {
+                (Some(l), Some(r)) => Some((l as f64 * r as f64 * selectivity).round() as usize),
+                _ => None,
+            };
+            let mut column_statistics = left.column_statistics;
+            column_statistics.extend(right.column_statistics);
+            Statistics::new(num_rows, cap_distinct_counts(column_statistics, num_rows))
+        }
+        Plan::DistinctOn(d) => {
+            let input = estimate_statistics(&d.input);
+            let num_rows = group_count(&d.keys, &d.input, &input);
+            Statistics::new(
+                num_rows,
+                cap_distinct_counts(input.column_statistics, num_rows),
+            )
+        }
+        Plan::Sessionize(s) => {
+            let mut input = estimate_statistics(&s.input);
+            input.column_statistics.push(ColumnStatistics::default());
+            input
+        }
+        Plan::Window(w) => {
+            let mut input = estimate_statistics(&w.input);
+            let column_count = input.column_statistics.len() + w.window_exprs.len();
+            input
+                .column_statistics
+                .resize(column_count, ColumnStatistics::default());
+            input
+        }
+        Plan::Fill(f) => estimate_statistics(&f.input),
+        Plan::Sort(s) => estimate_statistics(&s.input),
+        Plan::Limit(l) => {
+            let input = estimate_statistics(&l.input);
+            let num_rows = input.num_rows.map(|n| {
+                let n = n.saturating_sub(l.skip);
+                l.fetch.map_or(n, |fetch| n.min(fetch))
+            });
+            Statistics::new(
+                num_rows,
+                cap_distinct_counts(input.column_statistics, num_rows),
+            )
+        }
+        // Filling the gaps adds an unknown number of rows.
+        Plan::GapFill(g) => Statistics {
+            num_rows: None,
+            ..estimate_statistics(&g.input)
+        },
+        Plan::Command(c) => Statistics::unknown(c.schema().fields.len()),
+    }
+}
+
+/// Estimate the fraction of the input rows for which `predicate` is true.
+pub fn selectivity(predicate: &Expr, input: &Plan, statistics: &Statistics) -> f64 {
+    match predicate {
+        Expr::BinaryExpr(e) => match e.op {
+            Operator::And => {
+                selectivity(&e.left, input, statistics) * selectivity(&e.right, input, statistics)
+            }
+            Operator::Or => {
+                let left = selectivity(&e.left, input, statistics);
+                let right = selectivity(&e.right, input, statistics);
+                left + right - left * right
+            }
+            Operator::Eq => eq_selectivity(&e.left, &e.right, input, statistics),
+            Operator::Neq => 1.0 - eq_selectivity(&e.left, &e.right, input, statistics),
+            Operator::Gt | Operator::GtEq | Operator::Lt | Operator::LtEq => RANGE_SELECTIVITY,
+            _ => DEFAULT_SELECTIVITY,
+        },
+        Expr::Not(n) => 1.0 - selectivity(&n.expr, input, statistics),
+        Expr::Alias(a) => selectivity(&a.expr, input, statistics),
+        Expr::Literal(ScalarValue::Boolean(true)) => 1.0,
+        Expr::Literal(ScalarValue::Boolean(false)) => 0.0,
+        _ => DEFAULT_SELECTIVITY,
+    }
+}
+
+/// Estimate the relative cost of evaluating an expression for one row.
+pub fn evaluation_cost(expr: &Expr) -> f64 {
+    let cost = match expr {
+        Expr::Column(_) | Expr::ColumnIndex(_) | Expr::Literal(_) | Expr::Alias(_) => 0.0,
+        Expr::ScalarFunction(f) if EXPENSIVE_FUNCTIONS.contains(&f.name.as_str()) => {
+            EXPENSIVE_FUNCTION_COST
+        }
+        Expr::ScalarFunction(_) => FUNCTION_COST,
+        _ => OPERATOR_COST,
+    };
+    cost + expr
+        .children()
+        .into_iter()
+        .map(evaluation_cost)
+        .sum::<f64>()
+}
+
+/// An equality keeps one distinct value of the column it tests, when there is one.
+fn eq_selectivity(left: &Expr, right: &Expr, input: &Plan, statistics: &Statistics) -> f64 {
+    let distinct_count = [left, right]
+        .iter()
+        .filter_map(|e| column_statistics(e, input, statistics).distinct_count)
+        .max();
+    match distinct_count {
+        Some(0) => 0.0,
+        Some(distinct_count) => 1.0 / distinct_count as f64,
+        None => EQ_SELECTIVITY,
+    }
+}
+
+/// Estimate the number of groups formed by `keys`, which is the product of their
+/// distinct counts at most. There is a single group without keys.
+fn group_count(keys: &[Expr], input: &Plan, statistics: &Statistics) -> Option<usize> {
+    let groups = keys.iter().try_fold(1_usize, |groups, key| {
+        column_statistics(key, input, statistics)
+            .distinct_count
+            .map(|d| groups.saturating_mul(d))
+    })?;
+    Some(match statistics.num_rows {
+        Some(num_rows) if !keys.is_empty() => groups.min(num_rows),
+        _ => groups,
+    })
+}
+
+/// Return the statistics of the column produced by `expr`, known for plain column references only.
+fn column_statistics(expr: &Expr, input: &Plan, statistics: &Statistics) -> ColumnStatistics {
+    let index = match expr {
+        Expr::Column(c) => input.schema().fields.iter().position(|f| f.name == c.name),
+        Expr::ColumnIndex(c) => Some(c.index),
+        Expr::Alias(a) => return column_statistics(&a.expr, input, statistics),
+        _ => None,
+    };
+    ColumnStatistics {
+        distinct_count: index.and_then(|i| statistics.distinct_count(i)),
+    }
+}
+
+/// No column has more distinct values than there are rows.
+fn cap_distinct_counts(
+    column_statistics: Vec<ColumnStatistics>,
+    num_rows: Option<usize>,
+) -> Vec<ColumnStatistics> {
+    column_statistics
+        .into_iter()
+        .map(|c| ColumnStatistics {
+            distinct_count: match (c.distinct_count, num_rows) {
+                (Some(d), Some(n)) => Some(d.min(n)),
+                (d, _) => d,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::{
+            series_data_source::{Series, SeriesDataSource},
+            Source,
+        },
+        logical_plan::{
+            data_frame::DataFrame,
+            expr_fn::{col, count, lit},
+            scan::Scan,
+        },
+        test_util::get_primitive_field_data_source,
+    };
+
+    fn series(len: i64) -> DataFrame {
+        let data_source = SeriesDataSource::try_new(Series::Int64(1, len, 1), 10).unwrap();
+        DataFrame::new(Plan::Scan(Scan::new(
+            "series".to_string(),
+            Source::Series(data_source),
+            vec![],
+        )))
+    }
+
+    #[test]
+    fn test_estimate_statistics() {
+        let df = series(100);
+        assert_eq!(
+            estimate_statistics(&df.logical_plan()),
+            Statistics::new(
+                Some(100),
+                vec![ColumnStatistics {
+                    distinct_count: Some(100)
+                }]
+            )
+        );
+
+        // 1/100 of the rows match the equality and 1/3 of them the range.
+        let df = series(100)
+            .filter(col("value").eq(lit(5_i64)).or(col("value").gt(lit(50_i64))))
+            .project(vec![col("value").alias("v".to_string()), lit(1_i64)]);
+        let statistics = estimate_statistics(&df.logical_plan());
+        assert_eq!(statistics.num_rows, Some(34));
+        assert_eq!(statistics.distinct_count(0), Some(34));
+        assert_eq!(statistics.distinct_count(1), None);
+
+        let df = series(100).aggregate(vec![col("value")], vec![count(col("value"))]);
+        let statistics = estimate_statistics(&df.logical_plan());
+        assert_eq!(statistics.num_rows, Some(100));
+        assert_eq!(statistics.column_statistics.len(), 2);
+
+        let df = series(100).aggregate(vec![], vec![count(col("value"))]);
+        assert_eq!(estimate_statistics(&df.logical_plan()).num_rows, Some(1));
+    }
+
+    #[test]
+    fn test_estimate_join_statistics() {
+        let series =
+            |name: &str, len| series(len).project(vec![col("value").alias(name.to_string())]);
+        // Each key matches one of the distinct values of the side with the most of them.
+        let df = series("a", 100).join(&series("b", 10), vec![(col("a"), col("b"))]);
+        let statistics = estimate_statistics(&df.logical_plan());
+        assert_eq!(statistics.num_rows, Some(10));
+        assert_eq!(statistics.distinct_count(0), Some(10));
+        assert_eq!(statistics.distinct_count(1), Some(10));
+
+        let df = series("a", 100).join(&series("b", 10), vec![]);
+        assert_eq!(estimate_statistics(&df.logical_plan()).num_rows, Some(1000));
+    }
+
+    #[test]
+    fn test_estimate_unknown_statistics() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let df = DataFrame::new(Plan::Scan(Scan::new(
+            path,
+            csv_data_source,
+            vec!["c1".to_string(), "c2".to_string()],
+        )))
+        .filter(col("c1").eq(lit(1)));
+        assert_eq!(
+            estimate_statistics(&df.logical_plan()),
+            Statistics::unknown(2)
+        );
+    }
+}
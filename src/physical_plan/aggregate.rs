@@ -1,21 +1,52 @@
-use std::{any::Any, fmt::Display};
+use std::{any::Any, cmp::Ordering, collections::HashSet, fmt::Display, ops::Add, sync::Arc};
 
-use super::expr::Expr;
-use crate::logical_plan::expr::AggregateFunction;
+use super::{
+    expr::{evaluate_from_nullable_values, Column, Expr},
+    percentile::{to_f64, ApproxPercentileAccumulator, PercentileAccumulator},
+    sort_key::{KeyValue, SortKey},
+};
+use crate::{
+    data_types::{
+        arrow_field_array::ArrowFieldArray,
+        column_array::{ArrayRef, ColumnArray, DataType},
+    },
+    logical_plan::expr::AggregateFunction,
+};
 
-/// Accumulator for aggregate functions.
-pub struct Accumulator {
+use anyhow::Result;
+use arrow::{
+    array::{BooleanArray, PrimitiveArray},
+    datatypes::{ArrowPrimitiveType, Float32Type, Float64Type, Int32Type, Int64Type},
+};
+use serde::{Deserialize, Serialize};
+
+/// Accumulates the rows of a group into the result of an aggregate function.
+/// Values are passed as `None` when they are null.
+pub trait Accumulator {
+    /// Update the state with the argument values of an input row.
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()>;
+    /// Return the intermediate state, which can be merged into another
+    /// accumulator of the same function.
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>>;
+    /// Merge the intermediate state of another accumulator into this one.
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()>;
+    /// Return the result for the rows seen so far.
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>>;
+}
+
+/// Accumulator for the built-in aggregate functions.
+pub struct BuiltinAccumulator {
     pub fun: AggregateFunction,
     pub value: Option<Box<dyn Any>>,
 }
 
-impl Accumulator {
+impl BuiltinAccumulator {
     pub fn new(fun: AggregateFunction) -> Self {
         Self { fun, value: None }
     }
 }
 
-impl Accumulator {
+impl BuiltinAccumulator {
     pub fn accumulate(&mut self, value: Option<Box<dyn Any>>) {
         if let Some(value) = value {
             if self.value.is_none() {
@@ -33,7 +64,8 @@ impl Accumulator {
                             self.value = Some(value);
                         }
                     }
-                    _ => unimplemented!(),
+                    AggregateFunction::AnyValue => {}
+                    ref fun => unreachable!("{} has its own accumulator", fun),
                 }
             }
         }
@@ -44,38 +76,281 @@ impl Accumulator {
     }
 }
 
-fn is_max(l: &Box<dyn Any>, r: &Box<dyn Any>) -> bool {
-    if l.is::<i32>() {
-        return l.downcast_ref::<i32>().unwrap() > r.downcast_ref::<i32>().unwrap();
+impl Accumulator for BuiltinAccumulator {
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        self.accumulate(values.into_iter().next().flatten());
+        Ok(())
     }
-    if l.is::<i64>() {
-        return l.downcast_ref::<i64>().unwrap() > r.downcast_ref::<i64>().unwrap();
+
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+        Ok(vec![self.value.as_ref().map(clone_value)])
     }
-    if l.is::<f32>() {
-        return l.downcast_ref::<f32>().unwrap() > r.downcast_ref::<f32>().unwrap();
+
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        self.update(state)
     }
-    if l.is::<f64>() {
-        return l.downcast_ref::<f64>().unwrap() > r.downcast_ref::<f64>().unwrap();
+
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+        Ok(self.value.as_ref().map(clone_value))
     }
-    unreachable!()
 }
 
-fn is_min(l: &Box<dyn Any>, r: &Box<dyn Any>) -> bool {
-    if l.is::<i32>() {
-        return l.downcast_ref::<i32>().unwrap() < r.downcast_ref::<i32>().unwrap();
+/// Accumulator for COUNT, which counts the non-null values.
+#[derive(Default)]
+pub struct CountAccumulator {
+    count: i64,
+}
+
+impl Accumulator for CountAccumulator {
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        if values.into_iter().next().flatten().is_some() {
+            self.count += 1;
+        }
+        Ok(())
     }
-    if l.is::<i64>() {
-        return l.downcast_ref::<i64>().unwrap() < r.downcast_ref::<i64>().unwrap();
+
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+        Ok(vec![Some(Box::new(self.count))])
     }
-    if l.is::<f32>() {
-        return l.downcast_ref::<f32>().unwrap() < r.downcast_ref::<f32>().unwrap();
+
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        if let Some(count) = state.into_iter().next().flatten() {
+            self.count += count.downcast_ref::<i64>().unwrap();
+        }
+        Ok(())
     }
-    if l.is::<f64>() {
-        return l.downcast_ref::<f64>().unwrap() < r.downcast_ref::<f64>().unwrap();
+
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+        Ok(Some(Box::new(self.count)))
+    }
+}
+
+/// Accumulator for COUNT DISTINCT, which keeps the set of the distinct non-null values.
+#[derive(Default)]
+pub struct CountDistinctAccumulator {
+    values: HashSet<KeyValue>,
+}
+
+impl Accumulator for CountDistinctAccumulator {
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        if let Some(value) = values.into_iter().next().flatten() {
+            self.values.insert(KeyValue::try_from_any(value.as_ref())?);
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+        Ok(vec![Some(Box::new(self.values.clone()))])
+    }
+
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        if let Some(values) = state.into_iter().next().flatten() {
+            self.values.extend(
+                values
+                    .downcast_ref::<HashSet<KeyValue>>()
+                    .unwrap()
+                    .iter()
+                    .cloned(),
+            );
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+        Ok(Some(Box::new(self.values.len() as i64)))
+    }
+}
+
+/// Accumulator for BOOL_AND and BOOL_OR.
+/// Once a value decides the result, false for BOOL_AND and true for BOOL_OR,
+/// the remaining values are not read.
+pub struct BoolAccumulator {
+    and: bool,
+    value: Option<bool>,
+}
+
+impl BoolAccumulator {
+    pub fn new(and: bool) -> Self {
+        Self { and, value: None }
+    }
+
+    fn is_decided(&self) -> bool {
+        self.value == Some(!self.and)
+    }
+}
+
+impl Accumulator for BoolAccumulator {
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        if self.is_decided() {
+            return Ok(());
+        }
+        if let Some(value) = values.into_iter().next().flatten() {
+            self.value = Some(*value.downcast_ref::<bool>().unwrap());
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+        self.evaluate().map(|value| vec![value])
+    }
+
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        self.update(state)
+    }
+
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+        Ok(self.value.map(|v| Box::new(v) as Box<dyn Any>))
+    }
+}
+
+/// Accumulator for AVG, which keeps the sum and the count of the non-null values.
+#[derive(Default)]
+pub struct AvgAccumulator {
+    sum: f64,
+    count: i64,
+}
+
+impl Accumulator for AvgAccumulator {
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        if let Some(value) = values.into_iter().next().flatten() {
+            self.sum += to_f64(value.as_ref())?;
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+        Ok(vec![Some(Box::new(self.sum)), Some(Box::new(self.count))])
+    }
+
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        let mut state = state.into_iter().flatten();
+        if let (Some(sum), Some(count)) = (state.next(), state.next()) {
+            self.sum += sum.downcast_ref::<f64>().unwrap();
+            self.count += count.downcast_ref::<i64>().unwrap();
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+        if self.count == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(self.sum / self.count as f64)))
+    }
+}
+
+/// Accumulator for FIRST_VALUE and LAST_VALUE.
+/// The first argument is the value and the others are the ordering of the rows,
+/// ascending with nulls last. Without ordering the rows are taken in the order they are read.
+pub struct FirstLastAccumulator {
+    last: bool,
+    row: Option<(SortKey, Option<Box<dyn Any>>)>,
+}
+
+impl FirstLastAccumulator {
+    pub fn new(last: bool) -> Self {
+        Self { last, row: None }
+    }
+
+    fn replaces(&self, key: &SortKey) -> bool {
+        match &self.row {
+            None => true,
+            Some((current, _)) if self.last => key >= current,
+            Some((current, _)) => key < current,
+        }
+    }
+}
+
+impl Accumulator for FirstLastAccumulator {
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        let mut values = values.into_iter();
+        let value = values.next().flatten();
+        let key = values
+            .map(|v| {
+                let v = v.map(|v| KeyValue::try_from_any(v.as_ref())).transpose()?;
+                Ok((v.is_none(), v))
+            })
+            .collect::<Result<SortKey>>()?;
+        if self.replaces(&key) {
+            self.row = Some((key, value));
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+        Ok(match &self.row {
+            Some((key, value)) => {
+                vec![Some(Box::new(key.clone())), value.as_ref().map(clone_value)]
+            }
+            None => vec![None, None],
+        })
+    }
+
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        let mut state = state.into_iter();
+        if let Some(key) = state.next().flatten() {
+            let key = key.downcast_ref::<SortKey>().unwrap().clone();
+            if self.replaces(&key) {
+                self.row = Some((key, state.next().flatten()));
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+        Ok(self
+            .row
+            .as_ref()
+            .and_then(|(_, value)| value.as_ref().map(clone_value)))
+    }
+}
+
+fn clone_value(value: &Box<dyn Any>) -> Box<dyn Any> {
+    if let Some(v) = value.downcast_ref::<i32>() {
+        return Box::new(*v);
+    }
+    if let Some(v) = value.downcast_ref::<i64>() {
+        return Box::new(*v);
+    }
+    if let Some(v) = value.downcast_ref::<f32>() {
+        return Box::new(*v);
+    }
+    if let Some(v) = value.downcast_ref::<f64>() {
+        return Box::new(*v);
+    }
+    if let Some(v) = value.downcast_ref::<bool>() {
+        return Box::new(*v);
+    }
+    if let Some(v) = value.downcast_ref::<String>() {
+        return Box::new(v.clone());
     }
     unreachable!()
 }
 
+fn is_max(l: &Box<dyn Any>, r: &Box<dyn Any>) -> bool {
+    compare(l.as_ref(), r.as_ref()) == Some(Ordering::Greater)
+}
+
+fn is_min(l: &Box<dyn Any>, r: &Box<dyn Any>) -> bool {
+    compare(l.as_ref(), r.as_ref()) == Some(Ordering::Less)
+}
+
+/// Compare two values of the same type. Strings compare lexicographically
+/// and false is less than true. NaN is not ordered with any value.
+fn compare(l: &dyn Any, r: &dyn Any) -> Option<Ordering> {
+    fn compare_as<T: PartialOrd + 'static>(l: &dyn Any, r: &dyn Any) -> Option<Option<Ordering>> {
+        Some(l.downcast_ref::<T>()?.partial_cmp(r.downcast_ref::<T>()?))
+    }
+    compare_as::<i32>(l, r)
+        .or_else(|| compare_as::<i64>(l, r))
+        .or_else(|| compare_as::<f32>(l, r))
+        .or_else(|| compare_as::<f64>(l, r))
+        .or_else(|| compare_as::<bool>(l, r))
+        .or_else(|| compare_as::<String>(l, r))
+        .unwrap_or_else(|| unreachable!())
+}
+
 fn sum(l: &mut Box<dyn Any>, r: &Box<dyn Any>) {
     if l.is::<i32>() {
         let sum = *l.downcast_mut::<i32>().unwrap() + r.downcast_ref::<i32>().unwrap();
@@ -100,35 +375,290 @@ fn sum(l: &mut Box<dyn Any>, r: &Box<dyn Any>) {
     unreachable!()
 }
 
+/// Accumulates the rows of all the groups of an aggregation,
+/// keeping the state of each group at the index of its group id.
+pub trait GroupsAccumulator {
+    /// Update the groups with the argument values of a batch,
+    /// where `group_ids[i]` is the group of row `i` and ids are below `num_groups`.
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_ids: &[usize],
+        num_groups: usize,
+    ) -> Result<()>;
+    /// Return the results of all the groups, by group id.
+    fn evaluate(&self) -> Result<ArrayRef>;
+}
+
+/// Groups accumulator for SUM, MIN and MAX over a primitive type.
+/// Values are read from the Arrow array of each batch, without boxing.
+struct PrimitiveGroupsAccumulator<T: ArrowPrimitiveType> {
+    fun: fn(T::Native, T::Native) -> T::Native,
+    values: Vec<Option<T::Native>>,
+}
+
+impl<T: ArrowPrimitiveType> PrimitiveGroupsAccumulator<T>
+where
+    T::Native: Add<Output = T::Native> + PartialOrd,
+{
+    fn try_new(fun: &AggregateFunction) -> Option<Self> {
+        let fun: fn(T::Native, T::Native) -> T::Native = match fun {
+            AggregateFunction::Sum => |l, r| l + r,
+            AggregateFunction::Min => |l, r| if r < l { r } else { l },
+            AggregateFunction::Max => |l, r| if r > l { r } else { l },
+            AggregateFunction::AnyValue => |l, _| l,
+            _ => return None,
+        };
+        Some(Self {
+            fun,
+            values: vec![],
+        })
+    }
+
+    fn accumulate(&mut self, group_id: usize, value: T::Native) {
+        let acc = &mut self.values[group_id];
+        *acc = Some(match *acc {
+            Some(acc) => (self.fun)(acc, value),
+            None => value,
+        });
+    }
+}
+
+impl<T: ArrowPrimitiveType> GroupsAccumulator for PrimitiveGroupsAccumulator<T>
+where
+    T::Native: Add<Output = T::Native> + PartialOrd,
+{
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_ids: &[usize],
+        num_groups: usize,
+    ) -> Result<()> {
+        self.values.resize(num_groups, None);
+        let array = &values[0];
+        match array
+            .as_arrow()
+            .and_then(|a| a.as_any().downcast_ref::<PrimitiveArray<T>>())
+        {
+            Some(primitive) => {
+                for (value, &group_id) in primitive.iter().zip(group_ids) {
+                    if let Some(value) = value {
+                        self.accumulate(group_id, value);
+                    }
+                }
+            }
+            None => {
+                for (i, &group_id) in group_ids.iter().enumerate() {
+                    if !array.is_null(i) {
+                        let value = *array.get_value(i)?.downcast_ref::<T::Native>().unwrap();
+                        self.accumulate(group_id, value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<ArrayRef> {
+        let array = self.values.iter().collect::<PrimitiveArray<T>>();
+        Ok(Arc::new(ArrowFieldArray::new(Box::new(array))))
+    }
+}
+
+/// Groups accumulator for BOOL_AND and BOOL_OR, which skips the rows of decided groups.
+struct BoolGroupsAccumulator {
+    and: bool,
+    values: Vec<Option<bool>>,
+}
+
+impl GroupsAccumulator for BoolGroupsAccumulator {
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_ids: &[usize],
+        num_groups: usize,
+    ) -> Result<()> {
+        self.values.resize(num_groups, None);
+        let array = &values[0];
+        let booleans = array
+            .as_arrow()
+            .and_then(|a| a.as_any().downcast_ref::<BooleanArray>());
+        for (i, &group_id) in group_ids.iter().enumerate() {
+            if self.values[group_id] == Some(!self.and) || array.is_null(i) {
+                continue;
+            }
+            self.values[group_id] = Some(match booleans {
+                Some(booleans) => booleans.value(i),
+                None => *array.get_value(i)?.downcast_ref::<bool>().unwrap(),
+            });
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<ArrayRef> {
+        let array = self.values.iter().collect::<BooleanArray>();
+        Ok(Arc::new(ArrowFieldArray::new(Box::new(array))))
+    }
+}
+
+/// Groups accumulator that keeps one row [`Accumulator`] per group.
+/// It is used for the functions without a specialized implementation.
+struct RowGroupsAccumulator {
+    fun: AggregateFunction,
+    data_type: DataType,
+    accumulators: Vec<Box<dyn Accumulator>>,
+}
+
+impl GroupsAccumulator for RowGroupsAccumulator {
+    fn update_batch(
+        &mut self,
+        values: &[ArrayRef],
+        group_ids: &[usize],
+        num_groups: usize,
+    ) -> Result<()> {
+        while self.accumulators.len() < num_groups {
+            self.accumulators.push(create_accumulator(&self.fun));
+        }
+        for (i, &group_id) in group_ids.iter().enumerate() {
+            let row = values
+                .iter()
+                .map(|a| (!a.is_null(i)).then(|| a.get_value(i)).transpose())
+                .collect::<Result<Vec<_>>>()?;
+            self.accumulators[group_id].update(row)?;
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<ArrayRef> {
+        let values = self
+            .accumulators
+            .iter()
+            .map(|acc| acc.evaluate())
+            .collect::<Result<Vec<_>>>()?;
+        evaluate_from_nullable_values(&values, &self.data_type)
+    }
+}
+
+fn create_accumulator(fun: &AggregateFunction) -> Box<dyn Accumulator> {
+    match fun {
+        AggregateFunction::Udaf(udaf) => udaf.create_accumulator(),
+        AggregateFunction::Count => Box::<CountAccumulator>::default(),
+        AggregateFunction::Avg => Box::<AvgAccumulator>::default(),
+        AggregateFunction::CountDistinct => Box::<CountDistinctAccumulator>::default(),
+        AggregateFunction::FirstValue => Box::new(FirstLastAccumulator::new(false)),
+        AggregateFunction::LastValue => Box::new(FirstLastAccumulator::new(true)),
+        AggregateFunction::Median => Box::new(PercentileAccumulator::new(Some(0.5))),
+        AggregateFunction::PercentileCont => Box::new(PercentileAccumulator::new(None)),
+        AggregateFunction::ApproxPercentile => Box::new(ApproxPercentileAccumulator::new()),
+        AggregateFunction::BoolAnd => Box::new(BoolAccumulator::new(true)),
+        AggregateFunction::BoolOr => Box::new(BoolAccumulator::new(false)),
+        fun => Box::new(BuiltinAccumulator::new(fun.clone())),
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 /// AggregateExpr is an expression that aggregates a group of rows.
 pub struct AggregateExpr {
-    pub expr: Expr,
+    pub args: Vec<Expr>,
     pub fun: AggregateFunction,
 }
 
 impl AggregateExpr {
-    pub fn new(expr: Expr, fun: AggregateFunction) -> Self {
-        Self { expr, fun }
+    pub fn new(args: Vec<Expr>, fun: AggregateFunction) -> Self {
+        Self { args, fun }
     }
 
-    pub fn input_expr(&self) -> &Expr {
-        &self.expr
+    pub fn input_exprs(&self) -> &[Expr] {
+        &self.args
     }
 
-    pub fn create_accumulator(&self) -> Accumulator {
-        Accumulator::new(self.fun.clone())
+    pub fn create_accumulator(&self) -> Box<dyn Accumulator> {
+        create_accumulator(&self.fun)
+    }
+
+    /// Return the aggregate that merges the partial results of this one, read from the
+    /// column, or None if its partial results cannot be merged as values.
+    pub fn merge_expr(&self, column: usize) -> Option<AggregateExpr> {
+        let fun = match self.fun {
+            // The counts of the parts add up.
+            AggregateFunction::Count => AggregateFunction::Sum,
+            AggregateFunction::Sum
+            | AggregateFunction::Min
+            | AggregateFunction::Max
+            | AggregateFunction::AnyValue
+            | AggregateFunction::BoolAnd
+            | AggregateFunction::BoolOr => self.fun.clone(),
+            _ => return None,
+        };
+        Some(AggregateExpr::new(
+            vec![Expr::Column(Column::new(column))],
+            fun,
+        ))
+    }
+
+    /// Create an accumulator for all the groups of an aggregation,
+    /// given the types of the arguments and of the result.
+    pub fn create_groups_accumulator(
+        &self,
+        arg_types: &[DataType],
+        data_type: &DataType,
+    ) -> Box<dyn GroupsAccumulator> {
+        let primitive: Option<Box<dyn GroupsAccumulator>> = match arg_types {
+            [arg_type] if arg_type == data_type => match data_type {
+                DataType::Int32 => PrimitiveGroupsAccumulator::<Int32Type>::try_new(&self.fun)
+                    .map(|acc| Box::new(acc) as _),
+                DataType::Int64 => PrimitiveGroupsAccumulator::<Int64Type>::try_new(&self.fun)
+                    .map(|acc| Box::new(acc) as _),
+                DataType::Float32 => PrimitiveGroupsAccumulator::<Float32Type>::try_new(&self.fun)
+                    .map(|acc| Box::new(acc) as _),
+                DataType::Float64 => PrimitiveGroupsAccumulator::<Float64Type>::try_new(&self.fun)
+                    .map(|acc| Box::new(acc) as _),
+                DataType::Boolean => match self.fun {
+                    AggregateFunction::BoolAnd | AggregateFunction::BoolOr => {
+                        Some(Box::new(BoolGroupsAccumulator {
+                            and: self.fun == AggregateFunction::BoolAnd,
+                            values: vec![],
+                        }))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        primitive.unwrap_or_else(|| {
+            Box::new(RowGroupsAccumulator {
+                fun: self.fun.clone(),
+                data_type: data_type.clone(),
+                accumulators: vec![],
+            })
+        })
     }
 }
 
 impl Display for AggregateExpr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}({})", self.fun, self.expr)
+        write!(
+            f,
+            "{}({})",
+            self.fun,
+            self.args
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Accumulator, AggregateExpr};
+    use std::any::Any;
+
+    use super::{
+        Accumulator, AggregateExpr, AvgAccumulator, BoolAccumulator, BuiltinAccumulator,
+        CountAccumulator, CountDistinctAccumulator, FirstLastAccumulator,
+    };
     use crate::{
         logical_plan::expr::AggregateFunction,
         physical_plan::expr::{Column, Expr},
@@ -136,7 +666,7 @@ mod tests {
 
     #[test]
     fn test_max_accumulator_i32() {
-        let mut acc = Accumulator::new(AggregateFunction::Max);
+        let mut acc = BuiltinAccumulator::new(AggregateFunction::Max);
         acc.accumulate(Some(Box::new(1i32)));
         assert!(acc.final_value().is_some());
         assert_eq!(
@@ -160,7 +690,7 @@ mod tests {
 
     #[test]
     fn test_max_accumulator_i64() {
-        let mut acc = Accumulator::new(AggregateFunction::Max);
+        let mut acc = BuiltinAccumulator::new(AggregateFunction::Max);
         acc.accumulate(Some(Box::new(1i64)));
         assert!(acc.final_value().is_some());
         assert_eq!(
@@ -184,7 +714,7 @@ mod tests {
 
     #[test]
     fn test_max_accumulator_f32() {
-        let mut acc = Accumulator::new(AggregateFunction::Max);
+        let mut acc = BuiltinAccumulator::new(AggregateFunction::Max);
         acc.accumulate(Some(Box::new(1f32)));
         assert!(acc.final_value().is_some());
         assert_eq!(
@@ -208,7 +738,7 @@ mod tests {
 
     #[test]
     fn test_min_accumulator() {
-        let mut acc = Accumulator::new(AggregateFunction::Min);
+        let mut acc = BuiltinAccumulator::new(AggregateFunction::Min);
         acc.accumulate(Some(Box::new(1i64)));
         assert!(acc.final_value().is_some());
         assert_eq!(
@@ -230,9 +760,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_min_max_accumulator_utf8_and_boolean() {
+        let result = |fun, values: Vec<Box<dyn Any>>| {
+            let mut acc = BuiltinAccumulator::new(fun);
+            values.into_iter().for_each(|v| acc.accumulate(Some(v)));
+            acc.evaluate().unwrap().unwrap()
+        };
+        let codes = || -> Vec<Box<dyn Any>> {
+            ["fr", "DE", "us", "de"]
+                .into_iter()
+                .map(|c| Box::new(c.to_string()) as _)
+                .collect()
+        };
+        let max = result(AggregateFunction::Max, codes());
+        assert_eq!(max.downcast_ref::<String>().unwrap(), "us");
+        let min = result(AggregateFunction::Min, codes());
+        assert_eq!(min.downcast_ref::<String>().unwrap(), "DE");
+
+        let flags = || -> Vec<Box<dyn Any>> { vec![Box::new(true), Box::new(false)] };
+        let max = result(AggregateFunction::Max, flags());
+        assert_eq!(max.downcast_ref::<bool>(), Some(&true));
+        let min = result(AggregateFunction::Min, flags());
+        assert_eq!(min.downcast_ref::<bool>(), Some(&false));
+    }
+
+    #[test]
+    fn test_avg_accumulator() {
+        let mut left = AvgAccumulator::default();
+        assert!(left.evaluate().unwrap().is_none());
+        left.update(vec![Some(Box::new(1_i32))]).unwrap();
+        left.update(vec![None]).unwrap();
+        left.update(vec![Some(Box::new(2_i32))]).unwrap();
+        let mut right = AvgAccumulator::default();
+        right.update(vec![Some(Box::new(6_i32))]).unwrap();
+        left.merge(right.state().unwrap()).unwrap();
+        let avg = left.evaluate().unwrap().unwrap();
+        assert_eq!(avg.downcast_ref::<f64>(), Some(&3.0));
+    }
+
     #[test]
     fn test_sum_accumulator() {
-        let mut acc = Accumulator::new(AggregateFunction::Sum);
+        let mut acc = BuiltinAccumulator::new(AggregateFunction::Sum);
         acc.accumulate(Some(Box::new(1i64)));
         assert!(acc.final_value().is_some());
         assert_eq!(
@@ -254,9 +823,145 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_count_accumulator() {
+        let mut left = CountAccumulator::default();
+        for v in [Some(3_i32), None, Some(3)] {
+            left.update(vec![v.map(|v| Box::new(v) as Box<dyn Any>)])
+                .unwrap();
+        }
+        let mut right = CountAccumulator::default();
+        right.update(vec![Some(Box::new(1_i32))]).unwrap();
+        left.merge(right.state().unwrap()).unwrap();
+        assert_eq!(
+            left.evaluate()
+                .unwrap()
+                .unwrap()
+                .downcast_ref::<i64>()
+                .unwrap(),
+            &3
+        );
+    }
+
+    #[test]
+    fn test_count_distinct_accumulator() {
+        let mut left = CountDistinctAccumulator::default();
+        for v in ["a", "b", "a"] {
+            left.update(vec![Some(Box::new(v.to_string()))]).unwrap();
+        }
+        left.update(vec![None]).unwrap();
+        let mut right = CountDistinctAccumulator::default();
+        right.update(vec![Some(Box::new("c".to_string()))]).unwrap();
+        right.update(vec![Some(Box::new("a".to_string()))]).unwrap();
+        left.merge(right.state().unwrap()).unwrap();
+        assert_eq!(
+            left.evaluate()
+                .unwrap()
+                .unwrap()
+                .downcast_ref::<i64>()
+                .unwrap(),
+            &3
+        );
+    }
+
+    #[test]
+    fn test_bool_accumulator() {
+        let result = |and, values: &[Option<bool>]| {
+            let mut acc = BoolAccumulator::new(and);
+            for v in values {
+                acc.update(vec![v.map(|v| Box::new(v) as _)]).unwrap();
+            }
+            acc.evaluate()
+                .unwrap()
+                .map(|v| *v.downcast_ref::<bool>().unwrap())
+        };
+        let values = [Some(true), None, Some(false), Some(true)];
+        assert_eq!(result(true, &values), Some(false));
+        assert_eq!(result(false, &values), Some(true));
+        assert_eq!(result(true, &[Some(true), None]), Some(true));
+        assert_eq!(result(false, &[Some(false)]), Some(false));
+        assert_eq!(result(true, &[None]), None);
+
+        let mut left = BoolAccumulator::new(true);
+        left.update(vec![Some(Box::new(true))]).unwrap();
+        let mut right = BoolAccumulator::new(true);
+        right.update(vec![Some(Box::new(false))]).unwrap();
+        left.merge(right.state().unwrap()).unwrap();
+        assert!(!left
+            .evaluate()
+            .unwrap()
+            .unwrap()
+            .downcast_ref::<bool>()
+            .unwrap());
+    }
+
+    #[test]
+    fn test_first_last_accumulator() {
+        let rows = [
+            (1_i64, Some(30_i64)),
+            (2, None),
+            (3, Some(10)),
+            (4, Some(20)),
+        ];
+        let evaluate = |acc: &dyn Accumulator| {
+            acc.evaluate()
+                .unwrap()
+                .map(|v| *v.downcast_ref::<i64>().unwrap())
+        };
+
+        let mut first = FirstLastAccumulator::new(false);
+        let mut last = FirstLastAccumulator::new(true);
+        let mut last_by_order = FirstLastAccumulator::new(true);
+        for (value, order) in rows {
+            first.update(vec![Some(Box::new(value))]).unwrap();
+            last.update(vec![Some(Box::new(value))]).unwrap();
+            last_by_order
+                .update(vec![Some(Box::new(value)), order.map(|o| Box::new(o) as _)])
+                .unwrap();
+        }
+        assert_eq!(evaluate(&first), Some(1));
+        assert_eq!(evaluate(&last), Some(4));
+        // Nulls are ordered last.
+        assert_eq!(evaluate(&last_by_order), Some(2));
+
+        let mut first_by_order = FirstLastAccumulator::new(false);
+        first_by_order
+            .update(vec![Some(Box::new(5_i64)), Some(Box::new(15_i64))])
+            .unwrap();
+        first_by_order
+            .merge(last_by_order.state().unwrap())
+            .unwrap();
+        assert_eq!(evaluate(&first_by_order), Some(5));
+        let mut other = FirstLastAccumulator::new(false);
+        other
+            .update(vec![Some(Box::new(3_i64)), Some(Box::new(10_i64))])
+            .unwrap();
+        first_by_order.merge(other.state().unwrap()).unwrap();
+        assert_eq!(evaluate(&first_by_order), Some(3));
+    }
+
+    #[test]
+    fn test_merge_accumulator_state() {
+        let mut left = BuiltinAccumulator::new(AggregateFunction::Sum);
+        left.update(vec![Some(Box::new(1i64))]).unwrap();
+        left.update(vec![None]).unwrap();
+        let mut right = BuiltinAccumulator::new(AggregateFunction::Sum);
+        right.update(vec![Some(Box::new(10i64))]).unwrap();
+        left.merge(right.state().unwrap()).unwrap();
+        assert_eq!(
+            left.evaluate()
+                .unwrap()
+                .unwrap()
+                .downcast_ref::<i64>()
+                .unwrap(),
+            &11
+        );
+    }
+
     #[test]
     fn test_aggregate_expr_display() {
-        let agg_expr = AggregateExpr::new(Expr::Column(Column::new(0)), AggregateFunction::Max);
+        let agg_expr =
+            AggregateExpr::new(vec![Expr::Column(Column::new(0))], AggregateFunction::Max);
         assert_eq!(agg_expr.to_string(), "MAX(#0)");
     }
 }
@@ -0,0 +1,107 @@
+use std::fmt::Display;
+
+use super::plan::{check_batch, Partitioning, PhysicalPlan, Plan};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+
+use anyhow::Result;
+
+/// Validate every batch produced by the input against its declared schema.
+/// It is transparent in the plan tree and panics with the operator on a mismatch,
+/// so planner and operator bugs show up where they happen.
+pub struct CheckedExec {
+    input: Box<Plan>,
+}
+
+impl CheckedExec {
+    pub fn new(input: Plan) -> Self {
+        Self {
+            input: Box::new(input),
+        }
+    }
+
+    pub fn input(&self) -> &Plan {
+        &self.input
+    }
+
+    /// Check a batch produced by the input against its schema.
+    pub fn check(&self, schema: &Schema, batch: &RecordBatch) {
+        if let Err(e) = check_batch(schema, batch) {
+            panic!("{} produced an invalid batch: {}", self.input, e);
+        }
+    }
+}
+
+impl PhysicalPlan for CheckedExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        let schema = self.input.schema();
+        Ok(Box::new(self.input.execute(partition)?.inspect(
+            move |batch| {
+                if let Ok(batch) = batch {
+                    self.check(&schema, batch)
+                }
+            },
+        )))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        self.input.children()
+    }
+}
+
+impl Display for CheckedExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.input.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{
+            arrow_field_array::ArrowFieldArray,
+            column_array::{ArrayRef, DataType},
+            schema::Field,
+        },
+        physical_plan::scan::ScanExec,
+    };
+
+    use arrow::array::Int64Array;
+    use std::sync::Arc;
+
+    fn scan(schema: Schema) -> Plan {
+        let id = Int64Array::from(vec![1, 2, 3]);
+        let fields = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let batch = RecordBatch::new(schema.clone(), fields);
+        let source = Source::Mem(MemoryDataSource::new(schema, vec![batch]));
+        Plan::Scan(ScanExec::new(source, vec!["id".to_string()]))
+    }
+
+    #[test]
+    fn test_checked_exec() {
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
+        let checked = CheckedExec::new(scan(schema));
+        assert_eq!(checked.to_string(), "ScanExec: projection=id");
+        assert_eq!(checked.execute_all().unwrap().count(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "produced an invalid batch: Expected column id to be Int32")]
+    fn test_checked_exec_mismatch() {
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
+        let checked = CheckedExec::new(scan(schema));
+        checked.execute_all().unwrap().for_each(drop);
+    }
+}
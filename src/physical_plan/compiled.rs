@@ -0,0 +1,401 @@
+use std::sync::{Arc, Mutex, OnceLock};
+
+#[cfg(feature = "jit")]
+use super::jit::JitFunction;
+use super::{
+    expr::{to_arrow_array, BinaryOperator, Expr, PhysicalExpr, ScalarValue},
+    fixed_width::{evaluate_arithmetic, evaluate_fixed_width, fixed_width_type, FixedWidthBuffers},
+    selection::filter_batch,
+};
+use crate::{
+    data_types::{
+        arrow_field_array::ArrowFieldArray,
+        column_array::{ArrayRef, DataType},
+        record_batch::RecordBatch,
+        schema::Schema,
+    },
+    logical_plan::expr::Operator,
+};
+
+use anyhow::Result;
+use arrow::{
+    array::{as_boolean_array, as_primitive_array, Array, ArrayRef as ArrowArrayRef, BooleanArray},
+    compute::{
+        and_kleene, eq_dyn, gt_dyn, gt_eq_dyn, lt_dyn, lt_eq_dyn, neq_dyn, not, or_kleene,
+        prep_null_mask_filter,
+    },
+    datatypes::{Float32Type, Float64Type},
+};
+
+/// An expression compiled to a function over the batches of its input.
+pub type Evaluator = Box<dyn Fn(&RecordBatch) -> Result<ArrayRef> + Send + Sync>;
+
+/// A predicate compiled to a function returning whether each row of a batch satisfies it,
+/// where null is false.
+pub type MaskEvaluator = Box<dyn Fn(&RecordBatch) -> Result<BooleanArray> + Send + Sync>;
+
+/// A compiled node, producing Arrow arrays so that its parent uses them as they are.
+type ArrowEvaluator = Box<dyn Fn(&RecordBatch) -> Result<ArrowArrayRef> + Send + Sync>;
+
+type Kernel = Box<dyn Fn(&dyn Array, &dyn Array) -> Result<Box<dyn Array>> + Send + Sync>;
+
+/// Compile the expression over batches of the schema. The column indexes, the types
+/// of the operands and the kernels of the operators are resolved once, and literals
+/// are built once, for the first batch, and sliced for the batches that are not larger.
+/// Casts and arithmetic over fixed-width numeric columns and literals are evaluated into
+/// buffers kept between batches, with [`evaluate_fixed_width`], and only their result is
+/// copied to an array. Other casts, nested fields, functions and placeholders are
+/// evaluated as they are.
+/// With the `jit` feature, the largest supported subexpressions are compiled to native
+/// code, which the batches with nulls fall back from.
+pub fn compile(expr: &Expr, schema: &Schema) -> Evaluator {
+    match expr {
+        Expr::Column(column) => {
+            let i = column.i;
+            Box::new(move |batch| Ok(batch.field(i).clone()))
+        }
+        Expr::Literal(value) => {
+            let value = value.clone();
+            Box::new(move |batch| value.evaluate(batch))
+        }
+        _ => {
+            let evaluate = compile_arrow(expr, schema, true);
+            Box::new(move |batch| Ok(Arc::new(ArrowFieldArray::new(Box::new(evaluate(batch)?)))))
+        }
+    }
+}
+
+/// Compile the predicate over batches of the schema.
+/// The right side of an AND is only evaluated for the rows where the left side is true,
+/// and the right side of an OR for the rows where it is not, if there are any.
+pub fn compile_mask(expr: &Expr, schema: &Schema) -> MaskEvaluator {
+    compile_mask_with(expr, schema, true)
+}
+
+/// Compile the predicate, trying to compile it to native code first if `jit` is set.
+fn compile_mask_with(expr: &Expr, schema: &Schema, jit: bool) -> MaskEvaluator {
+    let jit = jit && cfg!(feature = "jit");
+    #[cfg(feature = "jit")]
+    if let Some(function) = jit.then(|| JitFunction::compile(expr, schema)).flatten() {
+        // Native predicates have no nulls, and evaluate both sides of AND and OR.
+        let fallback = compile_mask_with(expr, schema, false);
+        return Box::new(move |batch| match function.evaluate(batch)? {
+            Some(mask) => Ok(BooleanArray::from(mask.data().clone())),
+            None => fallback(batch),
+        });
+    }
+    match expr {
+        Expr::BinaryExpr(b) if matches!(b.op, Operator::And | Operator::Or) => {
+            let left = compile_mask_with(&b.left, schema, jit);
+            let right = compile_mask_with(&b.right, schema, jit);
+            let op = b.op;
+            Box::new(move |batch| {
+                let mask = left(batch)?;
+                let undecided = match op {
+                    Operator::And => BooleanArray::from(mask.data().clone()),
+                    _ => not(&mask)?,
+                };
+                let count = undecided.iter().filter(|u| *u == Some(true)).count();
+                if count == 0 {
+                    return Ok(mask);
+                }
+                let right = if count == mask.len() {
+                    right(batch)?
+                } else {
+                    right(&filter_batch(batch, &undecided)?)?
+                };
+                let mut right = right.iter();
+                Ok(mask
+                    .iter()
+                    .zip(undecided.iter())
+                    .map(|(m, u)| {
+                        if u == Some(true) {
+                            right.next().unwrap()
+                        } else {
+                            m
+                        }
+                    })
+                    .collect())
+            })
+        }
+        _ => {
+            let evaluate = compile_arrow(expr, schema, jit);
+            Box::new(move |batch| {
+                let selection = evaluate(batch)?;
+                let selection = as_boolean_array(&selection);
+                Ok(match selection.null_count() {
+                    0 => BooleanArray::from(selection.data().clone()),
+                    _ => prep_null_mask_filter(selection),
+                })
+            })
+        }
+    }
+}
+
+/// Compile the expression, trying to compile it to native code first if `jit` is set.
+fn compile_arrow(expr: &Expr, schema: &Schema, jit: bool) -> ArrowEvaluator {
+    let jit = jit && cfg!(feature = "jit");
+    #[cfg(feature = "jit")]
+    if let Some(function) = jit.then(|| JitFunction::compile(expr, schema)).flatten() {
+        let fallback = compile_arrow(expr, schema, false);
+        return Box::new(move |batch| match function.evaluate(batch)? {
+            Some(array) => Ok(array),
+            None => fallback(batch),
+        });
+    }
+    match expr {
+        Expr::Column(column) => {
+            let i = column.i;
+            Box::new(move |batch| to_arrow_array(batch.field(i)))
+        }
+        Expr::Literal(value) => {
+            // Batches are mostly as large as the first, so its array is kept, and sliced
+            // without copying for the others.
+            let value = value.clone();
+            let cached: OnceLock<ArrowArrayRef> = OnceLock::new();
+            Box::new(move |batch| {
+                let rows = batch.row_count();
+                match cached.get() {
+                    Some(array) if array.len() == rows => Ok(array.clone()),
+                    Some(array) if array.len() > rows => Ok(array.slice(0, rows)),
+                    Some(_) => to_arrow_array(&value.evaluate(batch)?),
+                    None => {
+                        let array = to_arrow_array(&value.evaluate(batch)?)?;
+                        let _ = cached.set(array.clone());
+                        Ok(array)
+                    }
+                }
+            })
+        }
+        Expr::BinaryExpr(_) | Expr::Cast(_) if fixed_width_type(expr, schema).is_some() => {
+            // Each evaluation takes buffers from the pool, so that batches evaluated in
+            // parallel do not share them.
+            let expr = expr.clone();
+            let pool: Mutex<Vec<FixedWidthBuffers>> = Mutex::default();
+            Box::new(move |batch| {
+                let mut buffers = pool.lock().unwrap().pop().unwrap_or_default();
+                let result = evaluate_fixed_width(&expr, batch, &mut buffers);
+                let array = result.and_then(|()| buffers.to_array());
+                pool.lock().unwrap().push(buffers);
+                array
+            })
+        }
+        Expr::BinaryExpr(b) => {
+            let left = compile_arrow(&b.left, schema, jit);
+            let right = compile_arrow(&b.right, schema, jit);
+            let kernel = binary_kernel(b.operator(), data_type(&b.left, schema));
+            Box::new(move |batch| {
+                let (left, right) = (left(batch)?, right(batch)?);
+                Ok(Arc::from(kernel(left.as_ref(), right.as_ref())?))
+            })
+        }
+        _ => {
+            let expr = expr.clone();
+            Box::new(move |batch| to_arrow_array(&expr.evaluate(batch)?))
+        }
+    }
+}
+
+/// Return the kernel applying the operator to operands of the type, if it is known.
+fn binary_kernel(operator: BinaryOperator, data_type: Option<DataType>) -> Kernel {
+    match (operator.op, data_type) {
+        (Operator::Add | Operator::Subtract | Operator::Multiply, Some(DataType::Float32)) => {
+            Box::new(move |l, r| {
+                let (l, r) = (as_primitive_array(l), as_primitive_array(r));
+                Ok(Box::new(operator.float_arithmetic::<Float32Type>(l, r)?))
+            })
+        }
+        (Operator::Add | Operator::Subtract | Operator::Multiply, Some(DataType::Float64)) => {
+            Box::new(move |l, r| {
+                let (l, r) = (as_primitive_array(l), as_primitive_array(r));
+                Ok(Box::new(operator.float_arithmetic::<Float64Type>(l, r)?))
+            })
+        }
+        (Operator::Add | Operator::Subtract | Operator::Multiply, None) => {
+            Box::new(move |l, r| operator.apply(l, r))
+        }
+        (
+            Operator::Add
+            | Operator::Subtract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::Modulus,
+            _,
+        ) => Box::new(move |l, r| evaluate_arithmetic(&operator, l, r)),
+        (Operator::And, _) => Box::new(|l, r| {
+            Ok(Box::new(and_kleene(
+                as_boolean_array(l),
+                as_boolean_array(r),
+            )?))
+        }),
+        (Operator::Or, _) => Box::new(|l, r| {
+            Ok(Box::new(or_kleene(
+                as_boolean_array(l),
+                as_boolean_array(r),
+            )?))
+        }),
+        // Floats are compared like the sort order does.
+        (_, None | Some(DataType::Float32 | DataType::Float64)) => {
+            Box::new(move |l, r| Ok(Box::new(operator.compare(l, r)?)))
+        }
+        (Operator::Eq, _) => Box::new(|l, r| Ok(Box::new(eq_dyn(l, r)?))),
+        (Operator::Neq, _) => Box::new(|l, r| Ok(Box::new(neq_dyn(l, r)?))),
+        (Operator::Lt, _) => Box::new(|l, r| Ok(Box::new(lt_dyn(l, r)?))),
+        (Operator::LtEq, _) => Box::new(|l, r| Ok(Box::new(lt_eq_dyn(l, r)?))),
+        (Operator::Gt, _) => Box::new(|l, r| Ok(Box::new(gt_dyn(l, r)?))),
+        (Operator::GtEq, _) => Box::new(|l, r| Ok(Box::new(gt_eq_dyn(l, r)?))),
+    }
+}
+
+/// Return the type of the values of the expression over batches of the schema,
+/// if it is known without evaluating it.
+fn data_type(expr: &Expr, schema: &Schema) -> Option<DataType> {
+    match expr {
+        Expr::Column(column) => schema.fields.get(column.i).map(|f| f.data_type.clone()),
+        Expr::Literal(value) => Some(match value {
+            ScalarValue::Boolean(_) => DataType::Boolean,
+            ScalarValue::String(_) => DataType::Utf8,
+            ScalarValue::Int32(_) => DataType::Int32,
+            ScalarValue::Int64(_) => DataType::Int64,
+            ScalarValue::Float32(_) => DataType::Float32,
+            ScalarValue::Float64(_) => DataType::Float64,
+            ScalarValue::Null(data_type) => data_type.clone(),
+        }),
+        Expr::BinaryExpr(b) => match b.op {
+            Operator::Add
+            | Operator::Subtract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::Modulus => data_type(&b.left, schema),
+            _ => Some(DataType::Boolean),
+        },
+        Expr::Cast(cast) => Some(cast.data_type.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_types::{column_array::ColumnArray, schema::Field},
+        physical_plan::expr::{BinaryExpr, Cast, Column, DivisionByZeroMode},
+    };
+
+    use arrow::array::{Float64Array, Int64Array};
+
+    fn batch(x: Vec<Option<i64>>, y: Vec<f64>) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("x".to_string(), DataType::Int64),
+            Field::new("y".to_string(), DataType::Float64),
+        ]);
+        let fields = vec![
+            Arc::new(ArrowFieldArray::new(Box::new(Int64Array::from(x)))) as ArrayRef,
+            Arc::new(ArrowFieldArray::new(Box::new(Float64Array::from(y)))) as ArrayRef,
+        ];
+        RecordBatch::new(schema, fields)
+    }
+
+    fn values(array: &ArrayRef) -> Vec<String> {
+        (0..array.size())
+            .map(|i| match array.is_null(i) {
+                true => "null".to_string(),
+                false => {
+                    let value = array.get_value(i).unwrap();
+                    if let Some(v) = value.downcast_ref::<i64>() {
+                        v.to_string()
+                    } else if let Some(v) = value.downcast_ref::<f64>() {
+                        v.to_string()
+                    } else {
+                        value.downcast_ref::<bool>().unwrap().to_string()
+                    }
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compile() {
+        let binary = |op, l, r| Expr::BinaryExpr(BinaryExpr::new(op, l, r));
+        let x = || Expr::Column(Column::new(0));
+        let y = || Expr::Column(Column::new(1));
+        let int64 = |v| Expr::Literal(ScalarValue::Int64(v));
+        let float64 = |v| Expr::Literal(ScalarValue::Float64(v));
+        let exprs = vec![
+            x(),
+            int64(3),
+            binary(
+                Operator::Multiply,
+                binary(Operator::Add, x(), int64(1)),
+                int64(2),
+            ),
+            binary(Operator::Subtract, y(), float64(0.5)),
+            binary(Operator::Gt, y(), float64(1.0)),
+            binary(Operator::LtEq, x(), int64(2)),
+            binary(
+                Operator::And,
+                binary(Operator::Neq, x(), int64(2)),
+                binary(Operator::Eq, y(), y()),
+            ),
+            Expr::BinaryExpr(
+                BinaryExpr::new(Operator::Divide, int64(6), x())
+                    .with_division_by_zero(DivisionByZeroMode::Null),
+            ),
+            Expr::Cast(Cast::new(x(), DataType::Float64)),
+            Expr::Cast(Cast::new(y(), DataType::Int64).with_try_cast(true)),
+            binary(
+                Operator::Modulus,
+                Expr::Cast(
+                    Cast::new(binary(Operator::Add, y(), y()), DataType::Int64).with_try_cast(true),
+                ),
+                int64(3),
+            ),
+        ];
+        let schema = batch(vec![], vec![]).schema;
+        let batches = [
+            batch(vec![Some(1), None, Some(0)], vec![0.5, f64::NAN, 2.0]),
+            batch(vec![Some(2), Some(3)], vec![1.0, f64::INFINITY]),
+        ];
+        for expr in &exprs {
+            // The literals of the first batch are sliced for a smaller one, and built
+            // again for a larger one.
+            for first in 0..batches.len() {
+                let evaluate = compile(expr, &schema);
+                for batch in batches.iter().cycle().skip(first).take(4) {
+                    let compiled = evaluate(batch).unwrap();
+                    let interpreted = expr.evaluate(batch).unwrap();
+                    assert_eq!(compiled.get_type(), interpreted.get_type(), "{}", expr);
+                    assert_eq!(values(&compiled), values(&interpreted), "{}", expr);
+                }
+            }
+        }
+
+        let evaluate = compile(&binary(Operator::Divide, int64(6), x()), &schema);
+        assert!(evaluate(&batches[0]).is_err());
+    }
+
+    #[test]
+    fn test_compile_mask() {
+        let binary = |op, l, r| Expr::BinaryExpr(BinaryExpr::new(op, l, r));
+        let x = || Expr::Column(Column::new(0));
+        let int64 = |v| Expr::Literal(ScalarValue::Int64(v));
+        let schema = batch(vec![], vec![]).schema;
+        // 6 / x fails for x = 0, unless that row is decided by the left side.
+        let predicate = binary(
+            Operator::Or,
+            binary(Operator::Eq, x(), int64(0)),
+            binary(
+                Operator::Gt,
+                binary(Operator::Divide, int64(6), x()),
+                int64(2),
+            ),
+        );
+        let mask = compile_mask(&predicate, &schema);
+        let batch = batch(vec![Some(0), Some(1), None, Some(3)], vec![0.0; 4]);
+        let mask = mask(&batch).unwrap();
+        assert_eq!(
+            mask.iter().collect::<Vec<_>>(),
+            vec![Some(true), Some(true), Some(false), Some(false)]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::{any::Any, collections::HashMap, fmt::Display};
+
+use super::{
+    expr::{evaluate_from_nullable_values, Expr, PhysicalExpr},
+    memory::MemoryBudget,
+    plan::{Partitioning, PhysicalPlan, Plan},
+    sort::{compare_rows, SortExpr},
+    sort_key::{key_value, key_values, KeyValue},
+};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+
+use anyhow::Result;
+
+/// Execute a DISTINCT ON with a hash table keeping the first row of each key.
+/// All the input is read first; the rows are output in the order their key first
+/// appeared in the input.
+pub struct DistinctOnExec {
+    pub(crate) input: Box<Plan>,
+    pub(crate) keys: Vec<Expr>,
+    pub(crate) order_by: Vec<SortExpr>,
+    memory: MemoryBudget,
+}
+
+impl DistinctOnExec {
+    pub fn new(input: Plan, keys: Vec<Expr>, order_by: Vec<SortExpr>) -> Self {
+        Self {
+            input: Box::new(input),
+            keys,
+            order_by,
+            memory: MemoryBudget::default(),
+        }
+    }
+
+    /// Reserve the data buffered by the operator from the memory budget of the query.
+    pub fn with_memory_budget(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Return the (batch, row) of the row kept for each key.
+    fn kept_rows(&self, batches: &[RecordBatch]) -> Result<Vec<(usize, usize)>> {
+        let mut index: HashMap<Vec<Option<KeyValue>>, usize> = HashMap::new();
+        let mut kept: Vec<(Vec<Option<KeyValue>>, _)> = vec![];
+        for (b, batch) in batches.iter().enumerate() {
+            let keys = self
+                .keys
+                .iter()
+                .map(|e| e.evaluate(batch))
+                .collect::<Result<Vec<_>>>()?;
+            let order_by = self
+                .order_by
+                .iter()
+                .map(|e| key_values(&e.expr.evaluate(batch)?))
+                .collect::<Result<Vec<_>>>()?;
+            for row in 0..batch.row_count() {
+                let key = keys
+                    .iter()
+                    .map(|k| key_value(k, row))
+                    .collect::<Result<Vec<_>>>()?;
+                let sort_key = order_by.iter().map(|k| k[row].clone()).collect::<Vec<_>>();
+                match index.get(&key) {
+                    Some(&i) => {
+                        if compare_rows(&self.order_by, &sort_key, &kept[i].0).is_lt() {
+                            kept[i] = (sort_key, (b, row));
+                        }
+                    }
+                    None => {
+                        index.insert(key, kept.len());
+                        kept.push((sort_key, (b, row)));
+                    }
+                }
+            }
+        }
+        Ok(kept.into_iter().map(|(_, location)| location).collect())
+    }
+}
+
+impl PhysicalPlan for DistinctOnExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let schema = self.schema();
+        let mut reservation = self.memory.reservation("DistinctOnExec");
+        let batches = reservation.collect(self.input.execute_all()?)?;
+        let rows = self.kept_rows(&batches)?;
+        if rows.is_empty() {
+            return Ok(Box::new(std::iter::empty()));
+        }
+
+        let fields = schema
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(c, field)| {
+                let values = rows
+                    .iter()
+                    .map(|(b, row)| {
+                        let array = batches[*b].field(c);
+                        (!array.is_null(*row))
+                            .then(|| array.get_value(*row))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<Option<Box<dyn Any>>>>>()?;
+                evaluate_from_nullable_values(&values, &field.data_type)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(std::iter::once(Ok(RecordBatch::new(
+            schema, fields,
+        )))))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for DistinctOnExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "DistinctOnExec: keys={}, orderBy={}",
+            self.keys
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.order_by
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
@@ -1,29 +1,64 @@
-use std::{any::Any, fmt::Display, rc::Rc};
+use std::{
+    any::Any,
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    ops::{Add, Mul, Sub},
+    sync::Arc,
+};
 
+use super::{
+    fixed_width::evaluate_arithmetic,
+    functions::ScalarFunctionExpr,
+    nested::{GetField, GetIndex},
+};
 use crate::{
     data_types::{
         arrow_field_array::ArrowFieldArray,
         column_array::{ArrayRef, DataType},
+        extension::ExtensionRef,
         literal_value_array::LiteralValueArray,
         record_batch::RecordBatch,
     },
     logical_plan::expr::Operator,
 };
 
-use anyhow::{Error, Result};
-use arrow::array::{BooleanArray, Int32Array, Int64Array};
+use anyhow::{anyhow, bail, Result};
+use arrow::{
+    array::{
+        as_boolean_array, as_primitive_array, make_array, Array, ArrayData,
+        ArrayRef as ArrowArrayRef, BinaryArray, BooleanArray, Float32Array, Float64Array,
+        Int32Array, Int64Array, PrimitiveArray, StringArray,
+    },
+    buffer::Buffer,
+    compute::{
+        add, and_kleene, eq_dyn, gt_dyn, gt_eq_dyn, lt_dyn, lt_eq_dyn, multiply, neq_dyn,
+        no_simd_compare_op, or_kleene, subtract,
+    },
+    datatypes::{ArrowNumericType, DataType as ArrowDataType, Float32Type, Float64Type},
+};
 use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
 
 /// Physical representation of an expression.
 pub trait PhysicalExpr: Display {
     fn evaluate(&self, input: &RecordBatch) -> Result<ArrayRef>;
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Expr {
     Column(Column),
     Literal(ScalarValue),
+    /// Placeholders evaluate to the parameters in scope on the thread executing them,
+    /// so plans with placeholders cannot be serialized.
+    #[serde(skip)]
+    Placeholder(Placeholder),
     BinaryExpr(BinaryExpr),
     Cast(Cast),
+    GetField(GetField),
+    GetIndex(GetIndex),
+    ScalarFunction(ScalarFunctionExpr),
 }
 
 impl PhysicalExpr for Expr {
@@ -31,8 +66,12 @@ impl PhysicalExpr for Expr {
         match self {
             Expr::Column(column) => column.evaluate(input),
             Expr::Literal(literal) => literal.evaluate(input),
+            Expr::Placeholder(placeholder) => placeholder.evaluate(input),
             Expr::BinaryExpr(binary_expr) => binary_expr.evaluate(input),
             Expr::Cast(cast) => cast.evaluate(input),
+            Expr::GetField(get_field) => get_field.evaluate(input),
+            Expr::GetIndex(get_index) => get_index.evaluate(input),
+            Expr::ScalarFunction(function) => function.evaluate(input),
         }
     }
 }
@@ -42,12 +81,71 @@ impl Display for Expr {
         match self {
             Expr::Column(column) => column.fmt(f),
             Expr::Literal(literal) => literal.fmt(f),
+            Expr::Placeholder(placeholder) => placeholder.fmt(f),
             Expr::BinaryExpr(binary_expr) => binary_expr.fmt(f),
             Expr::Cast(cast) => cast.fmt(f),
+            Expr::GetField(get_field) => get_field.fmt(f),
+            Expr::GetIndex(get_index) => get_index.fmt(f),
+            Expr::ScalarFunction(function) => function.fmt(f),
         }
     }
 }
 
+impl Expr {
+    /// Return the indexes of the input columns the expression reads, in order.
+    pub fn columns(&self) -> BTreeSet<usize> {
+        let mut columns = BTreeSet::new();
+        let mut stack = vec![self];
+        while let Some(expr) = stack.pop() {
+            match expr {
+                Expr::Column(column) => {
+                    columns.insert(column.i);
+                }
+                Expr::Literal(_) | Expr::Placeholder(_) => {}
+                Expr::BinaryExpr(b) => stack.extend([b.left.as_ref(), b.right.as_ref()]),
+                Expr::Cast(cast) => stack.push(&cast.expr),
+                Expr::GetField(get_field) => stack.push(&get_field.expr),
+                Expr::GetIndex(g) => stack.extend([g.expr.as_ref(), g.index.as_ref()]),
+                Expr::ScalarFunction(function) => stack.extend(function.args()),
+            }
+        }
+        columns
+    }
+
+    /// Rewrite the expression to read every input column at the index it is mapped to.
+    pub fn remap_columns(self, map: &HashMap<usize, usize>) -> Result<Expr> {
+        let remap = |expr: Box<Expr>| expr.remap_columns(map).map(Box::new);
+        Ok(match self {
+            Expr::Column(column) => match map.get(&column.i) {
+                Some(&i) => Expr::Column(Column::new(i)),
+                None => bail!("Column {} is not mapped", column.i),
+            },
+            Expr::Literal(_) | Expr::Placeholder(_) => self,
+            Expr::BinaryExpr(b) => Expr::BinaryExpr(BinaryExpr {
+                left: remap(b.left)?,
+                right: remap(b.right)?,
+                ..b
+            }),
+            Expr::Cast(cast) => Expr::Cast(Cast {
+                expr: remap(cast.expr)?,
+                ..cast
+            }),
+            Expr::GetField(get_field) => Expr::GetField(GetField {
+                expr: remap(get_field.expr)?,
+                ..get_field
+            }),
+            Expr::GetIndex(get_index) => Expr::GetIndex(GetIndex {
+                expr: remap(get_index.expr)?,
+                index: remap(get_index.index)?,
+            }),
+            Expr::ScalarFunction(function) => {
+                Expr::ScalarFunction(function.map_args(|arg| arg.remap_columns(map))?)
+            }
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Column {
     pub i: usize,
 }
@@ -71,42 +169,54 @@ impl Display for Column {
 }
 
 /// Represents a dynamically typed single value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ScalarValue {
+    Boolean(bool),
     String(String),
     Int32(i32),
     Int64(i64),
     Float32(f32),
     Float64(f64),
+    Null(DataType),
 }
 
 impl PhysicalExpr for ScalarValue {
     fn evaluate(&self, input: &RecordBatch) -> Result<ArrayRef> {
         match self {
-            ScalarValue::String(s) => Ok(Rc::new(LiteralValueArray::new(
+            ScalarValue::Boolean(b) => Ok(Arc::new(LiteralValueArray::new(
+                DataType::Boolean,
+                *b,
+                input.row_count(),
+            ))),
+            ScalarValue::String(s) => Ok(Arc::new(LiteralValueArray::new(
                 DataType::Utf8,
                 s.clone(),
                 input.row_count(),
             ))),
-            ScalarValue::Int32(i) => Ok(Rc::new(LiteralValueArray::new(
+            ScalarValue::Int32(i) => Ok(Arc::new(LiteralValueArray::new(
                 DataType::Int32,
                 *i,
                 input.row_count(),
             ))),
-            ScalarValue::Int64(i) => Ok(Rc::new(LiteralValueArray::new(
+            ScalarValue::Int64(i) => Ok(Arc::new(LiteralValueArray::new(
                 DataType::Int64,
                 *i,
                 input.row_count(),
             ))),
-            ScalarValue::Float32(f) => Ok(Rc::new(LiteralValueArray::new(
+            ScalarValue::Float32(f) => Ok(Arc::new(LiteralValueArray::new(
                 DataType::Float32,
                 *f,
                 input.row_count(),
             ))),
-            ScalarValue::Float64(f) => Ok(Rc::new(LiteralValueArray::new(
+            ScalarValue::Float64(f) => Ok(Arc::new(LiteralValueArray::new(
                 DataType::Float64,
                 *f,
                 input.row_count(),
             ))),
+            ScalarValue::Null(data_type) => {
+                let values = (0..input.row_count()).map(|_| None).collect::<Vec<_>>();
+                evaluate_from_nullable_values(&values, data_type)
+            }
         }
     }
 }
@@ -114,21 +224,125 @@ impl PhysicalExpr for ScalarValue {
 impl Display for ScalarValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            ScalarValue::Boolean(b) => write!(f, "{}", b),
             ScalarValue::String(s) => write!(f, "'{}'", s),
             ScalarValue::Int32(i) => write!(f, "{}", i),
             ScalarValue::Int64(i) => write!(f, "{}", i),
             ScalarValue::Float32(fv) => write!(f, "{}", fv),
             ScalarValue::Float64(fv) => write!(f, "{}", fv),
+            ScalarValue::Null(_) => write!(f, "NULL"),
         }
     }
 }
 
+thread_local! {
+    // The parameters of the execution running on the thread.
+    static SCOPED_PARAMETERS: RefCell<Parameters> = RefCell::default();
+}
+
+/// The values bound to the placeholders for one execution of a prepared plan.
+/// The placeholders of a plan evaluate to the parameters in scope on the thread
+/// executing it, so one plan executes with different values on different threads.
+#[derive(Debug, Clone, Default)]
+pub struct Parameters {
+    values: Arc<HashMap<String, ScalarValue>>,
+}
+
+impl Parameters {
+    pub fn new(values: impl IntoIterator<Item = (String, ScalarValue)>) -> Self {
+        Self {
+            values: Arc::new(values.into_iter().collect()),
+        }
+    }
+
+    /// Return the parameters in scope on the thread.
+    pub fn current() -> Self {
+        SCOPED_PARAMETERS.with(|scoped| scoped.borrow().clone())
+    }
+
+    /// Call `f` with the parameters in scope on the thread, restoring the previous
+    /// ones when it returns. Operators executing on other threads take the parameters
+    /// of the thread that started them into scope.
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        // Restored on drop, so a panic in `f` does not leave the parameters in scope.
+        struct Restore(Parameters);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let previous = std::mem::take(&mut self.0);
+                SCOPED_PARAMETERS.with(|scoped| scoped.replace(previous));
+            }
+        }
+        let previous = SCOPED_PARAMETERS.with(|scoped| scoped.replace(self.clone()));
+        let _restore = Restore(previous);
+        f()
+    }
+}
+
+/// A parameter of a prepared plan, which evaluates to the value bound to it.
+#[derive(Clone)]
+pub struct Placeholder {
+    id: String,
+}
+
+impl Placeholder {
+    pub fn new(id: String) -> Self {
+        Self { id }
+    }
+
+    /// Call `f` with the value bound to the placeholder in the parameters in scope.
+    pub fn with_value<T>(&self, f: impl FnOnce(&ScalarValue) -> Result<T>) -> Result<T> {
+        let parameters = Parameters::current();
+        match parameters.values.get(&self.id) {
+            Some(value) => f(value),
+            None => bail!("No value bound to placeholder {}", self.id),
+        }
+    }
+}
+
+impl PhysicalExpr for Placeholder {
+    fn evaluate(&self, input: &RecordBatch) -> Result<ArrayRef> {
+        self.with_value(|value| value.evaluate(input))
+    }
+}
+
+impl Display for Placeholder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+/// What integer addition, subtraction and multiplication do when the result
+/// does not fit in the type of the operands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverflowMode {
+    /// Fail the query.
+    #[default]
+    Checked,
+    /// Wrap around in two's complement.
+    Wrapping,
+    /// Clamp to the minimum or maximum value of the type.
+    Saturating,
+}
+
+/// What division and modulus do when the divisor is zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DivisionByZeroMode {
+    /// Fail the query.
+    #[default]
+    Error,
+    /// Return null.
+    Null,
+}
+
 /// For binary expressions we need to evaluate the left and right input expressions
 /// and then evaluate the specific binary operator against those input values.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BinaryExpr {
     pub op: Operator,
     pub left: Box<Expr>,
     pub right: Box<Expr>,
+    pub overflow_mode: OverflowMode,
+    pub division_by_zero: DivisionByZeroMode,
 }
 
 impl PhysicalExpr for BinaryExpr {
@@ -136,148 +350,40 @@ impl PhysicalExpr for BinaryExpr {
         let left = self.left.evaluate(input)?;
         let right = self.right.evaluate(input)?;
         assert!(left.get_type() == right.get_type());
-        let arrow_type = left.get_type();
-        let mut vals = vec![];
-        match self.op {
-            Operator::Add => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::math_binary_op!(&l,&r, &arrow_type,+);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &arrow_type)
-            }
-            Operator::Subtract => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::math_binary_op!(&l,&r, &arrow_type,-);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &arrow_type)
-            }
-            Operator::Multiply => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::math_binary_op!(&l,&r, &arrow_type,*);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &arrow_type)
-            }
-            Operator::Divide => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::math_binary_op!(&l,&r, &arrow_type,/);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &arrow_type)
-            }
-            Operator::Modulus => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::math_binary_op!(&l,&r, &arrow_type,%);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &arrow_type)
-            }
-            Operator::And => {
-                for i in 0..left.size() {
-                    let value = and(&left.get_value(i)?, &right.get_value(i)?, &arrow_type);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &DataType::Boolean)
-            }
-            Operator::Or => {
-                for i in 0..left.size() {
-                    let value = or(&left.get_value(i)?, &right.get_value(i)?, &arrow_type);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &DataType::Boolean)
-            }
-            Operator::Eq => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::bool_binary_op!(&l, &r, &arrow_type, eq);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &DataType::Boolean)
-            }
-            Operator::Neq => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::bool_binary_op!(&l, &r, &arrow_type, ne);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &DataType::Boolean)
-            }
-            Operator::Lt => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::bool_binary_op!(&l, &r, &arrow_type, lt);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &DataType::Boolean)
-            }
-            Operator::LtEq => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::bool_binary_op!(&l, &r, &arrow_type, le);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &DataType::Boolean)
-            }
-            Operator::Gt => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::bool_binary_op!(&l, &r, &arrow_type, gt);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &DataType::Boolean)
-            }
-            Operator::GtEq => {
-                for i in 0..left.size() {
-                    let l = left.get_value(i)?;
-                    let r = right.get_value(i)?;
-                    let value = crate::bool_binary_op!(&l, &r, &arrow_type, ge);
-                    vals.push(value);
-                }
-                evaluate_from_values(&vals, &DataType::Boolean)
-            }
-        }
+        let (left, right) = (to_arrow_array(&left)?, to_arrow_array(&right)?);
+        let result = self.operator().apply(left.as_ref(), right.as_ref())?;
+        Ok(Arc::new(ArrowFieldArray::new(result)))
     }
 }
 
 impl Display for BinaryExpr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.op {
-            Operator::Add => write!(f, "{} + {}", self.left, self.right),
-            Operator::Subtract => {
-                write!(f, "{} - {}", self.left, self.right)
-            }
-            Operator::Multiply => {
-                write!(f, "{} * {}", self.left, self.right)
-            }
-            Operator::Divide => write!(f, "{} / {}", self.left, self.right),
-            Operator::Modulus => {
-                write!(f, "{} % {}", self.left, self.right)
-            }
-            Operator::And => write!(f, "{} AND {}", self.left, self.right),
-            Operator::Or => write!(f, "{} OR {}", self.left, self.right),
-            Operator::Eq => write!(f, "{} == {}", self.left, self.right),
-            Operator::Neq => write!(f, "{} != {}", self.left, self.right),
-            Operator::Lt => write!(f, "{} < {}", self.left, self.right),
-            Operator::LtEq => write!(f, "{} <= {}", self.left, self.right),
-            Operator::Gt => write!(f, "{} > {}", self.left, self.right),
-            Operator::GtEq => write!(f, "{} >= {}", self.left, self.right),
+        let op = match self.op {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Modulus => "%",
+            Operator::And => "AND",
+            Operator::Or => "OR",
+            Operator::Eq => "==",
+            Operator::Neq => "!=",
+            Operator::Lt => "<",
+            Operator::LtEq => "<=",
+            Operator::Gt => ">",
+            Operator::GtEq => ">=",
+        };
+        // Parenthesize operands that bind looser, and right operands that bind the same
+        // since operators are left-associative.
+        let precedence = self.op.precedence();
+        match self.left.as_ref() {
+            Expr::BinaryExpr(l) if l.op.precedence() < precedence => write!(f, "({})", l)?,
+            l => write!(f, "{}", l)?,
+        }
+        write!(f, " {} ", op)?;
+        match self.right.as_ref() {
+            Expr::BinaryExpr(r) if r.op.precedence() <= precedence => write!(f, "({})", r),
+            r => write!(f, "{}", r),
         }
     }
 }
@@ -288,111 +394,254 @@ impl BinaryExpr {
             op,
             left: Box::new(left),
             right: Box::new(right),
+            overflow_mode: OverflowMode::default(),
+            division_by_zero: DivisionByZeroMode::default(),
         }
     }
-}
 
-// Build the arrow array from the values.
-pub fn evaluate_from_values(array: &[Box<dyn Any>], data_type: &DataType) -> Result<ArrayRef> {
-    match data_type {
-        DataType::Int32 => {
-            let arrow_array = Int32Array::from(
-                array
-                    .iter()
-                    .map(|v| *v.downcast_ref::<i32>().unwrap())
-                    .collect::<Vec<i32>>(),
-            );
-            Ok(Rc::new(ArrowFieldArray::new(Box::new(arrow_array))))
-        }
-        DataType::Int64 => {
-            let arrow_array = Int64Array::from(
-                array
-                    .iter()
-                    .map(|v| *v.downcast_ref::<i64>().unwrap())
-                    .collect::<Vec<i64>>(),
-            );
-            Ok(Rc::new(ArrowFieldArray::new(Box::new(arrow_array))))
-        }
-        DataType::Float32 => {
-            let arrow_array = arrow::array::Float32Array::from(
-                array
-                    .iter()
-                    .map(|v| *v.downcast_ref::<f32>().unwrap())
-                    .collect::<Vec<f32>>(),
-            );
-            Ok(Rc::new(ArrowFieldArray::new(Box::new(arrow_array))))
-        }
-        DataType::Float64 => {
-            let arrow_array = arrow::array::Float64Array::from(
-                array
-                    .iter()
-                    .map(|v| *v.downcast_ref::<f64>().unwrap())
-                    .collect::<Vec<f64>>(),
-            );
-            Ok(Rc::new(ArrowFieldArray::new(Box::new(arrow_array))))
-        }
-        DataType::Boolean => {
-            let arrow_array = BooleanArray::from(
-                array
-                    .iter()
-                    .map(|v| *v.downcast_ref::<bool>().unwrap())
-                    .collect::<Vec<bool>>(),
-            );
-            Ok(Rc::new(ArrowFieldArray::new(Box::new(arrow_array))))
+    pub fn with_division_by_zero(mut self, division_by_zero: DivisionByZeroMode) -> Self {
+        self.division_by_zero = division_by_zero;
+        self
+    }
+
+    pub fn with_overflow_mode(mut self, overflow_mode: OverflowMode) -> Self {
+        self.overflow_mode = overflow_mode;
+        self
+    }
+
+    /// Return the operator with the modes it is applied with.
+    pub(crate) fn operator(&self) -> BinaryOperator {
+        BinaryOperator {
+            op: self.op,
+            overflow_mode: self.overflow_mode,
+            division_by_zero: self.division_by_zero,
         }
-        _ => unreachable!(),
     }
 }
 
-#[macro_export]
-macro_rules! math_binary_op {
-    ($LEFT: expr, $RIGHT: expr, $DATA_TYPE: expr, $OP: tt) => {
-        match $DATA_TYPE {
-            DataType::Int32 => {
-                let l = $LEFT.downcast_ref::<i32>().unwrap();
-                let r = $RIGHT.downcast_ref::<i32>().unwrap();
-                Box::new(*l $OP *r) as Box<dyn Any>
-            }
-            DataType::Int64 => {
-                let l = $LEFT.downcast_ref::<i64>().unwrap();
-                let r = $RIGHT.downcast_ref::<i64>().unwrap();
-                Box::new(*l $OP *r) as Box<dyn Any>
-            }
-            DataType::Float32 => {
-                let l = $LEFT.downcast_ref::<f32>().unwrap();
-                let r = $RIGHT.downcast_ref::<f32>().unwrap();
-                Box::new(*l $OP *r) as Box<dyn Any>
-            }
-            DataType::Float64 => {
-                let l = $LEFT.downcast_ref::<f64>().unwrap();
-                let r = $RIGHT.downcast_ref::<f64>().unwrap();
-                Box::new(*l $OP *r) as Box<dyn Any>
+/// The operator of a [`BinaryExpr`] with its overflow and division by zero modes,
+/// applied to the values of the operands.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BinaryOperator {
+    pub op: Operator,
+    pub overflow_mode: OverflowMode,
+    pub division_by_zero: DivisionByZeroMode,
+}
+
+impl BinaryOperator {
+    /// Apply the operator to two Arrow arrays of the same type.
+    pub fn apply(&self, left: &dyn Array, right: &dyn Array) -> Result<Box<dyn Array>> {
+        let data_type = left.data_type();
+        Ok(match self.op {
+            Operator::Add | Operator::Subtract | Operator::Multiply
+                if matches!(data_type, ArrowDataType::Float32 | ArrowDataType::Float64) =>
+            {
+                match data_type {
+                    ArrowDataType::Float32 => Box::new(self.float_arithmetic::<Float32Type>(
+                        as_primitive_array(left),
+                        as_primitive_array(right),
+                    )?),
+                    _ => Box::new(self.float_arithmetic::<Float64Type>(
+                        as_primitive_array(left),
+                        as_primitive_array(right),
+                    )?),
+                }
             }
-            _ => unreachable!(),
+            Operator::Add
+            | Operator::Subtract
+            | Operator::Multiply
+            | Operator::Divide
+            | Operator::Modulus => evaluate_arithmetic(self, left, right)?,
+            Operator::And => Box::new(and_kleene(as_boolean_array(left), as_boolean_array(right))?),
+            Operator::Or => Box::new(or_kleene(as_boolean_array(left), as_boolean_array(right))?),
+            Operator::Eq
+            | Operator::Neq
+            | Operator::Lt
+            | Operator::LtEq
+            | Operator::Gt
+            | Operator::GtEq => Box::new(self.compare(left, right)?),
+        })
+    }
+
+    /// Add, subtract or multiply floats.
+    pub fn float_arithmetic<T>(
+        &self,
+        left: &PrimitiveArray<T>,
+        right: &PrimitiveArray<T>,
+    ) -> Result<PrimitiveArray<T>>
+    where
+        T: ArrowNumericType,
+        T::Native: Add<Output = T::Native> + Sub<Output = T::Native> + Mul<Output = T::Native>,
+    {
+        Ok(match self.op {
+            Operator::Add => add(left, right)?,
+            Operator::Subtract => subtract(left, right)?,
+            _ => multiply(left, right)?,
+        })
+    }
+
+    /// Apply a comparison operator. Floats are compared like the sort order does,
+    /// where NaN is equal to itself and greater than any other value.
+    pub fn compare(&self, left: &dyn Array, right: &dyn Array) -> Result<BooleanArray> {
+        let matches: fn(Ordering) -> bool = match self.op {
+            Operator::Eq => Ordering::is_eq,
+            Operator::Neq => Ordering::is_ne,
+            Operator::Lt => Ordering::is_lt,
+            Operator::LtEq => Ordering::is_le,
+            Operator::Gt => Ordering::is_gt,
+            _ => Ordering::is_ge,
+        };
+        Ok(match left.data_type() {
+            ArrowDataType::Float32 => no_simd_compare_op::<Float32Type, _>(
+                as_primitive_array(left),
+                as_primitive_array(right),
+                |l, r| matches(OrderedFloat(l).cmp(&OrderedFloat(r))),
+            )?,
+            ArrowDataType::Float64 => no_simd_compare_op::<Float64Type, _>(
+                as_primitive_array(left),
+                as_primitive_array(right),
+                |l, r| matches(OrderedFloat(l).cmp(&OrderedFloat(r))),
+            )?,
+            _ => match self.op {
+                Operator::Eq => eq_dyn(left, right)?,
+                Operator::Neq => neq_dyn(left, right)?,
+                Operator::Lt => lt_dyn(left, right)?,
+                Operator::LtEq => lt_eq_dyn(left, right)?,
+                Operator::Gt => gt_dyn(left, right)?,
+                _ => gt_eq_dyn(left, right)?,
+            },
+        })
+    }
+}
+
+/// Return the values of the column as an Arrow array.
+/// Literals are the only columns without one, so their value is repeated.
+pub(crate) fn to_arrow_array(array: &ArrayRef) -> Result<ArrowArrayRef> {
+    if let Some(arrow) = array.as_arrow() {
+        return Ok(make_array(arrow.data().clone()));
+    }
+    let data_type = array.get_type();
+    let values = match array.size() {
+        0 => build_array(std::iter::empty(), &data_type)?,
+        size => {
+            let value = array.get_value(0)?;
+            build_array(std::iter::repeat_n(Some(value.as_ref()), size), &data_type)?
         }
     };
+    Ok(make_array(values.as_arrow().unwrap().data().clone()))
 }
 
-fn and(l: &Box<dyn Any>, r: &Box<dyn Any>, data_type: &DataType) -> Box<dyn Any> {
-    match data_type {
-        DataType::Boolean => {
-            let l = l.downcast_ref::<bool>().unwrap();
-            let r = r.downcast_ref::<bool>().unwrap();
-            Box::new(*l && *r)
-        }
-        _ => unreachable!(),
-    }
+// Build the arrow array from the values.
+pub fn evaluate_from_values(array: &[Box<dyn Any>], data_type: &DataType) -> Result<ArrayRef> {
+    build_array(array.iter().map(|v| Some(v.as_ref())), data_type)
 }
 
-fn or(l: &Box<dyn Any>, r: &Box<dyn Any>, data_type: &DataType) -> Box<dyn Any> {
-    match data_type {
-        DataType::Boolean => {
-            let l = l.downcast_ref::<bool>().unwrap();
-            let r = r.downcast_ref::<bool>().unwrap();
-            Box::new(*l || *r)
+// Build the arrow array from the values, where None is a null.
+pub fn evaluate_from_nullable_values(
+    array: &[Option<Box<dyn Any>>],
+    data_type: &DataType,
+) -> Result<ArrayRef> {
+    build_array(array.iter().map(|v| v.as_deref()), data_type)
+}
+
+fn build_array<'a>(
+    values: impl Iterator<Item = Option<&'a dyn Any>>,
+    data_type: &DataType,
+) -> Result<ArrayRef> {
+    let arrow_array: Box<dyn Array> = match data_type {
+        DataType::Int32 => Box::new(
+            values
+                .map(|v| v.map(|v| *v.downcast_ref::<i32>().unwrap()))
+                .collect::<Int32Array>(),
+        ),
+        DataType::Int64 => Box::new(
+            values
+                .map(|v| v.map(|v| *v.downcast_ref::<i64>().unwrap()))
+                .collect::<Int64Array>(),
+        ),
+        DataType::Float32 => Box::new(
+            values
+                .map(|v| v.map(|v| *v.downcast_ref::<f32>().unwrap()))
+                .collect::<Float32Array>(),
+        ),
+        DataType::Float64 => Box::new(
+            values
+                .map(|v| v.map(|v| *v.downcast_ref::<f64>().unwrap()))
+                .collect::<Float64Array>(),
+        ),
+        DataType::Boolean => Box::new(
+            values
+                .map(|v| v.map(|v| *v.downcast_ref::<bool>().unwrap()))
+                .collect::<BooleanArray>(),
+        ),
+        DataType::Utf8 => Box::new(
+            values
+                .map(|v| v.map(|v| v.downcast_ref::<String>().unwrap().as_str()))
+                .collect::<StringArray>(),
+        ),
+        DataType::Binary => Box::new(
+            values
+                .map(|v| v.map(|v| v.downcast_ref::<Vec<u8>>().unwrap().as_slice()))
+                .collect::<BinaryArray>(),
+        ),
+        DataType::List(_) | DataType::Struct(_) => build_nested_array(values, data_type)?,
+    };
+    Ok(Arc::new(ArrowFieldArray::new(arrow_array)))
+}
+
+/// Build a list or struct array from values holding their items or fields.
+/// The children are built recursively from all the items or from each field.
+fn build_nested_array<'a>(
+    values: impl Iterator<Item = Option<&'a dyn Any>>,
+    data_type: &DataType,
+) -> Result<Box<dyn Array>> {
+    let values = values
+        .map(|v| v.map(|v| v.downcast_ref::<Vec<Option<Box<dyn Any>>>>().unwrap()))
+        .collect::<Vec<_>>();
+    let validity = values
+        .iter()
+        .map(|v| Some(v.is_some()))
+        .collect::<BooleanArray>();
+    let builder = ArrayData::builder(data_type.clone().into())
+        .len(values.len())
+        .null_bit_buffer(validity.values().clone());
+    let child_data = |values: Vec<Option<&'a dyn Any>>, data_type| {
+        build_array(values.into_iter(), data_type)
+            .map(|array| array.as_arrow().unwrap().data().clone())
+    };
+    let data = match data_type {
+        DataType::List(item_type) => {
+            let mut offsets = vec![0_i32];
+            for v in &values {
+                offsets.push(offsets[offsets.len() - 1] + v.map_or(0, |v| v.len() as i32));
+            }
+            let items = values
+                .iter()
+                .copied()
+                .flatten()
+                .flat_map(|v| v.iter().map(|v| v.as_deref()))
+                .collect();
+            builder
+                .add_buffer(Buffer::from_slice_ref(&offsets))
+                .add_child_data(child_data(items, item_type)?)
+        }
+        DataType::Struct(fields) => {
+            fields
+                .iter()
+                .enumerate()
+                .try_fold(builder, |builder, (i, (_, field_type))| {
+                    let field_values = values
+                        .iter()
+                        .map(|v| v.and_then(|v| v[i].as_deref()))
+                        .collect();
+                    Ok::<_, anyhow::Error>(
+                        builder.add_child_data(child_data(field_values, field_type)?),
+                    )
+                })?
         }
         _ => unreachable!(),
-    }
+    };
+    Ok(Box::new(make_array(data.build()?)))
 }
 
 #[macro_export]
@@ -423,14 +672,33 @@ macro_rules! bool_binary_op {
                 let r = OrderedFloat(*r);
                 Box::new(l.$OP(&r)) as Box<dyn Any>
             }
+            DataType::Utf8 => {
+                let l = $LEFT.downcast_ref::<String>().unwrap();
+                let r = $RIGHT.downcast_ref::<String>().unwrap();
+                Box::new(l.$OP(r)) as Box<dyn Any>
+            }
+            DataType::Boolean => {
+                let l = $LEFT.downcast_ref::<bool>().unwrap();
+                let r = $RIGHT.downcast_ref::<bool>().unwrap();
+                Box::new(l.$OP(r)) as Box<dyn Any>
+            }
+            DataType::Binary => {
+                let l = $LEFT.downcast_ref::<Vec<u8>>().unwrap();
+                let r = $RIGHT.downcast_ref::<Vec<u8>>().unwrap();
+                Box::new(l.$OP(r)) as Box<dyn Any>
+            }
             _ => unreachable!(),
         }
     };
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Cast {
-    expr: Box<Expr>,
-    data_type: DataType,
+    pub expr: Box<Expr>,
+    pub data_type: DataType,
+    pub try_cast: bool,
+    /// The extension type of the input, which casts its values itself.
+    pub extension: Option<ExtensionRef>,
 }
 
 impl Cast {
@@ -438,87 +706,160 @@ impl Cast {
         Self {
             expr: Box::new(expr),
             data_type,
+            try_cast: false,
+            extension: None,
         }
     }
+
+    /// Return null for the values that cannot be cast instead of failing.
+    pub fn with_try_cast(mut self, try_cast: bool) -> Self {
+        self.try_cast = try_cast;
+        self
+    }
+
+    /// Cast the values of an input of the extension type with [`ExtensionType::cast`].
+    ///
+    /// [`ExtensionType::cast`]: crate::data_types::extension::ExtensionType::cast
+    pub fn with_extension(mut self, extension: Option<ExtensionRef>) -> Self {
+        self.extension = extension;
+        self
+    }
 }
 
 impl PhysicalExpr for Cast {
     fn evaluate(&self, input: &RecordBatch) -> Result<ArrayRef> {
         let value = self.expr.evaluate(input)?;
-        let values = cast(&value, &self.data_type)?;
-        evaluate_from_values(&values, &self.data_type)
+        let from = value.get_type();
+        let values = (0..value.size())
+            .map(|i| {
+                if value.is_null(i) {
+                    return Ok(None);
+                }
+                let v = value.get_value(i)?;
+                let cast = match &self.extension {
+                    Some(extension) => extension.cast(v.as_ref(), &self.data_type),
+                    None => cast_value(v.as_ref(), &from, &self.data_type),
+                };
+                match cast {
+                    Ok(v) => Ok(Some(v)),
+                    Err(_) if self.try_cast => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        evaluate_from_nullable_values(&values, &self.data_type)
     }
 }
 
 impl Display for Cast {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "CAST({} AS {})", self.expr, self.data_type)
+        let name = if self.try_cast { "TRY_CAST" } else { "CAST" };
+        write!(f, "{}({} AS {})", name, self.expr, self.data_type)
     }
 }
 
-fn cast(value: &ArrayRef, data_type: &DataType) -> Result<Vec<Box<dyn Any>>> {
-    Ok(match value.get_type() {
-        DataType::Int32 => (0..value.size())
-            .map(|i| Ok(*value.get_value(i)?.downcast_ref::<i32>().unwrap()))
-            .map(|v: Result<i32, Error>| match v {
-                Ok(v) => match data_type {
-                    DataType::Int32 => Ok(Box::new(v) as Box<dyn Any>),
-                    DataType::Int64 => Ok(Box::new(v as i64) as Box<dyn Any>),
-                    DataType::Float32 => Ok(Box::new(v as f32) as Box<dyn Any>),
-                    DataType::Float64 => Ok(Box::new(v as f64) as Box<dyn Any>),
-                    _ => unreachable!(),
-                },
-                Err(e) => Err(e),
-            })
-            .collect::<Result<Vec<Box<dyn Any>>, _>>()?,
-        DataType::Int64 => (0..value.size())
-            .map(|i| Ok(*value.get_value(i)?.downcast_ref::<i64>().unwrap()))
-            .map(|v: Result<i64, Error>| match v {
-                Ok(v) => match data_type {
-                    DataType::Int32 => Ok(Box::new(v as i32) as Box<dyn Any>),
-                    DataType::Int64 => Ok(Box::new(v) as Box<dyn Any>),
-                    DataType::Float32 => Ok(Box::new(v as f32) as Box<dyn Any>),
-                    DataType::Float64 => Ok(Box::new(v as f64) as Box<dyn Any>),
-                    _ => unreachable!(),
-                },
-                Err(e) => Err(e),
-            })
-            .collect::<Result<Vec<Box<dyn Any>>, _>>()?,
-        DataType::Float32 => (0..value.size())
-            .map(|i| Ok(*value.get_value(i)?.downcast_ref::<f32>().unwrap()))
-            .map(|v: Result<f32, Error>| match v {
-                Ok(v) => match data_type {
-                    DataType::Int32 => Ok(Box::new(v as i32) as Box<dyn Any>),
-                    DataType::Int64 => Ok(Box::new(v as i64) as Box<dyn Any>),
-                    DataType::Float32 => Ok(Box::new(v) as Box<dyn Any>),
-                    DataType::Float64 => Ok(Box::new(v as f64) as Box<dyn Any>),
-                    _ => unreachable!(),
-                },
-                Err(e) => Err(e),
-            })
-            .collect::<Result<Vec<Box<dyn Any>>, _>>()?,
-        DataType::Float64 => (0..value.size())
-            .map(|i| Ok(*value.get_value(i)?.downcast_ref::<f64>().unwrap()))
-            .map(|v: Result<f64, Error>| match v {
-                Ok(v) => match data_type {
-                    DataType::Int32 => Ok(Box::new(v as i32) as Box<dyn Any>),
-                    DataType::Int64 => Ok(Box::new(v as i64) as Box<dyn Any>),
-                    DataType::Float32 => Ok(Box::new(v as f32) as Box<dyn Any>),
-                    DataType::Float64 => Ok(Box::new(v) as Box<dyn Any>),
-                    _ => unreachable!(),
+/// Cast a value of one type to another.
+/// Numbers that are out of range of an integer type, strings that do not parse
+/// and NaN or infinite floats cast to integers are errors.
+fn cast_value(value: &dyn Any, from: &DataType, to: &DataType) -> Result<Box<dyn Any>> {
+    let invalid = |v: &dyn Display| anyhow!("Cannot cast {} {} to {}", from, v, to);
+    if from.is_nested() || to.is_nested() {
+        bail!("Cannot cast {} to {}", from, to);
+    }
+    match (from, to) {
+        (DataType::Binary, DataType::Binary) => {
+            return Ok(Box::new(value.downcast_ref::<Vec<u8>>().unwrap().clone()))
+        }
+        (DataType::Utf8, DataType::Binary) => {
+            let v = value.downcast_ref::<String>().unwrap();
+            return Ok(Box::new(v.as_bytes().to_vec()));
+        }
+        (DataType::Binary, DataType::Utf8) => {
+            let v = value.downcast_ref::<Vec<u8>>().unwrap();
+            return String::from_utf8(v.clone())
+                .map(|s| Box::new(s) as Box<dyn Any>)
+                .map_err(|_| anyhow!("Cannot cast Binary to Utf8, the bytes are not valid UTF-8"));
+        }
+        (DataType::Binary, _) | (_, DataType::Binary) => bail!("Cannot cast {} to {}", from, to),
+        _ => {}
+    }
+    Ok(match from {
+        DataType::Int32 | DataType::Int64 => {
+            let v = match from {
+                DataType::Int32 => *value.downcast_ref::<i32>().unwrap() as i64,
+                _ => *value.downcast_ref::<i64>().unwrap(),
+            };
+            match to {
+                DataType::Int32 => Box::new(i32::try_from(v).map_err(|_| invalid(&v))?),
+                DataType::Int64 => Box::new(v),
+                DataType::Float32 => Box::new(v as f32),
+                DataType::Float64 => Box::new(v as f64),
+                DataType::Boolean => Box::new(v != 0),
+                DataType::Utf8 => Box::new(v.to_string()),
+                DataType::Binary | DataType::List(_) | DataType::Struct(_) => unreachable!(),
+            }
+        }
+        DataType::Float32 | DataType::Float64 => {
+            let v = match from {
+                DataType::Float32 => *value.downcast_ref::<f32>().unwrap() as f64,
+                _ => *value.downcast_ref::<f64>().unwrap(),
+            };
+            let in_range =
+                |min: f64, max: f64| v.is_finite() && v.trunc() >= min && v.trunc() <= max;
+            match to {
+                DataType::Int32 if in_range(i32::MIN as f64, i32::MAX as f64) => Box::new(v as i32),
+                DataType::Int64 if in_range(i64::MIN as f64, i64::MAX as f64) => Box::new(v as i64),
+                DataType::Int32 | DataType::Int64 => return Err(invalid(&v)),
+                DataType::Float32 => Box::new(v as f32),
+                DataType::Float64 => Box::new(v),
+                DataType::Boolean => Box::new(v != 0.0),
+                DataType::Utf8 => match from {
+                    DataType::Float32 => Box::new((v as f32).to_string()),
+                    _ => Box::new(v.to_string()),
                 },
-                Err(e) => Err(e),
-            })
-            .collect::<Result<Vec<Box<dyn Any>>, _>>()?,
-        _ => unreachable!(),
+                DataType::Binary | DataType::List(_) | DataType::Struct(_) => unreachable!(),
+            }
+        }
+        DataType::Boolean => {
+            let v = *value.downcast_ref::<bool>().unwrap();
+            match to {
+                DataType::Int32 => Box::new(v as i32),
+                DataType::Int64 => Box::new(v as i64),
+                DataType::Float32 => Box::new(v as i32 as f32),
+                DataType::Float64 => Box::new(v as i32 as f64),
+                DataType::Boolean => Box::new(v),
+                DataType::Utf8 => Box::new(v.to_string()),
+                DataType::Binary | DataType::List(_) | DataType::Struct(_) => unreachable!(),
+            }
+        }
+        DataType::Utf8 => {
+            let v = value.downcast_ref::<String>().unwrap();
+            let s = v.trim();
+            match to {
+                DataType::Int32 => Box::new(s.parse::<i32>().map_err(|_| invalid(v))?),
+                DataType::Int64 => Box::new(s.parse::<i64>().map_err(|_| invalid(v))?),
+                DataType::Float32 => Box::new(s.parse::<f32>().map_err(|_| invalid(v))?),
+                DataType::Float64 => Box::new(s.parse::<f64>().map_err(|_| invalid(v))?),
+                DataType::Boolean => Box::new(match s.to_ascii_lowercase().as_str() {
+                    "true" | "t" | "1" => true,
+                    "false" | "f" | "0" => false,
+                    _ => return Err(invalid(v)),
+                }),
+                DataType::Utf8 => Box::new(v.clone()),
+                DataType::Binary | DataType::List(_) | DataType::Struct(_) => unreachable!(),
+            }
+        }
+        DataType::Binary | DataType::List(_) | DataType::Struct(_) => unreachable!(),
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
+    use std::sync::Arc;
 
-    use super::{BinaryExpr, Cast, Column, Expr, PhysicalExpr, ScalarValue};
+    use super::{
+        BinaryExpr, Cast, Column, DivisionByZeroMode, Expr, OverflowMode, PhysicalExpr, ScalarValue,
+    };
     use crate::{
         data_types::{
             arrow_field_array::ArrowFieldArray,
@@ -529,12 +870,12 @@ mod tests {
         logical_plan::expr::Operator,
     };
 
-    use arrow::array::{BooleanArray, Int32Array, Int64Array};
+    use arrow::array::{BooleanArray, Float64Array, Int32Array, Int64Array, StringArray};
 
     #[test]
     fn test_column_expr_evaluate() {
         let id = Int64Array::from(vec![1, 2, 3, 4, 5]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = Column::new(0);
@@ -550,7 +891,7 @@ mod tests {
         );
 
         let id = Int32Array::from(vec![1, 2, 3, 4, 5]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = Column::new(0);
@@ -575,7 +916,7 @@ mod tests {
     #[test]
     fn test_scalar_value_expr_evaluate() {
         let id = Int64Array::from(vec![1]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = ScalarValue::Int64(1);
@@ -591,16 +932,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_boolean_scalar_value_expr_evaluate() {
+        let id = Int64Array::from(vec![1, 2]);
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
+        let input = RecordBatch::new(schema, id_arrary);
+        let result = ScalarValue::Boolean(true).evaluate(&input).unwrap();
+        assert_eq!(result.get_type(), DataType::Boolean);
+        assert_eq!(result.size(), 2);
+        assert_eq!(
+            result.get_value(1).unwrap().downcast_ref::<bool>().unwrap(),
+            &true
+        );
+    }
+
     #[test]
     fn test_scalar_value_expr_display() {
         let expr = ScalarValue::Int64(1);
         assert_eq!(expr.to_string(), "1");
+        assert_eq!(ScalarValue::Boolean(false).to_string(), "false");
     }
 
     #[test]
     fn test_add_expr_evaluate() {
         let id = Int64Array::from(vec![1]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -633,7 +990,7 @@ mod tests {
     #[test]
     fn test_subtract_expr_evaluate() {
         let id = Int64Array::from(vec![1]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -666,7 +1023,7 @@ mod tests {
     #[test]
     fn test_multiply_expr_evaluate() {
         let id = Int64Array::from(vec![2]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -699,7 +1056,7 @@ mod tests {
     #[test]
     fn test_divide_expr_evaluate() {
         let id = Int64Array::from(vec![2]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -732,7 +1089,7 @@ mod tests {
     #[test]
     fn test_modulus_expr_evaluate() {
         let id = Int64Array::from(vec![3]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -765,7 +1122,7 @@ mod tests {
     #[test]
     fn test_and_expr_evaluate() {
         let bool = BooleanArray::from(vec![false]);
-        let bool_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(bool))) as ArrayRef];
+        let bool_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(bool))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("bool".to_string(), DataType::Boolean)]);
         let input = RecordBatch::new(schema, bool_arrary);
         let expr = BinaryExpr::new(
@@ -798,7 +1155,7 @@ mod tests {
     #[test]
     fn test_or_expr_evaluate() {
         let bool = BooleanArray::from(vec![false]);
-        let bool_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(bool))) as ArrayRef];
+        let bool_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(bool))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("bool".to_string(), DataType::Boolean)]);
         let input = RecordBatch::new(schema, bool_arrary);
         let expr = BinaryExpr::new(
@@ -828,10 +1185,26 @@ mod tests {
         assert_eq!(expr.to_string(), "#0 OR #0");
     }
 
+    #[test]
+    fn test_nested_binary_expr_display() {
+        let column = |i| Expr::Column(Column::new(i));
+        let binary = |op, l, r| Expr::BinaryExpr(BinaryExpr::new(op, l, r));
+        let sum = || binary(Operator::Add, column(0), column(1));
+        let expr = BinaryExpr::new(Operator::Multiply, sum(), column(2));
+        assert_eq!(expr.to_string(), "(#0 + #1) * #2");
+        let expr = BinaryExpr::new(Operator::Subtract, column(2), sum());
+        assert_eq!(expr.to_string(), "#2 - (#0 + #1)");
+        let expr = BinaryExpr::new(Operator::Subtract, sum(), column(2));
+        assert_eq!(expr.to_string(), "#0 + #1 - #2");
+        let or = binary(Operator::Or, column(0), column(1));
+        let expr = BinaryExpr::new(Operator::And, or, column(2));
+        assert_eq!(expr.to_string(), "(#0 OR #1) AND #2");
+    }
+
     #[test]
     fn test_eq_expr_evaluate() {
         let id = Int64Array::from(vec![1]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -864,7 +1237,7 @@ mod tests {
     #[test]
     fn test_neq_expr_evaluate() {
         let id = Int64Array::from(vec![1]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -897,7 +1270,7 @@ mod tests {
     #[test]
     fn test_lt_expr_evaluate() {
         let id = Int64Array::from(vec![1]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -930,7 +1303,7 @@ mod tests {
     #[test]
     fn test_lt_eq_expr_evaluate() {
         let id = Int64Array::from(vec![2]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -963,7 +1336,7 @@ mod tests {
     #[test]
     fn test_gt_expr_evaluate() {
         let id = Int64Array::from(vec![1]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -996,7 +1369,7 @@ mod tests {
     #[test]
     fn test_gt_eq_expr_evaluate() {
         let id = Int64Array::from(vec![2]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = BinaryExpr::new(
@@ -1029,7 +1402,7 @@ mod tests {
     #[test]
     fn test_cast_expr_evaluate() {
         let id = Int64Array::from(vec![2]);
-        let id_arrary = vec![Rc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let id_arrary = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
         let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
         let input = RecordBatch::new(schema, id_arrary);
         let expr = Cast::new(Expr::Column(Column::new(0)), DataType::Float64);
@@ -1045,9 +1418,221 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cast_between_all_types() {
+        let s = StringArray::from(vec![
+            Some("12"),
+            Some(" 3.5 "),
+            Some("true"),
+            Some("x"),
+            None,
+        ]);
+        let schema = Schema::new(vec![Field::new("s".to_string(), DataType::Utf8)]);
+        let input = RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(s))) as ArrayRef],
+        );
+        let column = || Expr::Column(Column::new(0));
+
+        // A failing value is an error for CAST and a null for TRY_CAST.
+        assert!(Cast::new(column(), DataType::Int32)
+            .evaluate(&input)
+            .is_err());
+        let result = Cast::new(column(), DataType::Float64)
+            .with_try_cast(true)
+            .evaluate(&input)
+            .unwrap();
+        assert_eq!(
+            (0..result.size())
+                .map(|i| {
+                    (!result.is_null(i))
+                        .then(|| *result.get_value(i).unwrap().downcast_ref::<f64>().unwrap())
+                })
+                .collect::<Vec<_>>(),
+            vec![Some(12.0), Some(3.5), None, None, None]
+        );
+        let result = Cast::new(column(), DataType::Boolean)
+            .with_try_cast(true)
+            .evaluate(&input)
+            .unwrap();
+        assert!(result.is_null(0));
+        assert!(*result.get_value(2).unwrap().downcast_ref::<bool>().unwrap());
+
+        let big = Int64Array::from(vec![i64::MAX]);
+        let schema = Schema::new(vec![Field::new("big".to_string(), DataType::Int64)]);
+        let input = RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(big))) as ArrayRef],
+        );
+        assert!(Cast::new(column(), DataType::Int32)
+            .evaluate(&input)
+            .is_err());
+        let text = Cast::new(column(), DataType::Utf8)
+            .evaluate(&input)
+            .unwrap();
+        assert_eq!(
+            text.get_value(0).unwrap().downcast_ref::<String>().unwrap(),
+            &i64::MAX.to_string()
+        );
+        let flag = Cast::new(column(), DataType::Boolean)
+            .evaluate(&input)
+            .unwrap();
+        assert!(*flag.get_value(0).unwrap().downcast_ref::<bool>().unwrap());
+    }
+
     #[test]
     fn test_cast_expr_display() {
         let expr = Cast::new(Expr::Column(Column::new(0)), DataType::Int32);
         assert_eq!(expr.to_string(), "CAST(#0 AS Int32)");
+        let expr = expr.with_try_cast(true);
+        assert_eq!(expr.to_string(), "TRY_CAST(#0 AS Int32)");
+    }
+
+    #[test]
+    fn test_utf8_comparison_expr_evaluate() {
+        let s = StringArray::from(vec!["a", "b"]);
+        let s_array = vec![Arc::new(ArrowFieldArray::new(Box::new(s))) as ArrayRef];
+        let schema = Schema::new(vec![Field::new("s".to_string(), DataType::Utf8)]);
+        let input = RecordBatch::new(schema, s_array);
+        let expr = BinaryExpr::new(
+            Operator::Gt,
+            Expr::Column(Column::new(0)),
+            Expr::Literal(ScalarValue::String("a".to_string())),
+        );
+        let result = expr.evaluate(&input).unwrap();
+        assert!(!result.get_value(0).unwrap().downcast_ref::<bool>().unwrap());
+        assert!(result.get_value(1).unwrap().downcast_ref::<bool>().unwrap());
+    }
+
+    #[test]
+    fn test_integer_overflow_modes() {
+        let a = Int32Array::from(vec![Some(i32::MAX), Some(1), None]);
+        let schema = Schema::new(vec![Field::new("a".to_string(), DataType::Int32)]);
+        let input = RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(a))) as ArrayRef],
+        );
+        let add = |mode| {
+            BinaryExpr::new(
+                Operator::Add,
+                Expr::Column(Column::new(0)),
+                Expr::Literal(ScalarValue::Int32(1)),
+            )
+            .with_overflow_mode(mode)
+            .evaluate(&input)
+        };
+        let values = |result: ArrayRef| {
+            (0..result.size())
+                .map(|i| {
+                    (!result.is_null(i))
+                        .then(|| *result.get_value(i).unwrap().downcast_ref::<i32>().unwrap())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert!(add(OverflowMode::Checked).is_err());
+        assert_eq!(
+            values(add(OverflowMode::Wrapping).unwrap()),
+            vec![Some(i32::MIN), Some(2), None]
+        );
+        assert_eq!(
+            values(add(OverflowMode::Saturating).unwrap()),
+            vec![Some(i32::MAX), Some(2), None]
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero_modes() {
+        let a = Int64Array::from(vec![7, 7]);
+        let b = Int64Array::from(vec![2, 0]);
+        let x = Float64Array::from(vec![7.0, 7.0]);
+        let y = Float64Array::from(vec![2.0, 0.0]);
+        let schema = Schema::new(vec![
+            Field::new("a".to_string(), DataType::Int64),
+            Field::new("b".to_string(), DataType::Int64),
+            Field::new("x".to_string(), DataType::Float64),
+            Field::new("y".to_string(), DataType::Float64),
+        ]);
+        let input = RecordBatch::new(
+            schema,
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(a))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(b))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(x))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(y))) as ArrayRef,
+            ],
+        );
+        let divide = |op, l, r, mode| {
+            BinaryExpr::new(
+                op,
+                Expr::Column(Column::new(l)),
+                Expr::Column(Column::new(r)),
+            )
+            .with_division_by_zero(mode)
+            .evaluate(&input)
+        };
+
+        for op in [Operator::Divide, Operator::Modulus] {
+            assert!(divide(op, 0, 1, DivisionByZeroMode::Error).is_err());
+            assert!(divide(op, 2, 3, DivisionByZeroMode::Error).is_err());
+        }
+        let result = divide(Operator::Divide, 0, 1, DivisionByZeroMode::Null).unwrap();
+        assert_eq!(
+            *result.get_value(0).unwrap().downcast_ref::<i64>().unwrap(),
+            3
+        );
+        assert!(result.is_null(1));
+        let result = divide(Operator::Modulus, 2, 3, DivisionByZeroMode::Null).unwrap();
+        assert_eq!(
+            *result.get_value(0).unwrap().downcast_ref::<f64>().unwrap(),
+            1.0
+        );
+        assert!(result.is_null(1));
+    }
+
+    #[test]
+    fn test_binary_expr_null_semantics() {
+        let a = Int64Array::from(vec![Some(1), None, None]);
+        let b = BooleanArray::from(vec![None, Some(true), Some(false)]);
+        let x = Float64Array::from(vec![f64::NAN, 1.0, 2.0]);
+        let schema = Schema::new(vec![
+            Field::new("a".to_string(), DataType::Int64),
+            Field::new("b".to_string(), DataType::Boolean),
+            Field::new("x".to_string(), DataType::Float64),
+        ]);
+        let input = RecordBatch::new(
+            schema,
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(a))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(b))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(x))) as ArrayRef,
+            ],
+        );
+        let evaluate = |op, l, r| {
+            BinaryExpr::new(op, Expr::Column(Column::new(l)), r)
+                .evaluate(&input)
+                .unwrap()
+        };
+        let value =
+            |result: &ArrayRef, i| *result.get_value(i).unwrap().downcast_ref::<bool>().unwrap();
+
+        // Comparing with NULL is NULL.
+        let result = evaluate(Operator::Gt, 0, Expr::Literal(ScalarValue::Int64(0)));
+        assert!(value(&result, 0));
+        assert!(result.is_null(1));
+        assert!(result.is_null(2));
+
+        // AND and OR follow three-valued logic.
+        let result = evaluate(Operator::And, 1, Expr::Literal(ScalarValue::Boolean(false)));
+        assert!(!value(&result, 0));
+        let result = evaluate(Operator::Or, 1, Expr::Literal(ScalarValue::Boolean(true)));
+        assert!(value(&result, 0));
+        let result = evaluate(Operator::And, 1, Expr::Literal(ScalarValue::Boolean(true)));
+        assert!(result.is_null(0));
+
+        // NaN is equal to itself.
+        let result = evaluate(Operator::Eq, 2, Expr::Column(Column::new(2)));
+        assert!(value(&result, 0));
+        assert!(value(&result, 1));
     }
 }
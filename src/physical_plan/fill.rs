@@ -0,0 +1,152 @@
+use std::fmt::Display;
+
+use super::{
+    expr::{evaluate_from_nullable_values, Expr, PhysicalExpr},
+    memory::MemoryBudget,
+    plan::{Partitioning, PhysicalPlan, Plan},
+    sort_key::sort_key,
+};
+use crate::{
+    data_types::{record_batch::RecordBatch, schema::Schema},
+    logical_plan::fill::FillDirection,
+};
+
+use anyhow::Result;
+
+/// Execute a forward or backward fill of the columns at the given indices.
+/// All the input is read to order the rows, but the rows keep their input order
+/// and batches.
+pub struct FillExec {
+    pub(crate) input: Box<Plan>,
+    pub(crate) columns: Vec<usize>,
+    pub(crate) order_by: Vec<Expr>,
+    pub(crate) direction: FillDirection,
+    memory: MemoryBudget,
+}
+
+impl FillExec {
+    pub fn new(
+        input: Plan,
+        columns: Vec<usize>,
+        order_by: Vec<Expr>,
+        direction: FillDirection,
+    ) -> Self {
+        Self {
+            input: Box::new(input),
+            columns,
+            order_by,
+            direction,
+            memory: MemoryBudget::default(),
+        }
+    }
+
+    /// Reserve the data buffered by the operator from the memory budget of the query.
+    pub fn with_memory_budget(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Return the rows, as (batch, row), in the order the values are carried.
+    fn fill_order(&self, batches: &[RecordBatch]) -> Result<Vec<(usize, usize)>> {
+        let mut rows = vec![];
+        for (b, batch) in batches.iter().enumerate() {
+            let order_by = self
+                .order_by
+                .iter()
+                .map(|e| e.evaluate(batch))
+                .collect::<Result<Vec<_>>>()?;
+            for row in 0..batch.row_count() {
+                rows.push((sort_key(&order_by, row)?, (b, row)));
+            }
+        }
+        rows.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut rows = rows
+            .into_iter()
+            .map(|(_, location)| location)
+            .collect::<Vec<_>>();
+        if self.direction == FillDirection::Backward {
+            rows.reverse();
+        }
+        Ok(rows)
+    }
+}
+
+impl PhysicalPlan for FillExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let mut reservation = self.memory.reservation("FillExec");
+        let mut batches = reservation.collect(self.input.execute_all()?)?;
+        let order = self.fill_order(&batches)?;
+        for &c in &self.columns {
+            // The location of the value each row takes, by batch and row.
+            let mut sources = batches
+                .iter()
+                .enumerate()
+                .map(|(b, batch)| (0..batch.row_count()).map(|row| Some((b, row))).collect())
+                .collect::<Vec<Vec<_>>>();
+            let mut last = None;
+            for &(b, row) in &order {
+                if batches[b].field(c).is_null(row) {
+                    sources[b][row] = last;
+                } else {
+                    last = Some((b, row));
+                }
+            }
+            let filled = batches
+                .iter()
+                .zip(&sources)
+                .map(|(batch, sources)| {
+                    let values = sources
+                        .iter()
+                        .map(|source| match source {
+                            Some((b, row)) => {
+                                let array = batches[*b].field(c);
+                                (!array.is_null(*row))
+                                    .then(|| array.get_value(*row))
+                                    .transpose()
+                            }
+                            None => Ok(None),
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    evaluate_from_nullable_values(&values, &batch.field(c).get_type())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            batches
+                .iter_mut()
+                .zip(filled)
+                .for_each(|(batch, array)| batch.fields[c] = array);
+        }
+        Ok(Box::new(batches.into_iter().map(Ok)))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for FillExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "FillExec: direction={:?}, columns={:?}, orderBy={}",
+            self.direction,
+            self.columns,
+            self.order_by
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
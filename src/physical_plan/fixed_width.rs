@@ -0,0 +1,847 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::expr::{
+    BinaryExpr, BinaryOperator, Cast, Column, DivisionByZeroMode, Expr, OverflowMode, ScalarValue,
+};
+use crate::{
+    data_types::{
+        column_array::{ColumnArray, DataType},
+        record_batch::RecordBatch,
+        schema::Schema,
+    },
+    logical_plan::expr::Operator,
+};
+
+use anyhow::{bail, Result};
+use arrow::{
+    array::{as_primitive_array, Array, ArrayData, ArrayRef as ArrowArrayRef, PrimitiveArray},
+    buffer::{buffer_bin_and, Buffer, MutableBuffer},
+    compute::math_op,
+    datatypes::{
+        ArrowNumericType, ArrowPrimitiveType, DataType as ArrowDataType, Float32Type, Float64Type,
+        Int32Type, Int64Type,
+    },
+    util::bit_util,
+};
+
+/// The values of a fixed-width numeric column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixedWidthValues {
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Float32(Vec<f32>),
+    Float64(Vec<f64>),
+}
+
+impl FixedWidthValues {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            FixedWidthValues::Int32(_) => DataType::Int32,
+            FixedWidthValues::Int64(_) => DataType::Int64,
+            FixedWidthValues::Float32(_) => DataType::Float32,
+            FixedWidthValues::Float64(_) => DataType::Float64,
+        }
+    }
+
+    /// Clear the values and make them of the given type,
+    /// keeping the allocation if the type does not change.
+    fn reset(&mut self, data_type: &DataType) -> Result<()> {
+        if self.data_type() != *data_type {
+            *self = match data_type {
+                DataType::Int32 => FixedWidthValues::Int32(vec![]),
+                DataType::Int64 => FixedWidthValues::Int64(vec![]),
+                DataType::Float32 => FixedWidthValues::Float32(vec![]),
+                DataType::Float64 => FixedWidthValues::Float64(vec![]),
+                _ => bail!("{} is not a fixed-width numeric type", data_type),
+            };
+        }
+        match self {
+            FixedWidthValues::Int32(v) => v.clear(),
+            FixedWidthValues::Int64(v) => v.clear(),
+            FixedWidthValues::Float32(v) => v.clear(),
+            FixedWidthValues::Float64(v) => v.clear(),
+        }
+        Ok(())
+    }
+}
+
+/// Output buffers for [`evaluate_fixed_width`], with one node per expression node.
+/// The buffers keep their capacity between batches, so once they have seen a batch of
+/// the largest size, evaluating an expression does not allocate.
+#[derive(Debug)]
+pub struct FixedWidthBuffers {
+    values: FixedWidthValues,
+    validity: Vec<bool>,
+    children: Vec<FixedWidthBuffers>,
+}
+
+impl Default for FixedWidthBuffers {
+    fn default() -> Self {
+        Self {
+            values: FixedWidthValues::Int64(vec![]),
+            validity: vec![],
+            children: vec![],
+        }
+    }
+}
+
+impl FixedWidthBuffers {
+    /// Return the values of the last evaluation. The values of null rows are unspecified.
+    pub fn values(&self) -> &FixedWidthValues {
+        &self.values
+    }
+
+    /// Return whether each row of the last evaluation is not null.
+    pub fn validity(&self) -> &[bool] {
+        &self.validity
+    }
+
+    /// Copy the result of the last evaluation to an Arrow array.
+    pub fn to_array(&self) -> Result<ArrowArrayRef> {
+        let nulls = self
+            .validity
+            .contains(&false)
+            .then(|| self.validity.iter().copied().collect::<Buffer>());
+        match &self.values {
+            FixedWidthValues::Int32(v) => primitive_array::<Int32Type>(v, nulls),
+            FixedWidthValues::Int64(v) => primitive_array::<Int64Type>(v, nulls),
+            FixedWidthValues::Float32(v) => primitive_array::<Float32Type>(v, nulls),
+            FixedWidthValues::Float64(v) => primitive_array::<Float64Type>(v, nulls),
+        }
+    }
+}
+
+fn primitive_array<T: ArrowPrimitiveType>(
+    values: &[T::Native],
+    nulls: Option<Buffer>,
+) -> Result<ArrowArrayRef> {
+    let mut data = ArrayData::builder(T::DATA_TYPE)
+        .len(values.len())
+        .add_buffer(Buffer::from_slice_ref(&values));
+    if let Some(nulls) = nulls {
+        data = data.null_bit_buffer(nulls);
+    }
+    Ok(Arc::new(PrimitiveArray::<T>::from(data.build()?)))
+}
+
+/// Return the type of the values of the expression if [`evaluate_fixed_width`] evaluates
+/// it over batches of the schema: columns and literals of fixed-width numeric types, casts
+/// between them and arithmetic over operands of the same type.
+pub fn fixed_width_type(expr: &Expr, schema: &Schema) -> Option<DataType> {
+    let data_type = match expr {
+        Expr::Column(column) => schema.fields.get(column.i)?.data_type.clone(),
+        Expr::Literal(ScalarValue::Int32(_)) => DataType::Int32,
+        Expr::Literal(ScalarValue::Int64(_)) => DataType::Int64,
+        Expr::Literal(ScalarValue::Float32(_)) => DataType::Float32,
+        Expr::Literal(ScalarValue::Float64(_)) => DataType::Float64,
+        Expr::Literal(ScalarValue::Null(data_type)) => data_type.clone(),
+        Expr::Cast(cast) if cast.extension.is_none() => {
+            fixed_width_type(&cast.expr, schema)?;
+            cast.data_type.clone()
+        }
+        Expr::BinaryExpr(binary) if binary.op.is_arithmetic() => {
+            let data_type = fixed_width_type(&binary.left, schema)?;
+            if fixed_width_type(&binary.right, schema)? != data_type {
+                return None;
+            }
+            data_type
+        }
+        _ => return None,
+    };
+    matches!(
+        data_type,
+        DataType::Int32 | DataType::Int64 | DataType::Float32 | DataType::Float64
+    )
+    .then_some(data_type)
+}
+
+/// Evaluate a column, literal, placeholder, cast or arithmetic expression over fixed-width
+/// numeric values into the buffers, with the same results as [`PhysicalExpr::evaluate`].
+/// Columns are read directly from their Arrow arrays.
+///
+/// [`PhysicalExpr::evaluate`]: super::expr::PhysicalExpr::evaluate
+pub fn evaluate_fixed_width(
+    expr: &Expr,
+    input: &RecordBatch,
+    buffers: &mut FixedWidthBuffers,
+) -> Result<()> {
+    match expr {
+        Expr::Column(column) => evaluate_column(column, input, buffers),
+        Expr::Literal(literal) => evaluate_literal(literal, input.row_count(), buffers),
+        Expr::Placeholder(placeholder) => {
+            placeholder.with_value(|value| evaluate_literal(value, input.row_count(), buffers))
+        }
+        Expr::Cast(cast) => evaluate_cast(cast, input, buffers),
+        Expr::BinaryExpr(binary) => evaluate_binary(binary, input, buffers),
+        Expr::GetField(_) | Expr::GetIndex(_) | Expr::ScalarFunction(_) => {
+            bail!("{} is not a fixed-width numeric expression", expr)
+        }
+    }
+}
+
+fn evaluate_column(
+    column: &Column,
+    input: &RecordBatch,
+    buffers: &mut FixedWidthBuffers,
+) -> Result<()> {
+    let array = input.field(column.i);
+    let data_type = array.get_type();
+    buffers.values.reset(&data_type)?;
+    buffers.validity.clear();
+    buffers
+        .validity
+        .extend((0..array.size()).map(|i| !array.is_null(i)));
+    match &mut buffers.values {
+        FixedWidthValues::Int32(out) => copy_values::<Int32Type>(array.as_ref(), out),
+        FixedWidthValues::Int64(out) => copy_values::<Int64Type>(array.as_ref(), out),
+        FixedWidthValues::Float32(out) => copy_values::<Float32Type>(array.as_ref(), out),
+        FixedWidthValues::Float64(out) => copy_values::<Float64Type>(array.as_ref(), out),
+    }
+}
+
+/// Copy the values of the array into the buffer.
+/// Arrays that are not backed by Arrow are read value by value, which allocates.
+fn copy_values<T: ArrowPrimitiveType>(
+    array: &dyn ColumnArray,
+    out: &mut Vec<T::Native>,
+) -> Result<()> {
+    match array
+        .as_arrow()
+        .and_then(|a| a.as_any().downcast_ref::<PrimitiveArray<T>>())
+    {
+        Some(primitive) => out.extend_from_slice(primitive.values()),
+        None => {
+            for i in 0..array.size() {
+                out.push(match array.is_null(i) {
+                    true => T::Native::default(),
+                    false => *array.get_value(i)?.downcast_ref::<T::Native>().unwrap(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn evaluate_literal(
+    literal: &ScalarValue,
+    row_count: usize,
+    buffers: &mut FixedWidthBuffers,
+) -> Result<()> {
+    let data_type = match literal {
+        ScalarValue::Int32(_) => DataType::Int32,
+        ScalarValue::Int64(_) => DataType::Int64,
+        ScalarValue::Float32(_) => DataType::Float32,
+        ScalarValue::Float64(_) => DataType::Float64,
+        ScalarValue::Null(data_type) => data_type.clone(),
+        _ => bail!("{} is not a fixed-width numeric expression", literal),
+    };
+    buffers.values.reset(&data_type)?;
+    buffers.validity.clear();
+    let valid = !matches!(literal, ScalarValue::Null(_));
+    buffers.validity.resize(row_count, valid);
+    match (&mut buffers.values, literal) {
+        (FixedWidthValues::Int32(out), ScalarValue::Int32(v)) => out.resize(row_count, *v),
+        (FixedWidthValues::Int64(out), ScalarValue::Int64(v)) => out.resize(row_count, *v),
+        (FixedWidthValues::Float32(out), ScalarValue::Float32(v)) => out.resize(row_count, *v),
+        (FixedWidthValues::Float64(out), ScalarValue::Float64(v)) => out.resize(row_count, *v),
+        (FixedWidthValues::Int32(out), _) => out.resize(row_count, 0),
+        (FixedWidthValues::Int64(out), _) => out.resize(row_count, 0),
+        (FixedWidthValues::Float32(out), _) => out.resize(row_count, 0.0),
+        (FixedWidthValues::Float64(out), _) => out.resize(row_count, 0.0),
+    }
+    Ok(())
+}
+
+/// A fixed-width numeric type, converted through i128 or f64 like [`Cast`] does for boxed
+/// values, whose arithmetic is computed in the type itself.
+trait Native: Copy + Default + Display {
+    const NAME: &'static str;
+    const BITS: Option<u32>;
+    fn to_i128(self) -> i128;
+    fn to_f64(self) -> f64;
+    fn from_i128(v: i128) -> Self;
+    fn from_f64(v: f64) -> Self;
+
+    /// Return the function computing the operator if it never fails nor returns null
+    /// with the modes of the kernel.
+    fn infallible(kernel: &Arithmetic) -> Option<fn(Self, Self) -> Self>;
+
+    /// Apply the operator with the modes of the kernel, returning `None` for null.
+    fn apply(kernel: &Arithmetic, l: Self, r: Self) -> Result<Option<Self>>;
+}
+
+macro_rules! impl_conversions {
+    ($t:ty, $name:expr, $bits:expr) => {
+        const NAME: &'static str = $name;
+        const BITS: Option<u32> = $bits;
+        fn to_i128(self) -> i128 {
+            self as i128
+        }
+        fn to_f64(self) -> f64 {
+            self as f64
+        }
+        fn from_i128(v: i128) -> Self {
+            v as $t
+        }
+        fn from_f64(v: f64) -> Self {
+            v as $t
+        }
+    };
+}
+
+macro_rules! impl_integer {
+    ($t:ty, $name:expr, $bits:expr) => {
+        impl Native for $t {
+            impl_conversions!($t, $name, $bits);
+
+            fn infallible(kernel: &Arithmetic) -> Option<fn(Self, Self) -> Self> {
+                match (kernel.op, kernel.overflow_mode) {
+                    (_, OverflowMode::Checked) => None,
+                    (Operator::Add, OverflowMode::Wrapping) => Some(<$t>::wrapping_add),
+                    (Operator::Subtract, OverflowMode::Wrapping) => Some(<$t>::wrapping_sub),
+                    (Operator::Multiply, OverflowMode::Wrapping) => Some(<$t>::wrapping_mul),
+                    (Operator::Add, OverflowMode::Saturating) => Some(<$t>::saturating_add),
+                    (Operator::Subtract, OverflowMode::Saturating) => Some(<$t>::saturating_sub),
+                    (Operator::Multiply, OverflowMode::Saturating) => Some(<$t>::saturating_mul),
+                    _ => None,
+                }
+            }
+
+            fn apply(kernel: &Arithmetic, l: Self, r: Self) -> Result<Option<Self>> {
+                let result = match kernel.op {
+                    Operator::Add => l.checked_add(r),
+                    Operator::Subtract => l.checked_sub(r),
+                    Operator::Multiply => l.checked_mul(r),
+                    _ if r == 0 => return kernel.divide_by_zero(),
+                    Operator::Divide => l.checked_div(r),
+                    // The remainder of MIN / -1 is 0 even though the quotient overflows.
+                    _ => Some(l.wrapping_rem(r)),
+                };
+                if let Some(result) = result {
+                    return Ok(Some(result));
+                }
+                Ok(Some(match (kernel.overflow_mode, kernel.op) {
+                    (OverflowMode::Checked, op) => {
+                        bail!("{} overflow in {} {} {}", $name, l, op, r)
+                    }
+                    (OverflowMode::Wrapping, Operator::Add) => l.wrapping_add(r),
+                    (OverflowMode::Wrapping, Operator::Subtract) => l.wrapping_sub(r),
+                    (OverflowMode::Wrapping, Operator::Multiply) => l.wrapping_mul(r),
+                    (OverflowMode::Wrapping, _) => l.wrapping_div(r),
+                    (OverflowMode::Saturating, Operator::Add) => l.saturating_add(r),
+                    (OverflowMode::Saturating, Operator::Subtract) => l.saturating_sub(r),
+                    (OverflowMode::Saturating, Operator::Multiply) => l.saturating_mul(r),
+                    (OverflowMode::Saturating, _) => l.saturating_div(r),
+                }))
+            }
+        }
+    };
+}
+
+macro_rules! impl_float {
+    ($t:ty, $name:expr) => {
+        impl Native for $t {
+            impl_conversions!($t, $name, None);
+
+            fn infallible(kernel: &Arithmetic) -> Option<fn(Self, Self) -> Self> {
+                match kernel.op {
+                    Operator::Add => Some(|l, r| l + r),
+                    Operator::Subtract => Some(|l, r| l - r),
+                    Operator::Multiply => Some(|l, r| l * r),
+                    _ => None,
+                }
+            }
+
+            fn apply(kernel: &Arithmetic, l: Self, r: Self) -> Result<Option<Self>> {
+                Ok(Some(match kernel.op {
+                    Operator::Add => l + r,
+                    Operator::Subtract => l - r,
+                    Operator::Multiply => l * r,
+                    _ if r == 0.0 => return kernel.divide_by_zero(),
+                    Operator::Divide => l / r,
+                    _ => l % r,
+                }))
+            }
+        }
+    };
+}
+
+impl_integer!(i32, "Int32", Some(32));
+impl_integer!(i64, "Int64", Some(64));
+impl_float!(f32, "Float32");
+impl_float!(f64, "Float64");
+
+/// Call `$f` with the typed values of `$values` bound to `$v`.
+macro_rules! with_values {
+    ($values:expr, $v:ident => $f:expr) => {
+        match $values {
+            FixedWidthValues::Int32($v) => $f,
+            FixedWidthValues::Int64($v) => $f,
+            FixedWidthValues::Float32($v) => $f,
+            FixedWidthValues::Float64($v) => $f,
+        }
+    };
+}
+
+fn evaluate_cast(cast: &Cast, input: &RecordBatch, buffers: &mut FixedWidthBuffers) -> Result<()> {
+    if cast.extension.is_some() {
+        bail!("{} is not a fixed-width numeric expression", cast);
+    }
+    buffers.children.resize_with(1, Default::default);
+    let child = &mut buffers.children[0];
+    evaluate_fixed_width(&cast.expr, input, child)?;
+    buffers.values.reset(&cast.data_type)?;
+    buffers.validity.clear();
+    buffers.validity.extend_from_slice(&child.validity);
+    let validity = &mut buffers.validity;
+    with_values!(&child.values, from => with_values!(&mut buffers.values, to => {
+        cast_values(from, to, validity, cast.try_cast)
+    }))
+}
+
+fn cast_values<T: Native, U: Native>(
+    from: &[T],
+    to: &mut Vec<U>,
+    validity: &mut [bool],
+    try_cast: bool,
+) -> Result<()> {
+    for (&v, valid) in from.iter().zip(validity.iter_mut()) {
+        let value = if !*valid {
+            Some(U::default())
+        } else {
+            match (T::BITS, U::BITS) {
+                (Some(_), Some(bits)) => {
+                    let v = v.to_i128();
+                    in_range(v, bits).then(|| U::from_i128(v))
+                }
+                (Some(_), None) => Some(U::from_i128(v.to_i128())),
+                (None, Some(bits)) => {
+                    let v = v.to_f64();
+                    let (min, max) = int_range(bits);
+                    (v.is_finite() && v.trunc() >= min as f64 && v.trunc() <= max as f64)
+                        .then(|| U::from_f64(v))
+                }
+                (None, None) => Some(U::from_f64(v.to_f64())),
+            }
+        };
+        match value {
+            Some(value) => to.push(value),
+            None if try_cast => {
+                *valid = false;
+                to.push(U::default());
+            }
+            None => match T::BITS {
+                Some(_) => bail!("Cannot cast {} {} to {}", T::NAME, v.to_i128(), U::NAME),
+                None => bail!("Cannot cast {} {} to {}", T::NAME, v.to_f64(), U::NAME),
+            },
+        }
+    }
+    Ok(())
+}
+
+fn int_range(bits: u32) -> (i128, i128) {
+    (-(1_i128 << (bits - 1)), (1_i128 << (bits - 1)) - 1)
+}
+
+fn in_range(v: i128, bits: u32) -> bool {
+    let (min, max) = int_range(bits);
+    (min..=max).contains(&v)
+}
+
+fn evaluate_binary(
+    binary: &BinaryExpr,
+    input: &RecordBatch,
+    buffers: &mut FixedWidthBuffers,
+) -> Result<()> {
+    if !binary.op.is_arithmetic() {
+        bail!("{} is not a fixed-width numeric expression", binary);
+    }
+    buffers.children.resize_with(2, Default::default);
+    let (left, right) = buffers.children.split_at_mut(1);
+    let (left, right) = (&mut left[0], &mut right[0]);
+    evaluate_fixed_width(&binary.left, input, left)?;
+    evaluate_fixed_width(&binary.right, input, right)?;
+    let data_type = left.values.data_type();
+    if right.values.data_type() != data_type {
+        bail!(
+            "Operands of {} have different types {} and {}",
+            binary,
+            data_type,
+            right.values.data_type()
+        );
+    }
+    buffers.values.reset(&data_type)?;
+    buffers.validity.clear();
+    buffers.validity.extend(
+        left.validity
+            .iter()
+            .zip(&right.validity)
+            .map(|(l, r)| *l && *r),
+    );
+    let kernel = Arithmetic {
+        op: binary.op,
+        overflow_mode: binary.overflow_mode,
+        division_by_zero: binary.division_by_zero,
+    };
+    let validity = &mut buffers.validity;
+    match (&left.values, &right.values, &mut buffers.values) {
+        (FixedWidthValues::Int32(l), FixedWidthValues::Int32(r), FixedWidthValues::Int32(out)) => {
+            kernel.evaluate(l, r, out, validity)
+        }
+        (FixedWidthValues::Int64(l), FixedWidthValues::Int64(r), FixedWidthValues::Int64(out)) => {
+            kernel.evaluate(l, r, out, validity)
+        }
+        (
+            FixedWidthValues::Float32(l),
+            FixedWidthValues::Float32(r),
+            FixedWidthValues::Float32(out),
+        ) => kernel.evaluate(l, r, out, validity),
+        (
+            FixedWidthValues::Float64(l),
+            FixedWidthValues::Float64(r),
+            FixedWidthValues::Float64(out),
+        ) => kernel.evaluate(l, r, out, validity),
+        _ => unreachable!(),
+    }
+}
+
+/// Apply the arithmetic operator of the binary expression to two Arrow arrays of the same
+/// fixed-width numeric type, with its overflow and division by zero modes.
+pub(crate) fn evaluate_arithmetic(
+    binary: &BinaryOperator,
+    left: &dyn Array,
+    right: &dyn Array,
+) -> Result<Box<dyn Array>> {
+    let kernel = Arithmetic {
+        op: binary.op,
+        overflow_mode: binary.overflow_mode,
+        division_by_zero: binary.division_by_zero,
+    };
+    match left.data_type() {
+        ArrowDataType::Int32 => kernel.evaluate_arrays::<Int32Type>(left, right),
+        ArrowDataType::Int64 => kernel.evaluate_arrays::<Int64Type>(left, right),
+        ArrowDataType::Float32 => kernel.evaluate_arrays::<Float32Type>(left, right),
+        ArrowDataType::Float64 => kernel.evaluate_arrays::<Float64Type>(left, right),
+        data_type => bail!("Cannot apply {} to {}", binary.op, data_type),
+    }
+}
+
+/// An arithmetic operator with the overflow and division by zero modes of a [`BinaryExpr`].
+struct Arithmetic {
+    op: Operator,
+    overflow_mode: OverflowMode,
+    division_by_zero: DivisionByZeroMode,
+}
+
+impl Arithmetic {
+    fn evaluate<T: Native>(
+        &self,
+        left: &[T],
+        right: &[T],
+        out: &mut Vec<T>,
+        validity: &mut [bool],
+    ) -> Result<()> {
+        if let Some(op) = T::infallible(self) {
+            out.extend(left.iter().zip(right).map(|(&l, &r)| op(l, r)));
+            return Ok(());
+        }
+        for ((&l, &r), valid) in left.iter().zip(right).zip(validity.iter_mut()) {
+            let value = match *valid {
+                true => T::apply(self, l, r)?,
+                false => Some(T::default()),
+            };
+            match value {
+                Some(value) => out.push(value),
+                None => {
+                    *valid = false;
+                    out.push(T::default());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate the operator over whole arrays with Arrow's `math_op` kernel, or with
+    /// [`try_binary`] when it can fail or return null.
+    fn evaluate_arrays<T>(&self, left: &dyn Array, right: &dyn Array) -> Result<Box<dyn Array>>
+    where
+        T: ArrowNumericType,
+        T::Native: Native,
+    {
+        let (left, right) = (
+            as_primitive_array::<T>(left),
+            as_primitive_array::<T>(right),
+        );
+        Ok(Box::new(match T::Native::infallible(self) {
+            Some(op) => math_op(left, right, op)?,
+            None => try_binary(left, right, |l, r| T::Native::apply(self, l, r))?,
+        }))
+    }
+
+    fn divide_by_zero<T>(&self) -> Result<Option<T>> {
+        match self.division_by_zero {
+            DivisionByZeroMode::Error => bail!("Division by zero"),
+            DivisionByZeroMode::Null => Ok(None),
+        }
+    }
+}
+
+/// Apply `op` to the values of two arrays of the same length where neither is null, like
+/// Arrow's `math_op` kernel, except that `op` can fail or return `None` for null.
+fn try_binary<T, F>(
+    left: &PrimitiveArray<T>,
+    right: &PrimitiveArray<T>,
+    op: F,
+) -> Result<PrimitiveArray<T>>
+where
+    T: ArrowPrimitiveType,
+    F: Fn(T::Native, T::Native) -> Result<Option<T::Native>>,
+{
+    let len = left.len();
+    let nulls = match (
+        left.data_ref().null_buffer(),
+        right.data_ref().null_buffer(),
+    ) {
+        (None, None) => None,
+        (Some(l), None) => Some(l.bit_slice(left.offset(), len)),
+        (None, Some(r)) => Some(r.bit_slice(right.offset(), len)),
+        (Some(l), Some(r)) => Some(buffer_bin_and(l, left.offset(), r, right.offset(), len)),
+    };
+    let mut values = MutableBuffer::new(len * std::mem::size_of::<T::Native>());
+    let mut nulled = vec![];
+    for (i, (&l, &r)) in left.values().iter().zip(right.values()).enumerate() {
+        let valid = nulls
+            .as_ref()
+            .is_none_or(|nulls| bit_util::get_bit(nulls.as_slice(), i));
+        let value = match valid {
+            true => op(l, r)?,
+            false => Some(T::Native::default()),
+        };
+        values.push(value.unwrap_or_else(|| {
+            nulled.push(i);
+            T::Native::default()
+        }));
+    }
+    let nulls = match nulled.is_empty() {
+        true => nulls,
+        false => {
+            let mut nulls = match nulls {
+                Some(nulls) => {
+                    let mut copy = MutableBuffer::new(nulls.len());
+                    copy.extend_from_slice(nulls.as_slice());
+                    copy
+                }
+                None => MutableBuffer::new_null(len).with_bitset(bit_util::ceil(len, 8), true),
+            };
+            for i in nulled {
+                bit_util::unset_bit(nulls.as_slice_mut(), i);
+            }
+            Some(nulls.into())
+        }
+    };
+    let mut data = ArrayData::builder(T::DATA_TYPE)
+        .len(len)
+        .add_buffer(values.into());
+    if let Some(nulls) = nulls {
+        data = data.null_bit_buffer(nulls);
+    }
+    Ok(PrimitiveArray::from(data.build()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        data_types::{
+            arrow_field_array::ArrowFieldArray,
+            column_array::ArrayRef,
+            schema::{Field, Schema},
+        },
+        physical_plan::expr::PhysicalExpr,
+        test_util::count_allocations,
+    };
+
+    use arrow::array::{Float64Array, Int32Array};
+
+    fn batch(ints: Vec<Option<i32>>, floats: Vec<f64>) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("i".to_string(), DataType::Int32),
+            Field::new("f".to_string(), DataType::Float64),
+        ]);
+        RecordBatch::new(
+            schema,
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(Int32Array::from(ints)))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(Float64Array::from(floats)))) as ArrayRef,
+            ],
+        )
+    }
+
+    fn binary(op: Operator, left: Expr, right: Expr) -> Expr {
+        Expr::BinaryExpr(BinaryExpr::new(op, left, right))
+    }
+
+    /// (CAST(#0 AS Int64) * 3 + 1, #1 / CAST(#0 AS Float64))
+    fn exprs() -> Vec<Expr> {
+        let int64 = Expr::Cast(Cast::new(Expr::Column(Column::new(0)), DataType::Int64));
+        let float64 = Expr::Cast(Cast::new(Expr::Column(Column::new(0)), DataType::Float64));
+        vec![
+            binary(
+                Operator::Add,
+                binary(
+                    Operator::Multiply,
+                    int64,
+                    Expr::Literal(ScalarValue::Int64(3)),
+                ),
+                Expr::Literal(ScalarValue::Int64(1)),
+            ),
+            binary(Operator::Divide, Expr::Column(Column::new(1)), float64),
+        ]
+    }
+
+    #[test]
+    fn test_evaluate_fixed_width() {
+        let input = batch(vec![Some(1), None, Some(4)], vec![1.0, 2.0, 3.0]);
+        let exprs = exprs();
+        let mut buffers = FixedWidthBuffers::default();
+        evaluate_fixed_width(&exprs[0], &input, &mut buffers).unwrap();
+        assert_eq!(buffers.validity(), &[true, false, true]);
+        match buffers.values() {
+            FixedWidthValues::Int64(v) => assert_eq!((v[0], v[2]), (4, 13)),
+            values => panic!("unexpected {:?}", values),
+        }
+
+        // The results match the boxed evaluation.
+        for expr in &exprs {
+            evaluate_fixed_width(expr, &input, &mut buffers).unwrap();
+            let expected = expr.evaluate(&input).unwrap();
+            for (i, valid) in buffers.validity().iter().enumerate() {
+                assert_eq!(*valid, !expected.is_null(i));
+            }
+        }
+        match buffers.values() {
+            FixedWidthValues::Float64(v) => assert_eq!(v[2], 0.75),
+            values => panic!("unexpected {:?}", values),
+        }
+
+        let overflow = binary(
+            Operator::Multiply,
+            Expr::Column(Column::new(0)),
+            Expr::Literal(ScalarValue::Int32(i32::MAX)),
+        );
+        assert_eq!(
+            evaluate_fixed_width(&overflow, &input, &mut buffers)
+                .unwrap_err()
+                .to_string(),
+            "Int32 overflow in 4 * 2147483647"
+        );
+        let comparison = binary(
+            Operator::Lt,
+            Expr::Column(Column::new(0)),
+            Expr::Column(Column::new(0)),
+        );
+        assert!(evaluate_fixed_width(&comparison, &input, &mut buffers).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_fixed_width_without_allocations() {
+        let exprs = exprs();
+        let mut buffers = exprs
+            .iter()
+            .map(|_| FixedWidthBuffers::default())
+            .collect::<Vec<_>>();
+        let batches = (0..3)
+            .map(|b| {
+                batch(
+                    (0..1024).map(|i| (i % 7 != 0).then(|| i + b)).collect(),
+                    (0..1024).map(|i| i as f64).collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut evaluate = |input: &RecordBatch| {
+            for (expr, buffers) in exprs.iter().zip(buffers.iter_mut()) {
+                evaluate_fixed_width(expr, input, buffers).unwrap();
+            }
+        };
+        // The first batch sizes the buffers.
+        let ((), warmup) = count_allocations(|| evaluate(&batches[0]));
+        assert!(warmup > 0);
+        for input in &batches[1..] {
+            let ((), allocations) = count_allocations(|| evaluate(input));
+            assert_eq!(allocations, 0);
+        }
+    }
+    #[test]
+    fn test_evaluate_arithmetic() {
+        let operator = |op, overflow_mode, division_by_zero| BinaryOperator {
+            op,
+            overflow_mode,
+            division_by_zero,
+        };
+        let values = |array: Box<dyn Array>| {
+            as_primitive_array::<Int32Type>(array.as_ref())
+                .iter()
+                .collect::<Vec<_>>()
+        };
+        let min = Int32Array::from(vec![i32::MIN, i32::MIN]);
+        let minus_one = Int32Array::from(vec![-1, 1]);
+        let divide = |mode| {
+            let divide = operator(Operator::Divide, mode, DivisionByZeroMode::Error);
+            evaluate_arithmetic(&divide, &min, &minus_one)
+        };
+        assert_eq!(
+            divide(OverflowMode::Checked).unwrap_err().to_string(),
+            "Int32 overflow in -2147483648 / -1"
+        );
+        assert_eq!(
+            values(divide(OverflowMode::Wrapping).unwrap()),
+            vec![Some(i32::MIN), Some(i32::MIN)]
+        );
+        assert_eq!(
+            values(divide(OverflowMode::Saturating).unwrap()),
+            vec![Some(i32::MAX), Some(i32::MIN)]
+        );
+        let modulus = operator(
+            Operator::Modulus,
+            OverflowMode::Checked,
+            DivisionByZeroMode::Error,
+        );
+        assert_eq!(
+            values(evaluate_arithmetic(&modulus, &min, &minus_one).unwrap()),
+            vec![Some(0), Some(0)]
+        );
+
+        // Nulls of sliced arrays are combined at their offsets, and division by zero
+        // returns null in the Null mode.
+        let left = Int32Array::from(vec![Some(9), None, Some(8), Some(7), Some(6)]);
+        let right = Int32Array::from(vec![None, Some(2), Some(0), Some(2), Some(3)]);
+        let (left, right) = (left.slice(1, 4), right.slice(0, 4));
+        let divide = operator(
+            Operator::Divide,
+            OverflowMode::Checked,
+            DivisionByZeroMode::Null,
+        );
+        assert_eq!(
+            values(evaluate_arithmetic(&divide, left.as_ref(), right.as_ref()).unwrap()),
+            vec![None, Some(4), None, Some(3)]
+        );
+        let divide = operator(
+            Operator::Divide,
+            OverflowMode::Checked,
+            DivisionByZeroMode::Error,
+        );
+        assert!(evaluate_arithmetic(&divide, left.as_ref(), right.as_ref()).is_err());
+        let add = operator(
+            Operator::Add,
+            OverflowMode::Wrapping,
+            DivisionByZeroMode::Error,
+        );
+        assert_eq!(
+            values(evaluate_arithmetic(&add, left.as_ref(), right.as_ref()).unwrap()),
+            vec![None, Some(10), Some(7), Some(8)]
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+
+use super::{
+    aggregate::AggregateExpr,
+    distinct_on::DistinctOnExec,
+    expr::Expr,
+    fill::FillExec,
+    gap_fill::GapFillExec,
+    hash::HashExec,
+    hash_join::HashJoinExec,
+    limit::LimitExec,
+    memory::MemoryBudget,
+    pipeline::{MorselScheduler, PipelineExec},
+    plan::Plan,
+    projection::ProjectionExec,
+    scan::ScanExec,
+    selection::SelectionExec,
+    selection_projection::SelectionProjectionExec,
+    sessionize::SessionizeExec,
+    sort::{SortExec, SortExpr},
+    window::{WindowExec, WindowExpr},
+};
+use crate::{data_source::Source, data_types::schema::Schema, logical_plan::fill::FillDirection};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// A physical plan without the state of its execution, which can be serialized and shipped
+/// to another process to be executed there.
+/// Memory budgets, metrics and batch checks belong to the process executing the plan, so
+/// they are not part of the fragment, and plans that scan memory tables or have
+/// placeholders cannot be serialized.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum PlanFragment {
+    Scan {
+        data_source: Source,
+        projection: Vec<String>,
+    },
+    Projection {
+        input: Box<PlanFragment>,
+        schema: Schema,
+        expr: Vec<Expr>,
+    },
+    Selection {
+        input: Box<PlanFragment>,
+        expr: Expr,
+    },
+    /// The projection refers to the columns of the input, as it does before it is fused.
+    SelectionProjection {
+        input: Box<PlanFragment>,
+        predicate: Expr,
+        schema: Schema,
+        expr: Vec<Expr>,
+    },
+    Hash {
+        input: Box<PlanFragment>,
+        schema: Schema,
+        group_expr: Vec<Expr>,
+        aggregate_expr: Vec<AggregateExpr>,
+    },
+    HashJoin {
+        left: Box<PlanFragment>,
+        right: Box<PlanFragment>,
+        on: Vec<(Expr, Expr)>,
+        schema: Schema,
+    },
+    Sessionize {
+        input: Box<PlanFragment>,
+        schema: Schema,
+        key: Expr,
+        timestamp: Expr,
+        gap: i64,
+    },
+    GapFill {
+        input: Box<PlanFragment>,
+        every: i64,
+    },
+    DistinctOn {
+        input: Box<PlanFragment>,
+        keys: Vec<Expr>,
+        order_by: Vec<SortExpr>,
+    },
+    Fill {
+        input: Box<PlanFragment>,
+        columns: Vec<usize>,
+        order_by: Vec<Expr>,
+        direction: FillDirection,
+    },
+    Window {
+        input: Box<PlanFragment>,
+        schema: Schema,
+        window_exprs: Vec<WindowExpr>,
+    },
+    Sort {
+        input: Box<PlanFragment>,
+        order_by: Vec<SortExpr>,
+    },
+    Limit {
+        input: Box<PlanFragment>,
+        skip: usize,
+        fetch: Option<usize>,
+    },
+    /// The input is executed morsel by morsel on the thread pool of the executing process,
+    /// if it has one.
+    Pipeline { input: Box<PlanFragment> },
+}
+
+impl PlanFragment {
+    /// Describe the physical plan as a fragment.
+    pub fn try_new(plan: &Plan) -> Result<Self> {
+        let input = |plan: &Plan| PlanFragment::try_new(plan).map(Box::new);
+        Ok(match plan {
+            Plan::Scan(scan) => PlanFragment::Scan {
+                data_source: scan.data_source.clone(),
+                projection: scan.projection.clone(),
+            },
+            Plan::Projection(p) => PlanFragment::Projection {
+                input: input(&p.input)?,
+                schema: p.schema.clone(),
+                expr: p.expr.clone(),
+            },
+            Plan::Selection(s) => PlanFragment::Selection {
+                input: input(&s.input)?,
+                expr: s.expr.clone(),
+            },
+            Plan::SelectionProjection(fused) => {
+                // The projection reads the filtered columns, which are mapped back to
+                // the input columns they come from.
+                let map = fused
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &column)| (i, column))
+                    .collect::<HashMap<_, _>>();
+                PlanFragment::SelectionProjection {
+                    input: input(&fused.input)?,
+                    predicate: fused.predicate.clone(),
+                    schema: fused.schema.clone(),
+                    expr: fused
+                        .expr
+                        .iter()
+                        .map(|e| e.clone().remap_columns(&map))
+                        .collect::<Result<Vec<_>>>()?,
+                }
+            }
+            Plan::Hash(hash) => PlanFragment::Hash {
+                input: input(&hash.input)?,
+                schema: hash.schema.clone(),
+                group_expr: hash.group_expr.clone(),
+                aggregate_expr: hash.aggregate_expr.clone(),
+            },
+            Plan::HashJoin(j) => PlanFragment::HashJoin {
+                left: input(&j.left)?,
+                right: input(&j.right)?,
+                on: j.on.clone(),
+                schema: j.schema.clone(),
+            },
+            Plan::Sessionize(s) => PlanFragment::Sessionize {
+                input: input(&s.input)?,
+                schema: s.schema.clone(),
+                key: s.key.clone(),
+                timestamp: s.timestamp.clone(),
+                gap: s.gap,
+            },
+            Plan::GapFill(g) => PlanFragment::GapFill {
+                input: input(&g.input)?,
+                every: g.every,
+            },
+            Plan::DistinctOn(d) => PlanFragment::DistinctOn {
+                input: input(&d.input)?,
+                keys: d.keys.clone(),
+                order_by: d.order_by.clone(),
+            },
+            Plan::Fill(f) => PlanFragment::Fill {
+                input: input(&f.input)?,
+                columns: f.columns.clone(),
+                order_by: f.order_by.clone(),
+                direction: f.direction,
+            },
+            Plan::Window(w) => PlanFragment::Window {
+                input: input(&w.input)?,
+                schema: w.schema.clone(),
+                window_exprs: w.window_exprs.clone(),
+            },
+            Plan::Sort(s) => PlanFragment::Sort {
+                input: input(&s.input)?,
+                order_by: s.order_by.clone(),
+            },
+            Plan::Limit(l) => PlanFragment::Limit {
+                input: input(&l.input)?,
+                skip: l.skip,
+                fetch: l.fetch,
+            },
+            Plan::Pipeline(p) => PlanFragment::Pipeline {
+                input: input(&p.input)?,
+            },
+            Plan::Checked(checked) => return PlanFragment::try_new(checked.input()),
+            Plan::Metrics(metrics) => return PlanFragment::try_new(metrics.input()),
+            Plan::Observed(observed) => return PlanFragment::try_new(observed.input()),
+        })
+    }
+
+    /// Serialize the fragment to JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Deserialize a fragment serialized by `to_json`.
+    pub fn from_json(json: &str) -> Result<PlanFragment> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Create the physical plan of the fragment, whose operators reserve from the memory
+    /// budget. Pipelines run on the scheduler, and their input on its own without one.
+    pub fn create_physical_plan(
+        &self,
+        memory_budget: &MemoryBudget,
+        scheduler: Option<&MorselScheduler>,
+    ) -> Result<Plan> {
+        let create = |input: &PlanFragment| input.create_physical_plan(memory_budget, scheduler);
+        Ok(match self {
+            PlanFragment::Scan {
+                data_source,
+                projection,
+            } => Plan::Scan(ScanExec::new(data_source.clone(), projection.clone())),
+            PlanFragment::Projection {
+                input,
+                schema,
+                expr,
+            } => Plan::Projection(ProjectionExec::new(
+                create(input)?,
+                schema.clone(),
+                expr.clone(),
+            )),
+            PlanFragment::Selection { input, expr } => {
+                Plan::Selection(SelectionExec::new(create(input)?, expr.clone()))
+            }
+            PlanFragment::SelectionProjection {
+                input,
+                predicate,
+                schema,
+                expr,
+            } => Plan::SelectionProjection(SelectionProjectionExec::try_new(
+                create(input)?,
+                predicate.clone(),
+                schema.clone(),
+                expr.clone(),
+            )?),
+            PlanFragment::Hash {
+                input,
+                schema,
+                group_expr,
+                aggregate_expr,
+            } => Plan::Hash(
+                HashExec::new(
+                    create(input)?,
+                    schema.clone(),
+                    group_expr.clone(),
+                    aggregate_expr.clone(),
+                )
+                .with_memory_budget(memory_budget.clone()),
+            ),
+            PlanFragment::HashJoin {
+                left,
+                right,
+                on,
+                schema,
+            } => Plan::HashJoin(
+                HashJoinExec::new(create(left)?, create(right)?, on.clone(), schema.clone())
+                    .with_memory_budget(memory_budget.clone()),
+            ),
+            PlanFragment::Sessionize {
+                input,
+                schema,
+                key,
+                timestamp,
+                gap,
+            } => Plan::Sessionize(
+                SessionizeExec::new(
+                    create(input)?,
+                    schema.clone(),
+                    key.clone(),
+                    timestamp.clone(),
+                    *gap,
+                )
+                .with_memory_budget(memory_budget.clone()),
+            ),
+            PlanFragment::GapFill { input, every } => Plan::GapFill(
+                GapFillExec::new(create(input)?, *every).with_memory_budget(memory_budget.clone()),
+            ),
+            PlanFragment::DistinctOn {
+                input,
+                keys,
+                order_by,
+            } => Plan::DistinctOn(
+                DistinctOnExec::new(create(input)?, keys.clone(), order_by.clone())
+                    .with_memory_budget(memory_budget.clone()),
+            ),
+            PlanFragment::Fill {
+                input,
+                columns,
+                order_by,
+                direction,
+            } => Plan::Fill(
+                FillExec::new(
+                    create(input)?,
+                    columns.clone(),
+                    order_by.clone(),
+                    *direction,
+                )
+                .with_memory_budget(memory_budget.clone()),
+            ),
+            PlanFragment::Window {
+                input,
+                schema,
+                window_exprs,
+            } => Plan::Window(
+                WindowExec::new(create(input)?, schema.clone(), window_exprs.clone())
+                    .with_memory_budget(memory_budget.clone()),
+            ),
+            PlanFragment::Sort { input, order_by } => Plan::Sort(
+                SortExec::new(create(input)?, order_by.clone())
+                    .with_memory_budget(memory_budget.clone()),
+            ),
+            PlanFragment::Limit { input, skip, fetch } => {
+                Plan::Limit(LimitExec::new(create(input)?, *skip, *fetch))
+            }
+            PlanFragment::Pipeline { input } => match scheduler {
+                Some(scheduler) => {
+                    Plan::Pipeline(PipelineExec::new(create(input)?, scheduler.clone()))
+                }
+                None => create(input)?,
+            },
+        })
+    }
+}
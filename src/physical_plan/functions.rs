@@ -0,0 +1,1475 @@
+use std::{any::Any, fmt::Display, sync::Arc};
+
+use super::expr::{
+    evaluate_from_nullable_values, to_arrow_array, Expr, OverflowMode, PhysicalExpr, ScalarValue,
+};
+use super::fuzzy::{jaro_winkler, levenshtein, soundex};
+use super::url::{url_host, url_path, url_query_param};
+use crate::data_types::{
+    arrow_field_array::ArrowFieldArray,
+    binary::BinaryEncoding,
+    column_array::{ArrayRef, DataType},
+    inet::{inet_contains, parse_cidr, parse_inet},
+    point::{parse_point, st_distance, st_within_bbox},
+    record_batch::RecordBatch,
+};
+
+use anyhow::{anyhow, bail, Result};
+use arrow::{
+    array::{Array, BooleanArray, Float64Array, Int64Array, PrimitiveArray},
+    compute::kernels::arity::unary,
+    datatypes::{ArrowPrimitiveType, Float32Type, Float64Type, Int32Type, Int64Type},
+};
+use md5::{Digest, Md5};
+use ordered_float::OrderedFloat;
+use regex::Regex;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::Sha256;
+
+/// Built-in scalar functions.
+/// Arguments that must be known at planning time (such as regex patterns)
+/// are prepared once when the function is created.
+#[derive(Clone)]
+pub enum BuiltinScalarFunction {
+    /// Return true if the string matches the pattern.
+    RegexpLike(Regex),
+    /// Return the first capture group of the first match (or the whole match if
+    /// the pattern has no groups), or null if the string does not match.
+    RegexpMatch(Regex),
+    /// Return the given capture group of the first match (group 0 is the whole match),
+    /// or null if the string does not match or the group did not participate.
+    RegexpExtract(Regex, usize),
+    /// Return a list of the given capture group of every match, which is empty if the
+    /// string does not match.
+    RegexpExtractAll(Regex, usize),
+    Abs,
+    Sqrt,
+    Pow,
+    Exp,
+    Ln,
+    Log10,
+    Round,
+    Floor,
+    Ceil,
+    /// Return true if the CIDR block contains the IP address.
+    InetContains,
+    /// Return the edit distance between two strings.
+    Levenshtein,
+    /// Return the Jaro-Winkler similarity of two strings.
+    JaroWinkler,
+    /// Return the Soundex code of a string.
+    Soundex,
+    /// Return the start of the bucket of the given width and origin containing the timestamp.
+    DateBin(i64, i64),
+    /// Return the host of a URL.
+    UrlHost,
+    /// Return the path of a URL.
+    UrlPath,
+    /// Return the value of a query parameter of a URL.
+    UrlQueryParam,
+    /// Return the great-circle distance between two points in meters.
+    StDistance,
+    /// Return true if the point is inside the bounding box given by two corner points.
+    StWithinBbox,
+    /// Return the first argument that is not null.
+    Coalesce,
+    /// Return null if both arguments are equal, and the first argument otherwise.
+    NullIf,
+    /// Return the text form of a binary value in the given encoding.
+    Encode(BinaryEncoding),
+    /// Return the binary value of a text in the given encoding.
+    Decode(BinaryEncoding),
+    /// Return the MD5 digest of a string or binary value, as 32 lowercase hex digits.
+    Md5,
+    /// Return the SHA-256 digest of a string or binary value, as 64 lowercase hex digits.
+    Sha256,
+}
+
+impl BuiltinScalarFunction {
+    fn name(&self) -> &str {
+        match self {
+            BuiltinScalarFunction::RegexpLike(_) => "regexp_like",
+            BuiltinScalarFunction::RegexpMatch(_) => "regexp_match",
+            BuiltinScalarFunction::RegexpExtract(..) => "regexp_extract",
+            BuiltinScalarFunction::RegexpExtractAll(..) => "regexp_extract_all",
+            BuiltinScalarFunction::Abs => "abs",
+            BuiltinScalarFunction::Sqrt => "sqrt",
+            BuiltinScalarFunction::Pow => "pow",
+            BuiltinScalarFunction::Exp => "exp",
+            BuiltinScalarFunction::Ln => "ln",
+            BuiltinScalarFunction::Log10 => "log10",
+            BuiltinScalarFunction::Round => "round",
+            BuiltinScalarFunction::Floor => "floor",
+            BuiltinScalarFunction::Ceil => "ceil",
+            BuiltinScalarFunction::InetContains => "inet_contains",
+            BuiltinScalarFunction::Levenshtein => "levenshtein",
+            BuiltinScalarFunction::JaroWinkler => "jaro_winkler",
+            BuiltinScalarFunction::Soundex => "soundex",
+            BuiltinScalarFunction::DateBin(..) => "date_bin",
+            BuiltinScalarFunction::UrlHost => "url_host",
+            BuiltinScalarFunction::UrlPath => "url_path",
+            BuiltinScalarFunction::UrlQueryParam => "url_query_param",
+            BuiltinScalarFunction::StDistance => "st_distance",
+            BuiltinScalarFunction::StWithinBbox => "st_within_bbox",
+            BuiltinScalarFunction::Coalesce => "coalesce",
+            BuiltinScalarFunction::NullIf => "nullif",
+            BuiltinScalarFunction::Encode(_) => "encode",
+            BuiltinScalarFunction::Decode(_) => "decode",
+            BuiltinScalarFunction::Md5 => "md5",
+            BuiltinScalarFunction::Sha256 => "sha256",
+        }
+    }
+}
+
+/// Physical expression for a call of a built-in scalar function.
+#[derive(Clone)]
+pub struct ScalarFunctionExpr {
+    fun: BuiltinScalarFunction,
+    args: Vec<Expr>,
+    overflow_mode: OverflowMode,
+}
+
+/// A call serialized by the name of its function, which is prepared again from the
+/// arguments when it is deserialized.
+#[derive(Serialize, Deserialize)]
+struct ScalarFunctionCall {
+    name: String,
+    args: Vec<Expr>,
+    #[serde(default)]
+    overflow_mode: OverflowMode,
+}
+
+impl Serialize for ScalarFunctionExpr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let call = ScalarFunctionCall {
+            name: self.fun.name().to_string(),
+            args: self.args.clone(),
+            overflow_mode: self.overflow_mode,
+        };
+        call.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScalarFunctionExpr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let call = ScalarFunctionCall::deserialize(deserializer)?;
+        ScalarFunctionExpr::try_new(&call.name, call.args)
+            .map(|f| f.with_overflow_mode(call.overflow_mode))
+            .map_err(D::Error::custom)
+    }
+}
+
+impl ScalarFunctionExpr {
+    /// Create a scalar function by name.
+    pub fn try_new(name: &str, args: Vec<Expr>) -> Result<Self> {
+        let fun = match name {
+            "regexp_like" => BuiltinScalarFunction::RegexpLike(regex_arg(name, &args)?),
+            "regexp_match" => BuiltinScalarFunction::RegexpMatch(regex_arg(name, &args)?),
+            "regexp_extract" => {
+                let regex = regex_arg(name, &args)?;
+                let group = group_arg(name, &regex, &args)?;
+                BuiltinScalarFunction::RegexpExtract(regex, group)
+            }
+            "regexp_extract_all" => {
+                let regex = regex_arg(name, &args)?;
+                let group = group_arg(name, &regex, &args)?;
+                BuiltinScalarFunction::RegexpExtractAll(regex, group)
+            }
+            "abs" => BuiltinScalarFunction::Abs,
+            "sqrt" => BuiltinScalarFunction::Sqrt,
+            "pow" => BuiltinScalarFunction::Pow,
+            "exp" => BuiltinScalarFunction::Exp,
+            "ln" => BuiltinScalarFunction::Ln,
+            "log10" => BuiltinScalarFunction::Log10,
+            "round" => BuiltinScalarFunction::Round,
+            "floor" => BuiltinScalarFunction::Floor,
+            "ceil" => BuiltinScalarFunction::Ceil,
+            "inet_contains" => BuiltinScalarFunction::InetContains,
+            "levenshtein" => BuiltinScalarFunction::Levenshtein,
+            "jaro_winkler" => BuiltinScalarFunction::JaroWinkler,
+            "soundex" => BuiltinScalarFunction::Soundex,
+            "date_bin" => {
+                let interval = int_literal_arg(name, &args, 0)?;
+                if interval <= 0 {
+                    bail!("{} expects a positive interval, got {}", name, interval);
+                }
+                BuiltinScalarFunction::DateBin(interval, int_literal_arg(name, &args, 2)?)
+            }
+            "url_host" => BuiltinScalarFunction::UrlHost,
+            "url_path" => BuiltinScalarFunction::UrlPath,
+            "url_query_param" => BuiltinScalarFunction::UrlQueryParam,
+            "st_distance" => BuiltinScalarFunction::StDistance,
+            "st_within_bbox" => BuiltinScalarFunction::StWithinBbox,
+            "coalesce" => BuiltinScalarFunction::Coalesce,
+            "nullif" => BuiltinScalarFunction::NullIf,
+            "encode" => BuiltinScalarFunction::Encode(encoding_arg(name, &args)?),
+            "decode" => BuiltinScalarFunction::Decode(encoding_arg(name, &args)?),
+            "md5" => BuiltinScalarFunction::Md5,
+            "sha256" => BuiltinScalarFunction::Sha256,
+            _ => bail!("Unknown scalar function {}", name),
+        };
+        let arity = match fun {
+            BuiltinScalarFunction::Coalesce => {
+                if args.is_empty() {
+                    bail!("{} expects at least one argument", name);
+                }
+                args.len()
+            }
+            BuiltinScalarFunction::RegexpLike(_)
+            | BuiltinScalarFunction::RegexpMatch(_)
+            | BuiltinScalarFunction::Pow
+            | BuiltinScalarFunction::InetContains
+            | BuiltinScalarFunction::Levenshtein
+            | BuiltinScalarFunction::JaroWinkler
+            | BuiltinScalarFunction::UrlQueryParam
+            | BuiltinScalarFunction::StDistance
+            | BuiltinScalarFunction::NullIf
+            | BuiltinScalarFunction::Encode(_)
+            | BuiltinScalarFunction::Decode(_) => 2,
+            BuiltinScalarFunction::StWithinBbox
+            | BuiltinScalarFunction::RegexpExtract(..)
+            | BuiltinScalarFunction::RegexpExtractAll(..)
+            | BuiltinScalarFunction::DateBin(..) => 3,
+            _ => 1,
+        };
+        if args.len() != arity {
+            bail!("{} expects {} arguments, got {}", name, arity, args.len());
+        }
+
+        Ok(Self {
+            fun,
+            args,
+            overflow_mode: OverflowMode::default(),
+        })
+    }
+
+    /// Set what integer functions do when their result overflows.
+    pub fn with_overflow_mode(mut self, overflow_mode: OverflowMode) -> Self {
+        self.overflow_mode = overflow_mode;
+        self
+    }
+
+    pub fn args(&self) -> &[Expr] {
+        &self.args
+    }
+
+    /// Rewrite the arguments of the function, which keeps its compiled state.
+    pub fn map_args(self, f: impl FnMut(Expr) -> Result<Expr>) -> Result<Self> {
+        Ok(Self {
+            fun: self.fun,
+            args: self.args.into_iter().map(f).collect::<Result<_>>()?,
+            overflow_mode: self.overflow_mode,
+        })
+    }
+
+    /// Return the absolute value of an integer, which overflows for the minimum value of
+    /// its type.
+    fn abs<T: Display>(&self, value: T, abs: Option<T>, max: T) -> Result<T> {
+        match (abs, self.overflow_mode) {
+            (Some(abs), _) => Ok(abs),
+            (None, OverflowMode::Checked) => {
+                bail!("Int{} overflow in abs({})", 8 * size_of::<T>(), value)
+            }
+            // The absolute value of the minimum wraps around to itself.
+            (None, OverflowMode::Wrapping) => Ok(value),
+            (None, OverflowMode::Saturating) => Ok(max),
+        }
+    }
+
+    /// Return the start of the bin of a timestamp, which overflows when the timestamp is
+    /// far from the origin.
+    fn date_bin(&self, ts: i64, interval: i64, origin: i64) -> Result<i64> {
+        // The bin is exact in i128, which holds the difference of any two i64.
+        let (ts, interval, origin) = (ts as i128, interval as i128, origin as i128);
+        let bin = origin + (ts - origin).div_euclid(interval) * interval;
+        match (i64::try_from(bin), self.overflow_mode) {
+            (Ok(bin), _) => Ok(bin),
+            (Err(_), OverflowMode::Checked) => {
+                bail!(
+                    "Int64 overflow in date_bin({}, {}, {})",
+                    interval,
+                    ts,
+                    origin
+                )
+            }
+            (Err(_), OverflowMode::Wrapping) => Ok(bin as i64),
+            (Err(_), OverflowMode::Saturating) => {
+                Ok(bin.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+            }
+        }
+    }
+}
+
+/// Compile the pattern of a regex function, which must be a string literal.
+fn regex_arg(name: &str, args: &[Expr]) -> Result<Regex> {
+    match args.get(1) {
+        Some(Expr::Literal(ScalarValue::String(pattern))) => {
+            Regex::new(pattern).map_err(|e| anyhow!("Invalid pattern for {}: {}", name, e))
+        }
+        _ => bail!("{} expects a string and a literal pattern", name),
+    }
+}
+
+/// Return the encoding of an encoding function, which must be a string literal.
+fn encoding_arg(name: &str, args: &[Expr]) -> Result<BinaryEncoding> {
+    match args.get(1) {
+        Some(Expr::Literal(ScalarValue::String(encoding))) => BinaryEncoding::parse(encoding),
+        _ => bail!("{} expects a value and a literal encoding", name),
+    }
+}
+
+/// Return the capture group index of a regex function, which must be an integer literal
+/// naming one of the groups of the pattern.
+fn group_arg(name: &str, regex: &Regex, args: &[Expr]) -> Result<usize> {
+    let group = match args.get(2) {
+        Some(Expr::Literal(ScalarValue::Int32(group))) => *group as i64,
+        Some(Expr::Literal(ScalarValue::Int64(group))) => *group,
+        _ => bail!("{} expects a literal group index", name),
+    };
+    if group < 0 || group as usize >= regex.captures_len() {
+        bail!(
+            "Invalid group {} for {}: the pattern has {} groups",
+            group,
+            name,
+            regex.captures_len() - 1
+        );
+    }
+    Ok(group as usize)
+}
+
+/// Return the values of the array, which must be of the given type, with None for nulls.
+fn typed_values<T: Clone + Any>(array: &ArrayRef, data_type: DataType) -> Result<Vec<Option<T>>> {
+    if array.get_type() != data_type {
+        bail!(
+            "Expected a {} argument, got {}",
+            data_type,
+            array.get_type()
+        )
+    }
+
+    (0..array.size())
+        .map(|i| {
+            if array.is_null(i) {
+                return Ok(None);
+            }
+            Ok(Some(
+                array.get_value(i)?.downcast_ref::<T>().unwrap().clone(),
+            ))
+        })
+        .collect()
+}
+
+/// Return the argument at the given position, which must be an integer literal.
+fn int_literal_arg(name: &str, args: &[Expr], i: usize) -> Result<i64> {
+    match args.get(i) {
+        Some(Expr::Literal(ScalarValue::Int32(v))) => Ok(*v as i64),
+        Some(Expr::Literal(ScalarValue::Int64(v))) => Ok(*v),
+        _ => bail!("{} expects an integer literal as argument {}", name, i + 1),
+    }
+}
+
+/// Return the array as an Arrow array of the primitive type, which must be its type.
+/// Arrays that are not backed by Arrow, such as literals, are built first.
+fn primitive_array<T: ArrowPrimitiveType>(array: &ArrayRef) -> Result<PrimitiveArray<T>> {
+    let array = to_arrow_array(array)?;
+    if array.data_type() != &T::DATA_TYPE {
+        bail!(
+            "Expected a {:?} argument, got {:?}",
+            T::DATA_TYPE,
+            array.data_type()
+        );
+    }
+    Ok(PrimitiveArray::<T>::from(array.data().clone()))
+}
+
+/// Apply a function that may fail to every non-null value of a primitive array.
+fn try_unary<T: ArrowPrimitiveType>(
+    array: &PrimitiveArray<T>,
+    f: impl Fn(T::Native) -> Result<T::Native>,
+) -> Result<PrimitiveArray<T>> {
+    array.iter().map(|v| v.map(&f).transpose()).collect()
+}
+
+/// Wrap the Arrow array as a column array.
+fn into_array_ref(array: impl Array + 'static) -> ArrayRef {
+    Arc::new(ArrowFieldArray::new(Box::new(array)))
+}
+
+/// Return an integer array as an Int64 array, keeping its nulls.
+fn int64_array(array: &ArrayRef) -> Result<Int64Array> {
+    Ok(match array.get_type() {
+        DataType::Int32 => unary(&primitive_array::<Int32Type>(array)?, |v| v as i64),
+        DataType::Int64 => primitive_array::<Int64Type>(array)?,
+        data_type => bail!("Expected an integer argument, got {}", data_type),
+    })
+}
+
+/// Return a numeric array as a Float64 array, keeping its nulls.
+fn float64_array(array: &ArrayRef) -> Result<Float64Array> {
+    Ok(match array.get_type() {
+        DataType::Int32 => unary(&primitive_array::<Int32Type>(array)?, |v| v as f64),
+        DataType::Int64 => unary(&primitive_array::<Int64Type>(array)?, |v| v as f64),
+        DataType::Float32 => unary(&primitive_array::<Float32Type>(array)?, |v| v as f64),
+        DataType::Float64 => primitive_array::<Float64Type>(array)?,
+        data_type => bail!("Expected a numeric argument, got {}", data_type),
+    })
+}
+
+/// Apply a function to every non-null value of a numeric array, keeping the type of the
+/// array. The functions of integers may fail.
+fn map_numeric(
+    array: &ArrayRef,
+    int32_fn: impl Fn(i32) -> Result<i32>,
+    int64_fn: impl Fn(i64) -> Result<i64>,
+    float32_fn: fn(f32) -> f32,
+    float64_fn: fn(f64) -> f64,
+) -> Result<ArrayRef> {
+    Ok(match array.get_type() {
+        DataType::Int32 => {
+            into_array_ref(try_unary(&primitive_array::<Int32Type>(array)?, int32_fn)?)
+        }
+        DataType::Int64 => {
+            into_array_ref(try_unary(&primitive_array::<Int64Type>(array)?, int64_fn)?)
+        }
+        DataType::Float32 => into_array_ref(unary::<_, _, Float32Type>(
+            &primitive_array::<Float32Type>(array)?,
+            float32_fn,
+        )),
+        DataType::Float64 => into_array_ref(unary::<_, _, Float64Type>(
+            &primitive_array::<Float64Type>(array)?,
+            float64_fn,
+        )),
+        data_type => bail!("Expected a numeric argument, got {}", data_type),
+    })
+}
+
+/// Apply a function to every non-null value of a string array, returning an array of the
+/// given type that is also null where the function returns None.
+fn map_utf8<T: Any>(
+    array: &ArrayRef,
+    data_type: DataType,
+    f: impl Fn(&str) -> Result<Option<T>>,
+) -> Result<ArrayRef> {
+    let values = typed_values::<String>(array, DataType::Utf8)?
+        .iter()
+        .map(|s| match s {
+            Some(s) => Ok(f(s)?.map(|v| Box::new(v) as Box<dyn Any>)),
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    evaluate_from_nullable_values(&values, &data_type)
+}
+
+/// Apply a function to the values of two string arrays, returning an array of the given
+/// type that is null where either value is null.
+fn map_utf8_pairs<T: Any>(
+    left: &ArrayRef,
+    right: &ArrayRef,
+    data_type: DataType,
+    f: impl Fn(&str, &str) -> Result<T>,
+) -> Result<ArrayRef> {
+    let left = typed_values::<String>(left, DataType::Utf8)?;
+    let right = typed_values::<String>(right, DataType::Utf8)?;
+    let values = left
+        .iter()
+        .zip(&right)
+        .map(|(l, r)| {
+            l.as_deref()
+                .zip(r.as_deref())
+                .map(|(l, r)| f(l, r).map(|v| Box::new(v) as Box<dyn Any>))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    evaluate_from_nullable_values(&values, &data_type)
+}
+
+/// Return the bytes of the values of a string or binary array, or None for nulls.
+fn bytes_values(array: &ArrayRef) -> Result<Vec<Option<Vec<u8>>>> {
+    (0..array.size())
+        .map(|i| {
+            if array.is_null(i) {
+                return Ok(None);
+            }
+            let value = array.get_value(i)?;
+            Ok(Some(match array.get_type() {
+                DataType::Utf8 => value.downcast_ref::<String>().unwrap().as_bytes().to_vec(),
+                DataType::Binary => value.downcast_ref::<Vec<u8>>().unwrap().clone(),
+                data_type => bail!("Expected a Utf8 or Binary argument, got {}", data_type),
+            }))
+        })
+        .collect()
+}
+
+/// Apply a function to every non-null value of a string or binary array.
+fn map_bytes<T: Any>(
+    array: &ArrayRef,
+    data_type: DataType,
+    f: impl Fn(&[u8]) -> Result<T>,
+) -> Result<ArrayRef> {
+    let values = bytes_values(array)?
+        .into_iter()
+        .map(|v| {
+            v.map(|v| f(&v).map(|v| Box::new(v) as Box<dyn Any>))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    evaluate_from_nullable_values(&values, &data_type)
+}
+
+/// Return the values of an array of points as (lat, lon) pairs, with None for nulls.
+fn point_values(array: &ArrayRef) -> Result<Vec<Option<(f64, f64)>>> {
+    typed_values::<String>(array, DataType::Utf8)?
+        .iter()
+        .map(|s| s.as_deref().map(parse_point).transpose())
+        .collect()
+}
+
+/// Evaluate the arguments, which must all have the same type.
+fn same_type_args(name: &str, args: &[Expr], input: &RecordBatch) -> Result<Vec<ArrayRef>> {
+    let arrays = args
+        .iter()
+        .map(|arg| arg.evaluate(input))
+        .collect::<Result<Vec<_>>>()?;
+    let data_type = arrays[0].get_type();
+    if let Some(other) = arrays.iter().find(|a| a.get_type() != data_type) {
+        bail!(
+            "{} arguments must have the same type, got {} and {}",
+            name,
+            data_type,
+            other.get_type()
+        );
+    }
+    Ok(arrays)
+}
+
+/// Apply a function to every non-null value of a numeric array, returning a Float64 array.
+fn map_float64(array: &ArrayRef, f: fn(f64) -> f64) -> Result<ArrayRef> {
+    Ok(into_array_ref(unary::<_, _, Float64Type>(
+        &float64_array(array)?,
+        f,
+    )))
+}
+
+impl PhysicalExpr for ScalarFunctionExpr {
+    fn evaluate(&self, input: &RecordBatch) -> Result<ArrayRef> {
+        match &self.fun {
+            BuiltinScalarFunction::RegexpLike(regex) => {
+                map_utf8(&self.args[0].evaluate(input)?, DataType::Boolean, |s| {
+                    Ok(Some(regex.is_match(s)))
+                })
+            }
+            BuiltinScalarFunction::RegexpMatch(regex) => {
+                map_utf8(&self.args[0].evaluate(input)?, DataType::Utf8, |s| {
+                    Ok(regex
+                        .captures(s)
+                        .and_then(|c| c.get(1).or_else(|| c.get(0)))
+                        .map(|m| m.as_str().to_string()))
+                })
+            }
+            BuiltinScalarFunction::RegexpExtract(regex, group) => {
+                map_utf8(&self.args[0].evaluate(input)?, DataType::Utf8, |s| {
+                    Ok(regex
+                        .captures(s)
+                        .and_then(|c| c.get(*group))
+                        .map(|m| m.as_str().to_string()))
+                })
+            }
+            BuiltinScalarFunction::RegexpExtractAll(regex, group) => {
+                let data_type = DataType::List(Box::new(DataType::Utf8));
+                map_utf8(&self.args[0].evaluate(input)?, data_type, |s| {
+                    Ok(Some(
+                        regex
+                            .captures_iter(s)
+                            .map(|c| {
+                                c.get(*group)
+                                    .map(|m| Box::new(m.as_str().to_string()) as Box<dyn Any>)
+                            })
+                            .collect::<Vec<_>>(),
+                    ))
+                })
+            }
+            BuiltinScalarFunction::Abs => map_numeric(
+                &self.args[0].evaluate(input)?,
+                |v| self.abs(v, v.checked_abs(), i32::MAX),
+                |v| self.abs(v, v.checked_abs(), i64::MAX),
+                f32::abs,
+                f64::abs,
+            ),
+            BuiltinScalarFunction::Round => map_numeric(
+                &self.args[0].evaluate(input)?,
+                Ok,
+                Ok,
+                f32::round,
+                f64::round,
+            ),
+            BuiltinScalarFunction::Floor => map_numeric(
+                &self.args[0].evaluate(input)?,
+                Ok,
+                Ok,
+                f32::floor,
+                f64::floor,
+            ),
+            BuiltinScalarFunction::Ceil => {
+                map_numeric(&self.args[0].evaluate(input)?, Ok, Ok, f32::ceil, f64::ceil)
+            }
+            BuiltinScalarFunction::Sqrt => map_float64(&self.args[0].evaluate(input)?, f64::sqrt),
+            BuiltinScalarFunction::Exp => map_float64(&self.args[0].evaluate(input)?, f64::exp),
+            BuiltinScalarFunction::Ln => map_float64(&self.args[0].evaluate(input)?, f64::ln),
+            BuiltinScalarFunction::Log10 => map_float64(&self.args[0].evaluate(input)?, f64::log10),
+            BuiltinScalarFunction::Pow => {
+                let base = float64_array(&self.args[0].evaluate(input)?)?;
+                let exponent = float64_array(&self.args[1].evaluate(input)?)?;
+                let array = base
+                    .iter()
+                    .zip(exponent.iter())
+                    .map(|(b, e)| Some(b?.powf(e?)))
+                    .collect::<Float64Array>();
+                Ok(into_array_ref(array))
+            }
+            BuiltinScalarFunction::InetContains => map_utf8_pairs(
+                &self.args[0].evaluate(input)?,
+                &self.args[1].evaluate(input)?,
+                DataType::Boolean,
+                |cidr, addr| Ok(inet_contains(parse_cidr(cidr)?, parse_inet(addr)?)),
+            ),
+            BuiltinScalarFunction::Levenshtein => map_utf8_pairs(
+                &self.args[0].evaluate(input)?,
+                &self.args[1].evaluate(input)?,
+                DataType::Int32,
+                |a, b| Ok(levenshtein(a, b) as i32),
+            ),
+            BuiltinScalarFunction::JaroWinkler => map_utf8_pairs(
+                &self.args[0].evaluate(input)?,
+                &self.args[1].evaluate(input)?,
+                DataType::Float64,
+                |a, b| Ok(jaro_winkler(a, b)),
+            ),
+            BuiltinScalarFunction::Soundex => {
+                map_utf8(&self.args[0].evaluate(input)?, DataType::Utf8, |s| {
+                    Ok(Some(soundex(s)))
+                })
+            }
+            BuiltinScalarFunction::DateBin(interval, origin) => {
+                let timestamps = int64_array(&self.args[1].evaluate(input)?)?;
+                let array = try_unary(&timestamps, |ts| self.date_bin(ts, *interval, *origin))?;
+                Ok(into_array_ref(array))
+            }
+            BuiltinScalarFunction::UrlHost => {
+                map_utf8(&self.args[0].evaluate(input)?, DataType::Utf8, |s| {
+                    Ok(Some(url_host(s)))
+                })
+            }
+            BuiltinScalarFunction::UrlPath => {
+                map_utf8(&self.args[0].evaluate(input)?, DataType::Utf8, |s| {
+                    Ok(Some(url_path(s)))
+                })
+            }
+            BuiltinScalarFunction::UrlQueryParam => map_utf8_pairs(
+                &self.args[0].evaluate(input)?,
+                &self.args[1].evaluate(input)?,
+                DataType::Utf8,
+                |url, name| Ok(url_query_param(url, name)),
+            ),
+            BuiltinScalarFunction::StDistance => {
+                let a = point_values(&self.args[0].evaluate(input)?)?;
+                let b = point_values(&self.args[1].evaluate(input)?)?;
+                let array = a
+                    .into_iter()
+                    .zip(b)
+                    .map(|(a, b)| Some(st_distance(a?, b?)))
+                    .collect::<Float64Array>();
+                Ok(Arc::new(ArrowFieldArray::new(Box::new(array))))
+            }
+            BuiltinScalarFunction::StWithinBbox => {
+                let points = point_values(&self.args[0].evaluate(input)?)?;
+                let south_west = point_values(&self.args[1].evaluate(input)?)?;
+                let north_east = point_values(&self.args[2].evaluate(input)?)?;
+                let array = points
+                    .into_iter()
+                    .zip(south_west.into_iter().zip(north_east))
+                    .map(|(p, (sw, ne))| Some(st_within_bbox(p?, sw?, ne?)))
+                    .collect::<BooleanArray>();
+                Ok(Arc::new(ArrowFieldArray::new(Box::new(array))))
+            }
+            BuiltinScalarFunction::Coalesce => {
+                let arrays = same_type_args(self.fun.name(), &self.args, input)?;
+                let values = (0..input.row_count())
+                    .map(|i| {
+                        arrays
+                            .iter()
+                            .find(|a| !a.is_null(i))
+                            .map(|a| a.get_value(i))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                evaluate_from_nullable_values(&values, &arrays[0].get_type())
+            }
+            BuiltinScalarFunction::NullIf => {
+                let arrays = same_type_args(self.fun.name(), &self.args, input)?;
+                let (left, right) = (&arrays[0], &arrays[1]);
+                let data_type = left.get_type();
+                let values = (0..input.row_count())
+                    .map(|i| {
+                        if left.is_null(i) {
+                            return Ok(None);
+                        }
+                        let l = left.get_value(i)?;
+                        if !right.is_null(i) {
+                            let r = right.get_value(i)?;
+                            let equal = crate::bool_binary_op!(&l, &r, &data_type, eq);
+                            if *equal.downcast_ref::<bool>().unwrap() {
+                                return Ok(None);
+                            }
+                        }
+                        Ok(Some(l))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                evaluate_from_nullable_values(&values, &data_type)
+            }
+            BuiltinScalarFunction::Encode(encoding) => {
+                let array = self.args[0].evaluate(input)?;
+                if array.get_type() != DataType::Binary {
+                    bail!("encode expects a Binary argument, got {}", array.get_type());
+                }
+                map_bytes(&array, DataType::Utf8, |v| Ok(encoding.encode(v)))
+            }
+            BuiltinScalarFunction::Decode(encoding) => {
+                let array = self.args[0].evaluate(input)?;
+                if array.get_type() != DataType::Utf8 {
+                    bail!("decode expects a Utf8 argument, got {}", array.get_type());
+                }
+                map_bytes(&array, DataType::Binary, |v| {
+                    encoding.decode(std::str::from_utf8(v).unwrap())
+                })
+            }
+            BuiltinScalarFunction::Md5 => {
+                map_bytes(&self.args[0].evaluate(input)?, DataType::Utf8, |v| {
+                    Ok(hex::encode(Md5::digest(v)))
+                })
+            }
+            BuiltinScalarFunction::Sha256 => {
+                map_bytes(&self.args[0].evaluate(input)?, DataType::Utf8, |v| {
+                    Ok(hex::encode(Sha256::digest(v)))
+                })
+            }
+        }
+    }
+}
+
+impl Display for ScalarFunctionExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}({})",
+            self.fun.name(),
+            self.args
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        data_types::{
+            arrow_field_array::ArrowFieldArray,
+            schema::{Field, Schema},
+        },
+        physical_plan::expr::Column,
+    };
+
+    use arrow::array::{Int32Array, StringArray};
+
+    fn numeric_input() -> RecordBatch {
+        let i = Int32Array::from(vec![-4, 9]);
+        let f = Float64Array::from(vec![-1.5, 2.4]);
+        let schema = Schema::new(vec![
+            Field::new("i".to_string(), DataType::Int32),
+            Field::new("f".to_string(), DataType::Float64),
+        ]);
+        RecordBatch::new(
+            schema,
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(i))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(f))) as ArrayRef,
+            ],
+        )
+    }
+
+    fn math(name: &str, args: Vec<Expr>) -> ArrayRef {
+        ScalarFunctionExpr::try_new(name, args)
+            .unwrap()
+            .evaluate(&numeric_input())
+            .unwrap()
+    }
+
+    fn values<T: Clone + Any>(array: &ArrayRef) -> Vec<T> {
+        typed_values::<T>(array, array.get_type())
+            .unwrap()
+            .into_iter()
+            .map(Option::unwrap)
+            .collect()
+    }
+
+    fn input() -> RecordBatch {
+        let s = StringArray::from(vec!["user=alice id=1", "user=bob", "anonymous"]);
+        let schema = Schema::new(vec![Field::new("s".to_string(), DataType::Utf8)]);
+        RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(s))) as ArrayRef],
+        )
+    }
+
+    fn regexp(name: &str, pattern: &str) -> Result<ScalarFunctionExpr> {
+        ScalarFunctionExpr::try_new(
+            name,
+            vec![
+                Expr::Column(Column::new(0)),
+                Expr::Literal(ScalarValue::String(pattern.to_string())),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_regexp_like() {
+        let result = regexp("regexp_like", "^user=")
+            .unwrap()
+            .evaluate(&input())
+            .unwrap();
+        assert_eq!(result.get_type(), DataType::Boolean);
+        let values = (0..result.size())
+            .map(|i| *result.get_value(i).unwrap().downcast_ref::<bool>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![true, true, false]);
+    }
+
+    #[test]
+    fn test_regexp_match() {
+        let result = regexp("regexp_match", "user=(\\w+)")
+            .unwrap()
+            .evaluate(&input())
+            .unwrap();
+        assert_eq!(result.get_type(), DataType::Utf8);
+        let values = typed_values::<String>(&result, DataType::Utf8).unwrap();
+        assert_eq!(
+            values,
+            vec![Some("alice".to_string()), Some("bob".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(regexp("regexp_like", "(").is_err());
+        assert!(
+            ScalarFunctionExpr::try_new("regexp_like", vec![Expr::Column(Column::new(0))]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            regexp("regexp_like", "^a").unwrap().to_string(),
+            "regexp_like(#0, '^a')"
+        );
+    }
+
+    #[test]
+    fn test_abs() {
+        let result = math("abs", vec![Expr::Column(Column::new(0))]);
+        assert_eq!(result.get_type(), DataType::Int32);
+        assert_eq!(values::<i32>(&result), vec![4, 9]);
+        let result = math("abs", vec![Expr::Column(Column::new(1))]);
+        assert_eq!(result.get_type(), DataType::Float64);
+        assert_eq!(values::<f64>(&result), vec![1.5, 2.4]);
+    }
+
+    #[test]
+    fn test_abs_overflow_modes() {
+        let abs = |value, mode| {
+            ScalarFunctionExpr::try_new("abs", vec![Expr::Literal(value)])
+                .unwrap()
+                .with_overflow_mode(mode)
+                .evaluate(&numeric_input())
+        };
+        assert_eq!(
+            abs(ScalarValue::Int32(i32::MIN), OverflowMode::Checked)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Int32 overflow in abs(-2147483648)"
+        );
+        let result = abs(ScalarValue::Int32(i32::MIN), OverflowMode::Wrapping).unwrap();
+        assert_eq!(values::<i32>(&result), vec![i32::MIN, i32::MIN]);
+        let result = abs(ScalarValue::Int64(i64::MIN), OverflowMode::Saturating).unwrap();
+        assert_eq!(values::<i64>(&result), vec![i64::MAX, i64::MAX]);
+        assert!(abs(ScalarValue::Int64(i64::MIN), OverflowMode::Checked).is_err());
+    }
+
+    #[test]
+    fn test_rounding() {
+        let f = || vec![Expr::Column(Column::new(1))];
+        assert_eq!(values::<f64>(&math("round", f())), vec![-2.0, 2.0]);
+        assert_eq!(values::<f64>(&math("floor", f())), vec![-2.0, 2.0]);
+        assert_eq!(values::<f64>(&math("ceil", f())), vec![-1.0, 3.0]);
+        let result = math("round", vec![Expr::Column(Column::new(0))]);
+        assert_eq!(result.get_type(), DataType::Int32);
+        assert_eq!(values::<i32>(&result), vec![-4, 9]);
+    }
+
+    #[test]
+    fn test_float64_functions() {
+        let i = || vec![Expr::Column(Column::new(0))];
+        let result = math("sqrt", i());
+        assert_eq!(result.get_type(), DataType::Float64);
+        assert!(values::<f64>(&result)[0].is_nan());
+        assert_eq!(values::<f64>(&result)[1], 3.0);
+        assert_eq!(
+            values::<f64>(&math("exp", vec![Expr::Literal(ScalarValue::Int32(0))])),
+            vec![1.0, 1.0]
+        );
+        assert_eq!(
+            values::<f64>(&math("ln", vec![Expr::Literal(ScalarValue::Float64(1.0))])),
+            vec![0.0, 0.0]
+        );
+        assert_eq!(
+            values::<f64>(&math("log10", vec![Expr::Literal(ScalarValue::Int64(100))])),
+            vec![2.0, 2.0]
+        );
+        let result = math(
+            "pow",
+            vec![
+                Expr::Column(Column::new(0)),
+                Expr::Literal(ScalarValue::Int32(2)),
+            ],
+        );
+        assert_eq!(values::<f64>(&result), vec![16.0, 81.0]);
+    }
+
+    #[test]
+    fn test_math_functions_keep_nulls() {
+        // Every third value is null, and the arrays are sliced, so the values and the null
+        // bitmaps start at an offset.
+        let len = 10_000;
+        let ints = (0..len + 3)
+            .map(|i| (i % 3 != 0).then(|| i as i32 - 5_000))
+            .collect::<Int32Array>()
+            .slice(3, len);
+        let floats = (0..len + 3)
+            .map(|i| (i % 3 != 0).then(|| i as f64 / 4.0 - 1_250.0))
+            .collect::<Float64Array>()
+            .slice(3, len);
+        let schema = Schema::new(vec![
+            Field::new("i".to_string(), DataType::Int32),
+            Field::new("f".to_string(), DataType::Float64),
+        ]);
+        let input = RecordBatch::new(
+            schema,
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(ints))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(floats))) as ArrayRef,
+            ],
+        );
+        let evaluate = |name: &str, column: usize| {
+            let args = vec![Expr::Column(Column::new(column))];
+            let result = ScalarFunctionExpr::try_new(name, args)
+                .unwrap()
+                .evaluate(&input)
+                .unwrap();
+            assert_eq!(result.size(), len);
+            result
+        };
+        let expected = |f: &dyn Fn(usize) -> f64| {
+            (3..len + 3)
+                .map(|i| (i % 3 != 0).then(|| f(i)))
+                .collect::<Vec<_>>()
+        };
+
+        let result = evaluate("abs", 0);
+        assert_eq!(result.get_type(), DataType::Int32);
+        let abs = expected(&|i| (i as f64 - 5_000.0).abs());
+        let values = typed_values::<i32>(&result, DataType::Int32).unwrap();
+        assert_eq!(
+            values
+                .into_iter()
+                .map(|v| v.map(f64::from))
+                .collect::<Vec<_>>(),
+            abs
+        );
+        let result = evaluate("sqrt", 0);
+        assert_eq!(result.get_type(), DataType::Float64);
+        let sqrt = expected(&|i| (i as f64 - 5_000.0).sqrt());
+        let values = typed_values::<f64>(&result, DataType::Float64).unwrap();
+        assert_eq!(format!("{:?}", values), format!("{:?}", sqrt));
+        let result = evaluate("floor", 1);
+        let floor = expected(&|i| (i as f64 / 4.0 - 1_250.0).floor());
+        assert_eq!(
+            typed_values::<f64>(&result, DataType::Float64).unwrap(),
+            floor
+        );
+    }
+
+    #[test]
+    fn test_math_argument_errors() {
+        assert!(ScalarFunctionExpr::try_new("abs", vec![]).is_err());
+        let abs = ScalarFunctionExpr::try_new(
+            "abs",
+            vec![Expr::Literal(ScalarValue::String("a".to_string()))],
+        )
+        .unwrap();
+        assert!(abs.evaluate(&numeric_input()).is_err());
+    }
+
+    #[test]
+    fn test_inet_contains() {
+        let addrs = StringArray::from(vec![
+            "10.1.2.3",
+            "192.168.0.1",
+            "0000000000000000000000000a000001",
+        ]);
+        let schema = Schema::new(vec![Field::new("ip".to_string(), DataType::Utf8)]);
+        let input = RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(addrs))) as ArrayRef],
+        );
+        let result = ScalarFunctionExpr::try_new(
+            "inet_contains",
+            vec![
+                Expr::Literal(ScalarValue::String("10.0.0.0/8".to_string())),
+                Expr::Column(Column::new(0)),
+            ],
+        )
+        .unwrap()
+        .evaluate(&input)
+        .unwrap();
+        assert_eq!(values::<bool>(&result), vec![true, false, false]);
+    }
+
+    fn nullable_input() -> RecordBatch {
+        let a = Int32Array::from(vec![None, Some(2), None, Some(4)]);
+        let b = Int32Array::from(vec![Some(10), None, None, Some(4)]);
+        let schema = Schema::new(vec![
+            Field::new("a".to_string(), DataType::Int32),
+            Field::new("b".to_string(), DataType::Int32),
+        ]);
+        RecordBatch::new(
+            schema,
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(a))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(b))) as ArrayRef,
+            ],
+        )
+    }
+
+    fn nullable_values(array: &ArrayRef) -> Vec<Option<i32>> {
+        (0..array.size())
+            .map(|i| {
+                (!array.is_null(i))
+                    .then(|| *array.get_value(i).unwrap().downcast_ref::<i32>().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let result = ScalarFunctionExpr::try_new(
+            "coalesce",
+            vec![
+                Expr::Column(Column::new(0)),
+                Expr::Column(Column::new(1)),
+                Expr::Literal(ScalarValue::Int32(0)),
+            ],
+        )
+        .unwrap()
+        .evaluate(&nullable_input())
+        .unwrap();
+        assert_eq!(
+            nullable_values(&result),
+            vec![Some(10), Some(2), Some(0), Some(4)]
+        );
+
+        let result = ScalarFunctionExpr::try_new(
+            "coalesce",
+            vec![Expr::Column(Column::new(0)), Expr::Column(Column::new(1))],
+        )
+        .unwrap()
+        .evaluate(&nullable_input())
+        .unwrap();
+        assert_eq!(
+            nullable_values(&result),
+            vec![Some(10), Some(2), None, Some(4)]
+        );
+
+        assert!(ScalarFunctionExpr::try_new("coalesce", vec![]).is_err());
+    }
+
+    #[test]
+    fn test_nullif() {
+        let result = ScalarFunctionExpr::try_new(
+            "nullif",
+            vec![Expr::Column(Column::new(0)), Expr::Column(Column::new(1))],
+        )
+        .unwrap()
+        .evaluate(&nullable_input())
+        .unwrap();
+        assert_eq!(nullable_values(&result), vec![None, Some(2), None, None]);
+
+        let mismatched = ScalarFunctionExpr::try_new(
+            "nullif",
+            vec![
+                Expr::Column(Column::new(0)),
+                Expr::Literal(ScalarValue::Int64(2)),
+            ],
+        )
+        .unwrap();
+        assert!(mismatched.evaluate(&nullable_input()).is_err());
+    }
+
+    #[test]
+    fn test_null_arguments() {
+        let nulls = |array: &ArrayRef| {
+            (0..array.size())
+                .map(|i| array.is_null(i))
+                .collect::<Vec<_>>()
+        };
+        let column = |i| Expr::Column(Column::new(i));
+        let function = |name: &str, args: Vec<Expr>, input: &RecordBatch| {
+            ScalarFunctionExpr::try_new(name, args)
+                .unwrap()
+                .evaluate(input)
+                .unwrap()
+        };
+
+        let input = nullable_input();
+        let result = function("abs", vec![column(0)], &input);
+        assert_eq!(nullable_values(&result), vec![None, Some(2), None, Some(4)]);
+        for name in ["round", "sqrt", "ln"] {
+            let result = function(name, vec![column(0)], &input);
+            assert_eq!(nulls(&result), vec![true, false, true, false], "{}", name);
+        }
+        let result = function("pow", vec![column(0), column(1)], &input);
+        assert_eq!(nulls(&result), vec![true, true, true, false]);
+
+        let s = StringArray::from(vec![Some("user=alice"), None]);
+        let ip = StringArray::from(vec![None, Some("10.0.0.1")]);
+        let point = StringArray::from(vec![Some("52.52,13.405"), None]);
+        let schema = Schema::new(vec![
+            Field::new("s".to_string(), DataType::Utf8),
+            Field::new("ip".to_string(), DataType::Utf8),
+            Field::new("p".to_string(), DataType::Utf8),
+        ]);
+        let input = RecordBatch::new(
+            schema,
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(s))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(ip))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(point))) as ArrayRef,
+            ],
+        );
+        let string = |s: &str| Expr::Literal(ScalarValue::String(s.to_string()));
+        for (name, args) in [
+            ("regexp_like", vec![column(0), string("^user")]),
+            ("regexp_match", vec![column(0), string("user=(\\w+)")]),
+            ("soundex", vec![column(0)]),
+            ("url_host", vec![column(0)]),
+            ("url_query_param", vec![column(0), string("q")]),
+            ("levenshtein", vec![column(0), string("user")]),
+            ("st_distance", vec![column(2), string("0,0")]),
+            (
+                "st_within_bbox",
+                vec![column(2), string("0,0"), string("90,90")],
+            ),
+        ] {
+            let result = function(name, args, &input);
+            assert_eq!(nulls(&result), vec![false, true], "{}", name);
+        }
+        let result = function(
+            "inet_contains",
+            vec![string("10.0.0.0/8"), column(1)],
+            &input,
+        );
+        assert_eq!(nulls(&result), vec![true, false]);
+        assert!(*result.get_value(1).unwrap().downcast_ref::<bool>().unwrap());
+    }
+
+    #[test]
+    fn test_st_functions() {
+        let points = StringArray::from(vec!["52.52,13.405", "48.8566,2.3522"]);
+        let schema = Schema::new(vec![Field::new("p".to_string(), DataType::Utf8)]);
+        let input = RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(points))) as ArrayRef],
+        );
+        let point = |s: &str| Expr::Literal(ScalarValue::String(s.to_string()));
+
+        let result = ScalarFunctionExpr::try_new(
+            "st_distance",
+            vec![Expr::Column(Column::new(0)), point("52.52,13.405")],
+        )
+        .unwrap()
+        .evaluate(&input)
+        .unwrap();
+        let distances = values::<f64>(&result);
+        assert_eq!(distances[0], 0.0);
+        assert!((distances[1] - 877_500.0).abs() < 2_000.0);
+
+        let result = ScalarFunctionExpr::try_new(
+            "st_within_bbox",
+            vec![Expr::Column(Column::new(0)), point("47,5"), point("55,15")],
+        )
+        .unwrap()
+        .evaluate(&input)
+        .unwrap();
+        assert_eq!(values::<bool>(&result), vec![true, false]);
+    }
+
+    #[test]
+    fn test_fuzzy_functions() {
+        let name = |s: &str| Expr::Literal(ScalarValue::String(s.to_string()));
+        let fuzzy = |fun: &str, args| {
+            ScalarFunctionExpr::try_new(fun, args)
+                .unwrap()
+                .evaluate(&input())
+                .unwrap()
+        };
+
+        let result = fuzzy(
+            "levenshtein",
+            vec![Expr::Column(Column::new(0)), name("user=bobby")],
+        );
+        assert_eq!(values::<i32>(&result), vec![10, 2, 9]);
+
+        let result = fuzzy("jaro_winkler", vec![name("MARTHA"), name("MARHTA")]);
+        assert!((values::<f64>(&result)[0] - 0.961).abs() < 0.001);
+
+        let result = fuzzy("soundex", vec![name("Robert")]);
+        assert_eq!(values::<String>(&result)[0], "R163");
+    }
+
+    #[test]
+    fn test_regexp_extract() {
+        let extract = |pattern: &str, group: i32| {
+            ScalarFunctionExpr::try_new(
+                "regexp_extract",
+                vec![
+                    Expr::Column(Column::new(0)),
+                    Expr::Literal(ScalarValue::String(pattern.to_string())),
+                    Expr::Literal(ScalarValue::Int32(group)),
+                ],
+            )
+        };
+
+        let result = extract(r"user=(\w+)(?: id=(\d+))?", 2)
+            .unwrap()
+            .evaluate(&input())
+            .unwrap();
+        let values = typed_values::<String>(&result, DataType::Utf8).unwrap();
+        assert_eq!(values, vec![Some("1".to_string()), None, None]);
+
+        let result = extract(r"user=(\w+)", 0)
+            .unwrap()
+            .evaluate(&input())
+            .unwrap();
+        let values = typed_values::<String>(&result, DataType::Utf8).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Some("user=alice".to_string()),
+                Some("user=bob".to_string()),
+                None
+            ]
+        );
+
+        assert!(extract(r"user=(\w+)", 2).is_err());
+        assert!(extract(r"user=(\w+)", -1).is_err());
+    }
+
+    #[test]
+    fn test_regexp_extract_all() {
+        let result = ScalarFunctionExpr::try_new(
+            "regexp_extract_all",
+            vec![
+                Expr::Column(Column::new(0)),
+                Expr::Literal(ScalarValue::String(r"(\w+)=(\w+)".to_string())),
+                Expr::Literal(ScalarValue::Int32(1)),
+            ],
+        )
+        .unwrap()
+        .evaluate(&input())
+        .unwrap();
+        assert_eq!(result.get_type(), DataType::List(Box::new(DataType::Utf8)));
+        let lists = (0..result.size())
+            .map(|i| {
+                let value = result.get_value(i).unwrap();
+                value
+                    .downcast_ref::<Vec<Option<Box<dyn Any>>>>()
+                    .unwrap()
+                    .iter()
+                    .map(|item| {
+                        item.as_ref()
+                            .unwrap()
+                            .downcast_ref::<String>()
+                            .unwrap()
+                            .clone()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lists,
+            vec![vec!["user", "id"], vec!["user"], Vec::<&str>::new()]
+        );
+    }
+
+    #[test]
+    fn test_url_functions() {
+        let urls = StringArray::from(vec![
+            "https://example.com/search?q=rust",
+            "http://docs.example.org:8080/index.html",
+        ]);
+        let schema = Schema::new(vec![Field::new("url".to_string(), DataType::Utf8)]);
+        let input = RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(urls))) as ArrayRef],
+        );
+        let url = |fun: &str, mut args: Vec<Expr>| {
+            args.insert(0, Expr::Column(Column::new(0)));
+            values::<String>(
+                &ScalarFunctionExpr::try_new(fun, args)
+                    .unwrap()
+                    .evaluate(&input)
+                    .unwrap(),
+            )
+        };
+
+        assert_eq!(
+            url("url_host", vec![]),
+            vec!["example.com", "docs.example.org"]
+        );
+        assert_eq!(url("url_path", vec![]), vec!["/search", "/index.html"]);
+        assert_eq!(
+            url(
+                "url_query_param",
+                vec![Expr::Literal(ScalarValue::String("q".to_string()))]
+            ),
+            vec!["rust", ""]
+        );
+    }
+
+    #[test]
+    fn test_date_bin() {
+        let timestamps = Int64Array::from(vec![Some(0), Some(59), Some(60), Some(-1), None]);
+        let schema = Schema::new(vec![Field::new("ts".to_string(), DataType::Int64)]);
+        let input = RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(timestamps))) as ArrayRef],
+        );
+        let date_bin = |interval: i64, origin: i64| {
+            ScalarFunctionExpr::try_new(
+                "date_bin",
+                vec![
+                    Expr::Literal(ScalarValue::Int64(interval)),
+                    Expr::Column(Column::new(0)),
+                    Expr::Literal(ScalarValue::Int64(origin)),
+                ],
+            )
+        };
+
+        let result = date_bin(60, 0).unwrap().evaluate(&input).unwrap();
+        assert!(result.is_null(4));
+        assert_eq!(
+            (0..4)
+                .map(|i| *result.get_value(i).unwrap().downcast_ref::<i64>().unwrap())
+                .collect::<Vec<_>>(),
+            vec![0, 0, 60, -60]
+        );
+        let result = date_bin(60, 30).unwrap().evaluate(&input).unwrap();
+        assert_eq!(
+            *result.get_value(2).unwrap().downcast_ref::<i64>().unwrap(),
+            30
+        );
+        assert!(date_bin(0, 0).is_err());
+
+        // The bins of extreme timestamps overflow.
+        let bin_min = |mode| {
+            let timestamps = Int64Array::from(vec![i64::MIN]);
+            let schema = Schema::new(vec![Field::new("ts".to_string(), DataType::Int64)]);
+            let input = RecordBatch::new(
+                schema,
+                vec![Arc::new(ArrowFieldArray::new(Box::new(timestamps))) as ArrayRef],
+            );
+            date_bin(60, 30)
+                .unwrap()
+                .with_overflow_mode(mode)
+                .evaluate(&input)
+        };
+        assert_eq!(
+            bin_min(OverflowMode::Checked).err().unwrap().to_string(),
+            "Int64 overflow in date_bin(60, -9223372036854775808, 30)"
+        );
+        let result = bin_min(OverflowMode::Saturating).unwrap();
+        assert_eq!(values::<i64>(&result), vec![i64::MIN]);
+        let result = bin_min(OverflowMode::Wrapping).unwrap();
+        assert_eq!(values::<i64>(&result), vec![i64::MAX - 21]);
+    }
+
+    #[test]
+    fn test_binary_functions() {
+        let s = StringArray::from(vec![Some("abc"), None]);
+        let schema = Schema::new(vec![Field::new("s".to_string(), DataType::Utf8)]);
+        let input = RecordBatch::new(
+            schema,
+            vec![Arc::new(ArrowFieldArray::new(Box::new(s))) as ArrayRef],
+        );
+        let function = |name: &str, args: Vec<Expr>| {
+            ScalarFunctionExpr::try_new(name, args)
+                .unwrap()
+                .evaluate(&input)
+                .unwrap()
+        };
+        let encoding = |e: &str| Expr::Literal(ScalarValue::String(e.to_string()));
+        let column = || Expr::Column(Column::new(0));
+        let first_string = |array: &ArrayRef| {
+            assert!(array.is_null(1));
+            array
+                .get_value(0)
+                .unwrap()
+                .downcast_ref::<String>()
+                .unwrap()
+                .clone()
+        };
+
+        let md5 = function("md5", vec![column()]);
+        assert_eq!(first_string(&md5), "900150983cd24fb0d6963f7d28e17f72");
+
+        // The digests are both hex strings.
+        let sha256 = function("sha256", vec![column()]);
+        assert_eq!(sha256.get_type(), DataType::Utf8);
+        assert_eq!(
+            first_string(&sha256),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let decoded = function("decode", vec![column(), encoding("base64")]);
+        assert_eq!(
+            decoded
+                .get_value(0)
+                .unwrap()
+                .downcast_ref::<Vec<u8>>()
+                .unwrap(),
+            &vec![0x69, 0xb7]
+        );
+        assert!(ScalarFunctionExpr::try_new("encode", vec![column(), encoding("utf8")]).is_err());
+        assert!(
+            ScalarFunctionExpr::try_new("encode", vec![column(), encoding("hex")])
+                .unwrap()
+                .evaluate(&input)
+                .is_err()
+        );
+    }
+}
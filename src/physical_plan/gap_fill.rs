@@ -0,0 +1,131 @@
+use std::{any::Any, fmt::Display};
+
+use super::{
+    expr::evaluate_from_nullable_values,
+    memory::MemoryBudget,
+    plan::{Partitioning, PhysicalPlan, Plan},
+};
+use crate::data_types::{
+    column_array::{ColumnArray, DataType},
+    record_batch::RecordBatch,
+    schema::Schema,
+};
+
+use anyhow::{bail, Result};
+
+/// Execute a gap fill.
+/// All the input is read and sorted by the bucket in the first column, then a row
+/// of nulls is added for every missing bucket. Rows with a null bucket come last.
+pub struct GapFillExec {
+    pub(crate) input: Box<Plan>,
+    pub(crate) every: i64,
+    memory: MemoryBudget,
+}
+
+impl GapFillExec {
+    pub fn new(input: Plan, every: i64) -> Self {
+        Self {
+            input: Box::new(input),
+            every,
+            memory: MemoryBudget::default(),
+        }
+    }
+
+    /// Reserve the data buffered by the operator from the memory budget of the query.
+    pub fn with_memory_budget(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Return the rows of all the batches as (bucket, values), sorted by bucket.
+    fn sorted_rows(&self, batches: &[RecordBatch]) -> Result<Vec<Row>> {
+        let mut rows = vec![];
+        for batch in batches {
+            let buckets = batch.field(0);
+            if buckets.get_type() != DataType::Int64 {
+                bail!("Time buckets must be Int64, got {}", buckets.get_type());
+            }
+            for i in 0..batch.row_count() {
+                let bucket = if buckets.is_null(i) {
+                    None
+                } else {
+                    Some(*buckets.get_value(i)?.downcast_ref::<i64>().unwrap())
+                };
+                let values = batch
+                    .fields
+                    .iter()
+                    .map(|f| (!f.is_null(i)).then(|| f.get_value(i)).transpose())
+                    .collect::<Result<Vec<_>>>()?;
+                rows.push((bucket, values));
+            }
+        }
+        // Sort nulls last.
+        rows.sort_by_key(|(bucket, _)| (bucket.is_none(), *bucket));
+        Ok(rows)
+    }
+}
+
+type Row = (Option<i64>, Vec<Option<Box<dyn Any>>>);
+
+impl PhysicalPlan for GapFillExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let schema = self.schema();
+        let mut reservation = self.memory.reservation("GapFillExec");
+        let batches = reservation.collect(self.input.execute_all()?)?;
+        let rows = self.sorted_rows(&batches)?;
+        if rows.is_empty() {
+            return Ok(Box::new(std::iter::empty()));
+        }
+
+        let mut columns: Vec<Vec<Option<Box<dyn Any>>>> =
+            schema.fields.iter().map(|_| vec![]).collect();
+        let mut next_bucket = None;
+        for (bucket, values) in rows {
+            if let (Some(bucket), Some(mut next)) = (bucket, next_bucket) {
+                while next < bucket {
+                    columns[0].push(Some(Box::new(next)));
+                    columns.iter_mut().skip(1).for_each(|c| c.push(None));
+                    next += self.every;
+                }
+            }
+            if let Some(bucket) = bucket {
+                next_bucket = Some(bucket + self.every);
+            }
+            columns
+                .iter_mut()
+                .zip(values)
+                .for_each(|(c, value)| c.push(value));
+        }
+
+        let fields = columns
+            .iter()
+            .zip(&schema.fields)
+            .map(|(values, field)| evaluate_from_nullable_values(values, &field.data_type))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(std::iter::once(Ok(RecordBatch::new(
+            schema, fields,
+        )))))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for GapFillExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GapFillExec: every={}", self.every)
+    }
+}
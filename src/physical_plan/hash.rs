@@ -1,37 +1,38 @@
 use std::{
     any::Any,
-    collections::{hash_map::DefaultHasher, BTreeMap},
+    collections::HashMap,
     fmt::Display,
-    hash::{Hash, Hasher},
-    rc::Rc,
+    hash::{BuildHasherDefault, Hash, Hasher},
+    mem::size_of,
 };
 
 use super::{
-    aggregate::{Accumulator, AggregateExpr},
-    expr::{Expr, PhysicalExpr},
-    plan::{PhysicalPlan, Plan},
+    aggregate::{AggregateExpr, GroupsAccumulator},
+    expr::{evaluate_from_nullable_values, Expr, PhysicalExpr},
+    memory::MemoryBudget,
+    plan::{Partitioning, PhysicalPlan, Plan},
+    sort_key::{key_values, KeyValue},
 };
 use crate::data_types::{
-    arrow_field_array::ArrowFieldArray,
-    column_array::{ArrayRef, DataType},
+    column_array::{ArrayRef, ColumnArray, DataType},
     record_batch::RecordBatch,
     schema::Schema,
 };
 
 use anyhow::Result;
-use arrow::array::{ArrayBuilder, Float32Builder, Float64Builder, Int32Builder, Int64Builder};
-use ordered_float::OrderedFloat;
 
-// AccumulatorMap is a map storing the accumulators for each group.
-// GroupKey -> (GroupValues, Accumulators).
-type AccumulatorMap = BTreeMap<u64, (Vec<Box<dyn Any>>, Vec<Accumulator>)>;
+/// Estimated size of the state an accumulator keeps for a group.
+const ACCUMULATOR_STATE_SIZE: usize = 16;
 
 /// HashExec will hash the input record batches and group them by the hash value.
+/// Every group gets an id in the order it is first seen, and the accumulators keep
+/// the state of all the groups in vectors indexed by that id.
 pub struct HashExec {
-    input: Box<Plan>,
-    schema: Schema,
-    group_expr: Vec<Expr>,
-    aggregate_expr: Vec<AggregateExpr>,
+    pub(crate) input: Box<Plan>,
+    pub(crate) schema: Schema,
+    pub(crate) group_expr: Vec<Expr>,
+    pub(crate) aggregate_expr: Vec<AggregateExpr>,
+    memory: MemoryBudget,
 }
 
 impl HashExec {
@@ -46,97 +47,132 @@ impl HashExec {
             schema,
             group_expr,
             aggregate_expr,
+            memory: MemoryBudget::default(),
         }
     }
 
-    /// Create array builders by the schema.
-    fn create_builders(&self, row_count: usize) -> Vec<Box<dyn ArrayBuilder>> {
-        self.schema
-            .fields
+    /// Reserve the data buffered by the operator from the memory budget of the query.
+    pub fn with_memory_budget(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Estimate the bytes used by a group: its key, its entry in the group table, and the
+    /// state of every accumulator. The heap data of string and nested keys is not counted.
+    fn group_size(&self) -> usize {
+        self.group_expr.len() * size_of::<Option<KeyValue>>()
+            + 2 * size_of::<usize>()
+            + self.aggregate_expr.len() * ACCUMULATOR_STATE_SIZE
+    }
+
+    fn create_accumulators(&self, b: &RecordBatch) -> Result<Vec<Box<dyn GroupsAccumulator>>> {
+        self.aggregate_expr
             .iter()
-            .map(|f| match f.data_type {
-                DataType::Int32 => Box::new(Int32Builder::new(row_count)) as Box<dyn ArrayBuilder>,
-                DataType::Int64 => Box::new(Int64Builder::new(row_count)),
-                DataType::Float32 => Box::new(Float32Builder::new(row_count)),
-                DataType::Float64 => Box::new(Float64Builder::new(row_count)),
-                _ => unreachable!(),
+            .enumerate()
+            .map(|(i, e)| {
+                let arg_types = e
+                    .input_exprs()
+                    .iter()
+                    .map(|arg| Ok(arg.evaluate(b)?.get_type()))
+                    .collect::<Result<Vec<_>>>()?;
+                let data_type = &self.schema.fields[self.group_expr.len() + i].data_type;
+                Ok(e.create_groups_accumulator(&arg_types, data_type))
             })
             .collect()
     }
-}
-
-impl PhysicalPlan for HashExec {
-    fn schema(&self) -> Schema {
-        self.schema.clone()
-    }
 
-    fn execute(&self) -> anyhow::Result<Box<dyn Iterator<Item = RecordBatch> + '_>> {
-        let mut accumulator_map: AccumulatorMap = BTreeMap::new();
+    /// Aggregate the batches into a batch with a row for every group.
+    pub(crate) fn aggregate(
+        &self,
+        batches: impl Iterator<Item = Result<RecordBatch>>,
+    ) -> Result<RecordBatch> {
+        let mut groups = GroupTable::default();
+        let mut accumulators: Option<Vec<Box<dyn GroupsAccumulator>>> = None;
+        let mut group_ids = vec![];
+        let mut reservation = self.memory.reservation("HashExec");
+        // An aggregate without group expressions has a single group, even for no input rows.
+        if self.group_expr.is_empty() {
+            groups.group_ids(&[], 1, &mut group_ids);
+        }
 
         // For each batch from the input executor.
-        for b in self.input.execute()? {
-            // Evaluate the group expressions.
-            let group_keys: Vec<ArrayRef> = self
+        for b in batches {
+            let b = b?;
+            // Evaluate the group expressions and assign a group to each row.
+            let group_keys = self
                 .group_expr
                 .iter()
-                .map(|e| e.evaluate(&b))
-                .collect::<Result<Vec<ArrayRef>, _>>()?;
-            // Evaluate the aggregate expressions.
-            let aggr_input_values: Vec<ArrayRef> = self
+                .map(|e| key_values(&e.evaluate(&b)?))
+                .collect::<Result<Vec<_>>>()?;
+            let group_count = groups.len();
+            groups.group_ids(&group_keys, b.row_count(), &mut group_ids);
+            reservation.grow((groups.len() - group_count) * self.group_size())?;
+
+            // The accumulators are specialized on the argument types of the first batch.
+            if accumulators.is_none() {
+                accumulators = Some(self.create_accumulators(&b)?);
+            }
+            // Evaluate the aggregate expressions and update the groups.
+            for (e, acc) in self
                 .aggregate_expr
                 .iter()
-                .map(|e| e.input_expr().evaluate(&b))
-                .collect::<Result<Vec<ArrayRef>, _>>()?;
-            // For each row in the batch.
-            for row_index in 0..b.row_count() {
-                // Get the group values to calculate the hash.
-                let values = group_keys
+                .zip(accumulators.as_mut().unwrap())
+            {
+                let values = e
+                    .input_exprs()
                     .iter()
-                    .map(|a| a.get_value(row_index))
-                    .collect::<Result<Vec<Box<dyn Any>>, _>>()?;
-                let hash = create_hash(&values);
-                // Get or insert the accumulators for the group.
-                let accumulators = accumulator_map.entry(hash).or_insert_with(|| {
-                    (
-                        values,
-                        self.aggregate_expr
-                            .iter()
-                            .map(|a| a.create_accumulator())
-                            .collect(),
-                    )
-                });
-                // Preform the aggregate operation.
-                for (i, acc) in accumulators.1.iter_mut().enumerate() {
-                    let value = aggr_input_values[i].get_value(row_index)?;
-                    acc.accumulate(Some(value));
-                }
+                    .map(|arg| arg.evaluate(&b))
+                    .collect::<Result<Vec<ArrayRef>>>()?;
+                acc.update_batch(&values, &group_ids, groups.len())?;
             }
         }
-        // Create the output record batches.
-        let mut builders = self.create_builders(accumulator_map.len());
 
-        accumulator_map
+        // Create the output record batch.
+        let mut fields = self
+            .schema
+            .fields
             .iter()
+            .take(self.group_expr.len())
             .enumerate()
-            .for_each(|(_row_index, (_, (values, accumulators)))| {
-                self.group_expr
-                    .iter()
-                    .enumerate()
-                    .for_each(|(i, _)| append_value(&mut builders[i], &values[i]));
-                self.aggregate_expr.iter().enumerate().for_each(|(i, _)| {
-                    append_value(
-                        &mut builders[self.group_expr.len() + i],
-                        accumulators[i].final_value().as_ref().unwrap(),
-                    )
-                });
-            });
-        let fields: Vec<ArrayRef> = builders
-            .iter_mut()
-            .map(|b| Rc::new(ArrowFieldArray::new(Box::new(b.finish().clone()))) as ArrayRef)
-            .collect();
-        Ok(Box::new(
-            vec![RecordBatch::new(self.schema.clone(), fields)].into_iter(),
-        ))
+            .map(|(i, f)| key_array(groups.keys.iter().map(|k| k[i].as_ref()), &f.data_type))
+            .collect::<Result<Vec<_>>>()?;
+        match accumulators {
+            Some(accumulators) => {
+                for acc in accumulators {
+                    fields.push(acc.evaluate()?);
+                }
+            }
+            // Without input batches, every group has the value of an empty accumulator.
+            None => {
+                let data_types = self.schema.fields.iter().skip(self.group_expr.len());
+                for (e, f) in self.aggregate_expr.iter().zip(data_types) {
+                    let values = (0..groups.len())
+                        .map(|_| e.create_accumulator().evaluate())
+                        .collect::<Result<Vec<_>>>()?;
+                    fields.push(evaluate_from_nullable_values(&values, &f.data_type)?);
+                }
+            }
+        }
+        Ok(RecordBatch::new(self.schema.clone(), fields))
+    }
+}
+
+impl PhysicalPlan for HashExec {
+    fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let batch = self.aggregate(self.input.execute_all()?)?;
+        Ok(Box::new(std::iter::once(Ok(batch))))
     }
 
     fn children(&self) -> Vec<&Plan> {
@@ -144,59 +180,121 @@ impl PhysicalPlan for HashExec {
     }
 }
 
-/// Create a hash value for the group key.
-fn create_hash(values: &Vec<Box<dyn Any>>) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    for value in values {
-        if value.is::<i32>() {
-            hasher.write_i32(*value.downcast_ref::<i32>().unwrap());
-        } else if value.is::<i64>() {
-            hasher.write_i64(*value.downcast_ref::<i64>().unwrap());
-        } else if value.is::<f32>() {
-            let ft = OrderedFloat(*value.downcast_ref::<f32>().unwrap());
-            ft.hash(&mut hasher);
-        } else if value.is::<f64>() {
-            let ft = OrderedFloat(*value.downcast_ref::<f64>().unwrap());
-            ft.hash(&mut hasher);
-        } else {
-            unreachable!()
+/// The distinct group keys seen so far, identified by the order they were first seen.
+#[derive(Default)]
+struct GroupTable {
+    /// Group ids by the hash of their keys. Collisions are resolved by comparing the keys.
+    ids_by_hash: HashMap<u64, Vec<usize>, BuildHasherDefault<KeyHasher>>,
+    /// The key of every group, by group id.
+    keys: Vec<Vec<Option<KeyValue>>>,
+}
+
+impl GroupTable {
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Assign the group id of each row of the key columns to `group_ids`,
+    /// adding a group for every key not seen before.
+    fn group_ids(
+        &mut self,
+        columns: &[Vec<Option<KeyValue>>],
+        row_count: usize,
+        group_ids: &mut Vec<usize>,
+    ) {
+        // Hash the key columns a column at a time.
+        let mut hashes = vec![0_u64; row_count];
+        for column in columns {
+            for (hash, value) in hashes.iter_mut().zip(column) {
+                let mut hasher = KeyHasher(*hash);
+                value.hash(&mut hasher);
+                *hash = hasher.finish();
+            }
+        }
+
+        group_ids.clear();
+        for (row, hash) in hashes.into_iter().enumerate() {
+            let ids = self.ids_by_hash.entry(hash).or_default();
+            let keys = &mut self.keys;
+            let id = match ids
+                .iter()
+                .find(|&&id| columns.iter().zip(&keys[id]).all(|(c, k)| c[row] == *k))
+            {
+                Some(&id) => id,
+                None => {
+                    let id = keys.len();
+                    keys.push(columns.iter().map(|c| c[row].clone()).collect());
+                    ids.push(id);
+                    id
+                }
+            };
+            group_ids.push(id);
         }
     }
-    hasher.finish()
 }
 
-// Append the value to the array builder.
-fn append_value(build: &mut Box<dyn ArrayBuilder>, value: &Box<dyn Any>) {
-    if build.as_any().is::<Int32Builder>() {
-        build
-            .as_any_mut()
-            .downcast_mut::<Int32Builder>()
-            .unwrap()
-            .append_value(*value.downcast_ref::<i32>().unwrap())
-            .unwrap();
-    } else if build.as_any().is::<Int64Builder>() {
-        build
-            .as_any_mut()
-            .downcast_mut::<Int64Builder>()
-            .unwrap()
-            .append_value(*value.downcast_ref::<i64>().unwrap())
-            .unwrap();
-    } else if build.as_any().is::<Float32Builder>() {
-        build
-            .as_any_mut()
-            .downcast_mut::<Float32Builder>()
-            .unwrap()
-            .append_value(*value.downcast_ref::<f32>().unwrap())
-            .unwrap();
-    } else if build.as_any().is::<Float64Builder>() {
-        build
-            .as_any_mut()
-            .downcast_mut::<Float64Builder>()
-            .unwrap()
-            .append_value(*value.downcast_ref::<f64>().unwrap())
-            .unwrap();
-    } else {
-        unreachable!()
+/// A fast, non-cryptographic hasher for group keys, in the style of FxHash.
+/// Keys come from the data being queried, so there is no need to resist collisions.
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0.rotate_left(5) ^ i).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Build an array of the given type from the group key values.
+fn key_array<'a>(
+    keys: impl Iterator<Item = Option<&'a KeyValue>>,
+    data_type: &DataType,
+) -> Result<ArrayRef> {
+    let values = keys
+        .map(|key| key.map(|key| key_to_value(key, data_type)))
+        .collect::<Vec<_>>();
+    evaluate_from_nullable_values(&values, data_type)
+}
+
+/// Convert a group key value back to a value of the given type.
+fn key_to_value(key: &KeyValue, data_type: &DataType) -> Box<dyn Any> {
+    let nested = |values: &[Option<KeyValue>], types: &mut dyn Iterator<Item = &DataType>| {
+        values
+            .iter()
+            .zip(types)
+            .map(|(v, t)| v.as_ref().map(|v| key_to_value(v, t)))
+            .collect::<Vec<_>>()
+    };
+    match (key, data_type) {
+        (KeyValue::Boolean(v), _) => Box::new(*v),
+        (KeyValue::Int(v), DataType::Int32) => Box::new(*v as i32),
+        (KeyValue::Int(v), _) => Box::new(*v),
+        (KeyValue::Float(v), DataType::Float32) => Box::new(v.0 as f32),
+        (KeyValue::Float(v), _) => Box::new(v.0),
+        (KeyValue::Utf8(v), _) => Box::new(v.clone()),
+        (KeyValue::Binary(v), _) => Box::new(v.clone()),
+        (KeyValue::Nested(v), DataType::List(item_type)) => {
+            Box::new(nested(v, &mut std::iter::repeat(item_type.as_ref())))
+        }
+        (KeyValue::Nested(v), DataType::Struct(fields)) => {
+            Box::new(nested(v, &mut fields.iter().map(|(_, t)| t)))
+        }
+        (KeyValue::Nested(_), _) => unreachable!(),
     }
 }
 
@@ -224,22 +322,26 @@ mod tests {
 
     use super::*;
     use crate::{
-        data_source::{csv_data_source::CsvDataSource, Source},
-        data_types::schema::Field,
+        assert_batches_eq,
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{arrow_field_array::ArrowFieldArray, schema::Field},
+        execution::ExecutionContext,
         logical_plan::expr::AggregateFunction,
-        physical_plan::{expr::Column, scan::ScanExec},
-        test_util::rq_test_data,
+        physical_plan::{expr::Column, scan::ScanExec, sort_key::key_value},
+        test_util::csv_test_data_source,
     };
 
+    use arrow::array::{Int64Array, StringArray};
+    use std::sync::Arc;
+
     fn get_hash_exec() -> HashExec {
-        let data_path = rq_test_data("hash_test_filed.csv");
         let schema = Schema::new(vec![
             Field::new("c1".to_string(), DataType::Int32),
             Field::new("c2".to_string(), DataType::Int64),
             Field::new("c3".to_string(), DataType::Float32),
             Field::new("c4".to_string(), DataType::Float64),
         ]);
-        let csv_data_source = CsvDataSource::new(data_path, schema, 4);
+        let csv_data_source = csv_test_data_source("hash_test_filed.csv", schema, 4);
         let scan = ScanExec::new(
             Source::Csv(csv_data_source),
             vec![
@@ -251,7 +353,7 @@ mod tests {
         );
         let group_expr = vec![Expr::Column(Column::new(1)), Expr::Column(Column::new(2))];
         let aggregate_expr = vec![AggregateExpr::new(
-            Expr::Column(Column::new(1)),
+            vec![Expr::Column(Column::new(1))],
             AggregateFunction::Sum,
         )];
 
@@ -268,7 +370,7 @@ mod tests {
     #[test]
     fn test_hash_execute() {
         let hash = get_hash_exec();
-        let result = hash.execute().unwrap().next().unwrap();
+        let result = hash.execute_all().unwrap().next().unwrap().unwrap();
         assert_eq!(result.row_count(), 3);
         assert_eq!(result.column_count(), 3);
         // Assert the first row.
@@ -329,6 +431,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hash_execute_utf8_keys() {
+        let schema = Schema::new(vec![
+            Field::new("k".to_string(), DataType::Utf8),
+            Field::new("v".to_string(), DataType::Int64),
+        ]);
+        let k = StringArray::from(vec![Some("b"), Some("a"), None, Some("b"), None]);
+        let v = Int64Array::from(vec![1, 2, 3, 4, 5]);
+        let batch = RecordBatch::new(
+            schema.clone(),
+            vec![
+                Arc::new(ArrowFieldArray::new(Box::new(k))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(v))) as ArrayRef,
+            ],
+        );
+        let source = Source::Mem(MemoryDataSource::new(schema.clone(), vec![batch]));
+        let scan = ScanExec::new(source, vec!["k".to_string(), "v".to_string()]);
+        let hash = HashExec::new(
+            Plan::Scan(scan),
+            schema,
+            vec![Expr::Column(Column::new(0))],
+            vec![AggregateExpr::new(
+                vec![Expr::Column(Column::new(1))],
+                AggregateFunction::Sum,
+            )],
+        );
+
+        // Groups come out in the order they are first seen, and nulls form a group.
+        let result = hash.execute_all().unwrap().next().unwrap().unwrap();
+        assert_eq!(result.row_count(), 3);
+        let key = |i| key_value(result.field(0), i).unwrap();
+        assert_eq!(key(0), Some(KeyValue::Utf8("b".to_string())));
+        assert_eq!(key(1), Some(KeyValue::Utf8("a".to_string())));
+        assert_eq!(key(2), None);
+        let sums = (0..3)
+            .map(|i| {
+                *result
+                    .field(1)
+                    .get_value(i)
+                    .unwrap()
+                    .downcast_ref::<i64>()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sums, vec![5, 2, 8]);
+    }
+
     #[test]
     fn test_hash_display() {
         let hash = get_hash_exec();
@@ -338,4 +487,72 @@ mod tests {
             "HashAggregateExec: groupExpr=#1, #2, aggrExpr=SUM(#1)"
         );
     }
+
+    #[test]
+    fn test_hash_avg() {
+        let ctx = ExecutionContext::new(2);
+        let df = ctx
+            .sql(
+                "SELECT value % 2 AS k, AVG(value) AS a, AVG(CAST(value AS DOUBLE)) AS f \
+                 FROM generate_series(1, 5) GROUP BY value % 2 ORDER BY k",
+            )
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+---+---+---+",
+                "| k | a | f |",
+                "+---+---+---+",
+                "| 0 | 3 | 3 |",
+                "| 1 | 3 | 3 |",
+                "+---+---+---+",
+            ],
+            &df.collect(&ctx).unwrap()
+        );
+        let df = ctx
+            .sql("SELECT AVG(value) AS a FROM generate_series(1, 4)")
+            .unwrap();
+        assert_batches_eq!(
+            ["+-----+", "| a   |", "+-----+", "| 2.5 |", "+-----+"],
+            &df.collect(&ctx).unwrap()
+        );
+        let err = ctx
+            .sql("SELECT AVG(CAST(value AS VARCHAR)) FROM generate_series(1, 4)")
+            .and_then(|df| df.collect(&ctx))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("expects a numeric argument"));
+    }
+
+    #[test]
+    fn test_hash_global_aggregate_of_empty_input() {
+        let ctx = ExecutionContext::new(2);
+        // Inputs without batches and with empty batches both give a single row.
+        for input in [
+            "generate_series(1, 0)",
+            "generate_series(1, 4) WHERE value > 4",
+        ] {
+            let df = ctx
+                .sql(&format!(
+                    "SELECT COUNT(value) AS c, SUM(value) AS s, AVG(value) AS a FROM {}",
+                    input
+                ))
+                .unwrap();
+            assert_batches_eq!(
+                [
+                    "+---+---+---+",
+                    "| c | s | a |",
+                    "+---+---+---+",
+                    "| 0 |   |   |",
+                    "+---+---+---+",
+                ],
+                &df.collect(&ctx).unwrap()
+            );
+        }
+
+        // A grouped aggregate of an empty input has no groups.
+        let df = ctx
+            .sql("SELECT value, COUNT(value) FROM generate_series(1, 0) GROUP BY value")
+            .unwrap();
+        assert_eq!(df.count(&ctx).unwrap(), 0);
+    }
 }
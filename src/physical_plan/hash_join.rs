@@ -0,0 +1,292 @@
+use std::{collections::HashMap, fmt::Display, mem::size_of, sync::Arc};
+
+use super::{
+    expr::{to_arrow_array, Expr, PhysicalExpr},
+    memory::MemoryBudget,
+    plan::{Partitioning, PhysicalPlan, Plan},
+    sort_key::{key_values, KeyValue},
+};
+use crate::data_types::{
+    arrow_field_array::ArrowFieldArray, column_array::ArrayRef, record_batch::RecordBatch,
+    schema::Schema,
+};
+
+use anyhow::Result;
+use arrow::{
+    array::UInt32Array,
+    compute::{concat, take},
+};
+
+/// Execute an inner equi-join with a hash table of the rows of the left input by key.
+/// All the left input is read first, then every batch of the right input is joined on
+/// its own, so the left input should be the smaller one. Rows with a null key never match.
+pub struct HashJoinExec {
+    pub(crate) left: Box<Plan>,
+    pub(crate) right: Box<Plan>,
+    pub(crate) on: Vec<(Expr, Expr)>,
+    pub(crate) schema: Schema,
+    memory: MemoryBudget,
+}
+
+/// The rows of the left input, in a single batch, and their positions by key.
+struct JoinTable {
+    batch: RecordBatch,
+    rows_by_key: HashMap<Vec<KeyValue>, Vec<u32>>,
+}
+
+impl HashJoinExec {
+    pub fn new(left: Plan, right: Plan, on: Vec<(Expr, Expr)>, schema: Schema) -> Self {
+        Self {
+            left: Box::new(left),
+            right: Box::new(right),
+            on,
+            schema,
+            memory: MemoryBudget::default(),
+        }
+    }
+
+    /// Reserve the data buffered by the operator from the memory budget of the query.
+    pub fn with_memory_budget(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Return the key of every row of the batch, None when one of its values is null.
+    fn keys<'a>(
+        &self,
+        batch: &RecordBatch,
+        exprs: impl Iterator<Item = &'a Expr>,
+    ) -> Result<Vec<Option<Vec<KeyValue>>>> {
+        let columns = exprs
+            .map(|e| key_values(&e.evaluate(batch)?))
+            .collect::<Result<Vec<_>>>()?;
+        Ok((0..batch.row_count())
+            .map(|row| columns.iter().map(|c| c[row].clone()).collect())
+            .collect())
+    }
+
+    /// Read the left input into a single batch and index its rows by key.
+    fn build(&self, batches: Vec<RecordBatch>) -> Result<JoinTable> {
+        let schema = self.left.schema();
+        let fields = (0..schema.fields.len())
+            .map(|c| {
+                let arrays = batches
+                    .iter()
+                    .map(|b| to_arrow_array(b.field(c)))
+                    .collect::<Result<Vec<_>>>()?;
+                let arrays = arrays.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+                let array = match arrays.is_empty() {
+                    true => {
+                        arrow::array::new_empty_array(&schema.fields[c].data_type.clone().into())
+                    }
+                    false => concat(&arrays)?,
+                };
+                Ok(Arc::new(ArrowFieldArray::new(Box::new(array))) as ArrayRef)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let batch = RecordBatch::new(schema, fields);
+        let mut rows_by_key: HashMap<_, Vec<u32>> = HashMap::new();
+        for (row, key) in self
+            .keys(&batch, self.on.iter().map(|(left, _)| left))?
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(key) = key {
+                rows_by_key.entry(key).or_default().push(row as u32);
+            }
+        }
+        Ok(JoinTable { batch, rows_by_key })
+    }
+
+    /// Join a batch of the right input with the rows of the table.
+    fn probe(&self, table: &JoinTable, batch: &RecordBatch) -> Result<RecordBatch> {
+        let mut left_rows = vec![];
+        let mut right_rows = vec![];
+        for (row, key) in self
+            .keys(batch, self.on.iter().map(|(_, right)| right))?
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(rows) = key.and_then(|key| table.rows_by_key.get(&key)) {
+                left_rows.extend_from_slice(rows);
+                right_rows.resize(left_rows.len(), row as u32);
+            }
+        }
+        let take_rows = |batch: &RecordBatch, rows: Vec<u32>| {
+            let indices = UInt32Array::from(rows);
+            batch
+                .fields
+                .iter()
+                .map(|array| {
+                    let taken = take(to_arrow_array(array)?.as_ref(), &indices, None)?;
+                    Ok(Arc::new(ArrowFieldArray::new(Box::new(taken))) as ArrayRef)
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        let mut fields = take_rows(&table.batch, left_rows)?;
+        fields.extend(take_rows(batch, right_rows)?);
+        Ok(RecordBatch::new(self.schema.clone(), fields))
+    }
+}
+
+impl PhysicalPlan for HashJoinExec {
+    fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let mut reservation = self.memory.reservation("HashJoinExec");
+        let batches = reservation.collect(self.left.execute_all()?)?;
+        let table = self.build(batches)?;
+        // The batches are concatenated into one, and every row has an entry in the table.
+        let entry_size = self.on.len() * size_of::<KeyValue>() + size_of::<u32>();
+        reservation.grow(table.batch.memory_size() + table.batch.row_count() * entry_size)?;
+        // The reservation is held until the output is dropped, with the table.
+        let batches = self.right.execute_all()?.filter_map(move |batch| {
+            let _reservation = &reservation;
+            match batch.and_then(|batch| self.probe(&table, &batch)) {
+                Ok(batch) if batch.row_count() == 0 => None,
+                result => Some(result),
+            }
+        });
+        Ok(Box::new(batches))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.left, &self.right]
+    }
+}
+
+impl Display for HashJoinExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "HashJoinExec: on={}",
+            self.on
+                .iter()
+                .map(|(left, right)| format!("{} = {}", left, right))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{column_array::DataType, schema::Field},
+        physical_plan::{expr::Column, memory::MemoryBudget, scan::ScanExec},
+    };
+
+    use arrow::array::{Array, Int64Array, StringArray};
+
+    fn scan(fields: Vec<Field>, batches: Vec<Vec<Box<dyn Array>>>) -> Plan {
+        let schema = Schema::new(fields);
+        let batches = batches
+            .into_iter()
+            .map(|columns| {
+                let columns = columns
+                    .into_iter()
+                    .map(|c| Arc::new(ArrowFieldArray::new(c)) as ArrayRef)
+                    .collect();
+                RecordBatch::new(schema.clone(), columns)
+            })
+            .collect();
+        let data_source = MemoryDataSource::new(schema, batches);
+        Plan::Scan(ScanExec::new(Source::Mem(data_source), vec![]))
+    }
+
+    fn join(on: Vec<(Expr, Expr)>) -> HashJoinExec {
+        let customers = scan(
+            vec![
+                Field::new("id".to_string(), DataType::Int64),
+                Field::new("name".to_string(), DataType::Utf8),
+            ],
+            vec![
+                vec![
+                    Box::new(Int64Array::from(vec![Some(1), Some(2)])),
+                    Box::new(StringArray::from(vec!["ann", "bob"])),
+                ],
+                vec![
+                    Box::new(Int64Array::from(vec![Some(2), None])),
+                    Box::new(StringArray::from(vec!["bea", "nul"])),
+                ],
+            ],
+        );
+        let orders = scan(
+            vec![
+                Field::new("customer_id".to_string(), DataType::Int64),
+                Field::new("amount".to_string(), DataType::Int64),
+            ],
+            vec![
+                vec![
+                    Box::new(Int64Array::from(vec![Some(2), Some(3), None])),
+                    Box::new(Int64Array::from(vec![10, 20, 30])),
+                ],
+                vec![
+                    Box::new(Int64Array::from(vec![Some(1)])),
+                    Box::new(Int64Array::from(vec![40])),
+                ],
+            ],
+        );
+        let mut fields = customers.schema().fields;
+        fields.extend(orders.schema().fields);
+        HashJoinExec::new(customers, orders, on, Schema::new(fields))
+    }
+
+    fn rows(exec: &HashJoinExec) -> Vec<(i64, String, i64)> {
+        let mut rows = vec![];
+        for batch in exec.execute(0).unwrap() {
+            let batch = batch.unwrap();
+            for row in 0..batch.row_count() {
+                let value = |c: usize| batch.field(c).get_value(row).unwrap();
+                rows.push((
+                    *value(0).downcast_ref::<i64>().unwrap(),
+                    value(1).downcast_ref::<String>().unwrap().clone(),
+                    *value(3).downcast_ref::<i64>().unwrap(),
+                ));
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_hash_join() {
+        // Null keys match nothing, and a key of the left input can match several rows.
+        let exec = join(vec![(
+            Expr::Column(Column::new(0)),
+            Expr::Column(Column::new(0)),
+        )]);
+        assert_eq!(exec.to_string(), "HashJoinExec: on=#0 = #0");
+        assert_eq!(
+            rows(&exec),
+            vec![
+                (2, "bob".to_string(), 10),
+                (2, "bea".to_string(), 10),
+                (1, "ann".to_string(), 40),
+            ]
+        );
+
+        // Without keys, every pair of rows is returned.
+        assert_eq!(rows(&join(vec![])).len(), 16);
+    }
+
+    #[test]
+    fn test_hash_join_memory_limit() {
+        let exec = join(vec![]).with_memory_budget(MemoryBudget::new(Some(10)));
+        let error = exec.execute(0).err().unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("HashJoinExec exceeded the memory limit of 10 bytes"));
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::BTreeMap;
+
+use super::expr::{to_arrow_array, BinaryExpr, Expr, OverflowMode, ScalarValue};
+use crate::{
+    data_types::{column_array::DataType, record_batch::RecordBatch, schema::Schema},
+    logical_plan::expr::Operator,
+};
+
+use anyhow::Result;
+use arrow::{
+    array::{
+        as_primitive_array, ArrayRef as ArrowArrayRef, BooleanArray, Float64Array, Int64Array,
+    },
+    datatypes::{DataType as ArrowDataType, Float64Type, Int64Type},
+};
+use cranelift_codegen::{
+    ir::{condcodes::FloatCC, condcodes::IntCC, types, AbiParam, InstBuilder, MemFlags, Value},
+    settings::{self, Configurable},
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Linkage, Module};
+
+/// The types of the values a compiled expression works on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JitType {
+    Boolean,
+    Int64,
+    Float64,
+}
+
+impl JitType {
+    fn of(data_type: &DataType) -> Option<JitType> {
+        match data_type {
+            DataType::Int64 => Some(JitType::Int64),
+            DataType::Float64 => Some(JitType::Float64),
+            _ => None,
+        }
+    }
+
+    fn ir_type(self) -> types::Type {
+        match self {
+            JitType::Boolean => types::I8,
+            JitType::Int64 => types::I64,
+            JitType::Float64 => types::F64,
+        }
+    }
+
+    /// Return log2 of the size of a value in the arrays read and written.
+    fn size_shift(self) -> i64 {
+        match self {
+            JitType::Boolean => 0,
+            _ => 3,
+        }
+    }
+}
+
+/// The native function of an expression: it reads the values of the input columns and
+/// writes the value of the expression for the number of rows.
+type NativeFunction = unsafe extern "C" fn(*const *const u8, *mut u8, i64);
+
+/// An expression compiled to native code with Cranelift.
+/// Only expressions over Int64 and Float64 columns without nulls are supported: literals,
+/// comparisons, AND, OR, float arithmetic, and integer arithmetic that wraps on overflow.
+/// These never fail, so the rows are evaluated without short-circuit.
+pub struct JitFunction {
+    module: Option<JITModule>,
+    function: NativeFunction,
+    /// The input columns read, with their types, in the order they are passed.
+    columns: Vec<(usize, JitType)>,
+    output: JitType,
+}
+
+// SAFETY: the code is immutable once finalized, and the function has no state.
+unsafe impl Send for JitFunction {}
+unsafe impl Sync for JitFunction {}
+
+impl JitFunction {
+    /// Compile the expression over batches of the schema, if it is supported.
+    /// Columns and literals alone are not compiled, as there is nothing to gain.
+    pub fn compile(expr: &Expr, schema: &Schema) -> Option<JitFunction> {
+        if !matches!(expr, Expr::BinaryExpr(_)) {
+            return None;
+        }
+        let mut columns = BTreeMap::new();
+        let output = check(expr, schema, &mut columns)?;
+        Self::try_compile(expr, columns, output).ok()
+    }
+
+    fn try_compile(
+        expr: &Expr,
+        columns: BTreeMap<usize, JitType>,
+        output: JitType,
+    ) -> Result<JitFunction> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed")?;
+        let isa = cranelift_native::builder()
+            .map_err(|e| anyhow::anyhow!(e))?
+            .finish(settings::Flags::new(flags))?;
+        let pointer_type = isa.pointer_type();
+        if pointer_type != types::I64 {
+            anyhow::bail!("Only 64-bit targets are supported");
+        }
+        let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+        let mut context = module.make_context();
+        let mut signature = module.make_signature();
+        signature.params.push(AbiParam::new(pointer_type));
+        signature.params.push(AbiParam::new(pointer_type));
+        signature.params.push(AbiParam::new(types::I64));
+        context.func.signature = signature.clone();
+
+        let mut builder_context = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut context.func, &mut builder_context);
+        let entry = builder.create_block();
+        let header = builder.create_block();
+        let body = builder.create_block();
+        let exit = builder.create_block();
+
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let params = builder.block_params(entry).to_vec();
+        let (inputs, out, len) = (params[0], params[1], params[2]);
+        let flags = MemFlags::trusted();
+        let slots = columns
+            .iter()
+            .enumerate()
+            .map(|(slot, (&column, &jit_type))| {
+                let offset = (slot * 8) as i32;
+                let base = builder.ins().load(pointer_type, flags, inputs, offset);
+                (column, (base, jit_type))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let zero = builder.ins().iconst(types::I64, 0);
+        builder.ins().jump(header, &[zero]);
+
+        builder.append_block_param(header, types::I64);
+        builder.switch_to_block(header);
+        let row = builder.block_params(header)[0];
+        let more = builder.ins().icmp(IntCC::SignedLessThan, row, len);
+        builder.ins().brif(more, body, &[], exit, &[]);
+
+        builder.switch_to_block(body);
+        let value = Codegen {
+            builder: &mut builder,
+            slots: &slots,
+            row,
+        }
+        .expr(expr);
+        let offset = builder.ins().ishl_imm(row, output.size_shift());
+        let address = builder.ins().iadd(out, offset);
+        builder.ins().store(flags, value, address, 0);
+        let next = builder.ins().iadd_imm(row, 1);
+        builder.ins().jump(header, &[next]);
+
+        builder.switch_to_block(exit);
+        builder.ins().return_(&[]);
+        builder.seal_all_blocks();
+        builder.finalize();
+
+        let id = module.declare_function("evaluate", Linkage::Export, &signature)?;
+        module.define_function(id, &mut context)?;
+        module.clear_context(&mut context);
+        module.finalize_definitions()?;
+        let code = module.get_finalized_function(id);
+        // SAFETY: the code was compiled with the signature of a native function.
+        let function = unsafe { std::mem::transmute::<*const u8, NativeFunction>(code) };
+        Ok(JitFunction {
+            module: Some(module),
+            function,
+            columns: columns.into_iter().collect(),
+            output,
+        })
+    }
+
+    /// Evaluate the expression over the batch, or return None if a column it reads
+    /// has nulls or is not of the type it was compiled for.
+    pub fn evaluate(&self, batch: &RecordBatch) -> Result<Option<ArrowArrayRef>> {
+        let arrays = self
+            .columns
+            .iter()
+            .map(|(i, _)| to_arrow_array(batch.field(*i)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut pointers = Vec::with_capacity(arrays.len());
+        for (array, (_, jit_type)) in arrays.iter().zip(&self.columns) {
+            if array.null_count() > 0 {
+                return Ok(None);
+            }
+            pointers.push(match (jit_type, array.data_type()) {
+                (JitType::Int64, ArrowDataType::Int64) => {
+                    as_primitive_array::<Int64Type>(array).values().as_ptr() as *const u8
+                }
+                (JitType::Float64, ArrowDataType::Float64) => {
+                    as_primitive_array::<Float64Type>(array).values().as_ptr() as *const u8
+                }
+                _ => return Ok(None),
+            });
+        }
+        let rows = batch.row_count();
+        let function = self.function;
+        // SAFETY: every input has a value for each of the rows, and so has the output.
+        Ok(Some(match self.output {
+            JitType::Boolean => {
+                let mut out = vec![0_u8; rows];
+                unsafe { function(pointers.as_ptr(), out.as_mut_ptr(), rows as i64) };
+                let values = out.into_iter().map(|v| v != 0).collect::<Vec<_>>();
+                std::sync::Arc::new(BooleanArray::from(values))
+            }
+            JitType::Int64 => {
+                let mut out = vec![0_i64; rows];
+                unsafe { function(pointers.as_ptr(), out.as_mut_ptr().cast(), rows as i64) };
+                std::sync::Arc::new(Int64Array::from(out))
+            }
+            JitType::Float64 => {
+                let mut out = vec![0_f64; rows];
+                unsafe { function(pointers.as_ptr(), out.as_mut_ptr().cast(), rows as i64) };
+                std::sync::Arc::new(Float64Array::from(out))
+            }
+        }))
+    }
+}
+
+impl Drop for JitFunction {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: the function can't be called anymore.
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+/// Return the type of the expression if it can be compiled, adding the columns it reads.
+fn check(expr: &Expr, schema: &Schema, columns: &mut BTreeMap<usize, JitType>) -> Option<JitType> {
+    match expr {
+        Expr::Column(column) => {
+            let jit_type = JitType::of(&schema.fields.get(column.i)?.data_type)?;
+            columns.insert(column.i, jit_type);
+            Some(jit_type)
+        }
+        Expr::Literal(ScalarValue::Boolean(_)) => Some(JitType::Boolean),
+        Expr::Literal(ScalarValue::Int64(_)) => Some(JitType::Int64),
+        Expr::Literal(ScalarValue::Float64(_)) => Some(JitType::Float64),
+        Expr::BinaryExpr(b) => {
+            let left = check(&b.left, schema, columns)?;
+            if check(&b.right, schema, columns)? != left {
+                return None;
+            }
+            match (b.op, left) {
+                (Operator::And | Operator::Or, JitType::Boolean) => Some(JitType::Boolean),
+                (
+                    Operator::Eq
+                    | Operator::Neq
+                    | Operator::Lt
+                    | Operator::LtEq
+                    | Operator::Gt
+                    | Operator::GtEq,
+                    JitType::Int64 | JitType::Float64,
+                ) => Some(JitType::Boolean),
+                (Operator::Add | Operator::Subtract | Operator::Multiply, JitType::Float64) => {
+                    Some(JitType::Float64)
+                }
+                (Operator::Add | Operator::Subtract | Operator::Multiply, JitType::Int64)
+                    if b.overflow_mode == OverflowMode::Wrapping =>
+                {
+                    Some(JitType::Int64)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Generates the code evaluating an expression for a row, once it was checked.
+struct Codegen<'a, 'b> {
+    builder: &'a mut FunctionBuilder<'b>,
+    slots: &'a BTreeMap<usize, (Value, JitType)>,
+    row: Value,
+}
+
+impl Codegen<'_, '_> {
+    fn expr(&mut self, expr: &Expr) -> Value {
+        match expr {
+            Expr::Column(column) => {
+                let (base, jit_type) = self.slots[&column.i];
+                let offset = self.builder.ins().ishl_imm(self.row, jit_type.size_shift());
+                let address = self.builder.ins().iadd(base, offset);
+                self.builder
+                    .ins()
+                    .load(jit_type.ir_type(), MemFlags::trusted(), address, 0)
+            }
+            Expr::Literal(ScalarValue::Boolean(b)) => {
+                self.builder.ins().iconst(types::I8, i64::from(*b))
+            }
+            Expr::Literal(ScalarValue::Int64(i)) => self.builder.ins().iconst(types::I64, *i),
+            Expr::Literal(ScalarValue::Float64(f)) => self.builder.ins().f64const(*f),
+            Expr::BinaryExpr(b) => self.binary(b),
+            _ => unreachable!("{} was checked", expr),
+        }
+    }
+
+    fn binary(&mut self, b: &BinaryExpr) -> Value {
+        let left = self.expr(&b.left);
+        let right = self.expr(&b.right);
+        let is_float = self.builder.func.dfg.value_type(left) == types::F64;
+        let ins = self.builder.ins();
+        match b.op {
+            Operator::And => ins.band(left, right),
+            Operator::Or => ins.bor(left, right),
+            Operator::Add if is_float => ins.fadd(left, right),
+            Operator::Subtract if is_float => ins.fsub(left, right),
+            Operator::Multiply if is_float => ins.fmul(left, right),
+            Operator::Add => ins.iadd(left, right),
+            Operator::Subtract => ins.isub(left, right),
+            Operator::Multiply => ins.imul(left, right),
+            op if is_float => self.compare_floats(op, left, right),
+            op => {
+                let cc = match op {
+                    Operator::Eq => IntCC::Equal,
+                    Operator::Neq => IntCC::NotEqual,
+                    Operator::Lt => IntCC::SignedLessThan,
+                    Operator::LtEq => IntCC::SignedLessThanOrEqual,
+                    Operator::Gt => IntCC::SignedGreaterThan,
+                    _ => IntCC::SignedGreaterThanOrEqual,
+                };
+                ins.icmp(cc, left, right)
+            }
+        }
+    }
+
+    /// Compare floats like the sort order does, where NaN is equal to itself and greater
+    /// than any other value.
+    fn compare_floats(&mut self, op: Operator, left: Value, right: Value) -> Value {
+        let builder = &mut *self.builder;
+        let left_nan = builder.ins().fcmp(FloatCC::Unordered, left, left);
+        let right_nan = builder.ins().fcmp(FloatCC::Unordered, right, right);
+        let both_nan = builder.ins().band(left_nan, right_nan);
+        let equal = builder.ins().fcmp(FloatCC::Equal, left, right);
+        let eq = builder.ins().bor(equal, both_nan);
+        let less = builder.ins().fcmp(FloatCC::LessThan, left, right);
+        let left_number = builder.ins().bxor_imm(left_nan, 1);
+        let less_than_nan = builder.ins().band(left_number, right_nan);
+        let lt = builder.ins().bor(less, less_than_nan);
+        match op {
+            Operator::Eq => eq,
+            Operator::Neq => builder.ins().bxor_imm(eq, 1),
+            Operator::Lt => lt,
+            Operator::LtEq => builder.ins().bor(lt, eq),
+            Operator::Gt => {
+                let le = builder.ins().bor(lt, eq);
+                builder.ins().bxor_imm(le, 1)
+            }
+            _ => builder.ins().bxor_imm(lt, 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        data_types::{arrow_field_array::ArrowFieldArray, column_array::ArrayRef, schema::Field},
+        physical_plan::expr::{Column, PhysicalExpr},
+    };
+
+    use arrow::array::Array;
+
+    fn batch(x: Vec<Option<i64>>, y: Vec<f64>) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("x".to_string(), DataType::Int64),
+            Field::new("y".to_string(), DataType::Float64),
+        ]);
+        let fields = vec![
+            Arc::new(ArrowFieldArray::new(Box::new(Int64Array::from(x)))) as ArrayRef,
+            Arc::new(ArrowFieldArray::new(Box::new(Float64Array::from(y)))) as ArrayRef,
+        ];
+        RecordBatch::new(schema, fields)
+    }
+
+    fn binary(op: Operator, left: Expr, right: Expr) -> Expr {
+        Expr::BinaryExpr(BinaryExpr::new(op, left, right))
+    }
+
+    #[test]
+    fn test_jit_function() {
+        let x = || Expr::Column(Column::new(0));
+        let y = || Expr::Column(Column::new(1));
+        let int64 = |v| Expr::Literal(ScalarValue::Int64(v));
+        let float64 = |v| Expr::Literal(ScalarValue::Float64(v));
+        let batch = batch(
+            vec![Some(1), Some(5), Some(-3), Some(i64::MAX)],
+            vec![f64::NAN, 2.5, -0.0, f64::INFINITY],
+        );
+        let schema = batch.schema.clone();
+        let mut exprs = [
+            Operator::Eq,
+            Operator::Neq,
+            Operator::Lt,
+            Operator::LtEq,
+            Operator::Gt,
+            Operator::GtEq,
+        ]
+        .into_iter()
+        .flat_map(|op| {
+            [
+                binary(op, x(), int64(1)),
+                binary(op, y(), float64(2.5)),
+                binary(op, y(), float64(f64::NAN)),
+                binary(op, y(), float64(0.0)),
+            ]
+        })
+        .collect::<Vec<_>>();
+        exprs.extend([
+            binary(
+                Operator::Or,
+                binary(Operator::Gt, x(), int64(2)),
+                binary(Operator::Lt, y(), float64(0.0)),
+            ),
+            binary(
+                Operator::And,
+                binary(Operator::Gt, x(), int64(0)),
+                binary(Operator::Eq, y(), y()),
+            ),
+            binary(
+                Operator::Multiply,
+                binary(Operator::Add, y(), float64(1.5)),
+                binary(Operator::Subtract, y(), float64(0.5)),
+            ),
+            Expr::BinaryExpr(
+                BinaryExpr::new(Operator::Add, x(), int64(1))
+                    .with_overflow_mode(OverflowMode::Wrapping),
+            ),
+        ]);
+        for expr in &exprs {
+            let function = JitFunction::compile(expr, &schema)
+                .unwrap_or_else(|| panic!("{} is not compiled", expr));
+            let native = function.evaluate(&batch).unwrap().unwrap();
+            let expected = to_arrow_array(&expr.evaluate(&batch).unwrap()).unwrap();
+            assert_eq!(native.data(), expected.data(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_jit_unsupported() {
+        let x = || Expr::Column(Column::new(0));
+        let int64 = |v| Expr::Literal(ScalarValue::Int64(v));
+        let schema = batch(vec![], vec![]).schema;
+        // Checked arithmetic and division may fail, so they are left to the evaluators.
+        for expr in [
+            x(),
+            binary(Operator::Add, x(), int64(1)),
+            binary(Operator::Divide, x(), int64(2)),
+        ] {
+            assert!(JitFunction::compile(&expr, &schema).is_none(), "{}", expr);
+        }
+
+        let function = JitFunction::compile(&binary(Operator::Gt, x(), int64(1)), &schema).unwrap();
+        let batch = batch(vec![Some(1), None], vec![0.0, 0.0]);
+        assert!(function.evaluate(&batch).unwrap().is_none());
+    }
+}
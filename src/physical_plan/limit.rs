@@ -0,0 +1,76 @@
+use std::fmt::Display;
+
+use super::plan::{Partitioning, PhysicalPlan, Plan};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+
+use anyhow::Result;
+
+/// Execute a limit over all the partitions of the input, one after the other.
+/// The input is only read until the rows to return are found.
+pub struct LimitExec {
+    pub(crate) input: Box<Plan>,
+    pub(crate) skip: usize,
+    pub(crate) fetch: Option<usize>,
+}
+
+impl LimitExec {
+    pub fn new(input: Plan, skip: usize, fetch: Option<usize>) -> Self {
+        Self {
+            input: Box::new(input),
+            skip,
+            fetch,
+        }
+    }
+}
+
+impl PhysicalPlan for LimitExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let mut skip = self.skip;
+        let mut fetch = self.fetch.unwrap_or(usize::MAX);
+        let mut output = vec![];
+        let mut input = self.input.execute_all()?;
+        while fetch > 0 {
+            let Some(batch) = input.next().transpose()? else {
+                break;
+            };
+            let rows = batch.row_count();
+            if rows <= skip {
+                skip -= rows;
+                continue;
+            }
+            let length = fetch.min(rows - skip);
+            output.push(match (skip, length) {
+                (0, length) if length == rows => batch,
+                (offset, length) => batch.slice(offset, length)?,
+            });
+            skip = 0;
+            fetch -= length;
+        }
+        Ok(Box::new(output.into_iter().map(Ok)))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for LimitExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.fetch {
+            Some(fetch) => write!(f, "LimitExec: skip={}, fetch={}", self.skip, fetch),
+            None => write!(f, "LimitExec: skip={}, fetch=all", self.skip),
+        }
+    }
+}
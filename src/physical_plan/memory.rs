@@ -0,0 +1,232 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::data_types::record_batch::RecordBatch;
+
+use anyhow::{bail, Result};
+
+/// Bytes in use against an optional limit, with the most ever in use.
+#[derive(Debug, Default)]
+struct Usage {
+    limit: Option<usize>,
+    used: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl Usage {
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    /// Reserve `bytes` more, or return the bytes in use if that would exceed the limit.
+    fn try_grow(&self, bytes: usize) -> std::result::Result<(), usize> {
+        let used = self.used.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        if self.limit.is_some_and(|limit| used > limit) {
+            self.used.fetch_sub(bytes, Ordering::SeqCst);
+            return Err(used - bytes);
+        }
+        Ok(())
+    }
+
+    /// Update the peak once a reservation succeeded.
+    fn update_peak(&self) {
+        self.peak
+            .fetch_max(self.used.load(Ordering::SeqCst), Ordering::SeqCst);
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Ordering::SeqCst);
+    }
+}
+
+/// The memory shared by all the queries of a context, with an optional global limit.
+/// Clones share the memory in use.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPool {
+    usage: Arc<Usage>,
+}
+
+impl MemoryPool {
+    /// Create a pool of `limit` bytes, or an unlimited one.
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            usage: Arc::new(Usage::new(limit)),
+        }
+    }
+
+    /// Return the number of bytes reserved by all the queries.
+    pub fn used(&self) -> usize {
+        self.usage.used.load(Ordering::SeqCst)
+    }
+
+    /// Return the most bytes ever reserved by all the queries at the same time.
+    pub fn peak(&self) -> usize {
+        self.usage.peak.load(Ordering::SeqCst)
+    }
+
+    /// Create the budget of a query of `limit` bytes, or an unlimited one,
+    /// that reserves its memory from the pool.
+    pub fn budget(&self, limit: Option<usize>) -> MemoryBudget {
+        MemoryBudget {
+            pool: self.clone(),
+            usage: Arc::new(Usage::new(limit)),
+        }
+    }
+}
+
+/// The memory available to the operators of a query for the data they buffer.
+/// Clones share the memory in use, so the limit applies to all the operators together.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBudget {
+    pool: MemoryPool,
+    usage: Arc<Usage>,
+}
+
+impl MemoryBudget {
+    /// Create a budget of `limit` bytes, or an unlimited one, with its own pool.
+    pub fn new(limit: Option<usize>) -> Self {
+        MemoryPool::default().budget(limit)
+    }
+
+    /// Return the number of bytes reserved by the operators.
+    pub fn used(&self) -> usize {
+        self.usage.used.load(Ordering::SeqCst)
+    }
+
+    /// Return the most bytes ever reserved by the operators at the same time.
+    pub fn peak(&self) -> usize {
+        self.usage.peak.load(Ordering::SeqCst)
+    }
+
+    /// Create an empty reservation for the named operator.
+    pub fn reservation(&self, operator: &'static str) -> MemoryReservation {
+        MemoryReservation {
+            budget: self.clone(),
+            operator,
+            size: 0,
+        }
+    }
+}
+
+/// Memory reserved by an operator, returned to the budget when it is dropped.
+pub struct MemoryReservation {
+    budget: MemoryBudget,
+    operator: &'static str,
+    size: usize,
+}
+
+impl MemoryReservation {
+    /// Reserve `bytes` more, failing if the query or all the queries together
+    /// would use more than their limit.
+    pub fn grow(&mut self, bytes: usize) -> Result<()> {
+        let budget = &self.budget;
+        if let Err(used) = budget.usage.try_grow(bytes) {
+            bail!(
+                "{} exceeded the memory limit of {} bytes: {} bytes are in use and {} more were requested",
+                self.operator,
+                budget.usage.limit.unwrap(),
+                used,
+                bytes
+            );
+        }
+        if let Err(used) = budget.pool.usage.try_grow(bytes) {
+            budget.usage.shrink(bytes);
+            bail!(
+                "{} exceeded the global memory limit of {} bytes: {} bytes are in use by all \
+                 queries and {} more were requested",
+                self.operator,
+                budget.pool.usage.limit.unwrap(),
+                used,
+                bytes
+            );
+        }
+        budget.usage.update_peak();
+        budget.pool.usage.update_peak();
+        self.size += bytes;
+        Ok(())
+    }
+
+    /// Return the number of bytes reserved.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Collect the batches, reserving the memory they use.
+    pub fn collect(
+        &mut self,
+        batches: impl Iterator<Item = Result<RecordBatch>>,
+    ) -> Result<Vec<RecordBatch>> {
+        batches
+            .map(|b| {
+                let b = b?;
+                self.grow(b.memory_size())?;
+                Ok(b)
+            })
+            .collect()
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.budget.usage.shrink(self.size);
+        self.budget.pool.usage.shrink(self.size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_reservation() {
+        let budget = MemoryBudget::new(Some(100));
+        let mut first = budget.reservation("FirstExec");
+        first.grow(60).unwrap();
+        {
+            let mut second = budget.reservation("SecondExec");
+            assert_eq!(
+                second.grow(50).unwrap_err().to_string(),
+                "SecondExec exceeded the memory limit of 100 bytes: \
+                 60 bytes are in use and 50 more were requested"
+            );
+            second.grow(40).unwrap();
+            assert_eq!(budget.used(), 100);
+        }
+        assert_eq!(budget.used(), 60);
+        assert_eq!(first.size(), 60);
+        drop(first);
+        assert_eq!(budget.used(), 0);
+
+        assert_eq!(budget.peak(), 100);
+
+        let mut unlimited = MemoryBudget::default().reservation("Exec");
+        assert!(unlimited.grow(usize::MAX / 2).is_ok());
+    }
+
+    #[test]
+    fn test_memory_pool() {
+        let pool = MemoryPool::new(Some(100));
+        let first = pool.budget(Some(80));
+        let second = pool.budget(None);
+        let mut a = first.reservation("FirstExec");
+        a.grow(70).unwrap();
+        let mut b = second.reservation("SecondExec");
+        assert_eq!(
+            b.grow(40).unwrap_err().to_string(),
+            "SecondExec exceeded the global memory limit of 100 bytes: \
+             70 bytes are in use by all queries and 40 more were requested"
+        );
+        assert_eq!(second.used(), 0);
+        b.grow(30).unwrap();
+        assert_eq!(pool.used(), 100);
+        drop(a);
+        assert_eq!((pool.used(), pool.peak()), (30, 100));
+        assert_eq!((first.used(), first.peak()), (0, 70));
+        assert_eq!(second.peak(), 30);
+    }
+}
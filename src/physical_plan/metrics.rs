@@ -0,0 +1,136 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use super::plan::{Partitioning, PhysicalPlan, Plan};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+
+use anyhow::Result;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use std::time::Instant;
+
+/// wasm32-unknown-unknown has no clock, and `std::time::Instant` panics there, so
+/// nothing is timed: no time elapses between two instants.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+#[derive(Clone, Copy)]
+pub(crate) struct Instant;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+impl Instant {
+    pub(crate) fn now() -> Self {
+        Instant
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// The runtime metrics of an operator.
+#[derive(Debug, Default)]
+pub struct OperatorMetrics {
+    output_rows: AtomicUsize,
+    output_batches: AtomicUsize,
+    elapsed_nanos: AtomicU64,
+}
+
+impl OperatorMetrics {
+    pub fn output_rows(&self) -> usize {
+        self.output_rows.load(Ordering::Relaxed)
+    }
+
+    pub fn output_batches(&self) -> usize {
+        self.output_batches.load(Ordering::Relaxed)
+    }
+
+    /// Return the time spent producing the batches, including the time of the inputs.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_nanos.load(Ordering::Relaxed))
+    }
+
+    fn add_elapsed(&self, start: Instant) {
+        let nanos = start.elapsed().as_nanos() as u64;
+        self.elapsed_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+/// Record the metrics of the input as it is executed.
+/// It is transparent in the plan tree and shows the metrics after the input.
+pub struct MetricsExec {
+    input: Box<Plan>,
+    metrics: OperatorMetrics,
+}
+
+impl MetricsExec {
+    pub fn new(input: Plan) -> Self {
+        Self {
+            input: Box::new(input),
+            metrics: OperatorMetrics::default(),
+        }
+    }
+
+    pub fn metrics(&self) -> &OperatorMetrics {
+        &self.metrics
+    }
+
+    pub fn input(&self) -> &Plan {
+        &self.input
+    }
+
+    /// Record a batch produced by the input, which started producing it at `start`.
+    pub fn record(&self, batch: &RecordBatch, start: Instant) {
+        self.metrics.add_elapsed(start);
+        self.metrics
+            .output_rows
+            .fetch_add(batch.row_count(), Ordering::Relaxed);
+        self.metrics.output_batches.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl PhysicalPlan for MetricsExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        let start = Instant::now();
+        let mut batches = self.input.execute(partition)?;
+        self.metrics.add_elapsed(start);
+        Ok(Box::new(std::iter::from_fn(move || {
+            let start = Instant::now();
+            let batch = batches.next();
+            match &batch {
+                Some(Ok(batch)) => self.record(batch, start),
+                Some(Err(_)) | None => self.metrics.add_elapsed(start),
+            }
+            batch
+        })))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        self.input.children()
+    }
+}
+
+impl Display for MetricsExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, metrics=[output_rows={}, output_batches={}, elapsed={:?}]",
+            self.input,
+            self.metrics.output_rows(),
+            self.metrics.output_batches(),
+            self.metrics.elapsed()
+        )
+    }
+}
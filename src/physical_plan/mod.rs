@@ -1,7 +1,32 @@
 pub mod aggregate;
+pub mod checked;
+pub mod compiled;
+pub mod distinct_on;
 pub mod expr;
+pub mod fill;
+pub mod fixed_width;
+pub mod fragment;
+pub mod functions;
+pub mod fuzzy;
+pub mod gap_fill;
 pub mod hash;
+pub mod hash_join;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod limit;
+pub mod memory;
+pub mod metrics;
+pub mod nested;
+pub mod observed;
+pub mod percentile;
+pub mod pipeline;
 pub mod plan;
 pub mod projection;
 pub mod scan;
 pub mod selection;
+pub mod selection_projection;
+pub mod sessionize;
+pub mod sort;
+pub mod sort_key;
+pub mod url;
+pub mod window;
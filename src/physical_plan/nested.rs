@@ -0,0 +1,201 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::expr::{Expr, PhysicalExpr};
+use crate::data_types::{
+    arrow_field_array::ArrowFieldArray,
+    column_array::{ArrayRef, ColumnArray},
+    record_batch::RecordBatch,
+};
+
+use anyhow::{anyhow, Result};
+use arrow::{
+    array::{Array, ListArray, StructArray, UInt32Array},
+    compute::take,
+};
+use serde::{Deserialize, Serialize};
+
+/// Access to a named field of a struct.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GetField {
+    pub expr: Box<Expr>,
+    pub name: String,
+}
+
+impl GetField {
+    pub fn new(expr: Expr, name: String) -> Self {
+        Self {
+            expr: Box::new(expr),
+            name,
+        }
+    }
+}
+
+impl PhysicalExpr for GetField {
+    fn evaluate(&self, input: &RecordBatch) -> Result<ArrayRef> {
+        let value = self.expr.evaluate(input)?;
+        let fields = arrow_array::<StructArray>(&value, &self.expr)?;
+        let field = fields
+            .column_by_name(&self.name)
+            .ok_or_else(|| anyhow!("No field named {} in {}", self.name, self.expr))?;
+        // The values of the fields are undefined in null structs, so null them out.
+        let field = match fields.null_count() {
+            0 => field.clone(),
+            _ => {
+                let indices = (0..fields.len())
+                    .map(|i| fields.is_valid(i).then_some(i as u32))
+                    .collect::<UInt32Array>();
+                take(field.as_ref(), &indices, None)?
+            }
+        };
+        Ok(Arc::new(ArrowFieldArray::new(Box::new(field))))
+    }
+}
+
+impl Display for GetField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.expr, self.name)
+    }
+}
+
+/// Access to an item of a list by its zero-based Int64 index.
+/// The item is null when the list or the index is null, or the index is out of bounds.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GetIndex {
+    pub expr: Box<Expr>,
+    pub index: Box<Expr>,
+}
+
+impl GetIndex {
+    pub fn new(expr: Expr, index: Expr) -> Self {
+        Self {
+            expr: Box::new(expr),
+            index: Box::new(index),
+        }
+    }
+}
+
+impl PhysicalExpr for GetIndex {
+    fn evaluate(&self, input: &RecordBatch) -> Result<ArrayRef> {
+        let value = self.expr.evaluate(input)?;
+        let lists = arrow_array::<ListArray>(&value, &self.expr)?;
+        let index = self.index.evaluate(input)?;
+        let offsets = lists.value_offsets();
+        let indices = (0..lists.len())
+            .map(|i| {
+                if lists.is_null(i) || index.is_null(i) {
+                    return Ok(None);
+                }
+                let index = *index.get_value(i)?.downcast_ref::<i64>().unwrap();
+                let (start, end) = (offsets[i] as i64, offsets[i + 1] as i64);
+                Ok((index >= 0 && start + index < end).then(|| (start + index) as u32))
+            })
+            .collect::<Result<UInt32Array>>()?;
+        let items = take(lists.values().as_ref(), &indices, None)?;
+        Ok(Arc::new(ArrowFieldArray::new(Box::new(items))))
+    }
+}
+
+impl Display for GetIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]", self.expr, self.index)
+    }
+}
+
+/// Return the Arrow array behind a nested value.
+/// Nested values are only produced by Arrow arrays, never by literals.
+fn arrow_array<'a, T: 'static>(value: &'a ArrayRef, expr: &Expr) -> Result<&'a T> {
+    value
+        .as_arrow()
+        .and_then(|array| array.as_any().downcast_ref::<T>())
+        .ok_or_else(|| {
+            anyhow!(
+                "{} of type {} is not a nested array",
+                expr,
+                value.get_type()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_types::{
+            column_array::DataType,
+            schema::{Field, Schema},
+        },
+        physical_plan::expr::{Column, ScalarValue},
+    };
+
+    use arrow::{
+        array::{Int32Array, Int64Array},
+        datatypes::{Field as ArrowField, Int32Type},
+    };
+    use std::sync::Arc;
+
+    fn batch() -> RecordBatch {
+        let point = StructArray::from((
+            vec![(
+                ArrowField::new("x", arrow::datatypes::DataType::Int32, true),
+                Arc::new(Int32Array::from(vec![1, 2, 3])) as Arc<dyn Array>,
+            )],
+            arrow::buffer::Buffer::from([0b101]),
+        ));
+        let tags = ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(10), Some(11)]),
+            Some(vec![]),
+            None,
+        ]);
+        let fields = vec![
+            Arc::new(ArrowFieldArray::new(Box::new(point))) as ArrayRef,
+            Arc::new(ArrowFieldArray::new(Box::new(tags))) as ArrayRef,
+            Arc::new(ArrowFieldArray::new(Box::new(Int64Array::from(vec![
+                1, 0, 0,
+            ])))) as ArrayRef,
+        ];
+        let schema = Schema::new(
+            fields
+                .iter()
+                .zip(["point", "tags", "i"])
+                .map(|(f, name)| Field::new(name.to_string(), f.get_type()))
+                .collect(),
+        );
+        RecordBatch::new(schema, fields)
+    }
+
+    fn int32_values(array: &ArrayRef) -> Vec<Option<i32>> {
+        (0..array.size())
+            .map(|i| {
+                (!array.is_null(i))
+                    .then(|| *array.get_value(i).unwrap().downcast_ref::<i32>().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_field() {
+        let expr = GetField::new(Expr::Column(Column::new(0)), "x".to_string());
+        assert_eq!(expr.to_string(), "#0.x");
+        let values = expr.evaluate(&batch()).unwrap();
+        assert_eq!(values.get_type(), DataType::Int32);
+        assert_eq!(int32_values(&values), vec![Some(1), None, Some(3)]);
+
+        let expr = GetField::new(Expr::Column(Column::new(0)), "y".to_string());
+        assert!(expr.evaluate(&batch()).is_err());
+    }
+
+    #[test]
+    fn test_get_index() {
+        let expr = GetIndex::new(Expr::Column(Column::new(1)), Expr::Column(Column::new(2)));
+        assert_eq!(expr.to_string(), "#1[#2]");
+        let values = expr.evaluate(&batch()).unwrap();
+        assert_eq!(int32_values(&values), vec![Some(11), None, None]);
+
+        let expr = GetIndex::new(
+            Expr::Column(Column::new(1)),
+            Expr::Literal(ScalarValue::Int64(-1)),
+        );
+        let values = expr.evaluate(&batch()).unwrap();
+        assert_eq!(int32_values(&values), vec![None, None, None]);
+    }
+}
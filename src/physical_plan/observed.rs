@@ -0,0 +1,130 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::plan::{Partitioning, PhysicalPlan, Plan};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+
+use anyhow::Result;
+
+type HookFn = dyn Fn(&Plan, usize, usize) + Send + Sync;
+
+/// Called with an operator, its depth in the plan and the partition, when a partition
+/// of the operator starts executing.
+#[derive(Clone)]
+pub struct OperatorHook(Arc<HookFn>);
+
+impl OperatorHook {
+    pub fn new(hook: impl Fn(&Plan, usize, usize) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for OperatorHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OperatorHook")
+    }
+}
+
+/// Call a hook when a partition of the input starts executing.
+/// It is transparent in the plan tree.
+pub struct ObservedExec {
+    input: Box<Plan>,
+    depth: usize,
+    hook: OperatorHook,
+}
+
+impl ObservedExec {
+    pub fn new(input: Plan, depth: usize, hook: OperatorHook) -> Self {
+        Self {
+            input: Box::new(input),
+            depth,
+            hook,
+        }
+    }
+
+    pub fn input(&self) -> &Plan {
+        &self.input
+    }
+
+    /// Call the hook for a partition of the input.
+    pub fn start(&self, partition: usize) {
+        (self.hook.0)(&self.input, self.depth, partition);
+    }
+}
+
+impl PhysicalPlan for ObservedExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.start(partition);
+        self.input.execute(partition)
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        self.input.children()
+    }
+}
+
+impl Display for ObservedExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.input.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{
+            arrow_field_array::ArrowFieldArray,
+            column_array::{ArrayRef, DataType},
+            schema::Field,
+        },
+        physical_plan::scan::ScanExec,
+    };
+
+    use arrow::array::Int64Array;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_observed() {
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
+        let id = Int64Array::from(vec![1, 2, 3]);
+        let batch = RecordBatch::new(
+            schema.clone(),
+            vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef],
+        );
+        let source = Source::Mem(MemoryDataSource::new(schema, vec![batch]));
+        let started = Arc::new(Mutex::new(vec![]));
+        let hook = {
+            let started = started.clone();
+            OperatorHook::new(move |plan, depth, partition| {
+                started
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {} {}", plan, depth, partition))
+            })
+        };
+        let plan = ObservedExec::new(
+            Plan::Scan(ScanExec::new(source, vec!["id".to_string()])),
+            1,
+            hook,
+        );
+        assert_eq!(plan.to_string(), "ScanExec: projection=id");
+        assert!(started.lock().unwrap().is_empty());
+        assert_eq!(plan.execute(0).unwrap().count(), 1);
+        assert_eq!(
+            *started.lock().unwrap(),
+            vec!["ScanExec: projection=id 1 0"]
+        );
+    }
+}
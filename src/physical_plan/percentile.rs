@@ -0,0 +1,351 @@
+use std::any::Any;
+
+use super::aggregate::Accumulator;
+
+use anyhow::{bail, Result};
+
+/// Accumulator for the exact MEDIAN and PERCENTILE_CONT.
+/// All the values are buffered and the result is interpolated between the two closest ranks.
+pub struct PercentileAccumulator {
+    percentile: Option<f64>,
+    values: Vec<f64>,
+}
+
+impl PercentileAccumulator {
+    /// Create an accumulator for a fixed percentile, or for the percentile
+    /// given as the second argument when it is None.
+    pub fn new(percentile: Option<f64>) -> Self {
+        Self {
+            percentile,
+            values: vec![],
+        }
+    }
+}
+
+impl Accumulator for PercentileAccumulator {
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        let (value, percentile) = percentile_args(values)?;
+        if self.percentile.is_none() {
+            self.percentile = percentile;
+        }
+        if let Some(value) = value {
+            self.values.push(value);
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+        Ok(vec![
+            Some(Box::new(self.values.clone())),
+            self.percentile.map(|p| Box::new(p) as Box<dyn Any>),
+        ])
+    }
+
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        let mut state = state.into_iter();
+        if let Some(values) = state.next().flatten() {
+            self.values
+                .extend(values.downcast_ref::<Vec<f64>>().unwrap().iter());
+        }
+        if self.percentile.is_none() {
+            self.percentile = state.next().flatten().map(|p| *p.downcast_ref().unwrap());
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+        let percentile = match self.percentile {
+            Some(percentile) if !self.values.is_empty() => percentile,
+            _ => return Ok(None),
+        };
+        let mut values = self.values.clone();
+        values.sort_by(|l, r| l.total_cmp(r));
+        let rank = percentile * (values.len() - 1) as f64;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+        let value = values[lower] + (values[upper] - values[lower]) * (rank - lower as f64);
+        Ok(Some(Box::new(value)))
+    }
+}
+
+/// Accumulator for APPROX_PERCENTILE, which summarizes the values in a [`TDigest`].
+pub struct ApproxPercentileAccumulator {
+    percentile: Option<f64>,
+    digest: TDigest,
+}
+
+impl ApproxPercentileAccumulator {
+    pub fn new() -> Self {
+        Self {
+            percentile: None,
+            digest: TDigest::new(DEFAULT_COMPRESSION),
+        }
+    }
+}
+
+impl Default for ApproxPercentileAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Accumulator for ApproxPercentileAccumulator {
+    fn update(&mut self, values: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        let (value, percentile) = percentile_args(values)?;
+        if self.percentile.is_none() {
+            self.percentile = percentile;
+        }
+        if let Some(value) = value {
+            self.digest.add(value);
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<Vec<Option<Box<dyn Any>>>> {
+        Ok(vec![
+            Some(Box::new(self.digest.clone())),
+            self.percentile.map(|p| Box::new(p) as Box<dyn Any>),
+        ])
+    }
+
+    fn merge(&mut self, state: Vec<Option<Box<dyn Any>>>) -> Result<()> {
+        let mut state = state.into_iter();
+        if let Some(digest) = state.next().flatten() {
+            self.digest.merge(digest.downcast_ref::<TDigest>().unwrap());
+        }
+        if self.percentile.is_none() {
+            self.percentile = state.next().flatten().map(|p| *p.downcast_ref().unwrap());
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> Result<Option<Box<dyn Any>>> {
+        Ok(self
+            .percentile
+            .and_then(|p| self.digest.quantile(p))
+            .map(|v| Box::new(v) as Box<dyn Any>))
+    }
+}
+
+/// Return the value and the percentile of a row, checking that the percentile is in [0, 1].
+fn percentile_args(values: Vec<Option<Box<dyn Any>>>) -> Result<(Option<f64>, Option<f64>)> {
+    let mut values = values.into_iter();
+    let value = values
+        .next()
+        .flatten()
+        .map(|v| to_f64(v.as_ref()))
+        .transpose()?;
+    let percentile = values
+        .next()
+        .flatten()
+        .map(|p| to_f64(p.as_ref()))
+        .transpose()?;
+    if let Some(percentile) = percentile {
+        if !(0.0..=1.0).contains(&percentile) {
+            bail!("Percentile must be between 0 and 1, got {}", percentile);
+        }
+    }
+    Ok((value, percentile))
+}
+
+/// Convert a numeric value to f64.
+pub(super) fn to_f64(value: &dyn Any) -> Result<f64> {
+    if let Some(v) = value.downcast_ref::<i32>() {
+        return Ok(*v as f64);
+    }
+    if let Some(v) = value.downcast_ref::<i64>() {
+        return Ok(*v as f64);
+    }
+    if let Some(v) = value.downcast_ref::<f32>() {
+        return Ok(*v as f64);
+    }
+    if let Some(v) = value.downcast_ref::<f64>() {
+        return Ok(*v);
+    }
+    bail!("Expected a numeric value")
+}
+
+const DEFAULT_COMPRESSION: f64 = 100.0;
+const BUFFER_SIZE: usize = 500;
+
+#[derive(Clone, Copy, Debug)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A t-digest, which approximates the distribution of a stream of values with a bounded
+/// number of centroids. Centroids are kept small near the tails, so extreme percentiles
+/// such as P95 and P99 are accurate.
+#[derive(Clone, Debug)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<Centroid>,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        Self {
+            compression,
+            centroids: vec![],
+            buffer: vec![],
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(Centroid {
+            mean: value,
+            weight: 1.0,
+        });
+        if self.buffer.len() >= BUFFER_SIZE {
+            self.compress();
+        }
+    }
+
+    pub fn merge(&mut self, other: &TDigest) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.buffer.extend(&other.centroids);
+        self.buffer.extend(&other.buffer);
+        self.compress();
+    }
+
+    /// Return the approximate value at the given quantile, or None if there are no values.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let mut digest = self.clone();
+        digest.compress();
+        let centroids = &digest.centroids;
+        let total: f64 = centroids.iter().map(|c| c.weight).sum();
+        if centroids.is_empty() {
+            return None;
+        }
+        if centroids.len() == 1 {
+            return Some(centroids[0].mean);
+        }
+
+        // Interpolate between the centers of the centroids around the target rank,
+        // and between the extremes and the first and last centers.
+        let target = q * total;
+        let first = centroids[0];
+        if target < first.weight / 2.0 {
+            return Some(self.min + (first.mean - self.min) * target / (first.weight / 2.0));
+        }
+        let mut center = first.weight / 2.0;
+        for pair in centroids.windows(2) {
+            let next_center = center + (pair[0].weight + pair[1].weight) / 2.0;
+            if target < next_center {
+                let fraction = (target - center) / (next_center - center);
+                return Some(pair[0].mean + (pair[1].mean - pair[0].mean) * fraction);
+            }
+            center = next_center;
+        }
+        let last = centroids[centroids.len() - 1];
+        let fraction = ((target - center) / (last.weight / 2.0)).min(1.0);
+        Some(last.mean + (self.max - last.mean) * fraction)
+    }
+
+    /// Merge the buffered values into the centroids.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all = std::mem::take(&mut self.centroids);
+        all.append(&mut self.buffer);
+        all.sort_by(|l, r| l.mean.total_cmp(&r.mean));
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+
+        let mut merged = Vec::with_capacity(all.len());
+        let mut current = all[0];
+        let mut weight_before = 0.0;
+        for c in all.into_iter().skip(1) {
+            let weight = current.weight + c.weight;
+            let q = (weight_before + weight / 2.0) / total;
+            // The size bound of the original t-digest paper.
+            if weight <= 4.0 * total * q * (1.0 - q) / self.compression {
+                current.mean += (c.mean - current.mean) * c.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_before += current.weight;
+                merged.push(current);
+                current = c;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(acc: &dyn Accumulator) -> Option<f64> {
+        acc.evaluate()
+            .unwrap()
+            .map(|v| *v.downcast_ref::<f64>().unwrap())
+    }
+
+    #[test]
+    fn test_percentile_accumulator() {
+        let mut median = PercentileAccumulator::new(Some(0.5));
+        assert_eq!(evaluate(&median), None);
+        for v in [4_i64, 1, 3, 2] {
+            median.update(vec![Some(Box::new(v))]).unwrap();
+        }
+        median.update(vec![None]).unwrap();
+        assert_eq!(evaluate(&median), Some(2.5));
+
+        let mut p90 = PercentileAccumulator::new(None);
+        for v in 1..=11 {
+            p90.update(vec![Some(Box::new(v as f64)), Some(Box::new(0.9))])
+                .unwrap();
+        }
+        assert_eq!(evaluate(&p90), Some(10.0));
+
+        assert_eq!(
+            p90.update(vec![Some(Box::new(1.0)), Some(Box::new(1.5))])
+                .unwrap_err()
+                .to_string(),
+            "Percentile must be between 0 and 1, got 1.5"
+        );
+    }
+
+    #[test]
+    fn test_tdigest() {
+        let mut digest = TDigest::new(DEFAULT_COMPRESSION);
+        assert_eq!(digest.quantile(0.5), None);
+        for v in 0..100_000 {
+            digest.add(v as f64);
+        }
+        assert!(digest.centroids.len() < 1000);
+        for q in [0.01, 0.5, 0.95, 0.99] {
+            let expected = q * 99_999.0;
+            let actual = digest.quantile(q).unwrap();
+            assert!((actual - expected).abs() < 100.0, "{} {}", q, actual);
+        }
+        assert_eq!(digest.quantile(0.0), Some(0.0));
+        assert_eq!(digest.quantile(1.0), Some(99_999.0));
+    }
+
+    #[test]
+    fn test_approx_percentile_merge() {
+        let mut left = ApproxPercentileAccumulator::new();
+        let mut right = ApproxPercentileAccumulator::new();
+        for v in 1..=50 {
+            left.update(vec![Some(Box::new(v)), Some(Box::new(0.5))])
+                .unwrap();
+            right
+                .update(vec![Some(Box::new(v + 50)), Some(Box::new(0.5))])
+                .unwrap();
+        }
+        left.merge(right.state().unwrap()).unwrap();
+        let median = evaluate(&left).unwrap();
+        assert!((median - 50.5).abs() < 1.0, "{}", median);
+    }
+}
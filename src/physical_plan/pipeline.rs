@@ -0,0 +1,460 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Display,
+    iter,
+    ops::Range,
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+};
+
+use super::{
+    expr::Parameters,
+    hash::HashExec,
+    metrics::Instant,
+    plan::{Partitioning, PhysicalPlan, Plan},
+    scan::ScanExec,
+};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+
+use anyhow::{bail, Result};
+use rayon::ThreadPool;
+
+/// The number of rows of the morsels of a session that does not set it.
+pub const DEFAULT_MORSEL_SIZE: usize = 16 * 1024;
+
+/// The number of morsels in flight for every thread of the pool: pulled from the scan
+/// and not yet returned by the pipeline. It bounds the batches buffered, and the morsels
+/// processed beyond the ones consumed, e.g. by a limit.
+const MORSELS_PER_THREAD: usize = 4;
+
+/// Splits the batches of a scan into morsels of rows, processed on a thread pool.
+#[derive(Clone)]
+pub struct MorselScheduler {
+    thread_pool: Arc<ThreadPool>,
+    morsel_size: usize,
+}
+
+impl MorselScheduler {
+    pub fn try_new(thread_pool: Arc<ThreadPool>, morsel_size: usize) -> Result<Self> {
+        if morsel_size == 0 {
+            bail!("The morsel size must be at least 1");
+        }
+        Ok(Self {
+            thread_pool,
+            morsel_size,
+        })
+    }
+
+    /// Split the batch into morsels of `morsel_size` rows, without copying the columns.
+    fn split(&self, batch: RecordBatch) -> Result<Vec<RecordBatch>> {
+        let rows = batch.row_count();
+        if rows <= self.morsel_size {
+            return Ok(vec![batch]);
+        }
+        (0..rows)
+            .step_by(self.morsel_size)
+            .map(|offset| batch.slice(offset, self.morsel_size.min(rows - offset)))
+            .collect()
+    }
+
+    /// Return the number of morsels in flight at most.
+    fn capacity(&self) -> usize {
+        self.thread_pool.current_num_threads() * MORSELS_PER_THREAD
+    }
+}
+
+impl std::fmt::Debug for MorselScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MorselScheduler")
+            .field("threads", &self.thread_pool.current_num_threads())
+            .field("morsel_size", &self.morsel_size)
+            .finish()
+    }
+}
+
+/// Execute a pipeline, streaming operators above a scan, possibly topped by a partial
+/// aggregate, morsel by morsel.
+///
+/// Every thread of the pool runs a worker, which pulls the next morsel from the scan
+/// shared by all the workers and runs it through all the operators of the pipeline,
+/// so idle workers take the work and the load is balanced across cores. The processed
+/// morsels are sent back through a channel with room for the morsels in flight, and
+/// returned in the order of the scan. Only a bounded number of morsels is pulled ahead
+/// of the consumer, so a limit above the pipeline stops the scan once it has its rows.
+pub struct PipelineExec {
+    pub(crate) input: Box<Plan>,
+    scheduler: MorselScheduler,
+}
+
+impl PipelineExec {
+    pub fn new(input: Plan, scheduler: MorselScheduler) -> Self {
+        Self {
+            input: Box::new(input),
+            scheduler,
+        }
+    }
+
+    /// Return true if the plan is a pipeline: projections or selections, possibly
+    /// with other operators that process every batch on their own, above a scan.
+    pub fn is_pipeline(plan: &Plan) -> bool {
+        Self::operator_count(plan).is_some_and(|count| count > 0)
+    }
+
+    /// Return true if the plan can be the input of a partial aggregate in a pipeline:
+    /// a pipeline, or a scan on its own.
+    pub fn is_aggregate_input(plan: &Plan) -> bool {
+        Self::operator_count(plan).is_some()
+    }
+
+    /// Return the number of projections and selections above the scan at the bottom of
+    /// the plan, or None if the plan has other operators.
+    fn operator_count(plan: &Plan) -> Option<usize> {
+        let mut count = 0;
+        let mut plan = plan;
+        loop {
+            plan = match plan {
+                Plan::Scan(_) => return Some(count),
+                Plan::Projection(p) => {
+                    count += 1;
+                    p.input()
+                }
+                Plan::Selection(s) => {
+                    count += 1;
+                    s.input()
+                }
+                Plan::SelectionProjection(f) => {
+                    count += 1;
+                    f.input()
+                }
+                Plan::Checked(c) => c.input(),
+                Plan::Metrics(m) => m.input(),
+                Plan::Observed(o) => o.input(),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Run a morsel of the scan through the operators of the pipeline.
+    fn process_morsel(plan: &Plan, morsel: RecordBatch) -> Result<RecordBatch> {
+        match plan {
+            Plan::Scan(_) => Ok(morsel),
+            Plan::Projection(p) => p.process_batch(&Self::process_morsel(p.input(), morsel)?),
+            Plan::Selection(s) => s.process_batch(&Self::process_morsel(s.input(), morsel)?),
+            Plan::SelectionProjection(f) => {
+                f.process_batch(&Self::process_morsel(f.input(), morsel)?)
+            }
+            // The groups of the morsel are aggregated, to be merged with the groups of
+            // the other morsels above the pipeline.
+            Plan::Hash(h) => h.aggregate(iter::once(Self::process_morsel(&h.input, morsel))),
+            Plan::Checked(c) => {
+                let batch = Self::process_morsel(c.input(), morsel)?;
+                c.check(&c.input().schema(), &batch);
+                Ok(batch)
+            }
+            Plan::Metrics(m) => {
+                let start = Instant::now();
+                let batch = Self::process_morsel(m.input(), morsel)?;
+                m.record(&batch, start);
+                Ok(batch)
+            }
+            Plan::Observed(o) => Self::process_morsel(o.input(), morsel),
+            _ => bail!("{} can't process morsels", plan),
+        }
+    }
+
+    /// Return the partial aggregate at the top of the pipeline, if any.
+    fn partial_aggregate(&self) -> Option<&HashExec> {
+        let mut plan = self.input.as_ref();
+        loop {
+            plan = match plan {
+                Plan::Hash(h) => return Some(h),
+                Plan::Checked(c) => c.input(),
+                Plan::Metrics(m) => m.input(),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Return the scan at the bottom of the pipeline. The observed operators of the
+    /// pipeline are started on the way down, as they are not executed on their own.
+    fn start_scan(&self) -> Result<&ScanExec> {
+        let mut plan = self.input.as_ref();
+        loop {
+            plan = match plan {
+                Plan::Scan(scan) => return Ok(scan),
+                Plan::Projection(p) => p.input(),
+                Plan::Selection(s) => s.input(),
+                Plan::SelectionProjection(f) => f.input(),
+                Plan::Hash(h) => &h.input,
+                Plan::Checked(c) => c.input(),
+                Plan::Metrics(m) => m.input(),
+                Plan::Observed(o) => {
+                    o.start(0);
+                    o.input()
+                }
+                _ => bail!("{} is not the scan of a pipeline", plan),
+            }
+        }
+    }
+}
+
+impl PhysicalPlan for PipelineExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    /// The morsels of all the partitions of the scan are processed together.
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let scan = self.start_scan()?;
+        let (sender, receiver) = mpsc::sync_channel(self.scheduler.capacity());
+        Ok(Box::new(Morsels {
+            pipeline: self,
+            scan: Mutex::new(SharedScan {
+                scan,
+                partitions: 0..scan.output_partitioning().partition_count(),
+                batches: None,
+                morsels: VecDeque::new(),
+                pulled: 0,
+                exhausted: false,
+            }),
+            // The placeholders evaluate to the parameters of the calling thread.
+            parameters: Parameters::current(),
+            sender,
+            receiver,
+            processed: BTreeMap::new(),
+            next: 0,
+            done: false,
+        }))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for PipelineExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PipelineExec: morsel_size={}",
+            self.scheduler.morsel_size
+        )
+    }
+}
+
+/// The scan of a pipeline, shared by its workers. The partitions are read one after the
+/// other, and the morsels are numbered in the order they are pulled.
+struct SharedScan<'a> {
+    scan: &'a ScanExec,
+    partitions: Range<usize>,
+    batches: Option<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + 'a>>,
+    /// The morsels of the last batch read, not pulled yet.
+    morsels: VecDeque<RecordBatch>,
+    /// The number of morsels pulled.
+    pulled: usize,
+    /// True once the scan has no more morsels, or failed.
+    exhausted: bool,
+}
+
+impl SharedScan<'_> {
+    /// Pull the next morsel with its number, or return None once the scan is exhausted.
+    /// An error reading the scan is the last morsel.
+    fn pull(&mut self, scheduler: &MorselScheduler) -> Option<(usize, Result<RecordBatch>)> {
+        let morsel = loop {
+            if let Some(morsel) = self.morsels.pop_front() {
+                break Ok(morsel);
+            }
+            if self.exhausted {
+                return None;
+            }
+            let batch = match &mut self.batches {
+                Some(batches) => batches.next(),
+                None => None,
+            };
+            match batch {
+                Some(batch) => match batch.and_then(|batch| scheduler.split(batch)) {
+                    Ok(morsels) => self.morsels.extend(morsels),
+                    Err(e) => break Err(e),
+                },
+                None => match self.partitions.next() {
+                    Some(partition) => match self.scan.scan_partition(partition) {
+                        Ok(batches) => self.batches = Some(batches),
+                        Err(e) => break Err(e),
+                    },
+                    None => self.exhausted = true,
+                },
+            }
+        };
+        if morsel.is_err() {
+            self.exhausted = true;
+        }
+        self.pulled += 1;
+        Some((self.pulled - 1, morsel))
+    }
+}
+
+/// The processed morsels of a pipeline, in the order of the scan.
+struct Morsels<'a> {
+    pipeline: &'a PipelineExec,
+    scan: Mutex<SharedScan<'a>>,
+    parameters: Parameters,
+    sender: SyncSender<(usize, Result<RecordBatch>)>,
+    receiver: Receiver<(usize, Result<RecordBatch>)>,
+    /// The morsels processed ahead of the next one, by number.
+    processed: BTreeMap<usize, Result<RecordBatch>>,
+    /// The number of the next morsel to return.
+    next: usize,
+    /// True once all the morsels were returned, or one failed.
+    done: bool,
+}
+
+impl Morsels<'_> {
+    /// Run a worker on every thread of the pool until the morsels in flight fill the
+    /// channel, or the scan is exhausted.
+    fn run_workers(&self) {
+        let (pipeline, scan, parameters) = (self.pipeline, &self.scan, &self.parameters);
+        let scheduler = &pipeline.scheduler;
+        let limit = self.next + scheduler.capacity();
+        let worker = |sender: SyncSender<(usize, Result<RecordBatch>)>| loop {
+            let morsel = {
+                let mut scan = scan.lock().unwrap();
+                if scan.pulled >= limit {
+                    return;
+                }
+                scan.pull(scheduler)
+            };
+            let Some((number, morsel)) = morsel else {
+                return;
+            };
+            let processed = morsel.and_then(|morsel| {
+                parameters.scope(|| PipelineExec::process_morsel(&pipeline.input, morsel))
+            });
+            // The channel has room for all the morsels in flight, so it never blocks,
+            // and its receiver outlives the workers.
+            if sender.send((number, processed)).is_err() {
+                return;
+            }
+        };
+        let senders = (0..scheduler.thread_pool.current_num_threads())
+            .map(|_| self.sender.clone())
+            .collect::<Vec<_>>();
+        scheduler.thread_pool.install(|| {
+            rayon::scope(|s| {
+                for sender in senders {
+                    s.spawn(|_| worker(sender));
+                }
+            })
+        });
+    }
+}
+
+impl Iterator for Morsels<'_> {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(processed) = self.processed.remove(&self.next) {
+                self.next += 1;
+                self.done = processed.is_err();
+                return Some(processed);
+            }
+            if self.done {
+                return None;
+            }
+            if self.scan.lock().unwrap().exhausted && self.next == self.pulled() {
+                self.done = true;
+                // An aggregate without groups has a row even without morsels.
+                return match self.pipeline.partial_aggregate() {
+                    Some(h) if self.next == 0 && h.group_expr.is_empty() => {
+                        Some(h.aggregate(iter::empty()))
+                    }
+                    _ => None,
+                };
+            }
+            self.run_workers();
+            self.processed.extend(self.receiver.try_iter());
+        }
+    }
+}
+
+impl Morsels<'_> {
+    fn pulled(&self) -> usize {
+        self.scan.lock().unwrap().pulled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::{
+            series_data_source::{Series, SeriesDataSource},
+            Source,
+        },
+        data_types::column_array::ColumnArray,
+        physical_plan::metrics::MetricsExec,
+    };
+
+    use rayon::ThreadPoolBuilder;
+
+    fn pipeline(rows: i64, morsel_size: usize) -> PipelineExec {
+        let source = SeriesDataSource::try_new(Series::Int64(1, rows, 1), 100).unwrap();
+        let scan = ScanExec::new(Source::Series(source), vec!["value".to_string()]);
+        let thread_pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let scheduler = MorselScheduler::try_new(Arc::new(thread_pool), morsel_size).unwrap();
+        PipelineExec::new(Plan::Metrics(MetricsExec::new(Plan::Scan(scan))), scheduler)
+    }
+
+    fn values(batch: &RecordBatch) -> Vec<i64> {
+        (0..batch.row_count())
+            .map(|i| {
+                *batch
+                    .field(0)
+                    .get_value(i)
+                    .unwrap()
+                    .downcast_ref::<i64>()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn scanned_rows(pipeline: &PipelineExec) -> usize {
+        match pipeline.input.as_ref() {
+            Plan::Metrics(m) => m.metrics().output_rows(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_morsels_in_scan_order() {
+        let pipeline = pipeline(1000, 7);
+        let batches = pipeline
+            .execute_all()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert!(batches.iter().all(|b| b.row_count() <= 7));
+        let values = batches.iter().flat_map(values).collect::<Vec<_>>();
+        assert_eq!(values, (1..=1000).collect::<Vec<_>>());
+        assert_eq!(scanned_rows(&pipeline), 1000);
+    }
+
+    #[test]
+    fn test_morsels_pulled_ahead() {
+        let pipeline = pipeline(100_000, 10);
+        let first = pipeline.execute(0).unwrap().next().unwrap().unwrap();
+        assert_eq!(values(&first), (1..=10).collect::<Vec<_>>());
+        // Only the morsels in flight are scanned, not the whole scan.
+        assert!(scanned_rows(&pipeline) <= pipeline.scheduler.capacity() * 10);
+    }
+}
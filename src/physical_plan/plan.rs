@@ -1,17 +1,41 @@
 use std::fmt::Display;
 
-use super::{hash::HashExec, projection::ProjectionExec, scan::ScanExec, selection::SelectionExec};
-use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+use super::{
+    checked::CheckedExec, distinct_on::DistinctOnExec, fill::FillExec, gap_fill::GapFillExec,
+    hash::HashExec, hash_join::HashJoinExec, limit::LimitExec, metrics::MetricsExec,
+    observed::ObservedExec, pipeline::PipelineExec, projection::ProjectionExec, scan::ScanExec,
+    selection::SelectionExec, selection_projection::SelectionProjectionExec,
+    sessionize::SessionizeExec, sort::SortExec, window::WindowExec,
+};
+use crate::{
+    data_types::{column_array::ColumnArray, record_batch::RecordBatch, schema::Schema},
+    dot::{schema_lines, DotGraph},
+};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 /// A physical plan represents an executable piece of code that will produce data.
 pub trait PhysicalPlan: Display {
     /// Return the schema.
     fn schema(&self) -> Schema;
 
-    /// Execute a physical plan and produce a series of record batches.
-    fn execute(&self) -> Result<Box<dyn Iterator<Item = RecordBatch> + '_>>;
+    /// Return how the output is split into partitions, which are executed independently.
+    fn output_partitioning(&self) -> Partitioning;
+
+    /// Execute a partition of the physical plan and produce a series of record batches.
+    /// A batch that cannot be produced, e.g. from malformed input, is an error.
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>>;
+
+    /// Execute all the partitions one after the other, producing their batches in order.
+    fn execute_all(&self) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        let partitions = (0..self.output_partitioning().partition_count())
+            .map(|partition| self.execute(partition))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(partitions.into_iter().flatten()))
+    }
 
     /// Returns the children (inputs) of this physical plan.
     /// This method is used to enable use of the visitor pattern to walk a query tree
@@ -32,11 +56,96 @@ pub trait PhysicalPlan: Display {
     }
 }
 
+/// How the output of a plan is split into partitions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Partitioning {
+    /// The rows are split into the number of partitions in no particular way.
+    Unknown(usize),
+    /// The rows with the same values in the columns at the indexes are in the same partition,
+    /// one of the number of partitions.
+    Hash(Vec<usize>, usize),
+}
+
+impl Partitioning {
+    pub fn partition_count(&self) -> usize {
+        match self {
+            Partitioning::Unknown(n) | Partitioning::Hash(_, n) => *n,
+        }
+    }
+
+    /// Check that the partition is one of the partitions.
+    pub fn check(&self, partition: usize) -> Result<()> {
+        if partition >= self.partition_count() {
+            bail!(
+                "Partition {} is out of range, the plan has {} partitions",
+                partition,
+                self.partition_count()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Check that the batch has the columns of the schema, with the same names and types,
+/// and that all its columns have the same number of rows.
+pub fn check_batch(schema: &Schema, batch: &RecordBatch) -> Result<()> {
+    if batch.column_count() != schema.fields.len() {
+        bail!(
+            "Expected {} columns, got {}",
+            schema.fields.len(),
+            batch.column_count()
+        );
+    }
+    let row_count = batch.fields.first().map_or(0, |f| f.size());
+    for (i, (field, array)) in schema.fields.iter().zip(&batch.fields).enumerate() {
+        if let Some(batch_field) = batch.schema.fields.get(i) {
+            if batch_field.name != field.name {
+                bail!(
+                    "Expected column {} to be named {}, got {}",
+                    i,
+                    field.name,
+                    batch_field.name
+                );
+            }
+        }
+        if array.get_type() != field.data_type {
+            bail!(
+                "Expected column {} to be {}, got {}",
+                field.name,
+                field.data_type,
+                array.get_type()
+            );
+        }
+        if array.size() != row_count {
+            bail!(
+                "Expected column {} to have {} rows, got {}",
+                field.name,
+                row_count,
+                array.size()
+            );
+        }
+    }
+    Ok(())
+}
+
 pub enum Plan {
     Scan(ScanExec),
     Projection(ProjectionExec),
     Selection(SelectionExec),
+    SelectionProjection(SelectionProjectionExec),
     Hash(HashExec),
+    HashJoin(HashJoinExec),
+    Sessionize(SessionizeExec),
+    GapFill(GapFillExec),
+    DistinctOn(DistinctOnExec),
+    Fill(FillExec),
+    Window(WindowExec),
+    Sort(SortExec),
+    Limit(LimitExec),
+    Checked(CheckedExec),
+    Metrics(MetricsExec),
+    Observed(ObservedExec),
+    Pipeline(PipelineExec),
 }
 
 impl PhysicalPlan for Plan {
@@ -45,16 +154,67 @@ impl PhysicalPlan for Plan {
             Plan::Scan(scan) => scan.schema(),
             Plan::Projection(projection) => projection.schema(),
             Plan::Selection(selection) => selection.schema(),
+            Plan::SelectionProjection(fused) => fused.schema(),
             Plan::Hash(hash) => hash.schema(),
+            Plan::HashJoin(join) => join.schema(),
+            Plan::Sessionize(sessionize) => sessionize.schema(),
+            Plan::GapFill(gap_fill) => gap_fill.schema(),
+            Plan::DistinctOn(distinct_on) => distinct_on.schema(),
+            Plan::Fill(fill) => fill.schema(),
+            Plan::Window(window) => window.schema(),
+            Plan::Sort(sort) => sort.schema(),
+            Plan::Limit(limit) => limit.schema(),
+            Plan::Checked(checked) => checked.schema(),
+            Plan::Metrics(metrics) => metrics.schema(),
+            Plan::Observed(observed) => observed.schema(),
+            Plan::Pipeline(pipeline) => pipeline.schema(),
         }
     }
 
-    fn execute(&self) -> Result<Box<dyn Iterator<Item = RecordBatch> + '_>> {
+    fn output_partitioning(&self) -> Partitioning {
         match self {
-            Plan::Scan(scan) => scan.execute(),
-            Plan::Projection(projection) => projection.execute(),
-            Plan::Selection(selection) => selection.execute(),
-            Plan::Hash(hash) => hash.execute(),
+            Plan::Scan(scan) => scan.output_partitioning(),
+            Plan::Projection(projection) => projection.output_partitioning(),
+            Plan::Selection(selection) => selection.output_partitioning(),
+            Plan::SelectionProjection(fused) => fused.output_partitioning(),
+            Plan::Hash(hash) => hash.output_partitioning(),
+            Plan::HashJoin(join) => join.output_partitioning(),
+            Plan::Sessionize(sessionize) => sessionize.output_partitioning(),
+            Plan::GapFill(gap_fill) => gap_fill.output_partitioning(),
+            Plan::DistinctOn(distinct_on) => distinct_on.output_partitioning(),
+            Plan::Fill(fill) => fill.output_partitioning(),
+            Plan::Window(window) => window.output_partitioning(),
+            Plan::Sort(sort) => sort.output_partitioning(),
+            Plan::Limit(limit) => limit.output_partitioning(),
+            Plan::Checked(checked) => checked.output_partitioning(),
+            Plan::Metrics(metrics) => metrics.output_partitioning(),
+            Plan::Observed(observed) => observed.output_partitioning(),
+            Plan::Pipeline(pipeline) => pipeline.output_partitioning(),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        match self {
+            Plan::Scan(scan) => scan.execute(partition),
+            Plan::Projection(projection) => projection.execute(partition),
+            Plan::Selection(selection) => selection.execute(partition),
+            Plan::SelectionProjection(fused) => fused.execute(partition),
+            Plan::Hash(hash) => hash.execute(partition),
+            Plan::HashJoin(join) => join.execute(partition),
+            Plan::Sessionize(sessionize) => sessionize.execute(partition),
+            Plan::GapFill(gap_fill) => gap_fill.execute(partition),
+            Plan::DistinctOn(distinct_on) => distinct_on.execute(partition),
+            Plan::Fill(fill) => fill.execute(partition),
+            Plan::Window(window) => window.execute(partition),
+            Plan::Sort(sort) => sort.execute(partition),
+            Plan::Limit(limit) => limit.execute(partition),
+            Plan::Checked(checked) => checked.execute(partition),
+            Plan::Metrics(metrics) => metrics.execute(partition),
+            Plan::Observed(observed) => observed.execute(partition),
+            Plan::Pipeline(pipeline) => pipeline.execute(partition),
         }
     }
 
@@ -63,8 +223,55 @@ impl PhysicalPlan for Plan {
             Plan::Scan(scan) => scan.children(),
             Plan::Projection(projection) => projection.children(),
             Plan::Selection(selection) => selection.children(),
+            Plan::SelectionProjection(fused) => fused.children(),
             Plan::Hash(hash) => hash.children(),
+            Plan::HashJoin(join) => join.children(),
+            Plan::Sessionize(sessionize) => sessionize.children(),
+            Plan::GapFill(gap_fill) => gap_fill.children(),
+            Plan::DistinctOn(distinct_on) => distinct_on.children(),
+            Plan::Fill(fill) => fill.children(),
+            Plan::Window(window) => window.children(),
+            Plan::Sort(sort) => sort.children(),
+            Plan::Limit(limit) => limit.children(),
+            Plan::Checked(checked) => checked.children(),
+            Plan::Metrics(metrics) => metrics.children(),
+            Plan::Observed(observed) => observed.children(),
+            Plan::Pipeline(pipeline) => pipeline.children(),
+        }
+    }
+}
+
+impl Plan {
+    /// Return a Graphviz graph of the plan, with a node per operator showing its
+    /// output schema, and its metrics when they were collected.
+    pub fn to_dot(&self) -> String {
+        let mut graph = DotGraph::new();
+        self.add_to_dot(&mut graph);
+        graph.to_string()
+    }
+
+    fn add_to_dot(&self, graph: &mut DotGraph) -> usize {
+        let mut sections = vec![];
+        match self {
+            Plan::Metrics(metrics_exec) => {
+                let metrics = metrics_exec.metrics();
+                sections.push(vec![metrics_exec.input().to_string()]);
+                sections.push(vec![format!(
+                    "output_rows={}, output_batches={}, elapsed={:?}",
+                    metrics.output_rows(),
+                    metrics.output_batches(),
+                    metrics.elapsed()
+                )]);
+            }
+            _ => sections.push(vec![self.to_string()]),
         }
+        sections.insert(1, schema_lines(&self.schema()));
+        let node = graph.add_node(sections);
+        for child in self.children() {
+            let child = child.add_to_dot(graph);
+            graph.add_edge(node, child);
+        }
+        node
     }
 }
 
@@ -74,7 +281,64 @@ impl Display for Plan {
             Plan::Scan(scan) => scan.fmt(f),
             Plan::Projection(projection) => projection.fmt(f),
             Plan::Selection(selection) => selection.fmt(f),
+            Plan::SelectionProjection(fused) => fused.fmt(f),
             Plan::Hash(hash) => hash.fmt(f),
+            Plan::HashJoin(join) => join.fmt(f),
+            Plan::Sessionize(sessionize) => sessionize.fmt(f),
+            Plan::GapFill(gap_fill) => gap_fill.fmt(f),
+            Plan::DistinctOn(distinct_on) => distinct_on.fmt(f),
+            Plan::Fill(fill) => fill.fmt(f),
+            Plan::Window(window) => window.fmt(f),
+            Plan::Sort(sort) => sort.fmt(f),
+            Plan::Limit(limit) => limit.fmt(f),
+            Plan::Checked(checked) => checked.fmt(f),
+            Plan::Metrics(metrics) => metrics.fmt(f),
+            Plan::Observed(observed) => observed.fmt(f),
+            Plan::Pipeline(pipeline) => pipeline.fmt(f),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_types::{
+        arrow_field_array::ArrowFieldArray,
+        column_array::{ArrayRef, DataType},
+        schema::Field,
+    };
+
+    use arrow::array::{Int32Array, Int64Array};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_check_batch() {
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int32)]);
+        let id = Int32Array::from(vec![1, 2, 3]);
+        let fields = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let batch = RecordBatch::new(schema.clone(), fields);
+        assert!(check_batch(&schema, &batch).is_ok());
+
+        let renamed = Schema::new(vec![Field::new("key".to_string(), DataType::Int32)]);
+        assert_eq!(
+            check_batch(&renamed, &batch).unwrap_err().to_string(),
+            "Expected column 0 to be named key, got id"
+        );
+
+        let id = Int64Array::from(vec![1, 2, 3]);
+        let fields = vec![Arc::new(ArrowFieldArray::new(Box::new(id))) as ArrayRef];
+        let batch = RecordBatch::new(schema.clone(), fields);
+        assert_eq!(
+            check_batch(&schema, &batch).unwrap_err().to_string(),
+            "Expected column id to be Int32, got Int64"
+        );
+    }
+
+    #[test]
+    fn test_plans_and_batches_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Plan>();
+        assert_send_sync::<crate::logical_plan::plan::Plan>();
+        assert_send_sync::<RecordBatch>();
+    }
+}
@@ -1,8 +1,9 @@
 use std::fmt::Display;
 
 use super::{
-    expr::{Expr, PhysicalExpr},
-    plan::{PhysicalPlan, Plan},
+    compiled::{compile, Evaluator},
+    expr::Expr,
+    plan::{Partitioning, PhysicalPlan, Plan},
 };
 use crate::data_types::{record_batch::RecordBatch, schema::Schema};
 
@@ -10,19 +11,58 @@ use anyhow::Result;
 
 /// Execute a projection.
 pub struct ProjectionExec {
-    input: Box<Plan>,
-    schema: Schema,
-    expr: Vec<Expr>,
+    pub(crate) input: Box<Plan>,
+    pub(crate) schema: Schema,
+    pub(crate) expr: Vec<Expr>,
+    evaluators: Vec<Evaluator>,
 }
 
 impl ProjectionExec {
     pub fn new(input: Plan, schema: Schema, expr: Vec<Expr>) -> Self {
+        let input_schema = input.schema();
+        let evaluators = expr.iter().map(|e| compile(e, &input_schema)).collect();
         Self {
             input: Box::new(input),
             schema,
             expr,
+            evaluators,
         }
     }
+
+    pub fn input(&self) -> &Plan {
+        &self.input
+    }
+
+    /// Evaluate the expressions over a batch of the input.
+    pub fn process_batch(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let fields = self
+            .evaluators
+            .iter()
+            .map(|evaluate| evaluate(batch))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RecordBatch::new(self.schema.clone(), fields))
+    }
+}
+
+/// Return the partitioning of a projection, given the input column each output column
+/// is, if it is one.
+pub(crate) fn project_partitioning(
+    partitioning: Partitioning,
+    columns: &[Option<usize>],
+) -> Partitioning {
+    match partitioning {
+        Partitioning::Hash(hash_columns, n) => {
+            let projected = hash_columns
+                .iter()
+                .map(|c| columns.iter().position(|column| *column == Some(*c)))
+                .collect::<Option<Vec<_>>>();
+            match projected {
+                Some(columns) => Partitioning::Hash(columns, n),
+                None => Partitioning::Unknown(n),
+            }
+        }
+        partitioning => partitioning,
+    }
 }
 
 impl PhysicalPlan for ProjectionExec {
@@ -30,16 +70,25 @@ impl PhysicalPlan for ProjectionExec {
         self.schema.clone()
     }
 
-    fn execute(&self) -> Result<Box<dyn Iterator<Item = RecordBatch> + '_>> {
-        let input = self.input.execute()?;
-        Ok(Box::new(input.map(|b| {
-            let fields = self
-                .expr
-                .iter()
-                .map(|e| e.evaluate(&b).expect("evaluate expr failed"))
-                .collect::<Vec<_>>();
-            RecordBatch::new(self.schema.clone(), fields)
-        })))
+    /// A hash partitioning is preserved when its columns are all projected.
+    fn output_partitioning(&self) -> Partitioning {
+        let expr = self
+            .expr
+            .iter()
+            .map(|e| match e {
+                Expr::Column(column) => Some(column.i),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        project_partitioning(self.input.output_partitioning(), &expr)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        let input = self.input.execute(partition)?;
+        Ok(Box::new(input.map(|b| self.process_batch(&b?))))
     }
 
     fn children(&self) -> Vec<&Plan> {
@@ -66,27 +115,27 @@ mod tests {
 
     use super::*;
     use crate::{
-        data_source::{csv_data_source::CsvDataSource, Source},
+        data_source::Source,
         data_types::{column_array::DataType, schema::Field},
         physical_plan::{expr::Column, scan::ScanExec},
-        test_util::rq_test_data,
+        test_util::csv_test_data_source,
     };
 
     #[test]
     fn test_projection_execute() {
-        let data_path = rq_test_data("boolean_field.csv");
         let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Boolean)]);
-        let csv_data_source = CsvDataSource::new(data_path, schema.clone(), 3);
+        let csv_data_source = csv_test_data_source("boolean_field.csv", schema.clone(), 3);
         let scan = ScanExec::new(Source::Csv(csv_data_source), vec!["c1".to_string()]);
         let projection =
             ProjectionExec::new(Plan::Scan(scan), schema, vec![Expr::Column(Column::new(0))]);
-        assert!(projection.execute().is_ok());
-        assert_eq!(projection.execute().unwrap().count(), 1);
+        assert!(projection.execute_all().is_ok());
+        assert_eq!(projection.execute_all().unwrap().count(), 1);
         assert!(projection
-            .execute()
+            .execute_all()
             .unwrap()
             .next()
             .unwrap()
+            .unwrap()
             .field(0)
             .get_value(0)
             .unwrap()
@@ -96,9 +145,8 @@ mod tests {
 
     #[test]
     fn test_display() {
-        let data_path = rq_test_data("boolean_field.csv");
         let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Boolean)]);
-        let csv_data_source = CsvDataSource::new(data_path, schema.clone(), 3);
+        let csv_data_source = csv_test_data_source("boolean_field.csv", schema.clone(), 3);
         let scan = ScanExec::new(Source::Csv(csv_data_source), vec!["c1".to_string()]);
         let projection =
             ProjectionExec::new(Plan::Scan(scan), schema, vec![Expr::Column(Column::new(0))]);
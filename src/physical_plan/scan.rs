@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use super::plan::{PhysicalPlan, Plan};
+use super::plan::{Partitioning, PhysicalPlan, Plan};
 use crate::{
     data_source::{DataSource, Source},
     data_types::{record_batch::RecordBatch, schema::Schema},
@@ -10,8 +10,8 @@ use anyhow::Result;
 
 // Scan a data source with optional push-down projection.
 pub struct ScanExec {
-    data_source: Source,
-    projection: Vec<String>,
+    pub(crate) data_source: Source,
+    pub(crate) projection: Vec<String>,
 }
 
 impl ScanExec {
@@ -21,18 +21,41 @@ impl ScanExec {
             projection,
         }
     }
+
+    /// Scan a partition of the data source. The batches may be read on any thread, so
+    /// the workers of a pipeline pull them from a shared scan.
+    pub(crate) fn scan_partition(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + Send + '_>> {
+        self.output_partitioning().check(partition)?;
+        self.data_source.scan_partition(
+            self.projection.iter().map(|s| s.as_str()).collect(),
+            partition,
+        )
+    }
 }
 
 impl PhysicalPlan for ScanExec {
     fn schema(&self) -> Schema {
-        self.data_source
-            .get_schema()
-            .select(self.projection.iter().map(|s| s.as_str()).collect())
+        if self.projection.is_empty() {
+            self.data_source.get_schema().clone()
+        } else {
+            self.data_source
+                .get_schema()
+                .select(self.projection.iter().map(|s| s.as_str()).collect())
+        }
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(self.data_source.partition_count())
     }
 
-    fn execute(&self) -> Result<Box<dyn Iterator<Item = RecordBatch> + '_>> {
-        self.data_source
-            .scan(self.projection.iter().map(|s| s.as_str()).collect())
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        Ok(self.scan_partition(partition)?)
     }
 
     fn children(&self) -> Vec<&Plan> {
@@ -59,19 +82,18 @@ mod tests {
 
     use super::ScanExec;
     use crate::{
-        data_source::{csv_data_source::CsvDataSource, Source},
+        data_source::Source,
         data_types::{
             column_array::DataType,
             schema::{Field, Schema},
         },
-        test_util::rq_test_data,
+        test_util::csv_test_data_source,
     };
 
     #[test]
     fn test_scan_display() {
-        let data_path = rq_test_data("boolean_field.csv");
         let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Boolean)]);
-        let csv_data_source = CsvDataSource::new(data_path, schema, 3);
+        let csv_data_source = csv_test_data_source("boolean_field.csv", schema, 3);
         let scan = ScanExec::new(Source::Csv(csv_data_source), vec!["c1".to_string()]);
         assert_eq!(scan.to_string(), "ScanExec: projection=c1");
     }
@@ -1,62 +1,74 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use super::{
-    expr::{evaluate_from_values, Expr, PhysicalExpr},
-    plan::{PhysicalPlan, Plan},
+    compiled::{compile_mask, MaskEvaluator},
+    expr::{to_arrow_array, Expr},
+    plan::{Partitioning, PhysicalPlan, Plan},
+};
+use crate::data_types::{
+    arrow_field_array::ArrowFieldArray, column_array::ArrayRef, record_batch::RecordBatch,
+    schema::Schema,
 };
-use crate::data_types::{column_array::ArrayRef, record_batch::RecordBatch, schema::Schema};
 
-use anyhow::{Error, Result};
+use anyhow::Result;
+use arrow::{array::BooleanArray, compute::FilterBuilder};
 
 /// Execute a selection.
 pub struct SelectionExec {
-    input: Box<Plan>,
-    expr: Expr,
+    pub(crate) input: Box<Plan>,
+    pub(crate) expr: Expr,
+    mask: MaskEvaluator,
 }
 
 impl SelectionExec {
     pub fn new(input: Plan, expr: Expr) -> Self {
+        let mask = compile_mask(&expr, &input.schema());
         Self {
             input: Box::new(input),
             expr,
+            mask,
         }
     }
 
-    fn filter(&self, array: &ArrayRef, selection: &ArrayRef) -> Result<ArrayRef> {
-        let mut values = vec![];
-        for i in 0..selection.size() {
-            if *selection.get_value(i)?.downcast_ref::<bool>().unwrap() {
-                values.push(array.get_value(i)?);
-            }
-        }
+    pub fn input(&self) -> &Plan {
+        &self.input
+    }
 
-        evaluate_from_values(&values, &array.get_type())
+    /// Keep the rows of a batch of the input that satisfy the predicate.
+    pub fn process_batch(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        filter_batch(batch, &(self.mask)(batch)?)
     }
 }
 
+/// Keep the rows of the batch selected by the mask.
+pub(crate) fn filter_batch(batch: &RecordBatch, mask: &BooleanArray) -> Result<RecordBatch> {
+    let predicate = FilterBuilder::new(mask).optimize().build();
+    let fields = batch
+        .fields
+        .iter()
+        .map(|array| {
+            let filtered = predicate.filter(to_arrow_array(array)?.as_ref())?;
+            Ok(Arc::new(ArrowFieldArray::new(Box::new(filtered))) as ArrayRef)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::new(batch.schema.clone(), fields))
+}
+
 impl PhysicalPlan for SelectionExec {
     fn schema(&self) -> Schema {
         self.input.schema()
     }
 
-    fn execute(&self) -> Result<Box<dyn Iterator<Item = RecordBatch> + '_>> {
-        let batch = self.input.execute()?;
-        Ok(Box::new(
-            batch
-                .map(|b| {
-                    let selection = &self.expr.evaluate(&b)?;
-                    let schema = self.input.schema();
-                    let filtered_fields = schema
-                        .fields
-                        .iter()
-                        .enumerate()
-                        .map(|(i, _)| self.filter(b.field(i), selection))
-                        .collect::<Result<Vec<_>, _>>()?;
-                    Ok::<RecordBatch, Error>(RecordBatch::new(schema, filtered_fields))
-                })
-                .collect::<Result<Vec<RecordBatch>, _>>()?
-                .into_iter(),
-        ))
+    fn output_partitioning(&self) -> Partitioning {
+        self.input.output_partitioning()
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        let batch = self.input.execute(partition)?;
+        Ok(Box::new(batch.map(|b| self.process_batch(&b?))))
     }
 
     fn children(&self) -> Vec<&Plan> {
@@ -75,9 +87,10 @@ mod tests {
 
     use super::*;
     use crate::{
-        data_source::{csv_data_source::CsvDataSource, Source},
+        data_source::{memory_data_source::MemoryDataSource, Source},
         data_types::{
-            column_array::DataType,
+            column_array::{ColumnArray, DataType},
+            literal_value_array::LiteralValueArray,
             schema::{Field, Schema},
         },
         logical_plan::expr::Operator,
@@ -85,14 +98,15 @@ mod tests {
             expr::{BinaryExpr, Column, ScalarValue},
             scan::ScanExec,
         },
-        test_util::rq_test_data,
+        test_util::csv_test_data_source,
     };
 
+    use arrow::array::Int64Array;
+
     #[test]
     fn test_selection_execute() {
-        let data_path = rq_test_data("f32_field.csv");
         let schema = Schema::new(vec![Field::new("c1".to_string(), DataType::Float32)]);
-        let csv_data_source = CsvDataSource::new(data_path, schema, 3);
+        let csv_data_source = csv_test_data_source("f32_field.csv", schema, 3);
         let scan = ScanExec::new(Source::Csv(csv_data_source), vec!["c1".to_string()]);
         let filter = Expr::BinaryExpr(BinaryExpr::new(
             Operator::LtEq,
@@ -101,7 +115,7 @@ mod tests {
         ));
 
         let selection = SelectionExec::new(Plan::Scan(scan), filter);
-        let result = selection.execute().unwrap().next().unwrap();
+        let result = selection.execute_all().unwrap().next().unwrap().unwrap();
         let field = result.field(0);
         assert_eq!(field.get_type(), DataType::Float32);
         assert_eq!(field.size(), 2);
@@ -115,9 +129,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_selection_short_circuit() {
+        let schema = Schema::new(vec![Field::new("x".to_string(), DataType::Int64)]);
+        let x = Int64Array::from(vec![0, 2, 20]);
+        let fields = vec![Arc::new(ArrowFieldArray::new(Box::new(x))) as ArrayRef];
+        let batch = RecordBatch::new(schema.clone(), fields);
+        let source = Source::Mem(MemoryDataSource::new(schema, vec![batch]));
+        let binary = |op, l, r| Expr::BinaryExpr(BinaryExpr::new(op, l, r));
+        let x = || Expr::Column(Column::new(0));
+        let int64 = |v| Expr::Literal(ScalarValue::Int64(v));
+        // 10 / x fails for x = 0, unless that row is decided by the left side.
+        let ten_over_x_gt_one = || {
+            binary(
+                Operator::Gt,
+                binary(Operator::Divide, int64(10), x()),
+                int64(1),
+            )
+        };
+        let select = |expr| {
+            let scan = ScanExec::new(source.clone(), vec!["x".to_string()]);
+            let result = SelectionExec::new(Plan::Scan(scan), expr)
+                .execute_all()
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap();
+            (0..result.row_count())
+                .map(|i| {
+                    *result
+                        .field(0)
+                        .get_value(i)
+                        .unwrap()
+                        .downcast_ref::<i64>()
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let and = binary(
+            Operator::And,
+            binary(Operator::Neq, x(), int64(0)),
+            ten_over_x_gt_one(),
+        );
+        assert_eq!(select(and), vec![2]);
+        let or = binary(
+            Operator::Or,
+            binary(Operator::Eq, x(), int64(0)),
+            ten_over_x_gt_one(),
+        );
+        assert_eq!(select(or), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_selection_null_predicate() {
+        let schema = Schema::new(vec![
+            Field::new("x".to_string(), DataType::Int64),
+            Field::new("y".to_string(), DataType::Int64),
+        ]);
+        let x = Int64Array::from(vec![Some(1), None, Some(3)]);
+        let fields = vec![
+            Arc::new(ArrowFieldArray::new(Box::new(x))) as ArrayRef,
+            Arc::new(LiteralValueArray::new(DataType::Int64, 7i64, 3)) as ArrayRef,
+        ];
+        let batch = RecordBatch::new(schema.clone(), fields);
+        let source = Source::Mem(MemoryDataSource::new(schema, vec![batch]));
+        let scan = ScanExec::new(source, vec!["x".to_string(), "y".to_string()]);
+        let filter = Expr::BinaryExpr(BinaryExpr::new(
+            Operator::Gt,
+            Expr::Column(Column::new(0)),
+            Expr::Literal(ScalarValue::Int64(0)),
+        ));
+
+        let result = SelectionExec::new(Plan::Scan(scan), filter)
+            .execute_all()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.row_count(), 2);
+        let value = |c: usize, i| {
+            *result
+                .field(c)
+                .get_value(i)
+                .unwrap()
+                .downcast_ref::<i64>()
+                .unwrap()
+        };
+        assert_eq!((value(0, 0), value(0, 1)), (1, 3));
+        assert_eq!((value(1, 0), value(1, 1)), (7, 7));
+    }
+
     #[test]
     fn test_selection_display() {
-        let data_path = rq_test_data("f32_field.csv");
         let schema = Schema::new(vec![
             Field::new("c1".to_string(), DataType::Int32),
             Field::new("c2".to_string(), DataType::Int32),
@@ -126,7 +230,7 @@ mod tests {
             Field::new("c5".to_string(), DataType::Float32),
             Field::new("c6".to_string(), DataType::Float64),
         ]);
-        let csv_data_source = CsvDataSource::new(data_path, schema, 3);
+        let csv_data_source = csv_test_data_source("f32_field.csv", schema, 3);
         let scan = ScanExec::new(Source::Csv(csv_data_source), vec!["c5".to_string()]);
         let filter = Expr::BinaryExpr(BinaryExpr::new(
             Operator::LtEq,
@@ -0,0 +1,234 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    sync::Arc,
+};
+
+use super::{
+    compiled::{compile, compile_mask, Evaluator, MaskEvaluator},
+    expr::{to_arrow_array, Expr},
+    plan::{Partitioning, PhysicalPlan, Plan},
+    projection::project_partitioning,
+};
+use crate::data_types::{
+    arrow_field_array::ArrowFieldArray, column_array::ArrayRef, record_batch::RecordBatch,
+    schema::Schema,
+};
+
+use anyhow::Result;
+use arrow::compute::FilterBuilder;
+
+/// Execute a projection over a selection in a single pass over every batch.
+/// Only the input columns the projection reads are filtered, and the projection is
+/// evaluated over them, so the selected rows are never materialized as a batch.
+pub struct SelectionProjectionExec {
+    pub(crate) input: Box<Plan>,
+    pub(crate) predicate: Expr,
+    pub(crate) schema: Schema,
+    pub(crate) expr: Vec<Expr>,
+    /// The input columns read by the projection, in order.
+    pub(crate) columns: Vec<usize>,
+    /// The schema of the columns read by the projection.
+    columns_schema: Schema,
+    mask: MaskEvaluator,
+    evaluators: Vec<Evaluator>,
+}
+
+impl SelectionProjectionExec {
+    /// Fuse the projection with the selection over the input. The projection refers
+    /// to the columns of the input, and is rewritten to read the filtered columns,
+    /// which are displayed with the input column each one comes from.
+    pub fn try_new(input: Plan, predicate: Expr, schema: Schema, expr: Vec<Expr>) -> Result<Self> {
+        let input_schema = input.schema();
+        let mut columns = expr
+            .iter()
+            .flat_map(|e| e.columns())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        // A column is kept for the number of rows of a projection of literals.
+        if columns.is_empty() && !input_schema.fields.is_empty() {
+            columns.push(0);
+        }
+        let map = columns
+            .iter()
+            .enumerate()
+            .map(|(i, &column)| (column, i))
+            .collect::<HashMap<_, _>>();
+        let columns_schema = Schema::new(
+            columns
+                .iter()
+                .map(|&i| input_schema.fields[i].clone())
+                .collect(),
+        );
+        let expr = expr
+            .into_iter()
+            .map(|e| e.remap_columns(&map))
+            .collect::<Result<Vec<_>>>()?;
+        let mask = compile_mask(&predicate, &input_schema);
+        let evaluators = expr.iter().map(|e| compile(e, &columns_schema)).collect();
+        Ok(Self {
+            input: Box::new(input),
+            predicate,
+            schema,
+            expr,
+            columns,
+            columns_schema,
+            mask,
+            evaluators,
+        })
+    }
+
+    pub fn input(&self) -> &Plan {
+        &self.input
+    }
+
+    /// Filter the columns read by the projection and evaluate it over them.
+    pub fn process_batch(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let mask = (self.mask)(batch)?;
+        let predicate = FilterBuilder::new(&mask).optimize().build();
+        let fields = self
+            .columns
+            .iter()
+            .map(|&i| {
+                let filtered = predicate.filter(to_arrow_array(batch.field(i))?.as_ref())?;
+                Ok(Arc::new(ArrowFieldArray::new(Box::new(filtered))) as ArrayRef)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let filtered = RecordBatch::new(self.columns_schema.clone(), fields);
+        let fields = self
+            .evaluators
+            .iter()
+            .map(|evaluate| evaluate(&filtered))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RecordBatch::new(self.schema.clone(), fields))
+    }
+}
+
+impl PhysicalPlan for SelectionProjectionExec {
+    fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    /// A hash partitioning is preserved when its columns are all projected.
+    fn output_partitioning(&self) -> Partitioning {
+        let expr = self
+            .expr
+            .iter()
+            .map(|e| match e {
+                Expr::Column(column) => Some(self.columns[column.i]),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        project_partitioning(self.input.output_partitioning(), &expr)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        let input = self.input.execute(partition)?;
+        Ok(Box::new(input.map(|b| self.process_batch(&b?))))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for SelectionProjectionExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SelectionProjectionExec: {}; columns={:?}; {}",
+            self.predicate,
+            self.columns,
+            self.expr
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{column_array::DataType, schema::Field},
+        logical_plan::expr::Operator,
+        physical_plan::{
+            expr::{BinaryExpr, Column, ScalarValue},
+            scan::ScanExec,
+        },
+    };
+
+    use arrow::array::Int64Array;
+
+    #[test]
+    fn test_selection_projection() {
+        let schema = Schema::new(vec![
+            Field::new("x".to_string(), DataType::Int64),
+            Field::new("y".to_string(), DataType::Int64),
+            Field::new("z".to_string(), DataType::Int64),
+        ]);
+        let fields = [vec![0, 2, 5], vec![1, 2, 3], vec![4, 5, 6]]
+            .into_iter()
+            .map(|values| {
+                Arc::new(ArrowFieldArray::new(Box::new(Int64Array::from(values)))) as ArrayRef
+            })
+            .collect();
+        let batch = RecordBatch::new(schema.clone(), fields);
+        let source = Source::Mem(MemoryDataSource::new(schema.clone(), vec![batch]));
+        let binary = |op, l, r| Expr::BinaryExpr(BinaryExpr::new(op, l, r));
+        let column = |i| Expr::Column(Column::new(i));
+        let int64 = |v| Expr::Literal(ScalarValue::Int64(v));
+        let fused = |expr: Vec<Expr>| {
+            let projection = ["x", "y", "z"].map(String::from).to_vec();
+            let scan = ScanExec::new(source.clone(), projection);
+            let output_schema = Schema::new(
+                (0..expr.len())
+                    .map(|i| Field::new(format!("c{}", i), DataType::Int64))
+                    .collect(),
+            );
+            let predicate = binary(Operator::Neq, column(0), int64(0));
+            SelectionProjectionExec::try_new(Plan::Scan(scan), predicate, output_schema, expr)
+                .unwrap()
+        };
+        let values = |exec: &SelectionProjectionExec| {
+            let batch = exec.execute_all().unwrap().next().unwrap().unwrap();
+            (0..batch.column_count())
+                .map(|c| {
+                    (0..batch.row_count())
+                        .map(|i| {
+                            *batch
+                                .field(c)
+                                .get_value(i)
+                                .unwrap()
+                                .downcast_ref::<i64>()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // 10 / x fails for x = 0, which is only evaluated over the selected rows.
+        let exec = fused(vec![
+            column(2),
+            binary(Operator::Divide, int64(10), column(0)),
+        ]);
+        assert_eq!(exec.columns, vec![0, 2]);
+        assert_eq!(
+            exec.to_string(),
+            "SelectionProjectionExec: #0 != 0; columns=[0, 2]; #1,10 / #0"
+        );
+        assert_eq!(values(&exec), vec![vec![5, 6], vec![5, 2]]);
+
+        // A projection of literals has a row for every selected row.
+        let exec = fused(vec![int64(7)]);
+        assert_eq!(values(&exec), vec![vec![7, 7]]);
+    }
+}
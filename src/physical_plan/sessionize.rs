@@ -0,0 +1,223 @@
+use std::{collections::HashMap, fmt::Display, sync::Arc};
+
+use super::{
+    expr::{Expr, PhysicalExpr},
+    memory::MemoryBudget,
+    plan::{Partitioning, PhysicalPlan, Plan},
+};
+use crate::data_types::{
+    arrow_field_array::ArrowFieldArray,
+    column_array::{ArrayRef, DataType},
+    record_batch::RecordBatch,
+    schema::Schema,
+};
+
+use anyhow::{bail, Result};
+use arrow::array::Int64Array;
+
+/// Execute a sessionization.
+/// All the input is read before the sessions are assigned, since the events of a
+/// key can come in any order. Sessions are numbered from 1 in the order of their
+/// first event in the input, and the rows keep their input order.
+/// Rows with a null timestamp get a null session id.
+pub struct SessionizeExec {
+    pub(crate) input: Box<Plan>,
+    pub(crate) schema: Schema,
+    pub(crate) key: Expr,
+    pub(crate) timestamp: Expr,
+    pub(crate) gap: i64,
+    memory: MemoryBudget,
+}
+
+impl SessionizeExec {
+    pub fn new(input: Plan, schema: Schema, key: Expr, timestamp: Expr, gap: i64) -> Self {
+        Self {
+            input: Box::new(input),
+            schema,
+            key,
+            timestamp,
+            gap,
+            memory: MemoryBudget::default(),
+        }
+    }
+
+    /// Reserve the data buffered by the operator from the memory budget of the query.
+    pub fn with_memory_budget(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Assign the session ids of all the input rows.
+    fn assign_sessions(&self, batches: &[RecordBatch]) -> Result<Vec<Option<i64>>> {
+        // The rows of each key, as (timestamp, row) in input order.
+        let mut events: HashMap<Option<String>, Vec<(i64, usize)>> = HashMap::new();
+        let mut row = 0;
+        for batch in batches {
+            let keys = self.key.evaluate(batch)?;
+            let timestamps = self.timestamp.evaluate(batch)?;
+            for i in 0..batch.row_count() {
+                if let Some(ts) = timestamp_value(&timestamps, i)? {
+                    events
+                        .entry(key_value(&keys, i)?)
+                        .or_default()
+                        .push((ts, row));
+                }
+                row += 1;
+            }
+        }
+
+        // Split the events of each key into sessions, as lists of rows.
+        let mut sessions = vec![];
+        for mut key_events in events.into_values() {
+            key_events.sort_by_key(|(ts, _)| *ts);
+            let mut session = vec![];
+            let mut last_ts = None;
+            for (ts, row) in key_events {
+                if matches!(last_ts, Some(last) if ts - last > self.gap) {
+                    sessions.push(std::mem::take(&mut session));
+                }
+                session.push(row);
+                last_ts = Some(ts);
+            }
+            sessions.push(session);
+        }
+        sessions.sort_by_key(|rows| rows.iter().min().copied());
+
+        let mut session_ids = vec![None; row];
+        for (id, rows) in sessions.into_iter().enumerate() {
+            rows.into_iter()
+                .for_each(|row| session_ids[row] = Some(id as i64 + 1));
+        }
+        Ok(session_ids)
+    }
+}
+
+/// Return the value of the key as text, so keys of any type can be grouped.
+fn key_value(keys: &ArrayRef, i: usize) -> Result<Option<String>> {
+    if keys.is_null(i) {
+        return Ok(None);
+    }
+    let value = keys.get_value(i)?;
+    Ok(Some(match keys.get_type() {
+        DataType::Int32 => value.downcast_ref::<i32>().unwrap().to_string(),
+        DataType::Int64 => value.downcast_ref::<i64>().unwrap().to_string(),
+        DataType::Float32 => value.downcast_ref::<f32>().unwrap().to_string(),
+        DataType::Float64 => value.downcast_ref::<f64>().unwrap().to_string(),
+        DataType::Boolean => value.downcast_ref::<bool>().unwrap().to_string(),
+        DataType::Utf8 => value.downcast_ref::<String>().unwrap().clone(),
+        data_type @ (DataType::Binary | DataType::List(_) | DataType::Struct(_)) => {
+            bail!("Sessions cannot be keyed by {} values", data_type)
+        }
+    }))
+}
+
+fn timestamp_value(timestamps: &ArrayRef, i: usize) -> Result<Option<i64>> {
+    if timestamps.is_null(i) {
+        return Ok(None);
+    }
+    let value = timestamps.get_value(i)?;
+    Ok(Some(match timestamps.get_type() {
+        DataType::Int32 => *value.downcast_ref::<i32>().unwrap() as i64,
+        DataType::Int64 => *value.downcast_ref::<i64>().unwrap(),
+        data_type => bail!("Session timestamps must be integers, got {}", data_type),
+    }))
+}
+
+impl PhysicalPlan for SessionizeExec {
+    fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let mut reservation = self.memory.reservation("SessionizeExec");
+        let batches = reservation.collect(self.input.execute_all()?)?;
+        let mut session_ids = self.assign_sessions(&batches)?.into_iter();
+        let batches = batches
+            .into_iter()
+            .map(|b| {
+                let ids = session_ids
+                    .by_ref()
+                    .take(b.row_count())
+                    .collect::<Int64Array>();
+                let mut fields = (0..b.column_count())
+                    .map(|i| b.field(i).clone())
+                    .collect::<Vec<_>>();
+                fields.push(Arc::new(ArrowFieldArray::new(Box::new(ids))) as ArrayRef);
+                RecordBatch::new(self.schema.clone(), fields)
+            })
+            .collect::<Vec<_>>();
+        Ok(Box::new(batches.into_iter().map(Ok)))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for SessionizeExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SessionizeExec: key={}, timestamp={}, gap={}",
+            self.key, self.timestamp, self.gap
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::Source,
+        data_types::schema::Field,
+        physical_plan::{expr::Column, scan::ScanExec},
+        test_util::csv_test_data_source,
+    };
+
+    #[test]
+    fn test_sessionize_execute() {
+        let schema = Schema::new(vec![
+            Field::new("user".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        let csv_data_source = csv_test_data_source("events.csv", schema.clone(), 4);
+        let scan = ScanExec::new(
+            Source::Csv(csv_data_source),
+            vec!["user".to_string(), "ts".to_string()],
+        );
+        let mut fields = schema.fields;
+        fields.push(Field::new("session_id".to_string(), DataType::Int64));
+        let sessionize = SessionizeExec::new(
+            Plan::Scan(scan),
+            Schema::new(fields),
+            Expr::Column(Column::new(0)),
+            Expr::Column(Column::new(1)),
+            1800,
+        );
+        assert_eq!(
+            sessionize.to_string(),
+            "SessionizeExec: key=#0, timestamp=#1, gap=1800"
+        );
+
+        let session_ids = sessionize
+            .execute_all()
+            .unwrap()
+            .flat_map(|b| {
+                let b = b.unwrap();
+                let ids = b.field(2).clone();
+                (0..ids.size())
+                    .map(|i| *ids.get_value(i).unwrap().downcast_ref::<i64>().unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(session_ids, vec![1, 2, 1, 3, 2, 3]);
+    }
+}
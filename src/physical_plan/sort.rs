@@ -0,0 +1,244 @@
+use std::{any::Any, cmp::Ordering, fmt::Display};
+
+use super::{
+    expr::{evaluate_from_nullable_values, Expr, PhysicalExpr},
+    memory::MemoryBudget,
+    plan::{Partitioning, PhysicalPlan, Plan},
+    sort_key::{key_values, KeyValue},
+};
+use crate::data_types::{record_batch::RecordBatch, schema::Schema};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+/// An expression to order rows by, with its direction and where its nulls go.
+pub struct SortExpr {
+    pub expr: Expr,
+    pub asc: bool,
+    pub nulls_first: bool,
+}
+
+impl SortExpr {
+    pub fn new(expr: Expr, asc: bool, nulls_first: bool) -> Self {
+        Self {
+            expr,
+            asc,
+            nulls_first,
+        }
+    }
+
+    /// Compare the values of two rows.
+    pub(crate) fn compare(&self, a: &Option<KeyValue>, b: &Option<KeyValue>) -> Ordering {
+        match (a, b) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if self.nulls_first => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) if self.nulls_first => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) if self.asc => a.cmp(b),
+            (Some(a), Some(b)) => b.cmp(a),
+        }
+    }
+}
+
+impl Display for SortExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} NULLS {}",
+            self.expr,
+            if self.asc { "ASC" } else { "DESC" },
+            if self.nulls_first { "FIRST" } else { "LAST" }
+        )
+    }
+}
+
+/// Compare the values of two rows by a list of expressions, the first one deciding first.
+pub(crate) fn compare_rows(
+    order_by: &[SortExpr],
+    a: &[Option<KeyValue>],
+    b: &[Option<KeyValue>],
+) -> Ordering {
+    order_by
+        .iter()
+        .zip(a.iter().zip(b))
+        .map(|(e, (a, b))| e.compare(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Execute a sort of all the input rows into a single batch.
+/// The sort is stable, so rows that tie keep the order of the input.
+pub struct SortExec {
+    pub(crate) input: Box<Plan>,
+    pub(crate) order_by: Vec<SortExpr>,
+    memory: MemoryBudget,
+}
+
+impl SortExec {
+    pub fn new(input: Plan, order_by: Vec<SortExpr>) -> Self {
+        Self {
+            input: Box::new(input),
+            order_by,
+            memory: MemoryBudget::default(),
+        }
+    }
+
+    /// Reserve the data buffered by the operator from the memory budget of the query.
+    pub fn with_memory_budget(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Return the (batch, row) of every row, in sorted order.
+    fn sorted_rows(&self, batches: &[RecordBatch]) -> Result<Vec<(usize, usize)>> {
+        let mut rows = vec![];
+        for (b, batch) in batches.iter().enumerate() {
+            let keys = self
+                .order_by
+                .iter()
+                .map(|e| key_values(&e.expr.evaluate(batch)?))
+                .collect::<Result<Vec<_>>>()?;
+            for row in 0..batch.row_count() {
+                let key = keys.iter().map(|k| k[row].clone()).collect::<Vec<_>>();
+                rows.push((key, (b, row)));
+            }
+        }
+        rows.sort_by(|(a, _), (b, _)| compare_rows(&self.order_by, a, b));
+        Ok(rows.into_iter().map(|(_, location)| location).collect())
+    }
+}
+
+impl PhysicalPlan for SortExec {
+    fn schema(&self) -> Schema {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let schema = self.schema();
+        let mut reservation = self.memory.reservation("SortExec");
+        let batches = reservation.collect(self.input.execute_all()?)?;
+        let rows = self.sorted_rows(&batches)?;
+        if rows.is_empty() {
+            return Ok(Box::new(std::iter::empty()));
+        }
+
+        let fields = schema
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(c, field)| {
+                let values = rows
+                    .iter()
+                    .map(|(b, row)| {
+                        let array = batches[*b].field(c);
+                        (!array.is_null(*row))
+                            .then(|| array.get_value(*row))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<Option<Box<dyn Any>>>>>()?;
+                evaluate_from_nullable_values(&values, &field.data_type)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(std::iter::once(Ok(RecordBatch::new(
+            schema, fields,
+        )))))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for SortExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SortExec: orderBy={}",
+            self.order_by
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{
+            arrow_field_array::ArrowFieldArray,
+            column_array::{ArrayRef, DataType},
+            schema::Field,
+        },
+        physical_plan::{expr::Column, scan::ScanExec},
+    };
+
+    use arrow::array::{Int64Array, StringArray};
+
+    #[test]
+    fn test_sort() {
+        let schema = Schema::new(vec![
+            Field::new("k".to_string(), DataType::Int64),
+            Field::new("v".to_string(), DataType::Utf8),
+        ]);
+        let batch = |k: Vec<Option<i64>>, v: Vec<&str>| {
+            let fields = vec![
+                Arc::new(ArrowFieldArray::new(Box::new(Int64Array::from(k)))) as ArrayRef,
+                Arc::new(ArrowFieldArray::new(Box::new(StringArray::from(v)))) as ArrayRef,
+            ];
+            RecordBatch::new(schema.clone(), fields)
+        };
+        let batches = vec![
+            batch(vec![Some(2), None, Some(1)], vec!["a", "b", "c"]),
+            batch(vec![Some(2), Some(3)], vec!["d", "e"]),
+        ];
+        let source = Source::Mem(MemoryDataSource::new(schema.clone(), batches));
+        let sorted = |asc, nulls_first| {
+            let scan = ScanExec::new(source.clone(), vec!["k".to_string(), "v".to_string()]);
+            let order_by = vec![SortExpr::new(
+                Expr::Column(Column::new(0)),
+                asc,
+                nulls_first,
+            )];
+            let exec = SortExec::new(Plan::Scan(scan), order_by);
+            let batches = exec
+                .execute_all()
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(batches.len(), 1);
+            (0..batches[0].row_count())
+                .map(|i| {
+                    batches[0]
+                        .field(1)
+                        .get_value(i)
+                        .unwrap()
+                        .downcast_ref::<String>()
+                        .unwrap()
+                        .clone()
+                })
+                .collect::<String>()
+        };
+
+        // The rows with the same key keep their input order.
+        assert_eq!(sorted(true, false), "cadeb");
+        assert_eq!(sorted(true, true), "bcade");
+        assert_eq!(sorted(false, true), "beadc");
+        assert_eq!(sorted(false, false), "eadcb");
+    }
+}
@@ -0,0 +1,135 @@
+use std::any::Any;
+
+use crate::data_types::column_array::{ArrayRef, ColumnArray, DataType};
+
+use anyhow::{bail, Result};
+use arrow::array::{
+    BinaryArray, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array, StringArray,
+};
+use ordered_float::OrderedFloat;
+
+/// A value of a key or ordering expression that can be hashed and compared.
+/// Integers and floats are widened so values of the same kind compare across types.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyValue {
+    Boolean(bool),
+    Int(i64),
+    Float(OrderedFloat<f64>),
+    Utf8(String),
+    Binary(Vec<u8>),
+    /// The items of a list or the fields of a struct.
+    Nested(Vec<Option<KeyValue>>),
+}
+
+impl KeyValue {
+    /// Create a key from a value returned by [`ColumnArray::get_value`].
+    pub fn try_from_any(value: &dyn Any) -> Result<Self> {
+        if let Some(v) = value.downcast_ref::<bool>() {
+            return Ok(KeyValue::Boolean(*v));
+        }
+        if let Some(v) = value.downcast_ref::<i32>() {
+            return Ok(KeyValue::Int(*v as i64));
+        }
+        if let Some(v) = value.downcast_ref::<i64>() {
+            return Ok(KeyValue::Int(*v));
+        }
+        if let Some(v) = value.downcast_ref::<f32>() {
+            return Ok(KeyValue::Float(OrderedFloat(*v as f64)));
+        }
+        if let Some(v) = value.downcast_ref::<f64>() {
+            return Ok(KeyValue::Float(OrderedFloat(*v)));
+        }
+        if let Some(v) = value.downcast_ref::<String>() {
+            return Ok(KeyValue::Utf8(v.clone()));
+        }
+        if let Some(v) = value.downcast_ref::<Vec<u8>>() {
+            return Ok(KeyValue::Binary(v.clone()));
+        }
+        if let Some(v) = value.downcast_ref::<Vec<Option<Box<dyn Any>>>>() {
+            return Ok(KeyValue::Nested(
+                v.iter()
+                    .map(|v| v.as_deref().map(KeyValue::try_from_any).transpose())
+                    .collect::<Result<_>>()?,
+            ));
+        }
+        bail!("Unsupported key value")
+    }
+}
+
+/// Return the value at the given index of the array, or None for a null.
+pub fn key_value(array: &ArrayRef, i: usize) -> Result<Option<KeyValue>> {
+    if array.is_null(i) {
+        return Ok(None);
+    }
+    let value = array.get_value(i)?;
+    Ok(Some(match array.get_type() {
+        DataType::Boolean => KeyValue::Boolean(*value.downcast_ref::<bool>().unwrap()),
+        DataType::Int32 => KeyValue::Int(*value.downcast_ref::<i32>().unwrap() as i64),
+        DataType::Int64 => KeyValue::Int(*value.downcast_ref::<i64>().unwrap()),
+        DataType::Float32 => {
+            KeyValue::Float(OrderedFloat(*value.downcast_ref::<f32>().unwrap() as f64))
+        }
+        DataType::Float64 => KeyValue::Float(OrderedFloat(*value.downcast_ref::<f64>().unwrap())),
+        DataType::Utf8 => KeyValue::Utf8(value.downcast_ref::<String>().unwrap().clone()),
+        DataType::Binary => KeyValue::Binary(value.downcast_ref::<Vec<u8>>().unwrap().clone()),
+        DataType::List(_) | DataType::Struct(_) => KeyValue::try_from_any(value.as_ref())?,
+    }))
+}
+
+/// Return the values of all the rows of the array.
+/// Arrow-backed arrays are read directly, without boxing each value.
+pub fn key_values(array: &ArrayRef) -> Result<Vec<Option<KeyValue>>> {
+    let arrow = match array.as_arrow() {
+        Some(arrow) => arrow,
+        None => return (0..array.size()).map(|i| key_value(array, i)).collect(),
+    };
+    let any = arrow.as_any();
+    Ok(match array.get_type() {
+        DataType::Boolean => downcast::<BooleanArray>(any)
+            .iter()
+            .map(|v| v.map(KeyValue::Boolean))
+            .collect(),
+        DataType::Int32 => downcast::<Int32Array>(any)
+            .iter()
+            .map(|v| v.map(|v| KeyValue::Int(v as i64)))
+            .collect(),
+        DataType::Int64 => downcast::<Int64Array>(any)
+            .iter()
+            .map(|v| v.map(KeyValue::Int))
+            .collect(),
+        DataType::Float32 => downcast::<Float32Array>(any)
+            .iter()
+            .map(|v| v.map(|v| KeyValue::Float(OrderedFloat(v as f64))))
+            .collect(),
+        DataType::Float64 => downcast::<Float64Array>(any)
+            .iter()
+            .map(|v| v.map(|v| KeyValue::Float(OrderedFloat(v))))
+            .collect(),
+        DataType::Utf8 => downcast::<StringArray>(any)
+            .iter()
+            .map(|v| v.map(|v| KeyValue::Utf8(v.to_string())))
+            .collect(),
+        DataType::Binary => downcast::<BinaryArray>(any)
+            .iter()
+            .map(|v| v.map(|v| KeyValue::Binary(v.to_vec())))
+            .collect(),
+        DataType::List(_) | DataType::Struct(_) => {
+            return (0..array.size()).map(|i| key_value(array, i)).collect()
+        }
+    })
+}
+
+fn downcast<T: 'static>(any: &dyn Any) -> &T {
+    any.downcast_ref::<T>().expect("Failed to downcast")
+}
+
+/// The ordering of a row by a list of expressions, in ascending order with nulls last.
+pub type SortKey = Vec<(bool, Option<KeyValue>)>;
+
+/// Return the sort key of the row at the given index of the evaluated ordering expressions.
+pub fn sort_key(order_by: &[ArrayRef], i: usize) -> Result<SortKey> {
+    order_by
+        .iter()
+        .map(|array| key_value(array, i).map(|v| (v.is_none(), v)))
+        .collect()
+}
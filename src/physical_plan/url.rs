@@ -0,0 +1,105 @@
+/// Split a URL such as `https://user@example.com:8080/a/b?x=1#top` into its
+/// authority, path and query. URLs without a scheme are treated as a path.
+fn split_url(url: &str) -> (&str, &str, &str) {
+    let url = url.split('#').next().unwrap_or_default();
+    let (rest, query) = url.split_once('?').unwrap_or((url, ""));
+    match rest.split_once("://") {
+        Some((_, rest)) => match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..], query),
+            None => (rest, "", query),
+        },
+        None => ("", rest, query),
+    }
+}
+
+/// Return the host of the URL without user info and port, or an empty string.
+pub fn url_host(url: &str) -> String {
+    let (authority, _, _) = split_url(url);
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = if host.starts_with('[') {
+        // IPv6 literal, e.g. [::1]:8080.
+        host.split_inclusive(']').next().unwrap_or_default()
+    } else {
+        host.split(':').next().unwrap_or_default()
+    };
+    host.to_ascii_lowercase()
+}
+
+/// Return the path of the URL, or an empty string.
+pub fn url_path(url: &str) -> String {
+    split_url(url).1.to_string()
+}
+
+/// Return the decoded value of the first query parameter with the given name,
+/// or an empty string if the URL has no such parameter.
+pub fn url_query_param(url: &str, name: &str) -> String {
+    split_url(url)
+        .2
+        .split('&')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key) == name).then(|| percent_decode(value))
+        })
+        .next()
+        .unwrap_or_default()
+}
+
+/// Decode `%XX` escapes and `+` as a space. Invalid escapes are kept as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let escaped = std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match escaped {
+                    Some(b) => {
+                        decoded.push(b);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(
+            url_host("https://user@Example.com:8080/a?x=1"),
+            "example.com"
+        );
+        assert_eq!(url_host("http://[::1]:8080/"), "[::1]");
+        assert_eq!(url_host("http://example.com"), "example.com");
+        assert_eq!(url_host("/index.html"), "");
+    }
+
+    #[test]
+    fn test_url_path() {
+        assert_eq!(url_path("https://example.com/a/b?x=1#top"), "/a/b");
+        assert_eq!(url_path("https://example.com"), "");
+        assert_eq!(url_path("/index.html?x=1"), "/index.html");
+    }
+
+    #[test]
+    fn test_url_query_param() {
+        let url = "https://example.com/search?q=rust+sql&lang=en%2Dus&q=other&flag";
+        assert_eq!(url_query_param(url, "q"), "rust sql");
+        assert_eq!(url_query_param(url, "lang"), "en-us");
+        assert_eq!(url_query_param(url, "flag"), "");
+        assert_eq!(url_query_param(url, "missing"), "");
+        assert_eq!(url_query_param("/?x=100%", "x"), "100%");
+    }
+}
@@ -0,0 +1,448 @@
+use std::{any::Any, collections::HashMap, fmt::Display};
+
+use super::{
+    expr::{evaluate_from_nullable_values, Expr, PhysicalExpr},
+    memory::MemoryBudget,
+    plan::{Partitioning, PhysicalPlan, Plan},
+    sort_key::{key_value, sort_key, KeyValue, SortKey},
+};
+use crate::{
+    data_types::{column_array::ArrayRef, record_batch::RecordBatch, schema::Schema},
+    logical_plan::expr::WindowFunction,
+};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+/// WindowExpr is an expression that computes a value for each row
+/// from the rows of its partition.
+pub struct WindowExpr {
+    pub fun: WindowFunction,
+    pub args: Vec<Expr>,
+    pub partition_by: Vec<Expr>,
+    pub order_by: Vec<Expr>,
+}
+
+impl WindowExpr {
+    pub fn new(
+        fun: WindowFunction,
+        args: Vec<Expr>,
+        partition_by: Vec<Expr>,
+        order_by: Vec<Expr>,
+    ) -> Self {
+        Self {
+            fun,
+            args,
+            partition_by,
+            order_by,
+        }
+    }
+
+    /// Return the rows of each partition, numbered across all the batches, in ascending
+    /// order of their sort key. Rows that tie on the ordering are kept in input order.
+    fn partitions(&self, batches: &[RecordBatch]) -> Result<Vec<Vec<(SortKey, usize)>>> {
+        let mut index: HashMap<Vec<Option<KeyValue>>, usize> = HashMap::new();
+        let mut partitions: Vec<Vec<(SortKey, usize)>> = vec![];
+        let mut row = 0;
+        for batch in batches {
+            let keys = self
+                .partition_by
+                .iter()
+                .map(|e| e.evaluate(batch))
+                .collect::<Result<Vec<_>>>()?;
+            let order_by = self
+                .order_by
+                .iter()
+                .map(|e| e.evaluate(batch))
+                .collect::<Result<Vec<_>>>()?;
+            for i in 0..batch.row_count() {
+                let key = keys
+                    .iter()
+                    .map(|k| key_value(k, i))
+                    .collect::<Result<Vec<_>>>()?;
+                let p = *index.entry(key).or_insert_with(|| {
+                    partitions.push(vec![]);
+                    partitions.len() - 1
+                });
+                partitions[p].push((sort_key(&order_by, i)?, row));
+                row += 1;
+            }
+        }
+        for partition in &mut partitions {
+            partition.sort_by(|(l, _), (r, _)| l.cmp(r));
+        }
+        Ok(partitions)
+    }
+
+    /// Evaluate the window function for all the rows of the batches, in input order.
+    pub fn evaluate(&self, batches: &[RecordBatch]) -> Result<Vec<Option<Box<dyn Any>>>> {
+        let args = Arguments::evaluate(&self.args, batches)?;
+        let mut values = (0..args.row_count()).map(|_| None).collect::<Vec<_>>();
+        for partition in self.partitions(batches)? {
+            let (mut rank, mut dense_rank) = (0_i64, 0_i64);
+            for (i, (key, row)) in partition.iter().enumerate() {
+                // Peers share a rank.
+                if i == 0 || &partition[i - 1].0 != key {
+                    rank = i as i64 + 1;
+                    dense_rank += 1;
+                }
+                values[*row] = match self.fun {
+                    WindowFunction::RowNumber => Some(Box::new(i as i64 + 1) as Box<dyn Any>),
+                    WindowFunction::Rank => Some(Box::new(rank)),
+                    WindowFunction::DenseRank => Some(Box::new(dense_rank)),
+                    WindowFunction::Lead | WindowFunction::Lag => {
+                        let offset = match args.value(1, *row)? {
+                            Some(offset) => offset_value(offset.as_ref())?,
+                            None => bail!("{} offset cannot be null", self.fun),
+                        };
+                        let target = match self.fun {
+                            WindowFunction::Lead => i.checked_add(offset),
+                            _ => i.checked_sub(offset),
+                        };
+                        match target.and_then(|t| partition.get(t)) {
+                            Some((_, target_row)) => args.value(0, *target_row)?,
+                            None => args.value(2, *row)?,
+                        }
+                    }
+                    WindowFunction::Ntile => {
+                        let buckets = match args.value(0, *row)? {
+                            Some(buckets) => integer_value(buckets.as_ref())?,
+                            None => bail!("{} number of buckets cannot be null", self.fun),
+                        };
+                        if buckets <= 0 {
+                            bail!("Number of buckets must be positive, got {}", buckets);
+                        }
+                        Some(Box::new(bucket(i, partition.len(), buckets as usize)))
+                    }
+                };
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// The evaluated arguments of a window function, for all the batches of the input.
+struct Arguments {
+    /// The arrays of the arguments of each batch.
+    arrays: Vec<Vec<ArrayRef>>,
+    /// The batch and the index in the batch of each row.
+    rows: Vec<(usize, usize)>,
+}
+
+impl Arguments {
+    fn evaluate(args: &[Expr], batches: &[RecordBatch]) -> Result<Self> {
+        let arrays = batches
+            .iter()
+            .map(|batch| args.iter().map(|arg| arg.evaluate(batch)).collect())
+            .collect::<Result<Vec<Vec<_>>>>()?;
+        let rows = batches
+            .iter()
+            .enumerate()
+            .flat_map(|(b, batch)| (0..batch.row_count()).map(move |i| (b, i)))
+            .collect();
+        Ok(Self { arrays, rows })
+    }
+
+    fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Return the value of an argument at a row, or None for a null.
+    fn value(&self, arg: usize, row: usize) -> Result<Option<Box<dyn Any>>> {
+        let (b, i) = self.rows[row];
+        let array = &self.arrays[b][arg];
+        (!array.is_null(i)).then(|| array.get_value(i)).transpose()
+    }
+}
+
+fn integer_value(value: &dyn Any) -> Result<i64> {
+    if let Some(v) = value.downcast_ref::<i32>() {
+        return Ok(*v as i64);
+    }
+    if let Some(v) = value.downcast_ref::<i64>() {
+        return Ok(*v);
+    }
+    bail!("Window function arguments must be integers")
+}
+
+fn offset_value(offset: &dyn Any) -> Result<usize> {
+    let offset = integer_value(offset)?;
+    if offset < 0 {
+        bail!("Window offsets cannot be negative, got {}", offset);
+    }
+    Ok(offset as usize)
+}
+
+/// Return the bucket, from 1, of the i-th row of a partition split into buckets.
+/// The bucket sizes differ by at most one, and the larger buckets come first.
+fn bucket(i: usize, len: usize, buckets: usize) -> i64 {
+    let (size, larger) = (len / buckets, len % buckets);
+    let bucket = if i < larger * (size + 1) {
+        i / (size + 1)
+    } else {
+        larger + (i - larger * (size + 1)) / size
+    };
+    bucket as i64 + 1
+}
+
+impl Display for WindowExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |exprs: &[Expr]| {
+            exprs
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(f, "{}({}) OVER (", self.fun, join(&self.args))?;
+        if !self.partition_by.is_empty() {
+            write!(f, "PARTITION BY {}", join(&self.partition_by))?;
+            if !self.order_by.is_empty() {
+                write!(f, " ")?;
+            }
+        }
+        if !self.order_by.is_empty() {
+            write!(f, "ORDER BY {}", join(&self.order_by))?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Execute window functions.
+/// All the input is read first, since the rows of a partition can come in any order.
+/// The rows keep their input order, with a column added per window expression.
+pub struct WindowExec {
+    pub(crate) input: Box<Plan>,
+    pub(crate) schema: Schema,
+    pub(crate) window_exprs: Vec<WindowExpr>,
+    memory: MemoryBudget,
+}
+
+impl WindowExec {
+    pub fn new(input: Plan, schema: Schema, window_exprs: Vec<WindowExpr>) -> Self {
+        Self {
+            input: Box::new(input),
+            schema,
+            window_exprs,
+            memory: MemoryBudget::default(),
+        }
+    }
+
+    /// Reserve the data buffered by the operator from the memory budget of the query.
+    pub fn with_memory_budget(mut self, memory: MemoryBudget) -> Self {
+        self.memory = memory;
+        self
+    }
+}
+
+impl PhysicalPlan for WindowExec {
+    fn schema(&self) -> Schema {
+        self.schema.clone()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::Unknown(1)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<RecordBatch>> + '_>> {
+        self.output_partitioning().check(partition)?;
+        let mut reservation = self.memory.reservation("WindowExec");
+        let batches = reservation.collect(self.input.execute_all()?)?;
+        let columns = self
+            .window_exprs
+            .iter()
+            .map(|e| e.evaluate(&batches))
+            .collect::<Result<Vec<_>>>()?;
+        let window_fields = &self.schema.fields[self.schema.fields.len() - columns.len()..];
+        let mut offset = 0;
+        let batches = batches
+            .into_iter()
+            .map(|b| {
+                let rows = offset..offset + b.row_count();
+                offset = rows.end;
+                let mut fields = (0..b.column_count())
+                    .map(|i| b.field(i).clone())
+                    .collect::<Vec<_>>();
+                for (values, field) in columns.iter().zip(window_fields) {
+                    fields.push(evaluate_from_nullable_values(
+                        &values[rows.clone()],
+                        &field.data_type,
+                    )?);
+                }
+                Ok(RecordBatch::new(self.schema.clone(), fields))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(batches.into_iter().map(Ok)))
+    }
+
+    fn children(&self) -> Vec<&Plan> {
+        vec![&self.input]
+    }
+}
+
+impl Display for WindowExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "WindowExec: windowExpr={}",
+            self.window_exprs
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::Source,
+        data_types::{column_array::DataType, schema::Field},
+        physical_plan::{
+            expr::{Column, ScalarValue},
+            scan::ScanExec,
+        },
+        test_util::csv_test_data_source,
+    };
+
+    #[test]
+    fn test_window_execute() {
+        let schema = Schema::new(vec![
+            Field::new("user".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        let csv_data_source = csv_test_data_source("events.csv", schema.clone(), 4);
+        let scan = ScanExec::new(
+            Source::Csv(csv_data_source),
+            vec!["user".to_string(), "ts".to_string()],
+        );
+        let mut fields = schema.fields;
+        for name in ["row_number", "rank", "dense_rank"] {
+            fields.push(Field::new(name.to_string(), DataType::Int64));
+        }
+        let user = || Expr::Column(Column::new(0));
+        let ts = || Expr::Column(Column::new(1));
+        let window = WindowExec::new(
+            Plan::Scan(scan),
+            Schema::new(fields),
+            vec![
+                WindowExpr::new(WindowFunction::RowNumber, vec![], vec![user()], vec![ts()]),
+                WindowExpr::new(WindowFunction::Rank, vec![], vec![], vec![user()]),
+                WindowExpr::new(WindowFunction::DenseRank, vec![], vec![], vec![user()]),
+            ],
+        );
+        assert_eq!(
+            window.to_string(),
+            "WindowExec: windowExpr=ROW_NUMBER() OVER (PARTITION BY #0 ORDER BY #1), RANK() OVER (ORDER BY #0), DENSE_RANK() OVER (ORDER BY #0)"
+        );
+
+        let batches = window
+            .execute_all()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(batches.len(), 2);
+        let column = |c: usize| {
+            batches
+                .iter()
+                .flat_map(|b| {
+                    let values = b.field(c).clone();
+                    (0..values.size())
+                        .map(|i| *values.get_value(i).unwrap().downcast_ref::<i64>().unwrap())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(column(2), vec![1, 1, 2, 3, 2, 4]);
+        assert_eq!(column(3), vec![1, 5, 1, 1, 5, 1]);
+        assert_eq!(column(4), vec![1, 2, 1, 1, 2, 1]);
+    }
+
+    #[test]
+    fn test_window_lead_lag() {
+        let schema = Schema::new(vec![
+            Field::new("user".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        let csv_data_source = csv_test_data_source("events.csv", schema.clone(), 4);
+        let scan = ScanExec::new(
+            Source::Csv(csv_data_source),
+            vec!["user".to_string(), "ts".to_string()],
+        );
+        let mut fields = schema.fields;
+        for name in ["lead", "lag"] {
+            fields.push(Field::new(name.to_string(), DataType::Int64));
+        }
+        let offset_expr = |fun: WindowFunction, offset: i64, default: i64| {
+            WindowExpr::new(
+                fun,
+                vec![
+                    Expr::Column(Column::new(1)),
+                    Expr::Literal(ScalarValue::Int64(offset)),
+                    Expr::Literal(ScalarValue::Int64(default)),
+                ],
+                vec![Expr::Column(Column::new(0))],
+                vec![Expr::Column(Column::new(1))],
+            )
+        };
+        let window = WindowExec::new(
+            Plan::Scan(scan),
+            Schema::new(fields),
+            vec![
+                offset_expr(WindowFunction::Lead, 2, -1),
+                offset_expr(WindowFunction::Lag, 1, -1),
+            ],
+        );
+        assert_eq!(
+            window.to_string(),
+            "WindowExec: windowExpr=LEAD(#1, 2, -1) OVER (PARTITION BY #0 ORDER BY #1), LAG(#1, 1, -1) OVER (PARTITION BY #0 ORDER BY #1)"
+        );
+        let batches = window
+            .execute_all()
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let column = |c: usize| {
+            batches
+                .iter()
+                .flat_map(|b| {
+                    let values = b.field(c).clone();
+                    (0..values.size())
+                        .map(|i| *values.get_value(i).unwrap().downcast_ref::<i64>().unwrap())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(column(2), vec![5000, -1, 5100, -1, -1, -1]);
+        assert_eq!(column(3), vec![-1, -1, 0, 100, 10, 5000]);
+
+        let window = WindowExec::new(
+            *window.input,
+            window.schema,
+            vec![offset_expr(WindowFunction::Lead, -1, 0)],
+        );
+        assert_eq!(
+            window.execute_all().err().unwrap().to_string(),
+            "Window offsets cannot be negative, got -1"
+        );
+    }
+
+    #[test]
+    fn test_bucket() {
+        let buckets = |len: usize, buckets: usize| {
+            (0..len)
+                .map(|i| bucket(i, len, buckets))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(buckets(6, 4), vec![1, 1, 2, 2, 3, 4]);
+        assert_eq!(buckets(4, 2), vec![1, 1, 2, 2]);
+        assert_eq!(buckets(3, 5), vec![1, 2, 3]);
+        assert_eq!(buckets(10, 3), vec![1, 1, 1, 1, 2, 2, 2, 3, 3, 3]);
+    }
+}
@@ -1,41 +1,159 @@
+use std::cell::Cell;
+
 use crate::{
-    data_types::schema::{Field, Schema},
+    data_types::{
+        column_array::DataType,
+        schema::{Field, Schema},
+    },
     logical_plan::{
-        expr::{Expr as LogicalExpr, LogicalExpr as _, ScalarValue as LogicalScalarValue},
+        expr::{
+            Expr as LogicalExpr, LogicalExpr as _, ReturnType, ScalarValue as LogicalScalarValue,
+            WindowFunction,
+        },
+        join::Join,
         plan::{LogicalPlan as _, Plan as LogicalPlan},
     },
     physical_plan::{
         aggregate::AggregateExpr,
+        checked::CheckedExec,
+        distinct_on::DistinctOnExec,
         expr::{
-            BinaryExpr, Cast, Column, Expr as PhysicalExpr, ScalarValue as PhysicalScalarValue,
+            BinaryExpr, Cast, Column, DivisionByZeroMode, Expr as PhysicalExpr, OverflowMode,
+            Placeholder, ScalarValue as PhysicalScalarValue,
         },
+        fill::FillExec,
+        functions::ScalarFunctionExpr,
+        gap_fill::GapFillExec,
         hash::HashExec,
+        hash_join::HashJoinExec,
+        limit::LimitExec,
+        memory::MemoryBudget,
+        metrics::MetricsExec,
+        nested::{GetField, GetIndex},
+        observed::{ObservedExec, OperatorHook},
+        pipeline::{MorselScheduler, PipelineExec},
         plan::Plan as PhysicalPlan,
         projection::ProjectionExec,
         scan::ScanExec,
         selection::SelectionExec,
+        selection_projection::SelectionProjectionExec,
+        sessionize::SessionizeExec,
+        sort::{SortExec, SortExpr},
+        window::{WindowExec, WindowExpr},
     },
 };
 
 use anyhow::{anyhow, Error, Result};
 
 /// The query planner creates a physical query plan from a logical query plan.
-pub struct QueryPlanner;
+#[derive(Debug, Clone, Default)]
+pub struct QueryPlanner {
+    overflow_mode: OverflowMode,
+    division_by_zero: DivisionByZeroMode,
+    check_batches: bool,
+    collect_metrics: bool,
+    memory_budget: MemoryBudget,
+    morsel_scheduler: Option<MorselScheduler>,
+    operator_hook: Option<OperatorHook>,
+    // The depth in the plan of the operator being created.
+    depth: Cell<usize>,
+}
 
 impl QueryPlanner {
+    pub fn new(overflow_mode: OverflowMode, division_by_zero: DivisionByZeroMode) -> Self {
+        Self {
+            overflow_mode,
+            division_by_zero,
+            check_batches: false,
+            collect_metrics: false,
+            memory_budget: MemoryBudget::default(),
+            morsel_scheduler: None,
+            operator_hook: None,
+            depth: Cell::default(),
+        }
+    }
+
+    /// Wrap every operator in a [`CheckedExec`] that validates the batches it produces.
+    pub fn with_check_batches(mut self, check_batches: bool) -> Self {
+        self.check_batches = check_batches;
+        self
+    }
+
+    /// Wrap every operator in a [`MetricsExec`] that records its runtime metrics.
+    pub fn with_collect_metrics(mut self, collect_metrics: bool) -> Self {
+        self.collect_metrics = collect_metrics;
+        self
+    }
+
+    /// Share the memory budget of the query between the operators that buffer data.
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// Run the pipelines of the plan morsel by morsel with the scheduler.
+    pub fn with_morsel_scheduler(mut self, morsel_scheduler: MorselScheduler) -> Self {
+        self.morsel_scheduler = Some(morsel_scheduler);
+        self
+    }
+
+    /// Wrap every operator in an [`ObservedExec`] that calls the hook when it starts.
+    pub fn with_operator_hook(mut self, operator_hook: OperatorHook) -> Self {
+        self.operator_hook = Some(operator_hook);
+        self
+    }
+
     /// Create a physical plan from a logical plan.
-    pub fn create_physical_plan(plan: &LogicalPlan) -> Result<PhysicalPlan> {
+    pub fn create_physical_plan(&self, plan: &LogicalPlan) -> Result<PhysicalPlan> {
+        Ok(self.create_pipeline(self.create_plan(plan)?))
+    }
+
+    /// Run the plan on the morsel scheduler if it is a pipeline.
+    fn create_pipeline(&self, physical_plan: PhysicalPlan) -> PhysicalPlan {
+        match &self.morsel_scheduler {
+            Some(scheduler) if PipelineExec::is_pipeline(&physical_plan) => {
+                PhysicalPlan::Pipeline(PipelineExec::new(physical_plan, scheduler.clone()))
+            }
+            _ => physical_plan,
+        }
+    }
+
+    /// Create a physical plan whose streaming operators at the top may still be part
+    /// of the pipeline of the operator that consumes it.
+    fn create_plan(&self, plan: &LogicalPlan) -> Result<PhysicalPlan> {
+        let depth = self.depth.replace(self.depth.get() + 1);
+        let operator = self.create_operator(plan);
+        self.depth.set(depth);
+        let mut physical_plan = operator?;
+        if let Some(hook) = &self.operator_hook {
+            physical_plan =
+                PhysicalPlan::Observed(ObservedExec::new(physical_plan, depth, hook.clone()));
+        }
+        Ok(self.instrument(physical_plan))
+    }
+
+    /// Validate the batches of the operator and record its metrics, as configured.
+    fn instrument(&self, mut physical_plan: PhysicalPlan) -> PhysicalPlan {
+        if self.check_batches {
+            physical_plan = PhysicalPlan::Checked(CheckedExec::new(physical_plan));
+        }
+        if self.collect_metrics {
+            physical_plan = PhysicalPlan::Metrics(MetricsExec::new(physical_plan));
+        }
+        physical_plan
+    }
+
+    fn create_operator(&self, plan: &LogicalPlan) -> Result<PhysicalPlan> {
         match plan {
             LogicalPlan::Scan(scan) => {
                 let scan = ScanExec::new(scan.data_source.clone(), scan.projection.clone());
                 Ok(PhysicalPlan::Scan(scan))
             }
             LogicalPlan::Projection(projection) => {
-                let input = QueryPlanner::create_physical_plan(projection.input.as_ref())?;
                 let projection_exprs = projection
                     .exprs
                     .iter()
-                    .map(|expr| QueryPlanner::create_physical_expr(expr, projection.input.as_ref()))
+                    .map(|expr| self.create_physical_expr(expr, projection.input.as_ref()))
                     .collect::<Result<Vec<PhysicalExpr>, _>>()?;
                 let projection_schema = Schema::new(
                     projection
@@ -44,45 +162,282 @@ impl QueryPlanner {
                         .map(|expr| expr.to_field(projection.input.as_ref()))
                         .collect::<Result<Vec<Field>, _>>()?,
                 );
+                // A projection over a selection is fused with it, so that every batch
+                // is traversed once, without materializing the selected rows.
+                if let LogicalPlan::Selection(s) = projection.input.as_ref() {
+                    let input = self.create_plan(s.input.as_ref())?;
+                    let predicate = self.create_physical_expr(&s.expr, s.input.as_ref())?;
+                    let fused = SelectionProjectionExec::try_new(
+                        input,
+                        predicate,
+                        projection_schema,
+                        projection_exprs,
+                    )?;
+                    return Ok(PhysicalPlan::SelectionProjection(fused));
+                }
+                let input = self.create_plan(projection.input.as_ref())?;
                 let projection_exec =
                     ProjectionExec::new(input, projection_schema, projection_exprs);
                 Ok(PhysicalPlan::Projection(projection_exec))
             }
             LogicalPlan::Selection(s) => {
-                let input = QueryPlanner::create_physical_plan(s.input.as_ref())?;
-                let filer_expr = QueryPlanner::create_physical_expr(&s.expr, s.input.as_ref())?;
+                let input = self.create_plan(s.input.as_ref())?;
+                let filer_expr = self.create_physical_expr(&s.expr, s.input.as_ref())?;
                 let selection_exec = SelectionExec::new(input, filer_expr);
                 Ok(PhysicalPlan::Selection(selection_exec))
             }
+            LogicalPlan::Sessionize(s) => {
+                let input = self.create_physical_plan(s.input.as_ref())?;
+                let key = self.create_physical_expr(&s.key, s.input.as_ref())?;
+                let timestamp_type = s.timestamp.to_field(s.input.as_ref())?.data_type;
+                if !matches!(timestamp_type, DataType::Int32 | DataType::Int64) {
+                    return Err(anyhow!(
+                        "Session timestamps must be integers, got {}",
+                        timestamp_type
+                    ));
+                }
+                let timestamp = self.create_physical_expr(&s.timestamp, s.input.as_ref())?;
+                let sessionize_exec = SessionizeExec::new(input, s.schema(), key, timestamp, s.gap)
+                    .with_memory_budget(self.memory_budget.clone());
+                Ok(PhysicalPlan::Sessionize(sessionize_exec))
+            }
+            LogicalPlan::DistinctOn(d) => {
+                let input = self.create_physical_plan(d.input.as_ref())?;
+                let keys = d
+                    .keys
+                    .iter()
+                    .map(|expr| self.create_physical_expr(expr, d.input.as_ref()))
+                    .collect::<Result<Vec<PhysicalExpr>, _>>()?;
+                let order_by = d
+                    .order_by
+                    .iter()
+                    .map(|e| {
+                        let expr = self.create_physical_expr(&e.expr, d.input.as_ref())?;
+                        Ok(SortExpr::new(expr, e.asc, e.nulls_first))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let distinct_on_exec = DistinctOnExec::new(input, keys, order_by)
+                    .with_memory_budget(self.memory_budget.clone());
+                Ok(PhysicalPlan::DistinctOn(distinct_on_exec))
+            }
+            LogicalPlan::Join(j) => self.create_hash_join(j),
+            LogicalPlan::Sort(s) => {
+                let input = self.create_physical_plan(s.input.as_ref())?;
+                let order_by = s
+                    .order_by
+                    .iter()
+                    .map(|e| {
+                        let expr = self.create_physical_expr(&e.expr, s.input.as_ref())?;
+                        Ok(SortExpr::new(expr, e.asc, e.nulls_first))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let sort_exec =
+                    SortExec::new(input, order_by).with_memory_budget(self.memory_budget.clone());
+                Ok(PhysicalPlan::Sort(sort_exec))
+            }
+            LogicalPlan::Limit(l) => {
+                let input = self.create_physical_plan(l.input.as_ref())?;
+                Ok(PhysicalPlan::Limit(LimitExec::new(input, l.skip, l.fetch)))
+            }
+            LogicalPlan::Command(c) => Err(anyhow!(
+                "{} is applied by the context when it is executed",
+                c
+            )),
+            LogicalPlan::Fill(f) => {
+                let schema = f.input.schema();
+                let columns = f
+                    .columns
+                    .iter()
+                    .map(|name| {
+                        schema
+                            .fields
+                            .iter()
+                            .position(|field| &field.name == name)
+                            .ok_or_else(|| anyhow!("No column named {}", name))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let input = self.create_physical_plan(f.input.as_ref())?;
+                let order_by = f
+                    .order_by
+                    .iter()
+                    .map(|expr| self.create_physical_expr(expr, f.input.as_ref()))
+                    .collect::<Result<Vec<PhysicalExpr>, _>>()?;
+                let fill_exec = FillExec::new(input, columns, order_by, f.direction)
+                    .with_memory_budget(self.memory_budget.clone());
+                Ok(PhysicalPlan::Fill(fill_exec))
+            }
+            LogicalPlan::Window(w) => {
+                let input = self.create_physical_plan(w.input.as_ref())?;
+                let create_exprs = |exprs: &[LogicalExpr]| {
+                    exprs
+                        .iter()
+                        .map(|expr| self.create_physical_expr(expr, w.input.as_ref()))
+                        .collect::<Result<Vec<PhysicalExpr>, _>>()
+                };
+                let window_exprs = w
+                    .window_exprs
+                    .iter()
+                    .map(|expr| {
+                        // Check the expressions of the window function.
+                        let data_type = expr.to_field(w.input.as_ref())?.data_type;
+                        let mut unaliased = expr;
+                        while let LogicalExpr::Alias(alias) = unaliased {
+                            unaliased = alias.expr.as_ref();
+                        }
+                        match unaliased {
+                            LogicalExpr::WindowFunction(window) => {
+                                let mut args = create_exprs(&window.args)?;
+                                if let WindowFunction::Lead | WindowFunction::Lag = window.fun {
+                                    // The default value takes the type of the value.
+                                    let default = args.pop().unwrap();
+                                    args.push(self.coerce(
+                                        default,
+                                        &window.args[2],
+                                        &data_type,
+                                        w.input.as_ref(),
+                                    )?);
+                                }
+                                Ok(WindowExpr::new(
+                                    window.fun.clone(),
+                                    args,
+                                    create_exprs(&window.partition_by)?,
+                                    create_exprs(&window.order_by)?,
+                                ))
+                            }
+                            _ => Err(anyhow!("{} is not a window expression", expr)),
+                        }
+                    })
+                    .collect::<Result<Vec<WindowExpr>>>()?;
+                let window_exec = WindowExec::new(input, w.schema(), window_exprs)
+                    .with_memory_budget(self.memory_budget.clone());
+                Ok(PhysicalPlan::Window(window_exec))
+            }
+            LogicalPlan::GapFill(g) => {
+                let bucket_type = &g.input.schema().fields[0].data_type;
+                if *bucket_type != DataType::Int64 {
+                    return Err(anyhow!("Time buckets must be Int64, got {}", bucket_type));
+                }
+                let input = self.create_physical_plan(g.input.as_ref())?;
+                let gap_fill_exec =
+                    GapFillExec::new(input, g.every).with_memory_budget(self.memory_budget.clone());
+                Ok(PhysicalPlan::GapFill(gap_fill_exec))
+            }
             LogicalPlan::Aggregate(a) => {
-                let input = QueryPlanner::create_physical_plan(a.input.as_ref())?;
                 let group_exprs = a
                     .group_exprs
                     .iter()
-                    .map(|expr| QueryPlanner::create_physical_expr(expr, a.input.as_ref()))
+                    .map(|expr| self.create_physical_expr(expr, a.input.as_ref()))
                     .collect::<Result<Vec<PhysicalExpr>, _>>()?;
                 let aggr_exprs = a
                     .aggregate_exprs
                     .iter()
-                    .map(|expr| match expr {
-                        LogicalExpr::AggregateFunction(agg) => {
-                            let expr = QueryPlanner::create_physical_expr(
-                                agg.expr.as_ref(),
-                                a.input.as_ref(),
-                            )?;
-                            Ok::<_, Error>(AggregateExpr::new(expr, agg.fun.clone()))
+                    .map(|expr| {
+                        // An alias only names the output field, which the schema below takes
+                        // from the logical expression.
+                        let mut unaliased = expr;
+                        while let LogicalExpr::Alias(alias) = unaliased {
+                            unaliased = alias.expr.as_ref();
+                        }
+                        match unaliased {
+                            LogicalExpr::AggregateFunction(agg) => {
+                                let args = agg
+                                    .args
+                                    .iter()
+                                    .map(|arg| self.create_physical_expr(arg, a.input.as_ref()))
+                                    .collect::<Result<Vec<PhysicalExpr>, _>>()?;
+                                Ok::<_, Error>(AggregateExpr::new(args, agg.fun.clone()))
+                            }
+                            _ => Err(anyhow!("{} is not an aggregate expression", expr)),
                         }
-                        _ => unreachable!(),
                     })
                     .collect::<Result<Vec<AggregateExpr>, _>>()?;
-                let hash_exec = HashExec::new(input, a.schema(), group_exprs, aggr_exprs);
-                Ok(PhysicalPlan::Hash(hash_exec))
+                let aggregate_schema = Schema::new(
+                    a.group_exprs
+                        .iter()
+                        .chain(a.aggregate_exprs.iter())
+                        .map(|expr| expr.to_field(a.input.as_ref()))
+                        .collect::<Result<Vec<Field>, _>>()?,
+                );
+                // Unaliased aggregates are named after their function and arguments,
+                // so only the same aggregate twice, or colliding aliases, share a name.
+                for (i, field) in aggregate_schema.fields.iter().enumerate() {
+                    if aggregate_schema.fields[..i]
+                        .iter()
+                        .any(|f| f.name == field.name)
+                    {
+                        return Err(anyhow!(
+                            "Aggregate has several columns named {}, alias them to tell them apart",
+                            field.name
+                        ));
+                    }
+                }
+                let input = self.create_plan(a.input.as_ref())?;
+                let merge_exprs = aggr_exprs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, expr)| expr.merge_expr(group_exprs.len() + i))
+                    .collect::<Option<Vec<_>>>();
+                match (&self.morsel_scheduler, merge_exprs) {
+                    // Every morsel is aggregated in the pipeline, and the groups of the
+                    // morsels are merged above it.
+                    (Some(scheduler), Some(merge_exprs))
+                        if PipelineExec::is_aggregate_input(&input) =>
+                    {
+                        let merge_group_exprs = (0..group_exprs.len())
+                            .map(|i| PhysicalExpr::Column(Column::new(i)))
+                            .collect();
+                        let partial_exec =
+                            HashExec::new(input, aggregate_schema.clone(), group_exprs, aggr_exprs)
+                                .with_memory_budget(self.memory_budget.clone());
+                        let pipeline = PipelineExec::new(
+                            self.instrument(PhysicalPlan::Hash(partial_exec)),
+                            scheduler.clone(),
+                        );
+                        let hash_exec = HashExec::new(
+                            PhysicalPlan::Pipeline(pipeline),
+                            aggregate_schema,
+                            merge_group_exprs,
+                            merge_exprs,
+                        )
+                        .with_memory_budget(self.memory_budget.clone());
+                        Ok(PhysicalPlan::Hash(hash_exec))
+                    }
+                    _ => {
+                        let input = self.create_pipeline(input);
+                        let hash_exec =
+                            HashExec::new(input, aggregate_schema, group_exprs, aggr_exprs)
+                                .with_memory_budget(self.memory_budget.clone());
+                        Ok(PhysicalPlan::Hash(hash_exec))
+                    }
+                }
             }
         }
     }
 
     /// Create a physical expression from a logical expression.
-    fn create_physical_expr(expr: &LogicalExpr, input: &LogicalPlan) -> Result<PhysicalExpr> {
+    /// Create a hash join, whose keys are computed over its left and right inputs.
+    fn create_hash_join(&self, join: &Join) -> Result<PhysicalPlan> {
+        let on = join
+            .on
+            .iter()
+            .map(|(left, right)| {
+                let left = self.create_physical_expr(left, join.left.as_ref())?;
+                let right = self.create_physical_expr(right, join.right.as_ref())?;
+                Ok((left, right))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let left = self.create_physical_plan(join.left.as_ref())?;
+        let right = self.create_physical_plan(join.right.as_ref())?;
+        let join_exec = HashJoinExec::new(left, right, on, join.schema())
+            .with_memory_budget(self.memory_budget.clone());
+        Ok(PhysicalPlan::HashJoin(join_exec))
+    }
+
+    fn create_physical_expr(
+        &self,
+        expr: &LogicalExpr,
+        input: &LogicalPlan,
+    ) -> Result<PhysicalExpr> {
         match expr {
             LogicalExpr::Column(c) => {
                 let index = input.schema().fields.iter().position(|f| f.name == c.name);
@@ -98,35 +453,111 @@ impl QueryPlanner {
                 let column = Column::new(cl.index);
                 Ok(PhysicalExpr::Column(column))
             }
-            LogicalExpr::Literal(l) => {
-                let l = match l {
-                    LogicalScalarValue::String(s) => PhysicalScalarValue::String(s.clone()),
-                    LogicalScalarValue::Int32(i) => PhysicalScalarValue::Int32(*i),
-                    LogicalScalarValue::Int64(i) => PhysicalScalarValue::Int64(*i),
-                    LogicalScalarValue::Float32(f) => PhysicalScalarValue::Float32(*f),
-                    LogicalScalarValue::Float64(b) => PhysicalScalarValue::Float64(*b),
-                };
-                Ok(PhysicalExpr::Literal(l))
+            LogicalExpr::Literal(l) => Ok(PhysicalExpr::Literal(create_physical_scalar(l))),
+            LogicalExpr::Placeholder(p) => {
+                // Check that the placeholder has a type.
+                p.to_field(input)?;
+                let placeholder = Placeholder::new(p.id.clone());
+                Ok(PhysicalExpr::Placeholder(placeholder))
             }
             LogicalExpr::Cast(c) => {
-                let expr = QueryPlanner::create_physical_expr(c.expr.as_ref(), input)?;
-                Ok(PhysicalExpr::Cast(Cast::new(expr, c.data_type.clone())))
+                let expr = self.create_physical_expr(c.expr.as_ref(), input)?;
+                let cast = Cast::new(expr, c.data_type.clone())
+                    .with_try_cast(c.try_cast)
+                    .with_extension(c.expr.to_field(input)?.extension);
+                Ok(PhysicalExpr::Cast(cast))
             }
             LogicalExpr::BinaryExpr(b) => {
-                let l = QueryPlanner::create_physical_expr(b.left.as_ref(), input)?;
-                let r = QueryPlanner::create_physical_expr(b.right.as_ref(), input)?;
-                let binary_expr = BinaryExpr::new(b.op, l, r);
+                let operand_type = b.operand_type(input)?;
+                let l = self.create_physical_expr(b.left.as_ref(), input)?;
+                let l = self.coerce(l, b.left.as_ref(), &operand_type, input)?;
+                let r = self.create_physical_expr(b.right.as_ref(), input)?;
+                let r = self.coerce(r, b.right.as_ref(), &operand_type, input)?;
+                let binary_expr = BinaryExpr::new(b.op, l, r)
+                    .with_overflow_mode(self.overflow_mode)
+                    .with_division_by_zero(self.division_by_zero);
                 Ok(PhysicalExpr::BinaryExpr(binary_expr))
             }
             LogicalExpr::Alias(a) => {
                 // Note that there is no physical expression for an alias since the alias
                 // only affects the name using in the planning phase and not how the aliased
                 // expression is executed
-                return QueryPlanner::create_physical_expr(a.expr.as_ref(), input);
+                self.create_physical_expr(a.expr.as_ref(), input)
+            }
+            LogicalExpr::GetField(g) => {
+                // Check that the struct has the field.
+                expr.to_field(input)?;
+                let expr = self.create_physical_expr(g.expr.as_ref(), input)?;
+                Ok(PhysicalExpr::GetField(GetField::new(expr, g.name.clone())))
+            }
+            LogicalExpr::GetIndex(g) => {
+                // Check the types of the list and the index.
+                expr.to_field(input)?;
+                let list = self.create_physical_expr(g.expr.as_ref(), input)?;
+                let index = self.create_physical_expr(g.index.as_ref(), input)?;
+                let index = self.coerce(index, g.index.as_ref(), &DataType::Int64, input)?;
+                Ok(PhysicalExpr::GetIndex(GetIndex::new(list, index)))
             }
             LogicalExpr::Not(_) => unreachable!(),
-            LogicalExpr::ScalarFunction(_s) => unreachable!(),
+            LogicalExpr::ScalarFunction(f) => {
+                let mut args = f
+                    .args
+                    .iter()
+                    .map(|arg| self.create_physical_expr(arg, input))
+                    .collect::<Result<Vec<PhysicalExpr>, _>>()?;
+                if f.return_type == ReturnType::CommonType {
+                    let common_type = f.to_field(input)?.data_type;
+                    args = args
+                        .into_iter()
+                        .zip(&f.args)
+                        .map(|(arg, logical_arg)| {
+                            self.coerce(arg, logical_arg, &common_type, input)
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                }
+                let function = ScalarFunctionExpr::try_new(&f.name, args)?
+                    .with_overflow_mode(self.overflow_mode);
+                Ok(PhysicalExpr::ScalarFunction(function))
+            }
             LogicalExpr::AggregateFunction(_) => unreachable!(),
+            LogicalExpr::WindowFunction(_) => Err(anyhow!(
+                "Window functions can only be computed by a window plan"
+            )),
+        }
+    }
+
+    /// Cast the physical expression created for the logical expression
+    /// to the given type, unless it already has that type.
+    fn coerce(
+        &self,
+        expr: PhysicalExpr,
+        logical_expr: &LogicalExpr,
+        data_type: &DataType,
+        input: &LogicalPlan,
+    ) -> Result<PhysicalExpr> {
+        if logical_expr.is_untyped_null() {
+            Ok(PhysicalExpr::Literal(PhysicalScalarValue::Null(
+                data_type.clone(),
+            )))
+        } else if &logical_expr.to_field(input)?.data_type == data_type {
+            Ok(expr)
+        } else {
+            Ok(PhysicalExpr::Cast(Cast::new(expr, data_type.clone())))
+        }
+    }
+}
+
+/// Create the physical value of a logical value.
+pub fn create_physical_scalar(value: &LogicalScalarValue) -> PhysicalScalarValue {
+    match value {
+        LogicalScalarValue::Boolean(b) => PhysicalScalarValue::Boolean(*b),
+        LogicalScalarValue::String(s) => PhysicalScalarValue::String(s.clone()),
+        LogicalScalarValue::Int32(i) => PhysicalScalarValue::Int32(*i),
+        LogicalScalarValue::Int64(i) => PhysicalScalarValue::Int64(*i),
+        LogicalScalarValue::Float32(f) => PhysicalScalarValue::Float32(*f),
+        LogicalScalarValue::Float64(b) => PhysicalScalarValue::Float64(*b),
+        LogicalScalarValue::Null(data_type) => {
+            PhysicalScalarValue::Null(data_type.clone().unwrap_or(DataType::Utf8))
         }
     }
 }
@@ -140,7 +571,9 @@ mod tests {
             aggregate::Aggregate,
             expr_fn::{col, lit, max},
             plan::Plan,
+            projection::Projection,
             scan::Scan,
+            selection::Selection,
         },
         test_util::get_primitive_field_data_source,
     };
@@ -154,22 +587,73 @@ mod tests {
         let aggregate_exprs = vec![max(col1)];
         let agg = Aggregate::new(Plan::Scan(scan_plan), group_exprs, aggregate_exprs);
         let logical_plan = Plan::Aggregate(agg);
-        let physical_plan = QueryPlanner::create_physical_plan(&logical_plan);
+        let physical_plan = QueryPlanner::default().create_physical_plan(&logical_plan);
         assert!(physical_plan.is_ok());
         assert!(matches!(physical_plan.unwrap(), PhysicalPlan::Hash(_)));
     }
 
+    #[test]
+    fn test_fuse_projection_with_selection() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let scan_plan = Plan::Scan(Scan::new(path, csv_data_source, vec![]));
+        let selection = Plan::Selection(Selection::new(scan_plan, col("c1").gt(lit(1))));
+        let projection = Projection::new(selection.clone(), vec![col("c1")]);
+        let physical_plan = QueryPlanner::default()
+            .create_physical_plan(&Plan::Projection(projection))
+            .unwrap();
+        assert!(matches!(
+            physical_plan,
+            PhysicalPlan::SelectionProjection(_)
+        ));
+        let physical_plan = QueryPlanner::default()
+            .create_physical_plan(&selection)
+            .unwrap();
+        assert!(matches!(physical_plan, PhysicalPlan::Selection(_)));
+    }
+
     #[test]
     fn test_create_physical_expr() {
         let logical_expr = lit(1);
         let (path, csv_data_source) = get_primitive_field_data_source();
         let scan_plan = Scan::new(path, csv_data_source, vec![]);
         let physical_plan =
-            QueryPlanner::create_physical_expr(&logical_expr, &Plan::Scan(scan_plan));
+            QueryPlanner::default().create_physical_expr(&logical_expr, &Plan::Scan(scan_plan));
         assert!(physical_plan.is_ok());
         assert!(matches!(
             physical_plan.unwrap(),
             PhysicalExpr::Literal(PhysicalScalarValue::Int32(1))
         ));
     }
+
+    #[test]
+    fn test_create_physical_expr_with_coercion() {
+        let (path, csv_data_source) = get_primitive_field_data_source();
+        let scan_plan = Plan::Scan(Scan::new(path, csv_data_source, vec![]));
+
+        let logical_expr = col("c3").gt(lit(1));
+        let physical_expr = QueryPlanner::default()
+            .create_physical_expr(&logical_expr, &scan_plan)
+            .unwrap();
+        assert_eq!(physical_expr.to_string(), "#2 > CAST(1 AS Int64)");
+
+        let logical_expr = col("c1") + lit(1.5_f64);
+        assert_eq!(
+            logical_expr.to_field(&scan_plan).unwrap().data_type,
+            DataType::Float64
+        );
+        let physical_expr = QueryPlanner::default()
+            .create_physical_expr(&logical_expr, &scan_plan)
+            .unwrap();
+        assert_eq!(physical_expr.to_string(), "CAST(#0 AS Float64) + 1.5");
+
+        let logical_expr = col("c1").eq(lit("a".to_string()));
+        assert_eq!(
+            QueryPlanner::default()
+                .create_physical_expr(&logical_expr, &scan_plan)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Cannot apply eq to Int32 and Utf8"
+        );
+    }
 }
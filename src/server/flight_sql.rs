@@ -0,0 +1,856 @@
+// The service returns the errors of tonic, which are large.
+#![allow(clippy::result_large_err)]
+
+use std::{
+    collections::HashMap,
+    iter,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use super::panic_message;
+use crate::{
+    data_types::{record_batch::RecordBatch, schema::Schema},
+    execution::{
+        prepared_plan::{BoundPlan, PreparedPlan},
+        ExecutionContext,
+    },
+    logical_plan::{command::Command, data_frame::DataFrame, expr::ScalarValue, plan::Plan},
+};
+
+use anyhow::{anyhow, bail, Result};
+use arrow::{
+    array::{ArrayRef as ArrowArrayRef, BinaryArray, StringArray},
+    datatypes::{DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema, SchemaRef},
+    ipc::writer::IpcWriteOptions,
+    record_batch::RecordBatch as ArrowRecordBatch,
+};
+use arrow_flight::{
+    flight_service_server::{FlightService, FlightServiceServer},
+    sql::{
+        server::FlightSqlService, ActionClosePreparedStatementRequest,
+        ActionCreatePreparedStatementRequest, ActionCreatePreparedStatementResult,
+        CommandGetCatalogs, CommandGetCrossReference, CommandGetDbSchemas, CommandGetExportedKeys,
+        CommandGetImportedKeys, CommandGetPrimaryKeys, CommandGetSqlInfo, CommandGetTableTypes,
+        CommandGetTables, CommandPreparedStatementQuery, CommandPreparedStatementUpdate,
+        CommandStatementQuery, CommandStatementUpdate, ProstMessageExt, SqlInfo,
+        TicketStatementQuery,
+    },
+    utils::{flight_data_from_arrow_batch, flight_data_to_arrow_batch},
+    FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, IpcMessage, SchemaAsIpc, Ticket,
+};
+use futures::{stream, Stream, StreamExt};
+use prost::Message;
+use regex::Regex;
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{transport::Server, Response, Status, Streaming};
+
+type FlightDataStream = Pin<Box<dyn Stream<Item = Result<FlightData, Status>> + Send + 'static>>;
+
+/// A server speaking Arrow Flight SQL, so clients such as the Flight SQL JDBC and ADBC
+/// drivers can run SQL on the tables of the context and fetch the results as Arrow data.
+///
+/// Queries are planned when the client asks for their flight info, and executed on a
+/// blocking thread when it fetches the result, which is streamed a batch at a time.
+/// Prepared statements take one row of parameters. The tables and views of the context
+/// are listed without a catalog or schema, and have no keys. Clients are not
+/// authenticated, nor is the connection encrypted, so the server is meant to listen on
+/// a trusted network.
+pub struct FlightSqlServer {
+    ctx: Arc<ExecutionContext>,
+    statements: Mutex<HashMap<Vec<u8>, Arc<Mutex<PreparedStatement>>>>,
+    next_handle: AtomicU64,
+}
+
+/// A statement prepared by a client, until the client closes it.
+struct PreparedStatement {
+    df: DataFrame,
+    /// The plan of a query, or None for a statement that changes the tables or views.
+    plan: Option<PreparedPlan>,
+    /// The parameters bound by the client.
+    params: Vec<ScalarValue>,
+}
+
+impl FlightSqlServer {
+    pub fn new(ctx: Arc<ExecutionContext>) -> Self {
+        Self {
+            ctx,
+            statements: Mutex::new(HashMap::new()),
+            next_handle: AtomicU64::new(0),
+        }
+    }
+
+    /// Accept connections until the listener fails, serving them on the runtime of the
+    /// caller.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        Server::builder()
+            .add_service(FlightServiceServer::new(self))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await?;
+        Ok(())
+    }
+
+    fn statement(&self, handle: &[u8]) -> Result<Arc<Mutex<PreparedStatement>>, Status> {
+        let statements = self.statements.lock().unwrap();
+        statements.get(handle).cloned().ok_or_else(|| {
+            Status::not_found(format!(
+                "No prepared statement {}",
+                String::from_utf8_lossy(handle)
+            ))
+        })
+    }
+
+    /// Execute a statement that changes the tables or views of the context, returning the
+    /// number of rows inserted, or 0 for the other statements.
+    async fn execute_update(&self, df: DataFrame) -> Result<i64, Status> {
+        let Plan::Command(command) = df.logical_plan() else {
+            return Err(Status::invalid_argument(
+                "The statement returns rows, so it must be executed as a query",
+            ));
+        };
+        let ctx = self.ctx.clone();
+        run_blocking(move || match command {
+            Command::Insert { .. } => match df.collect_scalar(&ctx)? {
+                ScalarValue::Int64(rows) => Ok(rows),
+                value => bail!("Expected a count, got {}", value),
+            },
+            _ => df.collect(&ctx).map(|_| 0),
+        })
+        .await
+    }
+}
+
+#[tonic::async_trait]
+impl FlightSqlService for FlightSqlServer {
+    type FlightService = FlightSqlServer;
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let df = try_plan(|| self.ctx.sql(&query.query))?;
+        // The ticket is the query, planned again when the client fetches its result.
+        let ticket = TicketStatementQuery {
+            statement_handle: query.query.into_bytes(),
+        };
+        flight_info(&df.schema().into(), ticket, request)
+    }
+
+    async fn get_flight_info_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let statement = self.statement(&query.prepared_statement_handle)?;
+        let schema = statement.lock().unwrap().df.schema().into();
+        flight_info(&schema, query, request)
+    }
+
+    async fn get_flight_info_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(&catalogs_schema(), query, request)
+    }
+
+    async fn get_flight_info_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(&db_schemas_schema(), query, request)
+    }
+
+    async fn get_flight_info_tables(
+        &self,
+        query: CommandGetTables,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(&tables_schema(query.include_schema), query, request)
+    }
+
+    async fn get_flight_info_table_types(
+        &self,
+        query: CommandGetTableTypes,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(&table_types_schema(), query, request)
+    }
+
+    async fn get_flight_info_sql_info(
+        &self,
+        _query: CommandGetSqlInfo,
+        _request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("rq does not report SQL info"))
+    }
+
+    async fn get_flight_info_primary_keys(
+        &self,
+        query: CommandGetPrimaryKeys,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(&primary_keys_schema(), query, request)
+    }
+
+    async fn get_flight_info_exported_keys(
+        &self,
+        query: CommandGetExportedKeys,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(&foreign_keys_schema(), query, request)
+    }
+
+    async fn get_flight_info_imported_keys(
+        &self,
+        query: CommandGetImportedKeys,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(&foreign_keys_schema(), query, request)
+    }
+
+    async fn get_flight_info_cross_reference(
+        &self,
+        query: CommandGetCrossReference,
+        request: FlightDescriptor,
+    ) -> Result<Response<FlightInfo>, Status> {
+        flight_info(&foreign_keys_schema(), query, request)
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let sql = String::from_utf8(ticket.statement_handle)
+            .map_err(|_| Status::invalid_argument("The statement handle is not a query"))?;
+        let df = try_plan(|| self.ctx.sql(&sql))?;
+        let plan = try_plan(|| self.ctx.prepare(&df))?;
+        let plan = plan.bind(&[]).map_err(invalid_argument)?;
+        Ok(Response::new(execute(df.schema(), plan)))
+    }
+
+    async fn do_get_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let statement = self.statement(&query.prepared_statement_handle)?;
+        let statement = statement.lock().unwrap();
+        let plan = statement.plan.as_ref().ok_or_else(|| {
+            Status::invalid_argument(
+                "The statement returns no rows, so it must be executed as an update",
+            )
+        })?;
+        let plan = plan.bind(&statement.params).map_err(invalid_argument)?;
+        Ok(Response::new(execute(statement.df.schema(), plan)))
+    }
+
+    async fn do_get_catalogs(
+        &self,
+        _query: CommandGetCatalogs,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        Ok(Response::new(empty_result(catalogs_schema())))
+    }
+
+    async fn do_get_schemas(
+        &self,
+        _query: CommandGetDbSchemas,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        Ok(Response::new(empty_result(db_schemas_schema())))
+    }
+
+    async fn do_get_tables(
+        &self,
+        query: CommandGetTables,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let schema = Arc::new(tables_schema(query.include_schema));
+        let batch = self
+            .tables(&query, schema.clone())
+            .map_err(invalid_argument)?;
+        Ok(Response::new(flight_data(&schema, vec![batch])))
+    }
+
+    async fn do_get_table_types(
+        &self,
+        _query: CommandGetTableTypes,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        let schema = Arc::new(table_types_schema());
+        let table_types = Arc::new(StringArray::from(vec!["TABLE", "VIEW"]));
+        let batch =
+            ArrowRecordBatch::try_new(schema.clone(), vec![table_types]).map_err(internal)?;
+        Ok(Response::new(flight_data(&schema, vec![batch])))
+    }
+
+    async fn do_get_sql_info(
+        &self,
+        _query: CommandGetSqlInfo,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        Err(Status::unimplemented("rq does not report SQL info"))
+    }
+
+    async fn do_get_primary_keys(
+        &self,
+        _query: CommandGetPrimaryKeys,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        Ok(Response::new(empty_result(primary_keys_schema())))
+    }
+
+    async fn do_get_exported_keys(
+        &self,
+        _query: CommandGetExportedKeys,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        Ok(Response::new(empty_result(foreign_keys_schema())))
+    }
+
+    async fn do_get_imported_keys(
+        &self,
+        _query: CommandGetImportedKeys,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        Ok(Response::new(empty_result(foreign_keys_schema())))
+    }
+
+    async fn do_get_cross_reference(
+        &self,
+        _query: CommandGetCrossReference,
+    ) -> Result<Response<FlightDataStream>, Status> {
+        Ok(Response::new(empty_result(foreign_keys_schema())))
+    }
+
+    async fn do_put_statement_update(&self, ticket: CommandStatementUpdate) -> Result<i64, Status> {
+        let df = try_plan(|| self.ctx.sql(&ticket.query))?;
+        self.execute_update(df).await
+    }
+
+    /// Bind the parameters sent by the client to the statement. The message with the
+    /// schema of the parameters is read before this is called, so the batch is read with
+    /// the types of the placeholders of the statement.
+    async fn do_put_prepared_statement_query(
+        &self,
+        query: CommandPreparedStatementQuery,
+        mut request: Streaming<FlightData>,
+    ) -> Result<Response<<Self as FlightService>::DoPutStream>, Status> {
+        let statement = self.statement(&query.prepared_statement_handle)?;
+        let schema = match &statement.lock().unwrap().plan {
+            Some(plan) => Arc::new(params_schema(plan)),
+            None => return Err(Status::invalid_argument("Only queries take parameters")),
+        };
+        let mut rows = vec![];
+        while let Some(data) = request.message().await? {
+            if data.data_body.is_empty() {
+                continue;
+            }
+            let batch = flight_data_to_arrow_batch(&data, schema.clone(), &HashMap::new())
+                .map_err(invalid_argument)?;
+            let batch = RecordBatch::try_from(&batch).map_err(invalid_argument)?;
+            for row in 0..batch.row_count() {
+                let params = (0..batch.column_count())
+                    .map(|i| ScalarValue::try_from_array(batch.field(i).as_ref(), row))
+                    .collect::<Result<Vec<_>>>()
+                    .map_err(invalid_argument)?;
+                rows.push(params);
+            }
+        }
+        let params = match <[_; 1]>::try_from(rows) {
+            Ok([params]) => params,
+            Err(rows) => {
+                return Err(Status::invalid_argument(format!(
+                    "Expected one row of parameters, got {}",
+                    rows.len()
+                )))
+            }
+        };
+        let mut statement = statement.lock().unwrap();
+        if let Some(plan) = &statement.plan {
+            // The parameters are checked when they are bound, not when they are used.
+            plan.bind(&params).map_err(invalid_argument)?;
+        }
+        statement.params = params;
+        Ok(Response::new(Box::pin(stream::empty())))
+    }
+
+    async fn do_put_prepared_statement_update(
+        &self,
+        query: CommandPreparedStatementUpdate,
+        _request: Streaming<FlightData>,
+    ) -> Result<i64, Status> {
+        let statement = self.statement(&query.prepared_statement_handle)?;
+        let df = statement.lock().unwrap().df.clone();
+        self.execute_update(df).await
+    }
+
+    async fn do_action_create_prepared_statement(
+        &self,
+        query: ActionCreatePreparedStatementRequest,
+    ) -> Result<ActionCreatePreparedStatementResult, Status> {
+        let df = try_plan(|| self.ctx.sql(&query.query))?;
+        let (plan, dataset_schema, parameter_schema) = match df.logical_plan() {
+            Plan::Command(_) => (None, vec![], vec![]),
+            _ => {
+                let plan = try_plan(|| self.ctx.prepare(&df))?;
+                let dataset_schema = ipc_schema(&df.schema().into()).map_err(internal)?;
+                let parameter_schema = ipc_schema(&params_schema(&plan)).map_err(internal)?;
+                (Some(plan), dataset_schema, parameter_schema)
+            }
+        };
+        let handle = self
+            .next_handle
+            .fetch_add(1, Ordering::Relaxed)
+            .to_string()
+            .into_bytes();
+        let statement = PreparedStatement {
+            df,
+            plan,
+            params: vec![],
+        };
+        self.statements
+            .lock()
+            .unwrap()
+            .insert(handle.clone(), Arc::new(Mutex::new(statement)));
+        Ok(ActionCreatePreparedStatementResult {
+            prepared_statement_handle: handle,
+            dataset_schema,
+            parameter_schema,
+        })
+    }
+
+    async fn do_action_close_prepared_statement(&self, query: ActionClosePreparedStatementRequest) {
+        let mut statements = self.statements.lock().unwrap();
+        statements.remove(&query.prepared_statement_handle);
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+impl FlightSqlServer {
+    /// Return the tables and views of the context that the command selects.
+    fn tables(&self, query: &CommandGetTables, schema: SchemaRef) -> Result<ArrowRecordBatch> {
+        // The tables and views are in no catalog or schema, so a filter on them selects
+        // none of them, unless it matches an empty name.
+        let unnamed = |filter: &Option<String>| match filter {
+            Some(filter) => Ok::<_, anyhow::Error>(like(filter)?.is_match("")),
+            None => Ok(true),
+        };
+        let mut tables = vec![];
+        if unnamed(&query.catalog)? && unnamed(&query.db_schema_filter_pattern)? {
+            let names = self.ctx.table_names().into_iter().map(|n| (n, "TABLE"));
+            let views = self.ctx.view_names().into_iter().map(|n| (n, "VIEW"));
+            let pattern = query
+                .table_name_filter_pattern
+                .as_deref()
+                .map(like)
+                .transpose()?;
+            tables = names
+                .chain(views)
+                .filter(|(name, _)| pattern.as_ref().is_none_or(|p| p.is_match(name)))
+                .filter(|(_, table_type)| {
+                    query.table_types.is_empty()
+                        || query.table_types.iter().any(|t| t == table_type)
+                })
+                .collect::<Vec<_>>();
+        }
+        let null_names = Arc::new(StringArray::from(vec![None::<&str>; tables.len()]));
+        let mut columns: Vec<ArrowArrayRef> = vec![
+            null_names.clone(),
+            null_names,
+            Arc::new(StringArray::from_iter_values(tables.iter().map(|(n, _)| n))),
+            Arc::new(StringArray::from_iter_values(tables.iter().map(|(_, t)| t))),
+        ];
+        if query.include_schema {
+            let schemas = tables
+                .iter()
+                .map(|(name, _)| ipc_schema(&self.ctx.table(name)?.schema().into()))
+                .collect::<Result<Vec<_>>>()?;
+            let schemas = schemas.iter().map(Vec::as_slice).collect::<Vec<_>>();
+            columns.push(Arc::new(BinaryArray::from(schemas)));
+        }
+        Ok(ArrowRecordBatch::try_new(schema, columns)?)
+    }
+}
+
+/// Return the flight info of a result, with a single endpoint to fetch it from this server
+/// with the ticket.
+fn flight_info(
+    schema: &ArrowSchema,
+    ticket: impl ProstMessageExt,
+    request: FlightDescriptor,
+) -> Result<Response<FlightInfo>, Status> {
+    let endpoint = FlightEndpoint {
+        ticket: Some(Ticket {
+            ticket: ticket.as_any().encode_to_vec(),
+        }),
+        location: vec![],
+    };
+    let options = IpcWriteOptions::default();
+    let message = IpcMessage::try_from(SchemaAsIpc::new(schema, &options)).map_err(internal)?;
+    Ok(Response::new(FlightInfo::new(
+        message,
+        Some(request),
+        vec![endpoint],
+        -1,
+        -1,
+    )))
+}
+
+/// Execute a bound plan on a blocking thread, streaming the result batches, with the
+/// schema of the result first. An error executing the plan, or a panic, ends the stream.
+fn execute(schema: Schema, plan: BoundPlan) -> FlightDataStream {
+    // The plan runs one batch ahead of the client.
+    let (sender, receiver) = mpsc::channel(1);
+    tokio::task::spawn_blocking(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            plan.try_for_each(|batch| sender.blocking_send(Ok(batch)).is_ok())
+        }));
+        let error = match result {
+            Ok(Ok(())) => return,
+            Ok(Err(e)) => e,
+            Err(payload) => anyhow!("rq panicked: {}", panic_message(&payload)),
+        };
+        let _ = sender.blocking_send(Err(error));
+    });
+    let schema: ArrowSchema = schema.into();
+    let options = IpcWriteOptions::default();
+    let header = FlightData::from(SchemaAsIpc::new(&schema, &options));
+    let batches = ReceiverStream::new(receiver).flat_map(|batch: Result<RecordBatch>| {
+        let data = batch
+            .and_then(|batch| ArrowRecordBatch::try_from(&batch))
+            .map(|batch| batch_data(&batch).into_iter().map(Ok).collect::<Vec<_>>())
+            .unwrap_or_else(|e| vec![Err(internal(e))]);
+        stream::iter(data)
+    });
+    Box::pin(stream::once(async { Ok(header) }).chain(batches))
+}
+
+/// Return a stream of the schema and the batches.
+fn flight_data(schema: &ArrowSchema, batches: Vec<ArrowRecordBatch>) -> FlightDataStream {
+    let options = IpcWriteOptions::default();
+    let header = FlightData::from(SchemaAsIpc::new(schema, &options));
+    let data = iter::once(header).chain(batches.iter().flat_map(batch_data));
+    Box::pin(stream::iter(data.map(Ok).collect::<Vec<_>>()))
+}
+
+fn empty_result(schema: ArrowSchema) -> FlightDataStream {
+    flight_data(&schema, vec![])
+}
+
+/// Return the messages of a batch, its dictionaries first.
+fn batch_data(batch: &ArrowRecordBatch) -> Vec<FlightData> {
+    let (dictionaries, data) = flight_data_from_arrow_batch(batch, &IpcWriteOptions::default());
+    dictionaries.into_iter().chain(iter::once(data)).collect()
+}
+
+/// Return the schema of the parameters of a plan, a column named after each placeholder.
+fn params_schema(plan: &PreparedPlan) -> ArrowSchema {
+    let fields = (1..=plan.param_count())
+        .map(|i| {
+            // A placeholder that is not used in the plan takes any value.
+            let data_type = plan
+                .param_type(i)
+                .map_or(ArrowDataType::Null, |t| t.clone().into());
+            ArrowField::new(&format!("${}", i), data_type, true)
+        })
+        .collect();
+    ArrowSchema::new(fields)
+}
+
+/// Return the schema in the encapsulated IPC format of Flight SQL messages.
+fn ipc_schema(schema: &ArrowSchema) -> Result<Vec<u8>> {
+    let options = IpcWriteOptions::default();
+    let IpcMessage(bytes) = IpcMessage::try_from(SchemaAsIpc::new(schema, &options))?;
+    Ok(bytes)
+}
+
+/// Return a regex matching the names the SQL LIKE pattern of a Flight SQL filter matches.
+fn like(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Ok(Regex::new(&regex)?)
+}
+
+fn utf8(name: &str, nullable: bool) -> ArrowField {
+    ArrowField::new(name, ArrowDataType::Utf8, nullable)
+}
+
+fn catalogs_schema() -> ArrowSchema {
+    ArrowSchema::new(vec![utf8("catalog_name", false)])
+}
+
+fn db_schemas_schema() -> ArrowSchema {
+    ArrowSchema::new(vec![
+        utf8("catalog_name", true),
+        utf8("db_schema_name", false),
+    ])
+}
+
+fn tables_schema(include_schema: bool) -> ArrowSchema {
+    let mut fields = vec![
+        utf8("catalog_name", true),
+        utf8("db_schema_name", true),
+        utf8("table_name", false),
+        utf8("table_type", false),
+    ];
+    if include_schema {
+        fields.push(ArrowField::new(
+            "table_schema",
+            ArrowDataType::Binary,
+            false,
+        ));
+    }
+    ArrowSchema::new(fields)
+}
+
+fn table_types_schema() -> ArrowSchema {
+    ArrowSchema::new(vec![utf8("table_type", false)])
+}
+
+fn primary_keys_schema() -> ArrowSchema {
+    ArrowSchema::new(vec![
+        utf8("catalog_name", true),
+        utf8("db_schema_name", true),
+        utf8("table_name", false),
+        utf8("column_name", false),
+        utf8("key_name", true),
+        ArrowField::new("key_sequence", ArrowDataType::Int32, false),
+    ])
+}
+
+fn foreign_keys_schema() -> ArrowSchema {
+    ArrowSchema::new(vec![
+        utf8("pk_catalog_name", true),
+        utf8("pk_db_schema_name", true),
+        utf8("pk_table_name", false),
+        utf8("pk_column_name", false),
+        utf8("fk_catalog_name", true),
+        utf8("fk_db_schema_name", true),
+        utf8("fk_table_name", false),
+        utf8("fk_column_name", false),
+        ArrowField::new("key_sequence", ArrowDataType::Int32, false),
+        utf8("fk_key_name", true),
+        utf8("pk_key_name", true),
+        ArrowField::new("update_rule", ArrowDataType::UInt8, false),
+        ArrowField::new("delete_rule", ArrowDataType::UInt8, false),
+    ])
+}
+
+/// Run the function on a blocking thread, returning its error, or its panic as an error.
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T, Status> {
+    let result = tokio::task::spawn_blocking(move || {
+        panic::catch_unwind(AssertUnwindSafe(f))
+            .unwrap_or_else(|payload| Err(anyhow!("rq panicked: {}", panic_message(&payload))))
+    })
+    .await
+    .map_err(internal)?;
+    result.map_err(internal)
+}
+
+/// Plan a statement, returning its error, or its panic as an error.
+fn try_plan<T>(f: impl FnOnce() -> Result<T>) -> Result<T, Status> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result.map_err(invalid_argument),
+        Err(payload) => Err(Status::internal(format!(
+            "rq panicked: {}",
+            panic_message(&payload)
+        ))),
+    }
+}
+
+/// The error of a statement the client sent, which fails to be planned or bound.
+fn invalid_argument(e: impl Into<anyhow::Error>) -> Status {
+    Status::invalid_argument(format!("{:#}", e.into()))
+}
+
+/// The error of a statement that fails to be executed.
+fn internal(e: impl Into<anyhow::Error>) -> Status {
+    Status::internal(format!("{:#}", e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{column_array::DataType, schema::Field},
+        execution::table_function::TableFunction,
+    };
+
+    use arrow::array::{Array, Int64Array};
+    use arrow_flight::{
+        flight_service_client::FlightServiceClient,
+        sql::{DoPutUpdateResult, ProstAnyExt},
+        Action,
+    };
+    use tonic::{transport::Channel, Code};
+
+    struct PanickingFunction;
+
+    impl TableFunction for PanickingFunction {
+        fn create_source(&self, _args: &[ScalarValue], _batch_size: usize) -> Result<Source> {
+            panic!("the source is broken")
+        }
+    }
+
+    fn descriptor(command: impl ProstMessageExt) -> FlightDescriptor {
+        FlightDescriptor::new_cmd(command.as_any().encode_to_vec())
+    }
+
+    fn unpack<M: ProstMessageExt>(bytes: &[u8]) -> M {
+        let any = prost_types::Any::decode(bytes).unwrap();
+        any.unpack().unwrap().unwrap()
+    }
+
+    // Get the flight info of the command and fetch the result from its endpoint.
+    async fn fetch(
+        client: &mut FlightServiceClient<Channel>,
+        command: impl ProstMessageExt,
+    ) -> Result<Vec<ArrowRecordBatch>, Status> {
+        let info = client.get_flight_info(descriptor(command)).await?;
+        let ticket = info.into_inner().endpoint[0].ticket.clone().unwrap();
+        let mut stream = client.do_get(ticket).await?.into_inner();
+        let header = stream.message().await?.unwrap();
+        let schema = Arc::new(ArrowSchema::try_from(&header).unwrap());
+        let mut batches = vec![];
+        while let Some(data) = stream.message().await? {
+            let batch = flight_data_to_arrow_batch(&data, schema.clone(), &HashMap::new());
+            batches.push(batch.unwrap());
+        }
+        Ok(batches)
+    }
+
+    fn int64_values(batches: &[ArrowRecordBatch]) -> Vec<i64> {
+        batches
+            .iter()
+            .flat_map(|batch| {
+                let values = batch.column(0).as_any().downcast_ref::<Int64Array>();
+                values.unwrap().values().to_vec()
+            })
+            .collect()
+    }
+
+    async fn update(client: &mut FlightServiceClient<Channel>, query: &str) -> i64 {
+        let data = FlightData {
+            flight_descriptor: Some(descriptor(CommandStatementUpdate {
+                query: query.to_string(),
+            })),
+            ..Default::default()
+        };
+        let mut results = client
+            .do_put(stream::iter(vec![data]))
+            .await
+            .unwrap()
+            .into_inner();
+        let result = results.message().await.unwrap().unwrap();
+        unpack::<DoPutUpdateResult>(&result.app_metadata).record_count
+    }
+
+    #[test]
+    fn test_flight_sql_server() {
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
+        ctx.register_table("t", Source::Mem(MemoryDataSource::new(schema, vec![])));
+        ctx.register_table_function("broken", Arc::new(PanickingFunction));
+        let server = FlightSqlServer::new(Arc::new(ctx));
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            tokio::spawn(server.serve(listener));
+            let mut client = FlightServiceClient::connect(format!("http://{}", address))
+                .await
+                .unwrap();
+
+            let query = CommandStatementQuery {
+                query: "SELECT value FROM generate_series(1, 5) WHERE value % 2 = 1".to_string(),
+            };
+            let batches = fetch(&mut client, query).await.unwrap();
+            assert_eq!(int64_values(&batches), vec![1, 3, 5]);
+
+            let inserted = update(
+                &mut client,
+                "INSERT INTO t SELECT value FROM generate_series(7, 8)",
+            )
+            .await;
+            assert_eq!(inserted, 2);
+            let query = CommandStatementQuery {
+                query: "SELECT id FROM t".to_string(),
+            };
+            let batches = fetch(&mut client, query).await.unwrap();
+            assert_eq!(int64_values(&batches), vec![7, 8]);
+
+            // Prepare a query and bind 3 to its parameter.
+            let request = ActionCreatePreparedStatementRequest {
+                query: "SELECT value FROM generate_series(1, 5) WHERE value > $1".to_string(),
+            };
+            let action = Action {
+                r#type: "CreatePreparedStatement".to_string(),
+                body: request.as_any().encode_to_vec(),
+            };
+            let mut results = client.do_action(action).await.unwrap().into_inner();
+            let result = results.message().await.unwrap().unwrap();
+            let prepared: ActionCreatePreparedStatementResult = unpack(&result.body);
+            let statement = CommandPreparedStatementQuery {
+                prepared_statement_handle: prepared.prepared_statement_handle,
+            };
+            let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+                "$1",
+                ArrowDataType::Int64,
+                true,
+            )]));
+            let params = Arc::new(Int64Array::from(vec![3]));
+            let batch = ArrowRecordBatch::try_new(schema.clone(), vec![params]).unwrap();
+            let options = IpcWriteOptions::default();
+            let mut header = FlightData::from(SchemaAsIpc::new(&schema, &options));
+            header.flight_descriptor = Some(descriptor(statement.clone()));
+            let messages = iter::once(header)
+                .chain(batch_data(&batch))
+                .collect::<Vec<_>>();
+            client.do_put(stream::iter(messages)).await.unwrap();
+            let batches = fetch(&mut client, statement).await.unwrap();
+            assert_eq!(int64_values(&batches), vec![4, 5]);
+
+            let query = CommandGetTables {
+                table_name_filter_pattern: Some("t%".to_string()),
+                include_schema: true,
+                ..Default::default()
+            };
+            let batches = fetch(&mut client, query).await.unwrap();
+            let names = batches[0].column(2).as_any().downcast_ref::<StringArray>();
+            assert_eq!(names.unwrap().value(0), "t");
+            assert_eq!(batches[0].num_rows(), 1);
+            let schemas = batches[0].column(4).as_any().downcast_ref::<BinaryArray>();
+            let table_schema = ipc_schema(&ArrowSchema::new(vec![ArrowField::new(
+                "id",
+                ArrowDataType::Int64,
+                true,
+            )]));
+            assert_eq!(schemas.unwrap().value(0), table_schema.unwrap());
+
+            let query = CommandStatementQuery {
+                query: "SELECT nope FROM generate_series(1, 3)".to_string(),
+            };
+            let error = fetch(&mut client, query).await.unwrap_err();
+            assert_eq!(error.code(), Code::InvalidArgument);
+
+            let query = CommandStatementQuery {
+                query: "SELECT * FROM broken()".to_string(),
+            };
+            let error = fetch(&mut client, query).await.unwrap_err();
+            assert_eq!(error.code(), Code::Internal);
+            assert_eq!(error.message(), "rq panicked: the source is broken");
+        });
+    }
+}
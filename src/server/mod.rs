@@ -0,0 +1,15 @@
+use std::any::Any;
+
+#[cfg(feature = "flight-sql")]
+pub mod flight_sql;
+pub mod postgres;
+
+/// Return the message a panic was raised with.
+fn panic_message(payload: &Box<dyn Any + Send>) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("unknown panic", String::as_str),
+    }
+}
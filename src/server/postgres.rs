@@ -0,0 +1,455 @@
+use std::{
+    io::{BufReader, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream},
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    thread,
+};
+
+use super::panic_message;
+use crate::{
+    data_types::{column_array::DataType, schema::Schema},
+    execution::ExecutionContext,
+    logical_plan::{command::Command, data_frame::DataFrame, expr::ScalarValue, plan::Plan},
+};
+
+use anyhow::{bail, Result};
+use arrow::{
+    array::{Array, ArrayRef, BinaryArray, BooleanArray},
+    datatypes::DataType as ArrowDataType,
+    record_batch::RecordBatch as ArrowRecordBatch,
+    util::display::array_value_to_string,
+};
+
+const PROTOCOL_VERSION_3: i32 = 196608;
+const SSL_REQUEST: i32 = 80877103;
+const GSSENC_REQUEST: i32 = 80877104;
+const CANCEL_REQUEST: i32 = 80877102;
+// Larger messages are rejected rather than allocated.
+const MAX_MESSAGE_LENGTH: usize = 1 << 30;
+
+/// A server speaking a minimal PostgreSQL wire protocol, so clients such as psql can run
+/// SQL on the tables of the context.
+///
+/// Only the simple query flow is supported, with results sent as text. Clients are not
+/// authenticated, nor is the connection encrypted, so the server is meant to listen on
+/// a trusted network.
+pub struct PgServer {
+    ctx: Arc<ExecutionContext>,
+}
+
+impl PgServer {
+    pub fn new(ctx: Arc<ExecutionContext>) -> Self {
+        Self { ctx }
+    }
+
+    /// Accept connections until the listener fails, serving each on its own thread.
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let ctx = self.ctx.clone();
+            thread::spawn(move || {
+                // An error only closes the connection it happened on.
+                let _ = PgConnection::try_new(ctx, stream).and_then(|mut c| c.run());
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A connection of a client, running its queries one after the other.
+struct PgConnection {
+    ctx: Arc<ExecutionContext>,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl PgConnection {
+    fn try_new(ctx: Arc<ExecutionContext>, stream: TcpStream) -> Result<Self> {
+        Ok(Self {
+            ctx,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        })
+    }
+
+    fn run(&mut self) -> Result<()> {
+        if !self.startup()? {
+            return Ok(());
+        }
+        // After an error in the extended query flow, messages are skipped until a Sync.
+        let mut skip_until_sync = false;
+        loop {
+            let mut tag = [0; 1];
+            if self.reader.read(&mut tag)? == 0 {
+                return Ok(());
+            }
+            let body = self.read_body()?;
+            match tag[0] {
+                b'Q' => {
+                    self.simple_query(cstr(&body)?)?;
+                    self.ready_for_query()?;
+                }
+                b'X' => return Ok(()),
+                b'S' => {
+                    skip_until_sync = false;
+                    self.ready_for_query()?;
+                }
+                b'P' | b'B' | b'D' | b'E' | b'C' | b'H' | b'F' => {
+                    if !skip_until_sync {
+                        self.error("0A000", "The extended query protocol is not supported")?;
+                        skip_until_sync = true;
+                    }
+                }
+                tag => {
+                    let message = format!("Unsupported message {:?}", tag as char);
+                    self.error("08P01", &message)?;
+                    self.ready_for_query()?;
+                }
+            }
+            self.writer.flush()?;
+        }
+    }
+
+    /// Negotiate the start of the connection, returning whether queries follow.
+    /// Encryption is declined, and clients are trusted without a password.
+    fn startup(&mut self) -> Result<bool> {
+        loop {
+            let body = self.read_body()?;
+            if body.len() < 4 {
+                bail!("The startup message is too short");
+            }
+            match i32::from_be_bytes(body[..4].try_into()?) {
+                SSL_REQUEST | GSSENC_REQUEST => {
+                    self.writer.write_all(b"N")?;
+                    self.writer.flush()?;
+                }
+                CANCEL_REQUEST => return Ok(false),
+                PROTOCOL_VERSION_3 => break,
+                version => {
+                    self.error(
+                        "08P01",
+                        &format!("Unsupported protocol version {}", version),
+                    )?;
+                    self.writer.flush()?;
+                    return Ok(false);
+                }
+            }
+        }
+        // AuthenticationOk.
+        self.send(b'R', &0_i32.to_be_bytes())?;
+        for (name, value) in [
+            ("server_version", "14.0"),
+            ("server_encoding", "UTF8"),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            ("standard_conforming_strings", "on"),
+        ] {
+            let mut body = vec![];
+            put_cstr(&mut body, name);
+            put_cstr(&mut body, value);
+            self.send(b'S', &body)?;
+        }
+        // BackendKeyData, with a key that cancels nothing.
+        let mut body = vec![];
+        body.extend(std::process::id().to_be_bytes());
+        body.extend(0_i32.to_be_bytes());
+        self.send(b'K', &body)?;
+        self.ready_for_query()?;
+        self.writer.flush()?;
+        Ok(true)
+    }
+
+    /// Run the statement and send its rows, or the error planning or executing it.
+    /// A panic is sent as an error too, so it does not close the connection.
+    fn simple_query(&mut self, sql: &str) -> Result<()> {
+        if sql
+            .trim_matches(|c: char| c.is_whitespace() || c == ';')
+            .is_empty()
+        {
+            return self.send(b'I', &[]);
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| self.run_statement(sql))) {
+            Ok(result) => result,
+            Err(payload) => {
+                let message = format!("rq panicked: {}", panic_message(&payload));
+                self.error("XX000", &message)
+            }
+        }
+    }
+
+    fn run_statement(&mut self, sql: &str) -> Result<()> {
+        let df = match self.ctx.sql(sql) {
+            Ok(df) => df,
+            Err(e) => return self.error("42000", &format!("{:#}", e)),
+        };
+        // Inserts complete with the number of rows inserted, which are not sent as a row.
+        if let Plan::Command(Command::Insert { .. }) = df.logical_plan() {
+            return match df.collect_scalar(&self.ctx) {
+                Ok(ScalarValue::Int64(rows)) => {
+                    self.command_complete(&format!("INSERT 0 {}", rows))
+                }
+                Ok(value) => self.error("XX000", &format!("Expected a count, got {}", value)),
+                Err(e) => self.error("XX000", &format!("{:#}", e)),
+            };
+        }
+        match self.send_rows(&df) {
+            Ok(Some(rows)) => self.command_complete(&format!("SELECT {}", rows)),
+            // Statements without columns, such as CREATE, complete with their keyword.
+            Ok(None) => {
+                let keyword = sql.split_whitespace().next().unwrap_or_default();
+                self.command_complete(&keyword.to_uppercase())
+            }
+            Err(e) => self.error("XX000", &format!("{:#}", e)),
+        }
+    }
+
+    /// Execute the DataFrame and send its rows as text, returning their number, or None
+    /// if the DataFrame has no columns.
+    fn send_rows(&mut self, df: &DataFrame) -> Result<Option<usize>> {
+        let schema = df.schema();
+        let batches = df.collect(&self.ctx)?;
+        if schema.fields.is_empty() {
+            return Ok(None);
+        }
+        self.send(b'T', &row_description(&schema))?;
+        let mut rows = 0;
+        for batch in batches.iter().filter(|b| b.row_count() > 0) {
+            let batch = ArrowRecordBatch::try_from(batch)?;
+            for row in 0..batch.num_rows() {
+                let mut body = vec![];
+                body.extend((batch.num_columns() as i16).to_be_bytes());
+                for column in batch.columns() {
+                    match text_value(column, row)? {
+                        Some(value) => {
+                            body.extend((value.len() as i32).to_be_bytes());
+                            body.extend(value.as_bytes());
+                        }
+                        None => body.extend((-1_i32).to_be_bytes()),
+                    }
+                }
+                self.send(b'D', &body)?;
+            }
+            rows += batch.num_rows();
+        }
+        Ok(Some(rows))
+    }
+
+    fn command_complete(&mut self, tag: &str) -> Result<()> {
+        let mut body = vec![];
+        put_cstr(&mut body, tag);
+        self.send(b'C', &body)
+    }
+
+    fn ready_for_query(&mut self) -> Result<()> {
+        // Idle, as there are no transactions.
+        self.send(b'Z', b"I")
+    }
+
+    fn error(&mut self, code: &str, message: &str) -> Result<()> {
+        let mut body = vec![];
+        for (field, value) in [
+            (b'S', "ERROR"),
+            (b'V', "ERROR"),
+            (b'C', code),
+            (b'M', message),
+        ] {
+            body.push(field);
+            put_cstr(&mut body, value);
+        }
+        body.push(0);
+        self.send(b'E', &body)
+    }
+
+    fn send(&mut self, tag: u8, body: &[u8]) -> Result<()> {
+        self.writer.write_all(&[tag])?;
+        self.writer
+            .write_all(&(body.len() as i32 + 4).to_be_bytes())?;
+        self.writer.write_all(body)?;
+        Ok(())
+    }
+
+    /// Read the body of a message, after its length.
+    fn read_body(&mut self) -> Result<Vec<u8>> {
+        let mut length = [0; 4];
+        self.reader.read_exact(&mut length)?;
+        let length = i32::from_be_bytes(length) as usize;
+        if !(4..=MAX_MESSAGE_LENGTH).contains(&length) {
+            bail!("Invalid message length {}", length);
+        }
+        let mut body = vec![0; length - 4];
+        self.reader.read_exact(&mut body)?;
+        Ok(body)
+    }
+}
+
+fn put_cstr(body: &mut Vec<u8>, s: &str) {
+    // Interior nul bytes would end the string early.
+    body.extend(s.replace('\0', " ").as_bytes());
+    body.push(0);
+}
+
+/// Read the nul-terminated string at the start of the body.
+fn cstr(body: &[u8]) -> Result<&str> {
+    match body.iter().position(|&b| b == 0) {
+        Some(end) => Ok(std::str::from_utf8(&body[..end])?),
+        None => bail!("The string is not nul-terminated"),
+    }
+}
+
+fn row_description(schema: &Schema) -> Vec<u8> {
+    let mut body = vec![];
+    body.extend((schema.fields.len() as i16).to_be_bytes());
+    for field in &schema.fields {
+        let (type_oid, type_size) = pg_type(&field.data_type);
+        put_cstr(&mut body, &field.name);
+        // The columns are not from a table.
+        body.extend(0_i32.to_be_bytes());
+        body.extend(0_i16.to_be_bytes());
+        body.extend(type_oid.to_be_bytes());
+        body.extend(type_size.to_be_bytes());
+        body.extend((-1_i32).to_be_bytes());
+        // The text format.
+        body.extend(0_i16.to_be_bytes());
+    }
+    body
+}
+
+/// Return the OID and the size of the PostgreSQL type of the data type.
+/// Lists and structs are sent as text.
+fn pg_type(data_type: &DataType) -> (i32, i16) {
+    match data_type {
+        DataType::Boolean => (16, 1),
+        DataType::Int32 => (23, 4),
+        DataType::Int64 => (20, 8),
+        DataType::Float32 => (700, 4),
+        DataType::Float64 => (701, 8),
+        DataType::Binary => (17, -1),
+        DataType::Utf8 | DataType::List(_) | DataType::Struct(_) => (25, -1),
+    }
+}
+
+/// Format the value as PostgreSQL does in the text format, or return None if it is null.
+fn text_value(column: &ArrayRef, row: usize) -> Result<Option<String>> {
+    if column.is_null(row) {
+        return Ok(None);
+    }
+    Ok(Some(match column.data_type() {
+        ArrowDataType::Boolean => {
+            let column = column.as_any().downcast_ref::<BooleanArray>().unwrap();
+            if column.value(row) { "t" } else { "f" }.to_string()
+        }
+        ArrowDataType::Binary => {
+            let column = column.as_any().downcast_ref::<BinaryArray>().unwrap();
+            format!("\\x{}", hex::encode(column.value(row)))
+        }
+        _ => array_value_to_string(column, row)?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::schema::Field,
+        execution::table_function::TableFunction,
+    };
+
+    struct PanickingFunction;
+
+    impl TableFunction for PanickingFunction {
+        fn create_source(&self, _args: &[ScalarValue], _batch_size: usize) -> Result<Source> {
+            panic!("the source is broken")
+        }
+    }
+
+    // Read the messages of the server up to the next ReadyForQuery.
+    fn read_messages(reader: &mut impl Read) -> Vec<(u8, Vec<u8>)> {
+        let mut messages = vec![];
+        loop {
+            let mut header = [0; 5];
+            reader.read_exact(&mut header).unwrap();
+            let length = i32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+            let mut body = vec![0; length - 4];
+            reader.read_exact(&mut body).unwrap();
+            messages.push((header[0], body));
+            if header[0] == b'Z' {
+                return messages;
+            }
+        }
+    }
+
+    fn query(stream: &mut TcpStream, sql: &str) -> Vec<(u8, Vec<u8>)> {
+        let mut body = vec![];
+        put_cstr(&mut body, sql);
+        stream.write_all(b"Q").unwrap();
+        stream
+            .write_all(&(body.len() as i32 + 4).to_be_bytes())
+            .unwrap();
+        stream.write_all(&body).unwrap();
+        read_messages(stream)
+    }
+
+    #[test]
+    fn test_pg_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
+        ctx.register_table("t", Source::Mem(MemoryDataSource::new(schema, vec![])));
+        ctx.register_table_function("broken", Arc::new(PanickingFunction));
+        let server = PgServer::new(Arc::new(ctx));
+        thread::spawn(move || server.serve(listener));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut startup = vec![];
+        startup.extend(PROTOCOL_VERSION_3.to_be_bytes());
+        put_cstr(&mut startup, "user");
+        put_cstr(&mut startup, "rq");
+        startup.push(0);
+        stream
+            .write_all(&(startup.len() as i32 + 4).to_be_bytes())
+            .unwrap();
+        stream.write_all(&startup).unwrap();
+        let messages = read_messages(&mut stream);
+        assert_eq!(messages[0], (b'R', vec![0, 0, 0, 0]));
+        assert_eq!(messages.last().unwrap(), &(b'Z', b"I".to_vec()));
+
+        let messages = query(
+            &mut stream,
+            "SELECT value, value % 2 = 0 AS even FROM generate_series(1, 3)",
+        );
+        let tags = messages.iter().map(|(tag, _)| *tag).collect::<Vec<_>>();
+        assert_eq!(tags, b"TDDDCZ");
+        // The second row has the values 2 and t.
+        assert_eq!(messages[2].1, b"\0\x02\0\0\0\x012\0\0\0\x01t");
+        assert_eq!(cstr(&messages[4].1).unwrap(), "SELECT 3");
+
+        let messages = query(&mut stream, "SELECT nope FROM generate_series(1, 3)");
+        assert_eq!(messages[0].0, b'E');
+        let error = String::from_utf8_lossy(&messages[0].1);
+        assert!(error.contains("C42000\0MNo column named nope"));
+        assert_eq!(messages[1].0, b'Z');
+
+        let messages = query(
+            &mut stream,
+            "INSERT INTO t SELECT value FROM generate_series(1, 3)",
+        );
+        let tags = messages.iter().map(|(tag, _)| *tag).collect::<Vec<_>>();
+        assert_eq!(tags, b"CZ");
+        assert_eq!(cstr(&messages[0].1).unwrap(), "INSERT 0 3");
+
+        // The connection outlives a panic.
+        let messages = query(&mut stream, "SELECT * FROM broken()");
+        assert_eq!(messages[0].0, b'E');
+        let error = String::from_utf8_lossy(&messages[0].1);
+        assert!(error.contains("CXX000\0Mrq panicked: the source is broken"));
+        let messages = query(&mut stream, "SELECT COUNT(*) FROM t");
+        assert_eq!(messages[1].1, b"\0\x01\0\0\0\x013");
+
+        let messages = query(&mut stream, " ; ");
+        assert_eq!(messages[0], (b'I', vec![]));
+    }
+}
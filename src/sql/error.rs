@@ -0,0 +1,155 @@
+use std::fmt::Display;
+
+use anyhow::Error;
+use sqlparser::{
+    parser::ParserError,
+    tokenizer::{Location, Span},
+};
+
+/// An error parsing or planning a SQL statement, located at the span of the SQL text
+/// it was found at when it is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlError {
+    message: String,
+    span: Span,
+    /// The line of the SQL text the span starts at, with carets under the span.
+    snippet: Option<String>,
+}
+
+impl SqlError {
+    pub fn new(message: String, span: Span) -> Self {
+        Self {
+            message,
+            span,
+            snippet: None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Return the line and the column, from 1, the error was found at.
+    pub fn location(&self) -> Option<(u64, u64)> {
+        match self.span.start {
+            Location { line: 0, .. } => None,
+            Location { line, column } => Some((line, column)),
+        }
+    }
+
+    /// Return the line of the SQL text the error was found at, with carets under the span.
+    pub fn snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+
+    /// Locate the error at the span, unless it is already located or the span is unknown.
+    pub(crate) fn locate(error: Error, span: Span) -> Error {
+        match error.downcast_ref::<SqlError>() {
+            Some(e) if e.location().is_some() => error,
+            _ if span.start.line == 0 => error,
+            _ => SqlError::new(error.to_string(), span).into(),
+        }
+    }
+
+    /// Convert an error planning the SQL text, and show the line it was found at.
+    pub(crate) fn from_error(error: Error, sql: &str) -> Self {
+        let error = match error.downcast::<SqlError>() {
+            Ok(error) => error,
+            Err(error) => match error.downcast_ref::<ParserError>() {
+                Some(e) => parser_error(e),
+                None => SqlError::new(error.to_string(), Span::empty()),
+            },
+        };
+        error.with_sql(sql)
+    }
+
+    fn with_sql(mut self, sql: &str) -> Self {
+        let Some((line, column)) = self.location() else {
+            return self;
+        };
+        let Some(text) = sql.lines().nth(line as usize - 1) else {
+            return self;
+        };
+        // A span over several lines is underlined to the end of its first line.
+        let end = match self.span.end {
+            Location {
+                line: end_line,
+                column: end,
+            } if end_line == line && end > column => end,
+            _ if self.span.end.line > line => text.chars().count() as u64 + 1,
+            _ => column + 1,
+        };
+        self.snippet = Some(format!(
+            "{}\n{}{}",
+            text,
+            " ".repeat(column as usize - 1),
+            "^".repeat((end - column) as usize)
+        ));
+        self
+    }
+}
+
+/// The parser reports the location of an error at the end of its message.
+fn parser_error(error: &ParserError) -> SqlError {
+    let message = error.to_string();
+    let location = message
+        .rsplit_once(" at Line: ")
+        .and_then(|(message, location)| {
+            let (line, column) = location.split_once(", Column: ")?;
+            Some((message, line.parse().ok()?, column.parse().ok()?))
+        });
+    match location {
+        Some((message, line, column)) => {
+            let location = Location::new(line, column);
+            SqlError::new(message.to_string(), location.span_to(location))
+        }
+        None => SqlError::new(message, Span::empty()),
+    }
+}
+
+impl Display for SqlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some((line, column)) = self.location() {
+            write!(f, " at line {}, col {}", line, column)?;
+        }
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{}", snippet)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SqlError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet() {
+        let sql = "SELECT c1\nFROM t WHERE c9 > 1";
+        let span = Location::new(2, 14).span_to(Location::new(2, 16));
+        let error =
+            SqlError::from_error(SqlError::new("No column c9".to_string(), span).into(), sql);
+        assert_eq!(error.location(), Some((2, 14)));
+        assert_eq!(
+            error.to_string(),
+            "No column c9 at line 2, col 14\nFROM t WHERE c9 > 1\n             ^^"
+        );
+
+        let error = SqlError::from_error(anyhow::anyhow!("No table named t"), sql);
+        assert_eq!(error.location(), None);
+        assert_eq!(error.to_string(), "No table named t");
+
+        let parser_error = ParserError::ParserError(
+            "Expected: an expression, found: FROM at Line: 1, Column: 8".to_string(),
+        );
+        let error = SqlError::from_error(parser_error.into(), "SELECT FROM t");
+        assert_eq!(
+            error.message(),
+            "sql parser error: Expected: an expression, found: FROM"
+        );
+        assert_eq!(error.snippet(), Some("SELECT FROM t\n       ^"));
+    }
+}
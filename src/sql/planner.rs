@@ -0,0 +1,2100 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use super::error::SqlError;
+use crate::{
+    data_source::{memory_data_source::MemoryDataSource, Source},
+    data_types::{
+        arrow_field_array::ArrowFieldArray,
+        column_array::{ArrayRef, DataType},
+        record_batch::RecordBatch,
+        schema::{Field, Schema},
+    },
+    execution::ExecutionContext,
+    logical_plan::{
+        command::Command,
+        data_frame::DataFrame,
+        expr::{AggregateExpr, AggregateFunction, ColumnIndex, Expr, Operator, ScalarValue},
+        expr_fn::{self, binary_expr, col, lit, null, placeholder, typed_null, typed_placeholder},
+        plan::Plan,
+        scan::Scan,
+    },
+};
+
+use anyhow::{anyhow, bail, Result};
+use arrow::array::{Int64Array, StringArray};
+use sqlparser::{
+    ast::{
+        BinaryOperator, CastKind, ColumnOption, CreateTableOptions, DataType as SqlDataType,
+        Distinct, DuplicateTreatment, Expr as SqlExpr, Function, FunctionArg, FunctionArgExpr,
+        FunctionArguments, GroupByExpr, Ident, Insert, JoinConstraint, JoinOperator, ObjectName,
+        ObjectType, OrderByExpr, Query, Select, SelectItem, SetExpr, Spanned, Statement,
+        TableFactor, TableWithJoins, UnaryOperator, Value,
+    },
+    dialect::GenericDialect,
+    keywords::Keyword,
+    parser::Parser,
+    tokenizer::{Span, Token},
+};
+
+/// Plans SQL statements as DataFrames over the tables registered in a context.
+pub struct SqlPlanner<'a> {
+    ctx: &'a ExecutionContext,
+}
+
+/// The names the expressions of a SELECT are resolved with.
+struct Scope {
+    /// The relations of the FROM clause.
+    relations: Vec<Relation>,
+    /// The aggregates found in the expressions, aliased with their SQL text.
+    aggregates: Vec<Expr>,
+}
+
+/// A relation of the FROM clause.
+#[derive(Clone)]
+struct Relation {
+    /// The name the relation is referred to by, if any.
+    name: Option<String>,
+    /// The SQL names of the columns, with the names of the plan columns they refer to.
+    /// Joined relations qualify the columns whose name they share with the relation name.
+    columns: Vec<(String, String)>,
+}
+
+impl Relation {
+    fn new(name: Option<String>, df: &DataFrame) -> Self {
+        let columns = df
+            .schema()
+            .fields
+            .into_iter()
+            .map(|f| (f.name.clone(), f.name))
+            .collect();
+        Relation { name, columns }
+    }
+}
+
+impl Scope {
+    fn new(relations: Vec<Relation>) -> Self {
+        Scope {
+            relations,
+            aggregates: vec![],
+        }
+    }
+
+    fn column(&self, ident: &Ident) -> Result<Expr> {
+        let mut columns = self
+            .relations
+            .iter()
+            .flat_map(|r| &r.columns)
+            .filter(|(name, _)| name == &ident.value);
+        match (columns.next(), columns.next()) {
+            (Some((_, column)), None) => Ok(col(column)),
+            (Some(_), Some(_)) => bail!("Column reference {} is ambiguous", ident.value),
+            (None, _) => bail!("No column named {}", ident.value),
+        }
+    }
+
+    fn qualified_column(&self, relation: &Ident, ident: &Ident) -> Result<Expr> {
+        let relation = self
+            .relations
+            .iter()
+            .find(|r| r.name.as_ref() == Some(&relation.value))
+            .ok_or_else(|| anyhow!("No relation named {} in FROM", relation.value))?;
+        match relation
+            .columns
+            .iter()
+            .find(|(name, _)| name == &ident.value)
+        {
+            Some((_, column)) => Ok(col(column)),
+            None => bail!("No column named {}", ident.value),
+        }
+    }
+
+    /// Return the columns of all the relations, under their SQL names.
+    fn wildcard(&self) -> Vec<Expr> {
+        self.relations
+            .iter()
+            .flat_map(|r| &r.columns)
+            .map(|(name, column)| match name == column {
+                true => col(column),
+                false => col(column).alias(name.clone()),
+            })
+            .collect()
+    }
+
+    /// Return the SQL name of a plan column read from a relation.
+    fn sql_name(&self, column: &str) -> Option<&str> {
+        self.relations
+            .iter()
+            .flat_map(|r| &r.columns)
+            .find(|(_, c)| c == column)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+impl<'a> SqlPlanner<'a> {
+    pub fn new(ctx: &'a ExecutionContext) -> Self {
+        Self { ctx }
+    }
+
+    /// Parse the SQL text, which must hold a single statement, and plan it.
+    /// Planning does not change the context: DDL statements and inserts are planned as
+    /// commands, applied when they are executed.
+    /// The errors are [`SqlError`]s, which show where they were found in the SQL text.
+    pub fn plan(&self, sql: &str) -> Result<DataFrame> {
+        self.parse_and_plan(sql)
+            .map_err(|e| SqlError::from_error(e, sql).into())
+    }
+
+    fn parse_and_plan(&self, sql: &str) -> Result<DataFrame> {
+        let dialect = GenericDialect {};
+        let mut parser = Parser::new(&dialect).try_with_sql(sql)?;
+        // sqlparser only parses the file formats of Hive in CREATE EXTERNAL TABLE.
+        if parser.parse_keywords(&[Keyword::CREATE, Keyword::EXTERNAL, Keyword::TABLE]) {
+            return self.create_external_table(&mut parser);
+        }
+        let mut statements = parser.parse_statements()?;
+        if statements.len() != 1 {
+            bail!("Expected a single SQL statement, got {}", statements.len());
+        }
+        self.statement(statements.remove(0))
+    }
+
+    fn statement(&self, statement: Statement) -> Result<DataFrame> {
+        match statement {
+            Statement::Query(query) => self.query(*query),
+            Statement::Explain {
+                analyze,
+                verbose: false,
+                query_plan: false,
+                statement,
+                format: None,
+                options: None,
+                ..
+            } => self.explain(*statement, analyze),
+            Statement::CreateView {
+                or_replace,
+                materialized: false,
+                name,
+                columns,
+                query,
+                options: CreateTableOptions::None,
+                cluster_by,
+                comment: None,
+                with_no_schema_binding: false,
+                if_not_exists,
+                temporary: _,
+                to: None,
+            } if columns.is_empty() && cluster_by.is_empty() => {
+                self.create_view(name, *query, or_replace, if_not_exists)
+            }
+            Statement::Drop {
+                object_type: ObjectType::View,
+                if_exists,
+                names,
+                ..
+            } => self.drop_views(names, if_exists),
+            Statement::Insert(Insert {
+                or: None,
+                ignore: false,
+                table_name,
+                table_alias: None,
+                columns,
+                overwrite: false,
+                source: Some(source),
+                partitioned: None,
+                after_columns,
+                table: false,
+                on: None,
+                returning: None,
+                replace_into: false,
+                priority: None,
+                insert_alias: None,
+                ..
+            }) if after_columns.is_empty() => self.insert(table_name, columns, *source),
+            _ => bail!("Unsupported SQL statement: {}", statement),
+        }
+    }
+
+    /// Plan the registration of the query as a view. The tables it reads are scanned with
+    /// their data sources when the view is referred to.
+    fn create_view(
+        &self,
+        name: ObjectName,
+        query: Query,
+        or_replace: bool,
+        if_not_exists: bool,
+    ) -> Result<DataFrame> {
+        let df = self.query(query)?;
+        Ok(DataFrame::new(Plan::Command(Command::CreateView {
+            name: object_name(&name.0),
+            input: Arc::new(df.logical_plan()),
+            or_replace,
+            if_not_exists,
+        })))
+    }
+
+    /// Plan the insertion of the rows of the query into a memory table, which returns the
+    /// number of rows in a `count` column. The columns of the table missing from the column
+    /// list are null.
+    fn insert(&self, name: ObjectName, columns: Vec<Ident>, query: Query) -> Result<DataFrame> {
+        let table = object_name(&name.0);
+        let schema = self
+            .ctx
+            .table(&table)
+            .map_err(|e| SqlError::locate(e, name.span()))?
+            .schema();
+        if let Some(column) = columns
+            .iter()
+            .find(|c| !schema.fields.iter().any(|f| f.name == c.value))
+        {
+            let error = anyhow!("Table {} has no column named {}", table, column.value);
+            return Err(SqlError::locate(error, column.span));
+        }
+        let mut df = self.query(query)?;
+        if !columns.is_empty() {
+            if df.schema().fields.len() != columns.len() {
+                bail!(
+                    "{} columns were listed, but {} were inserted",
+                    columns.len(),
+                    df.schema().fields.len()
+                );
+            }
+            let exprs = schema
+                .fields
+                .iter()
+                .map(
+                    |field| match columns.iter().position(|c| c.value == field.name) {
+                        Some(index) => Expr::ColumnIndex(ColumnIndex { index }),
+                        None => typed_null(field.data_type.clone()),
+                    },
+                )
+                .collect();
+            df = df.project(exprs);
+        }
+        Ok(DataFrame::new(Plan::Command(Command::Insert {
+            table,
+            input: Arc::new(df.logical_plan()),
+        })))
+    }
+
+    fn drop_views(&self, names: Vec<ObjectName>, if_exists: bool) -> Result<DataFrame> {
+        Ok(DataFrame::new(Plan::Command(Command::DropViews {
+            names: names.iter().map(|name| object_name(&name.0)).collect(),
+            if_exists,
+        })))
+    }
+
+    /// Plan the registration of the file of a `CREATE EXTERNAL TABLE t (c1 INT, ...) STORED
+    /// AS CSV LOCATION 'path'` statement as a table. Files can only be read with the fs feature.
+    fn create_external_table(&self, parser: &mut Parser) -> Result<DataFrame> {
+        let if_not_exists = parser.parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]);
+        let name = object_name(&parser.parse_object_name(false)?.0);
+        let (columns, constraints) = parser.parse_columns()?;
+        if let Some(constraint) = constraints.first() {
+            bail!("Unsupported table constraint: {}", constraint);
+        }
+        parser.expect_keywords(&[Keyword::STORED, Keyword::AS])?;
+        let file_type = parser.parse_identifier(false)?;
+        parser.expect_keyword(Keyword::LOCATION)?;
+        let location = parser.parse_literal_string()?;
+        while parser.consume_token(&Token::SemiColon) {}
+        parser.expect_token(&Token::EOF)?;
+
+        if !file_type.value.eq_ignore_ascii_case("csv") {
+            let error = anyhow!("Unsupported file type {}, expected CSV", file_type);
+            return Err(SqlError::locate(error, file_type.span));
+        }
+        if columns.is_empty() {
+            bail!("External table {} has no columns", name);
+        }
+        let fields = columns
+            .iter()
+            .map(|column| {
+                let mut field =
+                    Field::new(column.name.value.clone(), sql_data_type(&column.data_type)?);
+                for option in &column.options {
+                    match option.option {
+                        ColumnOption::NotNull => field = field.with_nullable(false),
+                        ColumnOption::Null => field = field.with_nullable(true),
+                        _ => bail!("Unsupported column option: {}", option),
+                    }
+                }
+                Ok(field)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if cfg!(not(feature = "fs")) {
+            bail!("Cannot read the file {} without the fs feature", location);
+        }
+        Ok(DataFrame::new(Plan::Command(
+            Command::CreateExternalTable {
+                name,
+                location,
+                schema: Schema::new(fields),
+                if_not_exists,
+            },
+        )))
+    }
+
+    /// Return the lines of the optimized logical plan and the physical plan of the
+    /// query in a `plan` column. With ANALYZE, the query is executed to show the metrics
+    /// of every operator. Only queries are explained.
+    fn explain(&self, statement: Statement, analyze: bool) -> Result<DataFrame> {
+        let df = match statement {
+            Statement::Query(query) => self.query(*query)?,
+            statement => bail!("EXPLAIN only supports queries, got {}", statement),
+        };
+        let explain = self.ctx.explain(&df, analyze)?;
+        let schema = Schema::new(vec![
+            Field::new("plan".to_string(), DataType::Utf8).with_nullable(false)
+        ]);
+        let plan = StringArray::from(explain.lines().collect::<Vec<_>>());
+        let batch = RecordBatch::new(
+            schema.clone(),
+            vec![Arc::new(ArrowFieldArray::new(Box::new(plan))) as ArrayRef],
+        );
+        Ok(memory_table("explain", schema, vec![batch]))
+    }
+
+    fn query(&self, query: Query) -> Result<DataFrame> {
+        if query.with.is_some() || !query.limit_by.is_empty() || query.fetch.is_some() {
+            bail!("Unsupported SQL query: {}", query);
+        }
+        let order_by = match &query.order_by {
+            Some(order_by) if order_by.interpolate.is_none() => order_by.exprs.as_slice(),
+            Some(order_by) => bail!("Unsupported SQL clause: {}", order_by),
+            None => &[],
+        };
+        let df = match *query.body {
+            SetExpr::Select(select) => self.select(*select, order_by)?,
+            SetExpr::Query(query) if order_by.is_empty() => self.query(*query)?,
+            // The ORDER BY of a nested query can only refer to its output columns.
+            SetExpr::Query(query) => {
+                let df = self.query(*query)?;
+                let projection = df
+                    .schema()
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, _)| Expr::ColumnIndex(ColumnIndex { index }))
+                    .collect::<Vec<_>>();
+                let mut scope = Scope::new(vec![Relation::new(None, &df)]);
+                let names = df
+                    .schema()
+                    .fields
+                    .into_iter()
+                    .map(|f| Some(f.name))
+                    .collect::<Vec<_>>();
+                let order_by = self.order_by(order_by, &projection, &names, &mut scope)?;
+                if !scope.aggregates.is_empty() {
+                    bail!("Aggregate functions are not allowed in ORDER BY of a nested query");
+                }
+                sort(df, projection, vec![], order_by)
+            }
+            body => bail!("Unsupported SQL query: {}", body),
+        };
+        let skip = match &query.offset {
+            Some(offset) => row_count(&offset.value, "OFFSET")?,
+            None => 0,
+        };
+        let fetch = query
+            .limit
+            .as_ref()
+            .map(|limit| row_count(limit, "LIMIT"))
+            .transpose()?;
+        match (skip, fetch) {
+            (0, None) => Ok(df),
+            (skip, fetch) => Ok(df.limit(skip, fetch)),
+        }
+    }
+
+    /// Translate the ORDER BY of a query whose SELECT list is `projection`, with the
+    /// output column `names`.
+    fn order_by(
+        &self,
+        order_by: &[OrderByExpr],
+        projection: &[Expr],
+        names: &[Option<String>],
+        scope: &mut Scope,
+    ) -> Result<Vec<(SortTarget, bool, bool)>> {
+        order_by
+            .iter()
+            .map(|e| {
+                if e.with_fill.is_some() {
+                    bail!("Unsupported SQL clause: {}", e);
+                }
+                let target = self.sort_target(&e.expr, "ORDER BY", projection, names, scope)?;
+                // Nulls are greater than any value, as in PostgreSQL.
+                let asc = e.asc.unwrap_or(true);
+                Ok((target, asc, e.nulls_first.unwrap_or(!asc)))
+            })
+            .collect()
+    }
+
+    /// Translate an expression of the ORDER BY or DISTINCT ON of a query: a position in
+    /// the SELECT list, the name of one of its columns, or an expression over the input.
+    fn sort_target(
+        &self,
+        expr: &SqlExpr,
+        clause: &str,
+        projection: &[Expr],
+        names: &[Option<String>],
+        scope: &mut Scope,
+    ) -> Result<SortTarget> {
+        Ok(match expr {
+            SqlExpr::Value(Value::Number(n, _)) => match n.parse::<usize>() {
+                Ok(position) if (1..=projection.len()).contains(&position) => {
+                    SortTarget::Output(position - 1)
+                }
+                _ => bail!("{} position {} is not in the SELECT list", clause, n),
+            },
+            SqlExpr::Identifier(ident) if names.contains(&Some(ident.value.clone())) => {
+                let position = names.iter().position(|n| n.as_ref() == Some(&ident.value));
+                SortTarget::Output(position.unwrap())
+            }
+            expr => {
+                let sort_expr = self.expr(expr, scope)?;
+                match projection.iter().position(|p| unalias(p) == &sort_expr) {
+                    Some(position) => SortTarget::Output(position),
+                    None => SortTarget::Hidden(sort_expr.alias(expr.to_string())),
+                }
+            }
+        })
+    }
+
+    /// Plan a SELECT. Errors whose expression has no known span are located at the SELECT.
+    fn select(&self, select: Select, order_by: &[OrderByExpr]) -> Result<DataFrame> {
+        let span = select.span();
+        self.plan_select(select, order_by)
+            .map_err(|e| SqlError::locate(e, span))
+    }
+
+    fn plan_select(&self, select: Select, order_by: &[OrderByExpr]) -> Result<DataFrame> {
+        if matches!(select.distinct, Some(Distinct::Distinct))
+            || select.top.is_some()
+            || select.into.is_some()
+            || !select.lateral_views.is_empty()
+            || select.prewhere.is_some()
+            || select.qualify.is_some()
+            || !select.named_window.is_empty()
+        {
+            bail!("Unsupported SQL query: {}", select);
+        }
+        // A query without FROM reads a single row with no columns, as connection probes
+        // such as `SELECT 1` expect.
+        let (mut df, relations) = match select.from.is_empty() {
+            true => (single_row(), vec![]),
+            false => self.from(select.from)?,
+        };
+        let mut scope = Scope::new(relations);
+        if let Some(selection) = &select.selection {
+            let predicate = self.expr(selection, &mut scope)?;
+            if !scope.aggregates.is_empty() {
+                let error = anyhow!(
+                    "Aggregate functions are not allowed in WHERE: {}",
+                    selection
+                );
+                return Err(SqlError::locate(error, span(selection)));
+            }
+            df = df.filter(predicate);
+        }
+
+        let mut projection = vec![];
+        for item in &select.projection {
+            match item {
+                SelectItem::UnnamedExpr(expr) => match self.expr(expr, &mut scope)? {
+                    Expr::Column(column) => match scope.sql_name(&column.name) {
+                        Some(name) if name != column.name => {
+                            projection.push(col(&column.name).alias(name.to_string()))
+                        }
+                        _ => projection.push(Expr::Column(column)),
+                    },
+                    e => projection.push(e.alias(expr.to_string())),
+                },
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    projection.push(self.expr(expr, &mut scope)?.alias(alias.value.clone()))
+                }
+                SelectItem::Wildcard(_) if scope.wildcard().is_empty() => {
+                    bail!("SELECT * with no tables specified is not valid")
+                }
+                SelectItem::Wildcard(_) => projection.extend(scope.wildcard()),
+                SelectItem::QualifiedWildcard(..) => bail!("Unsupported SQL projection: {}", item),
+            }
+        }
+
+        let names = projection
+            .iter()
+            .map(|e| match e {
+                Expr::Alias(alias) => Some(alias.alias.clone()),
+                Expr::Column(column) => Some(column.name.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let order_by_exprs = order_by;
+        let order_by = self.order_by(order_by, &projection, &names, &mut scope)?;
+        let distinct_on = match &select.distinct {
+            Some(Distinct::On(exprs)) => exprs
+                .iter()
+                .map(|e| self.sort_target(e, "DISTINCT ON", &projection, &names, &mut scope))
+                .collect::<Result<Vec<_>>>()?,
+            _ => vec![],
+        };
+
+        let having = match &select.having {
+            Some(having) => Some(self.expr(having, &mut scope)?),
+            None => None,
+        };
+
+        let group_by = match &select.group_by {
+            GroupByExpr::Expressions(exprs, modifiers) if modifiers.is_empty() => exprs,
+            group_by => bail!("Unsupported SQL clause: {}", group_by),
+        };
+        if group_by.is_empty() && scope.aggregates.is_empty() && having.is_none() {
+            return Ok(sort(df, projection, distinct_on, order_by));
+        }
+        if let Some(item) = select.projection.iter().find(|item| {
+            matches!(
+                item,
+                SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)
+            )
+        }) {
+            let error = anyhow!("SELECT * is not allowed in an aggregate query");
+            return Err(SqlError::locate(error, item.span()));
+        }
+        let aggregate_names = scope
+            .aggregates
+            .iter()
+            .map(|a| match a {
+                Expr::Alias(alias) => alias.alias.clone(),
+                _ => unreachable!("aggregates are aliased"),
+            })
+            .collect::<Vec<_>>();
+        // A grouping key is a position into the SELECT list, a name, or an expression
+        // over the input. A name refers to an input column before a column of the SELECT
+        // list, and a grouping key that is not a column is named after its SQL text.
+        let input_names = df
+            .schema()
+            .fields
+            .into_iter()
+            .map(|f| Some(f.name))
+            .collect::<Vec<_>>();
+        let mut keys = vec![];
+        let mut group_exprs = vec![];
+        for expr in group_by {
+            let (key, name) = match expr {
+                SqlExpr::Value(Value::Number(n, _)) => match n.parse::<usize>() {
+                    Ok(position) if (1..=projection.len()).contains(&position) => {
+                        select_item_key(&projection[position - 1])
+                    }
+                    _ => bail!("GROUP BY position {} is not in the SELECT list", n),
+                },
+                SqlExpr::Identifier(ident)
+                    if !input_names.contains(&Some(ident.value.clone()))
+                        && names.contains(&Some(ident.value.clone())) =>
+                {
+                    let position = names.iter().position(|n| n.as_ref() == Some(&ident.value));
+                    select_item_key(&projection[position.unwrap()])
+                }
+                expr => {
+                    let key = self.expr(expr, &mut scope)?;
+                    let name = match &key {
+                        Expr::Column(column) => column.name.clone(),
+                        _ => expr.to_string(),
+                    };
+                    (key, name)
+                }
+            };
+            if scope.aggregates.len() != aggregate_names.len() || references(&key, &aggregate_names)
+            {
+                let error = anyhow!("Aggregate functions are not allowed in GROUP BY: {}", expr);
+                return Err(SqlError::locate(error, span(expr)));
+            }
+            group_exprs.push(match &key {
+                Expr::Column(column) if column.name == name => key.clone(),
+                _ => key.clone().alias(name.clone()),
+            });
+            keys.push((key, name));
+        }
+        // The errors are located at the SQL expression the replaced expression came from.
+        let replace = |e: &Expr, sql: &SqlExpr| {
+            replace_group_keys(e, &keys, &aggregate_names)
+                .map_err(|e| SqlError::locate(e, span(sql)))
+        };
+        let replace_target = |target, sql| match target {
+            SortTarget::Hidden(e) => Ok(SortTarget::Hidden(replace(&e, sql)?)),
+            target => Ok(target),
+        };
+        let projection = projection
+            .iter()
+            .zip(&select.projection)
+            .map(|(e, item)| match item {
+                SelectItem::UnnamedExpr(sql) | SelectItem::ExprWithAlias { expr: sql, .. } => {
+                    replace(e, sql)
+                }
+                _ => unreachable!("wildcards are not allowed in an aggregate query"),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let order_by = order_by
+            .into_iter()
+            .zip(order_by_exprs)
+            .map(|((target, asc, nulls_first), sql)| {
+                Ok((replace_target(target, &sql.expr)?, asc, nulls_first))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let distinct_on = match &select.distinct {
+            Some(Distinct::On(exprs)) => distinct_on
+                .into_iter()
+                .zip(exprs)
+                .map(|(target, sql)| replace_target(target, sql))
+                .collect::<Result<Vec<_>>>()?,
+            _ => distinct_on,
+        };
+        let mut df = df.aggregate(group_exprs, scope.aggregates);
+        if let (Some(having), Some(sql)) = (having, &select.having) {
+            df = df.filter(replace(&having, sql)?);
+        }
+        Ok(sort(df, projection, distinct_on, order_by))
+    }
+
+    /// Plan the FROM clause, returning the relations its columns are referred to by.
+    /// Relations separated by commas or joined without a condition are cross joined, and
+    /// the condition of an inner join filters the pairs of rows above the join, which the
+    /// optimizer turns into the keys of the join.
+    fn from(&self, from: Vec<TableWithJoins>) -> Result<(DataFrame, Vec<Relation>)> {
+        let mut factors = vec![];
+        for table in from {
+            factors.push((table.relation, None));
+            for join in table.joins {
+                let constraint = match join.join_operator {
+                    JoinOperator::Inner(JoinConstraint::On(expr)) => Some(expr),
+                    JoinOperator::Inner(JoinConstraint::None) | JoinOperator::CrossJoin => None,
+                    _ => bail!("Unsupported SQL join: {}", join.to_string().trim_start()),
+                };
+                factors.push((join.relation, constraint));
+            }
+        }
+        let mut inputs = vec![];
+        for (factor, constraint) in factors {
+            let (df, name) = self.relation(factor)?;
+            let relation = Relation::new(name, &df);
+            inputs.push((df, relation, constraint));
+        }
+
+        // The inputs of a join have distinct column names, so the columns that relations
+        // share a name with are qualified with the name of their relation.
+        if inputs.len() > 1 {
+            let mut counts = HashMap::<String, usize>::new();
+            for (_, relation, _) in &inputs {
+                for (name, _) in &relation.columns {
+                    *counts.entry(name.clone()).or_default() += 1;
+                }
+            }
+            let mut names = HashSet::new();
+            for (df, relation, _) in &mut inputs {
+                if !relation.columns.iter().any(|(name, _)| counts[name] > 1) {
+                    continue;
+                }
+                let Some(relation_name) = relation.name.clone() else {
+                    bail!("A subquery in FROM must have an alias to be joined");
+                };
+                if !names.insert(relation_name.clone()) {
+                    bail!(
+                        "Relation {} appears more than once in FROM, alias it to tell them apart",
+                        relation_name
+                    );
+                }
+                for (name, column) in &mut relation.columns {
+                    if counts[name.as_str()] > 1 {
+                        *column = format!("{}.{}", relation_name, name);
+                    }
+                }
+                *df = df.project(
+                    relation
+                        .columns
+                        .iter()
+                        .map(|(name, column)| match name == column {
+                            true => col(name),
+                            false => col(name).alias(column.clone()),
+                        })
+                        .collect(),
+                );
+            }
+        }
+
+        let mut inputs = inputs.into_iter();
+        let (mut df, relation, _) = inputs.next().expect("FROM has a relation");
+        let mut relations = vec![relation];
+        for (right, relation, constraint) in inputs {
+            relations.push(relation);
+            df = df.join(&right, vec![]);
+            if let Some(constraint) = constraint {
+                // The condition can only refer to the relations joined so far.
+                let mut scope = Scope::new(relations.clone());
+                let predicate = self.expr(&constraint, &mut scope)?;
+                if !scope.aggregates.is_empty() {
+                    let error = anyhow!(
+                        "Aggregate functions are not allowed in JOIN conditions: {}",
+                        constraint
+                    );
+                    return Err(SqlError::locate(error, span(&constraint)));
+                }
+                df = df.filter(predicate);
+            }
+        }
+        Ok((df, relations))
+    }
+
+    /// Plan a relation of the FROM clause, returning the name it is referred to by.
+    fn relation(&self, relation: TableFactor) -> Result<(DataFrame, Option<String>)> {
+        match relation {
+            TableFactor::Table {
+                name, alias, args, ..
+            } => {
+                let table_name = object_name(&name.0);
+                let df = match args {
+                    Some(args) => {
+                        let mut scope = Scope::new(vec![]);
+                        let args = args
+                            .args
+                            .iter()
+                            .map(|arg| self.function_arg(arg, &mut scope))
+                            .collect::<Result<Vec<_>>>()?;
+                        // Query templates are called like table functions, which they shadow.
+                        match self.ctx.has_query(&table_name) {
+                            true => self.ctx.query(&table_name, args),
+                            false => self.ctx.table_function(&table_name, args),
+                        }
+                    }
+                    None => self.ctx.table(&table_name),
+                }
+                .map_err(|e| SqlError::locate(e, name.span()))?;
+                let relation = match alias {
+                    Some(alias) if !alias.columns.is_empty() => {
+                        bail!("Column aliases are not supported: {}", alias)
+                    }
+                    Some(alias) => alias.name.value,
+                    None => table_name,
+                };
+                Ok((df, Some(relation)))
+            }
+            TableFactor::Derived {
+                lateral: false,
+                subquery,
+                alias,
+            } => {
+                let df = self.query(*subquery)?;
+                let relation = match alias {
+                    Some(alias) if !alias.columns.is_empty() => {
+                        bail!("Column aliases are not supported: {}", alias)
+                    }
+                    alias => alias.map(|alias| alias.name.value),
+                };
+                Ok((df, relation))
+            }
+            relation => bail!("Unsupported SQL relation: {}", relation),
+        }
+    }
+
+    /// Translate a SQL expression. Aggregates are added to the scope once, aliased with
+    /// their SQL text, and referred to as a column of that name. An error is located at
+    /// the innermost expression it was found in whose span is known.
+    fn expr(&self, expr: &SqlExpr, scope: &mut Scope) -> Result<Expr> {
+        self.translate_expr(expr, scope)
+            .map_err(|e| SqlError::locate(e, span(expr)))
+    }
+
+    fn translate_expr(&self, expr: &SqlExpr, scope: &mut Scope) -> Result<Expr> {
+        Ok(match expr {
+            SqlExpr::Identifier(ident) => scope.column(ident)?,
+            SqlExpr::CompoundIdentifier(idents) => match idents.as_slice() {
+                [relation, column] => scope.qualified_column(relation, column)?,
+                _ => bail!("Unsupported SQL identifier: {}", expr),
+            },
+            SqlExpr::Value(value) => literal(value, false)?,
+            SqlExpr::Nested(expr) => self.expr(expr, scope)?,
+            SqlExpr::UnaryOp { op, expr } => match (op, expr.as_ref()) {
+                (UnaryOperator::Minus, SqlExpr::Value(value)) => literal(value, true)?,
+                (UnaryOperator::Minus, expr) => lit(0) - self.expr(expr, scope)?,
+                (UnaryOperator::Plus, expr) => self.expr(expr, scope)?,
+                (UnaryOperator::Not, expr) => !self.expr(expr, scope)?,
+                _ => bail!("Unsupported SQL operator: {}", op),
+            },
+            SqlExpr::BinaryOp { left, op, right } => {
+                let op = match op {
+                    BinaryOperator::And => Operator::And,
+                    BinaryOperator::Or => Operator::Or,
+                    BinaryOperator::Eq => Operator::Eq,
+                    BinaryOperator::NotEq => Operator::Neq,
+                    BinaryOperator::Gt => Operator::Gt,
+                    BinaryOperator::GtEq => Operator::GtEq,
+                    BinaryOperator::Lt => Operator::Lt,
+                    BinaryOperator::LtEq => Operator::LtEq,
+                    BinaryOperator::Plus => Operator::Add,
+                    BinaryOperator::Minus => Operator::Subtract,
+                    BinaryOperator::Multiply => Operator::Multiply,
+                    BinaryOperator::Divide => Operator::Divide,
+                    BinaryOperator::Modulo => Operator::Modulus,
+                    _ => bail!("Unsupported SQL operator: {}", op),
+                };
+                binary_expr(self.expr(left, scope)?, op, self.expr(right, scope)?)
+            }
+            SqlExpr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let value = self.expr(expr, scope)?;
+                let between = value
+                    .clone()
+                    .gt_eq(self.expr(low, scope)?)
+                    .and(value.lt_eq(self.expr(high, scope)?));
+                if *negated {
+                    !between
+                } else {
+                    between
+                }
+            }
+            SqlExpr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let value = self.expr(expr, scope)?;
+                let mut in_list = None;
+                for item in list {
+                    let eq = value.clone().eq(self.expr(item, scope)?);
+                    in_list = Some(match in_list {
+                        Some(in_list) => expr_fn::or(in_list, eq),
+                        None => eq,
+                    });
+                }
+                let in_list = in_list.ok_or_else(|| anyhow!("IN expects a list of values"))?;
+                if *negated {
+                    !in_list
+                } else {
+                    in_list
+                }
+            }
+            SqlExpr::Cast {
+                kind,
+                expr,
+                data_type,
+                format: None,
+            } => {
+                let value = self.expr(expr, scope)?;
+                let data_type = sql_data_type(data_type)?;
+                // A cast gives its type to a placeholder, such as `CAST($1 AS INT)`.
+                if let Expr::Placeholder(p) = &value {
+                    if p.data_type.is_none() {
+                        return Ok(typed_placeholder(&p.id, data_type));
+                    }
+                }
+                match kind {
+                    CastKind::Cast | CastKind::DoubleColon => expr_fn::cast(value, data_type),
+                    CastKind::TryCast | CastKind::SafeCast => expr_fn::try_cast(value, data_type),
+                }
+            }
+            SqlExpr::Function(function) => self.function(expr, function, scope)?,
+            _ => bail!("Unsupported SQL expression: {}", expr),
+        })
+    }
+
+    fn function(&self, expr: &SqlExpr, function: &Function, scope: &mut Scope) -> Result<Expr> {
+        if function.filter.is_some()
+            || function.over.is_some()
+            || function.null_treatment.is_some()
+            || !function.within_group.is_empty()
+            || !matches!(function.parameters, FunctionArguments::None)
+        {
+            bail!("Unsupported SQL function call: {}", expr);
+        }
+        let name = object_name(&function.name.0).to_lowercase();
+        let (args, distinct) = match &function.args {
+            FunctionArguments::None => (vec![], false),
+            FunctionArguments::List(list) if list.clauses.is_empty() => (
+                list.args.iter().collect::<Vec<_>>(),
+                matches!(list.duplicate_treatment, Some(DuplicateTreatment::Distinct)),
+            ),
+            _ => bail!("Unsupported SQL function call: {}", expr),
+        };
+
+        // COUNT(*) counts the rows, which a literal has one value for.
+        let count_star = name == "count"
+            && matches!(
+                args.as_slice(),
+                [FunctionArg::Unnamed(FunctionArgExpr::Wildcard)]
+            );
+        // The arguments of an aggregate are evaluated over the input of the aggregate.
+        let mut arg_scope = Scope::new(scope.relations.clone());
+        let args = match count_star {
+            true => vec![lit(1)],
+            false => args
+                .into_iter()
+                .map(|arg| self.function_arg(arg, &mut arg_scope))
+                .collect::<Result<Vec<_>>>()?,
+        };
+        let aggregate = match (name.as_str(), distinct) {
+            ("count", true) => Some(AggregateFunction::CountDistinct),
+            ("count", false) => Some(AggregateFunction::Count),
+            ("sum", false) => Some(AggregateFunction::Sum),
+            ("min", false) => Some(AggregateFunction::Min),
+            ("max", false) => Some(AggregateFunction::Max),
+            ("avg", false) => Some(AggregateFunction::Avg),
+            ("median", false) => Some(AggregateFunction::Median),
+            ("percentile_cont", false) => Some(AggregateFunction::PercentileCont),
+            ("approx_percentile", false) => Some(AggregateFunction::ApproxPercentile),
+            ("first_value", false) => Some(AggregateFunction::FirstValue),
+            ("last_value", false) => Some(AggregateFunction::LastValue),
+            ("any_value", false) => Some(AggregateFunction::AnyValue),
+            ("bool_and", false) => Some(AggregateFunction::BoolAnd),
+            ("bool_or", false) => Some(AggregateFunction::BoolOr),
+            (_, true) => bail!("DISTINCT is not supported for {}", name),
+            (name, false) => self.ctx.udaf(name).ok().map(AggregateFunction::Udaf),
+        };
+        if let Some(fun) = aggregate {
+            if !arg_scope.aggregates.is_empty() {
+                bail!("Aggregate functions cannot be nested: {}", expr);
+            }
+            // The percentile follows the value, and the ordering expressions of the first
+            // and last values follow theirs.
+            match fun {
+                AggregateFunction::PercentileCont | AggregateFunction::ApproxPercentile
+                    if args.len() != 2 =>
+                {
+                    bail!("{} expects 2 arguments, got {}", name, args.len())
+                }
+                AggregateFunction::FirstValue | AggregateFunction::LastValue if args.is_empty() => {
+                    bail!("{} expects at least 1 argument", name)
+                }
+                _ => {}
+            }
+            let aggregate = Expr::AggregateFunction(AggregateExpr {
+                fun,
+                args,
+                is_distinct: distinct,
+            });
+            // An aggregate used several times is only computed once.
+            let name = match scope.aggregates.iter().find_map(|a| match a {
+                Expr::Alias(alias) if *alias.expr == aggregate => Some(alias.alias.clone()),
+                _ => None,
+            }) {
+                Some(name) => name,
+                None => {
+                    let name = expr.to_string();
+                    scope.aggregates.push(aggregate.alias(name.clone()));
+                    name
+                }
+            };
+            return Ok(col(&name));
+        }
+        scope.aggregates.extend(arg_scope.aggregates);
+
+        let unary = |f: fn(Expr) -> Expr, args| -> Result<Expr> {
+            let [arg] = fixed_args(&name, args)?;
+            Ok(f(arg))
+        };
+        let binary = |f: fn(Expr, Expr) -> Expr, args| -> Result<Expr> {
+            let [left, right] = fixed_args(&name, args)?;
+            Ok(f(left, right))
+        };
+        let ternary = |f: fn(Expr, Expr, Expr) -> Expr, args| -> Result<Expr> {
+            let [first, second, third] = fixed_args(&name, args)?;
+            Ok(f(first, second, third))
+        };
+        let with_string = |f: fn(Expr, &str) -> Expr, args| -> Result<Expr> {
+            match fixed_args(&name, args)? {
+                [arg, Expr::Literal(ScalarValue::String(s))] => Ok(f(arg, &s)),
+                [_, arg] => bail!("{} expects a string literal, got {}", name, arg),
+            }
+        };
+        let int_literal = |arg: Expr| -> Result<i64> {
+            match arg {
+                Expr::Literal(ScalarValue::Int32(v)) => Ok(v as i64),
+                Expr::Literal(ScalarValue::Int64(v)) => Ok(v),
+                arg => bail!("{} expects an integer literal, got {}", name, arg),
+            }
+        };
+        let with_group = |f: fn(Expr, &str, i32) -> Expr, args| -> Result<Expr> {
+            match fixed_args(&name, args)? {
+                [arg, Expr::Literal(ScalarValue::String(s)), group] => {
+                    let group = int_literal(group)?;
+                    let group = i32::try_from(group)
+                        .map_err(|_| anyhow!("Invalid group {} for {}", group, name))?;
+                    Ok(f(arg, &s, group))
+                }
+                [_, arg, _] => bail!("{} expects a string literal, got {}", name, arg),
+            }
+        };
+        Ok(match name.as_str() {
+            "abs" => unary(expr_fn::abs, args)?,
+            "sqrt" => unary(expr_fn::sqrt, args)?,
+            "exp" => unary(expr_fn::exp, args)?,
+            "ln" => unary(expr_fn::ln, args)?,
+            "log10" => unary(expr_fn::log10, args)?,
+            "round" => unary(expr_fn::round, args)?,
+            "floor" => unary(expr_fn::floor, args)?,
+            "ceil" => unary(expr_fn::ceil, args)?,
+            "soundex" => unary(expr_fn::soundex, args)?,
+            "md5" => unary(expr_fn::md5, args)?,
+            "sha256" => unary(expr_fn::sha256, args)?,
+            "url_host" => unary(expr_fn::url_host, args)?,
+            "url_path" => unary(expr_fn::url_path, args)?,
+            "pow" | "power" => binary(expr_fn::pow, args)?,
+            "levenshtein" => binary(expr_fn::levenshtein, args)?,
+            "jaro_winkler" => binary(expr_fn::jaro_winkler, args)?,
+            "inet_contains" => binary(expr_fn::inet_contains, args)?,
+            "st_distance" => binary(expr_fn::st_distance, args)?,
+            "nullif" => binary(expr_fn::nullif, args)?,
+            "st_within_bbox" => ternary(expr_fn::st_within_bbox, args)?,
+            "regexp_like" => with_string(expr_fn::regexp_like, args)?,
+            "regexp_match" => with_string(expr_fn::regexp_match, args)?,
+            "regexp_extract" => with_group(expr_fn::regexp_extract, args)?,
+            "regexp_extract_all" => with_group(expr_fn::regexp_extract_all, args)?,
+            "url_query_param" => with_string(expr_fn::url_query_param, args)?,
+            "encode" => with_string(expr_fn::encode, args)?,
+            "decode" => with_string(expr_fn::decode, args)?,
+            "date_bin" => {
+                let [interval, timestamp, origin] = fixed_args(&name, args)?;
+                expr_fn::date_bin(int_literal(interval)?, timestamp, int_literal(origin)?)
+            }
+            "coalesce" => expr_fn::coalesce(args),
+            _ => bail!("No function named {}", name),
+        })
+    }
+
+    fn function_arg(&self, arg: &FunctionArg, scope: &mut Scope) -> Result<Expr> {
+        match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => self.expr(expr, scope),
+            _ => bail!("Unsupported SQL function argument: {}", arg),
+        }
+    }
+}
+
+/// Return a DataFrame that scans the batches, such as the result of a statement that
+/// is run when it is planned.
+fn memory_table(name: &str, schema: Schema, batches: Vec<RecordBatch>) -> DataFrame {
+    let source = MemoryDataSource::new(schema, batches);
+    DataFrame::new(Plan::Scan(Scan::new(
+        name.to_string(),
+        Source::Mem(source),
+        vec![],
+    )))
+}
+
+/// Return a table of a single row, read by queries without FROM.
+fn single_row() -> DataFrame {
+    let schema = Schema::new(vec![
+        Field::new("dummy".to_string(), DataType::Int64).with_nullable(false)
+    ]);
+    let dummy = Int64Array::from(vec![0]);
+    let batch = RecordBatch::new(
+        schema.clone(),
+        vec![Arc::new(ArrowFieldArray::new(Box::new(dummy))) as ArrayRef],
+    );
+    memory_table("dual", schema, vec![batch])
+}
+
+/// What a query is ordered or deduplicated by: a column of the SELECT list, or an
+/// expression computed as a hidden column.
+enum SortTarget {
+    Output(usize),
+    Hidden(Expr),
+}
+
+/// Project the SELECT list over the input, keep the first row of each DISTINCT ON key
+/// in the ORDER BY order, and order the rows. The hidden columns are projected with it,
+/// and dropped after the sort.
+fn sort(
+    df: DataFrame,
+    projection: Vec<Expr>,
+    distinct_on: Vec<SortTarget>,
+    order_by: Vec<(SortTarget, bool, bool)>,
+) -> DataFrame {
+    if distinct_on.is_empty() && order_by.is_empty() {
+        return df.project(projection);
+    }
+    let visible = projection.len();
+    let mut exprs = projection;
+    let mut column = |target| {
+        let index = match target {
+            SortTarget::Output(index) => index,
+            SortTarget::Hidden(expr) => {
+                exprs.push(expr);
+                exprs.len() - 1
+            }
+        };
+        Expr::ColumnIndex(ColumnIndex { index })
+    };
+    let keys = distinct_on.into_iter().map(&mut column).collect::<Vec<_>>();
+    let order_by = order_by
+        .into_iter()
+        .map(|(target, asc, nulls_first)| column(target).sort(asc, nulls_first))
+        .collect::<Vec<_>>();
+    let hidden = exprs.len() > visible;
+    let mut df = df.project(exprs);
+    if !keys.is_empty() {
+        df = df.distinct_on(keys, order_by.clone());
+    }
+    if !order_by.is_empty() {
+        df = df.sort(order_by);
+    }
+    if !hidden {
+        return df;
+    }
+    df.project(
+        (0..visible)
+            .map(|index| Expr::ColumnIndex(ColumnIndex { index }))
+            .collect(),
+    )
+}
+
+/// Return the number of rows of a LIMIT or an OFFSET, which must be a literal.
+fn row_count(expr: &SqlExpr, clause: &str) -> Result<usize> {
+    match expr {
+        SqlExpr::Value(Value::Number(n, _)) => n
+            .parse::<usize>()
+            .map_err(|_| anyhow!("{} expects a number of rows, got {}", clause, n)),
+        _ => bail!("{} expects a number of rows, got {}", clause, expr),
+    }
+}
+
+fn unalias(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Alias(alias) => unalias(&alias.expr),
+        _ => expr,
+    }
+}
+
+/// Rewrite an expression of the SELECT list of an aggregate query over the output of
+/// the aggregate, where the grouping keys and the aggregates are columns.
+fn replace_group_keys(expr: &Expr, keys: &[(Expr, String)], aggregates: &[String]) -> Result<Expr> {
+    if let Some((_, name)) = keys.iter().find(|(key, _)| key == expr) {
+        return Ok(col(name));
+    }
+    match expr {
+        Expr::Column(column) if !aggregates.contains(&column.name) => bail!(
+            "Column {} must appear in GROUP BY or be used in an aggregate function",
+            column.name
+        ),
+        _ => expr.map_children(|e| replace_group_keys(e, keys, aggregates)),
+    }
+}
+
+/// Return the span of a SQL expression when sqlparser knows where the expression starts.
+/// The spans of literals are unknown, and those of casts, CASE and nested or unary
+/// expressions start at their first operand, which would put the error on the wrong token.
+fn span(expr: &SqlExpr) -> Span {
+    let first = match expr {
+        SqlExpr::Identifier(_) | SqlExpr::CompoundIdentifier(_) | SqlExpr::Function(_) => {
+            return expr.span()
+        }
+        SqlExpr::BinaryOp { left, .. }
+        | SqlExpr::IsDistinctFrom(left, _)
+        | SqlExpr::IsNotDistinctFrom(left, _)
+        | SqlExpr::AnyOp { left, .. }
+        | SqlExpr::AllOp { left, .. } => left,
+        SqlExpr::IsFalse(expr)
+        | SqlExpr::IsNotFalse(expr)
+        | SqlExpr::IsTrue(expr)
+        | SqlExpr::IsNotTrue(expr)
+        | SqlExpr::IsNull(expr)
+        | SqlExpr::IsNotNull(expr)
+        | SqlExpr::IsUnknown(expr)
+        | SqlExpr::IsNotUnknown(expr)
+        | SqlExpr::InList { expr, .. }
+        | SqlExpr::InSubquery { expr, .. }
+        | SqlExpr::Between { expr, .. }
+        | SqlExpr::Like { expr, .. }
+        | SqlExpr::ILike { expr, .. }
+        | SqlExpr::SimilarTo { expr, .. } => expr,
+        _ => return Span::empty(),
+    };
+    match span(first) == Span::empty() {
+        true => Span::empty(),
+        false => expr.span(),
+    }
+}
+
+/// Return the grouping key of an item of the SELECT list, and its name.
+fn select_item_key(item: &Expr) -> (Expr, String) {
+    match item {
+        Expr::Alias(alias) => (alias.expr.as_ref().clone(), alias.alias.clone()),
+        Expr::Column(column) => (item.clone(), column.name.clone()),
+        _ => unreachable!("the SELECT list is made of columns and aliases"),
+    }
+}
+
+/// Return whether the expression reads any of the columns.
+fn references(expr: &Expr, names: &[String]) -> bool {
+    match expr {
+        Expr::Column(column) => names.contains(&column.name),
+        _ => expr.children().into_iter().any(|e| references(e, names)),
+    }
+}
+
+/// Return the arguments of a function that expects `N` of them.
+fn fixed_args<const N: usize>(name: &str, args: Vec<Expr>) -> Result<[Expr; N]> {
+    let len = args.len();
+    <[Expr; N]>::try_from(args)
+        .map_err(|_| anyhow!("{} expects {} arguments, got {}", name, N, len))
+}
+
+/// Return the name of a table or function, which may be qualified.
+fn object_name(idents: &[Ident]) -> String {
+    idents
+        .iter()
+        .map(|ident| ident.value.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Translate a SQL value. Integers are Int64 literals, and other numbers Float64 ones.
+fn literal(value: &Value, negated: bool) -> Result<Expr> {
+    Ok(match value {
+        Value::Number(n, _) => {
+            let n = if negated {
+                format!("-{}", n)
+            } else {
+                n.clone()
+            };
+            match n.parse::<i64>() {
+                Ok(n) => lit(n),
+                Err(_) => lit(n
+                    .parse::<f64>()
+                    .map_err(|_| anyhow!("Invalid number {}", n))?),
+            }
+        }
+        _ if negated => bail!("Cannot negate {}", value),
+        Value::SingleQuotedString(s) => lit(s.clone()),
+        Value::Boolean(b) => lit(*b),
+        Value::Null => null(),
+        Value::Placeholder(id) => placeholder(id),
+        _ => bail!("Unsupported SQL value: {}", value),
+    })
+}
+
+/// Return the type of the columns and values of a SQL type.
+pub fn sql_data_type(data_type: &SqlDataType) -> Result<DataType> {
+    Ok(match data_type {
+        SqlDataType::Boolean | SqlDataType::Bool => DataType::Boolean,
+        SqlDataType::Int(_)
+        | SqlDataType::Integer(_)
+        | SqlDataType::Int4(_)
+        | SqlDataType::Int32 => DataType::Int32,
+        SqlDataType::BigInt(_) | SqlDataType::Int8(_) | SqlDataType::Int64 => DataType::Int64,
+        SqlDataType::Real | SqlDataType::Float4 | SqlDataType::Float32 => DataType::Float32,
+        SqlDataType::Double
+        | SqlDataType::DoublePrecision
+        | SqlDataType::Float8
+        | SqlDataType::Float64
+        | SqlDataType::Float(_) => DataType::Float64,
+        SqlDataType::Varchar(_)
+        | SqlDataType::Char(_)
+        | SqlDataType::Character(_)
+        | SqlDataType::Text
+        | SqlDataType::String(_) => DataType::Utf8,
+        _ => bail!("Unsupported SQL type {}", data_type),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "fs")]
+    use crate::test_util::rq_test_data;
+    use crate::{
+        data_source::{memory_data_source::MemoryDataSource, Source},
+        data_types::{
+            column_array::DataType,
+            record_batch::RecordBatch,
+            schema::{Field, Schema},
+        },
+        execution::{limits::QueryLimits, ExecutionContext},
+        logical_plan::{expr::ScalarValue, expr_fn::lit},
+        sql::error::SqlError,
+    };
+
+    use anyhow::Result;
+
+    #[test]
+    fn test_explain_has_no_side_effects() {
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![Field::new("id".to_string(), DataType::Int64)]);
+        ctx.register_table("t", Source::Mem(MemoryDataSource::new(schema, vec![])));
+        let error = |sql: &str| ctx.sql(sql).err().unwrap().to_string();
+
+        assert!(
+            error("EXPLAIN INSERT INTO t SELECT value FROM generate_series(1, 3)")
+                .starts_with("EXPLAIN only supports queries, got INSERT INTO t")
+        );
+        assert!(error("EXPLAIN CREATE VIEW v AS SELECT id FROM t")
+            .starts_with("EXPLAIN only supports queries, got CREATE VIEW v"));
+        let count = ctx.sql("SELECT COUNT(*) FROM t").unwrap();
+        assert_eq!(count.collect_scalar(&ctx).unwrap(), ScalarValue::Int64(0));
+        assert!(ctx.view_names().is_empty());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_distinct_on() {
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![
+            Field::new("name".to_string(), DataType::Utf8),
+            Field::new("ts".to_string(), DataType::Int64),
+        ]);
+        ctx.register_csv("events", rq_test_data("events.csv"), schema);
+        let batches = |sql: &str| ctx.sql(sql).unwrap().collect(&ctx).unwrap();
+
+        // The first row of each key is taken in the direction of the ORDER BY.
+        crate::assert_batches_eq!(
+            [
+                "+-------+------+",
+                "| name  | ts   |",
+                "+-------+------+",
+                "| alice | 5100 |",
+                "| bob   | 20   |",
+                "+-------+------+",
+            ],
+            &batches("SELECT DISTINCT ON (name) name, ts FROM events ORDER BY name, ts DESC"),
+        );
+        // The key can be an expression that is not selected, and the output is
+        // ordered by the ORDER BY rather than by the key.
+        crate::assert_batches_eq!(
+            ["+----+", "| ts |", "+----+", "| 0  |", "| 10 |", "+----+",],
+            &batches("SELECT DISTINCT ON (soundex(name)) ts FROM events ORDER BY ts"),
+        );
+        assert!(ctx.sql("SELECT DISTINCT ON (3) name FROM events").is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_sql() {
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int32),
+            Field::new("c3".to_string(), DataType::Int64),
+        ]);
+        ctx.register_csv("t", rq_test_data("primitive_field.csv"), schema);
+        let series = ctx.generate_series(lit(1), lit(10), lit(1)).unwrap();
+        let source = MemoryDataSource::new(series.schema(), series.collect(&ctx).unwrap());
+        ctx.register_table("series", Source::Mem(source));
+        let rows = |sql: &str| {
+            let df = ctx.sql(sql).unwrap();
+            let names = df
+                .schema()
+                .fields
+                .iter()
+                .map(|f| f.name.clone())
+                .collect::<Vec<_>>();
+            let mut rows = df
+                .collect(&ctx)
+                .unwrap()
+                .iter()
+                .flat_map(|b| {
+                    (0..b.row_count())
+                        .map(|i| {
+                            (0..b.column_count())
+                                .map(|c| ScalarValue::try_from_array(b.field(c).as_ref(), i))
+                                .collect::<Result<Vec<_>>>()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            rows.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            (names, rows)
+        };
+
+        assert_eq!(
+            rows("SELECT c1, MAX(c3) FROM t GROUP BY c1"),
+            (
+                vec!["c1".to_string(), "MAX(c3)".to_string()],
+                vec![
+                    vec![ScalarValue::Int32(1), ScalarValue::Int64(20)],
+                    vec![ScalarValue::Int32(2), ScalarValue::Int64(21)],
+                    vec![ScalarValue::Int32(3), ScalarValue::Int64(22)],
+                ]
+            )
+        );
+        assert_eq!(
+            rows("SELECT t.c2 * 2 AS double, -c1 FROM t WHERE c1 BETWEEN 2 AND 5"),
+            (
+                vec!["double".to_string(), "-c1".to_string()],
+                vec![
+                    vec![ScalarValue::Int64(20), ScalarValue::Int32(-2)],
+                    vec![ScalarValue::Int64(22), ScalarValue::Int32(-3)],
+                ]
+            )
+        );
+
+        // Grouping keys and aggregates may be used in expressions.
+        let (names, values) = rows(
+            "SELECT value % 2 = 0 AS even, SUM(value) + 1 AS total, COUNT(*) \
+             FROM series WHERE value IN (1, 2, 3, 4, 5) GROUP BY value % 2 = 0",
+        );
+        assert_eq!(names, vec!["even", "total", "COUNT(*)"]);
+        assert_eq!(
+            values,
+            vec![
+                vec![
+                    ScalarValue::Boolean(false),
+                    ScalarValue::Int64(10),
+                    ScalarValue::Int64(3)
+                ],
+                vec![
+                    ScalarValue::Boolean(true),
+                    ScalarValue::Int64(7),
+                    ScalarValue::Int64(2)
+                ],
+            ]
+        );
+        assert_eq!(
+            rows("SELECT * FROM generate_series(1, 3) AS s WHERE s.value > 1").1,
+            vec![vec![ScalarValue::Int64(2)], vec![ScalarValue::Int64(3)]]
+        );
+
+        let error = |sql: &str| {
+            let error = ctx.sql(sql).err().unwrap();
+            error
+                .downcast_ref::<SqlError>()
+                .unwrap()
+                .message()
+                .to_string()
+        };
+        assert_eq!(
+            error("SELECT c1, c2 FROM t GROUP BY c1"),
+            "Column c2 must appear in GROUP BY or be used in an aggregate function"
+        );
+        assert_eq!(
+            error("SELECT c1 FROM t WHERE MAX(c1) > 1"),
+            "Aggregate functions are not allowed in WHERE: MAX(c1) > 1"
+        );
+        assert_eq!(error("SELECT c1 FROM missing"), "No table named missing");
+        assert_eq!(error("SELECT foo(c1) FROM t"), "No function named foo");
+        assert!(error("SELECT FROM WHERE").starts_with("sql parser error"));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_sql_error() {
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![
+            Field::new("c1".to_string(), DataType::Int32),
+            Field::new("c2".to_string(), DataType::Int32),
+        ]);
+        ctx.register_csv("t", rq_test_data("primitive_field.csv"), schema);
+        let error = |sql: &str| {
+            let error = ctx.sql(sql).err().unwrap();
+            error.downcast_ref::<SqlError>().unwrap().clone()
+        };
+
+        let e = error("SELECT c1 FROM t WHERE c1 > 1 AND c9 < 2");
+        assert_eq!(e.location(), Some((1, 35)));
+        assert_eq!(
+            e.to_string(),
+            "No column named c9 at line 1, col 35
+SELECT c1 FROM t WHERE c1 > 1 AND c9 < 2
+                                  ^^"
+        );
+        // The innermost expression the error was found in is shown.
+        let e = error("SELECT c1,\n  nope(c1 + c2) AS n\nFROM t");
+        assert_eq!(e.message(), "No function named nope");
+        assert_eq!(e.snippet(), Some("  nope(c1 + c2) AS n\n  ^^^^^^^^^^^^"));
+        assert_eq!(
+            error("SELECT c1 FROM missing").snippet(),
+            Some("SELECT c1 FROM missing\n               ^^^^^^^")
+        );
+        let e = error("SELECT t.c1 FROM t LEFT JOIN t AS u ON t.c1 = u.c1");
+        assert_eq!(
+            e.message(),
+            "Unsupported SQL join: LEFT JOIN t AS u ON t.c1 = u.c1"
+        );
+        let e = error("SELECT c1 FROM t WHERE");
+        assert_eq!(e.location(), None);
+        let e = error("SELECT c1 FROM t WHERE c1 > 1 c2");
+        assert_eq!(
+            e.message(),
+            "sql parser error: Expected: end of statement, found: c2"
+        );
+        assert_eq!(e.location(), Some((1, 31)));
+        // Errors found after the expressions are translated are located at the SQL
+        // expression they came from.
+        let e = error("SELECT c1, c2 FROM t GROUP BY c1");
+        assert_eq!(
+            e.to_string(),
+            "Column c2 must appear in GROUP BY or be used in an aggregate function \
+             at line 1, col 12
+SELECT c1, c2 FROM t GROUP BY c1
+           ^^"
+        );
+        let e = error("SELECT c1, SUM(c2) FROM t GROUP BY c1 HAVING c2 > 1");
+        assert_eq!(e.location(), Some((1, 46)));
+        let e = error("SELECT SUM(c1) FROM t GROUP BY c2 + SUM(c2)");
+        assert_eq!(
+            e.snippet(),
+            Some("SELECT SUM(c1) FROM t GROUP BY c2 + SUM(c2)\n                               ^^^^^^^^^^^")
+        );
+        // The spans of literals and casts are unknown, so an error in an expression made of
+        // them is located at the nearest enclosing expression whose span is known, or at
+        // the SELECT, rather than at the first column in it.
+        let e = error("SELECT c1,\n  cast(c1 as varchar) || 'x'\nFROM t");
+        assert_eq!(e.message(), "Unsupported SQL operator: ||");
+        assert_eq!(e.location(), Some((1, 1)));
+        let e = error("SELECT c1 FROM t WHERE c2 > 1 AND CASE WHEN c1 > 1 THEN 1 END = 1");
+        assert!(e.message().starts_with("Unsupported SQL expression: CASE"));
+        assert_eq!(e.location(), Some((1, 24)));
+        let e = error("SELECT c1 FROM t WHERE c2 > 1 AND (c1 + 1) * nope(c2) > 1");
+        assert_eq!(e.location(), Some((1, 46)));
+        assert_eq!(error("SELECT 1 + c9 FROM t").location(), Some((1, 12)));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_sql_create_external_table() {
+        let ctx = ExecutionContext::new(2);
+        let path = rq_test_data("primitive_field.csv");
+        let create = format!(
+            "CREATE EXTERNAL TABLE t (c1 INT NOT NULL, c2 INT, c3 BIGINT) \
+             STORED AS CSV LOCATION '{}'",
+            path
+        );
+        let df = ctx.sql(&create).unwrap();
+        assert!(df.schema().fields.is_empty());
+        // The table is registered when the statement is executed.
+        assert!(ctx.table_names().is_empty());
+        assert!(df.collect(&ctx).unwrap().is_empty());
+        let schema = ctx.table("t").unwrap().schema();
+        assert_eq!(
+            schema
+                .fields
+                .iter()
+                .map(|f| (f.name.as_str(), f.data_type.clone(), f.nullable))
+                .collect::<Vec<_>>(),
+            vec![
+                ("c1", DataType::Int32, false),
+                ("c2", DataType::Int32, true),
+                ("c3", DataType::Int64, true),
+            ]
+        );
+        let df = ctx.sql("SELECT SUM(c3) FROM t WHERE c1 > 1").unwrap();
+        assert_eq!(df.collect_scalar(&ctx).unwrap(), ScalarValue::Int64(43));
+
+        let execute = |sql: &str| ctx.sql(sql).and_then(|df| df.collect(&ctx));
+        assert_eq!(
+            execute(&create).err().unwrap().to_string(),
+            "Table t already exists"
+        );
+        let create = create.replace("TABLE t", "TABLE IF NOT EXISTS t");
+        assert!(execute(&create).is_ok());
+        let create = create.replace("CSV", "PARQUET");
+        let error = ctx.sql(&create).err().unwrap();
+        let error = error.downcast_ref::<SqlError>().unwrap();
+        assert_eq!(
+            error.message(),
+            "Unsupported file type PARQUET, expected CSV"
+        );
+        assert_eq!(error.location(), Some((1, 86)));
+    }
+
+    #[test]
+    fn test_sql_view() {
+        let ctx = ExecutionContext::new(2);
+        let register_series = |stop: i64| {
+            let series = ctx.generate_series(lit(1), lit(stop), lit(1)).unwrap();
+            let source = MemoryDataSource::new(series.schema(), series.collect(&ctx).unwrap());
+            ctx.register_table("numbers", Source::Mem(source));
+        };
+        let sum = |sql: &str| ctx.sql(sql).unwrap().collect_scalar(&ctx).unwrap();
+        let execute = |sql: &str| ctx.sql(sql).and_then(|df| df.collect(&ctx));
+        let error = |sql: &str| {
+            let error = execute(sql).err().unwrap();
+            match error.downcast_ref::<SqlError>() {
+                Some(error) => error.message().to_string(),
+                None => error.to_string(),
+            }
+        };
+        register_series(5);
+
+        let df = ctx
+            .sql("CREATE VIEW big AS SELECT value * 10 AS v FROM numbers WHERE value > 3")
+            .unwrap();
+        // The view is registered when the statement is executed.
+        assert!(ctx.view_names().is_empty());
+        assert!(df.collect(&ctx).unwrap().is_empty());
+        assert_eq!(ctx.view_names(), vec!["big"]);
+        assert_eq!(sum("SELECT SUM(v) FROM big"), ScalarValue::Int64(90));
+        // The view reads the table registered again after it was created.
+        register_series(6);
+        assert_eq!(sum("SELECT SUM(v) FROM big"), ScalarValue::Int64(150));
+        execute("CREATE VIEW bigger AS SELECT v FROM big WHERE v > 50").unwrap();
+        assert_eq!(sum("SELECT SUM(v) FROM bigger"), ScalarValue::Int64(60));
+
+        assert_eq!(
+            error("CREATE VIEW big AS SELECT value AS v FROM numbers"),
+            "View big already exists"
+        );
+        assert_eq!(
+            error("CREATE VIEW numbers AS SELECT value FROM numbers"),
+            "Table numbers already exists"
+        );
+        execute("CREATE VIEW IF NOT EXISTS big AS SELECT value AS v FROM numbers").unwrap();
+        assert_eq!(sum("SELECT SUM(v) FROM big"), ScalarValue::Int64(150));
+        execute("CREATE OR REPLACE VIEW big AS SELECT value AS v FROM numbers").unwrap();
+        assert_eq!(sum("SELECT SUM(v) FROM big"), ScalarValue::Int64(21));
+
+        assert_eq!(error("DROP VIEW big, missing"), "No view named missing");
+        assert_eq!(ctx.view_names(), vec!["big", "bigger"]);
+        execute("DROP VIEW IF EXISTS big, missing").unwrap();
+        assert_eq!(error("SELECT v FROM big"), "No table named big");
+        // A view expands the views it was created from.
+        assert_eq!(sum("SELECT SUM(v) FROM bigger"), ScalarValue::Int64(60));
+    }
+
+    #[test]
+    fn test_sql_insert() {
+        let ctx = ExecutionContext::new(2);
+        let schema = Schema::new(vec![
+            Field::new("id".to_string(), DataType::Int64).with_nullable(false),
+            Field::new("name".to_string(), DataType::Utf8),
+        ]);
+        ctx.register_table("t", Source::Mem(MemoryDataSource::new(schema, vec![])));
+        let scalar = |sql: &str| ctx.sql(sql).unwrap().collect_scalar(&ctx).unwrap();
+        let execute = |sql: &str| ctx.sql(sql).and_then(|df| df.collect(&ctx));
+        let error = |sql: &str| {
+            let error = execute(sql).err().unwrap();
+            match error.downcast_ref::<SqlError>() {
+                Some(error) => error.message().to_string(),
+                None => error.to_string(),
+            }
+        };
+        execute("CREATE VIEW named AS SELECT id FROM t WHERE name = 'b'").unwrap();
+
+        // The rows are inserted when the statement is executed, every time it is.
+        let df = ctx
+            .sql("INSERT INTO t SELECT value, 'a' FROM generate_series(1, 3)")
+            .unwrap();
+        assert_eq!(df.schema().fields[0].name, "count");
+        assert_eq!(scalar("SELECT COUNT(*) FROM t"), ScalarValue::Int64(0));
+        assert_eq!(ctx.validate(&df).unwrap(), df.schema());
+        assert_eq!(scalar("SELECT COUNT(*) FROM t"), ScalarValue::Int64(0));
+        assert_eq!(df.collect_scalar(&ctx).unwrap(), ScalarValue::Int64(3));
+        assert_eq!(scalar("SELECT COUNT(*) FROM t"), ScalarValue::Int64(3));
+        // The columns missing from the column list are null.
+        assert_eq!(
+            scalar("INSERT INTO t (id) SELECT value FROM generate_series(7, 8)"),
+            ScalarValue::Int64(2)
+        );
+        // Int32 values are widened to the Int64 column.
+        assert_eq!(
+            scalar("INSERT INTO t (name, id) SELECT 'b', CAST(id * 10 AS INT) FROM t WHERE id < 3"),
+            ScalarValue::Int64(2)
+        );
+        assert_eq!(scalar("SELECT COUNT(*) FROM t"), ScalarValue::Int64(7));
+        assert_eq!(scalar("SELECT SUM(id) FROM named"), ScalarValue::Int64(30));
+
+        assert_eq!(
+            error("INSERT INTO t SELECT 'x', 'y' FROM generate_series(1, 1)"),
+            "Cannot insert Utf8 values into column id of type Int64"
+        );
+        assert_eq!(
+            error("INSERT INTO t (name) SELECT 'x' FROM generate_series(1, 1)"),
+            "Cannot insert nulls into column id"
+        );
+        assert_eq!(
+            error("INSERT INTO t SELECT 1 FROM generate_series(1, 1)"),
+            "Table t has 2 columns, but 1 were inserted"
+        );
+        assert_eq!(
+            error("INSERT INTO t (id, nope) SELECT 1, 2"),
+            "Table t has no column named nope"
+        );
+        assert_eq!(
+            error("INSERT INTO named SELECT 1 FROM generate_series(1, 1)"),
+            "Only memory tables can be inserted into, named is a view"
+        );
+        assert_eq!(scalar("SELECT COUNT(*) FROM t"), ScalarValue::Int64(7));
+    }
+
+    #[test]
+    fn test_sql_join() {
+        let ctx = ExecutionContext::new(2);
+        let customers = Schema::new(vec![
+            Field::new("id".to_string(), DataType::Int64),
+            Field::new("name".to_string(), DataType::Utf8),
+        ]);
+        let orders = Schema::new(vec![
+            Field::new("id".to_string(), DataType::Int64),
+            Field::new("customer_id".to_string(), DataType::Int64),
+            Field::new("amount".to_string(), DataType::Int64),
+        ]);
+        ctx.register_table(
+            "customers",
+            Source::Mem(MemoryDataSource::new(customers, vec![])),
+        );
+        ctx.register_table("orders", Source::Mem(MemoryDataSource::new(orders, vec![])));
+        let batches = |sql: &str| ctx.sql(sql).unwrap().collect(&ctx).unwrap();
+        let error = |sql: &str| {
+            let error = ctx.sql(sql).err().unwrap();
+            error
+                .downcast_ref::<SqlError>()
+                .unwrap()
+                .message()
+                .to_string()
+        };
+        for (id, name) in [(1, "ann"), (2, "bob"), (3, "cy")] {
+            batches(&format!("INSERT INTO customers SELECT {}, '{}'", id, name));
+        }
+        batches(
+            "INSERT INTO orders \
+             SELECT value, value % 3, value * 10 FROM generate_series(1, 6)",
+        );
+
+        // The columns both relations have are told apart by the name of their relation.
+        crate::assert_batches_eq!(
+            [
+                "+------+-------+",
+                "| name | total |",
+                "+------+-------+",
+                "| ann  | 50    |",
+                "| bob  | 70    |",
+                "+------+-------+",
+            ],
+            &batches(
+                "SELECT c.name, SUM(o.amount) AS total FROM orders AS o \
+                 JOIN customers AS c ON o.customer_id = c.id GROUP BY c.name ORDER BY c.name"
+            ),
+        );
+        // Relations separated by commas are joined on the equalities of the WHERE clause.
+        crate::assert_batches_eq!(
+            [
+                "+----+------+",
+                "| id | name |",
+                "+----+------+",
+                "| 4  | ann  |",
+                "| 5  | bob  |",
+                "+----+------+",
+            ],
+            &batches(
+                "SELECT o.id, name FROM orders o, customers c \
+                 WHERE customer_id = c.id AND amount > 30 ORDER BY o.id"
+            ),
+        );
+        crate::assert_batches_eq!(
+            ["+----+", "| n  |", "+----+", "| 18 |", "+----+"],
+            &batches("SELECT COUNT(*) AS n FROM customers CROSS JOIN orders"),
+        );
+        let explain = ctx
+            .sql("SELECT * FROM orders AS o JOIN customers AS c ON o.customer_id = c.id")
+            .unwrap()
+            .explain(false)
+            .unwrap();
+        // The hash table is built on the smaller relation.
+        assert!(explain
+            .contains("Join: on=#c.id = #customer_id\n\t\t\tProjection: #id as c.id,#name\n"));
+
+        assert_eq!(
+            error("SELECT id FROM orders JOIN customers ON customer_id = customers.id"),
+            "Column reference id is ambiguous"
+        );
+        assert_eq!(
+            error(
+                "SELECT 1 FROM orders AS o JOIN customers AS c ON o.id = d.id \
+                   JOIN customers AS d ON true"
+            ),
+            "No relation named d in FROM"
+        );
+        assert_eq!(
+            error("SELECT 1 FROM customers JOIN customers ON true"),
+            "Relation customers appears more than once in FROM, alias it to tell them apart"
+        );
+        assert_eq!(
+            error("SELECT 1 FROM orders JOIN customers USING (id)"),
+            "Unsupported SQL join: JOIN customers USING(id)"
+        );
+
+        // The joins of a query count against the limit of the context.
+        let ctx = ExecutionContext::new(2).with_limits(QueryLimits::new().with_max_joins(Some(1)));
+        let sql = "SELECT COUNT(*) FROM generate_series(1, 2) AS a, generate_series(1, 2) AS b";
+        assert_eq!(
+            ctx.sql(sql).unwrap().collect_scalar(&ctx).unwrap(),
+            ScalarValue::Int64(4)
+        );
+        let df = ctx
+            .sql(&format!("{}, generate_series(1, 2) AS c", sql))
+            .unwrap();
+        assert_eq!(
+            df.collect(&ctx).err().unwrap().to_string(),
+            "Query has 2 joins, which exceeds the limit of 1"
+        );
+    }
+
+    #[test]
+    fn test_sql_explain() {
+        let ctx = ExecutionContext::new(2);
+        let series = ctx.generate_series(lit(1), lit(3), lit(1)).unwrap();
+        let source = MemoryDataSource::new(series.schema(), series.collect(&ctx).unwrap());
+        ctx.register_table("series", Source::Mem(source));
+        let lines = |sql: &str| {
+            let df = ctx.sql(sql).unwrap();
+            assert_eq!(df.schema().fields[0].name, "plan");
+            df.collect(&ctx)
+                .unwrap()
+                .iter()
+                .flat_map(|b| {
+                    (0..b.row_count())
+                        .map(|i| {
+                            b.field(0)
+                                .get_value(i)
+                                .unwrap()
+                                .downcast_ref::<String>()
+                                .unwrap()
+                                .clone()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let query = "SELECT value FROM series WHERE value > 1";
+        assert_eq!(
+            lines(&format!("EXPLAIN {}", query)),
+            ctx.explain(&ctx.sql(query).unwrap(), false)
+                .unwrap()
+                .lines()
+                .collect::<Vec<_>>()
+        );
+        let analyzed = lines(&format!("EXPLAIN ANALYZE {}", query));
+        assert!(analyzed
+            .iter()
+            .any(|l| l.contains("metrics=[output_rows=2")));
+        assert!(ctx.sql("EXPLAIN SELECT c9 FROM series").is_err());
+    }
+
+    #[test]
+    fn test_sql_bind() {
+        let ctx = ExecutionContext::new(2);
+        let series = ctx.generate_series(lit(1), lit(5), lit(1)).unwrap();
+        let source = MemoryDataSource::new(series.schema(), series.collect(&ctx).unwrap());
+        ctx.register_table("series", Source::Mem(source));
+        let df = ctx
+            .sql("SELECT value, CAST($2 AS VARCHAR) AS tag FROM series WHERE value > $1")
+            .unwrap();
+        let prepared = ctx.prepare(&df).unwrap();
+        assert_eq!(prepared.param_type(1), Some(&DataType::Int64));
+        assert_eq!(prepared.param_type(2), Some(&DataType::Utf8));
+        assert_eq!(
+            prepared.bind(&[]).err().unwrap().to_string(),
+            "Expected 2 parameters, got 0"
+        );
+
+        let tag = ScalarValue::String("a'; DROP TABLE series; --".to_string());
+        let rows = |batches: Vec<RecordBatch>| {
+            batches
+                .iter()
+                .flat_map(|b| {
+                    (0..b.row_count()).map(|i| {
+                        (
+                            ScalarValue::try_from_array(b.field(0).as_ref(), i).unwrap(),
+                            ScalarValue::try_from_array(b.field(1).as_ref(), i).unwrap(),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let expected = vec![
+            (ScalarValue::Int64(4), tag.clone()),
+            (ScalarValue::Int64(5), tag.clone()),
+        ];
+        // The bound plans are independent, and run concurrently with their own values.
+        let bound = prepared
+            .bind(&[ScalarValue::Int64(3), tag.clone()])
+            .unwrap();
+        let other = prepared
+            .bind(&[ScalarValue::Int64(4), tag.clone()])
+            .unwrap();
+        let (batches, other_batches) = std::thread::scope(|s| {
+            let other = s.spawn(|| other.collect().unwrap());
+            (bound.collect().unwrap(), other.join().unwrap())
+        });
+        assert_eq!(rows(batches), expected);
+        assert_eq!(rows(other_batches), expected[1..]);
+        assert_eq!(rows(bound.collect().unwrap()), expected);
+        let batches = prepared.execute(&[ScalarValue::Int64(4), tag.clone()]);
+        assert_eq!(rows(batches.unwrap()), expected[1..]);
+        assert!(ctx.table("series").is_ok());
+    }
+
+    #[test]
+    fn test_sql_order_by_limit() {
+        let ctx = ExecutionContext::new(2);
+        let series = ctx.generate_series(lit(1), lit(6), lit(1)).unwrap();
+        let source = MemoryDataSource::new(series.schema(), series.collect(&ctx).unwrap());
+        ctx.register_table("series", Source::Mem(source));
+        // The rows are kept in the order of the query, and the column values are flattened.
+        let values = |sql: &str| {
+            ctx.sql(sql)
+                .unwrap()
+                .collect(&ctx)
+                .unwrap()
+                .iter()
+                .flat_map(|b| {
+                    (0..b.row_count())
+                        .flat_map(|i| {
+                            (0..b.column_count())
+                                .map(|c| ScalarValue::try_from_array(b.field(c).as_ref(), i))
+                                .collect::<Result<Vec<_>>>()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .map(|v| match v {
+                    ScalarValue::Int64(v) => Some(v),
+                    ScalarValue::Null(_) => None,
+                    v => panic!("Expected an Int64, got {}", v),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            values("SELECT value FROM series ORDER BY value DESC"),
+            vec![Some(6), Some(5), Some(4), Some(3), Some(2), Some(1)]
+        );
+        // Nulls are last in ascending order unless NULLS FIRST is given.
+        let nulls = "SELECT NULLIF(value % 3, 0) AS r FROM series ORDER BY r";
+        assert_eq!(
+            values(nulls),
+            vec![Some(1), Some(1), Some(2), Some(2), None, None]
+        );
+        assert_eq!(
+            values(&format!("{} DESC NULLS LAST", nulls)),
+            vec![Some(2), Some(2), Some(1), Some(1), None, None]
+        );
+        // An expression not in the SELECT list, then a position into it.
+        assert_eq!(
+            values("SELECT value FROM series ORDER BY value % 2, 1 DESC"),
+            vec![Some(6), Some(4), Some(2), Some(5), Some(3), Some(1)]
+        );
+        assert_eq!(
+            values(
+                "SELECT value % 3 AS r, SUM(value) FROM series GROUP BY value % 3 \
+                 ORDER BY COUNT(*) DESC, MAX(value)"
+            ),
+            vec![Some(1), Some(5), Some(2), Some(7), Some(0), Some(9)]
+        );
+        assert_eq!(
+            values("SELECT value FROM series ORDER BY value DESC LIMIT 2 OFFSET 1"),
+            vec![Some(5), Some(4)]
+        );
+        assert_eq!(
+            values("SELECT value FROM series ORDER BY value OFFSET 4"),
+            vec![Some(5), Some(6)]
+        );
+        assert_eq!(
+            values("SELECT * FROM (SELECT value FROM series LIMIT 3) ORDER BY value DESC"),
+            vec![Some(3), Some(2), Some(1)]
+        );
+        let error = ctx
+            .sql("SELECT value FROM series ORDER BY 2")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.downcast_ref::<SqlError>().unwrap().message(),
+            "ORDER BY position 2 is not in the SELECT list"
+        );
+    }
+
+    #[test]
+    fn test_sql_group_by_having() {
+        let ctx = ExecutionContext::new(2);
+        let series = ctx.generate_series(lit(1), lit(7), lit(1)).unwrap();
+        let source = MemoryDataSource::new(series.schema(), series.collect(&ctx).unwrap());
+        ctx.register_table("series", Source::Mem(source));
+        let values = |sql: &str| {
+            ctx.sql(sql)
+                .unwrap()
+                .collect(&ctx)
+                .unwrap()
+                .iter()
+                .flat_map(|b| {
+                    (0..b.row_count())
+                        .flat_map(|i| {
+                            (0..b.column_count())
+                                .map(|c| ScalarValue::try_from_array(b.field(c).as_ref(), i))
+                                .collect::<Result<Vec<_>>>()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let int64 = |values: &[i64]| {
+            values
+                .iter()
+                .map(|v| ScalarValue::Int64(*v))
+                .collect::<Vec<_>>()
+        };
+
+        // By an alias of the SELECT list, by position, and by expression.
+        let expected = int64(&[0, 2, 1, 3, 2, 2]);
+        assert_eq!(
+            values("SELECT value % 3 AS r, COUNT(*) FROM series GROUP BY r ORDER BY r"),
+            expected
+        );
+        assert_eq!(
+            values("SELECT value % 3 AS r, COUNT(*) FROM series GROUP BY 1 ORDER BY 1"),
+            expected
+        );
+        assert_eq!(
+            values("SELECT value % 3, COUNT(*) FROM series GROUP BY value % 3 ORDER BY 1"),
+            expected
+        );
+        assert_eq!(
+            values(
+                "SELECT value % 3 AS r, SUM(value) FROM series GROUP BY r \
+                 HAVING COUNT(*) = 2 AND MAX(value) > 5 ORDER BY r"
+            ),
+            int64(&[0, 9])
+        );
+        // A name refers to the input column before the column of the SELECT list.
+        assert_eq!(
+            values(
+                "SELECT value % 2 AS value, COUNT(*) FROM series GROUP BY value \
+                 HAVING value > 5 ORDER BY 1"
+            ),
+            int64(&[0, 1, 1, 1])
+        );
+        // Without GROUP BY, HAVING filters the single group.
+        assert_eq!(
+            values("SELECT SUM(value) FROM series HAVING COUNT(*) > 1"),
+            int64(&[28])
+        );
+        assert!(values("SELECT SUM(value) FROM series HAVING COUNT(*) > 7").is_empty());
+
+        let error = |sql: &str| {
+            let error = ctx.sql(sql).err().unwrap();
+            error
+                .downcast_ref::<SqlError>()
+                .unwrap()
+                .message()
+                .to_string()
+        };
+        assert_eq!(
+            error("SELECT COUNT(*) AS n FROM series GROUP BY n"),
+            "Aggregate functions are not allowed in GROUP BY: n"
+        );
+        assert_eq!(
+            error("SELECT value, COUNT(*) FROM series GROUP BY 2"),
+            "Aggregate functions are not allowed in GROUP BY: 2"
+        );
+        assert_eq!(
+            error("SELECT value FROM series GROUP BY 2"),
+            "GROUP BY position 2 is not in the SELECT list"
+        );
+        assert_eq!(
+            error("SELECT COUNT(*) FROM series HAVING value > 1"),
+            "Column value must appear in GROUP BY or be used in an aggregate function"
+        );
+    }
+}
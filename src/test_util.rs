@@ -1,4 +1,8 @@
-use std::path::PathBuf;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    path::PathBuf,
+};
 
 use crate::{
     data_source::{csv_data_source::CsvDataSource, Source},
@@ -18,10 +22,17 @@ pub fn get_primitive_field_data_source() -> (String, Source) {
         Field::new("c6".to_string(), DataType::Float64),
     ]);
     let path = rq_test_data("primitive_field.csv");
-    let csv_data_source = CsvDataSource::new(path.clone(), schema, 3);
+    let csv_data_source = csv_test_data_source("primitive_field.csv", schema, 3);
     (path, Source::Csv(csv_data_source))
 }
 
+/// Create a CSV data source over the test data file, read into memory so that the tests
+/// using it run without the `fs` feature.
+pub fn csv_test_data_source(file_name: &str, schema: Schema, batch_size: usize) -> CsvDataSource {
+    let bytes = std::fs::read(rq_test_data(file_name)).expect("failed to read the test data");
+    CsvDataSource::from_bytes(bytes, schema, batch_size)
+}
+
 /// Returns the rq test data directory.
 pub fn rq_test_data(file_name: &str) -> String {
     let mut data_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -31,3 +42,37 @@ pub fn rq_test_data(file_name: &str) -> String {
         .into_string()
         .expect("failed to get arrow data dir")
 }
+
+/// Allocator that counts the allocations made on each thread, so tests can check
+/// that a piece of code does not allocate while other tests run in parallel.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Run the function and return its result with the number of allocations it made.
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(|a| a.get());
+    let result = f();
+    (result, ALLOCATIONS.with(|a| a.get()) - before)
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use crate::data_types::{
+    arrow_field_array::ArrowFieldArray,
+    column_array::{ArrayRef, DataType},
+    record_batch::RecordBatch,
+    schema::{Field, Schema},
+};
+
+use anyhow::Result;
+use arrow::{array::StringArray, record_batch::RecordBatch as ArrowRecordBatch, util::pretty};
+
+/// Format the batches as an ASCII table, with a header of the field names of the first
+/// batch and empty cells for nulls. Values of extension types are shown in their text form.
+pub fn pretty_format_batches(batches: &[RecordBatch]) -> Result<String> {
+    let batches = batches
+        .iter()
+        .map(|batch| ArrowRecordBatch::try_from(&format_extensions(batch)?))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(pretty::pretty_format_batches(&batches)?.to_string())
+}
+
+/// Replace the columns of extension types with Utf8 columns of their formatted values.
+fn format_extensions(batch: &RecordBatch) -> Result<RecordBatch> {
+    let (fields, columns) = batch
+        .schema
+        .fields
+        .iter()
+        .zip(&batch.fields)
+        .map(|(field, column)| {
+            let Some(extension) = &field.extension else {
+                return Ok((field.clone(), column.clone()));
+            };
+            let values = (0..column.size())
+                .map(|i| {
+                    (!column.is_null(i))
+                        .then(|| Ok(extension.format(column.get_value(i)?.as_ref())))
+                        .transpose()
+                })
+                .collect::<Result<StringArray>>()?;
+            let field =
+                Field::new(field.name.clone(), DataType::Utf8).with_nullable(field.nullable);
+            let column: ArrayRef = Arc::new(ArrowFieldArray::new(Box::new(values)));
+            Ok((field, column))
+        })
+        .collect::<Result<(Vec<_>, Vec<_>)>>()?;
+    Ok(RecordBatch::new(Schema::new(fields), columns))
+}
+
+/// Compare the batches with the lines of the expected ASCII table, as formatted by
+/// [`pretty_format_batches`], and panic with both tables if they differ.
+///
+/// ```
+/// use rq::{assert_batches_eq, execution::ExecutionContext};
+///
+/// let ctx = ExecutionContext::new(1024);
+/// let df = ctx.sql("SELECT value, value * 2 AS double FROM generate_series(1, 2)")?;
+/// assert_batches_eq!(
+///     [
+///         "+-------+--------+",
+///         "| value | double |",
+///         "+-------+--------+",
+///         "| 1     | 2      |",
+///         "| 2     | 4      |",
+///         "+-------+--------+",
+///     ],
+///     &df.collect(&ctx)?
+/// );
+/// # anyhow::Ok(())
+/// ```
+#[macro_export]
+macro_rules! assert_batches_eq {
+    ($expected:expr, $batches:expr $(,)?) => {{
+        let expected = $expected
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<String>>();
+        let formatted = $crate::testing::pretty_format_batches($batches).unwrap();
+        let actual = formatted
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        assert!(
+            expected == actual,
+            "\n\nexpected:\n\n{}\n\nactual:\n\n{}\n\n",
+            expected.join("\n"),
+            actual.join("\n")
+        );
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{execution::ExecutionContext, logical_plan::expr_fn::lit};
+
+    #[test]
+    fn test_assert_batches_eq() {
+        let ctx = ExecutionContext::new(2);
+        let df = ctx
+            .sql(
+                "SELECT value, CAST(value AS DOUBLE) / 2 AS half, \
+                 NULLIF(CAST(value AS VARCHAR), '2') AS s FROM generate_series(1, 3)",
+            )
+            .unwrap();
+        // The rows of the batches are formatted as a single table.
+        let batches = df.collect(&ctx).unwrap();
+        assert!(batches.len() > 1);
+        assert_batches_eq!(
+            [
+                "+-------+------+---+",
+                "| value | half | s |",
+                "+-------+------+---+",
+                "| 1     | 0.5  | 1 |",
+                "| 2     | 1    |   |",
+                "| 3     | 1.5  | 3 |",
+                "+-------+------+---+",
+            ],
+            &batches
+        );
+
+        let batches = ctx
+            .generate_series(lit(1_i64), lit(0_i64), lit(1_i64))
+            .unwrap()
+            .collect(&ctx)
+            .unwrap();
+        assert_batches_eq!(["++", "++"], &batches);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected:")]
+    fn test_assert_batches_eq_mismatch() {
+        let ctx = ExecutionContext::new(2);
+        let batches = ctx
+            .generate_series(lit(1_i64), lit(1_i64), lit(1_i64))
+            .unwrap()
+            .collect(&ctx)
+            .unwrap();
+        assert_batches_eq!(
+            [
+                "+-------+",
+                "| value |",
+                "+-------+",
+                "| 2     |",
+                "+-------+"
+            ],
+            &batches
+        );
+    }
+}
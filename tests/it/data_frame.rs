@@ -0,0 +1,225 @@
+//! The tests read the CSV fixtures from the filesystem.
+#![cfg(feature = "fs")]
+
+use crate::golden::{assert_golden, test_data};
+
+use rq::{
+    data_types::{
+        column_array::DataType,
+        schema::{Field, Schema},
+    },
+    execution::ExecutionContext,
+    logical_plan::{
+        data_frame::DataFrame,
+        expr_fn::{
+            any_value, approx_percentile, bool_and, bool_or, cast, coalesce, col, count_distinct,
+            first_value, last_value, lit, max, median, min, percentile_cont, sum,
+        },
+    },
+};
+
+/// Register the CSV fixtures as the tables `primitive`, `events` and `readings`, which
+/// the DataFrame and SQL queries read.
+fn register_tables(ctx: &ExecutionContext) {
+    let primitive = Schema::new(vec![
+        Field::new("c1".to_string(), DataType::Int32),
+        Field::new("c2".to_string(), DataType::Int32),
+        Field::new("c3".to_string(), DataType::Int64),
+        Field::new("c4".to_string(), DataType::Int64),
+        Field::new("c5".to_string(), DataType::Float32),
+        Field::new("c6".to_string(), DataType::Float64),
+    ]);
+    ctx.register_csv("primitive", test_data("primitive_field.csv"), primitive);
+    let events = Schema::new(vec![
+        Field::new("user".to_string(), DataType::Utf8),
+        Field::new("ts".to_string(), DataType::Int64),
+    ]);
+    ctx.register_csv("events", test_data("events.csv"), events);
+    let readings = Schema::new(vec![
+        Field::new("ts".to_string(), DataType::Int64),
+        Field::new("value".to_string(), DataType::Int64),
+    ]);
+    ctx.register_csv("readings", test_data("readings.csv"), readings);
+}
+
+fn primitive(ctx: &ExecutionContext) -> DataFrame {
+    ctx.table("primitive").unwrap()
+}
+
+fn events(ctx: &ExecutionContext) -> DataFrame {
+    ctx.table("events").unwrap()
+}
+
+fn readings(ctx: &ExecutionContext) -> DataFrame {
+    ctx.table("readings").unwrap()
+}
+
+/// Plan a SQL query over the registered tables.
+fn sql(ctx: &ExecutionContext, sql: &str) -> DataFrame {
+    ctx.sql(sql)
+        .unwrap_or_else(|e| panic!("Cannot plan {}: {}", sql, e))
+}
+
+/// Execute the query and compare its results with the golden file of the same name.
+fn check(name: &str, ctx: &ExecutionContext, df: DataFrame) {
+    let batches = ctx
+        .execute(&df)
+        .unwrap_or_else(|e| panic!("{} failed: {}", name, e));
+    assert_golden(name, &df.schema(), &batches);
+}
+
+#[test]
+fn test_queries() {
+    for batch_size in [1, 2, 1024] {
+        let ctx = ExecutionContext::new(batch_size);
+        register_tables(&ctx);
+        let queries = vec![
+            (
+                "filter_project",
+                primitive(&ctx)
+                    .filter(col("c1").gt(lit(1_i32)))
+                    .project(vec![
+                        col("c1"),
+                        (col("c3") * lit(2_i64)).alias("double_c3".to_string()),
+                        col("c5"),
+                    ]),
+            ),
+            (
+                "cast_arithmetic",
+                primitive(&ctx).project(vec![
+                    (cast(col("c1"), DataType::Float64) / lit(2.0)).alias("half".to_string()),
+                    (col("c4") % lit(3_i64)).alias("mod3".to_string()),
+                ]),
+            ),
+            (
+                "aggregate_by_user",
+                events(&ctx).aggregate(
+                    vec![col("user")],
+                    vec![
+                        min(col("ts")),
+                        max(col("ts")),
+                        sum(col("ts")),
+                        count_distinct(col("ts")),
+                    ],
+                ),
+            ),
+            (
+                "min_max_strings",
+                events(&ctx).aggregate(
+                    vec![col("ts").gt(lit(50_i64)).alias("late".to_string())],
+                    vec![min(col("user")), max(col("user"))],
+                ),
+            ),
+            (
+                "bool_and_or",
+                events(&ctx).aggregate(
+                    vec![col("user")],
+                    vec![
+                        bool_and(col("ts").gt(lit(50_i64))),
+                        bool_or(col("ts").gt(lit(50_i64))),
+                        bool_and(col("ts").lt(lit(10_000_i64))).alias("all_recent".to_string()),
+                    ],
+                ),
+            ),
+            (
+                "percentiles",
+                events(&ctx).aggregate(
+                    vec![col("user")],
+                    vec![
+                        median(col("ts")),
+                        percentile_cont(col("ts"), 0.9),
+                        approx_percentile(col("ts"), 0.5),
+                    ],
+                ),
+            ),
+            (
+                "first_last",
+                events(&ctx).aggregate(
+                    vec![col("user")],
+                    vec![
+                        first_value(col("ts"), vec![]),
+                        last_value(col("ts"), vec![]),
+                        last_value(col("ts"), vec![lit(0_i64) - col("ts")])
+                            .alias("earliest".to_string()),
+                        any_value(col("ts")),
+                    ],
+                ),
+            ),
+            (
+                "sessionize",
+                events(&ctx)
+                    .sessionize(col("user"), col("ts"), 1000)
+                    .project(vec![col("user"), col("ts"), col("session_id")]),
+            ),
+            (
+                "distinct_on_latest",
+                events(&ctx)
+                    .distinct_on(vec![col("user")], vec![col("ts").sort(false, false)])
+                    .project(vec![col("user"), col("ts")]),
+            ),
+            (
+                "fill",
+                readings(&ctx)
+                    .ffill(vec!["value"], vec![col("ts")])
+                    .project(vec![col("ts"), col("value")]),
+            ),
+            (
+                "fill_null",
+                readings(&ctx)
+                    .fill_null("value", lit(0_i64))
+                    .unwrap()
+                    .project(vec![col("ts"), coalesce(vec![col("value"), lit(-1_i64)])]),
+            ),
+            (
+                "group_by_time",
+                readings(&ctx)
+                    .group_by_time(col("ts"), 2, vec![sum(col("value"))])
+                    .unwrap(),
+            ),
+            (
+                "generate_series",
+                ctx.generate_series(lit(1), lit(10), lit(3))
+                    .unwrap()
+                    .project(vec![
+                        col("value"),
+                        (col("value") * col("value")).alias("square".to_string()),
+                    ]),
+            ),
+            (
+                "sql_filter_order_limit",
+                sql(
+                    &ctx,
+                    "SELECT c1, c3 * 2 AS double_c3, c5 FROM primitive WHERE c1 > 1 \
+                     ORDER BY c3 DESC LIMIT 2",
+                ),
+            ),
+            (
+                "sql_group_by_having",
+                sql(
+                    &ctx,
+                    "SELECT \"user\", COUNT(*) AS n, SUM(ts) AS total, AVG(ts) AS mean \
+                     FROM events GROUP BY \"user\" HAVING COUNT(*) > 1 ORDER BY \"user\"",
+                ),
+            ),
+            (
+                "sql_distinct_on",
+                sql(
+                    &ctx,
+                    "SELECT DISTINCT ON (\"user\") \"user\", ts FROM events \
+                     ORDER BY \"user\", ts DESC",
+                ),
+            ),
+            (
+                "sql_nested_query",
+                sql(
+                    &ctx,
+                    "SELECT ts, COALESCE(value, -1) AS value \
+                     FROM (SELECT ts, value FROM readings WHERE ts > 1) AS r ORDER BY ts",
+                ),
+            ),
+        ];
+        for (name, df) in queries {
+            check(name, &ctx, df);
+        }
+    }
+}
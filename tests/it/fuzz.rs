@@ -0,0 +1,847 @@
+use std::{cmp::Ordering, fmt::Debug, panic::AssertUnwindSafe, sync::Arc};
+
+use rq::{
+    data_source::{memory_data_source::MemoryDataSource, Source},
+    data_types::{
+        column_array::DataType,
+        record_batch::RecordBatch,
+        schema::{Field, Schema},
+    },
+    execution::ExecutionContext,
+    logical_plan::{
+        data_frame::DataFrame,
+        expr::{Expr, ScalarValue},
+        expr_fn::{abs, avg, ceil, coalesce, col, count, floor, lit, max, min, round, sum},
+    },
+};
+
+use arrow::{
+    array::{
+        ArrayRef, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array, StringArray,
+    },
+    datatypes::{DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema},
+    record_batch::RecordBatch as ArrowRecordBatch,
+};
+
+/// A small xorshift generator, so that a failing seed can be replayed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Return a number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Return true with a probability of `percent` in 100.
+    pub fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int32,
+    Int,
+    Float32,
+    Float,
+    Str,
+    Bool,
+}
+
+impl Type {
+    const ALL: [Type; 6] = [
+        Type::Int32,
+        Type::Int,
+        Type::Float32,
+        Type::Float,
+        Type::Str,
+        Type::Bool,
+    ];
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Type::Int32 | Type::Int | Type::Float32 | Type::Float)
+    }
+
+    /// Return the type the engine converts operands of the two types to.
+    fn common(self, other: Type) -> Type {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Type::Int32, Type::Int) | (Type::Int, Type::Int32) => Type::Int,
+            (Type::Int32, Type::Float32) | (Type::Float32, Type::Int32) => Type::Float32,
+            _ => Type::Float,
+        }
+    }
+}
+
+impl From<Type> for ArrowDataType {
+    fn from(t: Type) -> Self {
+        match t {
+            Type::Int32 => ArrowDataType::Int32,
+            Type::Int => ArrowDataType::Int64,
+            Type::Float32 => ArrowDataType::Float32,
+            Type::Float => ArrowDataType::Float64,
+            Type::Str => ArrowDataType::Utf8,
+            Type::Bool => ArrowDataType::Boolean,
+        }
+    }
+}
+
+/// A value of the reference evaluator. Integers and floats of both widths are widened.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn random(rng: &mut Rng, t: Type) -> Value {
+        match t {
+            Type::Int32 | Type::Int => Value::Int(rng.below(11) as i64 - 5),
+            // Halves add up exactly whatever the order of the additions.
+            Type::Float32 | Type::Float => Value::Float((rng.below(11) as f64 - 5.0) / 2.0),
+            Type::Str => Value::Str(rng.pick(&["", "a", "ab", "b", "c"]).to_string()),
+            Type::Bool => Value::Bool(rng.chance(50)),
+        }
+    }
+
+    fn from_scalar(value: ScalarValue) -> Value {
+        match value {
+            ScalarValue::Null(_) => Value::Null,
+            ScalarValue::Int32(i) => Value::Int(i as i64),
+            ScalarValue::Int64(i) => Value::Int(i),
+            ScalarValue::Float32(f) => Value::Float(f as f64),
+            ScalarValue::Float64(f) => Value::Float(f),
+            ScalarValue::String(s) => Value::Str(s),
+            ScalarValue::Boolean(b) => Value::Bool(b),
+        }
+    }
+
+    fn to_expr(&self, t: Type) -> Expr {
+        match (self, t) {
+            (Value::Int(i), Type::Int32) => lit(*i as i32),
+            (Value::Int(i), _) => lit(*i),
+            (Value::Float(f), Type::Float32) => lit(*f as f32),
+            (Value::Float(f), _) => lit(*f),
+            (Value::Str(s), _) => lit(s.clone()),
+            (Value::Bool(b), _) => lit(*b),
+            (Value::Null, _) => unreachable!("the generated literals are not null"),
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Compare two non-null values, converting integers to floats as the engine does.
+    fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+                self.as_f64().unwrap().total_cmp(&other.as_f64().unwrap())
+            }
+            _ => self.partial_cmp(other).unwrap(),
+        }
+    }
+
+    /// Return true if the values are equal, or are floats that differ by rounding, as the
+    /// averages of the engine are not added up in the order of the reference.
+    fn approx_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Float(a), Value::Float(b)) => (a - b).abs() <= 1e-9 * a.abs().max(1.0),
+            _ => self == other,
+        }
+    }
+
+    /// Order the rows of a result, with nulls first.
+    fn total_cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            _ => self.partial_cmp(other).unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CmpOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RefFunction {
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+}
+
+impl RefFunction {
+    fn call(&self, expr: Expr) -> Expr {
+        match self {
+            RefFunction::Abs => abs(expr),
+            RefFunction::Round => round(expr),
+            RefFunction::Floor => floor(expr),
+            RefFunction::Ceil => ceil(expr),
+        }
+    }
+
+    fn evaluate(&self, value: Value) -> Value {
+        let f = match self {
+            RefFunction::Abs => f64::abs,
+            RefFunction::Round => f64::round,
+            RefFunction::Floor => f64::floor,
+            RefFunction::Ceil => f64::ceil,
+        };
+        match value {
+            Value::Int(i) if matches!(self, RefFunction::Abs) => Value::Int(i.abs()),
+            Value::Int(i) => Value::Int(i),
+            Value::Float(v) => Value::Float(f(v)),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// An expression of the reference evaluator, over the columns of its input by index.
+#[derive(Debug, Clone)]
+pub enum RefExpr {
+    Column(usize),
+    Literal(Value, Type),
+    Cmp(CmpOp, Box<RefExpr>, Box<RefExpr>),
+    And(Box<RefExpr>, Box<RefExpr>),
+    Or(Box<RefExpr>, Box<RefExpr>),
+    Not(Box<RefExpr>),
+    Add(Box<RefExpr>, Box<RefExpr>),
+    Mul(Box<RefExpr>, Box<RefExpr>),
+    Function(RefFunction, Box<RefExpr>),
+    Coalesce(Box<RefExpr>, Box<RefExpr>),
+}
+
+impl RefExpr {
+    fn data_type(&self, columns: &[(String, Type)]) -> Type {
+        match self {
+            RefExpr::Column(i) => columns[*i].1,
+            RefExpr::Literal(_, t) => *t,
+            RefExpr::Cmp(..) | RefExpr::And(..) | RefExpr::Or(..) | RefExpr::Not(_) => Type::Bool,
+            RefExpr::Add(l, r) | RefExpr::Mul(l, r) | RefExpr::Coalesce(l, r) => {
+                l.data_type(columns).common(r.data_type(columns))
+            }
+            RefExpr::Function(_, e) => e.data_type(columns),
+        }
+    }
+
+    fn to_expr(&self, columns: &[(String, Type)]) -> Expr {
+        match self {
+            RefExpr::Column(i) => col(&columns[*i].0),
+            RefExpr::Literal(value, t) => value.to_expr(*t),
+            RefExpr::Cmp(op, l, r) => {
+                let (l, r) = (l.to_expr(columns), r.to_expr(columns));
+                match op {
+                    CmpOp::Eq => l.eq(r),
+                    CmpOp::NotEq => l.not_eq(r),
+                    CmpOp::Lt => l.lt(r),
+                    CmpOp::LtEq => l.lt_eq(r),
+                    CmpOp::Gt => l.gt(r),
+                    CmpOp::GtEq => l.gt_eq(r),
+                }
+            }
+            RefExpr::And(l, r) => l.to_expr(columns).and(r.to_expr(columns)),
+            RefExpr::Or(l, r) => l.to_expr(columns).or(r.to_expr(columns)),
+            RefExpr::Not(e) => !e.to_expr(columns),
+            RefExpr::Add(l, r) => l.to_expr(columns) + r.to_expr(columns),
+            RefExpr::Mul(l, r) => l.to_expr(columns) * r.to_expr(columns),
+            RefExpr::Function(f, e) => f.call(e.to_expr(columns)),
+            RefExpr::Coalesce(l, r) => coalesce(vec![l.to_expr(columns), r.to_expr(columns)]),
+        }
+    }
+
+    /// Evaluate the expression on a row, with the null semantics of SQL.
+    fn evaluate(&self, columns: &[(String, Type)], row: &[Value]) -> Value {
+        match self {
+            RefExpr::Column(i) => row[*i].clone(),
+            RefExpr::Literal(value, _) => value.clone(),
+            RefExpr::Cmp(op, l, r) => {
+                let (l, r) = (l.evaluate(columns, row), r.evaluate(columns, row));
+                if l == Value::Null || r == Value::Null {
+                    return Value::Null;
+                }
+                let ordering = l.compare(&r);
+                Value::Bool(match op {
+                    CmpOp::Eq => ordering.is_eq(),
+                    CmpOp::NotEq => ordering.is_ne(),
+                    CmpOp::Lt => ordering.is_lt(),
+                    CmpOp::LtEq => ordering.is_le(),
+                    CmpOp::Gt => ordering.is_gt(),
+                    CmpOp::GtEq => ordering.is_ge(),
+                })
+            }
+            RefExpr::And(l, r) => match (l.evaluate(columns, row), r.evaluate(columns, row)) {
+                (Value::Bool(false), _) | (_, Value::Bool(false)) => Value::Bool(false),
+                (Value::Bool(true), Value::Bool(true)) => Value::Bool(true),
+                _ => Value::Null,
+            },
+            RefExpr::Or(l, r) => match (l.evaluate(columns, row), r.evaluate(columns, row)) {
+                (Value::Bool(true), _) | (_, Value::Bool(true)) => Value::Bool(true),
+                (Value::Bool(false), Value::Bool(false)) => Value::Bool(false),
+                _ => Value::Null,
+            },
+            RefExpr::Not(e) => match e.evaluate(columns, row) {
+                Value::Bool(b) => Value::Bool(!b),
+                _ => Value::Null,
+            },
+            RefExpr::Add(l, r) | RefExpr::Mul(l, r) => {
+                let add = matches!(self, RefExpr::Add(..));
+                match (l.evaluate(columns, row), r.evaluate(columns, row)) {
+                    (Value::Int(a), Value::Int(b)) => Value::Int(if add { a + b } else { a * b }),
+                    (Value::Null, _) | (_, Value::Null) => Value::Null,
+                    (a, b) => {
+                        let (a, b) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+                        Value::Float(if add { a + b } else { a * b })
+                    }
+                }
+            }
+            RefExpr::Function(f, e) => f.evaluate(e.evaluate(columns, row)),
+            RefExpr::Coalesce(l, r) => {
+                let value = match l.evaluate(columns, row) {
+                    Value::Null => r.evaluate(columns, row),
+                    value => value,
+                };
+                // The arguments are converted to their common type.
+                match (value, self.data_type(columns)) {
+                    (Value::Int(i), Type::Float32 | Type::Float) => Value::Float(i as f64),
+                    (value, _) => value,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RefAggregate {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl RefAggregate {
+    fn data_type(&self, input: Type) -> Type {
+        match self {
+            RefAggregate::Count => Type::Int,
+            RefAggregate::Avg => Type::Float,
+            _ => input,
+        }
+    }
+
+    fn call(&self, expr: Expr) -> Expr {
+        match self {
+            RefAggregate::Count => count(expr),
+            RefAggregate::Sum => sum(expr),
+            RefAggregate::Min => min(expr),
+            RefAggregate::Max => max(expr),
+            RefAggregate::Avg => avg(expr),
+        }
+    }
+
+    /// Aggregate the values, skipping nulls.
+    fn evaluate(&self, values: &[Value]) -> Value {
+        let values = values.iter().filter(|v| **v != Value::Null).cloned();
+        match self {
+            RefAggregate::Count => Value::Int(values.count() as i64),
+            RefAggregate::Sum => values
+                .reduce(|a, b| match (a, b) {
+                    (Value::Int(a), Value::Int(b)) => Value::Int(a + b),
+                    (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+                    _ => unreachable!("only numbers are summed"),
+                })
+                .unwrap_or(Value::Null),
+            RefAggregate::Min => values.min_by(|a, b| a.total_cmp(b)).unwrap_or(Value::Null),
+            RefAggregate::Max => values.max_by(|a, b| a.total_cmp(b)).unwrap_or(Value::Null),
+            RefAggregate::Avg => {
+                let values = values.map(|v| v.as_f64().unwrap()).collect::<Vec<_>>();
+                match values.is_empty() {
+                    true => Value::Null,
+                    false => Value::Float(values.iter().sum::<f64>() / values.len() as f64),
+                }
+            }
+        }
+    }
+}
+
+/// An operator applied to the relation built so far.
+#[derive(Debug, Clone)]
+pub enum RefPlan {
+    Filter(RefExpr),
+    Project(Vec<RefExpr>),
+    /// Group by the columns, computing the aggregates of the columns.
+    Aggregate(Vec<usize>, Vec<(RefAggregate, usize)>),
+}
+
+/// The columns and rows of a relation, evaluated a row at a time.
+#[derive(Debug, Clone)]
+pub struct Relation {
+    pub columns: Vec<(String, Type)>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Relation {
+    /// Generate a relation of 1 to 4 columns of random types, with some nulls.
+    pub fn random(rng: &mut Rng) -> Relation {
+        let columns = (0..1 + rng.below(4))
+            .map(|i| (format!("c{}", i), *rng.pick(&Type::ALL)))
+            .collect::<Vec<_>>();
+        let rows = (0..rng.below(40))
+            .map(|_| {
+                columns
+                    .iter()
+                    .map(|(_, t)| match rng.chance(15) {
+                        true => Value::Null,
+                        false => Value::random(rng, *t),
+                    })
+                    .collect()
+            })
+            .collect();
+        Relation { columns, rows }
+    }
+
+    /// Split the rows into Arrow batches of random sizes.
+    pub fn to_batches(&self, rng: &mut Rng) -> Vec<RecordBatch> {
+        let schema = Arc::new(ArrowSchema::new(
+            self.columns
+                .iter()
+                .map(|(name, t)| ArrowField::new(name, (*t).into(), true))
+                .collect(),
+        ));
+        let mut batches = vec![];
+        let mut rows = &self.rows[..];
+        while !rows.is_empty() {
+            let (batch, rest) = rows.split_at(1 + rng.below(rows.len()));
+            rows = rest;
+            let columns = self
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(i, (_, t))| to_array(*t, batch.iter().map(|row| &row[i])))
+                .collect();
+            let batch = ArrowRecordBatch::try_new(schema.clone(), columns).unwrap();
+            batches.push(RecordBatch::try_from(&batch).unwrap());
+        }
+        batches
+    }
+
+    /// Return the columns that can be used as a value of the type, such as Int32 columns
+    /// for an Int64 value, as the engine converts them.
+    fn columns_of(&self, t: Type) -> Vec<usize> {
+        (0..self.columns.len())
+            .filter(|i| {
+                let c = self.columns[*i].1;
+                c == t || (c.is_numeric() && t.is_numeric() && c.common(t) == t)
+            })
+            .collect()
+    }
+
+    /// Generate an expression of the type, or of a type converted to it, over the columns
+    /// of the relation.
+    fn random_expr(&self, rng: &mut Rng, t: Type, depth: usize) -> RefExpr {
+        let columns = self.columns_of(t);
+        let operand = |rng: &mut Rng| match columns.is_empty() || rng.chance(30) {
+            true => RefExpr::Literal(Value::random(rng, t), t),
+            false => RefExpr::Column(*rng.pick(&columns)),
+        };
+        if depth > 0 && rng.chance(10) {
+            let l = Box::new(self.random_expr(rng, t, depth - 1));
+            let r = Box::new(self.random_expr(rng, t, depth - 1));
+            return RefExpr::Coalesce(l, r);
+        }
+        match t {
+            Type::Bool if depth > 0 && rng.chance(30) => {
+                let l = Box::new(self.random_expr(rng, Type::Bool, depth - 1));
+                let r = Box::new(self.random_expr(rng, Type::Bool, depth - 1));
+                match rng.below(3) {
+                    0 => RefExpr::And(l, r),
+                    1 => RefExpr::Or(l, r),
+                    _ => RefExpr::Not(l),
+                }
+            }
+            Type::Bool if columns.is_empty() || rng.chance(70) => {
+                let types = [
+                    Type::Int32,
+                    Type::Int,
+                    Type::Float32,
+                    Type::Float,
+                    Type::Str,
+                ];
+                let operand_type = *rng.pick(&types);
+                let ops = [
+                    CmpOp::Eq,
+                    CmpOp::NotEq,
+                    CmpOp::Lt,
+                    CmpOp::LtEq,
+                    CmpOp::Gt,
+                    CmpOp::GtEq,
+                ];
+                let op = *rng.pick(&ops);
+                let l = Box::new(self.random_expr(rng, operand_type, 0));
+                let r = Box::new(self.random_expr(rng, operand_type, 0));
+                RefExpr::Cmp(op, l, r)
+            }
+            _ if t.is_numeric() && depth > 0 && rng.chance(15) => {
+                let functions = [
+                    RefFunction::Abs,
+                    RefFunction::Round,
+                    RefFunction::Floor,
+                    RefFunction::Ceil,
+                ];
+                let f = *rng.pick(&functions);
+                RefExpr::Function(f, Box::new(self.random_expr(rng, t, depth - 1)))
+            }
+            _ if t.is_numeric() && depth > 0 && rng.chance(40) => {
+                let l = Box::new(self.random_expr(rng, t, depth - 1));
+                let r = Box::new(operand(rng));
+                // Only 64-bit values are multiplied, so 32-bit values can't overflow or
+                // lose precision after a few steps.
+                let product = RefExpr::Mul(l.clone(), r.clone());
+                match rng.chance(50)
+                    && matches!(product.data_type(&self.columns), Type::Int | Type::Float)
+                {
+                    true => product,
+                    false => RefExpr::Add(l, r),
+                }
+            }
+            _ => operand(rng),
+        }
+    }
+
+    /// Generate an operator that can be applied to the relation.
+    pub fn random_plan(&self, rng: &mut Rng) -> RefPlan {
+        match rng.below(3) {
+            0 => RefPlan::Filter(self.random_expr(rng, Type::Bool, 2)),
+            1 => {
+                let exprs = (0..1 + rng.below(4))
+                    .map(|_| {
+                        let t = *rng.pick(&Type::ALL);
+                        self.random_expr(rng, t, 2)
+                    })
+                    .collect();
+                RefPlan::Project(exprs)
+            }
+            _ => {
+                // Some aggregates have no group columns, and a single group.
+                let mut group_by = vec![];
+                if rng.chance(80) {
+                    group_by.push(rng.below(self.columns.len()));
+                }
+                if self.columns.len() > 1 && rng.chance(30) {
+                    let column = rng.below(self.columns.len());
+                    if !group_by.contains(&column) {
+                        group_by.push(column);
+                    }
+                }
+                let aggregates = (0..1 + rng.below(3))
+                    .map(|_| {
+                        let column = rng.below(self.columns.len());
+                        let aggregate = match self.columns[column].1 {
+                            Type::Str => *rng.pick(&[
+                                RefAggregate::Count,
+                                RefAggregate::Min,
+                                RefAggregate::Max,
+                            ]),
+                            Type::Bool => RefAggregate::Count,
+                            _ => *rng.pick(&[
+                                RefAggregate::Count,
+                                RefAggregate::Sum,
+                                RefAggregate::Min,
+                                RefAggregate::Max,
+                                RefAggregate::Avg,
+                            ]),
+                        };
+                        (aggregate, column)
+                    })
+                    .collect();
+                RefPlan::Aggregate(group_by, aggregates)
+            }
+        }
+    }
+
+    /// Apply the operator to the DataFrame of the relation, and evaluate it on the rows
+    /// of the relation. The computed columns are named after their position in the plan.
+    pub fn apply(&self, plan: &RefPlan, df: &DataFrame, step: usize) -> (DataFrame, Relation) {
+        let name = |i: usize| format!("s{}_{}", step, i);
+        match plan {
+            RefPlan::Filter(predicate) => {
+                let df = df.filter(predicate.to_expr(&self.columns));
+                let rows = self
+                    .rows
+                    .iter()
+                    .filter(|row| predicate.evaluate(&self.columns, row) == Value::Bool(true))
+                    .cloned()
+                    .collect();
+                let relation = Relation {
+                    columns: self.columns.clone(),
+                    rows,
+                };
+                (df, relation)
+            }
+            RefPlan::Project(exprs) => {
+                let df = df.project(
+                    exprs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, e)| e.to_expr(&self.columns).alias(name(i)))
+                        .collect(),
+                );
+                let columns = exprs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| (name(i), e.data_type(&self.columns)))
+                    .collect();
+                let rows = self
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        exprs
+                            .iter()
+                            .map(|e| e.evaluate(&self.columns, row))
+                            .collect()
+                    })
+                    .collect();
+                (df, Relation { columns, rows })
+            }
+            RefPlan::Aggregate(group_by, aggregates) => {
+                let df = df.aggregate(
+                    group_by.iter().map(|i| col(&self.columns[*i].0)).collect(),
+                    aggregates
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (aggregate, column))| {
+                            aggregate.call(col(&self.columns[*column].0)).alias(name(i))
+                        })
+                        .collect(),
+                );
+                let columns = group_by
+                    .iter()
+                    .map(|i| self.columns[*i].clone())
+                    .chain(
+                        aggregates
+                            .iter()
+                            .enumerate()
+                            .map(|(i, (aggregate, column))| {
+                                (name(i), aggregate.data_type(self.columns[*column].1))
+                            }),
+                    )
+                    .collect();
+                let mut groups: Vec<(Vec<Value>, Vec<&Vec<Value>>)> = vec![];
+                if group_by.is_empty() {
+                    groups.push((vec![], vec![]));
+                }
+                for row in &self.rows {
+                    let key = group_by.iter().map(|i| row[*i].clone()).collect::<Vec<_>>();
+                    match groups.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, rows)) => rows.push(row),
+                        None => groups.push((key, vec![row])),
+                    }
+                }
+                let rows = groups
+                    .into_iter()
+                    .map(|(mut key, rows)| {
+                        key.extend(aggregates.iter().map(|(aggregate, column)| {
+                            let values =
+                                rows.iter().map(|r| r[*column].clone()).collect::<Vec<_>>();
+                            aggregate.evaluate(&values)
+                        }));
+                        key
+                    })
+                    .collect();
+                (df, Relation { columns, rows })
+            }
+        }
+    }
+}
+
+fn to_array<'a>(t: Type, values: impl Iterator<Item = &'a Value>) -> ArrayRef {
+    match t {
+        Type::Int32 => Arc::new(
+            values
+                .map(|v| match v {
+                    Value::Int(i) => Some(*i as i32),
+                    _ => None,
+                })
+                .collect::<Int32Array>(),
+        ),
+        Type::Float32 => Arc::new(
+            values
+                .map(|v| match v {
+                    Value::Float(f) => Some(*f as f32),
+                    _ => None,
+                })
+                .collect::<Float32Array>(),
+        ),
+        Type::Int => Arc::new(
+            values
+                .map(|v| match v {
+                    Value::Int(i) => Some(*i),
+                    _ => None,
+                })
+                .collect::<Int64Array>(),
+        ),
+        Type::Float => Arc::new(
+            values
+                .map(|v| match v {
+                    Value::Float(f) => Some(*f),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        Type::Str => Arc::new(
+            values
+                .map(|v| match v {
+                    Value::Str(s) => Some(s.as_str()),
+                    _ => None,
+                })
+                .collect::<StringArray>(),
+        ),
+        Type::Bool => Arc::new(
+            values
+                .map(|v| match v {
+                    Value::Bool(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+    }
+}
+
+fn sorted(mut rows: Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    rows.sort_by(|a, b| {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| a.total_cmp(b))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    rows
+}
+
+/// Generate a random relation and plan from the seed, execute the plan and compare its
+/// results with the reference evaluation. The rows are compared in any order.
+fn check_seed(seed: u64) -> Result<(), String> {
+    let mut rng = Rng::new(seed);
+    let mut relation = Relation::random(&mut rng);
+    let batches = relation.to_batches(&mut rng);
+    let ctx = ExecutionContext::new(*rng.pick(&[1, 3, 1024]));
+    let schema = ctx_schema(&relation);
+    let source = MemoryDataSource::new(schema, batches).with_partitions(1 + rng.below(3));
+    ctx.register_table("t", Source::Mem(source));
+    let mut df = ctx.table("t").unwrap();
+    let mut plans = vec![];
+    for step in 0..1 + rng.below(4) {
+        let plan = relation.random_plan(&mut rng);
+        (df, relation) = relation.apply(&plan, &df, step);
+        plans.push(plan);
+    }
+    let describe = |error: &dyn Debug| format!("seed {}: {:?}\nplans: {:#?}", seed, error, plans);
+
+    let types = df
+        .schema()
+        .fields
+        .iter()
+        .map(|f| f.data_type.clone())
+        .collect::<Vec<_>>();
+    let expected_types = relation
+        .columns
+        .iter()
+        .map(|(_, t)| ctx_type(*t))
+        .collect::<Vec<_>>();
+    if types != expected_types {
+        return Err(describe(&(types, expected_types)));
+    }
+    let batches = match std::panic::catch_unwind(AssertUnwindSafe(|| ctx.execute(&df))) {
+        Ok(Ok(batches)) => batches,
+        Ok(Err(e)) => return Err(describe(&e)),
+        Err(_) => return Err(describe(&"execution panicked")),
+    };
+    let rows = batches
+        .iter()
+        .flat_map(|batch| {
+            (0..batch.row_count()).map(move |r| {
+                (0..batch.column_count())
+                    .map(|c| {
+                        Value::from_scalar(ScalarValue::try_from_array(batch.field(c), r).unwrap())
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+    let (rows, expected) = (sorted(rows), sorted(relation.rows));
+    let equal = rows.len() == expected.len()
+        && rows
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| a.iter().zip(b).all(|(a, b)| a.approx_eq(b)));
+    if !equal {
+        return Err(describe(&(rows, expected)));
+    }
+    Ok(())
+}
+
+fn ctx_type(t: Type) -> DataType {
+    match t {
+        Type::Int32 => DataType::Int32,
+        Type::Float32 => DataType::Float32,
+        Type::Int => DataType::Int64,
+        Type::Float => DataType::Float64,
+        Type::Str => DataType::Utf8,
+        Type::Bool => DataType::Boolean,
+    }
+}
+
+fn ctx_schema(relation: &Relation) -> Schema {
+    Schema::new(
+        relation
+            .columns
+            .iter()
+            .map(|(name, t)| Field::new(name.clone(), ctx_type(*t)))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_random_queries() {
+    let failures = (0..500)
+        .filter_map(|seed| check_seed(seed).err())
+        .collect::<Vec<_>>();
+    assert!(
+        failures.is_empty(),
+        "{} of the random queries failed, the first one with {}",
+        failures.len(),
+        failures[0]
+    );
+}
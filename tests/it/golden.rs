@@ -0,0 +1,73 @@
+//! Golden files for the tests that read the CSV fixtures.
+#![cfg(feature = "fs")]
+
+use std::{fs, path::PathBuf};
+
+use rq::data_types::{
+    column_array::{ColumnArray, DataType},
+    record_batch::RecordBatch,
+    schema::Schema,
+};
+
+/// Return the path of a bundled CSV fixture.
+pub fn test_data(file_name: &str) -> String {
+    manifest_path(&format!("tests/data/{}", file_name))
+}
+
+fn manifest_path(path: &str) -> String {
+    let mut full_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    full_path.push(path);
+    full_path.into_os_string().into_string().unwrap()
+}
+
+/// Render the batches as CSV, with a header of the field names and empty values for nulls.
+pub fn to_csv(schema: &Schema, batches: &[RecordBatch]) -> String {
+    let mut csv = schema
+        .fields
+        .iter()
+        .map(|f| f.name.clone())
+        .collect::<Vec<_>>()
+        .join(",");
+    csv.push('\n');
+    for batch in batches {
+        for i in 0..batch.row_count() {
+            let row = batch
+                .fields
+                .iter()
+                .map(|array| {
+                    if array.is_null(i) {
+                        return String::new();
+                    }
+                    let value = array.get_value(i).unwrap();
+                    match array.get_type() {
+                        DataType::Boolean => value.downcast_ref::<bool>().unwrap().to_string(),
+                        DataType::Int32 => value.downcast_ref::<i32>().unwrap().to_string(),
+                        DataType::Int64 => value.downcast_ref::<i64>().unwrap().to_string(),
+                        DataType::Float32 => value.downcast_ref::<f32>().unwrap().to_string(),
+                        DataType::Float64 => value.downcast_ref::<f64>().unwrap().to_string(),
+                        DataType::Utf8 => value.downcast_ref::<String>().unwrap().clone(),
+                        data_type => panic!("Golden files do not support {} values", data_type),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            csv.push_str(&row);
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+/// Compare the batches with the golden file `tests/golden/<name>.csv`.
+/// Run the tests with `RQ_UPDATE_GOLDEN=1` to write the current results instead.
+pub fn assert_golden(name: &str, schema: &Schema, batches: &[RecordBatch]) {
+    let path = manifest_path(&format!("tests/golden/{}.csv", name));
+    let actual = to_csv(schema, batches);
+    if std::env::var_os("RQ_UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Cannot read {}: {}, run with RQ_UPDATE_GOLDEN=1", path, e));
+    assert_eq!(actual, expected, "Results of {} differ from {}", name, path);
+}
@@ -0,0 +1,5 @@
+mod data_frame;
+/// Random queries checked against a naive row-by-row evaluation of the same plans.
+mod fuzz;
+mod golden;
+mod sql;
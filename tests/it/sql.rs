@@ -0,0 +1,227 @@
+//! The tests read the CSV fixtures from the filesystem.
+#![cfg(feature = "fs")]
+
+use crate::golden::test_data;
+
+use rq::{
+    assert_batches_eq,
+    data_types::{
+        column_array::DataType,
+        record_batch::RecordBatch,
+        schema::{Field, Schema},
+    },
+    execution::{query_template::QueryTemplate, ExecutionContext},
+    logical_plan::expr_fn::col,
+};
+
+/// Create a context with `primitive_field.csv` registered as the table `t`.
+fn context() -> ExecutionContext {
+    let ctx = ExecutionContext::new(1024);
+    let schema = Schema::new(vec![
+        Field::new("c1".to_string(), DataType::Int32),
+        Field::new("c2".to_string(), DataType::Int32),
+        Field::new("c3".to_string(), DataType::Int64),
+    ]);
+    ctx.register_csv("t", test_data("primitive_field.csv"), schema);
+    ctx
+}
+
+/// Plan and execute a SQL query.
+fn run(ctx: &ExecutionContext, sql: &str) -> Vec<RecordBatch> {
+    let df = ctx
+        .sql(sql)
+        .unwrap_or_else(|e| panic!("Cannot plan {}: {}", sql, e));
+    ctx.execute(&df)
+        .unwrap_or_else(|e| panic!("{} failed: {}", sql, e))
+}
+
+#[test]
+fn test_guarded_division() {
+    // The division is only evaluated for the rows the guard written before it keeps.
+    let ctx = context();
+    assert_batches_eq!(
+        ["+----+", "| c1 |", "+----+", "| 2  |", "| 3  |", "+----+"],
+        &run(
+            &ctx,
+            "SELECT c1 FROM t WHERE c1 - 1 <> 0 AND 10 / (c1 - 1) > 1"
+        )
+    );
+}
+
+#[test]
+fn test_query_template() {
+    let ctx = context();
+    let t = ctx.table("t").unwrap();
+    ctx.register_query(
+        "c1_at_least",
+        QueryTemplate::new(vec!["n".to_string()], move |args| {
+            t.filter(col("c1").gt_eq(args[0].clone()))
+        }),
+    );
+    assert_batches_eq!(
+        [
+            "+----+----+",
+            "| c1 | c2 |",
+            "+----+----+",
+            "| 2  | 10 |",
+            "| 3  | 11 |",
+            "+----+----+"
+        ],
+        &run(&ctx, "SELECT c1, c2 FROM c1_at_least(2) ORDER BY c1")
+    );
+    assert_batches_eq!(
+        ["+----+", "| c1 |", "+----+", "| 3  |", "+----+"],
+        &run(&ctx, "SELECT q.c1 FROM c1_at_least(3) AS q")
+    );
+    assert!(ctx.sql("SELECT c1 FROM c1_at_least()").is_err());
+}
+
+#[test]
+fn test_select_without_from() {
+    let ctx = context();
+    assert_batches_eq!(
+        ["+---+", "| 1 |", "+---+", "| 1 |", "+---+"],
+        &run(&ctx, "SELECT 1")
+    );
+    assert_batches_eq!(
+        [
+            "+---------+---+",
+            "| version | n |",
+            "+---------+---+",
+            "| rq      | 3 |",
+            "+---------+---+"
+        ],
+        &run(&ctx, "SELECT 'rq' AS version, 1 + 2 AS n")
+    );
+    assert_batches_eq!(
+        [
+            "+----------+",
+            "| COUNT(*) |",
+            "+----------+",
+            "| 1        |",
+            "+----------+"
+        ],
+        &run(&ctx, "SELECT COUNT(*)")
+    );
+    let error = |sql| ctx.sql(sql).err().unwrap().to_string();
+    assert!(error("SELECT c1").starts_with("No column named c1"));
+    assert!(error("SELECT *").starts_with("SELECT * with no tables specified is not valid"));
+}
+
+#[test]
+fn test_regexp_extract() {
+    let ctx = context();
+    assert_batches_eq!(
+        [
+            "+----+---------+--------------+",
+            "| id | missing | numbers      |",
+            "+----+---------+--------------+",
+            "| 7  |         | [1, 22, 333] |",
+            "+----+---------+--------------+"
+        ],
+        &run(
+            &ctx,
+            r"SELECT regexp_extract('user=alice id=7', 'id=(\d+)', 1) AS id,
+                regexp_extract('user=alice', 'id=(\d+)', 1) AS missing,
+                regexp_extract_all('a1b22c333', '\d+', 0) AS numbers"
+        )
+    );
+    let error = |sql| ctx.sql(sql).err().unwrap().to_string();
+    assert!(error("SELECT regexp_extract('a', 'a')")
+        .starts_with("regexp_extract expects 3 arguments, got 2"));
+    assert!(error("SELECT regexp_extract_all('a', 'a', 1.5)")
+        .starts_with("regexp_extract_all expects an integer literal, got 1.5"));
+}
+
+#[test]
+fn test_date_bin() {
+    let ctx = context();
+    let schema = Schema::new(vec![
+        Field::new("name".to_string(), DataType::Utf8),
+        Field::new("ts".to_string(), DataType::Int64),
+    ]);
+    ctx.register_csv("events", test_data("events.csv"), schema);
+    assert_batches_eq!(
+        [
+            "+--------+--------+",
+            "| bucket | events |",
+            "+--------+--------+",
+            "| -950   | 3      |",
+            "| 50     | 1      |",
+            "| 4050   | 1      |",
+            "| 5050   | 1      |",
+            "+--------+--------+"
+        ],
+        &run(
+            &ctx,
+            "SELECT date_bin(1000, ts, 50) AS bucket, COUNT(*) AS events FROM events \
+             GROUP BY 1 ORDER BY 1"
+        )
+    );
+    assert!(ctx
+        .sql("SELECT date_bin(ts, ts, 0) FROM events")
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("date_bin expects an integer literal, got #ts"));
+}
+
+#[test]
+fn test_st_within_bbox() {
+    let ctx = context();
+    let schema = Schema::new(vec![Field::new("location".to_string(), DataType::Utf8)]);
+    ctx.register_csv("points", test_data("point_field.csv"), schema);
+    assert_batches_eq!(
+        [
+            "+----------------+",
+            "| location       |",
+            "+----------------+",
+            "| 52.52,13.405   |",
+            "| 53.5511,9.9937 |",
+            "+----------------+"
+        ],
+        &run(
+            &ctx,
+            "SELECT location FROM points WHERE st_within_bbox(location, '47,5', '55,15') \
+             ORDER BY location"
+        )
+    );
+    assert!(ctx
+        .sql("SELECT st_within_bbox(location, '47,5') FROM points")
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("st_within_bbox expects 3 arguments, got 2"));
+}
+
+#[test]
+fn test_percentile_first_last() {
+    let ctx = context();
+    let schema = Schema::new(vec![
+        Field::new("name".to_string(), DataType::Utf8),
+        Field::new("ts".to_string(), DataType::Int64),
+    ]);
+    ctx.register_csv("events", test_data("events.csv"), schema);
+    assert_batches_eq!(
+        [
+            "+-------+--------+---------------+-------+------+",
+            "| name  | median | approx_median | first | last |",
+            "+-------+--------+---------------+-------+------+",
+            "| alice | 2550   | 2550          | 0     | 5100 |",
+            "| bob   | 15     | 15            | 10    | 20   |",
+            "+-------+--------+---------------+-------+------+"
+        ],
+        &run(
+            &ctx,
+            "SELECT name, percentile_cont(ts, 0.5) AS median, \
+                approx_percentile(ts, 0.5) AS approx_median, \
+                first_value(ts, ts) AS first, last_value(ts, ts) AS last \
+             FROM events GROUP BY name ORDER BY name"
+        )
+    );
+    let error = |sql| ctx.sql(sql).err().unwrap().to_string();
+    assert!(error("SELECT percentile_cont(ts) FROM events")
+        .starts_with("percentile_cont expects 2 arguments, got 1"));
+    assert!(error("SELECT last_value() FROM events")
+        .starts_with("last_value expects at least 1 argument"));
+}